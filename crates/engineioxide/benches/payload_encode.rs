@@ -0,0 +1,70 @@
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use engineioxide::{encode_packets, Packet, ProtocolVersion};
+use tokio::runtime::Builder;
+
+const MAX_PAYLOAD: u64 = 1_000_000;
+
+fn packets() -> Vec<Packet> {
+    let mut packets = Vec::with_capacity(102);
+    for i in 0..50 {
+        packets.push(Packet::Message(format!("Hello world {i}").into()));
+        packets.push(Packet::Binary(Bytes::from_static(&[
+            1, 2, 3, 4, 5, 6, 7, 8,
+        ])));
+    }
+    packets.push(Packet::Message("a".repeat(64 * 1024).into()));
+    packets.push(Packet::Binary(vec![4; 64 * 1024].into()));
+    packets
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let rt = Builder::new_current_thread().build().unwrap();
+    let mut group = c.benchmark_group("engineio_payload/encode");
+    group.bench_function("Encode v4 payload", |b| {
+        b.iter_batched(
+            packets,
+            |packets| {
+                rt.block_on(encode_packets(
+                    black_box(packets),
+                    ProtocolVersion::V4,
+                    false,
+                    MAX_PAYLOAD,
+                ))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("Encode v3 string payload", |b| {
+        b.iter_batched(
+            packets,
+            |packets| {
+                rt.block_on(encode_packets(
+                    black_box(packets),
+                    ProtocolVersion::V3,
+                    false,
+                    MAX_PAYLOAD,
+                ))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("Encode v3 binary payload", |b| {
+        b.iter_batched(
+            packets,
+            |packets| {
+                rt.block_on(encode_packets(
+                    black_box(packets),
+                    ProtocolVersion::V3,
+                    true,
+                    MAX_PAYLOAD,
+                ))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
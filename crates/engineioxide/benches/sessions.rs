@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use engineioxide::{
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use http::{Method, Request};
+use http_body_util::{BodyExt, Full};
+use tokio::runtime::Runtime;
+use tower_service::Service;
+
+#[derive(Debug)]
+struct Handler;
+
+impl EngineIoHandler for Handler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, _: Arc<Socket<()>>) {}
+    fn on_disconnect(&self, _: Arc<Socket<()>>, _: DisconnectReason) {}
+    fn on_message(self: &Arc<Self>, _: Str, _: Arc<Socket<()>>) {}
+    fn on_binary(self: &Arc<Self>, _: Bytes, _: Arc<Socket<()>>) {}
+}
+
+async fn req(
+    svc: &mut EngineIoService<Handler>,
+    method: Method,
+    params: &str,
+    body: &'static str,
+) -> Bytes {
+    let req = Request::builder()
+        .method(method)
+        .uri(format!(
+            "http://127.0.0.1/engine.io/?EIO=4&transport=polling{params}"
+        ))
+        .body(Full::new(Bytes::from_static(body.as_bytes())))
+        .unwrap();
+    let res = svc.call(req).await.unwrap();
+    res.into_body().collect().await.unwrap().to_bytes()
+}
+
+/// Open a polling session and close it right away.
+async fn connect_disconnect(mut svc: EngineIoService<Handler>) {
+    let open = req(&mut svc, Method::GET, "", "").await;
+    let open: serde_json::Value = serde_json::from_slice(&open[1..]).unwrap();
+    let sid = open["sid"].as_str().unwrap();
+    req(&mut svc, Method::POST, &format!("&sid={sid}"), "1").await;
+}
+
+/// Connection storms: `n` clients connecting and disconnecting concurrently
+/// on a multi-threaded runtime, contending on the sessions map.
+fn bench_sessions(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let svc = EngineIoService::new(Arc::new(Handler));
+    let mut group = c.benchmark_group("engineio_sessions");
+    for n in [100, 1000, 10000] {
+        group.bench_with_input(BenchmarkId::new("connect_disconnect", n), &n, |b, &n| {
+            b.iter(|| {
+                rt.block_on(async {
+                    let tasks: Vec<_> = (0..n)
+                        .map(|_| tokio::spawn(connect_disconnect(svc.clone())))
+                        .collect();
+                    for task in tasks {
+                        task.await.unwrap();
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_sessions);
+criterion_main!(benches);
@@ -0,0 +1,8 @@
+#![no_main]
+
+use engineioxide::Packet;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    Packet::try_from(data.to_string()).ok();
+});
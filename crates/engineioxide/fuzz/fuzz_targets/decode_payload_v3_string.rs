@@ -0,0 +1,11 @@
+#![no_main]
+
+use bytes::Bytes;
+use engineioxide::{decode_payload, ProtocolVersion};
+use libfuzzer_sys::fuzz_target;
+
+// The payload is split in chunks to fuzz the decoding of packets across the body frames
+fuzz_target!(|chunks: Vec<Vec<u8>>| {
+    let chunks = chunks.into_iter().map(Bytes::from).collect();
+    decode_payload(chunks, ProtocolVersion::V3, false, 100_000);
+});
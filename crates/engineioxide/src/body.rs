@@ -4,6 +4,7 @@ use bytes::Bytes;
 use http_body::{Body, Frame, SizeHint};
 use http_body_util::Full;
 use pin_project_lite::pin_project;
+use std::collections::VecDeque;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
@@ -15,6 +16,9 @@ pin_project! {
             #[pin]
             body: Full<Bytes>,
         },
+        ChunkedBody {
+            chunks: VecDeque<Bytes>,
+        },
         Body {
             #[pin]
             body: B,
@@ -35,6 +39,13 @@ impl<B> ResponseBody<B> {
         ResponseBody::CustomBody { body }
     }
 
+    /// A body sent chunk by chunk, without concatenating the chunks.
+    pub fn chunked_response(chunks: impl Into<VecDeque<Bytes>>) -> Self {
+        ResponseBody::ChunkedBody {
+            chunks: chunks.into(),
+        }
+    }
+
     pub fn new(body: B) -> Self {
         ResponseBody::Body { body }
     }
@@ -54,6 +65,7 @@ where
             ResponseBody::EmptyResponse => true,
             ResponseBody::Body { body } => body.is_end_stream(),
             ResponseBody::CustomBody { body } => body.is_end_stream(),
+            ResponseBody::ChunkedBody { chunks } => chunks.is_empty(),
         }
     }
 
@@ -66,6 +78,9 @@ where
             }
             ResponseBody::Body { body } => body.size_hint(),
             ResponseBody::CustomBody { body } => body.size_hint(),
+            ResponseBody::ChunkedBody { chunks } => {
+                SizeHint::with_exact(chunks.iter().map(|c| c.len() as u64).sum())
+            }
         }
     }
 
@@ -77,6 +92,9 @@ where
             BodyProj::EmptyResponse => Poll::Ready(None),
             BodyProj::Body { body } => body.poll_frame(cx),
             BodyProj::CustomBody { body } => body.poll_frame(cx).map_err(|err| match err {}),
+            BodyProj::ChunkedBody { chunks } => {
+                Poll::Ready(chunks.pop_front().map(|c| Ok(Frame::data(c))))
+            }
         }
     }
 }
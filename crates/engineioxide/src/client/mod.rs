@@ -0,0 +1,412 @@
+//! A minimal engine.io client, enabled with the `client` feature flag.
+//!
+//! It handles the handshake, the polling and websocket transports,
+//! the upgrade from polling to websocket and the heartbeat mechanism.
+//! It is meant to write protocol-level tests and health checks of engine.io servers
+//! without relying on the javascript client.
+//!
+//! Only the engine.io protocol v4 is supported and TLS is not available,
+//! only the `http` and `ws` schemes can be used.
+//!
+//! ## Example
+//! ```no_run
+//! # use engineioxide::client::{ClientBuilder, ClientEvent};
+//! # async fn doc_main() -> Result<(), engineioxide::client::ClientError> {
+//! let (client, mut rx) = ClientBuilder::new("http://127.0.0.1:3000")
+//!     .req_path("/engine.io/")
+//!     .connect()
+//!     .await?;
+//! println!("connected with sid {} over {:?}", client.sid(), client.transport());
+//!
+//! client.emit("hello")?;
+//! while let Some(event) = rx.recv().await {
+//!     match event {
+//!         ClientEvent::Message(msg) => println!("received {msg}"),
+//!         ClientEvent::Binary(data) => println!("received {} bytes", data.len()),
+//!         ClientEvent::Close(reason) => println!("connection closed: {reason:?}"),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::{borrow::Cow, time::Duration};
+
+use bytes::Bytes;
+use http::{uri::Authority, HeaderMap, HeaderName, HeaderValue, StatusCode, Uri};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite;
+
+use crate::{packet::Packet, str::Str, TransportType};
+
+mod transport;
+
+/// Configuration of the engine.io [`Client`].
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// The path of the engine.io endpoint on the server.
+    ///
+    /// Defaults to `"/engine.io/"`.
+    pub req_path: Cow<'static, str>,
+
+    /// The transports to use, in the order in which they are tried.
+    ///
+    /// Defaults to polling then websocket.
+    pub transports: Vec<TransportType>,
+
+    /// Whether the client should try to upgrade a polling connection to websocket.
+    ///
+    /// Defaults to `true`.
+    pub upgrade: bool,
+
+    /// Headers added to every http request and to the websocket handshake.
+    pub extra_headers: HeaderMap,
+
+    /// Query parameters added to every http request and to the websocket handshake.
+    pub query: Vec<(String, String)>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            req_path: "/engine.io/".into(),
+            transports: vec![TransportType::Polling, TransportType::Websocket],
+            upgrade: true,
+            extra_headers: HeaderMap::new(),
+            query: Vec::new(),
+        }
+    }
+}
+
+/// A builder to open an engine.io connection with a [`Client`].
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    url: String,
+    config: ClientConfig,
+}
+
+impl ClientBuilder {
+    /// Create a new [`ClientBuilder`] for the given server url.
+    ///
+    /// Only the `http` and `ws` schemes are supported. The path of the url is ignored,
+    /// use [`ClientBuilder::req_path`] to change the path of the engine.io endpoint.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            config: ClientConfig::default(),
+        }
+    }
+
+    /// The path of the engine.io endpoint on the server.
+    ///
+    /// Defaults to `"/engine.io/"`.
+    pub fn req_path(mut self, req_path: impl Into<Cow<'static, str>>) -> Self {
+        self.config.req_path = req_path.into();
+        self
+    }
+
+    /// The transports to use, in the order in which they are tried.
+    ///
+    /// If the first transport is websocket, the connection is directly opened with websocket.
+    /// Otherwise it is opened with polling and upgraded to websocket if it is allowed.
+    ///
+    /// Defaults to polling then websocket.
+    pub fn transports<const N: usize>(mut self, transports: [TransportType; N]) -> Self {
+        self.config.transports = transports.to_vec();
+        self
+    }
+
+    /// Whether the client should try to upgrade a polling connection to websocket.
+    ///
+    /// Defaults to `true`.
+    pub fn upgrade(mut self, upgrade: bool) -> Self {
+        self.config.upgrade = upgrade;
+        self
+    }
+
+    /// Add a header to every http request and to the websocket handshake.
+    pub fn extra_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.config.extra_headers.insert(name, value);
+        self
+    }
+
+    /// Add a query parameter to every http request and to the websocket handshake.
+    pub fn query(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.query.push((key.into(), value.into()));
+        self
+    }
+
+    /// Replace the whole configuration of the client.
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Open the engine.io connection.
+    ///
+    /// It returns the [`Client`] to send packets and the channel receiving the [`ClientEvent`]s
+    /// of the connection. The connection is closed when this channel is dropped.
+    ///
+    /// There is no connection timeout, wrap this future with [`tokio::time::timeout`] if needed.
+    pub async fn connect(
+        self,
+    ) -> Result<(Client, mpsc::UnboundedReceiver<ClientEvent>), ClientError> {
+        let url: Uri = (self.url.parse())
+            .map_err(|e: http::uri::InvalidUri| ClientError::InvalidUrl(e.to_string()))?;
+        match url.scheme_str() {
+            Some("http" | "ws") => (),
+            Some(scheme) => return Err(ClientError::UnsupportedScheme(scheme.to_string())),
+            None => return Err(ClientError::InvalidUrl("missing scheme".into())),
+        }
+        let authority = (url.authority().cloned())
+            .ok_or_else(|| ClientError::InvalidUrl("missing host".into()))?;
+        transport::connect(authority, self.config).await
+    }
+}
+
+/// An opened engine.io connection.
+///
+/// It can be cheaply cloned. The connection is closed when [`Client::close`] is called
+/// or when the [`ClientEvent`] receiver is dropped.
+#[derive(Debug, Clone)]
+pub struct Client {
+    handshake: Handshake,
+    transport: TransportType,
+    tx: mpsc::UnboundedSender<Packet>,
+}
+
+impl Client {
+    /// Create a new [`ClientBuilder`] for the given server url.
+    pub fn builder(url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(url)
+    }
+
+    /// The engine.io session id.
+    pub fn sid(&self) -> &Str {
+        &self.handshake.sid
+    }
+
+    /// The handshake data sent by the server when the connection was opened.
+    pub fn handshake(&self) -> &Handshake {
+        &self.handshake
+    }
+
+    /// The transport used by the connection, once the upgrade is done.
+    pub fn transport(&self) -> TransportType {
+        self.transport
+    }
+
+    /// Send a message packet to the server.
+    pub fn emit(&self, msg: impl Into<Str>) -> Result<(), ClientError> {
+        self.send(Packet::Message(msg.into()))
+    }
+
+    /// Send a binary packet to the server.
+    /// It is sent as a binary frame with websocket and base64 encoded with polling.
+    pub fn emit_binary(&self, data: impl Into<Bytes>) -> Result<(), ClientError> {
+        self.send(Packet::Binary(data.into()))
+    }
+
+    /// Close the connection. A [`ClientEvent::Close`] event is sent once it is closed.
+    pub fn close(&self) {
+        self.tx.send(Packet::Close).ok();
+    }
+
+    /// Whether the connection is closed.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    fn send(&self, packet: Packet) -> Result<(), ClientError> {
+        self.tx.send(packet).map_err(|_| ClientError::Closed)
+    }
+}
+
+/// The handshake data sent by the server in the open packet.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handshake {
+    /// The engine.io session id
+    pub sid: Str,
+    /// The transports the connection can be upgraded to
+    pub upgrades: Vec<String>,
+    /// The interval at which the server sends ping packets
+    #[serde(deserialize_with = "deserialize_millis")]
+    pub ping_interval: Duration,
+    /// The amount of time the server waits for a pong packet
+    #[serde(deserialize_with = "deserialize_millis")]
+    pub ping_timeout: Duration,
+    /// The maximum number of bytes per chunk accepted by the server
+    pub max_payload: u64,
+}
+
+impl Handshake {
+    fn decode(data: &str) -> Result<Self, ClientError> {
+        let data = data
+            .strip_prefix('0')
+            .ok_or_else(|| ClientError::Handshake("expected an open packet".into()))?;
+        serde_json::from_str(data).map_err(|e| ClientError::Handshake(e.to_string()))
+    }
+
+    /// The server should send a ping every `ping_interval`,
+    /// the connection is considered closed if nothing is received in `ping_interval + ping_timeout`.
+    fn heartbeat(&self) -> Duration {
+        self.ping_interval + self.ping_timeout
+    }
+}
+
+fn deserialize_millis<'de, D: serde::Deserializer<'de>>(de: D) -> Result<Duration, D::Error> {
+    u64::deserialize(de).map(Duration::from_millis)
+}
+
+/// The events received from an engine.io connection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    /// A message packet
+    Message(Str),
+    /// A binary packet
+    Binary(Bytes),
+    /// The connection was closed. It is always the last event.
+    Close(CloseReason),
+}
+
+/// The reason why the engine.io connection was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    /// The server closed the connection
+    TransportClose,
+    /// The connection was abruptly closed or the server sent an invalid packet
+    TransportError,
+    /// The server did not send any ping in time
+    PingTimeout,
+    /// The client closed the connection
+    ClientClose,
+}
+
+/// Error type for the engine.io [`Client`].
+#[non_exhaustive]
+#[derive(thiserror::Error, Debug)]
+pub enum ClientError {
+    /// The provided url is not valid
+    #[error("invalid url: {0}")]
+    InvalidUrl(String),
+
+    /// Only the `http` and `ws` schemes are supported
+    #[error("unsupported url scheme: {0}")]
+    UnsupportedScheme(String),
+
+    /// Only the polling and websocket transports are supported
+    #[error("unsupported transport: {0:?}")]
+    UnsupportedTransport(TransportType),
+
+    /// The connection to the server failed
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// An error occurred while sending a polling request
+    #[error("http error: {0}")]
+    Http(#[from] hyper::Error),
+
+    /// An error occurred while opening the websocket connection
+    #[error("websocket error: {0}")]
+    Websocket(#[source] Box<tungstenite::Error>),
+
+    /// The server answered a polling request with an unexpected status code
+    #[error("unexpected http status: {0}")]
+    Status(StatusCode),
+
+    /// The engine.io handshake is invalid
+    #[error("invalid handshake: {0}")]
+    Handshake(String),
+
+    /// The connection is closed
+    #[error("connection closed")]
+    Closed,
+}
+
+impl ClientError {
+    /// The [`ErrorKind`](crate::errors::ErrorKind) of this error.
+    pub fn kind(&self) -> crate::errors::ErrorKind {
+        use crate::errors::ErrorKind;
+        match self {
+            ClientError::InvalidUrl(_)
+            | ClientError::UnsupportedScheme(_)
+            | ClientError::UnsupportedTransport(_) => ErrorKind::InvalidInput,
+            ClientError::Io(_)
+            | ClientError::Http(_)
+            | ClientError::Websocket(_)
+            | ClientError::Closed => ErrorKind::Transport,
+            ClientError::Status(_) => ErrorKind::Rejected,
+            ClientError::Handshake(_) => ErrorKind::Protocol,
+        }
+    }
+}
+
+impl From<tungstenite::Error> for ClientError {
+    fn from(err: tungstenite::Error) -> Self {
+        Self::Websocket(Box::new(err))
+    }
+}
+
+fn engine_uri(
+    authority: &Authority,
+    config: &ClientConfig,
+    transport: TransportType,
+    sid: Option<&str>,
+) -> String {
+    let scheme = if transport == TransportType::Websocket {
+        "ws"
+    } else {
+        "http"
+    };
+    let transport: &'static str = transport.into();
+    let mut uri = format!(
+        "{scheme}://{authority}{}?EIO=4&transport={transport}",
+        config.req_path,
+    );
+    if let Some(sid) = sid {
+        uri.push_str("&sid=");
+        uri.push_str(sid);
+    }
+    for (key, value) in &config.query {
+        uri.push('&');
+        uri.push_str(key);
+        uri.push('=');
+        uri.push_str(value);
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_handshake() {
+        const OPEN: &str = "0{\"sid\":\"lv_VI97HAXpY6yYWAAAC\",\"upgrades\":[\"websocket\"],\"pingInterval\":25000,\"pingTimeout\":20000,\"maxPayload\":1000000}";
+        let handshake = Handshake::decode(OPEN).unwrap();
+        assert_eq!(handshake.sid, "lv_VI97HAXpY6yYWAAAC");
+        assert_eq!(handshake.upgrades, ["websocket"]);
+        assert_eq!(handshake.ping_interval, Duration::from_secs(25));
+        assert_eq!(handshake.ping_timeout, Duration::from_secs(20));
+        assert_eq!(handshake.max_payload, 1000000);
+        assert_eq!(handshake.heartbeat(), Duration::from_secs(45));
+
+        assert!(Handshake::decode("4hello").is_err());
+    }
+
+    #[test]
+    fn uri() {
+        let authority = Authority::from_static("127.0.0.1:3000");
+        let mut config = ClientConfig::default();
+        config.query.push(("token".into(), "foo".into()));
+        assert_eq!(
+            engine_uri(&authority, &config, TransportType::Polling, None),
+            "http://127.0.0.1:3000/engine.io/?EIO=4&transport=polling&token=foo"
+        );
+        assert_eq!(
+            engine_uri(&authority, &config, TransportType::Websocket, Some("abc")),
+            "ws://127.0.0.1:3000/engine.io/?EIO=4&transport=websocket&sid=abc&token=foo"
+        );
+    }
+}
@@ -0,0 +1,468 @@
+//! The polling and websocket transports of the engine.io [`Client`].
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use base64::{engine::general_purpose, Engine as _};
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt};
+use http::{header, uri::Authority, Method, Request};
+use http_body_util::{BodyExt, Full};
+use hyper::client::conn::http1::SendRequest;
+use hyper_util::rt::TokioIo;
+use tokio::{
+    net::TcpStream,
+    sync::mpsc,
+    task::JoinHandle,
+    time::{Instant, Sleep},
+};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use super::{engine_uri, Client, ClientConfig, ClientError, ClientEvent, CloseReason, Handshake};
+use crate::{packet::Packet, str::Str, TransportType};
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Polling payloads are made of several packets separated by this character.
+const PACKET_SEPARATOR: char = '\x1e';
+
+/// Open an engine.io connection with the first allowed transport.
+/// If the connection starts with polling, it is upgraded to websocket when possible.
+pub(super) async fn connect(
+    authority: Authority,
+    config: ClientConfig,
+) -> Result<(Client, mpsc::UnboundedReceiver<ClientEvent>), ClientError> {
+    if let Some(transport) = config
+        .transports
+        .iter()
+        .find(|t| !matches!(t, TransportType::Polling | TransportType::Websocket))
+    {
+        return Err(ClientError::UnsupportedTransport(*transport));
+    }
+    let (tx, rx) = mpsc::unbounded_channel();
+    let (events_tx, events_rx) = mpsc::unbounded_channel();
+    let allowed = |transport| config.transports.contains(&transport);
+
+    if config.transports.first() == Some(&TransportType::Websocket)
+        || !allowed(TransportType::Polling)
+    {
+        let mut ws = connect_ws(&authority, &config, None).await?;
+        let handshake = match ws.next().await {
+            Some(Ok(Message::Text(msg))) => Handshake::decode(&msg)?,
+            Some(Err(e)) => return Err(e.into()),
+            _ => return Err(ClientError::Handshake("expected an open packet".into())),
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(sid = ?handshake.sid, "websocket connection opened");
+        let heartbeat = handshake.heartbeat();
+        tokio::spawn(run_ws(ws, Vec::new(), rx, events_tx, heartbeat));
+        let client = Client {
+            handshake,
+            transport: TransportType::Websocket,
+            tx,
+        };
+        return Ok((client, events_rx));
+    }
+
+    let mut http = HttpClient::new(&authority, &config);
+    let uri = engine_uri(&authority, &config, TransportType::Polling, None);
+    let mut packets = decode_payload(http.send(Method::GET, &uri, Bytes::new()).await?)?;
+    if packets.is_empty() {
+        return Err(ClientError::Handshake("expected an open packet".into()));
+    }
+    let handshake = Handshake::decode(&packets.remove(0))?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(sid = ?handshake.sid, "polling connection opened");
+
+    let sid = handshake.sid.clone();
+    let heartbeat = handshake.heartbeat();
+    let uri = engine_uri(&authority, &config, TransportType::Polling, Some(&sid));
+    let (poll_tx, mut poll_rx) = mpsc::unbounded_channel();
+    for packet in packets {
+        poll_tx.send(decode_packet(packet.into())).ok();
+    }
+    let paused = Arc::new(AtomicBool::new(false));
+    let poller = tokio::spawn(poll(
+        HttpClient::new(&authority, &config),
+        uri.clone(),
+        poll_tx,
+        paused.clone(),
+    ));
+
+    let can_upgrade = handshake.upgrades.iter().any(|u| u == "websocket");
+    if config.upgrade && can_upgrade && allowed(TransportType::Websocket) {
+        match upgrade(&authority, &config, &sid, &paused).await {
+            Ok(mut ws) => {
+                // Wait for the last polling request to finish before completing the upgrade.
+                poller.await.ok();
+                ws.send(text(Packet::Upgrade)).await?;
+                let mut pending = Vec::new();
+                while let Ok(packet) = poll_rx.try_recv() {
+                    pending.push(packet.map_err(|_| ClientError::Closed)?);
+                }
+                #[cfg(feature = "tracing")]
+                tracing::debug!(?sid, "connection upgraded to websocket");
+                tokio::spawn(run_ws(ws, pending, rx, events_tx, heartbeat));
+                let client = Client {
+                    handshake,
+                    transport: TransportType::Websocket,
+                    tx,
+                };
+                return Ok((client, events_rx));
+            }
+            Err(_e) if !paused.load(Ordering::Acquire) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(?_e, "failed to upgrade to websocket, keeping polling");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    tokio::spawn(run_polling(
+        http, uri, poll_rx, poller, rx, events_tx, heartbeat,
+    ));
+    let client = Client {
+        handshake,
+        transport: TransportType::Polling,
+        tx,
+    };
+    Ok((client, events_rx))
+}
+
+/// Probe the websocket transport for an existing polling session.
+///
+/// Once the server answered the probe, the polling transport is paused
+/// so that no new polling request is sent.
+async fn upgrade(
+    authority: &Authority,
+    config: &ClientConfig,
+    sid: &str,
+    paused: &AtomicBool,
+) -> Result<WsStream, ClientError> {
+    let mut ws = connect_ws(authority, config, Some(sid)).await?;
+    ws.send(text(Packet::PingUpgrade)).await?;
+    match ws.next().await {
+        Some(Ok(Message::Text(msg))) if msg.as_str() == "3probe" => {
+            paused.store(true, Ordering::Release);
+            Ok(ws)
+        }
+        Some(Err(e)) => Err(e.into()),
+        _ => Err(ClientError::Handshake("invalid probe response".into())),
+    }
+}
+
+/// What to do after receiving a packet from the server.
+enum Action {
+    Continue,
+    Pong,
+    Close(CloseReason),
+}
+
+fn on_packet(packet: Packet, events: &mpsc::UnboundedSender<ClientEvent>) -> Action {
+    let res = match packet {
+        Packet::Ping => return Action::Pong,
+        Packet::Close => return Action::Close(CloseReason::TransportClose),
+        Packet::Message(msg) => events.send(ClientEvent::Message(msg)),
+        Packet::Binary(data) => events.send(ClientEvent::Binary(data)),
+        _ => Ok(()),
+    };
+    // The event receiver was dropped
+    match res {
+        Ok(()) => Action::Continue,
+        Err(_) => Action::Close(CloseReason::ClientClose),
+    }
+}
+
+fn reset(timer: std::pin::Pin<&mut Sleep>, heartbeat: Duration) {
+    timer.reset(Instant::now() + heartbeat);
+}
+
+/// Forward packets between the websocket connection, the event channel and the outgoing packet channel.
+async fn run_ws(
+    ws: WsStream,
+    pending: Vec<Packet>,
+    mut rx: mpsc::UnboundedReceiver<Packet>,
+    events: mpsc::UnboundedSender<ClientEvent>,
+    heartbeat: Duration,
+) {
+    let (mut sink, mut stream) = ws.split();
+    let timer = tokio::time::sleep(heartbeat);
+    tokio::pin!(timer);
+
+    let reason = 'run: {
+        for packet in pending {
+            match on_packet(packet, &events) {
+                Action::Continue => (),
+                Action::Pong => {
+                    if sink.send(text(Packet::Pong)).await.is_err() {
+                        break 'run CloseReason::TransportError;
+                    }
+                }
+                Action::Close(reason) => break 'run reason,
+            }
+        }
+        loop {
+            tokio::select! {
+                msg = stream.next() => {
+                    let packet = match msg {
+                        Some(Ok(Message::Text(msg))) => {
+                            // SAFETY: the websocket text frames are valid utf8
+                            match decode_packet(unsafe { Str::from_bytes_unchecked(msg.into()) }) {
+                                Ok(packet) => packet,
+                                Err(reason) => break reason,
+                            }
+                        }
+                        Some(Ok(Message::Binary(data))) => Packet::Binary(data),
+                        Some(Ok(Message::Close(_))) | None => break CloseReason::TransportClose,
+                        Some(Ok(_)) => continue,
+                        Some(Err(_)) => break CloseReason::TransportError,
+                    };
+                    reset(timer.as_mut(), heartbeat);
+                    match on_packet(packet, &events) {
+                        Action::Continue => (),
+                        Action::Pong => {
+                            if sink.send(text(Packet::Pong)).await.is_err() {
+                                break CloseReason::TransportError;
+                            }
+                        }
+                        Action::Close(reason) => break reason,
+                    }
+                }
+                packet = rx.recv() => match packet {
+                    Some(Packet::Close) | None => {
+                        sink.send(text(Packet::Close)).await.ok();
+                        sink.close().await.ok();
+                        break CloseReason::ClientClose;
+                    }
+                    Some(packet) => {
+                        let msg = match packet {
+                            Packet::Binary(data) => Message::Binary(data),
+                            packet => text(packet),
+                        };
+                        if sink.send(msg).await.is_err() {
+                            break CloseReason::TransportError;
+                        }
+                    }
+                },
+                _ = &mut timer => break CloseReason::PingTimeout,
+            }
+        }
+    };
+    #[cfg(feature = "tracing")]
+    tracing::debug!(?reason, "websocket connection closed");
+    events.send(ClientEvent::Close(reason)).ok();
+}
+
+/// Forward packets between the polling requests, the event channel and the outgoing packet channel.
+async fn run_polling(
+    mut http: HttpClient,
+    uri: String,
+    mut poll_rx: mpsc::UnboundedReceiver<Result<Packet, CloseReason>>,
+    poller: JoinHandle<()>,
+    mut rx: mpsc::UnboundedReceiver<Packet>,
+    events: mpsc::UnboundedSender<ClientEvent>,
+    heartbeat: Duration,
+) {
+    let timer = tokio::time::sleep(heartbeat);
+    tokio::pin!(timer);
+
+    let reason = loop {
+        tokio::select! {
+            packet = poll_rx.recv() => {
+                let packet = match packet {
+                    Some(Ok(packet)) => packet,
+                    Some(Err(reason)) => break reason,
+                    None => break CloseReason::TransportError,
+                };
+                reset(timer.as_mut(), heartbeat);
+                match on_packet(packet, &events) {
+                    Action::Continue => (),
+                    Action::Pong => {
+                        if post(&mut http, &uri, vec![Packet::Pong]).await.is_err() {
+                            break CloseReason::TransportError;
+                        }
+                    }
+                    Action::Close(reason) => break reason,
+                }
+            }
+            packet = rx.recv() => {
+                let mut packets = vec![packet.unwrap_or(Packet::Close)];
+                while let Ok(packet) = rx.try_recv() {
+                    packets.push(packet);
+                }
+                let close = packets.contains(&Packet::Close);
+                if post(&mut http, &uri, packets).await.is_err() {
+                    break CloseReason::TransportError;
+                }
+                if close {
+                    break CloseReason::ClientClose;
+                }
+            }
+            _ = &mut timer => break CloseReason::PingTimeout,
+        }
+    };
+    poller.abort();
+    #[cfg(feature = "tracing")]
+    tracing::debug!(?reason, "polling connection closed");
+    events.send(ClientEvent::Close(reason)).ok();
+}
+
+/// Send polling requests until the transport is paused or the connection is closed.
+async fn poll(
+    mut http: HttpClient,
+    uri: String,
+    tx: mpsc::UnboundedSender<Result<Packet, CloseReason>>,
+    paused: Arc<AtomicBool>,
+) {
+    while !paused.load(Ordering::Acquire) {
+        let packets = match http.send(Method::GET, &uri, Bytes::new()).await {
+            Ok(body) => decode_payload(body),
+            Err(e) => Err(e),
+        };
+        let Ok(packets) = packets else {
+            tx.send(Err(CloseReason::TransportError)).ok();
+            return;
+        };
+        for packet in packets {
+            if tx.send(decode_packet(packet.into())).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+async fn post(http: &mut HttpClient, uri: &str, packets: Vec<Packet>) -> Result<(), ClientError> {
+    let mut body = String::new();
+    for packet in packets {
+        if !body.is_empty() {
+            body.push(PACKET_SEPARATOR);
+        }
+        body.push_str(&String::from(packet));
+    }
+    http.send(Method::POST, uri, Bytes::from(body)).await?;
+    Ok(())
+}
+
+fn decode_payload(body: Bytes) -> Result<Vec<String>, ClientError> {
+    let body = String::from_utf8(body.into())
+        .map_err(|_| ClientError::Handshake("invalid utf8 payload".into()))?;
+    Ok(body
+        .split(PACKET_SEPARATOR)
+        .filter(|packet| !packet.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Decode a v4 packet received from the server.
+///
+/// Binary packets are decoded here because the server side decoder also accepts
+/// the v3 `b4` prefix, which is ambiguous with a v4 base64 payload starting with `4`.
+fn decode_packet(data: Str) -> Result<Packet, CloseReason> {
+    match data.as_bytes().first() {
+        Some(b'b') => general_purpose::STANDARD
+            .decode(&data.as_bytes()[1..])
+            .map(|data| Packet::Binary(data.into()))
+            .map_err(|_| CloseReason::TransportError),
+        _ => Packet::try_from(data).map_err(|_| CloseReason::TransportError),
+    }
+}
+
+fn text(packet: Packet) -> Message {
+    Message::Text(packet.into())
+}
+
+async fn connect_ws(
+    authority: &Authority,
+    config: &ClientConfig,
+    sid: Option<&str>,
+) -> Result<WsStream, ClientError> {
+    let uri = engine_uri(authority, config, TransportType::Websocket, sid);
+    let mut req = uri.into_client_request()?;
+    req.headers_mut().extend(config.extra_headers.clone());
+    let (ws, _) = tokio_tungstenite::connect_async(req).await?;
+    Ok(ws)
+}
+
+/// A minimal http/1 client, reusing its connection between requests when possible.
+struct HttpClient {
+    authority: Authority,
+    headers: http::HeaderMap,
+    conn: Option<SendRequest<Full<Bytes>>>,
+}
+
+impl HttpClient {
+    fn new(authority: &Authority, config: &ClientConfig) -> Self {
+        Self {
+            authority: authority.clone(),
+            headers: config.extra_headers.clone(),
+            conn: None,
+        }
+    }
+
+    async fn send(&mut self, method: Method, uri: &str, body: Bytes) -> Result<Bytes, ClientError> {
+        let mut conn = match self.conn.take() {
+            Some(mut conn) => match conn.ready().await {
+                Ok(()) => conn,
+                Err(_) => self.connect().await?,
+            },
+            None => self.connect().await?,
+        };
+        let path = uri
+            .find(self.authority.as_str())
+            .map(|i| &uri[i + self.authority.as_str().len()..])
+            .unwrap_or(uri);
+        let mut req = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(header::HOST, self.authority.as_str())
+            .header(header::CONTENT_TYPE, "text/plain; charset=UTF-8")
+            .body(Full::new(body))
+            .map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
+        req.headers_mut().extend(self.headers.clone());
+
+        let res = conn.send_request(req).await?;
+        let status = res.status();
+        let body = res.into_body().collect().await?.to_bytes();
+        self.conn = Some(conn);
+        if !status.is_success() {
+            return Err(ClientError::Status(status));
+        }
+        Ok(body)
+    }
+
+    async fn connect(&self) -> Result<SendRequest<Full<Bytes>>, ClientError> {
+        let host = self.authority.host();
+        let port = self.authority.port_u16().unwrap_or(80);
+        let stream = TcpStream::connect((host, port)).await?;
+        let (conn, driver) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(async move {
+            if let Err(_e) = driver.await {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(?_e, "http connection error");
+            }
+        });
+        Ok(conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_v4_binary() {
+        // "4" is a valid first character of a base64 payload
+        let packet = decode_packet(Str::from("b4AE=")).unwrap();
+        assert_eq!(packet, Packet::Binary(Bytes::from_static(&[0xe0, 0x01])));
+        let packet = decode_packet(Str::from("4hello")).unwrap();
+        assert_eq!(packet, Packet::Message("hello".into()));
+        assert!(decode_packet(Str::from("0{}")).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+//! Resolution of the ip of the clients, possibly behind reverse proxies.
+//!
+//! By default the ip of a client is the ip of the peer of the connection, read from the
+//! [`SocketAddr`] http extension of the request, which should be inserted by the http server.
+//!
+//! When the server is behind reverse proxies, they can be declared with
+//! [`EngineIoConfigBuilder::trusted_proxies`](crate::config::EngineIoConfigBuilder::trusted_proxies).
+//! If the peer is a trusted proxy, the addresses forwarded in the `Forwarded` header
+//! (or in the `X-Forwarded-For` header if there is no `Forwarded` header) are then walked from
+//! the closest to the furthest hop, and the first one that is not a trusted proxy is the client ip.
+//! The walk stops at the first address that can't be parsed (e.g. an obfuscated identifier).
+//!
+//! The resolved ip is used by the [per-IP connection limit](crate::connection_limit) and is
+//! available with [`Socket::remote_addr`](crate::socket::Socket::remote_addr).
+//!
+//! **Note**: Only declare proxies that overwrite or append to these headers, otherwise
+//! clients can spoof their ip.
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use http::{header::FORWARDED, request::Parts, HeaderName};
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+/// The configuration of the client ip resolution, see the [module level documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct ClientIpConfig {
+    /// The proxies allowed to forward the ip of the clients.
+    /// Defaults to an empty list (the ip of the peer is always used).
+    pub trusted_proxies: Vec<IpRange>,
+}
+
+impl ClientIpConfig {
+    /// Resolve the ip of the client of a request.
+    ///
+    /// Returns `None` if the request doesn't have a [`SocketAddr`] extension.
+    pub fn resolve(&self, req: &Parts) -> Option<IpAddr> {
+        let peer = req.extensions.get::<SocketAddr>()?.ip().to_canonical();
+        if !self.is_trusted(peer) {
+            return Some(peer);
+        }
+
+        let forwarded = req.headers.get_all(FORWARDED);
+        let hops: Vec<&str> = if forwarded.iter().next().is_some() {
+            forwarded
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .filter_map(forwarded_for)
+                .collect()
+        } else {
+            req.headers
+                .get_all(X_FORWARDED_FOR)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .collect()
+        };
+
+        let mut ip = peer;
+        for hop in hops.into_iter().rev() {
+            if !self.is_trusted(ip) {
+                break;
+            }
+            match parse_node(hop) {
+                Some(hop) => ip = hop,
+                None => break,
+            }
+        }
+        Some(ip)
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|range| range.contains(ip))
+    }
+}
+
+/// Extract the `for` parameter of a `Forwarded` header element.
+fn forwarded_for(element: &str) -> Option<&str> {
+    element.split(';').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("for")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Parse an ip, optionally followed by a port, with brackets around ipv6 addresses.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim();
+    let ip = match node.parse::<IpAddr>() {
+        Ok(ip) => ip,
+        Err(_) => match node.parse::<SocketAddr>() {
+            Ok(addr) => addr.ip(),
+            Err(_) => node.strip_prefix('[')?.strip_suffix(']')?.parse().ok()?,
+        },
+    };
+    Some(ip.to_canonical())
+}
+
+/// A range of ip addresses, written in the CIDR notation (e.g. `10.0.0.0/8` or `fd00::/8`).
+/// A single address (e.g. `127.0.0.1`) is also accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    /// Create a new [`IpRange`] from an address and a prefix length.
+    ///
+    /// Returns an error if the prefix length is larger than the size of the address.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, InvalidIpRange> {
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix_len > max {
+            return Err(InvalidIpRange);
+        }
+        // Ipv4-mapped ranges are converted to ipv4 ranges, like the ips they are compared to
+        match addr.to_canonical() {
+            IpAddr::V4(v4) if addr.is_ipv6() => Ok(Self {
+                addr: v4.into(),
+                prefix_len: prefix_len.saturating_sub(96),
+            }),
+            addr => Ok(Self { addr, prefix_len }),
+        }
+    }
+
+    /// Check if the given ip is in this range.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(addr), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(addr) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(addr), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(addr) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<IpAddr> for IpRange {
+    fn from(addr: IpAddr) -> Self {
+        let addr = addr.to_canonical();
+        let prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+        Self { addr, prefix_len }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = InvalidIpRange;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((addr, prefix_len)) => {
+                let addr = addr.parse().map_err(|_| InvalidIpRange)?;
+                let prefix_len = prefix_len.parse().map_err(|_| InvalidIpRange)?;
+                Self::new(addr, prefix_len)
+            }
+            None => s
+                .parse::<IpAddr>()
+                .map(Self::from)
+                .map_err(|_| InvalidIpRange),
+        }
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// The error returned when parsing an invalid [`IpRange`].
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("invalid ip range")]
+pub struct InvalidIpRange;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(proxies: &[&str]) -> ClientIpConfig {
+        ClientIpConfig {
+            trusted_proxies: proxies.iter().map(|p| p.parse().unwrap()).collect(),
+        }
+    }
+
+    fn request(peer: &str, headers: &[(&str, &str)]) -> Parts {
+        let mut req = http::Request::builder();
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        let mut req = req.body(()).unwrap().into_parts().0;
+        req.extensions.insert(peer.parse::<SocketAddr>().unwrap());
+        req
+    }
+
+    fn ip(ip: &str) -> Option<IpAddr> {
+        Some(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn ip_range() {
+        let range: IpRange = "10.1.0.0/16".parse().unwrap();
+        assert!(range.contains([10, 1, 2, 3].into()));
+        assert!(!range.contains([10, 2, 0, 1].into()));
+        assert!(range.contains("::ffff:10.1.0.1".parse().unwrap()));
+        assert!(!range.contains("fd00::1".parse().unwrap()));
+
+        let range: IpRange = "fd00::/8".parse().unwrap();
+        assert!(range.contains("fd12::1".parse().unwrap()));
+        assert!(!range.contains("fe80::1".parse().unwrap()));
+
+        let range: IpRange = "127.0.0.1".parse().unwrap();
+        assert_eq!(range.to_string(), "127.0.0.1/32");
+        assert!(!range.contains([127, 0, 0, 2].into()));
+        assert!("0.0.0.0/0"
+            .parse::<IpRange>()
+            .unwrap()
+            .contains([1, 2, 3, 4].into()));
+        let range: IpRange = "::ffff:10.0.0.0/104".parse().unwrap();
+        assert_eq!(range.to_string(), "10.0.0.0/8");
+
+        assert_eq!("10.0.0.0/33".parse::<IpRange>(), Err(InvalidIpRange));
+        assert_eq!("foo/8".parse::<IpRange>(), Err(InvalidIpRange));
+    }
+
+    #[test]
+    fn untrusted_peer() {
+        let req = request("1.1.1.1:1234", &[("x-forwarded-for", "2.2.2.2")]);
+        assert_eq!(config(&[]).resolve(&req), ip("1.1.1.1"));
+        assert_eq!(config(&["10.0.0.0/8"]).resolve(&req), ip("1.1.1.1"));
+        let req = http::Request::<()>::default().into_parts().0;
+        assert_eq!(config(&[]).resolve(&req), None);
+    }
+
+    #[test]
+    fn x_forwarded_for() {
+        let config = config(&["10.0.0.0/8"]);
+        let req = request(
+            "10.0.0.1:1234",
+            &[
+                ("x-forwarded-for", "3.3.3.3, 2.2.2.2"),
+                ("x-forwarded-for", "10.0.0.2"),
+            ],
+        );
+        assert_eq!(config.resolve(&req), ip("2.2.2.2"));
+
+        // Only trusted proxies are walked
+        let req = request(
+            "10.0.0.1:1234",
+            &[("x-forwarded-for", "10.0.0.3, 10.0.0.2")],
+        );
+        assert_eq!(config.resolve(&req), ip("10.0.0.3"));
+        let req = request("10.0.0.1:1234", &[("x-forwarded-for", "foo, 10.0.0.2")]);
+        assert_eq!(config.resolve(&req), ip("10.0.0.2"));
+        let req = request("10.0.0.1:1234", &[]);
+        assert_eq!(config.resolve(&req), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn forwarded() {
+        let config = config(&["10.0.0.0/8", "::1"]);
+        let req = request(
+            "[::1]:1234",
+            &[
+                (
+                    "forwarded",
+                    r#"for="[2001:db8::17]:4711";proto=https, For=10.0.0.2:80;by=10.0.0.1"#,
+                ),
+                ("x-forwarded-for", "3.3.3.3"),
+            ],
+        );
+        assert_eq!(config.resolve(&req), ip("2001:db8::17"));
+
+        let req = request(
+            "10.0.0.1:1234",
+            &[("forwarded", "for=_hidden, for=10.0.0.2")],
+        );
+        assert_eq!(config.resolve(&req), ip("10.0.0.2"));
+    }
+}
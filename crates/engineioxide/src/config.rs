@@ -30,16 +30,30 @@
 //! let svc = EngineIoService::with_config(Arc::new(MyHandler), config);
 //! ```
 
-use std::{borrow::Cow, time::Duration};
+use std::{
+    borrow::Cow,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
-use crate::service::TransportType;
+use crate::{
+    client_ip::{ClientIpConfig, IpRange},
+    connection_limit::ConnectionLimits,
+    errors::Error,
+    handshake::{HandshakeFilter, HandshakeRejection},
+    rate_limit::{RateLimit, RateLimitPolicy},
+    runtime::{Runtime, TokioRuntime},
+    service::TransportType,
+    sid::{RandomSidGenerator, SidGenerator},
+};
 
 /// Configuration for the engine.io engine & transports
 #[derive(Debug, Clone)]
 pub struct EngineIoConfig {
     /// The path to listen for engine.io requests on.
+    /// It can be a single path, multiple paths or a matcher function, see [`ReqPath`].
     /// Defaults to "/engine.io".
-    pub req_path: Cow<'static, str>,
+    pub req_path: ReqPath,
 
     /// The interval at which the server will send a ping packet to the client.
     /// Defaults to 25 seconds.
@@ -49,14 +63,46 @@ pub struct EngineIoConfig {
     /// Defaults to 20 seconds.
     pub ping_timeout: Duration,
 
+    /// The ping interval of the sessions opened with the websocket or webtransport transports.
+    /// Sessions opened with the polling transport keep the [`ping_interval`](Self::ping_interval)
+    /// even after being upgraded, because it is negotiated during the handshake.
+    /// Defaults to `None` (the [`ping_interval`](Self::ping_interval) is used).
+    pub ws_ping_interval: Option<Duration>,
+
+    /// The ping timeout of the sessions opened with the websocket or webtransport transports.
+    /// Defaults to `None` (the [`ping_timeout`](Self::ping_timeout) is used).
+    pub ws_ping_timeout: Option<Duration>,
+
+    /// The maximum interval between two pings sent to an idle session.
+    ///
+    /// If it is set, the ping interval of a session is doubled after each ping during which
+    /// no message was received from the client, up to this interval. It goes back to the
+    /// [`ping_interval`](Self::ping_interval) as soon as a message is received.
+    /// This interval is advertised to the clients in the handshake so that they don't
+    /// consider the connection closed between two spaced pings.
+    ///
+    /// Defaults to `None` (pings are always sent every [`ping_interval`](Self::ping_interval)).
+    pub idle_ping_interval: Option<Duration>,
+
     /// The maximum number of packets that can be buffered per connection before being emitted to the client.
     ///
     /// If the buffer if full the `emit()` method will return an error
     ///
+    /// The limit applies to each [`PacketPriority`](crate::socket::PacketPriority) queue of the buffer.
+    ///
     /// Defaults to 128 packets
     pub max_buffer_size: usize,
 
+    /// The maximum number of bytes of the packets buffered per connection before being emitted to the client.
+    ///
+    /// Message and binary packets are refused once the limit is reached, like when the
+    /// [`max_buffer_size`](Self::max_buffer_size) limit is reached.
+    ///
+    /// Defaults to `None` (only the number of packets is limited).
+    pub max_buffer_bytes: Option<usize>,
+
     /// The maximum number of bytes that can be received per http request.
+    /// Requests with a larger body are rejected with a `413 Payload Too Large` response.
     /// Defaults to 100KB.
     pub max_payload: u64,
 
@@ -67,9 +113,70 @@ pub struct EngineIoConfig {
     /// but will consume more memory.
     pub ws_read_buffer_size: usize,
 
+    /// The maximum payload size of a binary websocket frame written by the server.
+    /// Larger binary messages are split into fragmented frames so that they are written
+    /// chunk by chunk rather than being fully copied into the websocket write buffer.
+    ///
+    /// Defaults to `None` (binary messages are never fragmented).
+    pub ws_max_frame_size: Option<usize>,
+
+    /// The maximum size of a websocket message received from the client.
+    /// The connection is closed with a `1009` close frame if a larger message is received.
+    /// Compressed messages are never inflated past this size.
+    ///
+    /// Defaults to `None` (64MiB, the limit of the websocket implementation).
+    pub ws_max_message_size: Option<usize>,
+
     /// Allowed transports on this server
     /// It is represented as a bitfield to allow to combine any number of transports easily
     pub transports: u8,
+
+    /// The permessage-deflate configuration for the websocket transport.
+    /// If it is set, the extension is negotiated with clients that support it.
+    /// Defaults to `None`.
+    #[cfg(feature = "ws-deflate")]
+    pub ws_deflate: Option<DeflateConfig>,
+
+    /// The compression configuration for the polling transport payloads.
+    /// If it is set, payloads are compressed for clients sending an `Accept-Encoding` header
+    /// with `gzip` or `deflate`.
+    /// Defaults to `None`.
+    #[cfg(feature = "http-compression")]
+    pub http_compression: Option<HttpCompressionConfig>,
+
+    /// The CORS configuration of the engine.io endpoint.
+    /// If it is set, preflight requests are answered and CORS headers are added to the responses.
+    /// Defaults to `None`.
+    pub cors: Option<CorsConfig>,
+
+    /// The maximum number of message and binary packets received per second for each session.
+    /// Defaults to `None` (no limit).
+    pub max_packets_per_second: Option<RateLimit>,
+
+    /// The maximum duration the buffer of a session can stay full before the session is closed
+    /// with the [`SlowConsumer`](crate::socket::DisconnectReason::SlowConsumer) reason.
+    /// Defaults to `None` (slow consumers are never closed).
+    pub slow_consumer_timeout: Option<Duration>,
+
+    /// The generator of the session ids.
+    /// Defaults to [`RandomSidGenerator`].
+    pub sid_generator: Arc<dyn SidGenerator>,
+
+    /// A filter called before every handshake to reject it with a custom http response.
+    /// Defaults to `None`, all the handshakes are accepted.
+    pub handshake_filter: Option<Arc<dyn HandshakeFilter>>,
+
+    /// The global and per-IP limits of the number of open sessions, checked at handshake.
+    /// Defaults to no limit.
+    pub connection_limits: ConnectionLimits,
+
+    /// The resolution of the ip of the clients behind reverse proxies.
+    /// Defaults to the ip of the peer of the connection.
+    pub client_ip: ClientIpConfig,
+
+    /// The runtime used to spawn the tasks and timers of the sessions.
+    /// Defaults to [`TokioRuntime`].
+    pub runtime: Arc<dyn Runtime>,
 }
 
 impl Default for EngineIoConfig {
@@ -78,10 +185,240 @@ impl Default for EngineIoConfig {
             req_path: "/engine.io".into(),
             ping_interval: Duration::from_millis(25000),
             ping_timeout: Duration::from_millis(20000),
+            ws_ping_interval: None,
+            ws_ping_timeout: None,
+            idle_ping_interval: None,
             max_buffer_size: 128,
+            max_buffer_bytes: None,
             max_payload: 1e5 as u64, // 100kb
             ws_read_buffer_size: 4096,
+            ws_max_frame_size: None,
+            ws_max_message_size: None,
             transports: TransportType::Polling as u8 | TransportType::Websocket as u8,
+            #[cfg(feature = "ws-deflate")]
+            ws_deflate: None,
+            #[cfg(feature = "http-compression")]
+            http_compression: None,
+            cors: None,
+            max_packets_per_second: None,
+            slow_consumer_timeout: None,
+            sid_generator: Arc::new(RandomSidGenerator),
+            handshake_filter: None,
+            connection_limits: ConnectionLimits::default(),
+            client_ip: ClientIpConfig::default(),
+            runtime: Arc::new(TokioRuntime),
+        }
+    }
+}
+
+/// A function matching the paths of the engine.io requests, see [`ReqPath::matcher`].
+type PathMatcher = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+enum PathKind {
+    Prefixes(Vec<Cow<'static, str>>),
+    Matcher(PathMatcher),
+}
+
+/// The paths of the requests handled by the engine.io server, see [`EngineIoConfig::req_path`].
+///
+/// It is either one or more path prefixes, to mount the same server at several paths,
+/// or a custom matcher function.
+///
+/// The paths are shared by all the clones of a [`ReqPath`], and therefore of the [`EngineIoConfig`],
+/// so they can be changed at runtime with [`ReqPath::set`] once the server is running.
+///
+/// # Example
+/// ```
+/// # use engineioxide::config::{EngineIoConfig, ReqPath};
+/// let config = EngineIoConfig::builder()
+///     .req_path(["/engine.io", "/legacy/engine.io"])
+///     .build();
+/// assert!(config.req_path.matches("/legacy/engine.io/"));
+///
+/// // Move the server to another path
+/// config.req_path.set("/v2/engine.io");
+/// assert!(!config.req_path.matches("/engine.io/"));
+///
+/// let path = ReqPath::matcher(|path| path.ends_with("/engine.io/"));
+/// assert!(path.matches("/tenant-1/engine.io/"));
+/// ```
+#[derive(Clone)]
+pub struct ReqPath(Arc<RwLock<PathKind>>);
+
+impl ReqPath {
+    /// Match the requests whose path starts with `path`.
+    pub fn new(path: impl Into<Cow<'static, str>>) -> Self {
+        Self::many([path])
+    }
+
+    /// Match the requests whose path starts with any of the given `paths`.
+    pub fn many<T: Into<Cow<'static, str>>>(paths: impl IntoIterator<Item = T>) -> Self {
+        let paths = paths.into_iter().map(Into::into).collect();
+        Self(Arc::new(RwLock::new(PathKind::Prefixes(paths))))
+    }
+
+    /// Match the requests whose path is accepted by the `matcher` function.
+    pub fn matcher(matcher: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(RwLock::new(PathKind::Matcher(Arc::new(matcher)))))
+    }
+
+    /// Returns true if a request with this path should be handled by the engine.io server.
+    pub fn matches(&self, path: &str) -> bool {
+        match &*self.0.read().unwrap() {
+            PathKind::Prefixes(prefixes) => prefixes.iter().any(|p| path.starts_with(p.as_ref())),
+            PathKind::Matcher(matcher) => matcher(path),
+        }
+    }
+
+    /// Replace the paths of this [`ReqPath`] and of all its clones.
+    /// The sessions already opened on the previous paths are not closed,
+    /// but their next requests must be sent on the new paths.
+    pub fn set(&self, path: impl Into<ReqPath>) {
+        let kind = path.into().0.read().unwrap().clone();
+        *self.0.write().unwrap() = kind;
+    }
+
+    /// The path prefixes, or an empty list with a [matcher](ReqPath::matcher) function.
+    ///
+    /// It is used to register the routes of the frameworks that can't
+    /// delegate the path matching to the server, which don't follow the changes made with [`ReqPath::set`].
+    pub fn prefixes(&self) -> Vec<Cow<'static, str>> {
+        match &*self.0.read().unwrap() {
+            PathKind::Prefixes(prefixes) => prefixes.clone(),
+            PathKind::Matcher(_) => Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ReqPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &*self.0.read().unwrap() {
+            PathKind::Prefixes(prefixes) => f.debug_tuple("ReqPath").field(prefixes).finish(),
+            PathKind::Matcher(_) => f.write_str("ReqPath(<matcher>)"),
+        }
+    }
+}
+
+impl From<&'static str> for ReqPath {
+    fn from(path: &'static str) -> Self {
+        Self::new(path)
+    }
+}
+impl From<String> for ReqPath {
+    fn from(path: String) -> Self {
+        Self::new(path)
+    }
+}
+impl From<Cow<'static, str>> for ReqPath {
+    fn from(path: Cow<'static, str>) -> Self {
+        Self::new(path)
+    }
+}
+impl<T: Into<Cow<'static, str>>> From<Vec<T>> for ReqPath {
+    fn from(paths: Vec<T>) -> Self {
+        Self::many(paths)
+    }
+}
+impl<T: Into<Cow<'static, str>>, const N: usize> From<[T; N]> for ReqPath {
+    fn from(paths: [T; N]) -> Self {
+        Self::many(paths)
+    }
+}
+
+/// The origins allowed to make cross-origin requests.
+#[derive(Debug, Clone)]
+pub enum AllowedOrigins {
+    /// Any origin is allowed.
+    Any,
+    /// Only the given origins are allowed (e.g. `https://example.com`).
+    List(Vec<Cow<'static, str>>),
+}
+
+/// CORS configuration of the engine.io endpoint.
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// The origins allowed to make cross-origin requests.
+    /// Defaults to [`AllowedOrigins::Any`].
+    pub allowed_origins: AllowedOrigins,
+
+    /// Allow requests with credentials (cookies, authorization headers...).
+    /// The request origin is then sent back rather than a wildcard.
+    /// Defaults to `false`.
+    pub credentials: bool,
+
+    /// How long the result of a preflight request can be cached by the client.
+    /// Defaults to `None`.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: AllowedOrigins::Any,
+            credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+/// Configuration of the compression of polling payloads.
+#[cfg(feature = "http-compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http-compression")))]
+#[derive(Debug, Clone)]
+pub struct HttpCompressionConfig {
+    /// The compression level, from 0 (no compression) to 9 (best compression).
+    /// Defaults to 6.
+    pub level: u32,
+
+    /// The minimum size in bytes of a payload to be compressed.
+    /// Smaller payloads are sent uncompressed.
+    /// Defaults to 1KiB.
+    pub threshold: usize,
+}
+
+#[cfg(feature = "http-compression")]
+impl Default for HttpCompressionConfig {
+    fn default() -> Self {
+        Self {
+            level: 6,
+            threshold: 1024,
+        }
+    }
+}
+
+/// Configuration of the permessage-deflate websocket extension.
+#[cfg(feature = "ws-deflate")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ws-deflate")))]
+#[derive(Debug, Clone)]
+pub struct DeflateConfig {
+    /// The compression level, from 0 (no compression) to 9 (best compression).
+    /// Defaults to 6.
+    pub level: u32,
+
+    /// Reset the compression context after each message sent by the server.
+    /// It reduces the memory usage but also the compression ratio.
+    /// Defaults to `false`.
+    pub server_no_context_takeover: bool,
+
+    /// Ask the client to reset its compression context after each message.
+    /// Defaults to `false`.
+    pub client_no_context_takeover: bool,
+
+    /// The minimum size in bytes of a message to be compressed.
+    /// Smaller messages are sent uncompressed.
+    /// Defaults to 1KiB.
+    pub threshold: usize,
+}
+
+#[cfg(feature = "ws-deflate")]
+impl Default for DeflateConfig {
+    fn default() -> Self {
+        Self {
+            level: 6,
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+            threshold: 1024,
         }
     }
 }
@@ -97,6 +434,66 @@ impl EngineIoConfig {
     pub fn allowed_transport(&self, transport: TransportType) -> bool {
         self.transports & transport as u8 == transport as u8
     }
+
+    /// Run the [`HandshakeFilter`] on the http parts of a handshake request and then reserve
+    /// a slot for the session if there are [`ConnectionLimits`].
+    #[cfg(feature = "webtransport")]
+    pub(crate) async fn allow_handshake(
+        &self,
+        req: &mut http::request::Parts,
+    ) -> Result<(), Error> {
+        if let Some(filter) = &self.handshake_filter {
+            filter.filter(req).await.map_err(rejected)?;
+        }
+        self.acquire_connection(req)
+    }
+
+    /// Reserve a slot for the session of a handshake request if there are [`ConnectionLimits`].
+    pub(crate) fn acquire_connection(&self, req: &mut http::request::Parts) -> Result<(), Error> {
+        let ip = self.client_ip.resolve(req);
+        self.connection_limits.acquire(req, ip).map_err(rejected)
+    }
+
+    /// The heartbeat of the sessions opened with the given [`TransportType`].
+    pub(crate) fn heartbeat(&self, transport: TransportType) -> Heartbeat {
+        let (interval, timeout) = if transport == TransportType::Polling {
+            (self.ping_interval, self.ping_timeout)
+        } else {
+            (
+                self.ws_ping_interval.unwrap_or(self.ping_interval),
+                self.ws_ping_timeout.unwrap_or(self.ping_timeout),
+            )
+        };
+        Heartbeat {
+            interval,
+            timeout,
+            idle_interval: self.idle_ping_interval.map(|idle| idle.max(interval)),
+        }
+    }
+}
+
+pub(crate) fn rejected(rejection: HandshakeRejection) -> Error {
+    #[cfg(feature = "tracing")]
+    tracing::debug!("handshake rejected: {:?}", rejection);
+    Error::HandshakeRejected(rejection)
+}
+
+/// The heartbeat settings of a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Heartbeat {
+    /// The interval between two pings.
+    pub interval: Duration,
+    /// The time to wait for a pong packet.
+    pub timeout: Duration,
+    /// The maximum interval between two pings for an idle session.
+    pub idle_interval: Option<Duration>,
+}
+
+impl Heartbeat {
+    /// The ping interval advertised to the client in the handshake.
+    pub fn advertised_interval(&self) -> Duration {
+        self.idle_interval.unwrap_or(self.interval)
+    }
 }
 
 /// Builder for [`EngineIoConfig`]
@@ -113,8 +510,9 @@ impl EngineIoConfigBuilder {
     }
 
     /// The path to listen for engine.io requests on.
+    /// It can be a single path, multiple paths or a matcher function, see [`ReqPath`].
     /// Defaults to "/engine.io".
-    pub fn req_path(mut self, req_path: impl Into<Cow<'static, str>>) -> Self {
+    pub fn req_path(mut self, req_path: impl Into<ReqPath>) -> Self {
         self.config.req_path = req_path.into();
         self
     }
@@ -133,6 +531,38 @@ impl EngineIoConfigBuilder {
         self
     }
 
+    /// The ping interval of the sessions opened with the websocket or webtransport transports.
+    /// Sessions opened with the polling transport keep the [`ping_interval`](Self::ping_interval)
+    /// even after being upgraded, because it is negotiated during the handshake.
+    /// Defaults to the [`ping_interval`](Self::ping_interval).
+    pub fn ws_ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.config.ws_ping_interval = Some(ping_interval);
+        self
+    }
+
+    /// The ping timeout of the sessions opened with the websocket or webtransport transports.
+    /// Defaults to the [`ping_timeout`](Self::ping_timeout).
+    pub fn ws_ping_timeout(mut self, ping_timeout: Duration) -> Self {
+        self.config.ws_ping_timeout = Some(ping_timeout);
+        self
+    }
+
+    /// Back off the pings sent to idle sessions, up to `max_interval` between two pings.
+    ///
+    /// The ping interval of a session is doubled after each ping during which no message was
+    /// received from the client, and goes back to the [`ping_interval`](Self::ping_interval)
+    /// as soon as a message is received. It reduces the wakeups of servers holding
+    /// a lot of mostly idle connections.
+    ///
+    /// The `max_interval` is advertised to the clients in the handshake, so a dead connection
+    /// may take up to `max_interval + ping_timeout` to be detected by the clients.
+    ///
+    /// Defaults to `None` (pings are always sent every [`ping_interval`](Self::ping_interval)).
+    pub fn idle_ping_interval(mut self, max_interval: Duration) -> Self {
+        self.config.idle_ping_interval = Some(max_interval);
+        self
+    }
+
     /// The maximum number of packets that can be buffered per connection before being emitted to the client.
     ///
     /// If the buffer if full the `emit()` method will return an error
@@ -174,7 +604,20 @@ impl EngineIoConfigBuilder {
         self
     }
 
+    /// The maximum number of bytes of the packets buffered per connection before being emitted to the client.
+    ///
+    /// Message and binary packets are refused once the limit is reached, like when the
+    /// [`max_buffer_size`](Self::max_buffer_size) limit is reached.
+    /// It bounds the memory used by a client that does not read its packets fast enough.
+    ///
+    /// Defaults to `None` (only the number of packets is limited).
+    pub fn max_buffer_bytes(mut self, max_buffer_bytes: usize) -> Self {
+        self.config.max_buffer_bytes = Some(max_buffer_bytes);
+        self
+    }
+
     /// The maximum number of bytes that can be received per http request.
+    /// Requests with a larger body are rejected with a `413 Payload Too Large` response.
     /// Defaults to 100kb.
     pub fn max_payload(mut self, max_payload: u64) -> Self {
         self.config.max_payload = max_payload;
@@ -191,14 +634,44 @@ impl EngineIoConfigBuilder {
         self
     }
 
+    /// The maximum payload size of a binary websocket frame written by the server.
+    /// Larger binary messages are split into fragmented frames so that they are written
+    /// chunk by chunk rather than being fully copied into the websocket write buffer.
+    ///
+    /// Fragmented messages are not compressed with the permessage-deflate extension.
+    ///
+    /// Defaults to `None` (binary messages are never fragmented).
+    ///
+    /// # Panics
+    /// If the frame size is 0.
+    pub fn ws_max_frame_size(mut self, ws_max_frame_size: usize) -> Self {
+        assert!(ws_max_frame_size > 0, "frame size must be greater than 0");
+        self.config.ws_max_frame_size = Some(ws_max_frame_size);
+        self
+    }
+
+    /// The maximum size of a websocket message received from the client.
+    /// The connection is closed with a `1009` close frame if a larger message is received.
+    /// Compressed messages are never inflated past this size.
+    ///
+    /// Defaults to `None` (64MiB, the limit of the websocket implementation).
+    pub fn ws_max_message_size(mut self, ws_max_message_size: usize) -> Self {
+        self.config.ws_max_message_size = Some(ws_max_message_size);
+        self
+    }
+
     /// Allowed transports on this server
     ///
-    /// The `transports` array should have a size of 1 or 2
+    /// The `transports` array should have a size of 1 to 3
     ///
     /// Defaults to :
     /// `[TransportType::Polling, TransportType::Websocket]`
+    ///
+    /// With the `webtransport` feature flag, [`TransportType::WebTransport`] can be added to enable it.
+    /// Sessions should then be given to
+    /// [`EngineIoService::on_webtransport_session`](crate::service::EngineIoService::on_webtransport_session).
     pub fn transports<const N: usize>(mut self, transports: [TransportType; N]) -> Self {
-        assert!(N > 0 && N <= 2);
+        assert!(N > 0 && N <= 3);
         self.config.transports = 0;
         for transport in transports {
             self.config.transports |= transport as u8;
@@ -206,6 +679,134 @@ impl EngineIoConfigBuilder {
         self
     }
 
+    /// Enable the permessage-deflate extension on the websocket transport.
+    /// It will be negotiated with clients that support it.
+    #[cfg(feature = "ws-deflate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ws-deflate")))]
+    pub fn ws_deflate(mut self, ws_deflate: DeflateConfig) -> Self {
+        self.config.ws_deflate = Some(ws_deflate);
+        self
+    }
+
+    /// Enable the compression of polling payloads with gzip or deflate
+    /// for clients that accept it.
+    #[cfg(feature = "http-compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http-compression")))]
+    pub fn http_compression(mut self, http_compression: HttpCompressionConfig) -> Self {
+        self.config.http_compression = Some(http_compression);
+        self
+    }
+
+    /// Enable CORS handling for the engine.io endpoint.
+    /// Preflight (`OPTIONS`) requests are answered and CORS headers are added to the responses
+    /// of allowed origins.
+    pub fn cors(mut self, cors: CorsConfig) -> Self {
+        self.config.cors = Some(cors);
+        self
+    }
+
+    /// Limit the number of message and binary packets received per second for each session
+    /// with a token bucket of `per_second` tokens. The `policy` sets what to do with the packets
+    /// received when the limit is exceeded, see [`RateLimitPolicy`].
+    ///
+    /// Defaults to `None` (no limit).
+    ///
+    /// # Panics
+    /// If `per_second` is 0.
+    pub fn max_packets_per_second(mut self, per_second: u32, policy: RateLimitPolicy) -> Self {
+        self.config.max_packets_per_second = Some(RateLimit::new(per_second, policy));
+        self
+    }
+
+    /// Close the sessions whose buffer stays full for longer than `timeout` with the
+    /// [`SlowConsumer`](crate::socket::DisconnectReason::SlowConsumer) reason,
+    /// rather than dropping their packets indefinitely.
+    ///
+    /// The buffer is considered full as long as no packet could be pushed to it.
+    /// The session is closed on the first failed emit after the timeout.
+    ///
+    /// Defaults to `None` (slow consumers are never closed).
+    pub fn slow_consumer_timeout(mut self, timeout: Duration) -> Self {
+        self.config.slow_consumer_timeout = Some(timeout);
+        self
+    }
+
+    /// The generator of the session ids, see [`SidGenerator`].
+    /// It can be used to embed a node identifier or a timestamp in the session ids.
+    ///
+    /// Defaults to [`RandomSidGenerator`].
+    pub fn sid_generator(mut self, generator: impl SidGenerator) -> Self {
+        self.config.sid_generator = Arc::new(generator);
+        self
+    }
+
+    /// The runtime used to spawn the tasks and timers of the sessions, see the
+    /// [`runtime`](crate::runtime) module doc.
+    ///
+    /// Defaults to [`TokioRuntime`].
+    pub fn runtime(mut self, runtime: impl Runtime) -> Self {
+        self.config.runtime = Arc::new(runtime);
+        self
+    }
+
+    /// A filter called with the http parts of every handshake request, before any session is
+    /// created. A rejected handshake is answered with the status and JSON body of the
+    /// [`HandshakeRejection`], see [`HandshakeFilter`].
+    ///
+    /// The filter can be asynchronous, e.g. to authorize the connections with some io,
+    /// see [`async_filter`](crate::handshake::async_filter).
+    pub fn handshake_filter(mut self, filter: impl HandshakeFilter) -> Self {
+        self.config.handshake_filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// The maximum number of sessions open at the same time. A handshake exceeding it
+    /// is rejected with a `503 Service Unavailable` status and a `Retry-After` header.
+    /// See the [`connection_limit`](crate::connection_limit) module doc for more details.
+    ///
+    /// Defaults to `None` (no limit).
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.config.connection_limits.max_connections = Some(max);
+        self
+    }
+
+    /// The maximum number of sessions open at the same time from the same ip. A handshake
+    /// exceeding it is rejected with a `503 Service Unavailable` status and a `Retry-After` header.
+    /// See the [`connection_limit`](crate::connection_limit) module doc for more details.
+    ///
+    /// Defaults to `None` (no limit).
+    pub fn max_connections_per_ip(mut self, max: usize) -> Self {
+        self.config.connection_limits.max_connections_per_ip = Some(max);
+        self
+    }
+
+    /// The delay sent in the `Retry-After` header of the handshakes rejected by a connection limit.
+    ///
+    /// Defaults to 5 seconds.
+    pub fn connection_retry_after(mut self, retry_after: Duration) -> Self {
+        self.config.connection_limits.retry_after = retry_after;
+        self
+    }
+
+    /// The reverse proxies allowed to forward the ip of the clients with the `Forwarded`
+    /// or `X-Forwarded-For` headers. The resolved ip is used by the per-IP connection limit
+    /// and is available with [`Socket::remote_addr`](crate::socket::Socket::remote_addr).
+    /// See the [`client_ip`](crate::client_ip) module doc for more details.
+    ///
+    /// Defaults to an empty list (the ip of the peer of the connection is always used).
+    ///
+    /// # Example
+    /// ```
+    /// # use engineioxide::config::EngineIoConfig;
+    /// let config = EngineIoConfig::builder()
+    ///     .trusted_proxies(["10.0.0.0/8".parse().unwrap(), "::1".parse().unwrap()])
+    ///     .build();
+    /// ```
+    pub fn trusted_proxies(mut self, proxies: impl IntoIterator<Item = IpRange>) -> Self {
+        self.config.client_ip.trusted_proxies = proxies.into_iter().collect();
+        self
+    }
+
     /// Build the config
     pub fn build(self) -> EngineIoConfig {
         self.config
@@ -240,4 +841,51 @@ mod tests {
         assert!(conf.allowed_transport(TransportType::Polling));
         assert!(conf.allowed_transport(TransportType::Websocket));
     }
+
+    #[test]
+    fn heartbeat_per_transport() {
+        let conf = EngineIoConfig::builder()
+            .ping_interval(Duration::from_secs(10))
+            .ping_timeout(Duration::from_secs(5))
+            .ws_ping_interval(Duration::from_secs(30))
+            .build();
+        let polling = conf.heartbeat(TransportType::Polling);
+        assert_eq!(polling.interval, Duration::from_secs(10));
+        assert_eq!(polling.timeout, Duration::from_secs(5));
+        let ws = conf.heartbeat(TransportType::Websocket);
+        assert_eq!(ws.interval, Duration::from_secs(30));
+        assert_eq!(ws.timeout, Duration::from_secs(5));
+        assert_eq!(ws.advertised_interval(), Duration::from_secs(30));
+
+        // The idle interval can't be shorter than the ping interval
+        let conf = EngineIoConfig::builder()
+            .ping_interval(Duration::from_secs(10))
+            .ws_ping_interval(Duration::from_secs(30))
+            .idle_ping_interval(Duration::from_secs(20))
+            .build();
+        let polling = conf.heartbeat(TransportType::Polling);
+        assert_eq!(polling.advertised_interval(), Duration::from_secs(20));
+        let ws = conf.heartbeat(TransportType::Websocket);
+        assert_eq!(ws.advertised_interval(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn req_path() {
+        let path = ReqPath::many(["/engine.io", "/legacy/"]);
+        assert!(path.matches("/engine.io/"));
+        assert!(path.matches("/legacy/engine.io/"));
+        assert!(!path.matches("/other"));
+        assert_eq!(path.prefixes(), ["/engine.io", "/legacy/"]);
+
+        let conf = EngineIoConfig::builder().req_path(path.clone()).build();
+        conf.clone()
+            .req_path
+            .set(ReqPath::matcher(|p| p.ends_with("/engine.io/")));
+        assert!(path.matches("/tenant/engine.io/"));
+        assert!(!conf.req_path.matches("/legacy/"));
+        assert!(conf.req_path.prefixes().is_empty());
+
+        // Setting a path to itself doesn't deadlock
+        path.set(path.clone());
+    }
 }
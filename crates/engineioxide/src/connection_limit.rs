@@ -0,0 +1,256 @@
+//! Limits of the number of sessions open at the same time, enforced at handshake.
+//!
+//! A global limit and a per-IP limit can be set with
+//! [`EngineIoConfigBuilder::max_connections`](crate::config::EngineIoConfigBuilder::max_connections)
+//! and [`EngineIoConfigBuilder::max_connections_per_ip`](crate::config::EngineIoConfigBuilder::max_connections_per_ip).
+//! A handshake exceeding a limit is answered with a `503 Service Unavailable` status and a
+//! `Retry-After` header, before any session is created. It is checked after the
+//! [`HandshakeFilter`](crate::handshake::HandshakeFilter), so rejected handshakes are not counted.
+//!
+//! The ip of a client is resolved with the [`ClientIpConfig`](crate::client_ip::ClientIpConfig)
+//! of the config, from the [`SocketAddr`](std::net::SocketAddr) http extension of the request,
+//! which should be inserted by the http server. The per-IP limit is not applied if it is missing.
+//!
+//! The open sessions and the rejected handshakes are counted by the [`ConnectionCounters`]
+//! of the config, available with [`ConnectionLimits::counters`]. Sessions are only counted
+//! when a limit is set.
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use http::{request::Parts, StatusCode};
+
+use crate::handshake::HandshakeRejection;
+
+/// The connection limits of a server, see the [module level documentation](self).
+#[derive(Debug, Clone)]
+pub struct ConnectionLimits {
+    /// The maximum number of sessions open at the same time.
+    /// Defaults to `None` (no limit).
+    pub max_connections: Option<usize>,
+
+    /// The maximum number of sessions open at the same time from the same ip.
+    /// Defaults to `None` (no limit).
+    pub max_connections_per_ip: Option<usize>,
+
+    /// The delay sent in the `Retry-After` header of the rejected handshakes.
+    /// It is rounded up to the second.
+    /// Defaults to 5 seconds.
+    pub retry_after: Duration,
+
+    counters: Arc<ConnectionCounters>,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: None,
+            max_connections_per_ip: None,
+            retry_after: Duration::from_secs(5),
+            counters: Arc::default(),
+        }
+    }
+}
+
+impl ConnectionLimits {
+    /// The counters of the open sessions and of the rejected handshakes.
+    pub fn counters(&self) -> &ConnectionCounters {
+        &self.counters
+    }
+
+    /// Check if any limit is set.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.max_connections.is_some() || self.max_connections_per_ip.is_some()
+    }
+
+    /// Reserve a connection slot for a handshake request.
+    /// The slot is stored in the request extensions and released when the session is closed.
+    pub(crate) fn acquire(
+        &self,
+        req: &mut Parts,
+        ip: Option<IpAddr>,
+    ) -> Result<(), HandshakeRejection> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let permit = self.try_acquire(ip).map_err(|_limit| {
+            #[cfg(feature = "metrics")]
+            crate::metrics::handshake_rejected(_limit);
+            self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+            HandshakeRejection::with_message(
+                StatusCode::SERVICE_UNAVAILABLE,
+                3,
+                "Too many connections",
+            )
+            .with_retry_after(self.retry_after)
+        })?;
+        req.extensions.insert(permit);
+        Ok(())
+    }
+
+    fn try_acquire(&self, ip: Option<IpAddr>) -> Result<ConnectionPermit, &'static str> {
+        let mut state = self.counters.state.lock().unwrap();
+        if self.max_connections.is_some_and(|max| state.open >= max) {
+            return Err("global");
+        }
+        if let Some(ip) = ip {
+            let open = state.per_ip.get(&ip).copied().unwrap_or_default();
+            if self.max_connections_per_ip.is_some_and(|max| open >= max) {
+                return Err("ip");
+            }
+            state.per_ip.insert(ip, open + 1);
+        }
+        state.open += 1;
+        Ok(ConnectionPermit(Arc::new(PermitInner {
+            counters: self.counters.clone(),
+            ip,
+            released: AtomicBool::new(false),
+        })))
+    }
+}
+
+/// The counters of the open sessions and of the rejected handshakes,
+/// see the [module level documentation](self).
+#[derive(Debug, Default)]
+pub struct ConnectionCounters {
+    state: Mutex<OpenConnections>,
+    rejected: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct OpenConnections {
+    open: usize,
+    per_ip: HashMap<IpAddr, usize>,
+}
+
+impl ConnectionCounters {
+    /// The number of sessions currently open.
+    pub fn open(&self) -> usize {
+        self.state.lock().unwrap().open
+    }
+
+    /// The number of sessions currently open from the given ip.
+    pub fn open_from(&self, ip: IpAddr) -> usize {
+        let state = self.state.lock().unwrap();
+        state.per_ip.get(&ip).copied().unwrap_or_default()
+    }
+
+    /// The total number of handshakes rejected because a limit was exceeded.
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    fn release(&self, ip: Option<IpAddr>) {
+        let mut state = self.state.lock().unwrap();
+        state.open -= 1;
+        if let Some(ip) = ip {
+            match state.per_ip.get_mut(&ip) {
+                Some(1) => {
+                    state.per_ip.remove(&ip);
+                }
+                Some(open) => *open -= 1,
+                None => (),
+            }
+        }
+    }
+}
+
+/// A connection slot reserved at handshake.
+///
+/// It is released when the session is closed, or when it is dropped if the session
+/// could not be created.
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionPermit(Arc<PermitInner>);
+
+#[derive(Debug)]
+struct PermitInner {
+    counters: Arc<ConnectionCounters>,
+    ip: Option<IpAddr>,
+    released: AtomicBool,
+}
+
+impl ConnectionPermit {
+    /// Release the connection slot. It is a no-op if it was already released.
+    pub fn release(&self) {
+        self.0.release();
+    }
+}
+
+impl PermitInner {
+    fn release(&self) {
+        if !self.released.swap(true, Ordering::AcqRel) {
+            self.counters.release(self.ip);
+        }
+    }
+}
+
+impl Drop for PermitInner {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max: Option<usize>, max_per_ip: Option<usize>) -> ConnectionLimits {
+        ConnectionLimits {
+            max_connections: max,
+            max_connections_per_ip: max_per_ip,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn max_connections() {
+        let limits = limits(Some(2), None);
+        let p1 = limits.try_acquire(None).unwrap();
+        let _p2 = limits.try_acquire(None).unwrap();
+        assert_eq!(limits.try_acquire(None).unwrap_err(), "global");
+        assert_eq!(limits.counters().open(), 2);
+
+        p1.release();
+        p1.release();
+        assert_eq!(limits.counters().open(), 1);
+        limits.try_acquire(None).unwrap();
+    }
+
+    #[test]
+    fn max_connections_per_ip() {
+        let limits = limits(None, Some(1));
+        let ip1 = IpAddr::from([127, 0, 0, 1]);
+        let ip2 = IpAddr::from([127, 0, 0, 2]);
+        let p1 = limits.try_acquire(Some(ip1)).unwrap();
+        assert_eq!(limits.try_acquire(Some(ip1)).unwrap_err(), "ip");
+        let _p2 = limits.try_acquire(Some(ip2)).unwrap();
+        // Requests without ip are not limited per ip
+        let _p3 = limits.try_acquire(None).unwrap();
+        assert_eq!(limits.counters().open_from(ip1), 1);
+        assert_eq!(limits.counters().open(), 3);
+
+        // Dropping the last clone of a permit releases it
+        drop(p1.clone());
+        assert_eq!(limits.counters().open_from(ip1), 1);
+        drop(p1);
+        assert_eq!(limits.counters().open_from(ip1), 0);
+        assert_eq!(limits.counters().open(), 2);
+    }
+
+    #[test]
+    fn acquire_rejection() {
+        let limits = limits(Some(0), None);
+        let mut req = http::Request::<()>::default().into_parts().0;
+        let rejection = limits.acquire(&mut req, None).unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(rejection.retry_after(), Some(Duration::from_secs(5)));
+        assert_eq!(limits.counters().rejected(), 1);
+        assert!(req.extensions.get::<ConnectionPermit>().is_none());
+    }
+}
@@ -1,24 +1,26 @@
-use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
-};
+use std::sync::Arc;
 
 use http::request::Parts;
 
 use crate::{
     config::EngineIoConfig,
+    connection_limit::ConnectionPermit,
     handler::EngineIoHandler,
     service::TransportType,
+    sharded::ShardedMap,
     socket::{DisconnectReason, Socket},
 };
-use crate::{service::ProtocolVersion, sid::Sid};
+use crate::{errors::Error, handshake::HandshakeRejection, service::ProtocolVersion, sid::Sid};
 
-type SocketMap<T> = RwLock<HashMap<Sid, Arc<T>>>;
+/// The number of session ids generated for a new session before failing the handshake,
+/// when the generated ids collide with the ids of the open sessions.
+const SID_GENERATION_ATTEMPTS: usize = 3;
 
 /// The [`EngineIo`] struct holds the state of the engine.io server as well as utility methods to manage the state
 pub struct EngineIo<H: EngineIoHandler> {
-    /// A map of all the sockets connected to the server
-    sockets: SocketMap<Socket<H::Data>>,
+    /// A map of all the sockets connected to the server, sharded to avoid
+    /// contention on a single lock when many clients connect at once
+    sockets: ShardedMap<Sid, Arc<Socket<H::Data>>>,
 
     /// The handler for the engine.io server that will be called when events are received
     pub handler: Arc<H>,
@@ -31,7 +33,7 @@ impl<H: EngineIoHandler> EngineIo<H> {
     /// Create a new Engine.IO server with a [`EngineIoHandler`] and a [`EngineIoConfig`]
     pub fn new(handler: Arc<H>, config: EngineIoConfig) -> Self {
         Self {
-            sockets: RwLock::new(HashMap::new()),
+            sockets: ShardedMap::new(),
             config,
             handler,
         }
@@ -39,18 +41,32 @@ impl<H: EngineIoHandler> EngineIo<H> {
 }
 
 impl<H: EngineIoHandler> EngineIo<H> {
-    /// Create a new engine.io session and a new socket and add it to the socket map
+    /// Create a new engine.io session and a new socket and add it to the socket map.
+    ///
+    /// A new session id is generated while it collides with the id of an open session.
+    /// The handshake fails with [`Error::SidCollision`] after [`SID_GENERATION_ATTEMPTS`] attempts.
     pub(crate) fn create_session(
         self: &Arc<Self>,
         protocol: ProtocolVersion,
         transport: TransportType,
         req: Parts,
         #[cfg(feature = "v3")] supports_binary: bool,
-    ) -> Arc<Socket<H::Data>> {
+    ) -> Result<Arc<Socket<H::Data>>, Error> {
+        let mut sid = self.config.sid_generator.generate();
+        for _ in 1..SID_GENERATION_ATTEMPTS {
+            if !self.sockets.contains_key(&sid) {
+                break;
+            }
+            #[cfg(feature = "tracing")]
+            tracing::warn!("[sid={sid}] generated session id already in use, regenerating");
+            sid = self.config.sid_generator.generate();
+        }
+
         let engine = self.clone();
         let close_fn = Box::new(move |sid, reason| engine.close_session(sid, reason));
 
         let socket = Socket::new(
+            sid,
             protocol,
             transport,
             &self.config,
@@ -60,35 +76,54 @@ impl<H: EngineIoHandler> EngineIo<H> {
             supports_binary,
         );
         let socket = Arc::new(socket);
-        self.sockets
-            .write()
-            .unwrap()
-            .insert(socket.id, socket.clone());
+        // The id is checked again on insertion, as another session may have taken it since.
+        if self.sockets.try_insert(sid, socket.clone()).is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("[sid={sid}] session id collision, the handshake is rejected");
+            return Err(Error::SidCollision(sid));
+        }
+        #[cfg(feature = "metrics")]
+        crate::metrics::session_opened(transport);
         self.handler.clone().on_connect(socket.clone());
-        socket
+        Ok(socket)
+    }
+
+    /// Reject the handshake if the handler doesn't accept new sessions.
+    pub(crate) fn accept_session(&self) -> Result<(), Error> {
+        if self.handler.accepts_sessions() {
+            Ok(())
+        } else {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("new sessions are not accepted, rejecting handshake");
+            Err(Error::HandshakeRejected(HandshakeRejection::unavailable()))
+        }
     }
 
     /// Get a socket by its sid
     /// Clones the socket ref to avoid holding the lock
     pub fn get_socket(&self, sid: Sid) -> Option<Arc<Socket<H::Data>>> {
-        self.sockets.read().unwrap().get(&sid).cloned()
+        self.sockets.get(&sid)
     }
 
     /// Close an engine.io session by removing the socket from the socket map and closing the socket
     /// It should be the only way to close a session and to remove a socket from the socket map
     pub fn close_session(&self, sid: Sid, reason: DisconnectReason) {
-        let socket = self.sockets.write().unwrap().remove(&sid);
+        let socket = self.sockets.remove(&sid);
         if let Some(socket) = socket {
+            #[cfg(feature = "metrics")]
+            crate::metrics::session_closed(socket.transport_type());
+            if let Some(permit) = socket.req_parts.extensions.get::<ConnectionPermit>() {
+                permit.release();
+            }
             // Try to close the internal channel if it is available
-            // E.g. with polling transport the channel is not always locked so it is necessary to close it here
-            socket.internal_rx.try_lock().map(|mut rx| rx.close()).ok();
+            // E.g. with polling transport the channel is not always taken so it is necessary to close it here
+            if let Some(mut rx) = socket.internal_rx.try_take() {
+                rx.close();
+            }
             socket.abort_heartbeat();
             self.handler.on_disconnect(socket, reason);
             #[cfg(feature = "tracing")]
-            tracing::debug!(
-                "remaining sockets: {:?}",
-                self.sockets.read().unwrap().len()
-            );
+            tracing::debug!("remaining sockets: {:?}", self.sockets.len());
         }
     }
 }
@@ -134,14 +169,16 @@ mod tests {
     #[tokio::test]
     async fn create_session() {
         let engine = create_engine();
-        let socket = engine.create_session(
-            ProtocolVersion::V4,
-            TransportType::Polling,
-            Request::<()>::default().into_parts().0,
-            #[cfg(feature = "v3")]
-            true,
-        );
-        assert_eq!(engine.sockets.read().unwrap().len(), 1);
+        let socket = engine
+            .create_session(
+                ProtocolVersion::V4,
+                TransportType::Polling,
+                Request::<()>::default().into_parts().0,
+                #[cfg(feature = "v3")]
+                true,
+            )
+            .unwrap();
+        assert_eq!(engine.sockets.len(), 1);
         assert_eq!(socket.protocol, ProtocolVersion::V4);
         assert!(socket.is_http());
     }
@@ -149,31 +186,95 @@ mod tests {
     #[tokio::test]
     async fn close_session() {
         let engine = create_engine();
-        let socket = engine.create_session(
-            ProtocolVersion::V4,
-            TransportType::Polling,
-            Request::<()>::default().into_parts().0,
-            #[cfg(feature = "v3")]
-            true,
-        );
-        assert_eq!(engine.sockets.read().unwrap().len(), 1);
+        let socket = engine
+            .create_session(
+                ProtocolVersion::V4,
+                TransportType::Polling,
+                Request::<()>::default().into_parts().0,
+                #[cfg(feature = "v3")]
+                true,
+            )
+            .unwrap();
+        assert_eq!(engine.sockets.len(), 1);
         engine.close_session(socket.id, DisconnectReason::TransportClose);
-        assert_eq!(engine.sockets.read().unwrap().len(), 0);
+        assert_eq!(engine.sockets.len(), 0);
     }
 
     #[tokio::test]
     async fn get_socket() {
         let engine = create_engine();
-        let socket = engine.create_session(
-            ProtocolVersion::V4,
-            TransportType::Polling,
-            Request::<()>::default().into_parts().0,
-            #[cfg(feature = "v3")]
-            true,
-        );
-        assert_eq!(engine.sockets.read().unwrap().len(), 1);
+        let socket = engine
+            .create_session(
+                ProtocolVersion::V4,
+                TransportType::Polling,
+                Request::<()>::default().into_parts().0,
+                #[cfg(feature = "v3")]
+                true,
+            )
+            .unwrap();
+        assert_eq!(engine.sockets.len(), 1);
         let socket = engine.get_socket(socket.id).unwrap();
         assert_eq!(socket.protocol, ProtocolVersion::V4);
         assert!(socket.is_http());
     }
+    #[tokio::test]
+    async fn sid_generator() {
+        let config = EngineIoConfig::builder()
+            .sid_generator(|| Sid::from_bytes([1; 12]))
+            .build();
+        let engine = Arc::new(EngineIo::new(Arc::new(MockHandler), config));
+        let socket = engine
+            .create_session(
+                ProtocolVersion::V4,
+                TransportType::Polling,
+                Request::<()>::default().into_parts().0,
+                #[cfg(feature = "v3")]
+                true,
+            )
+            .unwrap();
+        assert_eq!(socket.id, Sid::from_bytes([1; 12]));
+        assert!(engine.get_socket(Sid::from_bytes([1; 12])).is_some());
+    }
+
+    #[tokio::test]
+    async fn sid_generator_collision() {
+        use std::sync::atomic::{AtomicU8, Ordering};
+        let count = AtomicU8::new(0);
+        // Generates the same id twice before generating a new one
+        let config = EngineIoConfig::builder()
+            .sid_generator(move || Sid::from_bytes([count.fetch_add(1, Ordering::Relaxed) / 2; 12]))
+            .build();
+        let engine = Arc::new(EngineIo::new(Arc::new(MockHandler), config));
+        let create_session = || {
+            engine.create_session(
+                ProtocolVersion::V4,
+                TransportType::Polling,
+                Request::<()>::default().into_parts().0,
+                #[cfg(feature = "v3")]
+                true,
+            )
+        };
+        assert_eq!(create_session().unwrap().id, Sid::from_bytes([0; 12]));
+        assert_eq!(create_session().unwrap().id, Sid::from_bytes([1; 12]));
+
+        let config = EngineIoConfig::builder()
+            .sid_generator(|| Sid::from_bytes([1; 12]))
+            .build();
+        let engine = Arc::new(EngineIo::new(Arc::new(MockHandler), config));
+        let create_session = || {
+            engine.create_session(
+                ProtocolVersion::V4,
+                TransportType::Polling,
+                Request::<()>::default().into_parts().0,
+                #[cfg(feature = "v3")]
+                true,
+            )
+        };
+        let socket = create_session().unwrap();
+        let err = create_session().unwrap_err();
+        assert!(matches!(err, Error::SidCollision(sid) if sid == socket.id));
+        // The live session is not replaced
+        assert!(Arc::ptr_eq(&engine.get_socket(socket.id).unwrap(), &socket));
+        assert_eq!(engine.sockets.len(), 1);
+    }
 }
@@ -1,52 +1,185 @@
+//! The errors of the engine.io server.
+//!
+//! Every error can be classified with an [`ErrorKind`], so that the failures can be handled
+//! by class (e.g. retrying on a full buffer, logging the protocol errors...) without matching
+//! on every variant. The kind of an error is stable across releases, while new variants can be
+//! added to the [non exhaustive](https://doc.rust-lang.org/reference/attributes/type_system.html)
+//! error enums.
+use std::fmt;
+
 use http::{Response, StatusCode};
 use tokio::sync::mpsc;
 use tokio_tungstenite::tungstenite;
 
 use crate::body::ResponseBody;
+use crate::handshake::HandshakeRejection;
 use crate::packet::Packet;
 use crate::sid::Sid;
 
+/// The class of an error, returned by the `kind()` method of the error types.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The underlying connection failed or is closed.
+    Transport,
+    /// The peer sent something that doesn't follow the engine.io or socket.io protocol.
+    Protocol,
+    /// Some data could not be serialized or deserialized.
+    Serialization,
+    /// A buffer of the connection is full.
+    Buffer,
+    /// An operation didn't complete in time.
+    Timeout,
+    /// A configured limit was exceeded (rate limit, payload size...).
+    Limit,
+    /// The request was rejected by the server (e.g. by a handshake filter).
+    Rejected,
+    /// An error occurred in the adapter, while communicating with the other servers.
+    Adapter,
+    /// The operation is not supported or one of its arguments is invalid.
+    InvalidInput,
+}
+
+impl ErrorKind {
+    /// A stable code for this kind, in snake case (e.g. `"buffer"`).
+    /// It can be used as a metric label or in structured logs.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Transport => "transport",
+            ErrorKind::Protocol => "protocol",
+            ErrorKind::Serialization => "serialization",
+            ErrorKind::Buffer => "buffer",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Limit => "limit",
+            ErrorKind::Rejected => "rejected",
+            ErrorKind::Adapter => "adapter",
+            ErrorKind::InvalidInput => "invalid_input",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// The errors that can occur while handling an engine.io request or connection.
+#[non_exhaustive]
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
+    /// A binary packet of a polling request is not valid base64.
     #[error("error decoding binary packet from polling request: {0:?}")]
     Base64(#[from] base64::DecodeError),
+    /// A text packet is not valid utf-8.
     #[error("error decoding packet: {0:?}")]
     StrUtf8(#[from] std::str::Utf8Error),
+    /// An io error occurred on the connection.
     #[error("io error: {0:?}")]
     Io(#[from] std::io::Error),
+    /// A packet was received when it was not expected.
     #[error("bad packet received")]
     BadPacket(Packet),
+    /// An error occurred on the websocket connection.
     #[error("ws transport error: {0:?}")]
-    WsTransport(#[from] tungstenite::Error),
+    WsTransport(#[source] Box<tungstenite::Error>),
+    /// An http response could not be built.
     #[error("http error: {0:?}")]
     Http(#[from] http::Error),
+    /// A packet could not be sent to the internal channel of the session.
     #[error("internal channel error: {0:?}")]
     SendChannel(#[from] mpsc::error::TrySendError<Packet>),
+    /// A packet could not be received from the internal channel of the session.
     #[error("internal channel error: {0:?}")]
     RecvChannel(#[from] mpsc::error::TryRecvError),
+    /// The client didn't answer a ping in time.
     #[error("heartbeat timeout")]
     HeartbeatTimeout,
+    /// The transport upgrade failed.
     #[error("upgrade error")]
     Upgrade,
+    /// The connection was aborted.
     #[error("aborted connection")]
     Aborted,
 
+    /// The request is answered with an http error status.
     #[error("http error response: {0:?}")]
     HttpErrorResponse(StatusCode),
+    /// The handshake was rejected by a filter, a hook or a connection limit.
+    #[error("handshake rejected: {0:?}")]
+    HandshakeRejected(HandshakeRejection),
 
+    /// The [`SidGenerator`](crate::sid::SidGenerator) generated the id of a session that is still open.
+    #[error("session id collision: {0}")]
+    SidCollision(Sid),
+
+    /// The session id of the request is unknown.
     #[error("unknown session id")]
     UnknownSessionID(Sid),
+    /// The transport of the request doesn't match the transport of the session.
     #[error("transport mismatch")]
     TransportMismatch,
+    /// The payload of the request is larger than the configured maximum.
     #[error("payload too large")]
     PayloadTooLarge,
+    /// The client sent more packets than the configured rate limit.
+    #[error("rate limit exceeded")]
+    RateLimitExceeded,
 
+    /// The length prefix of a packet is invalid.
     #[error("Invalid packet length")]
     InvalidPacketLength,
+    /// The type of a packet is invalid.
     #[error("Invalid packet type")]
     InvalidPacketType(Option<char>),
 }
 
+impl Error {
+    /// The [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        use mpsc::error::TrySendError;
+        match self {
+            Error::Io(_) | Error::WsTransport(_) | Error::Http(_) | Error::Aborted => {
+                ErrorKind::Transport
+            }
+            Error::SendChannel(TrySendError::Full(_)) => ErrorKind::Buffer,
+            Error::SendChannel(TrySendError::Closed(_)) | Error::RecvChannel(_) => {
+                ErrorKind::Transport
+            }
+            Error::Base64(_) | Error::StrUtf8(_) => ErrorKind::Serialization,
+            Error::BadPacket(_)
+            | Error::Upgrade
+            | Error::UnknownSessionID(_)
+            | Error::TransportMismatch
+            | Error::InvalidPacketLength
+            | Error::InvalidPacketType(_) => ErrorKind::Protocol,
+            Error::HeartbeatTimeout => ErrorKind::Timeout,
+            Error::PayloadTooLarge | Error::RateLimitExceeded => ErrorKind::Limit,
+            Error::HttpErrorResponse(_) | Error::HandshakeRejected(_) => ErrorKind::Rejected,
+            Error::SidCollision(_) => ErrorKind::InvalidInput,
+        }
+    }
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(err: tungstenite::Error) -> Self {
+        match err {
+            // A websocket message or frame exceeded the configured max size
+            tungstenite::Error::Capacity(_) => Error::PayloadTooLarge,
+            // An inflated message exceeded the configured max size
+            #[cfg(feature = "ws-deflate")]
+            tungstenite::Error::Io(err)
+                if err
+                    .get_ref()
+                    .is_some_and(|err| err.is::<crate::transport::deflate::MessageTooLarge>()) =>
+            {
+                Error::PayloadTooLarge
+            }
+            err => Error::WsTransport(Box::new(err)),
+        }
+    }
+}
+
 /// Convert an error into an http response
 /// If it is a known error, return the appropriate http status code
 /// Otherwise, return a 500
@@ -64,12 +197,17 @@ impl<B> From<Error> for Response<ResponseBody<B>> {
                 .status(code)
                 .body(ResponseBody::empty_response())
                 .unwrap(),
+            Error::HandshakeRejected(rejection) => rejection.into(),
             Error::BadPacket(_) | Error::InvalidPacketLength | Error::InvalidPacketType(_) => {
                 Response::builder()
                     .status(400)
                     .body(ResponseBody::empty_response())
                     .unwrap()
             }
+            Error::RateLimitExceeded => Response::builder()
+                .status(429)
+                .body(ResponseBody::empty_response())
+                .unwrap(),
             Error::PayloadTooLarge => Response::builder()
                 .status(413)
                 .body(ResponseBody::empty_response())
@@ -85,7 +223,7 @@ impl<B> From<Error> for Response<ResponseBody<B>> {
 
             _e => {
                 #[cfg(feature = "tracing")]
-                tracing::debug!("uncaught error {_e:?}");
+                tracing::debug!(kind = %_e.kind(), "uncaught error {_e:?}");
                 Response::builder()
                     .status(StatusCode::INTERNAL_SERVER_ERROR)
                     .body(ResponseBody::empty_response())
@@ -94,3 +232,31 @@ impl<B> From<Error> for Response<ResponseBody<B>> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+
+    #[test]
+    fn error_kind() {
+        let err = Error::from(tungstenite::Error::ConnectionClosed);
+        assert_eq!(err.kind(), ErrorKind::Transport);
+        assert!(err.source().is_some());
+        let err = Error::from(tungstenite::Error::Capacity(
+            tungstenite::error::CapacityError::MessageTooLong {
+                size: 2,
+                max_size: 1,
+            },
+        ));
+        assert_eq!(err.kind(), ErrorKind::Limit);
+
+        let (tx, _rx) = mpsc::channel(1);
+        tx.try_send(Packet::Noop).unwrap();
+        let err = Error::from(tx.try_send(Packet::Noop).unwrap_err());
+        assert_eq!(err.kind(), ErrorKind::Buffer);
+        assert_eq!(err.kind().code(), "buffer");
+        assert_eq!(Error::InvalidPacketLength.kind(), ErrorKind::Protocol);
+    }
+}
@@ -39,10 +39,13 @@
 //! // Create an engine io service with the given handler
 //! let svc = EngineIoService::new(Arc::new(MyHandler::default()));
 //! ```
+use std::future::Future;
 use std::sync::Arc;
 
 use bytes::Bytes;
 
+use crate::handoff::{HandoffRequest, HandoffResponse};
+use crate::sid::Sid;
 use crate::socket::{DisconnectReason, Socket};
 use crate::str::Str;
 
@@ -64,4 +67,28 @@ pub trait EngineIoHandler: std::fmt::Debug + Send + Sync + 'static {
 
     /// Called when a binary message is received from the client.
     fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<Self::Data>>);
+
+    /// Whether new sessions can be opened, e.g. it returns `false` while the server is shutting down.
+    ///
+    /// When it returns `false`, the handshake requests are rejected with a
+    /// `503 Service Unavailable` status before any session is created.
+    /// By default it returns `true`.
+    fn accepts_sessions(&self) -> bool {
+        true
+    }
+
+    /// Called when an http polling request is received for a session that is not open on this server.
+    ///
+    /// It can be implemented to forward the request to the server owning the session, so that
+    /// http long-polling works without sticky sessions. See the [`handoff`](crate::handoff) module.
+    ///
+    /// By default it returns `None` and the request is rejected with an unknown session error.
+    fn forward_polling(
+        &self,
+        sid: Sid,
+        req: HandoffRequest,
+    ) -> impl Future<Output = Option<HandoffResponse>> + Send {
+        let _ = (sid, req);
+        std::future::ready(None)
+    }
 }
@@ -0,0 +1,67 @@
+//! ## Session handoff for deployments without sticky sessions
+//!
+//! With the http long-polling transport, every request of a session must reach the server that
+//! opened it. Without a sticky load balancer, a request may hit another server which does not know
+//! the session.
+//!
+//! In this case the [`EngineIoHandler::forward_polling`] fn is called with a [`HandoffRequest`].
+//! It can forward it to the server owning the session (e.g. through a message broker), which will
+//! handle it with [`Socket::handoff`] and send back the [`HandoffResponse`] to return to the client.
+//!
+//! [`EngineIoHandler::forward_polling`]: crate::handler::EngineIoHandler::forward_polling
+//! [`Socket::handoff`]: crate::socket::Socket::handoff
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::StatusCode;
+
+/// An http polling request received for a session that is not open on this server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandoffRequest {
+    /// A polling GET request to receive the packets of the session.
+    Poll {
+        /// The maximum duration to wait for packets.
+        /// A noop packet is sent to the client if there is no packet to send after this duration.
+        ///
+        /// It is set to the ping interval and can be reduced to fit the timeout of the forwarder.
+        timeout: Duration,
+    },
+    /// A polling POST request with a payload of packets sent by the client.
+    Post {
+        /// The raw payload of the request.
+        data: Bytes,
+        /// If the payload is a binary payload (only used with the engine.io v3 protocol).
+        is_binary: bool,
+    },
+}
+
+/// The response to a [`HandoffRequest`] to send back to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandoffResponse {
+    /// The status code of the response.
+    pub status: StatusCode,
+    /// The body of the response.
+    pub data: Bytes,
+    /// If the body is a binary payload.
+    pub is_binary: bool,
+}
+
+impl HandoffResponse {
+    /// A successful response with the given body.
+    pub fn ok(data: impl Into<Bytes>, is_binary: bool) -> Self {
+        Self {
+            status: StatusCode::OK,
+            data: data.into(),
+            is_binary,
+        }
+    }
+
+    /// An error response without body.
+    pub fn error(status: StatusCode) -> Self {
+        Self {
+            status,
+            data: Bytes::new(),
+            is_binary: false,
+        }
+    }
+}
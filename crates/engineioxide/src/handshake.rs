@@ -0,0 +1,194 @@
+//! Hooks to reject the engine.io handshakes before any session is created.
+//!
+//! A [`HandshakeFilter`] set with
+//! [`EngineIoConfigBuilder::handshake_filter`](crate::config::EngineIoConfigBuilder::handshake_filter)
+//! is called with the http parts of every handshake request, that is the first polling
+//! request of a session and the websocket requests that are not an upgrade of a polling session.
+//! It is the cheapest place to implement a maintenance mode, an ip ban or an origin check: the
+//! request is answered with the status and JSON body of the [`HandshakeRejection`] and no session,
+//! socket or task is ever created.
+//!
+//! The filter returns a future, so the decision can also need some io, like checking a token
+//! against a database: an asynchronous closure can be wrapped with [`async_filter`], like the
+//! `allowRequest` option of the javascript engine.io server. The handshake is only deferred
+//! until the future resolves when it does not complete immediately.
+use std::borrow::Cow;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::future::{self, BoxFuture};
+use http::{request::Parts, Response, StatusCode};
+use http_body_util::Full;
+
+use crate::body::ResponseBody;
+
+/// A rejection of an engine.io handshake, answered with an http status and a JSON body.
+#[derive(Debug, Clone)]
+pub struct HandshakeRejection {
+    status: StatusCode,
+    body: serde_json::Value,
+    retry_after: Option<Duration>,
+}
+
+impl HandshakeRejection {
+    /// Reject the handshake with a status and a custom JSON body.
+    pub fn new(status: StatusCode, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            body,
+            retry_after: None,
+        }
+    }
+
+    /// Reject the handshake with a status and an engine.io error body:
+    /// `{"code": <code>, "message": <message>}`, like the errors returned by the server itself.
+    pub fn with_message(
+        status: StatusCode,
+        code: u8,
+        message: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        let body = serde_json::json!({ "code": code, "message": message.into() });
+        Self::new(status, body)
+    }
+
+    /// Reject the handshake with a `403 Forbidden` status and the engine.io
+    /// `{"code": 4, "message": "Forbidden"}` body.
+    pub fn forbidden() -> Self {
+        Self::with_message(StatusCode::FORBIDDEN, 4, "Forbidden")
+    }
+
+    /// Reject the handshake with a `503 Service Unavailable` status and the engine.io
+    /// `{"code": 3, "message": "Server unavailable"}` body.
+    pub fn unavailable() -> Self {
+        Self::with_message(StatusCode::SERVICE_UNAVAILABLE, 3, "Server unavailable")
+    }
+
+    /// The http status of the rejection.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The JSON body of the rejection.
+    pub fn body(&self) -> &serde_json::Value {
+        &self.body
+    }
+
+    /// Tell the client when to retry with a `Retry-After` header, rounded up to the second.
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    /// The delay sent in the `Retry-After` header of the rejection, if any.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+}
+
+impl<B> From<HandshakeRejection> for Response<ResponseBody<B>> {
+    fn from(rejection: HandshakeRejection) -> Self {
+        let body = Full::new(Bytes::from(rejection.body.to_string()));
+        let mut res = Response::builder()
+            .status(rejection.status)
+            .header("Content-Type", "application/json");
+        if let Some(retry_after) = rejection.retry_after {
+            let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            res = res.header("Retry-After", secs);
+        }
+        res.body(ResponseBody::custom_response(body)).unwrap()
+    }
+}
+
+/// A filter of the engine.io handshakes, see the [module level documentation](self).
+///
+/// The filter returns a future, so it can accept or reject the handshake after some io.
+/// It is implemented for any synchronous `Fn(&Parts) -> Result<(), HandshakeRejection>` closure,
+/// whose handshakes are answered without being deferred.
+/// ```
+/// # use engineioxide::{config::EngineIoConfig, handshake::HandshakeRejection};
+/// let config = EngineIoConfig::builder()
+///     .handshake_filter(|req: &http::request::Parts| {
+///         match req.headers.get("origin") {
+///             Some(origin) if origin == "https://example.com" => Ok(()),
+///             _ => Err(HandshakeRejection::forbidden()),
+///         }
+///     })
+///     .build();
+/// ```
+///
+/// Asynchronous closures can be wrapped with [`async_filter`].
+pub trait HandshakeFilter: Send + Sync + 'static {
+    /// Accept or reject a handshake request from its http parts.
+    fn filter(&self, req: &Parts) -> BoxFuture<'static, Result<(), HandshakeRejection>>;
+}
+
+impl<F> HandshakeFilter for F
+where
+    F: Fn(&Parts) -> Result<(), HandshakeRejection> + Send + Sync + 'static,
+{
+    fn filter(&self, req: &Parts) -> BoxFuture<'static, Result<(), HandshakeRejection>> {
+        Box::pin(future::ready(self(req)))
+    }
+}
+
+impl Debug for dyn HandshakeFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("HandshakeFilter")
+    }
+}
+
+/// A [`HandshakeFilter`] made from an asynchronous closure, see [`async_filter`].
+#[derive(Clone)]
+pub struct AsyncFilter<F>(F);
+
+/// Create a [`HandshakeFilter`] from a `Fn(&Parts) -> impl Future<Output = Result<(), HandshakeRejection>>`
+/// closure, like the `allowRequest` option of the javascript engine.io server.
+///
+/// The returned future can't borrow the request, so the closure should extract what it
+/// needs from the http parts before moving it into the future.
+/// ```
+/// # use engineioxide::{config::EngineIoConfig, handshake::{async_filter, HandshakeRejection}};
+/// # use http::StatusCode;
+/// async fn is_valid(token: Option<http::HeaderValue>) -> bool {
+///     // Check the token against a database or an auth server
+///     token.is_some()
+/// }
+///
+/// let config = EngineIoConfig::builder()
+///     .handshake_filter(async_filter(|req: &http::request::Parts| {
+///         let token = req.headers.get("authorization").cloned();
+///         async move {
+///             if is_valid(token).await {
+///                 Ok(())
+///             } else {
+///                 Err(HandshakeRejection::with_message(StatusCode::UNAUTHORIZED, 4, "Invalid token"))
+///             }
+///         }
+///     }))
+///     .build();
+/// ```
+pub fn async_filter<F, Fut>(f: F) -> AsyncFilter<F>
+where
+    F: Fn(&Parts) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), HandshakeRejection>> + Send + 'static,
+{
+    AsyncFilter(f)
+}
+
+impl<F, Fut> HandshakeFilter for AsyncFilter<F>
+where
+    F: Fn(&Parts) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), HandshakeRejection>> + Send + 'static,
+{
+    fn filter(&self, req: &Parts) -> BoxFuture<'static, Result<(), HandshakeRejection>> {
+        Box::pin((self.0)(req))
+    }
+}
+
+impl<F> Debug for AsyncFilter<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AsyncFilter")
+    }
+}
@@ -2,7 +2,7 @@
 #![warn(
     clippy::all,
     clippy::todo,
-    clippy::empty_enum,
+    clippy::empty_enums,
     clippy::mem_forget,
     clippy::unused_self,
     clippy::filter_map_next,
@@ -11,7 +11,6 @@
     clippy::match_wildcard_for_single_variants,
     clippy::if_let_mutex,
     clippy::await_holding_lock,
-    clippy::match_on_vec_items,
     clippy::imprecise_flops,
     clippy::suboptimal_flops,
     clippy::lossy_float_literal,
@@ -33,23 +32,44 @@
 
 pub use crate::str::Str;
 pub use service::{ProtocolVersion, TransportType};
-pub use socket::{DisconnectReason, Socket};
+pub use socket::{DisconnectReason, PacketPriority, Socket};
 
 #[doc(hidden)]
 #[cfg(feature = "__test_harness")]
 pub use packet::*;
+#[doc(hidden)]
+#[cfg(feature = "__test_harness")]
+pub use transport::polling::{decode_payload, encode_packets};
 
+#[cfg(feature = "client")]
+#[cfg_attr(docsrs, doc(cfg(feature = "client")))]
+pub mod client;
+pub mod client_ip;
 pub mod config;
+pub mod connection_limit;
+pub mod errors;
 pub mod handler;
+pub mod handoff;
+pub mod handshake;
 pub mod layer;
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub mod metrics;
+pub mod rate_limit;
+pub mod runtime;
 pub mod service;
+// Shared with socketioxide, not part of the public api
+#[doc(hidden)]
+pub mod sharded;
 pub mod sid;
 pub mod socket;
 
 mod body;
 mod engine;
-mod errors;
+#[cfg(feature = "otel")]
+mod otel;
 mod packet;
 mod peekable;
+mod slot;
 mod str;
 mod transport;
@@ -0,0 +1,82 @@
+//! Metrics exposed through the [`metrics`](https://docs.rs/metrics) crate facade.
+//!
+//! They are only recorded if a metrics recorder is installed by the application,
+//! e.g. with [`metrics-exporter-prometheus`](https://docs.rs/metrics-exporter-prometheus)
+//! to scrape them with Prometheus.
+//!
+//! | Name | Type | Labels | Description |
+//! |------|------|--------|-------------|
+//! | [`SESSIONS`] | gauge | `transport` | The number of open sessions |
+//! | [`PACKETS_RECEIVED`] | counter | `type` | The number of packets received from the clients |
+//! | [`PACKETS_SENT`] | counter | `type` | The number of packets sent to the clients |
+//! | [`PACKETS_DROPPED`] | counter | | The number of packets dropped because the socket buffer was full |
+//! | [`POLLING_PAYLOAD_SIZE`] | histogram | | The size in bytes of the payloads sent to polling requests |
+//! | [`HANDSHAKES_REJECTED`] | counter | `limit` | The number of handshakes rejected by a [connection limit](crate::connection_limit) |
+//!
+//! The `type` label is the lowercase packet type (`message`, `binary`, `ping`, `pong`...).
+//! The `limit` label is the exceeded limit (`global` or `ip`).
+
+use metrics::{counter, gauge, histogram};
+
+use crate::{packet::Packet, service::TransportType};
+
+/// The number of open sessions, labeled by transport.
+pub const SESSIONS: &str = "engineio_sessions";
+/// The number of packets received from the clients, labeled by packet type.
+pub const PACKETS_RECEIVED: &str = "engineio_packets_received_total";
+/// The number of packets sent to the clients, labeled by packet type.
+pub const PACKETS_SENT: &str = "engineio_packets_sent_total";
+/// The number of packets dropped because the socket buffer was full.
+pub const PACKETS_DROPPED: &str = "engineio_packets_dropped_total";
+/// The size in bytes of the payloads sent to polling requests.
+pub const POLLING_PAYLOAD_SIZE: &str = "engineio_polling_payload_bytes";
+/// The number of handshakes rejected by a connection limit, labeled by limit.
+pub const HANDSHAKES_REJECTED: &str = "engineio_handshakes_rejected_total";
+
+fn transport_label(transport: TransportType) -> &'static str {
+    match transport {
+        TransportType::Polling => "polling",
+        TransportType::Websocket => "websocket",
+        TransportType::WebTransport => "webtransport",
+    }
+}
+
+pub(crate) fn session_opened(transport: TransportType) {
+    gauge!(SESSIONS, "transport" => transport_label(transport)).increment(1);
+}
+
+pub(crate) fn session_closed(transport: TransportType) {
+    gauge!(SESSIONS, "transport" => transport_label(transport)).decrement(1);
+}
+
+pub(crate) fn session_upgraded(from: TransportType, to: TransportType) {
+    session_closed(from);
+    session_opened(to);
+}
+
+pub(crate) fn packet_received(packet: &Packet) {
+    counter!(PACKETS_RECEIVED, "type" => packet.kind()).increment(1);
+}
+
+/// A binary websocket frame is not decoded as a [`Packet`].
+pub(crate) fn binary_received() {
+    counter!(PACKETS_RECEIVED, "type" => "binary").increment(1);
+}
+
+pub(crate) fn packets_sent<'a>(packets: impl IntoIterator<Item = &'a Packet>) {
+    for packet in packets {
+        counter!(PACKETS_SENT, "type" => packet.kind()).increment(1);
+    }
+}
+
+pub(crate) fn packet_dropped() {
+    counter!(PACKETS_DROPPED).increment(1);
+}
+
+pub(crate) fn polling_payload(size: usize) {
+    histogram!(POLLING_PAYLOAD_SIZE).record(size as f64);
+}
+
+pub(crate) fn handshake_rejected(limit: &'static str) {
+    counter!(HANDSHAKES_REJECTED, "limit" => limit).increment(1);
+}
@@ -0,0 +1,51 @@
+//! OpenTelemetry context propagation for the session spans.
+//!
+//! The remote context is extracted from the headers of the request that opened the session
+//! with the globally configured [`TextMapPropagator`](opentelemetry::propagation::TextMapPropagator),
+//! e.g. the W3C `traceparent` header.
+use http::HeaderMap;
+use opentelemetry::propagation::Extractor;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// Set the remote context found in the request headers as the parent of the span.
+pub(crate) fn set_parent(span: &tracing::Span, headers: &HeaderMap) {
+    let cx = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    span.set_parent(cx);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"
+                .parse()
+                .unwrap(),
+        );
+        let extractor = HeaderExtractor(&headers);
+        assert_eq!(
+            extractor.get("traceparent"),
+            Some("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01")
+        );
+        assert_eq!(extractor.keys(), vec!["traceparent"]);
+        assert_eq!(extractor.get("tracestate"), None);
+    }
+}
@@ -1,5 +1,5 @@
 use base64::{engine::general_purpose, Engine};
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
 use serde::Serialize;
 
 use crate::config::EngineIoConfig;
@@ -58,6 +58,21 @@ impl Packet {
         matches!(self, Packet::Binary(_) | Packet::BinaryV3(_))
     }
 
+    /// The lowercase name of the packet type, used in traces and metrics
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Packet::Open(_) => "open",
+            Packet::Close => "close",
+            Packet::Ping | Packet::PingUpgrade => "ping",
+            Packet::Pong | Packet::PongUpgrade => "pong",
+            Packet::Message(_) => "message",
+            Packet::Upgrade => "upgrade",
+            Packet::Noop => "noop",
+            Packet::Binary(_) | Packet::BinaryV3(_) => "binary",
+        }
+    }
+
     /// If the packet is a message packet (text), it returns the message
     pub(crate) fn into_message(self) -> Str {
         match self {
@@ -82,7 +97,11 @@ impl Packet {
     /// The base64 max size factor is `ceil(n / 3) * 4`
     pub(crate) fn get_size_hint(&self, b64: bool) -> usize {
         match self {
-            Packet::Open(_) => 156, // max possible size for the open packet serialized
+            Packet::Open(open) => {
+                // max possible size for the open packet serialized without any upgrades
+                let upgrades_len: usize = open.upgrades.iter().map(|u| u.len() + 2).sum();
+                145 + upgrades_len + open.upgrades.len().saturating_sub(1)
+            }
             Packet::Close => 1,
             Packet::Ping => 1,
             Packet::Pong => 1,
@@ -109,6 +128,51 @@ impl Packet {
     }
 }
 
+impl Packet {
+    /// Serialize the packet at the end of the given buffer according to the Engine.IO protocol.
+    /// Binary packets are base64 encoded.
+    ///
+    /// It avoids allocating an intermediate [`String`] when encoding multiple packets in a single payload.
+    pub(crate) fn encode(self, buf: &mut BytesMut) {
+        buf.reserve(self.get_size_hint(true));
+        match self {
+            Packet::Open(open) => {
+                buf.put_u8(b'0');
+                serde_json::to_writer(buf.writer(), &open).unwrap();
+            }
+            Packet::Close => buf.put_u8(b'1'),
+            Packet::Ping => buf.put_u8(b'2'),
+            Packet::Pong => buf.put_u8(b'3'),
+            Packet::PingUpgrade => buf.put_slice(b"2probe"),
+            Packet::PongUpgrade => buf.put_slice(b"3probe"),
+            Packet::Message(msg) => {
+                buf.put_u8(b'4');
+                buf.put_slice(msg.as_bytes());
+            }
+            Packet::Upgrade => buf.put_u8(b'5'),
+            Packet::Noop => buf.put_u8(b'6'),
+            Packet::Binary(data) => {
+                buf.put_u8(b'b');
+                encode_base64(&data, buf);
+            }
+            Packet::BinaryV3(data) => {
+                buf.put_slice(b"b4");
+                encode_base64(&data, buf);
+            }
+        }
+    }
+}
+
+/// Encode base64 data at the end of the buffer without any intermediate allocation.
+fn encode_base64(data: &[u8], buf: &mut BytesMut) {
+    let start = buf.len();
+    let len = base64::encoded_len(data.len(), true).expect("base64 length overflow");
+    buf.resize(start + len, 0);
+    general_purpose::STANDARD
+        .encode_slice(data, &mut buf[start..])
+        .expect("the buffer is large enough");
+}
+
 /// Serialize a [Packet] to a [String] according to the Engine.IO protocol
 impl From<Packet> for String {
     fn from(packet: Packet) -> String {
@@ -142,9 +206,28 @@ impl From<Packet> for String {
         buffer
     }
 }
+/// Serialize a [Packet] to a websocket text payload.
+///
+/// Packets without payload are static and are never allocated.
+/// Other packets are encoded in a buffer that is directly used for the websocket frame.
 impl From<Packet> for tokio_tungstenite::tungstenite::Utf8Bytes {
     fn from(value: Packet) -> Self {
-        String::from(value).into()
+        use tokio_tungstenite::tungstenite::Utf8Bytes;
+        match value {
+            Packet::Close => Utf8Bytes::from_static("1"),
+            Packet::Ping => Utf8Bytes::from_static("2"),
+            Packet::Pong => Utf8Bytes::from_static("3"),
+            Packet::PingUpgrade => Utf8Bytes::from_static("2probe"),
+            Packet::PongUpgrade => Utf8Bytes::from_static("3probe"),
+            Packet::Upgrade => Utf8Bytes::from_static("5"),
+            Packet::Noop => Utf8Bytes::from_static("6"),
+            packet => {
+                let mut buf = BytesMut::new();
+                packet.encode(&mut buf);
+                // SAFETY: the engine.io text encoding is always valid utf8
+                unsafe { Utf8Bytes::from_bytes_unchecked(buf.freeze()) }
+            }
+        }
     }
 }
 /// Deserialize a [Packet] from a [String] according to the Engine.IO protocol
@@ -155,7 +238,7 @@ impl TryFrom<Str> for Packet {
             .as_bytes()
             .first()
             .ok_or(Error::InvalidPacketType(None))?;
-        let is_upgrade = value.len() == 6 && &value[1..6] == "probe";
+        let is_upgrade = value.as_bytes().get(1..) == Some(&b"probe"[..]);
         let res = match packet_type {
             b'1' => Packet::Close,
             b'2' if is_upgrade => Packet::PingUpgrade,
@@ -165,11 +248,6 @@ impl TryFrom<Str> for Packet {
             b'4' => Packet::Message(value.slice(1..)),
             b'5' => Packet::Upgrade,
             b'6' => Packet::Noop,
-            b'b' if value.as_bytes().get(1) == Some(&b'4') => Packet::BinaryV3(
-                general_purpose::STANDARD
-                    .decode(value.slice(2..).as_bytes())?
-                    .into(),
-            ),
             b'b' => Packet::Binary(
                 general_purpose::STANDARD
                     .decode(value.slice(1..).as_bytes())?
@@ -180,6 +258,24 @@ impl TryFrom<Str> for Packet {
         Ok(res)
     }
 }
+impl Packet {
+    /// Deserialize a string packet of the engine.io v3 protocol.
+    ///
+    /// Unlike v4, a v3 binary packet encoded in base64 is prefixed with its message type: `b4<base64>`.
+    /// The protocol of the session must be known to parse it, because a v4 base64 payload can also start with a `4`.
+    #[cfg(feature = "v3")]
+    pub(crate) fn try_from_v3(value: Str) -> Result<Self, Error> {
+        match value.as_bytes() {
+            [b'b', b'4', ..] => Ok(Packet::BinaryV3(
+                general_purpose::STANDARD
+                    .decode(value.slice(2..).as_bytes())?
+                    .into(),
+            )),
+            _ => Packet::try_from(value),
+        }
+    }
+}
+
 impl TryFrom<tokio_tungstenite::tungstenite::Utf8Bytes> for Packet {
     type Error = Error;
     fn try_from(value: tokio_tungstenite::tungstenite::Utf8Bytes) -> Result<Self, Self::Error> {
@@ -209,17 +305,26 @@ pub struct OpenPacket {
 impl OpenPacket {
     /// Create a new [OpenPacket]
     /// If the current transport is polling, the server will always allow the client to upgrade to websocket
+    /// and to webtransport if it is enabled
     pub fn new(transport: TransportType, sid: Sid, config: &EngineIoConfig) -> Self {
-        let upgrades = if transport == TransportType::Polling {
+        #[allow(unused_mut)]
+        let mut upgrades = if transport == TransportType::Polling {
             vec!["websocket".to_string()]
         } else {
             vec![]
         };
+        #[cfg(feature = "webtransport")]
+        if transport == TransportType::Polling
+            && config.allowed_transport(TransportType::WebTransport)
+        {
+            upgrades.push("webtransport".to_string());
+        }
+        let heartbeat = config.heartbeat(transport);
         OpenPacket {
             sid,
             upgrades,
-            ping_interval: config.ping_interval.as_millis() as u64,
-            ping_timeout: config.ping_timeout.as_millis() as u64,
+            ping_interval: heartbeat.advertised_interval().as_millis() as u64,
+            ping_timeout: heartbeat.timeout.as_millis() as u64,
             max_payload: config.max_payload,
         }
     }
@@ -251,6 +356,52 @@ mod tests {
         assert_eq!(packet_str, "4hello");
     }
 
+    #[test]
+    fn test_encode_packet() {
+        let sid = Sid::new();
+        let packets = [
+            Packet::Open(OpenPacket::new(
+                TransportType::Polling,
+                sid,
+                &EngineIoConfig::default(),
+            )),
+            Packet::Close,
+            Packet::Ping,
+            Packet::PongUpgrade,
+            Packet::Message("hello€".into()),
+            Packet::Noop,
+            Packet::Binary(Bytes::from_static(&[1, 2, 3, 4])),
+            Packet::BinaryV3(Bytes::from_static(&[1, 2, 3, 4])),
+        ];
+        let mut buf = BytesMut::new();
+        for packet in packets {
+            let start = buf.len();
+            let packet_str: String = packet.clone().into();
+            packet.encode(&mut buf);
+            assert_eq!(&buf[start..], packet_str.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_ws_text_payload() {
+        use tokio_tungstenite::tungstenite::Utf8Bytes;
+        let packets = [
+            Packet::Close,
+            Packet::Ping,
+            Packet::Pong,
+            Packet::PingUpgrade,
+            Packet::PongUpgrade,
+            Packet::Message("hello€".into()),
+            Packet::Upgrade,
+            Packet::Noop,
+            Packet::Binary(Bytes::from_static(&[1, 2, 3, 4])),
+        ];
+        for packet in packets {
+            let packet_str: String = packet.clone().into();
+            assert_eq!(Utf8Bytes::from(packet).as_str(), packet_str);
+        }
+    }
+
     #[test]
     fn test_message_packet_deserialize() {
         let packet_str = "4hello".to_string();
@@ -258,6 +409,14 @@ mod tests {
         assert_eq!(packet, Packet::Message("hello".into()));
     }
 
+    #[test]
+    fn test_packet_deserialize_multibyte() {
+        let err = Packet::try_from("é1234".to_string()).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketType(_)));
+        let packet: Packet = "4€abc".to_string().try_into().unwrap();
+        assert_eq!(packet, Packet::Message("€abc".into()));
+    }
+
     #[test]
     fn test_binary_packet() {
         let packet = Packet::Binary(vec![1, 2, 3].into());
@@ -280,10 +439,22 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "v3")]
     fn test_binary_packet_v3_deserialize() {
-        let packet_str = "b4AQID".to_string();
-        let packet: Packet = packet_str.try_into().unwrap();
+        let packet = Packet::try_from_v3("b4AQID".into()).unwrap();
         assert_eq!(packet, Packet::BinaryV3(vec![1, 2, 3].into()));
+
+        let packet = Packet::try_from_v3("4hello".into()).unwrap();
+        assert_eq!(packet, Packet::Message("hello".into()));
+    }
+
+    #[test]
+    fn test_binary_packet_deserialize_leading_4() {
+        // A v4 binary packet with base64 data starting with a '4', padded or not
+        let packet: Packet = "b4BAI".to_string().try_into().unwrap();
+        assert_eq!(packet, Packet::Binary(vec![224, 16, 8].into()));
+        let packet: Packet = "b4BAIBAI=".to_string().try_into().unwrap();
+        assert_eq!(packet, Packet::Binary(vec![224, 16, 8, 4, 2].into()));
     }
 
     #[test]
@@ -305,6 +476,24 @@ mod tests {
         let packet = Packet::Open(open);
         assert_eq!(packet.get_size_hint(false), size);
 
+        #[cfg(feature = "webtransport")]
+        {
+            let open = OpenPacket::new(
+                TransportType::Polling,
+                Sid::new(),
+                &EngineIoConfig {
+                    max_buffer_size: usize::MAX,
+                    max_payload: u64::MAX,
+                    ping_interval: Duration::MAX,
+                    ping_timeout: Duration::MAX,
+                    transports: TransportType::Polling as u8 | TransportType::WebTransport as u8,
+                    ..Default::default()
+                },
+            );
+            let size = serde_json::to_string(&open).unwrap().len();
+            assert_eq!(Packet::Open(open).get_size_hint(false), size);
+        }
+
         let packet = Packet::Close;
         assert_eq!(packet.get_size_hint(false), 1);
 
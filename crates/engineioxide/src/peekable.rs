@@ -1,42 +1,167 @@
-use tokio::sync::mpsc::{error::TryRecvError, Receiver};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use tokio::sync::{
+    futures::Notified,
+    mpsc::{error::TryRecvError, Receiver},
+    Notify,
+};
+
+/// Hook called for each item consumed from a [`PeekableReceiver`]
+type RecvHook<T> = Box<dyn Fn(&T) + Send + Sync>;
+
+/// The drain state of a [`PeekableReceiver`], shared with the senders
+/// to wait for all the sent items to be consumed.
+#[derive(Debug, Default)]
+pub struct Drain {
+    /// Notified each time the receiver is emptied.
+    notify: Notify,
+    /// Set while an item is peeked but not consumed yet.
+    peeked: AtomicBool,
+}
+impl Drain {
+    /// Returns true if an item is held by the receiver without being consumed.
+    pub fn is_peeked(&self) -> bool {
+        self.peeked.load(Ordering::Acquire)
+    }
+    /// Wait for the receiver to be emptied.
+    pub fn notified(&self) -> Notified<'_> {
+        self.notify.notified()
+    }
+}
 
 /// Peekable receiver for polling transport
-/// It is a thin wrapper around a [`Receiver`](tokio::sync::mpsc::Receiver) that allows to peek the next packet without consuming it
+/// It is a thin wrapper around one or more [`Receiver`](tokio::sync::mpsc::Receiver)s that allows to peek the next packet without consuming it
 ///
 /// Its main goal is to be able to peek the next packet without consuming it to calculate the
 /// packet length when using polling transport to check if it fits according to the max_payload setting
-#[derive(Debug)]
+///
+/// With multiple receivers, the items are consumed from the first non empty receiver,
+/// so they are ordered from the highest to the lowest priority.
+///
+/// An optional hook can be set to be notified of each item consumed (but not peeked) from the receiver,
+/// and an optional [`Drain`] to be notified when the receiver is emptied.
 pub struct PeekableReceiver<T> {
-    rx: Receiver<T>,
+    rxs: Vec<Receiver<T>>,
     next: Option<T>,
+    on_recv: Option<RecvHook<T>>,
+    drain: Option<Arc<Drain>>,
 }
 impl<T> PeekableReceiver<T> {
+    #[cfg(any(test, feature = "__test_harness"))]
     pub fn new(rx: Receiver<T>) -> Self {
-        Self { rx, next: None }
+        Self::prioritized(vec![rx])
+    }
+    /// Create a receiver consuming the items of the given receivers,
+    /// ordered from the highest to the lowest priority.
+    pub fn prioritized(rxs: Vec<Receiver<T>>) -> Self {
+        Self {
+            rxs,
+            next: None,
+            on_recv: None,
+            drain: None,
+        }
+    }
+    /// Call `on_recv` for each item consumed from the receiver.
+    pub fn with_hook(mut self, on_recv: impl Fn(&T) + Send + Sync + 'static) -> Self {
+        self.on_recv = Some(Box::new(on_recv));
+        self
+    }
+    /// Update the given [`Drain`] state each time an item is peeked or consumed.
+    pub fn with_drain(mut self, drain: Arc<Drain>) -> Self {
+        self.drain = Some(drain);
+        self
     }
     pub fn peek(&mut self) -> Option<&T> {
         if self.next.is_none() {
-            self.next = self.rx.try_recv().ok();
+            self.next = self.try_next().ok();
+            if let (Some(drain), Some(_)) = (&self.drain, &self.next) {
+                drain.peeked.store(true, Ordering::Release);
+            }
         }
         self.next.as_ref()
     }
     pub async fn recv(&mut self) -> Option<T> {
-        if self.next.is_none() {
-            self.rx.recv().await
+        let item = if self.next.is_none() {
+            std::future::poll_fn(|cx| self.poll_next(cx)).await
         } else {
             self.next.take()
+        };
+        if let Some(item) = &item {
+            self.consumed(item);
         }
+        item
     }
     pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
-        if self.next.is_none() {
-            self.rx.try_recv()
+        let item = if self.next.is_none() {
+            self.try_next()?
         } else {
-            Ok(self.next.take().unwrap())
-        }
+            self.next.take().unwrap()
+        };
+        self.consumed(&item);
+        Ok(item)
     }
 
     pub fn close(&mut self) {
-        self.rx.close()
+        self.rxs.iter_mut().for_each(Receiver::close);
+    }
+
+    /// Call the hook and notify the drain waiters if nothing is left to consume.
+    fn consumed(&self, item: &T) {
+        if let Some(on_recv) = &self.on_recv {
+            on_recv(item);
+        }
+        if let Some(drain) = &self.drain {
+            drain.peeked.store(false, Ordering::Release);
+            if self.rxs.iter().all(Receiver::is_empty) {
+                drain.notify.notify_waiters();
+            }
+        }
+    }
+
+    /// Take the next item of the first non empty receiver.
+    /// The receiver is disconnected once all the receivers are disconnected.
+    fn try_next(&mut self) -> Result<T, TryRecvError> {
+        let mut err = TryRecvError::Disconnected;
+        for rx in &mut self.rxs {
+            match rx.try_recv() {
+                Ok(item) => return Ok(item),
+                Err(TryRecvError::Empty) => err = TryRecvError::Empty,
+                Err(TryRecvError::Disconnected) => (),
+            }
+        }
+        Err(err)
+    }
+
+    /// Poll the receivers in order, registering the waker on each of them.
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut closed = true;
+        for rx in &mut self.rxs {
+            match rx.poll_recv(cx) {
+                Poll::Ready(Some(item)) => return Poll::Ready(Some(item)),
+                Poll::Ready(None) => (),
+                Poll::Pending => closed = false,
+            }
+        }
+        if closed {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for PeekableReceiver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PeekableReceiver")
+            .field("rxs", &self.rxs)
+            .field("next", &self.next)
+            .finish()
     }
 }
 
@@ -76,4 +201,60 @@ mod tests {
         assert_eq!(rx.recv().await, Some(Packet::Close));
         assert!(rx.peek().is_none());
     }
+
+    #[tokio::test]
+    async fn hook() {
+        use super::PeekableReceiver;
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+        use tokio::sync::mpsc::channel;
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = channel(2);
+        let count_clone = count.clone();
+        let mut rx = PeekableReceiver::new(rx).with_hook(move |_: &u8| {
+            count_clone.fetch_add(1, Ordering::Relaxed);
+        });
+
+        tx.send(1).await.unwrap();
+        tx.send(2).await.unwrap();
+        assert_eq!(rx.peek(), Some(&1));
+        assert_eq!(count.load(Ordering::Relaxed), 0);
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn prioritized() {
+        use super::PeekableReceiver;
+        use tokio::sync::mpsc::channel;
+
+        let (high_tx, high_rx) = channel(2);
+        let (low_tx, low_rx) = channel(2);
+        let mut rx = PeekableReceiver::prioritized(vec![high_rx, low_rx]);
+
+        low_tx.send(3).await.unwrap();
+        high_tx.send(1).await.unwrap();
+        high_tx.send(2).await.unwrap();
+        assert_eq!(rx.peek(), Some(&1));
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.recv().await, Some(3));
+
+        // A pending recv is woken by any of the receivers
+        let handle = tokio::spawn(async move { (rx.recv().await, rx) });
+        tokio::task::yield_now().await;
+        low_tx.send(4).await.unwrap();
+        let (item, mut rx) = handle.await.unwrap();
+        assert_eq!(item, Some(4));
+
+        drop(low_tx);
+        assert!(rx.try_recv().is_err());
+        drop(high_tx);
+        assert_eq!(rx.recv().await, None);
+    }
 }
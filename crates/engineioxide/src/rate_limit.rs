@@ -0,0 +1,152 @@
+//! Rate limiting of the packets received from the clients with a token bucket.
+//!
+//! A [`RateLimit`] can be set per session with
+//! [`EngineIoConfigBuilder::max_packets_per_second`](crate::config::EngineIoConfigBuilder::max_packets_per_second).
+//! Only message and binary packets are limited, heartbeat packets are always accepted.
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{errors::Error, runtime::Runtime};
+
+/// What to do with a packet received while the rate limit is exceeded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitPolicy {
+    /// The packet is dropped.
+    #[default]
+    Drop,
+    /// The packet is delayed until the rate limit allows it.
+    /// The next packets of the connection are not read in the meantime, so they stay ordered.
+    Queue,
+    /// The connection is closed with a
+    /// [`RateLimitExceeded`](crate::DisconnectReason::RateLimitExceeded) reason.
+    Disconnect,
+}
+
+/// A limit of packets per second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The number of packets allowed per second. It is also the maximum burst size.
+    pub per_second: u32,
+    /// What to do when the limit is exceeded.
+    pub policy: RateLimitPolicy,
+}
+
+impl RateLimit {
+    /// Create a new rate limit with the given number of packets per second and policy.
+    ///
+    /// # Panics
+    /// If `per_second` is 0.
+    pub fn new(per_second: u32, policy: RateLimitPolicy) -> Self {
+        assert!(per_second > 0, "rate limit must be greater than 0");
+        Self { per_second, policy }
+    }
+}
+
+/// A token bucket refilled at a constant rate. The bucket starts full.
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a bucket of `per_second` tokens refilled at `per_second` tokens per second.
+    pub fn new(per_second: u32) -> Self {
+        let capacity = per_second as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Take a token from the bucket.
+    /// If the bucket is empty, it returns the duration to wait before the next token is available.
+    pub fn try_acquire(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = elapsed
+            .mul_add(self.capacity, self.tokens)
+            .min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.capacity))
+        }
+    }
+}
+
+/// The rate limiter of a session.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    bucket: Mutex<TokenBucket>,
+    policy: RateLimitPolicy,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            bucket: Mutex::new(TokenBucket::new(limit.per_second)),
+            policy: limit.policy,
+        }
+    }
+
+    /// Acquire a permit to handle a packet.
+    ///
+    /// Returns `Ok(false)` if the packet should be dropped and an error if the connection should be closed.
+    pub async fn acquire(&self, runtime: &dyn Runtime) -> Result<bool, Error> {
+        loop {
+            let res = self.bucket.lock().unwrap().try_acquire();
+            match (res, self.policy) {
+                (Ok(()), _) => return Ok(true),
+                (Err(_), RateLimitPolicy::Drop) => return Ok(false),
+                (Err(_), RateLimitPolicy::Disconnect) => return Err(Error::RateLimitExceeded),
+                (Err(wait), RateLimitPolicy::Queue) => runtime.sleep(wait).await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::TokioRuntime;
+
+    #[test]
+    fn token_bucket_burst() {
+        let mut bucket = TokenBucket::new(3);
+        for _ in 0..3 {
+            assert!(bucket.try_acquire().is_ok());
+        }
+        let wait = bucket.try_acquire().unwrap_err();
+        assert!(wait <= Duration::from_millis(334));
+    }
+
+    #[tokio::test]
+    async fn rate_limiter_policies() {
+        let drop = RateLimiter::new(RateLimit::new(1, RateLimitPolicy::Drop));
+        assert!(drop.acquire(&TokioRuntime).await.unwrap());
+        assert!(!drop.acquire(&TokioRuntime).await.unwrap());
+
+        let disconnect = RateLimiter::new(RateLimit::new(1, RateLimitPolicy::Disconnect));
+        assert!(disconnect.acquire(&TokioRuntime).await.unwrap());
+        assert!(matches!(
+            disconnect.acquire(&TokioRuntime).await,
+            Err(Error::RateLimitExceeded)
+        ));
+
+        let queue = RateLimiter::new(RateLimit::new(50, RateLimitPolicy::Queue));
+        for _ in 0..50 {
+            assert!(queue.acquire(&TokioRuntime).await.unwrap());
+        }
+        let start = Instant::now();
+        assert!(queue.acquire(&TokioRuntime).await.unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+}
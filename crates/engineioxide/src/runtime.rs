@@ -0,0 +1,201 @@
+//! Abstraction over the async runtime used to spawn the background tasks and timers of the sessions.
+//!
+//! Engine.IO spawns a few tasks per session (heartbeat, websocket writer) and waits on timers
+//! (heartbeat and polling timeouts). They go through the [`Runtime`] of the
+//! [`EngineIoConfig`](crate::config::EngineIoConfig), set with
+//! [`EngineIoConfigBuilder::runtime`](crate::config::EngineIoConfigBuilder::runtime).
+//! It defaults to [`TokioRuntime`].
+//!
+//! The channels used by the sessions are runtime agnostic, so a custom [`Runtime`] is enough
+//! to run the sessions on another executor such as `async-std` or `smol`.
+//!
+//! With the `http-compression` feature, large polling payloads are compressed with
+//! [`Runtime::spawn_blocking`]. By default it runs on the current thread, it can be overridden to
+//! use the blocking thread pool of the executor, like [`TokioRuntime`] does.
+//!
+//! # Example
+//! ```
+//! # use engineioxide::{config::EngineIoConfig, runtime::Runtime};
+//! # use futures_core::future::BoxFuture;
+//! # use std::time::Duration;
+//! // A runtime spawning the tasks on a dedicated tokio runtime,
+//! // which could instead be `smol::spawn(fut).detach()` and `smol::Timer::after(duration)`
+//! #[derive(Debug)]
+//! struct MyRuntime(tokio::runtime::Handle);
+//!
+//! impl Runtime for MyRuntime {
+//!     fn spawn(&self, fut: BoxFuture<'static, ()>) {
+//!         self.0.spawn(fut);
+//!     }
+//!     fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+//!         Box::pin(tokio::time::sleep(duration))
+//!     }
+//! }
+//! # let rt = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+//! let config = EngineIoConfig::builder()
+//!     .runtime(MyRuntime(rt.handle().clone()))
+//!     .build();
+//! ```
+use std::{fmt, future::Future, time::Duration};
+
+use futures_core::future::BoxFuture;
+use futures_util::{
+    future::{self, Either, RemoteHandle},
+    FutureExt,
+};
+
+/// An async runtime able to spawn tasks and to sleep, see the [module level documentation](self).
+pub trait Runtime: fmt::Debug + Send + Sync + 'static {
+    /// Spawn a detached task.
+    fn spawn(&self, fut: BoxFuture<'static, ()>);
+
+    /// Create a future completing after the given duration.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    /// Run a blocking function without blocking the other tasks.
+    ///
+    /// The default implementation runs it on the current thread.
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+        f()
+    }
+}
+
+/// The default [`Runtime`], based on `tokio`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+impl Runtime for TokioRuntime {
+    fn spawn(&self, fut: BoxFuture<'static, ()>) {
+        tokio::spawn(fut);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+        tokio::task::spawn_blocking(f);
+    }
+}
+
+/// The error returned by [`timeout`] when the duration elapsed.
+#[derive(Debug)]
+pub(crate) struct Elapsed;
+
+impl dyn Runtime {
+    /// Spawn a task and get a handle to its output.
+    /// The task is aborted when the handle is dropped.
+    pub(crate) fn spawn_with_handle<F>(&self, fut: F) -> RemoteHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        let (fut, handle) = fut.remote_handle();
+        self.spawn(Box::pin(fut));
+        handle
+    }
+
+    /// Run a blocking function with [`Runtime::spawn_blocking`] and get its output.
+    /// Returns `None` if the function panicked.
+    #[cfg(feature = "http-compression")]
+    pub(crate) async fn run_blocking<F, T>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.spawn_blocking(Box::new(move || {
+            tx.send(f()).ok();
+        }));
+        rx.await.ok()
+    }
+
+    /// Wait for a future to complete, at most for the given duration.
+    pub(crate) async fn timeout<F: Future>(
+        &self,
+        duration: Duration,
+        fut: F,
+    ) -> Result<F::Output, Elapsed> {
+        let fut = std::pin::pin!(fut);
+        match future::select(fut, self.sleep(duration)).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right(_) => Err(Elapsed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct CountingRuntime(AtomicUsize);
+
+    impl Runtime for CountingRuntime {
+        fn spawn(&self, fut: BoxFuture<'static, ()>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            TokioRuntime.spawn(fut);
+        }
+        fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+            TokioRuntime.sleep(duration)
+        }
+    }
+
+    #[tokio::test]
+    async fn spawn_with_handle() {
+        let counting = CountingRuntime::default();
+        let runtime: &dyn Runtime = &counting;
+        let handle = runtime.spawn_with_handle(async { 42 });
+        assert_eq!(handle.await, 42);
+
+        // Dropping the handle aborts the task
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<()>(1);
+        let handle = runtime.spawn_with_handle(async move {
+            std::future::pending::<()>().await;
+            drop(tx);
+        });
+        drop(handle);
+        assert_eq!(rx.recv().await, None);
+        assert_eq!(counting.0.load(Ordering::Relaxed), 2);
+    }
+
+    #[cfg(feature = "http-compression")]
+    #[tokio::test]
+    async fn run_blocking() {
+        #[derive(Debug)]
+        struct InlineRuntime;
+        impl Runtime for InlineRuntime {
+            fn spawn(&self, fut: BoxFuture<'static, ()>) {
+                TokioRuntime.spawn(fut);
+            }
+            fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+                TokioRuntime.sleep(duration)
+            }
+        }
+
+        let runtime: &dyn Runtime = &TokioRuntime;
+        assert_eq!(runtime.run_blocking(|| 42).await, Some(42));
+        let res = runtime.run_blocking(|| panic!("blocking task")).await;
+        assert_eq!(res, None::<()>);
+
+        // The default implementation doesn't need a tokio runtime
+        let runtime: &dyn Runtime = &InlineRuntime;
+        let res = futures_util::FutureExt::now_or_never(runtime.run_blocking(|| 42));
+        assert_eq!(res, Some(Some(42)));
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let runtime: &dyn Runtime = &TokioRuntime;
+        let res = runtime
+            .timeout(Duration::from_millis(50), async { 1 })
+            .await;
+        assert_eq!(res.unwrap(), 1);
+        let res = runtime
+            .timeout(Duration::from_millis(5), std::future::pending::<()>())
+            .await;
+        assert!(res.is_err());
+    }
+}
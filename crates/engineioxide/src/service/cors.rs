@@ -0,0 +1,131 @@
+//! CORS handling for the engine.io endpoint.
+//! It is only used if a [`CorsConfig`] is set in the [`EngineIoConfig`](crate::config::EngineIoConfig).
+
+use http::{header::*, HeaderMap, HeaderValue, Method, Request, Response, StatusCode};
+
+use crate::{
+    body::ResponseBody,
+    config::{AllowedOrigins, CorsConfig},
+};
+
+/// Get the CORS headers to add to the response of a request.
+/// Returns `None` if the request is not a cross-origin request or if its origin is not allowed.
+pub fn headers(config: &CorsConfig, req_headers: &HeaderMap) -> Option<HeaderMap> {
+    let origin = req_headers.get(ORIGIN)?;
+    let allowed = match &config.allowed_origins {
+        AllowedOrigins::Any => true,
+        AllowedOrigins::List(origins) => origins.iter().any(|o| o.as_bytes() == origin.as_bytes()),
+    };
+    if !allowed {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?origin, "cors origin not allowed");
+        return None;
+    }
+
+    let mut headers = HeaderMap::new();
+    match config.allowed_origins {
+        // A wildcard can't be used with credentials
+        AllowedOrigins::Any if !config.credentials => {
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+        }
+        _ => {
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+            headers.insert(VARY, HeaderValue::from_static("Origin"));
+        }
+    }
+    if config.credentials {
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+    Some(headers)
+}
+
+/// Check if the request is a CORS preflight request.
+pub fn is_preflight<R>(req: &Request<R>) -> bool {
+    req.method() == Method::OPTIONS
+        && req.headers().contains_key(ORIGIN)
+        && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+/// Answer a CORS preflight request.
+pub fn preflight_response<B>(
+    config: &CorsConfig,
+    req_headers: &HeaderMap,
+) -> Result<Response<ResponseBody<B>>, http::Error> {
+    let mut res = Response::builder().status(StatusCode::NO_CONTENT);
+    if let Some(headers) = headers(config, req_headers) {
+        res.headers_mut().unwrap().extend(headers);
+        res = res.header(
+            ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static("GET, POST"),
+        );
+        if let Some(req_headers) = req_headers.get(ACCESS_CONTROL_REQUEST_HEADERS) {
+            res = res.header(ACCESS_CONTROL_ALLOW_HEADERS, req_headers);
+        }
+        if let Some(max_age) = config.max_age {
+            res = res.header(ACCESS_CONTROL_MAX_AGE, max_age.as_secs());
+        }
+    }
+    res.body(ResponseBody::empty_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn req_headers(origin: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ORIGIN, origin.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn cors_headers_any() {
+        let config = CorsConfig::default();
+        let headers = headers(&config, &req_headers("https://example.com")).unwrap();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!headers.contains_key(ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        assert!(super::headers(&config, &HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn cors_headers_credentials() {
+        let config = CorsConfig {
+            allowed_origins: AllowedOrigins::List(vec!["https://example.com".into()]),
+            credentials: true,
+            ..Default::default()
+        };
+        let headers = headers(&config, &req_headers("https://example.com")).unwrap();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[VARY], "Origin");
+
+        assert!(super::headers(&config, &req_headers("https://other.com")).is_none());
+    }
+
+    #[test]
+    fn cors_preflight() {
+        let config = CorsConfig {
+            max_age: Some(Duration::from_secs(600)),
+            ..Default::default()
+        };
+        let mut headers = req_headers("https://example.com");
+        headers.insert(ACCESS_CONTROL_REQUEST_METHOD, "POST".parse().unwrap());
+        headers.insert(
+            ACCESS_CONTROL_REQUEST_HEADERS,
+            "content-type".parse().unwrap(),
+        );
+        let res = preflight_response::<()>(&config, &headers).unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let headers = res.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "GET, POST");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+        assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "600");
+    }
+}
@@ -1,7 +1,7 @@
 use crate::body::ResponseBody;
 use crate::errors::Error;
 use futures_core::ready;
-use http::Response;
+use http::{HeaderMap, Response};
 use pin_project_lite::pin_project;
 use std::future::Future;
 use std::pin::Pin;
@@ -44,6 +44,34 @@ impl<F, B> ResponseFuture<F, B> {
     }
 }
 
+impl<F, B: Send + 'static> ResponseFuture<F, B> {
+    /// Add headers to the engine.io response. Responses from the inner service are not modified.
+    pub fn with_headers(self, headers: HeaderMap) -> Self {
+        let extend = move |res: Result<Response<ResponseBody<B>>, Error>| {
+            let mut res = res.unwrap_or_else(|e| e.into());
+            // The headers are appended to keep the `Vary` header of the response
+            for (name, value) in &headers {
+                res.headers_mut().append(name, value.clone());
+            }
+            Ok(res)
+        };
+        match self {
+            ResponseFuture::EmptyResponse { code } => {
+                let res = Response::builder()
+                    .status(code)
+                    .body(ResponseBody::empty_response())
+                    .map_err(Error::Http);
+                ResponseFuture::ready(extend(res))
+            }
+            ResponseFuture::ReadyResponse { res: Some(res) } => ResponseFuture::ready(extend(res)),
+            ResponseFuture::AsyncResponse { future } => {
+                ResponseFuture::async_response(Box::pin(async move { extend(future.await) }))
+            }
+            res => res,
+        }
+    }
+}
+
 impl<ResBody, F, E> Future for ResponseFuture<F, ResBody>
 where
     F: Future<Output = Result<Response<ResBody>, E>>,
@@ -28,7 +28,7 @@
 
 use std::{
     convert::Infallible,
-    sync::Arc,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 
@@ -38,15 +38,19 @@ use http::{Request, Response};
 use http_body::Body;
 use http_body_util::Empty;
 use hyper::service::Service as HyperSvc;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tower_service::Service as TowerSvc;
 
 use crate::{
     body::ResponseBody, config::EngineIoConfig, engine::EngineIo, handler::EngineIoHandler,
 };
 
+mod cors;
 mod futures;
 mod parser;
 
+#[cfg(feature = "v3")]
+pub(crate) use self::parser::is_b64;
 pub use self::parser::{ProtocolVersion, TransportType};
 use self::{futures::ResponseFuture, parser::dispatch_req};
 
@@ -91,6 +95,48 @@ impl<S: Clone, H: EngineIoHandler> EngineIoService<H, S> {
     pub fn into_make_service(self) -> MakeEngineIoService<H, S> {
         MakeEngineIoService::new(self)
     }
+
+    /// Get the [`EngineIoConfig`] of the service.
+    pub fn config(&self) -> &EngineIoConfig {
+        &self.engine.config
+    }
+}
+
+#[cfg(feature = "webtransport")]
+impl<H: EngineIoHandler, S> EngineIoService<H, S> {
+    /// Handle an engine.io session over the bidirectional stream of an accepted WebTransport session.
+    ///
+    /// engineioxide doesn't embed any HTTP/3 server, so you have to accept the WebTransport session
+    /// with your own stack (e.g. `wtransport` or `h3-webtransport`), accept the first bidirectional stream
+    /// opened by the client and give it to this fn, with the http [`Parts`](http::request::Parts) of the
+    /// session request. If the send and recv streams are separated, you can use [`tokio::io::join`] to merge them.
+    ///
+    /// The [`TransportType::WebTransport`] transport should be enabled in the [`EngineIoConfig`].
+    /// The returned future will complete when the session is closed.
+    #[cfg_attr(docsrs, doc(cfg(feature = "webtransport")))]
+    pub fn on_webtransport_session<T>(
+        &self,
+        req_data: http::request::Parts,
+        stream: T,
+    ) -> impl std::future::Future<Output = ()> + Send + 'static
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static,
+    {
+        let engine = self.engine.clone();
+        async move {
+            let res = crate::transport::webtransport::on_session(engine, req_data, stream).await;
+            match res {
+                Ok(_) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("webtransport session closed")
+                }
+                Err(_e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("webtransport session closed with error: {:?}", _e)
+                }
+            }
+        }
+    }
 }
 
 impl<S: Clone, H: EngineIoHandler> Clone for EngineIoService<H, S> {
@@ -126,8 +172,7 @@ where
     }
 
     fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
-        let path = self.engine.config.req_path.as_ref();
-        if req.uri().path().starts_with(path) {
+        if self.engine.config.req_path.matches(req.uri().path()) {
             dispatch_req(req, self.engine.clone())
         } else {
             ResponseFuture::new(self.inner.call(req))
@@ -150,8 +195,7 @@ where
     type Future = ResponseFuture<S::Future, ResBody>;
 
     fn call(&self, req: Request<ReqBody>) -> Self::Future {
-        let path = self.engine.config.req_path.as_ref();
-        if req.uri().path().starts_with(path) {
+        if self.engine.config.req_path.matches(req.uri().path()) {
             dispatch_req(req, self.engine.clone())
         } else {
             ResponseFuture::new(self.inner.call(req))
@@ -245,3 +289,38 @@ impl<ReqBody> HyperSvc<Request<ReqBody>> for NotFoundService {
             .unwrap()))
     }
 }
+
+/// A connection upgraded by an http framework that doesn't rely on [`hyper`] upgrades.
+///
+/// By default a websocket session is handled over the connection returned by
+/// [`hyper::upgrade::on`]. If this extension is set in the request, the session is handled over the
+/// given connection instead. It should only carry the bytes exchanged after the
+/// `101 Switching Protocols` response returned by the service.
+///
+/// This is used to integrate engine.io with frameworks that are not based on hyper v1.
+#[derive(Clone)]
+pub struct UpgradedConn(Arc<Mutex<Option<Box<dyn UpgradedIo>>>>);
+
+impl UpgradedConn {
+    /// Create a new [`UpgradedConn`] from a raw connection.
+    pub fn new<T>(conn: T) -> Self
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        Self(Arc::new(Mutex::new(Some(Box::new(conn)))))
+    }
+
+    /// Take the connection. It can only be taken once.
+    pub(crate) fn take(&self) -> Option<Box<dyn UpgradedIo>> {
+        self.0.lock().unwrap().take()
+    }
+}
+impl std::fmt::Debug for UpgradedConn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpgradedConn").finish()
+    }
+}
+
+/// A raw bidirectional connection.
+pub(crate) trait UpgradedIo: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> UpgradedIo for T {}
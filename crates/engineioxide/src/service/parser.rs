@@ -1,16 +1,22 @@
 //! A Parser module to parse any `EngineIo` query
 
-use std::{str::FromStr, sync::Arc};
+use std::{
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
 
 use futures_core::Future;
+use futures_util::task::noop_waker_ref;
 use http::{Method, Request, Response};
 
 use crate::{
     body::ResponseBody,
-    config::EngineIoConfig,
+    config::{rejected, EngineIoConfig},
     engine::EngineIo,
+    errors::Error,
     handler::EngineIoHandler,
-    service::futures::ResponseFuture,
+    service::{cors, futures::ResponseFuture},
     sid::Sid,
     transport::{polling, ws},
 };
@@ -20,6 +26,36 @@ pub fn dispatch_req<F, H, ReqBody, ResBody>(
     req: Request<ReqBody>,
     engine: Arc<EngineIo<H>>,
 ) -> ResponseFuture<F, ResBody>
+where
+    ReqBody: http_body::Body + Send + Unpin + 'static,
+    ReqBody::Data: Send,
+    ReqBody::Error: std::fmt::Debug,
+    ResBody: Send + 'static,
+    H: EngineIoHandler,
+    F: Future,
+{
+    let cors = match &engine.config.cors {
+        Some(config) if cors::is_preflight(&req) => {
+            return ResponseFuture::ready(
+                cors::preflight_response(config, req.headers()).map_err(Into::into),
+            );
+        }
+        Some(config) => cors::headers(config, req.headers()),
+        None => None,
+    };
+    let res = dispatch(req, engine);
+    match cors {
+        Some(headers) => res.with_headers(headers),
+        None => res,
+    }
+}
+
+// The `..` patterns skip the v3 fields, which are not declared without the v3 feature
+#[cfg_attr(not(feature = "v3"), allow(clippy::rest_pat_in_fully_bound_structs))]
+fn dispatch<F, H, ReqBody, ResBody>(
+    req: Request<ReqBody>,
+    engine: Arc<EngineIo<H>>,
+) -> ResponseFuture<F, ResBody>
 where
     ReqBody: http_body::Body + Send + Unpin + 'static,
     ReqBody::Data: Send,
@@ -36,29 +72,65 @@ where
             method: Method::GET,
             #[cfg(feature = "v3")]
             b64,
-        }) => ResponseFuture::ready(polling::open_req(
-            engine,
-            protocol,
-            req,
             #[cfg(feature = "v3")]
-            !b64,
-        )),
+            jsonp,
+        }) => handshake(req, engine, move |engine, req| {
+            polling::open_req(
+                engine,
+                protocol,
+                req,
+                #[cfg(feature = "v3")]
+                !b64,
+                #[cfg(feature = "v3")]
+                jsonp,
+            )
+        }),
         Ok(RequestInfo {
             protocol,
             sid: Some(sid),
             transport: TransportType::Polling,
             method: Method::GET,
+            #[cfg(feature = "v3")]
+            jsonp,
             ..
-        }) => ResponseFuture::async_response(Box::pin(polling::polling_req(engine, protocol, sid))),
+        }) => {
+            #[cfg(feature = "http-compression")]
+            let encoding = polling::negotiate_encoding(req.headers());
+            ResponseFuture::async_response(Box::pin(polling::polling_req(
+                engine,
+                protocol,
+                sid,
+                #[cfg(feature = "http-compression")]
+                encoding,
+                #[cfg(feature = "v3")]
+                jsonp,
+            )))
+        }
         Ok(RequestInfo {
             protocol,
             sid: Some(sid),
             transport: TransportType::Polling,
             method: Method::POST,
+            #[cfg(feature = "v3")]
+            jsonp,
             ..
-        }) => {
-            ResponseFuture::async_response(Box::pin(polling::post_req(engine, protocol, sid, req)))
-        }
+        }) => ResponseFuture::async_response(Box::pin(polling::post_req(
+            engine,
+            protocol,
+            sid,
+            req,
+            #[cfg(feature = "v3")]
+            jsonp.is_some(),
+        ))),
+        Ok(RequestInfo {
+            protocol,
+            sid,
+            transport: TransportType::Websocket,
+            method: Method::GET,
+            ..
+        }) if sid.is_none() => handshake(req, engine, move |engine, req| {
+            ws::new_req(engine, protocol, None, req)
+        }),
         Ok(RequestInfo {
             protocol,
             sid,
@@ -79,6 +151,55 @@ where
     }
 }
 
+/// Open a new session if the handler accepts new sessions, after running the [`HandshakeFilter`](crate::handshake::HandshakeFilter)
+/// of the config on the request and checking the [connection limits](crate::connection_limit).
+///
+/// The response is only deferred if the filter does not resolve immediately.
+fn handshake<F, H, ReqBody, ResBody>(
+    req: Request<ReqBody>,
+    engine: Arc<EngineIo<H>>,
+    open: impl FnOnce(Arc<EngineIo<H>>, Request<ReqBody>) -> Result<Response<ResponseBody<ResBody>>, Error>
+        + Send
+        + 'static,
+) -> ResponseFuture<F, ResBody>
+where
+    ReqBody: Send + 'static,
+    ResBody: Send + 'static,
+    H: EngineIoHandler,
+{
+    if let Err(e) = engine.accept_session() {
+        return ResponseFuture::ready(Err(e));
+    }
+    let config = &engine.config;
+    let Some(filter) = &config.handshake_filter else {
+        if !config.connection_limits.is_enabled() {
+            return ResponseFuture::ready(open(engine, req));
+        }
+        let (mut parts, body) = req.into_parts();
+        let res = config.acquire_connection(&mut parts);
+        return ResponseFuture::ready(
+            res.and_then(|_| open(engine, Request::from_parts(parts, body))),
+        );
+    };
+    let (mut parts, body) = req.into_parts();
+    let mut filtered = filter.filter(&parts);
+    // Synchronous filters resolve on the first poll, their handshakes are answered immediately
+    let mut cx = Context::from_waker(noop_waker_ref());
+    if let Poll::Ready(res) = filtered.as_mut().poll(&mut cx) {
+        let res = res
+            .map_err(rejected)
+            .and_then(|_| config.acquire_connection(&mut parts));
+        return ResponseFuture::ready(
+            res.and_then(|_| open(engine, Request::from_parts(parts, body))),
+        );
+    }
+    ResponseFuture::async_response(Box::pin(async move {
+        filtered.await.map_err(rejected)?;
+        engine.config.acquire_connection(&mut parts)?;
+        open(engine, Request::from_parts(parts, body))
+    }))
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ParseError {
     #[error("transport unknown")]
@@ -154,6 +275,11 @@ pub enum TransportType {
     Polling = 0x01,
     /// Websocket transport
     Websocket = 0x02,
+    /// WebTransport transport.
+    ///
+    /// The variant is always declared so that enabling the `webtransport` feature flag
+    /// doesn't break exhaustive matches, but it is only parsed and served with the flag.
+    WebTransport = 0x04,
 }
 
 impl From<u8> for TransportType {
@@ -161,6 +287,7 @@ impl From<u8> for TransportType {
         match t {
             0x01 => TransportType::Polling,
             0x02 => TransportType::Websocket,
+            0x04 => TransportType::WebTransport,
             _ => panic!("unknown transport type"),
         }
     }
@@ -173,6 +300,8 @@ impl FromStr for TransportType {
         match s {
             "websocket" => Ok(TransportType::Websocket),
             "polling" => Ok(TransportType::Polling),
+            #[cfg(feature = "webtransport")]
+            "webtransport" => Ok(TransportType::WebTransport),
             _ => Err(ParseError::UnknownTransport),
         }
     }
@@ -182,6 +311,7 @@ impl From<TransportType> for &'static str {
         match t {
             TransportType::Polling => "polling",
             TransportType::Websocket => "websocket",
+            TransportType::WebTransport => "webtransport",
         }
     }
 }
@@ -190,6 +320,7 @@ impl From<TransportType> for String {
         match t {
             TransportType::Polling => "polling".into(),
             TransportType::Websocket => "websocket".into(),
+            TransportType::WebTransport => "webtransport".into(),
         }
     }
 }
@@ -208,6 +339,9 @@ pub struct RequestInfo {
     /// If the client asked for base64 encoding only.
     #[cfg(feature = "v3")]
     pub b64: bool,
+    /// The callback index of a JSONP polling client, set with the `j` query param.
+    #[cfg(feature = "v3")]
+    pub jsonp: Option<String>,
 }
 
 impl RequestInfo {
@@ -241,11 +375,16 @@ impl RequestInfo {
         }
 
         #[cfg(feature = "v3")]
-        let b64: bool = query
+        let b64 = is_b64(query);
+
+        // JSONP polling clients set the `j` query param to the index of their callback.
+        // Like the reference implementation, only the digits are kept as it is written in the response.
+        #[cfg(feature = "v3")]
+        let jsonp = query
             .split('&')
-            .find(|s| s.starts_with("b64="))
-            .map(|_| true)
-            .unwrap_or_default();
+            .find_map(|s| s.strip_prefix("j="))
+            .filter(|_| transport == TransportType::Polling)
+            .map(|j| j.chars().filter(char::is_ascii_digit).collect());
 
         let method = req.method().clone();
         if !matches!(method, Method::GET) && sid.is_none() {
@@ -258,11 +397,24 @@ impl RequestInfo {
                 method,
                 #[cfg(feature = "v3")]
                 b64,
+                #[cfg(feature = "v3")]
+                jsonp,
             })
         }
     }
 }
 
+/// Returns true if the client asked for base64 encoded binary packets with the `b64` query param.
+///
+/// Like the reference implementation, any non-empty value is considered as true (e.g `b64=1` or `b64=true`).
+#[cfg(feature = "v3")]
+pub(crate) fn is_b64(query: &str) -> bool {
+    query
+        .split('&')
+        .filter_map(|s| s.strip_prefix("b64="))
+        .any(|v| !v.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -327,13 +479,53 @@ mod tests {
     #[test]
     #[cfg(feature = "v3")]
     fn request_info_polling_withb64() {
-        assert!(cfg!(feature = "v3"));
-
         let req = build_request("http://localhost:3000/socket.io/?EIO=3&transport=polling&b64=1");
         let req = RequestInfo::parse(&req, &EngineIoConfig::default()).unwrap();
         assert!(req.b64);
     }
 
+    #[test]
+    #[cfg(feature = "v3")]
+    fn request_info_b64_values() {
+        for (query, b64) in [
+            ("b64=1", true),
+            ("b64=true", true),
+            ("b64=", false),
+            ("", false),
+        ] {
+            let req = build_request(&format!(
+                "http://localhost:3000/socket.io/?EIO=3&transport=polling&{query}"
+            ));
+            let req = RequestInfo::parse(&req, &EngineIoConfig::default()).unwrap();
+            assert_eq!(req.b64, b64, "{query}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "v3")]
+    fn request_info_websocket_withb64() {
+        let req = build_request("http://localhost:3000/socket.io/?EIO=3&transport=websocket&b64=1");
+        let req = RequestInfo::parse(&req, &EngineIoConfig::default()).unwrap();
+        assert_eq!(req.transport, TransportType::Websocket);
+        assert!(req.b64);
+    }
+
+    #[test]
+    #[cfg(feature = "v3")]
+    fn request_info_jsonp() {
+        let req = build_request("http://localhost:3000/socket.io/?EIO=3&transport=polling&j=12");
+        let req = RequestInfo::parse(&req, &EngineIoConfig::default()).unwrap();
+        assert_eq!(req.jsonp.as_deref(), Some("12"));
+
+        let req = build_request("http://localhost:3000/socket.io/?EIO=3&transport=polling&j=1);x(");
+        let req = RequestInfo::parse(&req, &EngineIoConfig::default()).unwrap();
+        assert_eq!(req.jsonp.as_deref(), Some("1"));
+
+        let req = build_request("http://localhost:3000/socket.io/?EIO=3&transport=polling");
+        let req = RequestInfo::parse(&req, &EngineIoConfig::default()).unwrap();
+        assert_eq!(req.jsonp, None);
+    }
+
     #[test]
     fn transport_unknown_err() {
         let req = build_request("http://localhost:3000/socket.io/?EIO=4&transport=grpc");
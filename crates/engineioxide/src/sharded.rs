@@ -0,0 +1,191 @@
+//! A concurrent hash map split in independently locked shards.
+//!
+//! The sessions of a server are inserted and removed by every connection and disconnection,
+//! so a single lock over them is contended when many clients connect at once.
+//! Each key is hashed with [`ahash`] to pick its shard, so concurrent operations on different
+//! keys rarely wait on the same lock.
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    hash::Hash,
+    sync::RwLock,
+};
+
+use ahash::RandomState;
+
+/// A concurrent hash map split in shards, each protected by its own [`RwLock`].
+///
+/// The values are cloned out of the map rather than borrowed so that no lock is held
+/// by the caller. It is meant for cheaply clonable values such as [`Arc`](std::sync::Arc)s.
+pub struct ShardedMap<K, V> {
+    hasher: RandomState,
+    shift: u32,
+    shards: Box<[RwLock<HashMap<K, V, RandomState>>]>,
+}
+
+impl<K: Eq + Hash, V> ShardedMap<K, V> {
+    /// Create a map with 4 shards per available thread, rounded to a power of two.
+    pub fn new() -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, usize::from);
+        Self::with_shards(threads * 4)
+    }
+
+    /// Create a map with `shards` shards, rounded up to a power of two.
+    pub fn with_shards(shards: usize) -> Self {
+        let shards = shards.max(1).next_power_of_two();
+        let hasher = RandomState::new();
+        Self {
+            shift: u64::BITS - shards.trailing_zeros(),
+            shards: (0..shards)
+                .map(|_| RwLock::new(HashMap::with_hasher(hasher.clone())))
+                .collect(),
+            hasher,
+        }
+    }
+
+    fn shard(&self, key: &K) -> &RwLock<HashMap<K, V, RandomState>> {
+        // The top 7 bits of the hash are skipped because the hashbrown tables of the shards
+        // use them as control bytes, and a shard only holds the keys sharing its bits.
+        // The shift overflows with a single shard, which always has the index 0.
+        let hash = self.hasher.hash_one(key) << 7;
+        let idx = hash.checked_shr(self.shift).unwrap_or(0) as usize;
+        &self.shards[idx]
+    }
+
+    /// Insert a value, returning the previous value of the key.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.shard(&key).write().unwrap().insert(key, value)
+    }
+
+    /// Insert a value if the key is not already in the map.
+    /// Otherwise the value is given back and the map is left unchanged.
+    pub fn try_insert(&self, key: K, value: V) -> Result<(), V> {
+        match self.shard(&key).write().unwrap().entry(key) {
+            Entry::Occupied(_) => Err(value),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove a key, returning its value.
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.shard(key).write().unwrap().remove(key)
+    }
+
+    /// Check if the map contains a key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.shard(key).read().unwrap().contains_key(key)
+    }
+
+    /// The number of entries, summed over the shards.
+    ///
+    /// Entries inserted or removed concurrently may or may not be counted.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.read().unwrap().len()).sum()
+    }
+
+    /// Check if all the shards are empty.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| s.read().unwrap().is_empty())
+    }
+
+    /// Remove all the entries.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.write().unwrap().clear();
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> ShardedMap<K, V> {
+    /// Get a clone of the value of a key.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.shard(key).read().unwrap().get(key).cloned()
+    }
+
+    /// A snapshot of the keys. The shards are locked one after the other,
+    /// so it is not an atomic view of the map.
+    pub fn keys(&self) -> Vec<K> {
+        let mut keys = Vec::with_capacity(self.len());
+        for shard in self.shards.iter() {
+            keys.extend(shard.read().unwrap().keys().cloned());
+        }
+        keys
+    }
+
+    /// A snapshot of the values. The shards are locked one after the other,
+    /// so it is not an atomic view of the map.
+    pub fn values(&self) -> Vec<V> {
+        let mut values = Vec::with_capacity(self.len());
+        for shard in self.shards.iter() {
+            values.extend(shard.read().unwrap().values().cloned());
+        }
+        values
+    }
+}
+
+impl<K: Eq + Hash, V> Default for ShardedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: std::fmt::Debug, V: std::fmt::Debug> std::fmt::Debug for ShardedMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut map = f.debug_map();
+        for shard in self.shards.iter() {
+            map.entries(shard.read().unwrap().iter());
+        }
+        map.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_get_remove() {
+        let map = ShardedMap::with_shards(4);
+        for i in 0..100 {
+            assert_eq!(map.insert(i, i * 2), None);
+        }
+        assert_eq!(map.insert(1, 3), Some(2));
+        assert_eq!(map.try_insert(1, 4), Err(4));
+        assert_eq!(map.try_insert(100, 200), Ok(()));
+        assert_eq!(map.remove(&100), Some(200));
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get(&1), Some(3));
+        assert!(map.contains_key(&99));
+        assert_eq!(map.remove(&99), Some(198));
+        assert_eq!(map.remove(&99), None);
+        assert!(!map.contains_key(&99));
+
+        let mut keys = map.keys();
+        keys.sort_unstable();
+        assert_eq!(keys, (0..99).collect::<Vec<_>>());
+        assert_eq!(map.values().len(), 99);
+
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn keys_spread_over_shards() {
+        let map = ShardedMap::with_shards(8);
+        for i in 0..1000 {
+            map.insert(i, ());
+        }
+        assert_eq!(map.shards.len(), 8);
+        assert!(map.shards.iter().all(|s| !s.read().unwrap().is_empty()));
+    }
+
+    #[test]
+    fn single_shard() {
+        let map = ShardedMap::with_shards(0);
+        assert_eq!(map.shards.len(), 1);
+        map.insert("foo", 1);
+        assert_eq!(map.get(&"foo"), Some(1));
+    }
+}
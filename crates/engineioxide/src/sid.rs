@@ -1,6 +1,9 @@
 //! [`Socket`](crate::Socket) id type and generator
 //!
 //! It is stored as a 128-bit id and it represent a base64 16 char string
+//!
+//! The session ids are generated by the [`SidGenerator`] of the
+//! [`EngineIoConfig`](crate::config::EngineIoConfig), which defaults to [`RandomSidGenerator`].
 use std::{
     fmt::{Debug, Display, Formatter},
     str::FromStr,
@@ -26,6 +29,75 @@ impl Sid {
         // SAFETY: SID is always a base64 chars string
         unsafe { std::str::from_utf8_unchecked(&self.0) }
     }
+
+    /// Create a session id by encoding 96 bits in base64 (16 chars).
+    pub fn from_bytes(bytes: [u8; 12]) -> Self {
+        let mut id = [0u8; 16];
+        base64::prelude::BASE64_URL_SAFE_NO_PAD
+            .encode_slice(bytes, &mut id)
+            .unwrap();
+        Sid(id)
+    }
+
+    /// Decode the 96 bits encoded in the session id.
+    pub fn to_bytes(&self) -> [u8; 12] {
+        let mut bytes = [0u8; 12];
+        // Any 16 base64 chars can be decoded into 12 bytes
+        base64::prelude::BASE64_URL_SAFE_NO_PAD
+            .decode_slice(self.0, &mut bytes)
+            .unwrap();
+        bytes
+    }
+}
+
+/// A generator of session ids, set with
+/// [`EngineIoConfigBuilder::sid_generator`](crate::config::EngineIoConfigBuilder::sid_generator).
+///
+/// A session id is always a 16 chars base64 string, so a generator can embed at most 96 bits
+/// with [`Sid::from_bytes`], e.g. a node identifier followed by random bytes, or a timestamp
+/// to have time ordered ids in the logs. The generated ids must be unique: when an id is already
+/// used by an open session, a new one is generated, and the handshake fails after 3 collisions.
+///
+/// It is implemented for any `Fn() -> Sid` closure.
+/// ```
+/// # use engineioxide::{config::EngineIoConfig, sid::Sid};
+/// const NODE_ID: u16 = 42;
+/// let config = EngineIoConfig::builder()
+///     .sid_generator(|| {
+///         let mut bytes: [u8; 12] = rand::random();
+///         bytes[..2].copy_from_slice(&NODE_ID.to_be_bytes());
+///         Sid::from_bytes(bytes)
+///     })
+///     .build();
+/// ```
+pub trait SidGenerator: Send + Sync + 'static {
+    /// Generate a new session id.
+    fn generate(&self) -> Sid;
+}
+
+impl<F> SidGenerator for F
+where
+    F: Fn() -> Sid + Send + Sync + 'static,
+{
+    fn generate(&self) -> Sid {
+        self()
+    }
+}
+
+impl Debug for dyn SidGenerator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SidGenerator")
+    }
+}
+
+/// The default [`SidGenerator`], generating random session ids.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomSidGenerator;
+
+impl SidGenerator for RandomSidGenerator {
+    fn generate(&self) -> Sid {
+        Sid::new()
+    }
 }
 
 /// Error type for [`Sid::from_str`]
@@ -68,15 +140,8 @@ impl FromStr for Sid {
 impl Default for Sid {
     fn default() -> Self {
         let mut random = [0u8; 12]; // 12 bytes = 16 chars base64
-        let mut id = [0u8; 16];
-
         rand::thread_rng().fill(&mut random);
-
-        base64::prelude::BASE64_URL_SAFE_NO_PAD
-            .encode_slice(random, &mut id)
-            .unwrap();
-
-        Sid(id)
+        Sid::from_bytes(random)
     }
 }
 
@@ -119,7 +184,7 @@ impl Debug for Sid {
 mod tests {
     use std::str::FromStr;
 
-    use crate::sid::Sid;
+    use crate::sid::{Sid, SidGenerator};
 
     #[test]
     fn test_sid_from_str() {
@@ -139,4 +204,19 @@ mod tests {
         let id = Sid::from_str("aoassaAZDoinazd<").unwrap_err();
         assert_eq!(id.to_string(), "Invalid url base64 string");
     }
+
+    #[test]
+    fn test_sid_bytes() {
+        let bytes = [0xff, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let id = Sid::from_bytes(bytes);
+        assert_eq!(id.to_bytes(), bytes);
+        let id = Sid::from_str(&id.to_string()).unwrap();
+        assert_eq!(id.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_sid_generator() {
+        let generator = || Sid::from_bytes([0; 12]);
+        assert_eq!(generator.generate().as_str(), "AAAAAAAAAAAAAAAA");
+    }
 }
@@ -0,0 +1,136 @@
+//! A slot holding a value owned by a single user at a time.
+//!
+//! The outgoing receiver of a socket is owned by the transport request that flushes it:
+//! a polling request, or the websocket and webtransport tasks for the whole connection.
+//! It is moved out of the slot rather than locked, so a concurrent polling request fails
+//! immediately and no lock guard is ever held across an `.await`.
+//! The inner mutex is only held to swap the value in and out of the slot.
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+use tokio::sync::Notify;
+
+pub struct Slot<T> {
+    /// The value, or `None` when it is taken.
+    value: Mutex<Option<T>>,
+    /// Notified when the value is put back.
+    released: Notify,
+}
+
+impl<T> Slot<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value: Mutex::new(Some(value)),
+            released: Notify::new(),
+        }
+    }
+
+    /// Take the value if it is not already taken.
+    pub fn try_take(&self) -> Option<SlotGuard<'_, T>> {
+        let value = self
+            .value
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()?;
+        Some(SlotGuard {
+            slot: self,
+            value: Some(value),
+        })
+    }
+
+    /// Wait for the value to be put back and take it.
+    pub async fn take(&self) -> SlotGuard<'_, T> {
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            // Register the waiter before checking so a release in between is not missed
+            released.as_mut().enable();
+            if let Some(guard) = self.try_take() {
+                return guard;
+            }
+            released.await;
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Slot<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let taken = self.value.lock().map(|v| v.is_none()).unwrap_or_default();
+        f.debug_struct("Slot").field("taken", &taken).finish()
+    }
+}
+
+/// The value taken out of a [`Slot`], put back when the guard is dropped.
+pub struct SlotGuard<'a, T> {
+    slot: &'a Slot<T>,
+    /// Always `Some` until the guard is dropped.
+    value: Option<T>,
+}
+
+impl<T> Deref for SlotGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.value.as_ref().expect("slot value is taken until drop")
+    }
+}
+impl<T> DerefMut for SlotGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value.as_mut().expect("slot value is taken until drop")
+    }
+}
+
+impl<T> Drop for SlotGuard<'_, T> {
+    fn drop(&mut self) {
+        let mut value = self.slot.value.lock().unwrap_or_else(|e| e.into_inner());
+        *value = self.value.take();
+        drop(value);
+        self.slot.released.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn try_take() {
+        let slot = Slot::new(1);
+        let mut guard = slot.try_take().unwrap();
+        assert!(slot.try_take().is_none());
+        *guard += 1;
+        drop(guard);
+        assert_eq!(*slot.try_take().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn take_waits_for_release() {
+        let slot = Arc::new(Slot::new(()));
+        let guard = slot.try_take().unwrap();
+        let handle = tokio::spawn({
+            let slot = slot.clone();
+            async move {
+                slot.take().await;
+            }
+        });
+        tokio::task::yield_now().await;
+        assert!(!handle.is_finished());
+        drop(guard);
+        tokio::time::timeout(std::time::Duration::from_millis(100), handle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[test]
+    fn drop_value() {
+        let value = Arc::new(());
+        let slot = Slot::new(value.clone());
+        drop(slot.try_take());
+        drop(slot);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}
@@ -56,33 +56,45 @@
 //! ```
 use std::{
     collections::VecDeque,
+    net::IpAddr,
     sync::{
-        atomic::{AtomicU8, Ordering},
-        Arc,
+        atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+        Arc, OnceLock,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use bytes::Bytes;
+use futures_util::future::{self, Either, RemoteHandle};
 use http::request::Parts;
 use smallvec::{smallvec, SmallVec};
-use tokio::{
-    sync::{
-        mpsc::{self},
-        mpsc::{error::TrySendError, Receiver},
-        Mutex,
+use tokio::sync::{
+    mpsc::{self},
+    mpsc::{
+        error::{SendError, TrySendError},
+        Receiver,
     },
-    task::JoinHandle,
+    Mutex, Notify,
 };
 use tokio_tungstenite::tungstenite;
 
 use crate::{
-    config::EngineIoConfig, errors::Error, packet::Packet, peekable::PeekableReceiver,
-    service::ProtocolVersion, Str,
+    config::{EngineIoConfig, Heartbeat},
+    errors::Error,
+    handler::EngineIoHandler,
+    handoff::{HandoffRequest, HandoffResponse},
+    packet::Packet,
+    peekable::{Drain, PeekableReceiver},
+    rate_limit::RateLimiter,
+    runtime::Runtime,
+    service::ProtocolVersion,
+    slot::Slot,
+    Str,
 };
 use crate::{service::TransportType, sid::Sid};
 
 /// A [`DisconnectReason`] represents the reason why a [`Socket`] was closed.
+#[non_exhaustive]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DisconnectReason {
     /// The client gracefully closed the connection
@@ -98,6 +110,74 @@ pub enum DisconnectReason {
     HeartbeatTimeout,
     /// The server is being closed
     ClosingServer,
+    /// The client exceeded the rate limit with the [`Disconnect`](crate::rate_limit::RateLimitPolicy::Disconnect) policy
+    RateLimitExceeded,
+    /// The buffer of the client stayed full for longer than the
+    /// [`slow_consumer_timeout`](crate::config::EngineIoConfig::slow_consumer_timeout)
+    SlowConsumer,
+    /// The server closed the session with a [`CloseReason`], see [`Socket::close_with_reason`]
+    ServerClose,
+}
+
+/// The code and message sent to the client when the server closes its session
+/// with [`Socket::close_with_reason`].
+///
+/// They are sent in the close frame of the websocket transport. The engine.io close packet
+/// of the other transports has no payload, so they are not sent to the polling
+/// and webtransport clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseReason {
+    code: u16,
+    message: Str,
+}
+
+impl CloseReason {
+    /// The maximum length in bytes of the message of a websocket close frame.
+    pub const MAX_MESSAGE_LEN: usize = 123;
+
+    /// The code sent in place of a code that can't be sent in a websocket close frame: policy violation.
+    pub const FALLBACK_CODE: u16 = 1008;
+
+    /// Create a new close reason. The message is truncated to [`CloseReason::MAX_MESSAGE_LEN`] bytes.
+    ///
+    /// The code should be in the `1000..=1003`, `1007..=1014` or `3000..=4999` ranges according to
+    /// [RFC 6455](https://datatracker.ietf.org/doc/html/rfc6455#section-7.4), the latter being reserved
+    /// for applications. Any other code can't be sent in a websocket close frame
+    /// and is replaced with [`CloseReason::FALLBACK_CODE`].
+    pub fn new(code: u16, message: impl Into<Str>) -> Self {
+        let code = match code {
+            1000..=1003 | 1007..=1014 | 3000..=4999 => code,
+            _ => Self::FALLBACK_CODE,
+        };
+        let mut message: Str = message.into();
+        if message.len() > Self::MAX_MESSAGE_LEN {
+            let end = (0..=Self::MAX_MESSAGE_LEN)
+                .rev()
+                .find(|&i| message.is_char_boundary(i))
+                .unwrap_or(0);
+            message = message.slice(..end);
+        }
+        Self { code, message }
+    }
+
+    /// The close code.
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    /// The close message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<&CloseReason> for tungstenite::protocol::CloseFrame {
+    fn from(reason: &CloseReason) -> Self {
+        Self {
+            code: reason.code.into(),
+            reason: String::from(reason.message.clone()).into(),
+        }
+    }
 }
 
 /// Convert an [`Error`] to a [`DisconnectReason`] if possible
@@ -107,31 +187,119 @@ impl From<&Error> for Option<DisconnectReason> {
     fn from(err: &Error) -> Self {
         use Error::*;
         match err {
-            WsTransport(tungstenite::Error::ConnectionClosed) => None,
+            WsTransport(e) if matches!(**e, tungstenite::Error::ConnectionClosed) => None,
             WsTransport(_) | Io(_) => Some(DisconnectReason::TransportError),
             BadPacket(_) | Base64(_) | StrUtf8(_) | PayloadTooLarge | InvalidPacketLength
             | InvalidPacketType(_) => Some(DisconnectReason::PacketParsingError),
             HeartbeatTimeout => Some(DisconnectReason::HeartbeatTimeout),
+            RateLimitExceeded => Some(DisconnectReason::RateLimitExceeded),
             _ => None,
         }
     }
 }
 
+/// The time since which the internal chan of a [`Socket`] is full.
+#[derive(Debug)]
+struct SlowConsumer {
+    timeout: Duration,
+    full_since: std::sync::Mutex<Option<Instant>>,
+}
+impl SlowConsumer {
+    fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            full_since: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Mark the chan as full and returns true if it has been full for longer than the timeout.
+    /// The timer is then restarted so that the socket is not closed repeatedly.
+    fn is_expired(&self) -> bool {
+        let mut full_since = self.full_since.lock().unwrap();
+        match *full_since {
+            Some(since) if since.elapsed() >= self.timeout => {
+                *full_since = None;
+                true
+            }
+            Some(_) => false,
+            None => {
+                *full_since = Some(Instant::now());
+                false
+            }
+        }
+    }
+
+    fn reset(&self) {
+        *self.full_since.lock().unwrap() = None;
+    }
+}
+
+/// The number of bytes of the packets buffered in the internal chan of a [`Socket`].
+///
+/// The limit is checked before pushing new packets so the last packet may exceed it.
+#[derive(Debug)]
+struct BufferedBytes {
+    max: usize,
+    bytes: AtomicUsize,
+    /// Notified when the buffered bytes go below the limit
+    notify: Notify,
+}
+impl BufferedBytes {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            bytes: AtomicUsize::new(0),
+            notify: Notify::new(),
+        }
+    }
+
+    fn size(packets: &[Packet]) -> usize {
+        packets.iter().map(|p| p.get_size_hint(false)).sum()
+    }
+
+    fn is_full(&self) -> bool {
+        self.bytes.load(Ordering::Relaxed) >= self.max
+    }
+
+    /// Must be called before pushing the packets to the chan so that they are never
+    /// removed before being added.
+    fn add(&self, packets: &[Packet]) {
+        self.bytes.fetch_add(Self::size(packets), Ordering::Relaxed);
+    }
+
+    fn remove(&self, packets: &[Packet]) {
+        let size = Self::size(packets);
+        let prev = self.bytes.fetch_sub(size, Ordering::Relaxed);
+        if prev >= self.max && prev - size < self.max {
+            self.notify.notify_waiters();
+        }
+    }
+}
+
 /// A permit to emit a message to the client.
 /// A permit holds a place in the internal channel to send one packet to the client.
 pub struct Permit<'a> {
     inner: mpsc::Permit<'a, PacketBuf>,
+    buffered_bytes: Option<&'a BufferedBytes>,
 }
 impl Permit<'_> {
+    #[inline]
+    fn send(self, packets: PacketBuf) {
+        if let Some(buffered_bytes) = self.buffered_bytes {
+            buffered_bytes.add(&packets);
+        }
+        self.inner.send(packets);
+    }
+
     /// Consume the permit and emit a message to the client.
     #[inline]
     pub fn emit(self, msg: Str) {
-        self.inner.send(smallvec![Packet::Message(msg)]);
+        self.send(smallvec![Packet::Message(msg)]);
     }
     /// Consume the permit and emit a binary message to the client.
     #[inline]
     pub fn emit_binary(self, data: Bytes) {
-        self.inner.send(smallvec![Packet::Binary(data)]);
+        self.send(smallvec![Packet::Binary(data)]);
     }
 
     /// Consume the permit and emit a message with multiple binary data to the client.
@@ -143,7 +311,7 @@ impl Permit<'_> {
         for d in data {
             packets.push(Packet::Binary(d));
         }
-        self.inner.send(packets);
+        self.send(packets);
     }
 
     /// Consume the permit and emit a message with multiple binary data to the client.
@@ -155,7 +323,99 @@ impl Permit<'_> {
         for d in data {
             packets.push(Packet::Binary(d));
         }
-        self.inner.send(packets);
+        self.send(packets);
+    }
+
+    /// Consume the permit and emit a [`PacketBatch`] to the client.
+    ///
+    /// The packets are pushed at once to the internal chan, so they are written with a single
+    /// flush of the websocket or in the same polling payload (if they fit in its
+    /// [`max_payload`](crate::config::EngineIoConfig::max_payload)).
+    pub fn emit_batch(self, batch: PacketBatch) {
+        self.send(batch.0);
+    }
+}
+
+/// A batch of messages emitted at once with [`Permit::emit_batch`].
+#[derive(Debug, Default)]
+pub struct PacketBatch(PacketBuf);
+impl PacketBatch {
+    /// Create an empty batch with room for `capacity` packets.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(SmallVec::with_capacity(capacity))
+    }
+    /// Add a message to the batch.
+    pub fn push_message(&mut self, msg: Str) {
+        self.0.push(Packet::Message(msg));
+    }
+    /// Add a binary message to the batch.
+    pub fn push_binary(&mut self, data: Bytes) {
+        self.0.push(Packet::Binary(data));
+    }
+    /// The number of packets in the batch.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Returns true if the batch is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// The priority of the packets emitted to the client.
+///
+/// The outgoing buffer of a [`Socket`] has a queue for each priority, flushed from the highest
+/// to the lowest priority. Packets of the same priority are sent in order,
+/// but a packet may overtake the packets of lower priorities emitted before it.
+///
+/// The heartbeat packets have their own queue, flushed before all the others,
+/// so that they are never starved behind a large backlog of messages on a slow client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PacketPriority {
+    /// For the packets that should be sent as soon as possible, such as acknowledgements.
+    High,
+    /// The priority of the messages by default.
+    #[default]
+    Normal,
+    /// For the packets that may be delayed or dropped, such as volatile messages.
+    Low,
+}
+impl PacketPriority {
+    /// The index of the outgoing queue of the priority.
+    fn queue(self) -> usize {
+        match self {
+            PacketPriority::High => 1,
+            PacketPriority::Normal => 2,
+            PacketPriority::Low => 3,
+        }
+    }
+}
+
+/// The index of the outgoing queue of the heartbeat packets.
+const HEARTBEAT_QUEUE: usize = 0;
+/// The number of outgoing queues: heartbeat, high, normal and low priority.
+const QUEUES: usize = 4;
+
+/// Create the outgoing queues of a [`Socket`], each one holding up to `buffer_size` [`PacketBuf`]s.
+/// The receivers are ordered from the highest to the lowest priority.
+fn outgoing_queues(
+    buffer_size: usize,
+) -> ([mpsc::Sender<PacketBuf>; QUEUES], Vec<Receiver<PacketBuf>>) {
+    let mut rxs = Vec::with_capacity(QUEUES);
+    let txs = std::array::from_fn(|_| {
+        let (tx, rx) = mpsc::channel(buffer_size);
+        rxs.push(rx);
+        tx
+    });
+    (txs, rxs)
+}
+
+/// Resets the upgrading flag of a [`Socket`] when dropped,
+/// whether the upgrade succeeded or not.
+pub(crate) struct UpgradeGuard<'a>(&'a AtomicBool);
+impl Drop for UpgradeGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
     }
 }
 
@@ -189,13 +449,16 @@ where
     /// without any mutex
     transport: AtomicU8,
 
+    /// Set while the client is upgrading its transport, between the probe and the upgrade packet
+    upgrading: AtomicBool,
+
     /// Channel to send [`PacketBuf`] to the connection
     ///
     /// It is used and managed by the [`EngineIo`](crate::engine) struct depending on the transport type
     ///
-    /// It is locked if [`EngineIo`](crate::engine) is currently reading from it :
-    /// * In case of polling transport it will be locked and released for each request
-    /// * In case of websocket transport it will always be locked until the connection is closed
+    /// It is taken out of its [`Slot`] while [`EngineIo`](crate::engine) is reading from it :
+    /// * In case of polling transport it will be taken and put back for each request
+    /// * In case of websocket transport it will be taken until the connection is closed
     ///
     /// It will be closed when a [`Close`](Packet::Close) packet is received:
     /// * From the [encoder](crate::service::encoder) if the transport is polling
@@ -204,10 +467,17 @@ where
     ///   Because with polling transport, if the client is not currently polling then the encoder will never be able to close the channel
     ///
     /// The channel is made of a [`SmallVec`] of [`Packet`]s so that adjacent packets can be sent atomically.
-    pub(crate) internal_rx: Mutex<PeekableReceiver<PacketBuf>>,
+    ///
+    /// It is made of one queue per [`PacketPriority`] plus one for the heartbeat packets,
+    /// consumed from the highest to the lowest priority.
+    pub(crate) internal_rx: Slot<PeekableReceiver<PacketBuf>>,
 
-    /// Channel to send [PacketBuf] to the internal connection
-    internal_tx: mpsc::Sender<PacketBuf>,
+    /// Channels to send [PacketBuf] to the internal connection, one per outgoing queue
+    internal_tx: [mpsc::Sender<PacketBuf>; QUEUES],
+    /// Notified when the outgoing queues are emptied by the transport, see [`Socket::flush`]
+    drain: Arc<Drain>,
+    /// The reason sent to the client in the close frame, set with [`Socket::close_with_reason`]
+    pub(crate) close_reason: OnceLock<CloseReason>,
 
     /// Internal channel to receive Pong [`Packets`](Packet) (v4 protocol) or Ping (v3 protocol) in the heartbeat job
     /// which is running in a separate task
@@ -216,7 +486,10 @@ where
     /// which is running in a separate task
     pub(crate) heartbeat_tx: mpsc::Sender<()>,
     /// Handle to the heartbeat job so that it can be aborted when the socket is closed
-    heartbeat_handle: Mutex<Option<JoinHandle<()>>>,
+    heartbeat_handle: Mutex<Option<RemoteHandle<()>>>,
+
+    /// The runtime used to spawn the tasks and timers of the session
+    pub(crate) runtime: Arc<dyn Runtime>,
 
     /// Function to call when the socket is closed
     close_fn: Box<dyn Fn(Sid, DisconnectReason) + Send + Sync>,
@@ -226,9 +499,35 @@ where
     /// Http Request data used to create a socket
     pub req_parts: Parts,
 
+    /// The ip of the client, resolved at handshake
+    remote_addr: Option<IpAddr>,
+
     /// If the client supports binary packets (via polling XHR2)
     #[cfg(feature = "v3")]
     pub(crate) supports_binary: bool,
+
+    /// The heartbeat settings of the session, depending on the transport that opened it
+    heartbeat: Heartbeat,
+
+    /// If a message was received from the client since the last ping, used to back off the pings
+    /// of idle sessions
+    active: AtomicBool,
+
+    /// The rate limiter of the packets received from the client
+    rate_limiter: Option<RateLimiter>,
+
+    /// Tracks for how long the internal chan is full to close slow consumers
+    slow_consumer: Option<SlowConsumer>,
+
+    /// Tracks the bytes buffered in the internal chan if a limit is set
+    buffered_bytes: Option<Arc<BufferedBytes>>,
+
+    /// The maximum payload size of polling requests, used to handle forwarded requests
+    pub(crate) max_payload: u64,
+
+    /// The span of the session, parent of all the spans of its packets
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
 }
 
 impl<D> Socket<D>
@@ -236,6 +535,7 @@ where
     D: Default + Send + Sync + 'static,
 {
     pub(crate) fn new(
+        id: Sid,
         protocol: ProtocolVersion,
         transport: TransportType,
         config: &EngineIoConfig,
@@ -243,34 +543,69 @@ where
         close_fn: Box<dyn Fn(Sid, DisconnectReason) + Send + Sync>,
         #[cfg(feature = "v3")] supports_binary: bool,
     ) -> Self {
-        let (internal_tx, internal_rx) = mpsc::channel(config.max_buffer_size);
+        let (internal_tx, internal_rx) = outgoing_queues(config.max_buffer_size);
         let (heartbeat_tx, heartbeat_rx) = mpsc::channel(1);
 
+        let buffered_bytes = config
+            .max_buffer_bytes
+            .map(|max| Arc::new(BufferedBytes::new(max)));
+        let drain = Arc::new(Drain::default());
+        let internal_rx = PeekableReceiver::prioritized(internal_rx).with_drain(drain.clone());
+        let internal_rx = match buffered_bytes.clone() {
+            Some(buffered_bytes) => {
+                internal_rx.with_hook(move |p: &PacketBuf| buffered_bytes.remove(p))
+            }
+            None => internal_rx,
+        };
+
+        #[cfg(feature = "tracing")]
+        let span =
+            tracing::info_span!(parent: None, "engineio.session", sid = %id, ?protocol, ?transport);
+        #[cfg(feature = "otel")]
+        crate::otel::set_parent(&span, &req_parts.headers);
+        let remote_addr = config.client_ip.resolve(&req_parts);
+
         Self {
-            id: Sid::new(),
+            id,
             protocol,
             transport: AtomicU8::new(transport as u8),
+            upgrading: AtomicBool::new(false),
 
-            internal_rx: Mutex::new(PeekableReceiver::new(internal_rx)),
+            internal_rx: Slot::new(internal_rx),
             internal_tx,
+            drain,
+            close_reason: OnceLock::new(),
 
             heartbeat_rx: Mutex::new(heartbeat_rx),
             heartbeat_tx,
             heartbeat_handle: Mutex::new(None),
+            runtime: config.runtime.clone(),
             close_fn,
 
             data: D::default(),
             req_parts,
+            remote_addr,
 
             #[cfg(feature = "v3")]
             supports_binary,
+
+            heartbeat: config.heartbeat(transport),
+            active: AtomicBool::new(false),
+            rate_limiter: config.max_packets_per_second.map(RateLimiter::new),
+            slow_consumer: config.slow_consumer_timeout.map(SlowConsumer::new),
+            buffered_bytes,
+            max_payload: config.max_payload,
+
+            #[cfg(feature = "tracing")]
+            span,
         }
     }
 
     /// Abort the heartbeat job if it is running
     pub(crate) fn abort_heartbeat(&self) {
+        // Dropping the handle aborts the job
         if let Ok(Some(handle)) = self.heartbeat_handle.try_lock().map(|mut h| h.take()) {
-            handle.abort();
+            drop(handle);
         }
     }
 
@@ -278,23 +613,70 @@ where
     pub(crate) fn send(&self, packet: Packet) -> Result<(), TrySendError<Packet>> {
         #[cfg(feature = "tracing")]
         tracing::debug!("[sid={}] sending packet: {:?}", self.id, packet);
-        self.internal_tx
-            .try_send(smallvec![packet])
+        let packets: PacketBuf = smallvec![packet];
+        if let Some(buffered_bytes) = &self.buffered_bytes {
+            // Only the user packets are limited so that control packets are always sent
+            if buffered_bytes.is_full()
+                && matches!(packets[0], Packet::Message(_) | Packet::Binary(_))
+            {
+                self.on_buffer_full();
+                return Err(TrySendError::Full(packets.into_iter().next().unwrap()));
+            }
+            buffered_bytes.add(&packets);
+        }
+        self.internal_tx[PacketPriority::Normal.queue()]
+            .try_send(packets)
             .map_err(|p| match p {
-                TrySendError::Full(mut p) => TrySendError::Full(p.pop().unwrap()),
-                TrySendError::Closed(mut p) => TrySendError::Closed(p.pop().unwrap()),
+                TrySendError::Full(mut p) => {
+                    self.on_buffer_full();
+                    self.unbuffer(&p);
+                    TrySendError::Full(p.pop().unwrap())
+                }
+                TrySendError::Closed(mut p) => {
+                    self.unbuffer(&p);
+                    TrySendError::Closed(p.pop().unwrap())
+                }
             })?;
+        self.on_buffer_available();
         Ok(())
     }
 
+    /// Removes packets that could not be pushed to the internal chan from the buffered bytes.
+    fn unbuffer(&self, packets: &[Packet]) {
+        if let Some(buffered_bytes) = &self.buffered_bytes {
+            buffered_bytes.remove(packets);
+        }
+    }
+
+    /// Called when a packet could not be pushed to the internal chan because it is full.
+    /// If the chan has been full for longer than the slow consumer timeout, the socket is closed.
+    fn on_buffer_full(&self) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::packet_dropped();
+        if let Some(slow_consumer) = &self.slow_consumer {
+            if slow_consumer.is_expired() {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("[sid={}] closing slow consumer", self.id);
+                self.close(DisconnectReason::SlowConsumer);
+            }
+        }
+    }
+
+    /// Called when a packet was pushed to the internal chan, it is not full anymore.
+    fn on_buffer_available(&self) {
+        if let Some(slow_consumer) = &self.slow_consumer {
+            slow_consumer.reset();
+        }
+    }
+
     /// Spawn the heartbeat job
     ///
     /// Keep a handle to the job so that it can be aborted when the socket is closed
-    pub(crate) fn spawn_heartbeat(self: Arc<Self>, interval: Duration, timeout: Duration) {
+    pub(crate) fn spawn_heartbeat(self: Arc<Self>) {
         let socket = self.clone();
 
-        let handle = tokio::spawn(async move {
-            if let Err(_e) = socket.heartbeat_job(interval, timeout).await {
+        let handle = self.runtime.spawn_with_handle(async move {
+            if let Err(_e) = socket.heartbeat_job().await {
                 socket.close(DisconnectReason::HeartbeatTimeout);
                 #[cfg(feature = "tracing")]
                 tracing::debug!("[sid={}] heartbeat error: {:?}", socket.id, _e);
@@ -310,10 +692,10 @@ where
     ///
     /// If the client or server does not respond within the timeout, the connection is closed.
     #[cfg(feature = "v3")]
-    async fn heartbeat_job(&self, interval: Duration, timeout: Duration) -> Result<(), Error> {
+    async fn heartbeat_job(&self) -> Result<(), Error> {
         match self.protocol {
-            ProtocolVersion::V3 => self.heartbeat_job_v3(interval, timeout).await,
-            ProtocolVersion::V4 => self.heartbeat_job_v4(interval, timeout).await,
+            ProtocolVersion::V3 => self.heartbeat_job_v3().await,
+            ProtocolVersion::V4 => self.heartbeat_job_v4().await,
         }
     }
 
@@ -321,14 +703,22 @@ where
     ///
     /// If the client does not respond within the timeout, the connection is closed.
     #[cfg(not(feature = "v3"))]
-    async fn heartbeat_job(&self, interval: Duration, timeout: Duration) -> Result<(), Error> {
-        self.heartbeat_job_v4(interval, timeout).await
+    async fn heartbeat_job(&self) -> Result<(), Error> {
+        self.heartbeat_job_v4().await
     }
 
     /// Heartbeat is sent every `interval` milliseconds and the client is expected to respond within `timeout` milliseconds.
     ///
     /// If the client does not respond within the timeout, the connection is closed.
-    async fn heartbeat_job_v4(&self, interval: Duration, timeout: Duration) -> Result<(), Error> {
+    ///
+    /// If an idle interval is set, the interval is doubled after each ping during which
+    /// no message was received, up to the idle interval.
+    async fn heartbeat_job_v4(&self) -> Result<(), Error> {
+        let Heartbeat {
+            interval,
+            timeout,
+            idle_interval,
+        } = self.heartbeat;
         let mut heartbeat_rx = self
             .heartbeat_rx
             .try_lock()
@@ -337,22 +727,27 @@ where
         #[cfg(feature = "tracing")]
         tracing::debug!(sid = ?self.id, "heartbeat sender routine started");
 
-        let mut interval_tick = tokio::time::interval(interval);
-        interval_tick.tick().await;
+        let mut next_ping = Instant::now();
+        let mut period = interval;
+        // The first ping is sent on the next tick of the timer, after the handshake is flushed
+        self.runtime.sleep(Duration::ZERO).await;
         // Some clients send the pong packet in first. If that happens, we should consume it.
         heartbeat_rx.try_recv().ok();
         loop {
             #[cfg(feature = "tracing")]
             tracing::trace!(sid = ?self.id, "emitting ping");
 
-            self.internal_tx
-                .try_send(smallvec![Packet::Ping])
-                .map_err(|_| Error::HeartbeatTimeout)?;
+            if !self.send_heartbeat(Packet::Ping)? {
+                next_ping += period;
+                self.sleep_until(next_ping).await;
+                continue;
+            }
 
             #[cfg(feature = "tracing")]
             tracing::trace!(sid = ?self.id, "waiting for pong");
 
-            tokio::time::timeout(timeout, heartbeat_rx.recv())
+            self.runtime
+                .timeout(timeout, heartbeat_rx.recv())
                 .await
                 .map_err(|_| Error::HeartbeatTimeout)?
                 .ok_or(Error::HeartbeatTimeout)?;
@@ -360,12 +755,29 @@ where
             #[cfg(feature = "tracing")]
             tracing::trace!(sid = ?self.id, "pong received");
 
-            interval_tick.tick().await;
+            if let Some(idle_interval) = idle_interval {
+                period = if self.active.swap(false, Ordering::Relaxed) {
+                    interval
+                } else {
+                    (period * 2).min(idle_interval)
+                };
+            }
+            next_ping += period;
+            self.sleep_until(next_ping).await;
         }
     }
 
+    /// Sleep until the given deadline with the runtime of the session.
+    async fn sleep_until(&self, deadline: Instant) {
+        self.runtime
+            .sleep(deadline.saturating_duration_since(Instant::now()))
+            .await;
+    }
+
     #[cfg(feature = "v3")]
-    async fn heartbeat_job_v3(&self, interval: Duration, timeout: Duration) -> Result<(), Error> {
+    async fn heartbeat_job_v3(&self) -> Result<(), Error> {
+        // The client pings at the interval advertised in the handshake
+        let timeout = self.heartbeat.advertised_interval() + self.heartbeat.timeout;
         let mut heartbeat_rx = self
             .heartbeat_rx
             .try_lock()
@@ -375,49 +787,192 @@ where
         tracing::debug!(sid = ?self.id, "heartbeat receiver routine started");
 
         loop {
-            tokio::time::timeout(interval + timeout, heartbeat_rx.recv())
+            self.runtime
+                .timeout(timeout, heartbeat_rx.recv())
                 .await
                 .map_err(|_| Error::HeartbeatTimeout)?
                 .ok_or(Error::HeartbeatTimeout)?;
 
             #[cfg(feature = "tracing")]
             tracing::trace!(sid = ?self.id, "ping received, sending pong");
-            self.internal_tx
-                .try_send(smallvec![Packet::Pong])
-                .map_err(|_| Error::HeartbeatTimeout)?;
+            self.send_heartbeat(Packet::Pong)?;
         }
     }
 
-    /// Returns true if the [`Socket`] has a websocket [`TransportType`]
-    pub(crate) fn is_ws(&self) -> bool {
-        self.transport.load(Ordering::Relaxed) == TransportType::Websocket as u8
+    /// Push a heartbeat packet to its queue in the internal chan and returns whether it was sent.
+    ///
+    /// If the chan is full and a slow consumer timeout is set, the packet is skipped and the socket
+    /// is left to the slow consumer policy rather than being closed with a heartbeat timeout.
+    fn send_heartbeat(&self, packet: Packet) -> Result<bool, Error> {
+        let packets: PacketBuf = smallvec![packet];
+        if let Some(buffered_bytes) = &self.buffered_bytes {
+            buffered_bytes.add(&packets);
+        }
+        match self.internal_tx[HEARTBEAT_QUEUE].try_send(packets) {
+            // The heartbeat queue is not the one of the messages, so the slow consumer timer is kept
+            Ok(()) => Ok(true),
+            Err(TrySendError::Full(p)) if self.slow_consumer.is_some() => {
+                self.unbuffer(&p);
+                self.on_buffer_full();
+                Ok(false)
+            }
+            Err(TrySendError::Full(p) | TrySendError::Closed(p)) => {
+                self.unbuffer(&p);
+                Err(Error::HeartbeatTimeout)
+            }
+        }
     }
+
     /// returns true if the [`Socket`] has an HTTP [`TransportType`]
     pub(crate) fn is_http(&self) -> bool {
         self.transport.load(Ordering::Relaxed) == TransportType::Polling as u8
     }
 
+    /// The tracing span of the session.
+    ///
+    /// It is the parent of the spans created for each packet, transport upgrade and flush of the session.
+    /// With the `otel` feature flag, the remote OpenTelemetry context found in the headers of the request
+    /// that opened the session is set as its parent.
+    #[cfg(feature = "tracing")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+    pub fn span(&self) -> &tracing::Span {
+        &self.span
+    }
+
+    /// Acquire a permit from the rate limiter to handle a message or binary packet received from the client.
+    ///
+    /// Returns `Ok(false)` if the packet should be dropped
+    /// and an error if the connection should be closed.
+    ///
+    /// The session is also marked as active so that its pings are not backed off.
+    pub(crate) async fn acquire_rate_limit(&self) -> Result<bool, Error> {
+        if self.heartbeat.idle_interval.is_some() {
+            self.active.store(true, Ordering::Relaxed);
+        }
+        match &self.rate_limiter {
+            Some(limiter) => limiter.acquire(self.runtime.as_ref()).await,
+            None => Ok(true),
+        }
+    }
+
+    /// Create the span of a packet received from the client
+    #[cfg(feature = "tracing")]
+    pub(crate) fn packet_span(&self, kind: &'static str) -> tracing::Span {
+        tracing::debug_span!(parent: &self.span, "engineio.packet", r#type = kind)
+    }
+
     /// Sets the [`TransportType`] to WebSocket
     /// Used when the client upgrade the connection from HTTP to WebSocket
     pub(crate) fn upgrade_to_websocket(&self) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::session_upgraded(self.transport_type(), TransportType::Websocket);
         self.transport
             .store(TransportType::Websocket as u8, Ordering::Relaxed);
     }
 
+    /// Sets the [`TransportType`] to WebTransport
+    /// Used when the client upgrade the connection from HTTP to WebTransport
+    #[cfg(feature = "webtransport")]
+    pub(crate) fn upgrade_to_webtransport(&self) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::session_upgraded(self.transport_type(), TransportType::WebTransport);
+        self.transport
+            .store(TransportType::WebTransport as u8, Ordering::Relaxed);
+    }
+
+    /// Marks the socket as upgrading until the returned guard is dropped.
+    /// Used by the upgrade handshakes once the probe packet is received.
+    pub(crate) fn start_upgrade(&self) -> UpgradeGuard<'_> {
+        self.upgrading.store(true, Ordering::Relaxed);
+        UpgradeGuard(&self.upgrading)
+    }
+
+    /// Returns true if the client is currently upgrading its transport (e.g. from polling to websocket).
+    ///
+    /// During this window, packets are buffered until the new transport is ready.
+    #[inline]
+    pub fn is_upgrading(&self) -> bool {
+        self.upgrading.load(Ordering::Relaxed)
+    }
+
     /// Returns the current [`TransportType`] of the [`Socket`]
     pub fn transport_type(&self) -> TransportType {
         TransportType::from(self.transport.load(Ordering::Relaxed))
     }
 
+    /// Returns the ip of the client, resolved at handshake with the
+    /// [`ClientIpConfig`](crate::client_ip::ClientIpConfig) of the server.
+    ///
+    /// It is `None` if the http server didn't insert a [`SocketAddr`](std::net::SocketAddr)
+    /// extension in the handshake request.
+    #[inline]
+    pub fn remote_addr(&self) -> Option<IpAddr> {
+        self.remote_addr
+    }
+
     /// Reserve `n` permits to emit multiple messages and ensure that there is enough
     /// space in the internal chan.
     ///
-    /// If the internal chan is full, the function will return a [`TrySendError::Full`] error.
+    /// If the internal chan is full or if the [`max_buffer_bytes`](crate::config::EngineIoConfig::max_buffer_bytes)
+    /// limit is reached, the function will return a [`TrySendError::Full`] error.
     /// If the socket is closed, the function will return a [`TrySendError::Closed`] error.
     #[inline]
     pub fn reserve(&self) -> Result<Permit<'_>, TrySendError<()>> {
-        let permit = self.internal_tx.try_reserve()?;
-        Ok(Permit { inner: permit })
+        self.reserve_with_priority(PacketPriority::Normal)
+    }
+
+    /// Reserve a permit to emit a message with the given [`PacketPriority`].
+    ///
+    /// Each priority has its own queue in the internal chan, so a permit may be available for a
+    /// priority while the queue of another one is full. See [`Socket::reserve`] for the errors.
+    pub fn reserve_with_priority(
+        &self,
+        priority: PacketPriority,
+    ) -> Result<Permit<'_>, TrySendError<()>> {
+        let buffered_bytes = self.buffered_bytes.as_deref();
+        let permit = match self.internal_tx[priority.queue()].try_reserve() {
+            Ok(_) if buffered_bytes.is_some_and(BufferedBytes::is_full) => {
+                Err(TrySendError::Full(()))
+            }
+            permit => permit,
+        };
+        match permit {
+            // Only the normal queue tells if the client is consuming its messages
+            Ok(_) if priority == PacketPriority::Normal => self.on_buffer_available(),
+            Ok(_) => (),
+            Err(TrySendError::Full(_)) => self.on_buffer_full(),
+            Err(TrySendError::Closed(_)) => (),
+        }
+        Ok(Permit {
+            inner: permit?,
+            buffered_bytes,
+        })
+    }
+
+    /// Wait for a permit to emit a message to the client.
+    ///
+    /// Unlike [`Socket::reserve`], it applies back-pressure by waiting for space in the internal chan
+    /// when it is full. If the socket is closed, the function will return a [`SendError`].
+    pub async fn reserve_async(&self) -> Result<Permit<'_>, SendError<()>> {
+        let buffered_bytes = self.buffered_bytes.as_deref();
+        if let Some(buffered_bytes) = buffered_bytes {
+            loop {
+                // The future is created before the check so that a notification is not missed
+                let notified = std::pin::pin!(buffered_bytes.notify.notified());
+                if !buffered_bytes.is_full() {
+                    break;
+                }
+                let closed = std::pin::pin!(self.normal_tx().closed());
+                if let Either::Right(_) = future::select(notified, closed).await {
+                    return Err(SendError(()));
+                }
+            }
+        }
+        let permit = self.normal_tx().reserve().await?;
+        Ok(Permit {
+            inner: permit,
+            buffered_bytes,
+        })
     }
 
     /// Emits a message to the client.
@@ -441,15 +996,90 @@ where
         self.send(Packet::Close).ok();
     }
 
+    /// Immediately closes the socket like [`Socket::close`] with the
+    /// [`ServerClose`](DisconnectReason::ServerClose) reason, and sends the given [`CloseReason`]
+    /// to the client in the websocket close frame.
+    ///
+    /// If the socket is already being closed, the close reason is ignored.
+    pub fn close_with_reason(&self, reason: CloseReason) {
+        if self.close_reason.set(reason).is_ok() {
+            self.close(DisconnectReason::ServerClose);
+        }
+    }
+
+    /// Closes the socket once the packets already emitted are consumed by the transport,
+    /// or after `timeout` if they are not.
+    ///
+    /// Unlike [`Socket::close`], the packets buffered for a polling client are not discarded:
+    /// the socket waits for the next polling request to send them.
+    pub async fn close_after_flush(&self, reason: DisconnectReason, timeout: Duration) {
+        self.runtime.timeout(timeout, self.flush()).await.ok();
+        self.close(reason);
+    }
+
+    /// Returns true if all the packets emitted to the socket were consumed by the transport.
+    pub fn is_flushed(&self) -> bool {
+        !self.drain.is_peeked()
+            && self
+                .internal_tx
+                .iter()
+                .all(|tx| tx.capacity() == tx.max_capacity())
+    }
+
+    /// Waits for all the packets emitted to the socket to be consumed by the transport,
+    /// or for the socket to be closed.
+    ///
+    /// The packets emitted while waiting are also waited for,
+    /// so it should be bounded by a timeout if the socket keeps emitting.
+    pub async fn flush(&self) {
+        loop {
+            let mut notified = std::pin::pin!(self.drain.notified());
+            notified.as_mut().enable();
+            if self.is_flushed() || self.is_closed() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Removes the socket from the `Engine` and notifies the [`Handler`](crate::handler::EngineIoHandler)
+    /// without sending a close packet.
+    pub(crate) fn close_session(&self, reason: DisconnectReason) {
+        (self.close_fn)(self.id, reason);
+    }
+
+    /// Handles a polling request forwarded by another server for this session
+    /// and returns the response to send back to the client.
+    ///
+    /// The `handler` should be the [`EngineIoHandler`] of the engine that owns this socket.
+    /// See the [`handoff`](crate::handoff) module for more details.
+    pub async fn handoff<H>(
+        self: &Arc<Self>,
+        handler: &Arc<H>,
+        req: HandoffRequest,
+    ) -> HandoffResponse
+    where
+        H: EngineIoHandler<Data = D>,
+    {
+        crate::transport::polling::handoff(handler, self, req).await
+    }
+
     /// Returns true if the socket is closed
     /// It means that no more packets can be sent to the client
     pub fn is_closed(&self) -> bool {
-        self.internal_tx.is_closed()
+        self.normal_tx().is_closed()
     }
 
     /// Wait for the socket to be fully closed
     pub async fn closed(&self) {
-        self.internal_tx.closed().await
+        self.normal_tx().closed().await
+    }
+
+    /// The sender of the queue of the [`PacketPriority::Normal`] packets.
+    /// All the queues are closed at the same time.
+    #[inline]
+    fn normal_tx(&self) -> &mpsc::Sender<PacketBuf> {
+        &self.internal_tx[PacketPriority::Normal.queue()]
     }
 
     /// Emits a binary message to the client.
@@ -484,6 +1114,7 @@ impl<D: Default + Send + Sync + 'static> std::fmt::Debug for Socket<D> {
             .field("heartbeat_tx", &self.heartbeat_tx)
             .field("heartbeat_handle", &self.heartbeat_handle)
             .field("req_data", &self.req_parts)
+            .field("remote_addr", &self.remote_addr)
             .finish()
     }
 }
@@ -528,37 +1159,65 @@ where
         close_fn: Box<dyn Fn(Sid, DisconnectReason) + Send + Sync>,
         buffer_size: usize,
     ) -> (Arc<Socket<D>>, tokio::sync::mpsc::Receiver<Packet>) {
-        let (internal_tx, internal_rx) = mpsc::channel(buffer_size);
+        let (internal_tx, internal_rx) = outgoing_queues(buffer_size);
         let (heartbeat_tx, heartbeat_rx) = mpsc::channel(1);
+        let drain = Arc::new(Drain::default());
 
         let sock = Self {
             id: sid,
             protocol: ProtocolVersion::V4,
             transport: AtomicU8::new(TransportType::Websocket as u8),
+            upgrading: AtomicBool::new(false),
 
-            internal_rx: Mutex::new(PeekableReceiver::new(internal_rx)),
+            internal_rx: Slot::new(
+                PeekableReceiver::prioritized(internal_rx).with_drain(drain.clone()),
+            ),
             internal_tx,
+            drain,
+            close_reason: OnceLock::new(),
 
             heartbeat_rx: Mutex::new(heartbeat_rx),
             heartbeat_tx,
             heartbeat_handle: Mutex::new(None),
+            runtime: Arc::new(crate::runtime::TokioRuntime),
             close_fn,
 
             data: D::default(),
             req_parts: http::Request::<()>::default().into_parts().0,
+            remote_addr: None,
 
             #[cfg(feature = "v3")]
             supports_binary: true,
+
+            heartbeat: EngineIoConfig::default().heartbeat(TransportType::Websocket),
+            active: AtomicBool::new(false),
+            rate_limiter: None,
+            slow_consumer: None,
+            buffered_bytes: None,
+            max_payload: EngineIoConfig::default().max_payload,
+
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(parent: None, "engineio.session", sid = %sid),
         };
         let sock = Arc::new(sock);
 
         let (tx, rx) = mpsc::channel(buffer_size);
         let sock_clone = sock.clone();
         tokio::spawn(async move {
-            let mut internal_rx = sock_clone.internal_rx.try_lock().unwrap();
-            while let Some(packets) = internal_rx.recv().await {
+            let mut internal_rx = sock_clone.internal_rx.try_take().unwrap();
+            // The socket holds its own sender so the pipe is also closed when the receiver is dropped,
+            // otherwise the task and the socket would be leaked.
+            loop {
+                let recv = std::pin::pin!(internal_rx.recv());
+                let closed = std::pin::pin!(tx.closed());
+                let packets = match future::select(recv, closed).await {
+                    Either::Left((Some(packets), _)) => packets,
+                    Either::Left((None, _)) | Either::Right(_) => break,
+                };
                 for packet in packets {
-                    tx.send(packet).await.unwrap();
+                    if tx.send(packet).await.is_err() {
+                        return;
+                    }
                 }
             }
         });
@@ -566,3 +1225,200 @@ where
         (sock, rx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_socket(config: &EngineIoConfig) -> Socket<()> {
+        let parts = http::Request::<()>::default().into_parts().0;
+        Socket::new(
+            Sid::new(),
+            ProtocolVersion::V4,
+            TransportType::Websocket,
+            config,
+            parts,
+            Box::new(|_, _| {}),
+            #[cfg(feature = "v3")]
+            true,
+        )
+    }
+
+    #[tokio::test]
+    async fn max_buffer_bytes() {
+        let config = EngineIoConfig::builder().max_buffer_bytes(10).build();
+        let socket = new_socket(&config);
+
+        socket.emit("hello world").unwrap();
+        assert!(matches!(socket.emit("foo"), Err(TrySendError::Full(_))));
+        assert!(matches!(socket.reserve(), Err(TrySendError::Full(_))));
+        // Control packets are not limited
+        socket.send(Packet::Noop).unwrap();
+
+        let mut rx = socket.internal_rx.try_take().unwrap();
+        rx.recv().await.unwrap();
+        socket.emit("foo").unwrap();
+        rx.recv().await.unwrap();
+        rx.recv().await.unwrap();
+        assert_eq!(
+            socket
+                .buffered_bytes
+                .as_ref()
+                .unwrap()
+                .bytes
+                .load(Ordering::Relaxed),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn max_buffer_bytes_reserve_async() {
+        let config = EngineIoConfig::builder().max_buffer_bytes(10).build();
+        let socket = Arc::new(new_socket(&config));
+        socket.emit("hello world").unwrap();
+
+        let socket_clone = socket.clone();
+        let handle = tokio::spawn(async move {
+            socket_clone
+                .reserve_async()
+                .await
+                .unwrap()
+                .emit("foo".into());
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!handle.is_finished());
+
+        let mut rx = socket.internal_rx.try_take().unwrap();
+        rx.recv().await.unwrap();
+        tokio::time::timeout(Duration::from_millis(50), handle)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rx.recv().await.unwrap()[0], Packet::Message("foo".into()));
+    }
+
+    #[tokio::test]
+    async fn priority_queues() {
+        let config = EngineIoConfig::builder().max_buffer_size(1).build();
+        let socket = new_socket(&config);
+
+        socket
+            .reserve_with_priority(PacketPriority::Low)
+            .unwrap()
+            .emit("volatile".into());
+        socket.emit("message").unwrap();
+        assert!(matches!(socket.emit("foo"), Err(TrySendError::Full(_))));
+        // The other queues are not full
+        socket
+            .reserve_with_priority(PacketPriority::High)
+            .unwrap()
+            .emit("ack".into());
+        assert!(socket.send_heartbeat(Packet::Ping).unwrap());
+
+        let mut rx = socket.internal_rx.try_take().unwrap();
+        assert_eq!(rx.recv().await.unwrap()[0], Packet::Ping);
+        assert_eq!(rx.recv().await.unwrap()[0], Packet::Message("ack".into()));
+        assert_eq!(
+            rx.recv().await.unwrap()[0],
+            Packet::Message("message".into())
+        );
+        assert_eq!(
+            rx.recv().await.unwrap()[0],
+            Packet::Message("volatile".into())
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn flush() {
+        let config = EngineIoConfig::default();
+        let socket = Arc::new(new_socket(&config));
+        assert!(socket.is_flushed());
+        socket.emit("foo").unwrap();
+        socket.emit("bar").unwrap();
+        assert!(!socket.is_flushed());
+
+        let socket_clone = socket.clone();
+        let handle = tokio::spawn(async move { socket_clone.flush().await });
+        let mut rx = socket.internal_rx.try_take().unwrap();
+        rx.recv().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!handle.is_finished());
+
+        // A peeked packet is not flushed yet
+        assert!(rx.peek().is_some());
+        assert!(!socket.is_flushed());
+        rx.try_recv().unwrap();
+        assert!(socket.is_flushed());
+        tokio::time::timeout(Duration::from_millis(50), handle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    fn new_polling_socket(close_tx: mpsc::UnboundedSender<DisconnectReason>) -> Arc<Socket<()>> {
+        let config = EngineIoConfig::default();
+        Arc::new(Socket::new(
+            Sid::new(),
+            ProtocolVersion::V4,
+            TransportType::Polling,
+            &config,
+            http::Request::<()>::default().into_parts().0,
+            Box::new(move |_, reason| close_tx.send(reason).unwrap()),
+            #[cfg(feature = "v3")]
+            false,
+        ))
+    }
+
+    #[tokio::test]
+    async fn close_after_flush() {
+        let (close_tx, mut close_rx) = mpsc::unbounded_channel();
+        let socket = new_polling_socket(close_tx);
+        socket.emit("foo").unwrap();
+
+        let socket_clone = socket.clone();
+        let handle = tokio::spawn(async move {
+            socket_clone
+                .close_after_flush(DisconnectReason::ClosingServer, Duration::from_secs(1))
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(close_rx.try_recv().is_err());
+
+        let mut rx = socket.internal_rx.try_take().unwrap();
+        assert_eq!(rx.recv().await.unwrap()[0], Packet::Message("foo".into()));
+        handle.await.unwrap();
+        assert_eq!(close_rx.recv().await, Some(DisconnectReason::ClosingServer));
+        assert_eq!(rx.recv().await.unwrap()[0], Packet::Close);
+    }
+
+    #[tokio::test]
+    async fn close_after_flush_timeout() {
+        let (close_tx, mut close_rx) = mpsc::unbounded_channel();
+        let socket = new_polling_socket(close_tx);
+        // The client never polls so the packets are never flushed
+        socket.emit("foo").unwrap();
+        socket
+            .close_after_flush(DisconnectReason::ClosingServer, Duration::from_millis(10))
+            .await;
+        assert_eq!(close_rx.recv().await, Some(DisconnectReason::ClosingServer));
+    }
+
+    #[test]
+    fn close_reason_truncate() {
+        let reason = CloseReason::new(1000, "é".repeat(100));
+        assert_eq!(reason.message().len(), 122);
+        assert_eq!(reason.code(), 1000);
+        let reason = CloseReason::new(4000, "bye");
+        assert_eq!(reason.message(), "bye");
+    }
+
+    #[test]
+    fn close_reason_invalid_code() {
+        for code in [0, 999, 1004, 1005, 1006, 1015, 2999, 5000] {
+            let reason = CloseReason::new(code, "invalid code");
+            assert_eq!(reason.code(), CloseReason::FALLBACK_CODE);
+            assert_eq!(reason.message(), "invalid code");
+        }
+    }
+}
@@ -0,0 +1,656 @@
+//! Permessage-deflate websocket extension ([RFC 7692](https://datatracker.ietf.org/doc/html/rfc7692)).
+//!
+//! The websocket implementation doesn't support extensions, therefore the extension is implemented
+//! as an IO layer: [`DeflateStream`] sits between the raw connection and the websocket stream.
+//! * Compressed frames received from the client are inflated and rewritten as plain frames.
+//! * Data frames written by the server are deflated if they are larger than the configured threshold.
+//!
+//! Inflated messages larger than the max message size are rejected with a [`MessageTooLarge`] error,
+//! before being fully inflated.
+//! Received frames larger than the max message size are rejected as soon as their header is read,
+//! before being buffered.
+
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use bytes::{Buf, BytesMut};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use http::{HeaderMap, HeaderValue};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::config::DeflateConfig;
+
+const EXTENSION_NAME: &str = "permessage-deflate";
+/// Trailer removed from each compressed message (RFC 7692 7.2.1).
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+/// Default maximum size of an inflated message, it is the default max message size of the websocket stream.
+/// It is also the maximum size of a frame written by the websocket stream.
+pub const MAX_MESSAGE_SIZE: usize = 64 << 20;
+/// Above this amount of pending data, writes are not accepted until it is written to the connection.
+const WRITE_HIGH_WATERMARK: usize = 128 * 1024;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+
+/// The negotiated extension parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeflateParams {
+    level: u32,
+    threshold: usize,
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    /// The client offered a `server_max_window_bits` parameter, which must be echoed (RFC 7692 7.1.2.1).
+    server_max_window_bits: bool,
+    /// The maximum size of an inflated message.
+    max_message_size: usize,
+}
+
+/// The error returned when an inflated message is larger than the max message size.
+#[derive(Debug)]
+pub struct MessageTooLarge;
+
+impl fmt::Display for MessageTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("message too large")
+    }
+}
+
+impl std::error::Error for MessageTooLarge {}
+
+fn message_too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, MessageTooLarge)
+}
+
+impl DeflateParams {
+    /// The `Sec-WebSocket-Extensions` response header value.
+    pub fn header_value(&self) -> HeaderValue {
+        let mut value = EXTENSION_NAME.to_string();
+        if self.server_no_context_takeover {
+            value.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            value.push_str("; client_no_context_takeover");
+        }
+        if self.server_max_window_bits {
+            value.push_str("; server_max_window_bits=15");
+        }
+        HeaderValue::from_str(&value).unwrap()
+    }
+}
+
+/// Negotiate the extension from the `Sec-WebSocket-Extensions` request headers.
+///
+/// The first acceptable offer is selected. Offers restricting the server window size are declined
+/// because the compressor always uses the maximum window size. When an offer accepts the maximum
+/// window size with `server_max_window_bits=15`, the parameter is echoed in the response.
+///
+/// Received messages are inflated up to `max_message_size` bytes.
+pub fn negotiate(
+    config: &DeflateConfig,
+    max_message_size: usize,
+    headers: &HeaderMap,
+) -> Option<DeflateParams> {
+    headers
+        .get_all(http::header::SEC_WEBSOCKET_EXTENSIONS)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|offer| parse_offer(config, max_message_size, offer))
+}
+
+fn parse_offer(
+    config: &DeflateConfig,
+    max_message_size: usize,
+    offer: &str,
+) -> Option<DeflateParams> {
+    let mut parts = offer.split(';').map(str::trim);
+    if parts.next()? != EXTENSION_NAME {
+        return None;
+    }
+    let mut params = DeflateParams {
+        level: config.level,
+        threshold: config.threshold,
+        server_no_context_takeover: config.server_no_context_takeover,
+        client_no_context_takeover: config.client_no_context_takeover,
+        server_max_window_bits: false,
+        max_message_size,
+    };
+    let (mut server_nct, mut client_nct, mut server_bits, mut client_bits) =
+        (false, false, false, false);
+    for param in parts {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (param, None),
+        };
+        let seen = match (name, value) {
+            ("server_no_context_takeover", None) => {
+                params.server_no_context_takeover = true;
+                &mut server_nct
+            }
+            ("client_no_context_takeover", None) => {
+                params.client_no_context_takeover = true;
+                &mut client_nct
+            }
+            ("server_max_window_bits", Some(bits)) if window_bits(bits)? == 15 => {
+                params.server_max_window_bits = true;
+                &mut server_bits
+            }
+            ("client_max_window_bits", None) => &mut client_bits,
+            ("client_max_window_bits", Some(bits)) => {
+                window_bits(bits)?;
+                &mut client_bits
+            }
+            _ => return None,
+        };
+        // A parameter must not be duplicated
+        if std::mem::replace(seen, true) {
+            return None;
+        }
+    }
+    Some(params)
+}
+
+fn window_bits(value: &str) -> Option<u8> {
+    match value.parse() {
+        Ok(bits @ 8..=15) => Some(bits),
+        _ => None,
+    }
+}
+
+/// The header of a websocket frame.
+#[derive(Debug, Clone, Copy)]
+struct FrameHeader {
+    fin: bool,
+    rsv1: bool,
+    opcode: u8,
+    mask: Option<[u8; 4]>,
+    header_len: usize,
+    payload_len: usize,
+}
+
+impl FrameHeader {
+    /// Parse a frame header from the given buffer. Returns `None` if the header is incomplete.
+    ///
+    /// Frames with a payload larger than `max_payload_len` are rejected as soon as their header
+    /// is received, so that they are never buffered.
+    fn parse(buf: &[u8], max_payload_len: usize) -> io::Result<Option<Self>> {
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let (first, second) = (buf[0], buf[1]);
+        let masked = second & 0x80 != 0;
+        let (len_size, payload_len) = match second & 0x7f {
+            126 if buf.len() >= 4 => (2, u16::from_be_bytes([buf[2], buf[3]]) as u64),
+            127 if buf.len() >= 10 => (8, u64::from_be_bytes(buf[2..10].try_into().unwrap())),
+            126 | 127 => return Ok(None),
+            len => (0, len as u64),
+        };
+        if payload_len > max_payload_len as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "frame too large",
+            ));
+        }
+        let header_len = 2 + len_size + if masked { 4 } else { 0 };
+        if buf.len() < header_len {
+            return Ok(None);
+        }
+        let mask = masked.then(|| buf[header_len - 4..header_len].try_into().unwrap());
+        Ok(Some(Self {
+            fin: first & 0x80 != 0,
+            rsv1: first & 0x40 != 0,
+            opcode: first & 0x0f,
+            mask,
+            header_len,
+            payload_len: payload_len as usize,
+        }))
+    }
+
+    fn is_data(&self) -> bool {
+        self.opcode == OP_TEXT || self.opcode == OP_BINARY
+    }
+
+    /// Write a final frame header with the given payload length.
+    fn write(out: &mut BytesMut, rsv1: bool, opcode: u8, mask: Option<[u8; 4]>, len: usize) {
+        out.extend_from_slice(&[0x80 | if rsv1 { 0x40 } else { 0 } | opcode]);
+        let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+        match len {
+            0..=125 => out.extend_from_slice(&[mask_bit | len as u8]),
+            126..=0xffff => {
+                out.extend_from_slice(&[mask_bit | 126]);
+                out.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            _ => {
+                out.extend_from_slice(&[mask_bit | 127]);
+                out.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        if let Some(mask) = mask {
+            out.extend_from_slice(&mask);
+        }
+    }
+}
+
+/// Split a complete frame from the buffer. Frames larger than `max_payload_len` are rejected.
+fn next_frame(
+    buf: &mut BytesMut,
+    max_payload_len: usize,
+) -> io::Result<Option<(FrameHeader, BytesMut)>> {
+    match FrameHeader::parse(buf, max_payload_len)? {
+        Some(header) if buf.len() >= header.header_len + header.payload_len => {
+            let frame = buf.split_to(header.header_len + header.payload_len);
+            Ok(Some((header, frame)))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn unmask(payload: &mut [u8], mask: Option<[u8; 4]>) {
+    if let Some(mask) = mask {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i & 3];
+        }
+    }
+}
+
+struct DeflateState {
+    params: DeflateParams,
+    compress: Compress,
+    decompress: Decompress,
+
+    /// Raw data read from the connection
+    rd_raw: BytesMut,
+    /// Data ready to be given to the websocket stream
+    rd_out: BytesMut,
+    /// A compressed fragmented message being read, with its opcode.
+    rd_msg: Option<(u8, Vec<u8>)>,
+    rd_eof: bool,
+
+    /// Raw data written by the websocket stream
+    wr_raw: BytesMut,
+    /// Data ready to be written to the connection
+    wr_out: BytesMut,
+}
+
+impl DeflateState {
+    fn new(params: DeflateParams) -> Self {
+        Self {
+            compress: Compress::new(Compression::new(params.level.min(9)), false),
+            decompress: Decompress::new(false),
+            rd_raw: BytesMut::new(),
+            rd_out: BytesMut::new(),
+            rd_msg: None,
+            rd_eof: false,
+            wr_raw: BytesMut::new(),
+            wr_out: BytesMut::new(),
+            params,
+        }
+    }
+
+    /// Process a frame received from the client.
+    fn on_read_frame(&mut self, header: FrameHeader, mut frame: BytesMut) -> io::Result<()> {
+        let compressed = match header.opcode {
+            op if header.is_data() && header.rsv1 => {
+                self.rd_msg = Some((op, Vec::with_capacity(header.payload_len)));
+                true
+            }
+            OP_CONTINUATION => self.rd_msg.is_some(),
+            _ => false,
+        };
+        if !compressed {
+            self.rd_out.unsplit(frame);
+            return Ok(());
+        }
+
+        let payload = &mut frame[header.header_len..];
+        unmask(payload, header.mask);
+        let (_, msg) = self.rd_msg.as_mut().unwrap();
+        if msg.len() + payload.len() > self.params.max_message_size {
+            return Err(message_too_large());
+        }
+        msg.extend_from_slice(payload);
+
+        if header.fin {
+            let (opcode, msg) = self.rd_msg.take().unwrap();
+            let data = self.inflate(msg)?;
+            // The websocket stream expects masked frames from the client, a zero mask is a no-op.
+            FrameHeader::write(&mut self.rd_out, false, opcode, Some([0; 4]), data.len());
+            self.rd_out.extend_from_slice(&data);
+        }
+        Ok(())
+    }
+
+    /// Process a frame written by the websocket stream.
+    fn on_write_frame(&mut self, header: FrameHeader, mut frame: BytesMut) -> io::Result<()> {
+        // Fragmented messages are forwarded uncompressed
+        let compress = header.is_data()
+            && header.fin
+            && !header.rsv1
+            && header.payload_len >= self.params.threshold;
+        if !compress {
+            self.wr_out.unsplit(frame);
+            return Ok(());
+        }
+        let mut payload = frame.split_off(header.header_len);
+        unmask(&mut payload, header.mask);
+        let data = self.deflate(&payload)?;
+        FrameHeader::write(&mut self.wr_out, true, header.opcode, None, data.len());
+        self.wr_out.extend_from_slice(&data);
+        Ok(())
+    }
+
+    /// Inflate a message. The output buffer never grows past the max message size + 1,
+    /// so that a small compressed message can't be inflated to a huge one.
+    fn inflate(&mut self, mut data: Vec<u8>) -> io::Result<Vec<u8>> {
+        let max = self.params.max_message_size.saturating_add(1);
+        data.extend_from_slice(&TRAILER);
+        let mut out = Vec::with_capacity((data.len() * 2).min(max));
+        let start = self.decompress.total_in();
+        loop {
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let (total_in, total_out) = (self.decompress.total_in(), self.decompress.total_out());
+            self.decompress
+                .decompress_vec(&data[consumed..], &mut out, FlushDecompress::Sync)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            if out.len() > self.params.max_message_size {
+                return Err(message_too_large());
+            }
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let progress =
+                total_in != self.decompress.total_in() || total_out != self.decompress.total_out();
+            if consumed == data.len() && out.len() < out.capacity() || !progress {
+                break;
+            }
+            out.reserve_exact(out.capacity().min(max - out.len()));
+        }
+        if self.params.client_no_context_takeover {
+            self.decompress.reset(false);
+        }
+        Ok(out)
+    }
+
+    fn deflate(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        let start = self.compress.total_in();
+        loop {
+            let consumed = (self.compress.total_in() - start) as usize;
+            self.compress
+                .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(io::Error::other)?;
+            let consumed = (self.compress.total_in() - start) as usize;
+            if consumed == data.len() && out.len() < out.capacity() {
+                break;
+            }
+            out.reserve(out.capacity());
+        }
+        if out.ends_with(&TRAILER) {
+            out.truncate(out.len() - TRAILER.len());
+        }
+        if self.params.server_no_context_takeover {
+            self.compress.reset();
+        }
+        Ok(out)
+    }
+}
+
+/// An IO layer implementing the permessage-deflate extension.
+///
+/// If the extension was not negotiated, all calls are directly forwarded to the inner stream.
+pub struct DeflateStream<S> {
+    inner: S,
+    state: Option<Box<DeflateState>>,
+}
+
+impl<S> DeflateStream<S> {
+    /// Wrap a connection with the negotiated extension parameters, if any.
+    pub fn new(inner: S, params: Option<DeflateParams>) -> Self {
+        let state = params.map(|params| Box::new(DeflateState::new(params)));
+        Self { inner, state }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> DeflateStream<S> {
+    /// Write all the pending processed data to the connection.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let Some(state) = self.state.as_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+        while !state.wr_out.is_empty() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &state.wr_out))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            state.wr_out.advance(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DeflateStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let Some(state) = this.state.as_mut() else {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        };
+        loop {
+            if !state.rd_out.is_empty() {
+                let n = state.rd_out.len().min(buf.remaining());
+                buf.put_slice(&state.rd_out[..n]);
+                state.rd_out.advance(n);
+                return Poll::Ready(Ok(()));
+            }
+            let max_frame_size = state.params.max_message_size;
+            if let Some((header, frame)) = next_frame(&mut state.rd_raw, max_frame_size)? {
+                state.on_read_frame(header, frame)?;
+                continue;
+            }
+            if state.rd_eof {
+                // Forward any incomplete frame, the websocket stream will handle the error
+                let rest = state.rd_raw.split();
+                state.rd_out.unsplit(rest);
+                if state.rd_out.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                continue;
+            }
+
+            let mut tmp = [0; 4096];
+            let mut tmp = ReadBuf::new(&mut tmp);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut tmp))?;
+            if tmp.filled().is_empty() {
+                state.rd_eof = true;
+            }
+            state.rd_raw.extend_from_slice(tmp.filled());
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.state.is_none() {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+        // Backpressure: try to write the pending data before accepting more
+        if let Poll::Ready(res) = this.poll_drain(cx) {
+            res?;
+        }
+        let state = this.state.as_mut().unwrap();
+        if state.wr_out.len() >= WRITE_HIGH_WATERMARK {
+            return Poll::Pending;
+        }
+        state.wr_raw.extend_from_slice(buf);
+        while let Some((header, frame)) = next_frame(&mut state.wr_raw, MAX_MESSAGE_SIZE)? {
+            state.on_write_frame(header, frame)?;
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(ext: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::SEC_WEBSOCKET_EXTENSIONS,
+            HeaderValue::from_str(ext).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn negotiate_offers() {
+        let config = DeflateConfig::default();
+        let params =
+            |ext| negotiate(&config, MAX_MESSAGE_SIZE, &headers(ext)).map(|p| p.header_value());
+
+        assert_eq!(params("permessage-deflate").unwrap(), "permessage-deflate");
+        assert_eq!(
+            params("permessage-deflate; client_max_window_bits").unwrap(),
+            "permessage-deflate"
+        );
+        assert_eq!(
+            params("permessage-deflate; server_no_context_takeover").unwrap(),
+            "permessage-deflate; server_no_context_takeover"
+        );
+        assert_eq!(
+            params("permessage-deflate; server_max_window_bits=10, permessage-deflate").unwrap(),
+            "permessage-deflate"
+        );
+        assert!(params("permessage-deflate; server_max_window_bits=10").is_none());
+        assert_eq!(
+            params("permessage-deflate; server_max_window_bits=15; client_max_window_bits")
+                .unwrap(),
+            "permessage-deflate; server_max_window_bits=15"
+        );
+        assert!(params("permessage-deflate; unknown").is_none());
+        assert!(params(
+            "permessage-deflate; client_no_context_takeover; client_no_context_takeover"
+        )
+        .is_none());
+        assert!(params("x-webkit-deflate-frame").is_none());
+        assert!(negotiate(&config, MAX_MESSAGE_SIZE, &HeaderMap::new()).is_none());
+
+        let config = DeflateConfig {
+            client_no_context_takeover: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            negotiate(&config, MAX_MESSAGE_SIZE, &headers("permessage-deflate"))
+                .unwrap()
+                .header_value(),
+            "permessage-deflate; client_no_context_takeover"
+        );
+    }
+
+    #[test]
+    fn deflate_inflate_roundtrip() {
+        let config = DeflateConfig::default();
+        let params = negotiate(&config, MAX_MESSAGE_SIZE, &headers("permessage-deflate")).unwrap();
+        let mut server = DeflateState::new(params.clone());
+        let mut client = DeflateState::new(params);
+        let data = "hello world ".repeat(100);
+        for _ in 0..3 {
+            let compressed = server.deflate(data.as_bytes()).unwrap();
+            assert!(compressed.len() < data.len());
+            assert_eq!(client.inflate(compressed).unwrap(), data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn inflate_max_message_size() {
+        let config = DeflateConfig::default();
+        let params = negotiate(&config, 1000, &headers("permessage-deflate")).unwrap();
+        let mut server = DeflateState::new(params.clone());
+        let mut client = DeflateState::new(params);
+
+        let data = "a".repeat(1000);
+        let compressed = server.deflate(data.as_bytes()).unwrap();
+        assert_eq!(client.inflate(compressed).unwrap(), data.as_bytes());
+
+        let data = "a".repeat(1 << 20);
+        let compressed = server.deflate(data.as_bytes()).unwrap();
+        let err = client.inflate(compressed).unwrap_err();
+        assert!(err.get_ref().unwrap().is::<MessageTooLarge>());
+    }
+
+    #[test]
+    fn read_frame_max_size() {
+        let config = DeflateConfig::default();
+        let params = negotiate(&config, 1000, &headers("permessage-deflate")).unwrap();
+        let mut state = DeflateState::new(params);
+        let max = state.params.max_message_size;
+
+        // Only the header of an uncompressed frame is needed to reject it
+        FrameHeader::write(&mut state.rd_raw, false, OP_BINARY, Some([0; 4]), 1001);
+        assert!(next_frame(&mut state.rd_raw, max).is_err());
+
+        let mut buf = BytesMut::new();
+        FrameHeader::write(&mut buf, false, OP_BINARY, Some([0; 4]), 1000);
+        assert!(next_frame(&mut buf, max).unwrap().is_none());
+        buf.extend_from_slice(&[0; 1000]);
+        assert!(next_frame(&mut buf, max).unwrap().is_some());
+    }
+
+    #[test]
+    fn write_frames() {
+        let config = DeflateConfig {
+            threshold: 10,
+            ..Default::default()
+        };
+        let params = negotiate(&config, MAX_MESSAGE_SIZE, &headers("permessage-deflate")).unwrap();
+        let mut state = DeflateState::new(params);
+
+        // Small frame is not compressed
+        let mut frame = BytesMut::new();
+        FrameHeader::write(&mut frame, false, OP_TEXT, None, 3);
+        frame.extend_from_slice(b"4hi");
+        state.wr_raw.extend_from_slice(&frame);
+        // Large frame is compressed
+        let data = "4".repeat(100);
+        FrameHeader::write(&mut state.wr_raw, false, OP_TEXT, None, data.len());
+        state.wr_raw.extend_from_slice(data.as_bytes());
+
+        while let Some((header, frame)) = next_frame(&mut state.wr_raw, MAX_MESSAGE_SIZE).unwrap() {
+            state.on_write_frame(header, frame).unwrap();
+        }
+        let (header, small) = next_frame(&mut state.wr_out, MAX_MESSAGE_SIZE)
+            .unwrap()
+            .unwrap();
+        assert!(!header.rsv1);
+        assert_eq!(small, frame);
+        let (header, large) = next_frame(&mut state.wr_out, MAX_MESSAGE_SIZE)
+            .unwrap()
+            .unwrap();
+        assert!(header.rsv1 && header.fin);
+        let payload = large[header.header_len..].to_vec();
+        assert_eq!(state.inflate(payload).unwrap(), data.as_bytes());
+    }
+}
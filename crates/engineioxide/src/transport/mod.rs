@@ -1,4 +1,8 @@
 //! All transports modules available in engineioxide
 
+#[cfg(feature = "ws-deflate")]
+pub mod deflate;
 pub mod polling;
+#[cfg(feature = "webtransport")]
+pub mod webtransport;
 pub mod ws;
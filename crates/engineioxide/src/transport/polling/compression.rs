@@ -0,0 +1,132 @@
+//! Compression of polling payloads according to the `Accept-Encoding` request header.
+
+use std::io::{self, Write};
+
+use bytes::Bytes;
+use flate2::{
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use http::{HeaderMap, HeaderValue};
+
+/// Above this size in bytes, payloads are compressed on the blocking thread pool
+/// rather than on the runtime threads.
+pub const BLOCKING_THRESHOLD: usize = 64 * 1024;
+
+/// A content encoding supported by the polling transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    /// The `Content-Encoding` header value.
+    pub fn header_value(self) -> HeaderValue {
+        match self {
+            Encoding::Gzip => HeaderValue::from_static("gzip"),
+            Encoding::Deflate => HeaderValue::from_static("deflate"),
+        }
+    }
+
+    /// Compress the data chunks with the given compression level.
+    pub fn compress(self, chunks: &[Bytes], level: u32) -> io::Result<Bytes> {
+        let level = Compression::new(level.min(9));
+        let len: usize = chunks.iter().map(Bytes::len).sum();
+        let buf = Vec::with_capacity(len / 2);
+        let buf = match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(buf, level);
+                for chunk in chunks {
+                    encoder.write_all(chunk)?;
+                }
+                encoder.finish()?
+            }
+            Encoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(buf, level);
+                for chunk in chunks {
+                    encoder.write_all(chunk)?;
+                }
+                encoder.finish()?
+            }
+        };
+        Ok(buf.into())
+    }
+}
+
+/// Select the preferred encoding accepted by the client. Gzip is preferred over deflate.
+pub fn negotiate(headers: &HeaderMap) -> Option<Encoding> {
+    let mut selected = None;
+    let encodings = headers
+        .get_all(http::header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for encoding in encodings {
+        let mut parts = encoding.split(';').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        // An encoding with a zero quality value is not acceptable
+        let refused = parts
+            .filter_map(|p| p.strip_prefix("q="))
+            .any(|q| q.parse::<f32>().is_ok_and(|q| q <= 0.0));
+        if refused {
+            continue;
+        }
+        match name {
+            n if n.eq_ignore_ascii_case("gzip") || n == "*" => return Some(Encoding::Gzip),
+            n if n.eq_ignore_ascii_case("deflate") => selected = Some(Encoding::Deflate),
+            _ => (),
+        }
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::{GzDecoder, ZlibDecoder};
+
+    use super::*;
+
+    fn headers(accept: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::ACCEPT_ENCODING, accept.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn negotiate_encoding() {
+        assert_eq!(negotiate(&headers("gzip")), Some(Encoding::Gzip));
+        assert_eq!(negotiate(&headers("deflate, gzip")), Some(Encoding::Gzip));
+        assert_eq!(negotiate(&headers("deflate, br")), Some(Encoding::Deflate));
+        assert_eq!(
+            negotiate(&headers("gzip;q=0, deflate")),
+            Some(Encoding::Deflate)
+        );
+        assert_eq!(negotiate(&headers("*")), Some(Encoding::Gzip));
+        assert_eq!(negotiate(&headers("br, identity")), None);
+        assert_eq!(negotiate(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn compress_roundtrip() {
+        let data = "4hello world\x1e".repeat(100);
+        let chunks = [Bytes::from(data.clone()), Bytes::from_static(b"4end")];
+        let data = data + "4end";
+
+        let compressed = Encoding::Gzip.compress(&chunks, 6).unwrap();
+        let mut out = String::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, data);
+
+        let compressed = Encoding::Deflate.compress(&chunks, 6).unwrap();
+        let mut out = String::new();
+        ZlibDecoder::new(&compressed[..])
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, data);
+    }
+}
@@ -0,0 +1,125 @@
+//! JSONP polling used by engine.io v3 clients that can use neither XHR nor websockets.
+//!
+//! The client loads each poll response as a `<script>` calling its `___eio[j]` callback,
+//! where `j` is the index given in the query. Packets are posted through a form with the
+//! payload in the `d` field. Binary packets are always base64 encoded.
+use bytes::Bytes;
+use http::{Response, StatusCode};
+use http_body_util::Full;
+
+use crate::{body::ResponseBody, errors::Error};
+
+/// Create a response calling the `___eio[index]` callback of the client with the payload.
+pub(crate) fn response<B>(
+    index: &str,
+    payload: &[u8],
+) -> Result<Response<ResponseBody<B>>, http::Error> {
+    use http::header::*;
+    let body = Bytes::from(wrap(index, payload));
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_LENGTH, body.len())
+        .header(CONTENT_TYPE, "text/javascript; charset=UTF-8")
+        .body(ResponseBody::custom_response(Full::new(body)))
+}
+
+/// Wrap the payload into a call to the `___eio[index]` callback.
+///
+/// The payload is escaped as a JSON string. U+2028 and U+2029 are also escaped because
+/// they are valid in JSON strings but not in javascript string literals.
+fn wrap(index: &str, payload: &[u8]) -> String {
+    let payload = String::from_utf8_lossy(payload);
+    let js = serde_json::to_string(&payload)
+        .expect("a string is always serializable")
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029");
+    format!("___eio[{index}]({js});")
+}
+
+/// Extract the payload from the `d` field of the url encoded form posted by the client.
+///
+/// The client escapes the newlines of the payload as `\n` and the `\n` sequences as `\\n`
+/// so that they are preserved by the textarea of the form.
+pub(crate) fn decode(body: &[u8]) -> Result<Bytes, Error> {
+    let data = body
+        .split(|&b| b == b'&')
+        .find_map(|field| field.strip_prefix(b"d="))
+        .ok_or(Error::HttpErrorResponse(StatusCode::BAD_REQUEST))?;
+    let data = percent_decode(data)?;
+
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        match &data[i..] {
+            [b'\\', b'\\', b'n', ..] => {
+                out.extend_from_slice(b"\\n");
+                i += 3;
+            }
+            [b'\\', b'n', ..] => {
+                out.push(b'\n');
+                i += 2;
+            }
+            [b, ..] => {
+                out.push(*b);
+                i += 1;
+            }
+            [] => unreachable!(),
+        }
+    }
+    Ok(out.into())
+}
+
+/// Decode a `application/x-www-form-urlencoded` value.
+fn percent_decode(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let hex = |b: u8| match b {
+        b'0'..=b'9' => Ok(b - b'0'),
+        b'a'..=b'f' => Ok(b - b'a' + 10),
+        b'A'..=b'F' => Ok(b - b'A' + 10),
+        _ => Err(Error::HttpErrorResponse(StatusCode::BAD_REQUEST)),
+    };
+    let mut out = Vec::with_capacity(data.len());
+    let mut iter = data.iter();
+    while let Some(&b) = iter.next() {
+        match b {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let (Some(&h), Some(&l)) = (iter.next(), iter.next()) else {
+                    return Err(Error::HttpErrorResponse(StatusCode::BAD_REQUEST));
+                };
+                out.push(hex(h)? << 4 | hex(l)?);
+            }
+            b => out.push(b),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_payload() {
+        assert_eq!(wrap("0", b"6:4hello"), r#"___eio[0]("6:4hello");"#);
+        assert_eq!(
+            wrap("12", "7:4\"a\"\n\u{2028}".as_bytes()),
+            r#"___eio[12]("7:4\"a\"\n\u2028");"#
+        );
+    }
+
+    #[test]
+    fn decode_form() {
+        assert_eq!(decode(b"d=6%3A4hello").unwrap(), "6:4hello");
+        assert_eq!(decode(b"d=8:4hello+w").unwrap(), "8:4hello w");
+        assert_eq!(decode(b"foo=bar&d=3:4%C3%A9").unwrap(), "3:4é");
+        assert_eq!(decode(br"d=4:4a\nb").unwrap(), "4:4a\nb");
+        assert_eq!(decode(br"d=5:4a\\nb").unwrap(), r"5:4a\nb");
+    }
+
+    #[test]
+    fn decode_form_err() {
+        assert!(decode(b"e=6:4hello").is_err());
+        assert!(decode(b"d=6:4hello%3").is_err());
+        assert!(decode(b"d=6:4hello%zz").is_err());
+    }
+}
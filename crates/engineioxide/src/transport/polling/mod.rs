@@ -1,25 +1,37 @@
 //! The polling transport module handles polling, post and init requests
 use std::sync::Arc;
 
-use bytes::Bytes;
+use bytes::{Buf, Bytes, BytesMut};
+use futures_core::Stream;
 use futures_util::StreamExt;
 use http::{Request, Response, StatusCode};
 use http_body::Body;
-use http_body_util::Full;
+use http_body_util::{BodyStream, Full};
 
 use crate::{
     body::ResponseBody,
     engine::EngineIo,
     errors::Error,
     handler::EngineIoHandler,
+    handoff::{HandoffRequest, HandoffResponse},
     packet::{OpenPacket, Packet},
     service::{ProtocolVersion, TransportType},
     sid::Sid,
+    socket::Socket,
     transport::polling::payload::Payload,
     DisconnectReason,
 };
 
+#[cfg(feature = "http-compression")]
+mod compression;
+#[cfg(feature = "v3")]
+mod jsonp;
 mod payload;
+#[cfg(feature = "__test_harness")]
+pub use payload::{decode_payload, encode_packets};
+
+#[cfg(feature = "http-compression")]
+pub use compression::{negotiate as negotiate_encoding, Encoding};
 
 /// Create a response for http request
 fn http_response<B, D>(
@@ -43,11 +55,29 @@ where
     .body(ResponseBody::custom_response(Full::new(body)))
 }
 
+/// Create a response for http polling request with a body sent chunk by chunk
+fn http_chunked_response<B>(
+    code: StatusCode,
+    chunks: Vec<Bytes>,
+    is_binary: bool,
+) -> Result<Response<ResponseBody<B>>, http::Error> {
+    use http::header::*;
+    let len: usize = chunks.iter().map(Bytes::len).sum();
+    let res = Response::builder().status(code).header(CONTENT_LENGTH, len);
+    if is_binary {
+        res.header(CONTENT_TYPE, "application/octet-stream")
+    } else {
+        res.header(CONTENT_TYPE, "text/plain; charset=UTF-8")
+    }
+    .body(ResponseBody::chunked_response(chunks))
+}
+
 pub fn open_req<H, B, R>(
     engine: Arc<EngineIo<H>>,
     protocol: ProtocolVersion,
     req: Request<R>,
     #[cfg(feature = "v3")] supports_binary: bool,
+    #[cfg(feature = "v3")] jsonp: Option<String>,
 ) -> Result<Response<ResponseBody<B>>, Error>
 where
     H: EngineIoHandler,
@@ -57,13 +87,14 @@ where
         protocol,
         TransportType::Polling,
         req.into_parts().0,
+        // JSONP clients cannot receive binary payloads
         #[cfg(feature = "v3")]
-        supports_binary,
-    );
+        (supports_binary && jsonp.is_none()),
+    )?;
 
     let packet = OpenPacket::new(TransportType::Polling, socket.id, &engine.config);
 
-    socket.spawn_heartbeat(engine.config.ping_interval, engine.config.ping_timeout);
+    socket.spawn_heartbeat();
 
     let packet: String = Packet::Open(packet).into();
     let packet = {
@@ -80,6 +111,10 @@ where
         #[cfg(not(feature = "v3"))]
         packet
     };
+    #[cfg(feature = "v3")]
+    if let Some(index) = jsonp {
+        return jsonp::response(&index, packet.as_bytes()).map_err(Error::Http);
+    }
     http_response(StatusCode::OK, packet, false).map_err(Error::Http)
 }
 
@@ -91,40 +126,75 @@ pub async fn polling_req<B, H>(
     engine: Arc<EngineIo<H>>,
     protocol: ProtocolVersion,
     sid: Sid,
+    #[cfg(feature = "http-compression")] encoding: Option<Encoding>,
+    #[cfg(feature = "v3")] jsonp: Option<String>,
 ) -> Result<Response<ResponseBody<B>>, Error>
 where
     B: Send + 'static,
     H: EngineIoHandler,
 {
-    let socket = engine.get_socket(sid).ok_or(Error::UnknownSessionID(sid))?;
+    let Some(socket) = engine.get_socket(sid) else {
+        let timeout = engine.config.ping_interval;
+        let req = HandoffRequest::Poll { timeout };
+        #[cfg(feature = "v3")]
+        if let Some(index) = jsonp {
+            let res = forward_req(&engine, sid, req).await?;
+            return match res.status {
+                StatusCode::OK => Ok(jsonp::response(&index, &res.data)?),
+                status => Ok(http_response(status, res.data, res.is_binary)?),
+            };
+        }
+        return forward(&engine, sid, req).await;
+    };
     if !socket.is_http() {
         return Err(Error::TransportMismatch);
     }
 
-    // If the socket is already locked, it means that the socket is being used by another request
-    // In case of multiple http polling, session should be closed
-    let rx = match socket.internal_rx.try_lock() {
-        Ok(s) => s,
-        Err(_) => {
-            socket.close(DisconnectReason::MultipleHttpPollingError);
-            return Err(Error::HttpErrorResponse(StatusCode::BAD_REQUEST));
-        }
-    };
-
-    #[cfg(feature = "tracing")]
-    tracing::debug!("[sid={sid}] polling request");
-
-    let max_payload = engine.config.max_payload;
+    let Payload { data, has_binary } =
+        encode_payload(&socket, protocol, engine.config.max_payload).await?;
 
     #[cfg(feature = "v3")]
-    let Payload { data, has_binary } =
-        payload::encoder(rx, protocol, socket.supports_binary, max_payload).await?;
-    #[cfg(not(feature = "v3"))]
-    let Payload { data, has_binary } = payload::encoder(rx, protocol, max_payload).await?;
+    if let Some(index) = jsonp {
+        return Ok(jsonp::response(&index, &data.concat())?);
+    }
 
-    #[cfg(feature = "tracing")]
-    tracing::debug!("[sid={sid}] sending data: {:?}", data);
-    Ok(http_response(StatusCode::OK, data, has_binary)?)
+    #[cfg(feature = "http-compression")]
+    if let Some(config) = &engine.config.http_compression {
+        let len = data.iter().map(Bytes::len).sum::<usize>();
+        let mut res = match encoding {
+            Some(encoding) if len >= config.threshold => {
+                let level = config.level;
+                let compressed = if len >= compression::BLOCKING_THRESHOLD {
+                    // Large payloads are compressed off the runtime threads
+                    engine
+                        .config
+                        .runtime
+                        .run_blocking(move || encoding.compress(&data, level))
+                        .await
+                        .ok_or_else(|| std::io::Error::other("compression task panicked"))?
+                } else {
+                    encoding.compress(&data, level)
+                };
+                let data = compressed.map_err(|e| {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("[sid={sid}] error compressing payload: {e}");
+                    Error::Io(e)
+                })?;
+                let mut res = http_response(StatusCode::OK, data, has_binary)?;
+                res.headers_mut()
+                    .insert(http::header::CONTENT_ENCODING, encoding.header_value());
+                res
+            }
+            _ => http_chunked_response(StatusCode::OK, data, has_binary)?,
+        };
+        // The payload is compressed depending on the accepted encodings of the request
+        res.headers_mut().append(
+            http::header::VARY,
+            http::HeaderValue::from_static("Accept-Encoding"),
+        );
+        return Ok(res);
+    }
+    Ok(http_chunked_response(StatusCode::OK, data, has_binary)?)
 }
 
 /// Handle http polling post request
@@ -135,6 +205,7 @@ pub async fn post_req<R, B, H>(
     protocol: ProtocolVersion,
     sid: Sid,
     body: Request<R>,
+    #[cfg(feature = "v3")] jsonp: bool,
 ) -> Result<Response<ResponseBody<B>>, Error>
 where
     H: EngineIoHandler,
@@ -143,21 +214,110 @@ where
     <R as Body>::Data: Send,
     B: Send + 'static,
 {
-    let socket = engine.get_socket(sid).ok_or(Error::UnknownSessionID(sid))?;
+    let Some(socket) = engine.get_socket(sid) else {
+        let is_binary = is_binary_body(&body);
+        let data = collect_body(body.into_body(), engine.config.max_payload).await?;
+        #[cfg(feature = "v3")]
+        let data = if jsonp { jsonp::decode(&data)? } else { data };
+        return forward(&engine, sid, HandoffRequest::Post { data, is_binary }).await;
+    };
     if !socket.is_http() {
         return Err(Error::TransportMismatch);
     }
 
+    #[cfg(feature = "v3")]
+    if jsonp {
+        let data = collect_body(body.into_body(), engine.config.max_payload).await?;
+        let body = Request::new(Full::new(jsonp::decode(&data)?));
+        let packets = payload::decoder(body, protocol, engine.config.max_payload);
+        handle_packets(&engine.handler, &socket, packets).await?;
+        return Ok(http_response(StatusCode::OK, "ok", false)?);
+    }
+
     let packets = payload::decoder(body, protocol, engine.config.max_payload);
+    handle_packets(&engine.handler, &socket, packets).await?;
+    Ok(http_response(StatusCode::OK, "ok", false)?)
+}
+
+/// Wait for the next packets of the socket and encode them into a payload.
+///
+/// If the socket is already being polled by another request, the session is closed.
+async fn encode_payload<D>(
+    socket: &Socket<D>,
+    protocol: ProtocolVersion,
+    max_payload: u64,
+) -> Result<Payload, Error>
+where
+    D: Default + Send + Sync + 'static,
+{
+    #[cfg(feature = "tracing")]
+    let sid = socket.id;
+    // If the receiver is already taken, it means that the socket is being polled by another request
+    // In case of multiple http polling, session should be closed
+    let Some(mut rx) = socket.internal_rx.try_take() else {
+        socket.close(DisconnectReason::MultipleHttpPollingError);
+        return Err(Error::HttpErrorResponse(StatusCode::BAD_REQUEST));
+    };
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!("[sid={sid}] polling request");
+
+    #[cfg(feature = "v3")]
+    let encoder = payload::encoder(&mut rx, protocol, socket.supports_binary, max_payload);
+    #[cfg(not(feature = "v3"))]
+    let encoder = payload::encoder(&mut rx, protocol, max_payload);
+    #[cfg(feature = "tracing")]
+    let encoder = tracing::Instrument::instrument(
+        encoder,
+        tracing::debug_span!(parent: socket.span(), "engineio.flush"),
+    );
+    let payload = encoder.await?;
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!("[sid={sid}] sending data: {:?}", payload.data);
+    #[cfg(feature = "metrics")]
+    crate::metrics::polling_payload(payload.data.iter().map(Bytes::len).sum());
+    Ok(payload)
+}
+
+/// Handle the packets decoded from a polling post request.
+async fn handle_packets<H: EngineIoHandler>(
+    handler: &Arc<H>,
+    socket: &Arc<Socket<H::Data>>,
+    packets: impl Stream<Item = Result<Packet, Error>>,
+) -> Result<(), Error> {
+    #[cfg(feature = "tracing")]
+    let sid = socket.id;
     futures_util::pin_mut!(packets);
 
     while let Some(packet) = packets.next().await {
+        if let Ok(Packet::Message(_) | Packet::Binary(_) | Packet::BinaryV3(_)) = &packet {
+            match socket.acquire_rate_limit().await {
+                Ok(true) => (),
+                Ok(false) => continue,
+                Err(e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("[sid={sid}] rate limit exceeded");
+                    socket.close_session(DisconnectReason::RateLimitExceeded);
+                    return Err(e);
+                }
+            }
+        }
+        #[cfg(feature = "tracing")]
+        let _span = packet
+            .as_ref()
+            .ok()
+            .map(|p| socket.packet_span(p.kind()).entered());
+        #[cfg(feature = "metrics")]
+        if let Ok(packet) = &packet {
+            crate::metrics::packet_received(packet);
+        }
         match packet {
             Ok(Packet::Close) => {
                 #[cfg(feature = "tracing")]
                 tracing::debug!("[sid={sid}] closing session");
                 socket.send(Packet::Noop)?;
-                engine.close_session(sid, DisconnectReason::TransportClose);
+                socket.close_session(DisconnectReason::TransportClose);
                 break;
             }
             Ok(Packet::Pong | Packet::Ping) => socket
@@ -165,11 +325,11 @@ where
                 .try_send(())
                 .map_err(|_| Error::HeartbeatTimeout),
             Ok(Packet::Message(msg)) => {
-                engine.handler.on_message(msg, socket.clone());
+                handler.on_message(msg, socket.clone());
                 Ok(())
             }
             Ok(Packet::Binary(bin) | Packet::BinaryV3(bin)) => {
-                engine.handler.on_binary(bin, socket.clone());
+                handler.on_binary(bin, socket.clone());
                 Ok(())
             }
             Ok(p) => {
@@ -180,10 +340,121 @@ where
             Err(e) => {
                 #[cfg(feature = "tracing")]
                 tracing::debug!("[sid={sid}] error parsing packet: {:?}", e);
-                engine.close_session(sid, DisconnectReason::PacketParsingError);
+                socket.close_session(DisconnectReason::PacketParsingError);
                 return Err(e);
             }
         }?;
     }
-    Ok(http_response(StatusCode::OK, "ok", false)?)
+    Ok(())
+}
+
+/// Forward a polling request for a session that is not open on this server
+/// with the [`EngineIoHandler::forward_polling`] fn.
+async fn forward<H, B>(
+    engine: &EngineIo<H>,
+    sid: Sid,
+    req: HandoffRequest,
+) -> Result<Response<ResponseBody<B>>, Error>
+where
+    H: EngineIoHandler,
+    B: Send + 'static,
+{
+    let res = forward_req(engine, sid, req).await?;
+    Ok(http_response(res.status, res.data, res.is_binary)?)
+}
+
+/// Forward a polling request with the [`EngineIoHandler::forward_polling`] fn
+/// and return the raw response of the server owning the session.
+async fn forward_req<H: EngineIoHandler>(
+    engine: &EngineIo<H>,
+    sid: Sid,
+    req: HandoffRequest,
+) -> Result<HandoffResponse, Error> {
+    #[cfg(feature = "tracing")]
+    tracing::debug!("[sid={sid}] forwarding polling request for unknown session");
+    engine
+        .handler
+        .forward_polling(sid, req)
+        .await
+        .ok_or(Error::UnknownSessionID(sid))
+}
+
+/// Handle a [`HandoffRequest`] forwarded by another server for a session open on this server.
+pub(crate) async fn handoff<H: EngineIoHandler>(
+    handler: &Arc<H>,
+    socket: &Arc<Socket<H::Data>>,
+    req: HandoffRequest,
+) -> HandoffResponse {
+    let res = match req {
+        _ if !socket.is_http() => Err(Error::TransportMismatch),
+        HandoffRequest::Poll { timeout } => {
+            let encoder = encode_payload(socket, socket.protocol, socket.max_payload);
+            futures_util::pin_mut!(encoder);
+            // Do not cancel the encoder on timeout as it may have already consumed packets,
+            // instead a noop packet is sent to flush it.
+            let payload = match socket.runtime.timeout(timeout, encoder.as_mut()).await {
+                Ok(payload) => payload,
+                Err(_) => {
+                    socket.send(Packet::Noop).ok();
+                    encoder.await
+                }
+            };
+            payload.map(|p| HandoffResponse::ok(p.data.concat(), p.has_binary))
+        }
+        HandoffRequest::Post { data, is_binary } => {
+            let mut body = Request::new(Full::new(data));
+            if is_binary {
+                body.headers_mut().insert(
+                    http::header::CONTENT_TYPE,
+                    http::HeaderValue::from_static("application/octet-stream"),
+                );
+            }
+            let packets = payload::decoder(body, socket.protocol, socket.max_payload);
+            handle_packets(handler, socket, packets)
+                .await
+                .map(|_| HandoffResponse::ok("ok", false))
+        }
+    };
+    res.unwrap_or_else(|e| {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("[sid={}] handoff error: {e:?}", socket.id);
+        let res: Response<ResponseBody<()>> = e.into();
+        HandoffResponse::error(res.status())
+    })
+}
+
+/// If the body of the request is a binary payload (engine.io v3 protocol).
+fn is_binary_body<R>(req: &Request<R>) -> bool {
+    req.headers().get(http::header::CONTENT_TYPE)
+        == Some(&http::HeaderValue::from_static("application/octet-stream"))
+}
+
+/// Collect the body of a request to forward it.
+/// Returns an error if the body exceeds the maximum allowed payload size.
+async fn collect_body<R>(body: R, max_payload: u64) -> Result<Bytes, Error>
+where
+    R: Body + Unpin,
+    <R as Body>::Error: std::fmt::Debug,
+{
+    let mut body = BodyStream::new(body);
+    let mut data = BytesMut::new();
+    while let Some(frame) = body.next().await {
+        let frame = frame.map_err(|_e| {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("error reading body stream: {:?}", _e);
+            Error::HttpErrorResponse(StatusCode::BAD_REQUEST)
+        })?;
+        if let Ok(mut chunk) = frame.into_data() {
+            if (data.len() + chunk.remaining()) as u64 > max_payload {
+                return Err(Error::PayloadTooLarge);
+            }
+            while chunk.has_remaining() {
+                let bytes = chunk.chunk();
+                data.extend_from_slice(bytes);
+                let len = bytes.len();
+                chunk.advance(len);
+            }
+        }
+    }
+    Ok(data.freeze())
 }
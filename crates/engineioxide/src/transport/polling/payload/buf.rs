@@ -92,6 +92,41 @@ impl<T: Buf> Buf for BufList<T> {
     }
 }
 
+/// A pool of reusable [`BytesMut`] buffers.
+///
+/// The data written in a buffer is split and frozen into [`Bytes`] that share its allocation.
+/// Once all these [`Bytes`] are dropped, the allocation is reclaimed the next time the buffer is
+/// taken from the pool rather than allocating a new one.
+pub(crate) struct BufPool {
+    bufs: std::sync::Mutex<Vec<BytesMut>>,
+    max_bufs: usize,
+}
+
+impl BufPool {
+    pub(crate) const fn new(max_bufs: usize) -> Self {
+        Self {
+            bufs: std::sync::Mutex::new(Vec::new()),
+            max_bufs,
+        }
+    }
+
+    /// Take a buffer from the pool with at least `capacity` bytes available.
+    pub(crate) fn take(&self, capacity: usize) -> BytesMut {
+        let mut buf = self.bufs.lock().unwrap().pop().unwrap_or_default();
+        buf.reserve(capacity);
+        buf
+    }
+
+    /// Give a buffer back to the pool. It is dropped if the pool is full.
+    pub(crate) fn put(&self, mut buf: BytesMut) {
+        buf.clear();
+        let mut bufs = self.bufs.lock().unwrap();
+        if bufs.len() < self.max_bufs {
+            bufs.push(buf);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::ptr;
@@ -143,6 +178,26 @@ mod tests {
         assert_eq!(buf.chunk(), b" World");
     }
 
+    #[test]
+    fn pool_reuse_allocation() {
+        let pool = BufPool::new(1);
+        let mut buf = pool.take(64);
+        buf.put_slice(b"Hello World");
+        let ptr = buf.as_ptr();
+        let data = buf.split().freeze();
+        pool.put(buf);
+        drop(data);
+
+        let buf = pool.take(64);
+        assert!(ptr::eq(ptr, buf.as_ptr()));
+        assert!(buf.capacity() >= 64);
+
+        // The pool is full
+        pool.put(BytesMut::new());
+        pool.put(buf);
+        assert_eq!(pool.bufs.lock().unwrap().len(), 1);
+    }
+
     #[test]
     #[should_panic(expected = "`len` greater than remaining")]
     fn buf_to_bytes_too_many() {
@@ -64,7 +64,10 @@ where
     }?;
     if state.current_payload_size + (data.remaining() as u64) <= max_payload {
         state.current_payload_size += data.remaining() as u64;
-        state.buffer.push(data);
+        // Empty data frames (e.g. with http2) would block the reads of the buffer
+        if data.has_remaining() {
+            state.buffer.push(data);
+        }
         Ok(())
     } else {
         Err(Error::PayloadTooLarge)
@@ -180,14 +183,13 @@ where
                         break Some((Err(Error::InvalidPacketLength), state));
                     }
 
-                    let size_str = &packet_buf[1..]
-                        .iter()
-                        .map(|b| (b + 48) as char)
-                        .collect::<String>();
-                    if let Ok(size) = size_str.parse() {
-                        packet_size = size;
-                    } else {
-                        break Some((Err(Error::InvalidPacketLength), state));
+                    // The packet size is encoded with one byte per decimal digit (e.g. [1, 0] for 10)
+                    let size = packet_buf[1..].iter().try_fold(0u64, |size, &digit| {
+                        (digit <= 9).then_some(size * 10 + digit as u64)
+                    });
+                    match size {
+                        Some(size) if size > 0 => packet_size = size,
+                        _ => break Some((Err(Error::InvalidPacketLength), state)),
                     }
                     packet_buf.clear();
                 }
@@ -205,12 +207,15 @@ where
                 let packet = match packet_type.unwrap() {
                     STRING_PACKET_IDENTIFIER_V3 => String::from_utf8(packet_buf)
                         .map_err(|_| Error::InvalidPacketLength)
-                        .and_then(Packet::try_from), // Convert the packet buffer to a Packet object
+                        .and_then(|packet| Packet::try_from_v3(packet.into())), // Convert the packet buffer to a Packet object
                     BINARY_PACKET_IDENTIFIER_V3 => Ok(Packet::BinaryV3(packet_buf.into())),
                     _ => Err(Error::InvalidPacketLength),
                 };
 
                 break Some((packet, state));
+            } else if state.end_of_stream && packet_type.is_some() {
+                // The stream ended before the end of the packet
+                break Some((Err(Error::InvalidPacketLength), state));
             } else if state.end_of_stream && state.buffer.remaining() == 0 {
                 break None;
             }
@@ -224,9 +229,8 @@ pub fn v3_string_decoder(
     max_payload: u64,
 ) -> impl Stream<Item = Result<Packet, Error>> {
     use std::io::ErrorKind;
-    use unicode_segmentation::UnicodeSegmentation;
 
-    use crate::transport::polling::payload::STRING_PACKET_SEPARATOR_V3;
+    use crate::transport::polling::payload::{utf16_len, STRING_PACKET_SEPARATOR_V3};
 
     #[cfg(feature = "tracing")]
     tracing::debug!("decoding payload with v3 string decoder");
@@ -234,7 +238,9 @@ pub fn v3_string_decoder(
 
     futures_util::stream::unfold(state, move |mut state| async move {
         let mut packet_buf: Vec<u8> = Vec::new();
-        let mut packet_graphemes_len: usize = 0;
+        // The length of the packet and the length read, in utf16 code units
+        let mut packet_len: usize = 0;
+        let mut packet_read: usize = 0;
         loop {
             // Read data from the body stream into the buffer
             if !state.end_of_stream {
@@ -242,16 +248,18 @@ pub fn v3_string_decoder(
                     break Some((Err(e), state));
                 }
             }
-            if state.end_of_stream && state.buffer.remaining() == 0 && state.yield_packets > 0 {
-                break None; // Reached end of stream with no more data, end the stream
-            } else if state.end_of_stream && state.buffer.remaining() == 0 {
+            if state.end_of_stream && state.buffer.remaining() == 0 {
+                if state.yield_packets > 0 && packet_len == 0 && packet_buf.is_empty() {
+                    break None; // Reached end of stream with no more data, end the stream
+                }
+                // The stream is empty or ended in the middle of a packet
                 return Some((Err(Error::InvalidPacketLength), state));
             }
 
             let mut reader = (&mut state.buffer).reader();
 
             // Read the packet length from the buffer
-            if packet_graphemes_len == 0 {
+            if packet_len == 0 {
                 loop {
                     let (done, used) = {
                         let remaining = reader.get_ref().remaining();
@@ -266,7 +274,7 @@ pub fn v3_string_decoder(
                         match memchr::memchr(STRING_PACKET_SEPARATOR_V3, &packet_buf) {
                             Some(i) => {
                                 // Extract the packet length from the available data
-                                packet_graphemes_len = match std::str::from_utf8(&packet_buf[..i])
+                                packet_len = match std::str::from_utf8(&packet_buf[..i])
                                     .map_err(|_| Error::InvalidPacketLength)
                                     .and_then(|s| {
                                         s.parse::<usize>().map_err(|_| Error::InvalidPacketLength)
@@ -291,7 +299,7 @@ pub fn v3_string_decoder(
                 }
             }
 
-            if packet_graphemes_len == 0 {
+            if packet_len == 0 {
                 continue; // No packet length, continue to read more data
             }
 
@@ -299,50 +307,39 @@ pub fn v3_string_decoder(
 
             // Read the next chunk of data from the chunk list
             let data: &[u8] = reader.fill_buf().unwrap();
-
-            let old_len = packet_buf.len();
-            packet_buf.extend_from_slice(data);
-            let byte_read = match std::str::from_utf8(&packet_buf) {
-                Ok(fulldata) => {
-                    let i = fulldata
-                        .grapheme_indices(true)
-                        .nth(packet_graphemes_len)
-                        .map(|(i, _)| i);
-                    if let Some(i) = i {
-                        packet_buf.truncate(i);
-                        packet_buf.len() - old_len
-                    } else {
-                        data.len()
-                    }
+            let mut end = None;
+            for (i, &byte) in data.iter().enumerate() {
+                let len = utf16_len(byte);
+                // The packet ends before the start of the next char
+                if len > 0 && packet_read == packet_len {
+                    end = Some(i);
+                    break;
                 }
-                Err(e) => {
-                    let chunk =
-                        unsafe { std::str::from_utf8_unchecked(&packet_buf[..e.valid_up_to()]) };
-                    let i = chunk
-                        .grapheme_indices(true)
-                        .nth(packet_graphemes_len)
-                        .map(|(i, _)| i);
-                    if let Some(i) = i {
-                        packet_buf.truncate(i);
-                        packet_buf.len() - old_len
-                    } else {
-                        data.len()
-                    }
-                }
-            };
+                packet_read += len;
+            }
+            let byte_read = end.unwrap_or(data.len());
+            packet_buf.extend_from_slice(&data[..byte_read]);
             reader.consume(byte_read);
 
-            // Check if the packet length matches the number of characters
-            if let Ok(packet) = std::str::from_utf8(&packet_buf) {
-                if packet.graphemes(true).count() == packet_graphemes_len {
-                    // SAFETY: packet_buf is a valid utf8 string checkd above
-                    let packet = unsafe { String::from_utf8_unchecked(packet_buf) };
-                    let packet = Packet::try_from(packet).map_err(|_| Error::InvalidPacketLength);
-                    state.yield_packets += 1;
-                    break Some((packet, state)); // Emit the packet and the updated state
-                }
+            // The length ends in the middle of a surrogate pair
+            if packet_read > packet_len {
+                break Some((Err(Error::InvalidPacketLength), state));
+            }
+
+            // The packet is complete once its last char is complete
+            if packet_read == packet_len
+                && (end.is_some() || std::str::from_utf8(&packet_buf).is_ok())
+            {
+                let packet = String::from_utf8(packet_buf)
+                    .map_err(|_| Error::InvalidPacketLength)
+                    .and_then(|packet| {
+                        Packet::try_from_v3(packet.into()).map_err(|_| Error::InvalidPacketLength)
+                    });
+                state.yield_packets += 1;
+                break Some((packet, state)); // Emit the packet and the updated state
             } else if state.end_of_stream && state.buffer.remaining() == 0 {
-                break None; // Reached end of stream with no more data, end the stream
+                // The stream ended before the end of the packet
+                break Some((Err(Error::InvalidPacketLength), state));
             }
         }
     })
@@ -410,6 +407,23 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn binary_payload_v4() {
+        // The base64 data of the first packet starts with a '4' like a v3 binary packet
+        let data = Full::new(Bytes::from("b4BAI\x1ebAQID"));
+        let payload = v4_decoder(data, MAX_PAYLOAD);
+        futures_util::pin_mut!(payload);
+        assert_eq!(
+            payload.next().await.unwrap().unwrap(),
+            Packet::Binary(vec![224, 16, 8].into())
+        );
+        assert_eq!(
+            payload.next().await.unwrap().unwrap(),
+            Packet::Binary(vec![1, 2, 3].into())
+        );
+        assert!(payload.next().await.is_none());
+    }
+
     #[tokio::test]
     async fn max_payload_v4() {
         const DATA: &[u8] = "4foo\x1e4€f\x1e4fo".as_bytes();
@@ -430,8 +444,6 @@ mod tests {
     #[cfg(feature = "v3")]
     #[tokio::test]
     async fn string_payload_iterator_v3() {
-        assert!(cfg!(feature = "v3"));
-
         let data = Full::new(Bytes::from("4:4foo3:4€f11:4faaaaaaaaa"));
         let payload = v3_string_decoder(data, MAX_PAYLOAD);
         futures_util::pin_mut!(payload);
@@ -453,8 +465,6 @@ mod tests {
     #[cfg(feature = "v3")]
     #[tokio::test]
     async fn binary_payload_iterator_v3() {
-        assert!(cfg!(feature = "v3"));
-
         const PAYLOAD: &[u8] = &[
             0, 9, 255, 52, 104, 101, 108, 108, 111, 226, 130, 172, 1, 5, 255, 4, 1, 2, 3, 4,
         ];
@@ -476,7 +486,6 @@ mod tests {
     #[cfg(feature = "v3")]
     #[tokio::test]
     async fn string_payload_stream_v3() {
-        assert!(cfg!(feature = "v3"));
         const DATA: &[u8] = "4:4foo3:4€f11:4baaaaaaaar".as_bytes();
         for i in 1..DATA.len() {
             println!("payload stream v3 chunk size: {i}");
@@ -507,8 +516,6 @@ mod tests {
     #[cfg(feature = "v3")]
     #[tokio::test]
     async fn binary_payload_stream_v3() {
-        assert!(cfg!(feature = "v3"));
-
         const PAYLOAD: &[u8] = &[
             0, 9, 255, 52, 104, 101, 108, 108, 111, 226, 130, 172, 1, 5, 255, 4, 1, 2, 3, 4,
         ];
@@ -536,10 +543,127 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn empty_frames_v4() {
+        let frames = ["", "4foo\x1e", "", "4bar", ""];
+        let stream = StreamBody::new(futures_util::stream::iter(
+            frames
+                .into_iter()
+                .map(|frame| Frame::data(Bytes::from_static(frame.as_bytes())))
+                .map(Ok::<_, std::convert::Infallible>),
+        ));
+        let payload = v4_decoder(stream, MAX_PAYLOAD);
+        futures_util::pin_mut!(payload);
+        assert!(matches!(
+            payload.next().await.unwrap().unwrap(),
+            Packet::Message(msg) if msg == "foo"
+        ));
+        assert!(matches!(
+            payload.next().await.unwrap().unwrap(),
+            Packet::Message(msg) if msg == "bar"
+        ));
+        assert!(payload.next().await.is_none());
+    }
+
+    #[cfg(feature = "v3")]
+    #[tokio::test]
+    async fn invalid_binary_payload_v3() {
+        const PAYLOADS: &[&[u8]] = &[
+            // Invalid size digit
+            &[0, 208, 255, 52, 104],
+            // Empty and zero size
+            &[0, 255, 52, 104],
+            &[0, 0, 255, 52, 104],
+            // Truncated packet
+            &[0, 9, 255, 52, 104],
+            &[1, 5, 255],
+        ];
+        for data in PAYLOADS {
+            let payload = v3_binary_decoder(Full::new(Bytes::from_static(data)), MAX_PAYLOAD);
+            futures_util::pin_mut!(payload);
+            let packet = payload.next().await.unwrap();
+            assert!(
+                matches!(packet, Err(Error::InvalidPacketLength)),
+                "{data:?}"
+            );
+        }
+    }
+
+    #[cfg(feature = "v3")]
+    #[tokio::test]
+    async fn invalid_string_payload_v3() {
+        // The packet is followed by a split utf8 sequence instead of the next packet length
+        let frames: [&[u8]; 2] = [b"2:4a\xcb", b"\xaa"];
+        let stream = StreamBody::new(futures_util::stream::iter(
+            frames
+                .into_iter()
+                .map(|frame| Frame::data(Bytes::from_static(frame)))
+                .map(Ok::<_, std::convert::Infallible>),
+        ));
+        let payload = v3_string_decoder(stream, MAX_PAYLOAD);
+        futures_util::pin_mut!(payload);
+        let packet = payload.next().await.unwrap();
+        assert_eq!(packet.unwrap(), Packet::Message("a".into()));
+        let packet = payload.next().await.unwrap();
+        assert!(packet.is_err());
+    }
+
+    #[cfg(feature = "v3")]
+    #[tokio::test]
+    async fn utf16_length_payload_v3() {
+        // The length is in utf16 code units: 2 for astral chars and 1 for each combining char
+        const DATA: &[u8] = "3:4🦀3:4e\u{301}2:4a".as_bytes();
+        for i in 1..DATA.len() {
+            let stream = StreamBody::new(futures_util::stream::iter(
+                DATA.chunks(i)
+                    .map(Frame::data)
+                    .map(Ok::<_, std::convert::Infallible>),
+            ));
+            let payload = v3_string_decoder(stream, MAX_PAYLOAD);
+            futures_util::pin_mut!(payload);
+            let packets: Vec<_> = payload.map(Result::unwrap).collect().await;
+            assert_eq!(
+                packets,
+                [
+                    Packet::Message("🦀".into()),
+                    Packet::Message("e\u{301}".into()),
+                    Packet::Message("a".into()),
+                ]
+            );
+        }
+
+        // The length ends in the middle of a surrogate pair
+        let data = Full::new(Bytes::from("2:4🦀"));
+        let payload = v3_string_decoder(data, MAX_PAYLOAD);
+        futures_util::pin_mut!(payload);
+        let packet = payload.next().await.unwrap();
+        assert!(matches!(packet, Err(Error::InvalidPacketLength)));
+    }
+
+    #[cfg(feature = "v3")]
+    #[tokio::test]
+    async fn truncated_string_payload_v3() {
+        // The payload ends in the middle of the second packet
+        let data = Full::new(Bytes::from("2:4a5:4foo"));
+        let payload = v3_string_decoder(data, MAX_PAYLOAD);
+        futures_util::pin_mut!(payload);
+        let packet = payload.next().await.unwrap();
+        assert_eq!(packet.unwrap(), Packet::Message("a".into()));
+        let packet = payload.next().await.unwrap();
+        assert!(matches!(packet, Err(Error::InvalidPacketLength)));
+
+        // The payload ends after a packet length
+        let data = Full::new(Bytes::from("2:4a3:"));
+        let payload = v3_string_decoder(data, MAX_PAYLOAD);
+        futures_util::pin_mut!(payload);
+        payload.next().await.unwrap().unwrap();
+        let packet = payload.next().await.unwrap();
+        assert!(matches!(packet, Err(Error::InvalidPacketLength)));
+    }
+
     #[cfg(feature = "v3")]
     #[tokio::test]
     async fn max_payload_v3() {
-        assert!(cfg!(feature = "v3"));
         const DATA: &[u8] = "4:4foo3:4€f11:4baaaaaaaar".as_bytes();
         const MAX_PAYLOAD: u64 = 3;
         for i in 1..DATA.len() {
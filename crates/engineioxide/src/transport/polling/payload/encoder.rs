@@ -6,14 +6,25 @@
 //!    * string encoder (used when there is no binary packet or when the client does not support binary)
 //!    * binary encoder (used when there are binary packets and the client supports binary)
 //!
+//! Payloads are written into buffers taken from a shared [`BufPool`] and frozen into [`Bytes`]
+//! that are directly used as chunks of the response body.
 
-use tokio::sync::MutexGuard;
+use bytes::{BufMut, Bytes, BytesMut};
 
 use crate::{
-    errors::Error, packet::Packet, peekable::PeekableReceiver, socket::PacketBuf,
-    transport::polling::payload::Payload,
+    errors::Error,
+    packet::Packet,
+    peekable::PeekableReceiver,
+    socket::PacketBuf,
+    transport::polling::payload::{buf::BufPool, Payload},
 };
 
+/// The pool of buffers used to encode the payloads of all the sessions.
+static BUF_POOL: BufPool = BufPool::new(64);
+
+/// The capacity reserved in a pooled buffer before encoding a payload.
+const BUF_CAPACITY: usize = 4 * 1024;
+
 /// Try to immediately poll a new packet buf from the rx channel and check that the new packet can be added to the payload
 ///
 /// Manually close the channel if the packet is a close packet
@@ -25,7 +36,7 @@ use crate::{
 /// * `max_payload` - The maximum payload length
 /// * `b64` - If binary packets should be encoded in base64
 fn try_recv_packet(
-    rx: &mut MutexGuard<'_, PeekableReceiver<PacketBuf>>,
+    rx: &mut PeekableReceiver<PacketBuf>,
     payload_len: usize,
     max_payload: u64,
     b64: bool,
@@ -40,6 +51,10 @@ fn try_recv_packet(
     }
 
     let packets = rx.try_recv().ok();
+    #[cfg(feature = "metrics")]
+    if let Some(packets) = &packets {
+        crate::metrics::packets_sent(packets);
+    }
 
     if Some(&Packet::Close) == packets.as_ref().and_then(|p| p.first()) {
         #[cfg(feature = "tracing")]
@@ -55,10 +70,10 @@ fn try_recv_packet(
 
 /// Same as [`try_recv_packet`]
 /// but wait for a new packet if there is no packet in the buffer
-async fn recv_packet(
-    rx: &mut MutexGuard<'_, PeekableReceiver<PacketBuf>>,
-) -> Result<PacketBuf, Error> {
+async fn recv_packet(rx: &mut PeekableReceiver<PacketBuf>) -> Result<PacketBuf, Error> {
     let packet = rx.recv().await.ok_or(Error::Aborted)?;
+    #[cfg(feature = "metrics")]
+    crate::metrics::packets_sent(&packet);
     if Some(&Packet::Close) == packet.first() {
         #[cfg(feature = "tracing")]
         tracing::debug!("Received close packet, closing channel");
@@ -70,51 +85,107 @@ async fn recv_packet(
     Ok(packet)
 }
 
+/// Packets larger than this are not copied into the payload buffer
+/// and are sent as separate chunks of the response body.
+const CHUNK_THRESHOLD: usize = 16 * 1024;
+
+/// A string payload split into chunks.
+/// Small packets are concatenated in a pooled buffer while large ones are kept as separate chunks.
+/// The content of large messages is not copied.
+struct ChunkedPayload {
+    chunks: Vec<Bytes>,
+    buf: BytesMut,
+    /// The length of the chunks, without the current buffer
+    chunks_len: usize,
+}
+impl ChunkedPayload {
+    fn new() -> Self {
+        Self {
+            chunks: Vec::new(),
+            buf: BUF_POOL.take(BUF_CAPACITY),
+            chunks_len: 0,
+        }
+    }
+    fn push(&mut self, packet: Packet) {
+        match packet {
+            Packet::Message(msg) if msg.len() >= CHUNK_THRESHOLD => {
+                self.buf.put_u8(b'4');
+                self.flush();
+                self.chunks_len += msg.len();
+                self.chunks.push(msg.into());
+            }
+            packet if packet.get_size_hint(true) >= CHUNK_THRESHOLD => {
+                self.flush();
+                packet.encode(&mut self.buf);
+                self.flush();
+            }
+            packet => packet.encode(&mut self.buf),
+        }
+    }
+    fn push_separator(&mut self, separator: u8) {
+        self.buf.put_u8(separator);
+    }
+    fn flush(&mut self) {
+        if !self.buf.is_empty() {
+            self.chunks_len += self.buf.len();
+            self.chunks.push(self.buf.split().freeze());
+        }
+    }
+    fn len(&self) -> usize {
+        self.chunks_len + self.buf.len()
+    }
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn into_chunks(mut self) -> Vec<Bytes> {
+        self.flush();
+        BUF_POOL.put(self.buf);
+        self.chunks
+    }
+}
+
 /// Encode multiple packets into a string payload according to the
 /// [engine.io v4 protocol](https://socket.io/fr/docs/v4/engine-io-protocol/#http-long-polling-1)
 pub async fn v4_encoder(
-    mut rx: MutexGuard<'_, PeekableReceiver<PacketBuf>>,
+    rx: &mut PeekableReceiver<PacketBuf>,
     max_payload: u64,
 ) -> Result<Payload, Error> {
     use crate::transport::polling::payload::PACKET_SEPARATOR_V4;
 
     #[cfg(feature = "tracing")]
     tracing::debug!("encoding payload with v4 encoder");
-    let mut data: String = String::new();
+    let mut data = ChunkedPayload::new();
 
     // Send all packets in the buffer
     const PUNCTUATION_LEN: usize = 1;
-    while let Some(packets) =
-        try_recv_packet(&mut rx, data.len() + PUNCTUATION_LEN, max_payload, true)
-    {
+    while let Some(packets) = try_recv_packet(rx, data.len() + PUNCTUATION_LEN, max_payload, true) {
         for packet in packets {
-            let packet: String = packet.into();
-
             if !data.is_empty() {
-                data.push(std::char::from_u32(PACKET_SEPARATOR_V4 as u32).unwrap());
+                data.push_separator(PACKET_SEPARATOR_V4);
             }
-            data.push_str(&packet);
+            data.push(packet);
         }
     }
 
     // If there is no packet in the buffer, wait for the next packet
     if data.is_empty() {
-        let packets = recv_packet(&mut rx).await?;
+        let packets = recv_packet(rx).await?;
         for packet in packets {
-            let packet: String = packet.into();
-            data.push_str(&packet);
+            if !data.is_empty() {
+                data.push_separator(PACKET_SEPARATOR_V4);
+            }
+            data.push(packet);
         }
     }
 
-    Ok(Payload::new(data.into(), false))
+    Ok(Payload::new(data.into_chunks(), false))
 }
 
 /// Encode one packet into a *binary* payload according to the
 /// [engine.io v3 protocol](https://github.com/socketio/engine.io-protocol/tree/v3#payload)
 #[cfg(feature = "v3")]
-pub fn v3_bin_packet_encoder(packet: Packet, data: &mut bytes::BytesMut) -> Result<(), Error> {
+pub fn v3_bin_packet_encoder(packet: Packet, data: &mut BytesMut) -> Result<(), Error> {
     use crate::transport::polling::payload::BINARY_PACKET_SEPARATOR_V3;
-    use bytes::BufMut;
 
     let mut itoa = itoa::Buffer::new();
     match packet {
@@ -133,37 +204,49 @@ pub fn v3_bin_packet_encoder(packet: Packet, data: &mut bytes::BytesMut) -> Resu
             data.extend_from_slice(&bin); // raw data
         }
         packet => {
-            let packet: String = packet.into();
-            let len = itoa.format(packet.len());
-            let len_len = len.len(); // len is guaranteed to be ascii
-
-            data.reserve(1 + len_len + 1 + packet.len());
-
             data.put_u8(0x0); // 0 = string
-            for char in len.chars() {
-                data.put_u8(char as u8 - 48);
+            let start = data.len();
+            packet.encode(data);
+            let len = itoa.format(data.len() - start);
+
+            // The length digits followed by the separator, inserted before the packet
+            let mut prefix = [0u8; 21];
+            for (i, char) in len.bytes().enumerate() {
+                prefix[i] = char - b'0';
             }
-            data.put_u8(BINARY_PACKET_SEPARATOR_V3); // separator
-            data.extend_from_slice(packet.as_bytes()); // packet
+            prefix[len.len()] = BINARY_PACKET_SEPARATOR_V3;
+            insert_prefix(data, start, &prefix[..len.len() + 1]);
         }
     };
     Ok(())
 }
 
+/// Insert a prefix at the `start` index of the buffer by shifting the data after it.
+/// Shifting the encoded packet is cheaper than encoding it in an intermediate buffer.
+#[cfg(feature = "v3")]
+fn insert_prefix(data: &mut BytesMut, start: usize, prefix: &[u8]) {
+    let end = data.len();
+    data.put_slice(prefix);
+    data.copy_within(start..end, start + prefix.len());
+    data[start..start + prefix.len()].copy_from_slice(prefix);
+}
+
 /// Encode one packet into a *string* payload according to the
 /// [engine.io v3 protocol](https://github.com/socketio/engine.io-protocol/tree/v3#payload)
 #[cfg(feature = "v3")]
-pub fn v3_string_packet_encoder(packet: Packet, data: &mut bytes::BytesMut) -> Result<(), Error> {
-    use crate::transport::polling::payload::STRING_PACKET_SEPARATOR_V3;
-    use bytes::BufMut;
-    let packet: String = packet.into();
-    let packet = format!(
-        "{}{}{}",
-        packet.chars().count(),
-        STRING_PACKET_SEPARATOR_V3 as char,
-        packet
-    );
-    data.put_slice(packet.as_bytes());
+pub fn v3_string_packet_encoder(packet: Packet, data: &mut BytesMut) -> Result<(), Error> {
+    use crate::transport::polling::payload::{utf16_len, STRING_PACKET_SEPARATOR_V3};
+
+    let start = data.len();
+    packet.encode(data);
+    let utf16_len: usize = data[start..].iter().map(|&b| utf16_len(b)).sum();
+
+    let mut itoa = itoa::Buffer::new();
+    let len = itoa.format(utf16_len);
+    let mut prefix = [0u8; 21];
+    prefix[..len.len()].copy_from_slice(len.as_bytes());
+    prefix[len.len()] = STRING_PACKET_SEPARATOR_V3;
+    insert_prefix(data, start, &prefix[..len.len() + 1]);
     Ok(())
 }
 
@@ -171,10 +254,10 @@ pub fn v3_string_packet_encoder(packet: Packet, data: &mut bytes::BytesMut) -> R
 /// according to the [engine.io v3 protocol](https://github.com/socketio/engine.io-protocol/tree/v3#payload)
 #[cfg(feature = "v3")]
 pub async fn v3_binary_encoder(
-    mut rx: MutexGuard<'_, PeekableReceiver<PacketBuf>>,
+    rx: &mut PeekableReceiver<PacketBuf>,
     max_payload: u64,
 ) -> Result<Payload, Error> {
-    let mut data = bytes::BytesMut::new();
+    let mut data = BUF_POOL.take(BUF_CAPACITY);
     let mut packet_buffer: Vec<Packet> = Vec::new();
 
     // estimated size of the `packet_buffer` in bytes
@@ -187,7 +270,7 @@ pub async fn v3_binary_encoder(
     // buffer all packets to find if there is binary packets
     let mut has_binary = false;
 
-    while let Some(packets) = try_recv_packet(&mut rx, estimated_size, max_payload, false) {
+    while let Some(packets) = try_recv_packet(rx, estimated_size, max_payload, false) {
         for packet in packets {
             if packet.is_binary() {
                 has_binary = true;
@@ -212,7 +295,7 @@ pub async fn v3_binary_encoder(
 
     // If there is no packet in the buffer, wait for the next packet
     if data.is_empty() {
-        let packets = recv_packet(&mut rx).await?;
+        let packets = recv_packet(rx).await?;
         for packet in packets {
             match packet {
                 Packet::BinaryV3(_) | Packet::Binary(_) => {
@@ -228,17 +311,19 @@ pub async fn v3_binary_encoder(
 
     #[cfg(feature = "tracing")]
     tracing::debug!("sending packet: {:?}", &data);
-    Ok(Payload::new(data.freeze(), has_binary))
+    let payload = data.split().freeze();
+    BUF_POOL.put(data);
+    Ok(Payload::new(vec![payload], has_binary))
 }
 
 /// Encode multiple packet packet into a *string* payload according to the
 /// [engine.io v3 protocol](https://github.com/socketio/engine.io-protocol/tree/v3#payload)
 #[cfg(feature = "v3")]
 pub async fn v3_string_encoder(
-    mut rx: MutexGuard<'_, PeekableReceiver<PacketBuf>>,
+    rx: &mut PeekableReceiver<PacketBuf>,
     max_payload: u64,
 ) -> Result<Payload, Error> {
-    let mut data = bytes::BytesMut::new();
+    let mut data = BUF_POOL.take(BUF_CAPACITY);
 
     #[cfg(feature = "tracing")]
     tracing::debug!("encoding payload with v3 string encoder");
@@ -248,7 +333,7 @@ pub async fn v3_string_encoder(
     let max_packet_size_len = max_payload.checked_ilog10().unwrap_or(0) as usize + 1;
     // Current size of the payload
     let current_size = data.len() + PUNCTUATION_LEN + max_packet_size_len;
-    while let Some(packets) = try_recv_packet(&mut rx, current_size, max_payload, true) {
+    while let Some(packets) = try_recv_packet(rx, current_size, max_payload, true) {
         for packet in packets {
             v3_string_packet_encoder(packet, &mut data)?;
         }
@@ -256,31 +341,32 @@ pub async fn v3_string_encoder(
 
     // If there is no packet in the buffer, wait for the next packet
     if data.is_empty() {
-        let packets = recv_packet(&mut rx).await?;
+        let packets = recv_packet(rx).await?;
         for packet in packets {
             v3_string_packet_encoder(packet, &mut data)?;
         }
     }
 
-    Ok(Payload::new(data.freeze(), false))
+    let payload = data.split().freeze();
+    BUF_POOL.put(data);
+    Ok(Payload::new(vec![payload], false))
 }
 
 #[cfg(test)]
 mod tests {
     use bytes::Bytes;
-    use tokio::sync::Mutex;
 
     use PacketBuf;
 
     use super::*;
+    use crate::str::Str;
     const MAX_PAYLOAD: u64 = 100_000;
 
     #[tokio::test]
     async fn encode_v4_payload() {
         const PAYLOAD: &str = "4hello€\x1ebAQIDBA==\x1e4hello€";
         let (tx, rx) = tokio::sync::mpsc::channel::<PacketBuf>(10);
-        let rx = Mutex::new(PeekableReceiver::new(rx));
-        let rx = rx.lock().await;
+        let mut rx = PeekableReceiver::new(rx);
         tx.try_send(smallvec::smallvec![Packet::Message("hello€".into())])
             .unwrap();
         tx.try_send(smallvec::smallvec![Packet::Binary(Bytes::from_static(&[
@@ -289,15 +375,53 @@ mod tests {
         .unwrap();
         tx.try_send(smallvec::smallvec![Packet::Message("hello€".into())])
             .unwrap();
-        let Payload { data, .. } = v4_encoder(rx, MAX_PAYLOAD).await.unwrap();
-        assert_eq!(data, PAYLOAD.as_bytes());
+        let Payload { data, .. } = v4_encoder(&mut rx, MAX_PAYLOAD).await.unwrap();
+        assert_eq!(data.concat(), PAYLOAD.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn encode_v4_large_message() {
+        let (tx, rx) = tokio::sync::mpsc::channel::<PacketBuf>(10);
+        let mut rx = PeekableReceiver::new(rx);
+        let msg = Str::from("a".repeat(CHUNK_THRESHOLD));
+        tx.try_send(smallvec::smallvec![Packet::Message("hello".into())])
+            .unwrap();
+        tx.try_send(smallvec::smallvec![Packet::Message(msg.clone())])
+            .unwrap();
+        tx.try_send(smallvec::smallvec![Packet::Message("hello".into())])
+            .unwrap();
+        let Payload { data, .. } = v4_encoder(&mut rx, MAX_PAYLOAD).await.unwrap();
+        assert_eq!(data.len(), 3);
+        // The large message is not copied
+        assert!(std::ptr::eq(data[1].as_ptr(), msg.as_ptr()));
+        assert_eq!(
+            data.concat(),
+            format!("4hello\x1e4{msg}\x1e4hello").as_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn encode_v4_payload_wait_for_packet() {
+        const PAYLOAD: &str = "4hello€\x1ebAQIDBA==";
+        let (tx, rx) = tokio::sync::mpsc::channel::<PacketBuf>(10);
+        let mut rx = PeekableReceiver::new(rx);
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            tx.try_send(smallvec::smallvec![
+                Packet::Message("hello€".into()),
+                Packet::Binary(Bytes::from_static(&[1, 2, 3, 4]))
+            ])
+            .unwrap();
+        });
+        let Payload { data, .. } = v4_encoder(&mut rx, MAX_PAYLOAD).await.unwrap();
+        assert_eq!(data.concat(), PAYLOAD.as_bytes());
     }
 
     #[tokio::test]
     async fn max_payload_v4() {
         const MAX_PAYLOAD: u64 = 10;
         let (tx, rx) = tokio::sync::mpsc::channel::<PacketBuf>(10);
-        let mutex = Mutex::new(PeekableReceiver::new(rx));
+        let mut rx = PeekableReceiver::new(rx);
         tx.try_send(smallvec::smallvec![Packet::Message("hello€".into())])
             .unwrap();
         tx.try_send(smallvec::smallvec![Packet::Binary(Bytes::from_static(&[
@@ -309,19 +433,16 @@ mod tests {
         tx.try_send(smallvec::smallvec![Packet::Message("hello€".into())])
             .unwrap();
         {
-            let rx = mutex.lock().await;
-            let Payload { data, .. } = v4_encoder(rx, MAX_PAYLOAD).await.unwrap();
-            assert_eq!(data, "4hello€".as_bytes());
+            let Payload { data, .. } = v4_encoder(&mut rx, MAX_PAYLOAD).await.unwrap();
+            assert_eq!(data.concat(), "4hello€".as_bytes());
         }
         {
-            let rx = mutex.lock().await;
-            let Payload { data, .. } = v4_encoder(rx, MAX_PAYLOAD + 10).await.unwrap();
-            assert_eq!(data, "bAQIDBA==\x1e4hello€".as_bytes());
+            let Payload { data, .. } = v4_encoder(&mut rx, MAX_PAYLOAD + 10).await.unwrap();
+            assert_eq!(data.concat(), "bAQIDBA==\x1e4hello€".as_bytes());
         }
         {
-            let rx = mutex.lock().await;
-            let Payload { data, .. } = v4_encoder(rx, MAX_PAYLOAD + 10).await.unwrap();
-            assert_eq!(data, "4hello€".as_bytes());
+            let Payload { data, .. } = v4_encoder(&mut rx, MAX_PAYLOAD + 10).await.unwrap();
+            assert_eq!(data.concat(), "4hello€".as_bytes());
         }
     }
 
@@ -330,8 +451,7 @@ mod tests {
     async fn encode_v3b64_payload() {
         const PAYLOAD: &str = "7:4hello€10:b4AQIDBA==7:4hello€";
         let (tx, rx) = tokio::sync::mpsc::channel::<PacketBuf>(10);
-        let mutex = Mutex::new(PeekableReceiver::new(rx));
-        let rx = mutex.lock().await;
+        let mut rx = PeekableReceiver::new(rx);
 
         tx.try_send(smallvec::smallvec![Packet::Message("hello€".into())])
             .unwrap();
@@ -343,18 +463,32 @@ mod tests {
             .unwrap();
         let Payload {
             data, has_binary, ..
-        } = v3_string_encoder(rx, MAX_PAYLOAD).await.unwrap();
-        assert_eq!(data, PAYLOAD.as_bytes());
+        } = v3_string_encoder(&mut rx, MAX_PAYLOAD).await.unwrap();
+        assert_eq!(data.concat(), PAYLOAD.as_bytes());
         assert!(!has_binary);
     }
 
+    #[cfg(feature = "v3")]
+    #[tokio::test]
+    async fn encode_v3_utf16_length_payload() {
+        const PAYLOAD: &str = "3:4🦀3:4e\u{301}";
+        let (tx, rx) = tokio::sync::mpsc::channel::<PacketBuf>(10);
+        let mut rx = PeekableReceiver::new(rx);
+        tx.try_send(smallvec::smallvec![Packet::Message("🦀".into())])
+            .unwrap();
+        tx.try_send(smallvec::smallvec![Packet::Message("e\u{301}".into())])
+            .unwrap();
+        let Payload { data, .. } = v3_string_encoder(&mut rx, MAX_PAYLOAD).await.unwrap();
+        assert_eq!(data.concat(), PAYLOAD.as_bytes());
+    }
+
     #[cfg(feature = "v3")]
     #[tokio::test]
     async fn max_payload_v3_b64() {
         const MAX_PAYLOAD: u64 = 10;
 
         let (tx, rx) = tokio::sync::mpsc::channel::<PacketBuf>(10);
-        let mutex = Mutex::new(PeekableReceiver::new(rx));
+        let mut rx = PeekableReceiver::new(rx);
         tx.try_send(smallvec::smallvec![Packet::Message("hello€".into())])
             .unwrap();
         tx.try_send(smallvec::smallvec![Packet::BinaryV3(Bytes::from_static(
@@ -366,14 +500,12 @@ mod tests {
         tx.try_send(smallvec::smallvec![Packet::Message("hello€".into())])
             .unwrap();
         {
-            let rx = mutex.lock().await;
-            let Payload { data, .. } = v3_string_encoder(rx, MAX_PAYLOAD).await.unwrap();
-            assert_eq!(data, "7:4hello€".as_bytes());
+            let Payload { data, .. } = v3_string_encoder(&mut rx, MAX_PAYLOAD).await.unwrap();
+            assert_eq!(data.concat(), "7:4hello€".as_bytes());
         }
         {
-            let rx = mutex.lock().await;
-            let Payload { data, .. } = v3_string_encoder(rx, MAX_PAYLOAD + 10).await.unwrap();
-            assert_eq!(data, "10:b4AQIDBA==7:4hello€7:4hello€".as_bytes());
+            let Payload { data, .. } = v3_string_encoder(&mut rx, MAX_PAYLOAD + 10).await.unwrap();
+            assert_eq!(data.concat(), "10:b4AQIDBA==7:4hello€7:4hello€".as_bytes());
         }
     }
 
@@ -384,8 +516,7 @@ mod tests {
             0, 9, 255, 52, 104, 101, 108, 108, 111, 226, 130, 172, 1, 5, 255, 4, 1, 2, 3, 4,
         ];
         let (tx, rx) = tokio::sync::mpsc::channel::<PacketBuf>(10);
-        let mutex = Mutex::new(PeekableReceiver::new(rx));
-        let rx = mutex.lock().await;
+        let mut rx = PeekableReceiver::new(rx);
 
         tx.try_send(smallvec::smallvec![Packet::Message("hello€".into())])
             .unwrap();
@@ -395,8 +526,8 @@ mod tests {
         .unwrap();
         let Payload {
             data, has_binary, ..
-        } = v3_binary_encoder(rx, MAX_PAYLOAD).await.unwrap();
-        assert_eq!(*data, PAYLOAD);
+        } = v3_binary_encoder(&mut rx, MAX_PAYLOAD).await.unwrap();
+        assert_eq!(data.concat(), PAYLOAD);
         assert!(has_binary);
     }
 
@@ -410,7 +541,7 @@ mod tests {
             3, 4,
         ];
         let (tx, rx) = tokio::sync::mpsc::channel::<PacketBuf>(10);
-        let mutex = Mutex::new(PeekableReceiver::new(rx));
+        let mut rx = PeekableReceiver::new(rx);
         tx.try_send(smallvec::smallvec![Packet::Message("hellooo€".into())])
             .unwrap();
         tx.try_send(smallvec::smallvec![Packet::BinaryV3(Bytes::from_static(
@@ -422,14 +553,12 @@ mod tests {
         tx.try_send(smallvec::smallvec![Packet::Message("hello€".into())])
             .unwrap();
         {
-            let rx = mutex.lock().await;
-            let Payload { data, .. } = v3_binary_encoder(rx, MAX_PAYLOAD).await.unwrap();
-            assert_eq!(*data, PAYLOAD);
+            let Payload { data, .. } = v3_binary_encoder(&mut rx, MAX_PAYLOAD).await.unwrap();
+            assert_eq!(data.concat(), PAYLOAD);
         }
         {
-            let rx = mutex.lock().await;
-            let Payload { data, .. } = v3_binary_encoder(rx, MAX_PAYLOAD).await.unwrap();
-            assert_eq!(data, "7:4hello€7:4hello€".as_bytes());
+            let Payload { data, .. } = v3_binary_encoder(&mut rx, MAX_PAYLOAD).await.unwrap();
+            assert_eq!(data.concat(), "7:4hello€7:4hello€".as_bytes());
         }
     }
 }
@@ -7,7 +7,6 @@ use crate::{
 use bytes::Bytes;
 use futures_core::Stream;
 use http::Request;
-use tokio::sync::MutexGuard;
 
 mod buf;
 mod decoder;
@@ -23,6 +22,19 @@ const STRING_PACKET_IDENTIFIER_V3: u8 = 0x00;
 #[cfg(feature = "v3")]
 const BINARY_PACKET_IDENTIFIER_V3: u8 = 0x01;
 
+/// The number of utf16 code units of the char starting with this utf8 byte, zero for a continuation byte.
+///
+/// The v3 string payloads prefix each packet with its length in utf16 code units,
+/// which is the length of a javascript string.
+#[cfg(feature = "v3")]
+fn utf16_len(byte: u8) -> usize {
+    match byte {
+        0x80..=0xbf => 0,
+        0xf0.. => 2,
+        _ => 1,
+    }
+}
+
 pub fn decoder(
     body: Request<impl http_body::Body<Error = impl std::fmt::Debug> + Unpin>,
     #[allow(unused_variables)] protocol: ProtocolVersion,
@@ -54,19 +66,22 @@ pub fn decoder(
 }
 
 /// A payload to transmit to the client through http polling
+///
+/// The data is split into chunks so that large packets are sent as is
+/// rather than being copied into a single buffer.
 #[non_exhaustive]
 pub struct Payload {
-    pub data: Bytes,
+    pub data: Vec<Bytes>,
     pub has_binary: bool,
 }
 impl Payload {
-    pub fn new(data: Bytes, has_binary: bool) -> Self {
+    pub fn new(data: Vec<Bytes>, has_binary: bool) -> Self {
         Self { data, has_binary }
     }
 }
 
 pub async fn encoder(
-    rx: MutexGuard<'_, PeekableReceiver<PacketBuf>>,
+    rx: &mut PeekableReceiver<PacketBuf>,
     #[allow(unused_variables)] protocol: ProtocolVersion,
     #[cfg(feature = "v3")] supports_binary: bool,
     max_payload: u64,
@@ -87,3 +102,77 @@ pub async fn encoder(
         encoder::v4_encoder(rx, max_payload).await
     }
 }
+
+/// Encode the packets into the payload of a polling response.
+/// Each packet is pushed separately to the internal channel, like when it is emitted.
+///
+/// It is only used to benchmark the encoders.
+#[doc(hidden)]
+#[cfg(feature = "__test_harness")]
+pub async fn encode_packets(
+    packets: Vec<Packet>,
+    protocol: ProtocolVersion,
+    #[cfg(feature = "v3")] supports_binary: bool,
+    max_payload: u64,
+) -> Vec<Bytes> {
+    let (tx, rx) = tokio::sync::mpsc::channel(packets.len().max(1));
+    for packet in packets {
+        tx.try_send(smallvec::smallvec![packet]).unwrap();
+    }
+    let mut rx = PeekableReceiver::new(rx);
+    let payload = encoder(
+        &mut rx,
+        protocol,
+        #[cfg(feature = "v3")]
+        supports_binary,
+        max_payload,
+    )
+    .await
+    .unwrap();
+    payload.data
+}
+
+/// Decode a payload received in the given chunks, until the end of the payload or the first error,
+/// like when it is received by the polling transport.
+///
+/// It is only used to fuzz the decoders.
+#[doc(hidden)]
+#[cfg(feature = "__test_harness")]
+pub fn decode_payload(
+    chunks: Vec<Bytes>,
+    protocol: ProtocolVersion,
+    #[cfg(feature = "v3")] binary: bool,
+    max_payload: u64,
+) -> Vec<Result<Packet, Error>> {
+    use futures_util::StreamExt;
+    use std::task::{Context, Poll};
+
+    let frames = chunks
+        .into_iter()
+        .map(|chunk| Ok::<_, std::convert::Infallible>(http_body::Frame::data(chunk)));
+    #[allow(unused_mut)]
+    let mut req = Request::new(http_body_util::StreamBody::new(futures_util::stream::iter(
+        frames,
+    )));
+    #[cfg(feature = "v3")]
+    if binary {
+        req.headers_mut().insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("application/octet-stream"),
+        );
+    }
+    let packets = decoder(req, protocol, max_payload);
+    futures_util::pin_mut!(packets);
+
+    // The body is never pending, so the decoder always completes on the first poll.
+    let mut cx = Context::from_waker(futures_util::task::noop_waker_ref());
+    let mut res = Vec::new();
+    while let Poll::Ready(Some(packet)) = packets.poll_next_unpin(&mut cx) {
+        let is_err = packet.is_err();
+        res.push(packet);
+        if is_err {
+            break;
+        }
+    }
+    res
+}
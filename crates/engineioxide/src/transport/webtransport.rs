@@ -0,0 +1,438 @@
+//! The webtransport transport module handles engine.io sessions over a WebTransport bidirectional stream.
+//!
+//! engineioxide doesn't embed any HTTP/3 server. The bidirectional stream of an accepted WebTransport session
+//! (e.g. with `wtransport` or `h3-webtransport`) should be given to
+//! [`EngineIoService::on_webtransport_session`](crate::service::EngineIoService::on_webtransport_session)
+//! and the engine.io session will be handled over it.
+//!
+//! Each packet is prefixed with a header according to the
+//! [engine.io protocol](https://socket.io/docs/v4/engine-io-protocol/#webtransport):
+//! * 1 byte if the payload length is < 126.
+//! * 3 bytes (126 followed by the length as an u16) if the payload length is < 65536.
+//! * 9 bytes (127 followed by the length as an u64) otherwise.
+//!
+//! The first bit of the header is set if the payload is binary.
+
+use std::sync::Arc;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_util::future::RemoteHandle;
+use http::request::Parts;
+use serde::Deserialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+
+use crate::{
+    engine::EngineIo,
+    errors::Error,
+    handler::EngineIoHandler,
+    packet::{OpenPacket, Packet},
+    service::{ProtocolVersion, TransportType},
+    sid::Sid,
+    str::Str,
+    DisconnectReason, Socket,
+};
+
+const BINARY_FLAG: u8 = 0x80;
+const U16_LEN: u8 = 126;
+const U64_LEN: u8 = 127;
+
+/// The optional data of the open packet sent by the client to upgrade an existing session.
+#[derive(Debug, Deserialize)]
+struct UpgradeData {
+    sid: Sid,
+}
+
+/// Handle a new WebTransport session on the given bidirectional stream.
+///
+/// The first packet sent by the client should be an open packet.
+/// If it contains a sid it means that the client wants to upgrade an existing polling session.
+/// Otherwise a new session is created and an open packet is sent back.
+///
+/// Read packets from the stream and handle them, it will block until the session is closed
+pub async fn on_session<H: EngineIoHandler, S>(
+    engine: Arc<EngineIo<H>>,
+    mut req_data: Parts,
+    stream: S,
+) -> Result<(), Error>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    if !engine.config.allowed_transport(TransportType::WebTransport) {
+        return Err(Error::TransportMismatch);
+    }
+    let max_payload = engine.config.max_payload;
+    let (mut rx, mut tx) = tokio::io::split(stream);
+
+    let socket = match read_handshake(&mut rx, max_payload).await? {
+        Some(sid) => match engine.get_socket(sid) {
+            None => return Err(Error::UnknownSessionID(sid)),
+            Some(socket) if !socket.is_http() => return Err(Error::Upgrade),
+            Some(socket) => {
+                upgrade_handshake::<H, S>(&socket, &mut rx, &mut tx, max_payload).await?;
+                socket
+            }
+        },
+        None => {
+            engine.accept_session()?;
+            engine.config.allow_handshake(&mut req_data).await?;
+            let socket = engine.create_session(
+                ProtocolVersion::V4,
+                TransportType::WebTransport,
+                req_data,
+                #[cfg(feature = "v3")]
+                true,
+            )?;
+            #[cfg(feature = "tracing")]
+            tracing::debug!("[sid={}] new webtransport session", socket.id);
+            let packet = OpenPacket::new(TransportType::WebTransport, socket.id, &engine.config);
+            write_packet(&mut tx, Packet::Open(packet)).await?;
+            socket.clone().spawn_heartbeat();
+            socket
+        }
+    };
+
+    let tx_handle = forward_to_socket::<H, S>(socket.clone(), tx);
+
+    if let Err(ref e) = forward_to_handler(&engine, rx, &socket).await {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("[sid={}] error when handling packet: {:?}", socket.id, e);
+        if let Some(reason) = e.into() {
+            engine.close_session(socket.id, reason);
+        }
+    } else {
+        engine.close_session(socket.id, DisconnectReason::TransportClose);
+    }
+    // Dropping the handle aborts the task
+    drop(tx_handle);
+    Ok(())
+}
+
+/// Forwards all packets received from the stream to a EngineIo [`Socket`]
+async fn forward_to_handler<H: EngineIoHandler, S>(
+    engine: &Arc<EngineIo<H>>,
+    mut rx: ReadHalf<S>,
+    socket: &Arc<Socket<H::Data>>,
+) -> Result<(), Error>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    while let Some(packet) = read_packet(&mut rx, engine.config.max_payload).await? {
+        if matches!(packet, Packet::Message(_) | Packet::Binary(_))
+            && !socket.acquire_rate_limit().await?
+        {
+            continue;
+        }
+        #[cfg(feature = "tracing")]
+        let _span = socket.packet_span(packet.kind()).entered();
+        #[cfg(feature = "metrics")]
+        crate::metrics::packet_received(&packet);
+        match packet {
+            Packet::Close => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("[sid={}] closing session", socket.id);
+                engine.close_session(socket.id, DisconnectReason::TransportClose);
+                break;
+            }
+            Packet::Pong | Packet::Ping => socket
+                .heartbeat_tx
+                .try_send(())
+                .map_err(|_| Error::HeartbeatTimeout),
+            Packet::Message(msg) => {
+                engine.handler.on_message(msg, socket.clone());
+                Ok(())
+            }
+            Packet::Binary(data) => {
+                engine.handler.on_binary(data, socket.clone());
+                Ok(())
+            }
+            p => return Err(Error::BadPacket(p)),
+        }?
+    }
+    Ok(())
+}
+
+/// Forwards all packets waiting to be sent to the stream
+///
+/// The stream is flushed only when the internal channel is drained
+fn forward_to_socket<H: EngineIoHandler, S>(
+    socket: Arc<Socket<H::Data>>,
+    mut tx: WriteHalf<S>,
+) -> RemoteHandle<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let runtime = socket.runtime.clone();
+    runtime.spawn_with_handle(async move {
+        let mut internal_rx = socket.internal_rx.try_take().unwrap();
+        let mut buf = BytesMut::new();
+
+        while let Some(items) = internal_rx.recv().await {
+            let mut closed = false;
+            #[cfg(feature = "metrics")]
+            crate::metrics::packets_sent(&items);
+            for item in items {
+                closed |= encode_packet(item, &mut buf);
+            }
+            // For every available packet we continue to encode until the channel is drained
+            while let Ok(items) = internal_rx.try_recv() {
+                #[cfg(feature = "metrics")]
+                crate::metrics::packets_sent(&items);
+                for item in items {
+                    closed |= encode_packet(item, &mut buf);
+                }
+            }
+
+            let write = async { tx.write_all_buf(&mut buf).await.and(tx.flush().await) };
+            #[cfg(feature = "tracing")]
+            let write = tracing::Instrument::instrument(
+                write,
+                tracing::debug_span!(parent: socket.span(), "engineio.flush"),
+            );
+            if let Err(_e) = write.await {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("[sid={}] error sending packets: {}", socket.id, _e);
+            }
+            if closed {
+                tx.shutdown().await.ok();
+                internal_rx.close();
+                break;
+            }
+        }
+    })
+}
+
+/// Upgrade a session from a polling request to a WebTransport session.
+///
+/// It follows the same handshake as the websocket upgrade:
+/// the client sends a `2probe` packet, the server responds with a `3probe` packet
+/// and then the client sends an upgrade packet.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "engineio.upgrade",
+        parent = socket.span(),
+        skip(socket, rx, tx),
+        fields(sid = socket.id.to_string(), to = "webtransport")
+    )
+)]
+async fn upgrade_handshake<H: EngineIoHandler, S>(
+    socket: &Arc<Socket<H::Data>>,
+    rx: &mut ReadHalf<S>,
+    tx: &mut WriteHalf<S>,
+    max_payload: u64,
+) -> Result<(), Error>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    #[cfg(feature = "tracing")]
+    tracing::debug!("webtransport session upgrade");
+
+    match read_packet(rx, max_payload).await? {
+        Some(Packet::PingUpgrade) => write_packet(tx, Packet::PongUpgrade).await?,
+        Some(p) => Err(Error::BadPacket(p))?,
+        None => Err(Error::Upgrade)?,
+    };
+
+    let _upgrading = socket.start_upgrade();
+
+    // send a NOOP packet to any pending polling request so it closes gracefully
+    socket.send(Packet::Noop)?;
+
+    match read_packet(rx, max_payload).await? {
+        Some(Packet::Upgrade) => {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("webtransport upgraded successful")
+        }
+        Some(p) => Err(Error::BadPacket(p))?,
+        None => Err(Error::Upgrade)?,
+    };
+
+    // wait for any polling connection to finish by waiting for the receiver to be put back
+    let _ = socket.internal_rx.take().await;
+    socket.upgrade_to_webtransport();
+    Ok(())
+}
+
+/// Read the open packet sent by the client and extract the sid to upgrade if there is one.
+async fn read_handshake<R: AsyncRead + Unpin>(
+    rx: &mut R,
+    max_payload: u64,
+) -> Result<Option<Sid>, Error> {
+    let (is_binary, data) = read_frame(rx, max_payload).await?.ok_or(Error::Upgrade)?;
+    match data.split_first() {
+        Some((b'0', _)) if is_binary => Err(Error::Upgrade),
+        Some((b'0', [])) => Ok(None),
+        Some((b'0', data)) => {
+            let data: UpgradeData = serde_json::from_slice(data).map_err(|_| Error::Upgrade)?;
+            Ok(Some(data.sid))
+        }
+        Some((c, _)) => Err(Error::InvalidPacketType(Some(*c as char))),
+        None => Err(Error::InvalidPacketType(None)),
+    }
+}
+
+/// Read a frame and decode it to a [`Packet`].
+/// Returns `None` if the stream is closed.
+async fn read_packet<R: AsyncRead + Unpin>(
+    rx: &mut R,
+    max_payload: u64,
+) -> Result<Option<Packet>, Error> {
+    match read_frame(rx, max_payload).await? {
+        Some((true, data)) => Ok(Some(Packet::Binary(data))),
+        Some((false, data)) => {
+            std::str::from_utf8(&data)?;
+            // SAFETY: The data was checked to be valid utf8 just above
+            let data = unsafe { Str::from_bytes_unchecked(data) };
+            Ok(Some(Packet::try_from(data)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Read a frame from the stream.
+/// Returns `None` if the stream is closed before the start of the frame.
+async fn read_frame<R: AsyncRead + Unpin>(
+    rx: &mut R,
+    max_payload: u64,
+) -> Result<Option<(bool, Bytes)>, Error> {
+    let header = match rx.read_u8().await {
+        Ok(header) => header,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let is_binary = header & BINARY_FLAG == BINARY_FLAG;
+    let len = match header & !BINARY_FLAG {
+        U16_LEN => rx.read_u16().await? as u64,
+        U64_LEN => rx.read_u64().await?,
+        len => len as u64,
+    };
+    if len > max_payload {
+        return Err(Error::PayloadTooLarge);
+    }
+
+    let mut data = vec![0; len as usize];
+    rx.read_exact(&mut data).await?;
+    Ok(Some((is_binary, data.into())))
+}
+
+/// Encode a [`Packet`] with its frame header in the given buffer.
+/// Returns `true` if the packet is a close packet.
+fn encode_packet(packet: Packet, buf: &mut BytesMut) -> bool {
+    let (is_binary, data): (bool, Bytes) = match packet {
+        Packet::Binary(bin) | Packet::BinaryV3(bin) => (true, bin),
+        // A Noop Packet maybe sent by the server to upgrade from a polling connection
+        // In the case that the packet was not poll in time it will remain in the buffer and therefore
+        // it should be discarded here
+        Packet::Noop => return false,
+        Packet::Close => return true,
+        packet => (false, String::from(packet).into()),
+    };
+
+    let flag = if is_binary { BINARY_FLAG } else { 0 };
+    let len = data.len();
+    if len < U16_LEN as usize {
+        buf.reserve(1 + len);
+        buf.put_u8(len as u8 | flag);
+    } else if len < 1 << 16 {
+        buf.reserve(3 + len);
+        buf.put_u8(U16_LEN | flag);
+        buf.put_u16(len as u16);
+    } else {
+        buf.reserve(9 + len);
+        buf.put_u8(U64_LEN | flag);
+        buf.put_u64(len as u64);
+    }
+    buf.extend_from_slice(&data);
+    false
+}
+
+/// Encode and directly write a [`Packet`] to the stream.
+async fn write_packet<W: AsyncWrite + Unpin>(tx: &mut W, packet: Packet) -> Result<(), Error> {
+    let mut buf = BytesMut::new();
+    encode_packet(packet, &mut buf);
+    tx.write_all_buf(&mut buf).await?;
+    tx.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(packet: Packet) -> Bytes {
+        let mut buf = BytesMut::new();
+        encode_packet(packet, &mut buf);
+        buf.freeze()
+    }
+
+    #[test]
+    fn encode_text_packet() {
+        let data = encode(Packet::Message("hello".into()));
+        assert_eq!(&data[..], b"\x064hello");
+    }
+
+    #[test]
+    fn encode_binary_packet() {
+        let data = encode(Packet::Binary(Bytes::from_static(&[1, 2, 3])));
+        assert_eq!(&data[..], &[0x83, 1, 2, 3]);
+    }
+
+    #[test]
+    fn encode_large_packets() {
+        let data = encode(Packet::Binary(vec![0; 200].into()));
+        assert_eq!(&data[..3], &[0x80 | 126, 0, 200]);
+        assert_eq!(data.len(), 203);
+
+        let data = encode(Packet::Binary(vec![0; 70000].into()));
+        assert_eq!(&data[..9], &[0x80 | 127, 0, 0, 0, 0, 0, 1, 0x11, 0x70]);
+        assert_eq!(data.len(), 70009);
+    }
+
+    #[test]
+    fn encode_noop_and_close() {
+        let mut buf = BytesMut::new();
+        assert!(!encode_packet(Packet::Noop, &mut buf));
+        assert!(encode_packet(Packet::Close, &mut buf));
+        assert!(buf.is_empty());
+    }
+
+    #[tokio::test]
+    async fn decode_packets() {
+        let mut buf = BytesMut::new();
+        encode_packet(Packet::Message("hello".into()), &mut buf);
+        encode_packet(Packet::Binary(vec![4; 300].into()), &mut buf);
+        encode_packet(Packet::Ping, &mut buf);
+        let mut rx = &buf[..];
+
+        let packet = read_packet(&mut rx, 1000).await.unwrap();
+        assert_eq!(packet, Some(Packet::Message("hello".into())));
+        let packet = read_packet(&mut rx, 1000).await.unwrap();
+        assert_eq!(packet, Some(Packet::Binary(vec![4; 300].into())));
+        let packet = read_packet(&mut rx, 1000).await.unwrap();
+        assert_eq!(packet, Some(Packet::Ping));
+        let packet = read_packet(&mut rx, 1000).await.unwrap();
+        assert_eq!(packet, None);
+    }
+
+    #[tokio::test]
+    async fn decode_payload_too_large() {
+        let data = encode(Packet::Binary(vec![4; 300].into()));
+        let err = read_packet(&mut &data[..], 100).await.unwrap_err();
+        assert!(matches!(err, Error::PayloadTooLarge));
+    }
+
+    #[tokio::test]
+    async fn decode_handshake() {
+        let sid = Sid::new();
+        let mut buf = BytesMut::new();
+        buf.put_u8(1);
+        buf.put_u8(b'0');
+        let data = format!("0{{\"sid\":\"{sid}\"}}");
+        buf.put_u8(data.len() as u8);
+        buf.extend_from_slice(data.as_bytes());
+        let mut rx = &buf[..];
+
+        assert_eq!(read_handshake(&mut rx, 1000).await.unwrap(), None);
+        assert_eq!(read_handshake(&mut rx, 1000).await.unwrap(), Some(sid));
+    }
+}
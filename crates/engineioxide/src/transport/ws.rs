@@ -6,24 +6,34 @@
 
 use std::sync::Arc;
 
+use bytes::Bytes;
+
 use futures_util::{
+    future::RemoteHandle,
     stream::{SplitSink, SplitStream},
     SinkExt, StreamExt, TryStreamExt,
 };
 use http::{request::Parts, HeaderValue, Request, Response, StatusCode};
-use tokio::{
-    io::{AsyncRead, AsyncWrite},
-    task::JoinHandle,
-};
+use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_tungstenite::{
     tungstenite::{
+        self,
         handshake::derive_accept_key,
-        protocol::{Role, WebSocketConfig},
+        protocol::{
+            frame::{
+                coding::{Data, OpCode},
+                Frame,
+            },
+            Role, WebSocketConfig,
+        },
         Message,
     },
     WebSocketStream,
 };
 
+#[cfg(feature = "ws-deflate")]
+use super::deflate;
 use crate::{
     body::ResponseBody,
     config::EngineIoConfig,
@@ -31,25 +41,47 @@ use crate::{
     errors::Error,
     handler::EngineIoHandler,
     packet::{OpenPacket, Packet},
-    service::ProtocolVersion,
-    service::TransportType,
+    service::{ProtocolVersion, TransportType, UpgradedConn},
     sid::Sid,
+    socket::CloseReason,
     DisconnectReason, Socket,
 };
 
 /// Create a response for websocket upgrade
-fn ws_response<B>(ws_key: &HeaderValue) -> Result<Response<ResponseBody<B>>, http::Error> {
+fn ws_response<B>(
+    ws_key: &HeaderValue,
+    extensions: Option<HeaderValue>,
+) -> Result<Response<ResponseBody<B>>, http::Error> {
     let derived = derive_accept_key(ws_key.as_bytes());
     let sec = derived.parse::<HeaderValue>().unwrap();
-    Response::builder()
+    let mut res = Response::builder()
         .status(StatusCode::SWITCHING_PROTOCOLS)
         .header(http::header::UPGRADE, HeaderValue::from_static("websocket"))
         .header(
             http::header::CONNECTION,
             HeaderValue::from_static("Upgrade"),
         )
-        .header(http::header::SEC_WEBSOCKET_ACCEPT, sec)
-        .body(ResponseBody::empty_response())
+        .header(http::header::SEC_WEBSOCKET_ACCEPT, sec);
+    if let Some(extensions) = extensions {
+        res = res.header(http::header::SEC_WEBSOCKET_EXTENSIONS, extensions);
+    }
+    res.body(ResponseBody::empty_response())
+}
+
+/// Negotiate the permessage-deflate extension if it is enabled.
+/// Received messages are inflated up to the [`ws_max_message_size`](EngineIoConfig::ws_max_message_size).
+#[cfg(feature = "ws-deflate")]
+fn negotiate_deflate(
+    config: &EngineIoConfig,
+    headers: &http::HeaderMap,
+) -> Option<deflate::DeflateParams> {
+    let max_message_size = config
+        .ws_max_message_size
+        .unwrap_or(deflate::MAX_MESSAGE_SIZE);
+    config
+        .ws_deflate
+        .as_ref()
+        .and_then(|deflate| deflate::negotiate(deflate, max_message_size, headers))
 }
 
 /// Upgrade a websocket request to create a websocket connection.
@@ -71,17 +103,32 @@ pub fn new_req<R: Send + 'static, B, H: EngineIoHandler>(
         .ok_or(Error::HttpErrorResponse(StatusCode::BAD_REQUEST))?
         .clone();
 
-    tokio::spawn(async move {
-        let conn = hyper::upgrade::on(req)
-            .await
-            .map(hyper_util::rt::TokioIo::new);
-        let res = match conn {
-            Ok(conn) => on_init(engine, conn, protocol, sid, parts).await,
-            Err(_e) => {
-                #[cfg(feature = "tracing")]
-                tracing::debug!("ws upgrade error: {}", _e);
-                return;
-            }
+    #[cfg(feature = "ws-deflate")]
+    let extensions = negotiate_deflate(&engine.config, &parts.headers).map(|p| p.header_value());
+    #[cfg(not(feature = "ws-deflate"))]
+    let extensions = None;
+
+    let upgraded = parts
+        .extensions
+        .get::<UpgradedConn>()
+        .and_then(UpgradedConn::take);
+    // The upgrade mechanism only exists with http/1.1. With http/2 and up, the client
+    // should fall back to polling instead of getting an invalid upgrade response.
+    if upgraded.is_none() && parts.version >= http::Version::HTTP_2 {
+        return Err(Error::HttpErrorResponse(StatusCode::BAD_REQUEST));
+    }
+    let runtime = engine.config.runtime.clone();
+    runtime.spawn(Box::pin(async move {
+        let res = match upgraded {
+            Some(conn) => on_init(engine, conn, protocol, sid, parts).await,
+            None => match hyper::upgrade::on(req).await {
+                Ok(conn) => on_init(engine, TokioIo::new(conn), protocol, sid, parts).await,
+                Err(_e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("ws upgrade error: {}", _e);
+                    return;
+                }
+            },
         };
 
         match res {
@@ -94,9 +141,9 @@ pub fn new_req<R: Send + 'static, B, H: EngineIoHandler>(
                 tracing::debug!("ws closed with error: {:?}", _e)
             }
         }
-    });
+    }));
 
-    Ok(ws_response(&ws_key)?)
+    Ok(ws_response(&ws_key, extensions)?)
 }
 
 /// Handle a websocket connection upgrade
@@ -114,39 +161,62 @@ pub async fn on_init<H: EngineIoHandler, S>(
 where
     S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
+    #[cfg(feature = "ws-deflate")]
+    let deflate = negotiate_deflate(&engine.config, &req_data.headers);
+    // Fragmented messages are not compressed by the deflate layer.
+    #[cfg(feature = "ws-deflate")]
+    let shared_frames = deflate.is_none();
+    #[cfg(not(feature = "ws-deflate"))]
+    let shared_frames = true;
+    #[cfg(feature = "ws-deflate")]
+    let conn = deflate::DeflateStream::new(conn, deflate);
     let ws_config = WebSocketConfig::default().read_buffer_size(engine.config.ws_read_buffer_size);
+    let ws_config = match engine.config.ws_max_message_size {
+        Some(max) => ws_config
+            .max_message_size(Some(max))
+            .max_frame_size(Some(max)),
+        None => ws_config,
+    };
     let ws_init = move || WebSocketStream::from_raw_socket(conn, Role::Server, Some(ws_config));
     let (socket, ws) = if let Some(sid) = sid {
         match engine.get_socket(sid) {
             None => return Err(Error::UnknownSessionID(sid)),
-            Some(socket) if socket.is_ws() => return Err(Error::Upgrade),
+            Some(socket) if !socket.is_http() => return Err(Error::Upgrade),
             Some(socket) => {
                 let mut ws = ws_init().await;
-                upgrade_handshake::<H, S>(&socket, &mut ws).await?;
+                upgrade_handshake::<H, _>(&socket, &mut ws).await?;
                 (socket, ws)
             }
         }
     } else {
+        // v3 clients that cannot handle binary frames set the `b64` query param,
+        // binary packets are then sent as base64 text frames.
+        #[cfg(feature = "v3")]
+        let supports_binary = !crate::service::is_b64(req_data.uri.query().unwrap_or(""));
         let socket = engine.create_session(
             protocol,
             TransportType::Websocket,
             req_data,
             #[cfg(feature = "v3")]
-            false,
-        );
+            supports_binary,
+        )?;
         #[cfg(feature = "tracing")]
         tracing::debug!("[sid={}] new websocket connection", socket.id);
         let mut ws = ws_init().await;
         init_handshake(socket.id, &mut ws, &engine.config).await?;
-        socket
-            .clone()
-            .spawn_heartbeat(engine.config.ping_interval, engine.config.ping_timeout);
+        socket.clone().spawn_heartbeat();
         (socket, ws)
     };
     let (tx, rx) = ws.split();
-    let rx_handle = forward_to_socket::<H, S>(socket.clone(), tx);
+    let mut rx_handle = forward_to_socket::<H, _>(
+        socket.clone(),
+        tx,
+        engine.config.ws_max_frame_size,
+        shared_frames,
+    );
 
-    if let Err(ref e) = forward_to_handler(&engine, rx, &socket).await {
+    let res = forward_to_handler(&engine, rx, &socket).await;
+    if let Err(ref e) = res {
         #[cfg(feature = "tracing")]
         tracing::debug!("[sid={}] error when handling packet: {:?}", socket.id, e);
         if let Some(reason) = e.into() {
@@ -155,7 +225,22 @@ where
     } else {
         engine.close_session(socket.id, DisconnectReason::TransportClose);
     }
-    rx_handle.abort();
+    if matches!(res, Err(Error::PayloadTooLarge)) {
+        // Let the client know why the connection is closed by sending a close frame
+        // rather than dropping the connection.
+        socket
+            .close_reason
+            .set(CloseReason::new(1009, "message too big"))
+            .ok();
+        socket.send(Packet::Close).ok();
+        socket
+            .runtime
+            .timeout(engine.config.ping_timeout, &mut rx_handle)
+            .await
+            .ok();
+    }
+    // Dropping the handle aborts the task
+    drop(rx_handle);
     Ok(())
 }
 
@@ -170,24 +255,58 @@ where
 {
     while let Some(msg) = rx.try_next().await? {
         match msg {
-            Message::Text(msg) => match Packet::try_from(msg)? {
-                Packet::Close => {
-                    #[cfg(feature = "tracing")]
-                    tracing::debug!("[sid={}] closing session", socket.id);
-                    engine.close_session(socket.id, DisconnectReason::TransportClose);
-                    break;
+            Message::Text(msg) => {
+                let packet = match socket.protocol {
+                    #[cfg(feature = "v3")]
+                    ProtocolVersion::V3 => {
+                        // SAFETY: The utf8 bytes are guaranteed to be valid utf8
+                        let msg = unsafe { crate::Str::from_bytes_unchecked(msg.into()) };
+                        Packet::try_from_v3(msg)?
+                    }
+                    _ => Packet::try_from(msg)?,
+                };
+                if matches!(
+                    packet,
+                    Packet::Message(_) | Packet::Binary(_) | Packet::BinaryV3(_)
+                ) && !socket.acquire_rate_limit().await?
+                {
+                    continue;
                 }
-                Packet::Pong | Packet::Ping => socket
-                    .heartbeat_tx
-                    .try_send(())
-                    .map_err(|_| Error::HeartbeatTimeout),
-                Packet::Message(msg) => {
-                    engine.handler.on_message(msg, socket.clone());
-                    Ok(())
+                #[cfg(feature = "tracing")]
+                let _span = socket.packet_span(packet.kind()).entered();
+                #[cfg(feature = "metrics")]
+                crate::metrics::packet_received(&packet);
+                match packet {
+                    Packet::Close => {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!("[sid={}] closing session", socket.id);
+                        engine.close_session(socket.id, DisconnectReason::TransportClose);
+                        break;
+                    }
+                    Packet::Pong | Packet::Ping => socket
+                        .heartbeat_tx
+                        .try_send(())
+                        .map_err(|_| Error::HeartbeatTimeout),
+                    Packet::Message(msg) => {
+                        engine.handler.on_message(msg, socket.clone());
+                        Ok(())
+                    }
+                    // Clients without binary support send base64 encoded binary packets as text frames
+                    Packet::Binary(data) | Packet::BinaryV3(data) => {
+                        engine.handler.on_binary(data, socket.clone());
+                        Ok(())
+                    }
+                    p => return Err(Error::BadPacket(p)),
                 }
-                p => return Err(Error::BadPacket(p)),
-            },
+            }
             Message::Binary(mut data) => {
+                if !socket.acquire_rate_limit().await? {
+                    continue;
+                }
+                #[cfg(feature = "tracing")]
+                let _span = socket.packet_span("binary").entered();
+                #[cfg(feature = "metrics")]
+                crate::metrics::binary_received();
                 if socket.protocol == ProtocolVersion::V3 && !data.is_empty() {
                     // The first byte is the message type, which we don't need.
                     data = data.split_off(1);
@@ -208,37 +327,51 @@ where
 
 /// Forwards all packets waiting to be sent to the websocket
 ///
-/// The websocket stream is flushed only when the internal channel is drained
+/// The websocket stream is flushed only when the internal channel is drained.
+/// If `shared_frames` is false, large messages are not split to share their buffer
+/// (see [`SHARED_FRAME_THRESHOLD`]).
 fn forward_to_socket<H: EngineIoHandler, S>(
     socket: Arc<Socket<H::Data>>,
     mut tx: SplitSink<WebSocketStream<S>, Message>,
-) -> JoinHandle<()>
+    max_frame_size: Option<usize>,
+    shared_frames: bool,
+) -> RemoteHandle<()>
 where
     S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
     // Pipe between websocket and internal socket channel
-    tokio::spawn(async move {
-        let mut internal_rx = socket.internal_rx.try_lock().unwrap();
+    let runtime = socket.runtime.clone();
+    runtime.spawn_with_handle(async move {
+        let mut internal_rx = socket.internal_rx.try_take().unwrap();
 
         // map a packet to a websocket message
         // It is declared as a macro rather than a closure to avoid ownership issues
         macro_rules! map_fn {
             ($item:ident) => {
+                #[cfg(feature = "metrics")]
+                crate::metrics::packets_sent([&$item]);
                 let res = match $item {
+                    #[cfg(feature = "v3")]
+                    Packet::Binary(bin) | Packet::BinaryV3(bin)
+                        if socket.protocol == ProtocolVersion::V3 && !socket.supports_binary =>
+                    {
+                        let packet: String = Packet::BinaryV3(bin).try_into().unwrap();
+                        tx.feed(Message::Text(packet.into())).await
+                    }
                     Packet::Binary(bin) | Packet::BinaryV3(bin) => {
                         if socket.protocol == ProtocolVersion::V3 {
                             // v3 protocol requires packet type as the first byte.
-                            // This requires a new buffer. This is OK as it is only for the V3 protocol.
-                            let mut buff = Vec::with_capacity(bin.len() + 1);
-                            buff.push(0x04);
-                            buff.extend(bin);
-                            tx.feed(Message::Binary(buff.into())).await
+                            feed_data(&mut tx, Data::Binary, b"\x04", bin, max_frame_size).await
                         } else {
-                            tx.feed(Message::Binary(bin)).await
+                            feed_data(&mut tx, Data::Binary, b"", bin, max_frame_size).await
                         }
                     }
+                    Packet::Message(msg) if shared_frames && msg.len() >= SHARED_FRAME_THRESHOLD => {
+                        feed_data(&mut tx, Data::Text, b"4", msg.into(), max_frame_size).await
+                    }
                     Packet::Close => {
-                        tx.send(Message::Close(None)).await.ok();
+                        let frame = socket.close_reason.get().map(Into::into);
+                        tx.send(Message::Close(frame)).await.ok();
                         internal_rx.close();
                         break;
                     },
@@ -246,10 +379,7 @@ where
                     // In the case that the packet was not poll in time it will remain in the buffer and therefore
                     // it should be discarded here
                     Packet::Noop => Ok(()),
-                    _ => {
-                        let packet: String = $item.try_into().unwrap();
-                        tx.feed(Message::Text(packet.into())).await
-                    }
+                    _ => tx.feed(Message::Text($item.into())).await,
                 };
                 if let Err(_e) = res {
                     #[cfg(feature = "tracing")]
@@ -269,10 +399,72 @@ where
                 }
             }
 
-            tx.flush().await.ok();
+            let flush = tx.flush();
+            #[cfg(feature = "tracing")]
+            let flush = tracing::Instrument::instrument(
+                flush,
+                tracing::debug_span!(parent: socket.span(), "engineio.flush"),
+            );
+            flush.await.ok();
         }
     })
 }
+/// Messages larger than this are not copied in a new buffer to be prefixed with their packet type.
+/// The prefix is sent in its own frame, followed by a continuation frame that shares the buffer of the packet.
+///
+/// Therefore a message broadcasted to many sockets is never copied for each of them.
+/// It is not done with the permessage-deflate extension, which doesn't compress fragmented messages
+/// and copies the messages to compress them anyway.
+const SHARED_FRAME_THRESHOLD: usize = 16 * 1024;
+
+/// Feed a message made of a `prefix` followed by `data` to the websocket sink.
+///
+/// If there is a prefix or if the message is larger than the max frame size,
+/// it is split into fragmented frames that share the same underlying buffer.
+/// The data of text messages must be valid utf8.
+async fn feed_data<S>(
+    tx: &mut SplitSink<WebSocketStream<S>, Message>,
+    opcode: Data,
+    prefix: &'static [u8],
+    data: Bytes,
+    max_frame_size: Option<usize>,
+) -> Result<(), tungstenite::Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let max = max_frame_size.unwrap_or(usize::MAX).max(1);
+    if prefix.is_empty() && data.len() <= max {
+        return match opcode {
+            // SAFETY: text data is always valid utf8
+            Data::Text => {
+                let data = unsafe { tungstenite::Utf8Bytes::from_bytes_unchecked(data) };
+                tx.feed(Message::Text(data)).await
+            }
+            _ => tx.feed(Message::Binary(data)).await,
+        };
+    }
+
+    let mut opcode = opcode;
+    if !prefix.is_empty() {
+        let frame = Frame::message(prefix, OpCode::Data(opcode), data.is_empty());
+        tx.feed(Message::Frame(frame)).await?;
+        opcode = Data::Continue;
+    }
+    let mut start = 0;
+    while start < data.len() {
+        let end = start.saturating_add(max).min(data.len());
+        let frame = Frame::message(
+            data.slice(start..end),
+            OpCode::Data(opcode),
+            end == data.len(),
+        );
+        tx.feed(Message::Frame(frame)).await?;
+        opcode = Data::Continue;
+        start = end;
+    }
+    Ok(())
+}
+
 /// Send a Engine.IO [`OpenPacket`] to initiate a websocket connection
 async fn init_handshake<S>(
     sid: Sid,
@@ -310,7 +502,15 @@ where
 ///│                                                      │
 ///│            -----  WebSocket frames -----             │
 /// ```
-#[cfg_attr(feature = "tracing", tracing::instrument(skip(socket, ws), fields(sid = socket.id.to_string())))]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "engineio.upgrade",
+        parent = socket.span(),
+        skip(socket, ws),
+        fields(sid = socket.id.to_string(), to = "websocket")
+    )
+)]
 async fn upgrade_handshake<H: EngineIoHandler, S>(
     socket: &Arc<Socket<H::Data>>,
     ws: &mut WebSocketStream<S>,
@@ -334,6 +534,8 @@ where
         p => Err(Error::BadPacket(p))?,
     };
 
+    let _upgrading = socket.start_upgrade();
+
     // send a NOOP packet to any pending polling request so it closes gracefully
     socket.send(Packet::Noop)?;
 
@@ -359,8 +561,8 @@ where
         p => Err(Error::BadPacket(p))?,
     };
 
-    // wait for any polling connection to finish by waiting for the socket to be unlocked
-    let _ = socket.internal_rx.lock().await;
+    // wait for any polling connection to finish by waiting for the receiver to be put back
+    let _ = socket.internal_rx.take().await;
     socket.upgrade_to_websocket();
     Ok(())
 }
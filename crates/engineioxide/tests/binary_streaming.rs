@@ -0,0 +1,188 @@
+//! Tests for the chunked write of large binary packets and messages
+use std::sync::Arc;
+
+use base64::{engine::general_purpose, Engine};
+use bytes::Bytes;
+use engineioxide::{
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    ProtocolVersion, Str,
+};
+use http::{header, Request};
+use http_body_util::{BodyExt, Full};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
+use tower_service::Service;
+
+#[derive(Debug, Clone)]
+struct MyHandler;
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, socket: Arc<Socket<()>>) {
+        println!("socket connect {}", socket.id);
+    }
+    fn on_disconnect(&self, socket: Arc<Socket<()>>, reason: DisconnectReason) {
+        println!("socket disconnect {}: {:?}", socket.id, reason);
+    }
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+fn create_conn(svc: &EngineIoService<MyHandler>) -> DuplexStream {
+    let (client, server) = tokio::io::duplex(1 << 16);
+    let parts = Request::builder()
+        .method("GET")
+        .header("Host", "127.0.0.1")
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", generate_key())
+        .uri("ws://127.0.0.1/engine.io/?EIO=4&transport=websocket")
+        .body(())
+        .unwrap()
+        .into_parts()
+        .0;
+    tokio::spawn(svc.ws_init(server, ProtocolVersion::V4, None, parts));
+    client
+}
+
+/// Read a frame and return its header byte and its payload.
+async fn recv_frame(stream: &mut DuplexStream) -> (u8, Vec<u8>) {
+    let header = stream.read_u16().await.unwrap();
+    let len = match header & 0x7f {
+        126 => stream.read_u16().await.unwrap() as usize,
+        127 => stream.read_u64().await.unwrap() as usize,
+        len => len as usize,
+    };
+    let mut data = vec![0; len];
+    stream.read_exact(&mut data).await.unwrap();
+    ((header >> 8) as u8, data)
+}
+
+#[tokio::test]
+pub async fn ws_fragmented_binary() {
+    let config = EngineIoConfig::builder().ws_max_frame_size(256).build();
+    let svc = EngineIoService::with_config(Arc::new(MyHandler), config);
+    let mut stream = create_conn(&svc);
+    let (_, open) = recv_frame(&mut stream).await;
+    assert!(open.starts_with(b"0{\"sid\":"));
+
+    // Send a masked binary frame of 1000 bytes
+    let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let mask = [1, 2, 3, 4];
+    let payload: Vec<u8> = data
+        .iter()
+        .enumerate()
+        .map(|(i, b)| b ^ mask[i % 4])
+        .collect();
+    stream.write_all(&[0x82, 0x80 | 126]).await.unwrap();
+    stream.write_u16(payload.len() as u16).await.unwrap();
+    stream.write_all(&mask).await.unwrap();
+    stream.write_all(&payload).await.unwrap();
+
+    let mut frames = Vec::new();
+    loop {
+        let (header, payload) = recv_frame(&mut stream).await;
+        // Skip ping packets
+        if header == 0x81 {
+            continue;
+        }
+        frames.push((header, payload));
+        if header & 0x80 != 0 {
+            break;
+        }
+    }
+    let headers: Vec<u8> = frames.iter().map(|(h, _)| *h).collect();
+    assert_eq!(headers, [0x02, 0x00, 0x00, 0x80]);
+    assert!(frames.iter().all(|(_, p)| p.len() <= 256));
+    let payloads: Vec<&[u8]> = frames.iter().map(|(_, p)| &p[..]).collect();
+    assert_eq!(payloads.concat(), data);
+}
+
+#[tokio::test]
+pub async fn polling_chunked_binary() {
+    let mut svc = EngineIoService::new(Arc::new(MyHandler));
+    let mut send_req = |params: String, method: http::Method, body: String| {
+        let req = Request::builder()
+            .method(method)
+            .uri(format!("http://127.0.0.1/engine.io/?EIO=4&{params}"))
+            .body(Full::new(Bytes::from(body)))
+            .unwrap();
+        svc.call(req)
+    };
+
+    let res = send_req("transport=polling".into(), http::Method::GET, "".into()).await;
+    let body = res.unwrap().into_body().collect().await.unwrap().to_bytes();
+    let open: serde_json::Value = serde_json::from_slice(&body[1..]).unwrap();
+    let sid = open["sid"].as_str().unwrap().to_string();
+    let params = format!("transport=polling&sid={sid}");
+
+    let bin = general_purpose::STANDARD.encode(vec![7u8; 40_000]);
+    let body = format!("4hello\x1eb{bin}");
+    let res = send_req(params.clone(), http::Method::POST, body.clone()).await;
+    assert!(res.unwrap().status().is_success());
+
+    let mut res = send_req(params, http::Method::GET, "".into())
+        .await
+        .unwrap();
+    let len: usize = res.headers()[header::CONTENT_LENGTH]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    // The large binary packet is sent as a separate chunk
+    let mut chunks = Vec::new();
+    while let Some(frame) = res.body_mut().frame().await {
+        chunks.push(frame.unwrap().into_data().unwrap());
+    }
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks.concat(), body.as_bytes());
+    assert_eq!(len, body.len());
+}
+
+#[tokio::test]
+pub async fn ws_large_message() {
+    let svc = EngineIoService::new(Arc::new(MyHandler));
+    let mut stream = create_conn(&svc);
+    recv_frame(&mut stream).await; // Open packet
+
+    // Send a masked text frame of 20kb
+    let data = format!("4{}", "a".repeat(20_000));
+    let mask = [1, 2, 3, 4];
+    let payload: Vec<u8> = data
+        .bytes()
+        .enumerate()
+        .map(|(i, b)| b ^ mask[i % 4])
+        .collect();
+    stream.write_all(&[0x81, 0x80 | 126]).await.unwrap();
+    stream.write_u16(payload.len() as u16).await.unwrap();
+    stream.write_all(&mask).await.unwrap();
+    stream.write_all(&payload).await.unwrap();
+
+    // The packet type is sent in its own frame so that the message is not copied
+    let mut frames = Vec::new();
+    loop {
+        let (header, payload) = recv_frame(&mut stream).await;
+        if header == 0x81 {
+            continue;
+        }
+        frames.push((header, payload));
+        if header & 0x80 != 0 {
+            break;
+        }
+    }
+    let headers: Vec<u8> = frames.iter().map(|(h, _)| *h).collect();
+    assert_eq!(headers, [0x01, 0x80]);
+    let payloads: Vec<&[u8]> = frames.iter().map(|(_, p)| &p[..]).collect();
+    assert_eq!(payloads.concat(), data.as_bytes());
+}
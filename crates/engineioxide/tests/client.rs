@@ -0,0 +1,209 @@
+//! Tests for the engine.io client, against a real engineioxide server:
+//! * Handshake and echo on polling and websocket transports
+//! * Upgrade from polling to websocket
+//! * Heartbeat
+//! * Client and server close
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use engineioxide::{
+    client::{Client, ClientBuilder, ClientError, ClientEvent, CloseReason},
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str, TransportType,
+};
+use hyper::server::conn::http1;
+use hyper_util::rt::TokioIo;
+use tokio::{net::TcpListener, sync::mpsc};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+struct MyHandler {
+    disconnect_tx: mpsc::UnboundedSender<DisconnectReason>,
+}
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, _socket: Arc<Socket<()>>) {}
+    fn on_disconnect(&self, _socket: Arc<Socket<()>>, reason: DisconnectReason) {
+        self.disconnect_tx.send(reason).ok();
+    }
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        if msg == "close" {
+            socket.close(DisconnectReason::TransportClose);
+        } else {
+            socket.emit(msg).ok();
+        }
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+/// Spawn a server on a random port and return its url.
+async fn create_server() -> (String, mpsc::UnboundedReceiver<DisconnectReason>) {
+    let (disconnect_tx, disconnect_rx) = mpsc::unbounded_channel();
+    let config = EngineIoConfig::builder()
+        .ping_interval(Duration::from_millis(100))
+        .ping_timeout(Duration::from_millis(100))
+        .build();
+    let svc = EngineIoService::with_config(Arc::new(MyHandler { disconnect_tx }), config);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let svc = svc.clone();
+            tokio::spawn(async move {
+                http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), svc)
+                    .with_upgrades()
+                    .await
+                    .ok();
+            });
+        }
+    });
+    (format!("http://{addr}"), disconnect_rx)
+}
+
+async fn recv<T>(rx: &mut mpsc::UnboundedReceiver<T>) -> T {
+    tokio::time::timeout(TIMEOUT, rx.recv())
+        .await
+        .expect("timeout")
+        .expect("channel closed")
+}
+
+async fn echo(client: &Client, rx: &mut mpsc::UnboundedReceiver<ClientEvent>) {
+    client.emit("hello").unwrap();
+    assert_eq!(recv(rx).await, ClientEvent::Message("hello".into()));
+    client.emit_binary(vec![1, 2, 3]).unwrap();
+    assert_eq!(
+        recv(rx).await,
+        ClientEvent::Binary(Bytes::from_static(&[1, 2, 3]))
+    );
+}
+
+#[tokio::test]
+pub async fn polling() {
+    let (url, _) = create_server().await;
+    let (client, mut rx) = ClientBuilder::new(url)
+        .transports([TransportType::Polling])
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(client.transport(), TransportType::Polling);
+    assert_eq!(client.handshake().upgrades, ["websocket"]);
+    assert_eq!(client.handshake().ping_interval, Duration::from_millis(100));
+    echo(&client, &mut rx).await;
+}
+
+#[tokio::test]
+pub async fn websocket() {
+    let (url, _) = create_server().await;
+    let (client, mut rx) = ClientBuilder::new(url)
+        .transports([TransportType::Websocket])
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(client.transport(), TransportType::Websocket);
+    assert!(client.handshake().upgrades.is_empty());
+    echo(&client, &mut rx).await;
+}
+
+#[tokio::test]
+pub async fn upgrade() {
+    let (url, _) = create_server().await;
+    let (client, mut rx) = Client::builder(url).connect().await.unwrap();
+    assert_eq!(client.transport(), TransportType::Websocket);
+    echo(&client, &mut rx).await;
+
+    let (url, _) = create_server().await;
+    let (client, mut rx) = Client::builder(url).upgrade(false).connect().await.unwrap();
+    assert_eq!(client.transport(), TransportType::Polling);
+    echo(&client, &mut rx).await;
+}
+
+#[tokio::test]
+pub async fn heartbeat() {
+    for transport in [TransportType::Polling, TransportType::Websocket] {
+        let (url, mut disconnect_rx) = create_server().await;
+        let (client, mut rx) = ClientBuilder::new(url)
+            .transports([transport])
+            .connect()
+            .await
+            .unwrap();
+        // Several ping/pong cycles
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(disconnect_rx.try_recv().is_err());
+        echo(&client, &mut rx).await;
+    }
+}
+
+#[tokio::test]
+pub async fn client_close() {
+    for transport in [TransportType::Polling, TransportType::Websocket] {
+        let (url, mut disconnect_rx) = create_server().await;
+        let (client, mut rx) = ClientBuilder::new(url)
+            .transports([transport])
+            .connect()
+            .await
+            .unwrap();
+        client.close();
+        assert_eq!(
+            recv(&mut rx).await,
+            ClientEvent::Close(CloseReason::ClientClose)
+        );
+        assert_eq!(
+            recv(&mut disconnect_rx).await,
+            DisconnectReason::TransportClose
+        );
+        assert!(matches!(client.emit("foo"), Err(ClientError::Closed)));
+    }
+}
+
+#[tokio::test]
+pub async fn server_close() {
+    for transport in [TransportType::Polling, TransportType::Websocket] {
+        let (url, _) = create_server().await;
+        let (client, mut rx) = ClientBuilder::new(url)
+            .transports([transport])
+            .connect()
+            .await
+            .unwrap();
+        client.emit("close").unwrap();
+        let event = recv(&mut rx).await;
+        if transport == TransportType::Websocket {
+            assert_eq!(event, ClientEvent::Close(CloseReason::TransportClose));
+        } else {
+            // The session is removed before the close packet is polled,
+            // so the next polling request may be rejected instead.
+            assert!(matches!(
+                event,
+                ClientEvent::Close(CloseReason::TransportClose | CloseReason::TransportError)
+            ));
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn invalid_url() {
+    let err = ClientBuilder::new("https://127.0.0.1")
+        .connect()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::UnsupportedScheme(_)), "{err:?}");
+
+    let (url, _) = create_server().await;
+    let err = ClientBuilder::new(url)
+        .req_path("/socket.io/")
+        .connect()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Status(_)), "{err:?}");
+}
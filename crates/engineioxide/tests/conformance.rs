@@ -0,0 +1,782 @@
+//! A port of the engine.io [protocol test suite](https://github.com/socketio/engine.io-protocol),
+//! also run in the CI against the `engineioxide-e2e` server with node.js.
+//!
+//! The server is served over in-memory http/1.1 connections, so the suite runs with `cargo test`
+//! without any network or node.js setup. The v3 cases need the `v3` feature.
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use futures_util::{SinkExt, StreamExt};
+use http::{Method, Request};
+use http_body_util::{BodyExt, Full};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+use tokio::io::DuplexStream;
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+const PING_INTERVAL: u64 = 300;
+const PING_TIMEOUT: u64 = 200;
+
+#[derive(Debug, Clone)]
+struct EchoHandler;
+
+impl EngineIoHandler for EchoHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, _socket: Arc<Socket<()>>) {}
+    fn on_disconnect(&self, _socket: Arc<Socket<()>>, _reason: DisconnectReason) {}
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+fn create_server() -> EngineIoService<EchoHandler> {
+    let config = EngineIoConfig::builder()
+        .ping_interval(Duration::from_millis(PING_INTERVAL))
+        .ping_timeout(Duration::from_millis(PING_TIMEOUT))
+        .max_payload(1e6 as u64)
+        .build();
+    EngineIoService::with_config(Arc::new(EchoHandler), config)
+}
+
+/// Serve a new in-memory http/1.1 connection and return its client side.
+fn connect(svc: &EngineIoService<EchoHandler>) -> DuplexStream {
+    let (client, server) = tokio::io::duplex(1 << 16);
+    let conn = hyper::server::conn::http1::Builder::new()
+        .serve_connection(TokioIo::new(server), svc.clone())
+        .with_upgrades();
+    tokio::spawn(conn);
+    client
+}
+
+struct Response {
+    status: u16,
+    #[cfg_attr(not(feature = "v3"), allow(dead_code))]
+    content_type: Option<String>,
+    body: Bytes,
+}
+impl Response {
+    fn text(&self) -> &str {
+        std::str::from_utf8(&self.body).unwrap()
+    }
+}
+
+/// Send a http request on its own connection, like a browser with several pending requests.
+/// Returns `None` if the connection is closed abnormally.
+async fn try_req(
+    svc: &EngineIoService<EchoHandler>,
+    method: Method,
+    query: &str,
+    body: &str,
+) -> Option<Response> {
+    let io = TokioIo::new(connect(svc));
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(io).await.unwrap();
+    tokio::spawn(conn);
+    let req = Request::builder()
+        .method(method)
+        .uri(format!("http://127.0.0.1/engine.io/?{query}"))
+        .header("Host", "127.0.0.1")
+        .body(Full::new(Bytes::copy_from_slice(body.as_bytes())))
+        .unwrap();
+    let res = tokio::time::timeout(Duration::from_secs(2), sender.send_request(req))
+        .await
+        .expect("timeout")
+        .ok()?;
+    let status = res.status().as_u16();
+    let content_type = res
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .map(|v| v.to_str().unwrap().to_string());
+    let body = res.into_body().collect().await.ok()?.to_bytes();
+    Some(Response {
+        status,
+        content_type,
+        body,
+    })
+}
+
+async fn req(
+    svc: &EngineIoService<EchoHandler>,
+    method: Method,
+    query: &str,
+    body: &str,
+) -> Response {
+    try_req(svc, method, query, body)
+        .await
+        .expect("connection closed")
+}
+
+/// Open a websocket connection. It fails if the server rejects the upgrade request.
+async fn try_ws(
+    svc: &EngineIoService<EchoHandler>,
+    query: &str,
+) -> Result<WebSocketStream<DuplexStream>, tokio_tungstenite::tungstenite::Error> {
+    let url = format!("ws://127.0.0.1/engine.io/?{query}");
+    let (ws, _) = tokio_tungstenite::client_async(url, connect(svc)).await?;
+    Ok(ws)
+}
+
+async fn ws(svc: &EngineIoService<EchoHandler>, query: &str) -> WebSocketStream<DuplexStream> {
+    try_ws(svc, query).await.expect("websocket upgrade failed")
+}
+
+/// Receive the next message, `None` if the connection is closed.
+async fn recv(ws: &mut WebSocketStream<DuplexStream>) -> Option<Message> {
+    match tokio::time::timeout(Duration::from_secs(1), ws.next())
+        .await
+        .expect("timeout")
+    {
+        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => None,
+        Some(Ok(msg)) => Some(msg),
+    }
+}
+
+async fn recv_text(ws: &mut WebSocketStream<DuplexStream>) -> String {
+    match recv(ws).await {
+        Some(Message::Text(text)) => text.to_string(),
+        msg => panic!("expected a text message, got {msg:?}"),
+    }
+}
+
+async fn send(ws: &mut WebSocketStream<DuplexStream>, msg: impl Into<Message>) {
+    ws.send(msg.into()).await.unwrap();
+}
+
+/// Wait for the server to close a websocket connection.
+async fn assert_ws_closed(ws: &mut WebSocketStream<DuplexStream>) {
+    let closed = async { while recv(ws).await.is_some() {} };
+    tokio::time::timeout(Duration::from_secs(2), closed)
+        .await
+        .expect("the connection was not closed");
+}
+
+/// Either the websocket upgrade is rejected or the connection is closed right away.
+async fn assert_ws_rejected(svc: &EngineIoService<EchoHandler>, query: &str) {
+    if let Ok(mut ws) = try_ws(svc, query).await {
+        assert_ws_closed(&mut ws).await;
+    }
+}
+
+/// Check an open packet and return its sid.
+fn check_open_packet(packet: &str, upgrades: &[&str]) -> String {
+    assert_eq!(&packet[..1], "0", "{packet}");
+    let value: Value = serde_json::from_str(&packet[1..]).unwrap();
+    let mut keys: Vec<_> = value.as_object().unwrap().keys().collect();
+    keys.sort();
+    let expected = [
+        "maxPayload",
+        "pingInterval",
+        "pingTimeout",
+        "sid",
+        "upgrades",
+    ];
+    assert_eq!(keys, expected);
+    assert_eq!(value["upgrades"], json!(upgrades));
+    assert_eq!(value["pingInterval"], PING_INTERVAL);
+    assert_eq!(value["pingTimeout"], PING_TIMEOUT);
+    assert_eq!(value["maxPayload"], 1_000_000);
+    value["sid"].as_str().expect("sid is a string").to_string()
+}
+
+mod v4 {
+    use super::*;
+
+    fn polling(sid: &str) -> String {
+        format!("EIO=4&transport=polling&sid={sid}")
+    }
+
+    /// Open a polling session and receive its first ping packet.
+    async fn init_polling_session(svc: &EngineIoService<EchoHandler>) -> String {
+        let res = req(svc, Method::GET, "EIO=4&transport=polling", "").await;
+        let sid = check_open_packet(res.text(), &["websocket"]);
+        let ping = req(svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!((ping.status, ping.text()), (200, "2"));
+        sid
+    }
+
+    #[tokio::test]
+    async fn polling_handshake() {
+        let svc = create_server();
+        let res = req(&svc, Method::GET, "EIO=4&transport=polling", "").await;
+        assert_eq!(res.status, 200);
+        check_open_packet(res.text(), &["websocket"]);
+    }
+
+    #[tokio::test]
+    async fn polling_handshake_invalid_eio() {
+        let svc = create_server();
+        for query in ["transport=polling", "EIO=abc&transport=polling"] {
+            let res = req(&svc, Method::GET, query, "").await;
+            assert_eq!(res.status, 400, "{query}");
+        }
+    }
+
+    #[cfg(not(feature = "v3"))]
+    #[tokio::test]
+    async fn polling_handshake_v3_disabled() {
+        let svc = create_server();
+        let res = req(&svc, Method::GET, "EIO=3&transport=polling", "").await;
+        assert_eq!(res.status, 400);
+    }
+
+    #[tokio::test]
+    async fn polling_handshake_invalid_transport() {
+        let svc = create_server();
+        for query in ["EIO=4", "EIO=4&transport=abc"] {
+            let res = req(&svc, Method::GET, query, "").await;
+            assert_eq!(res.status, 400, "{query}");
+        }
+    }
+
+    #[tokio::test]
+    async fn polling_handshake_invalid_method() {
+        let svc = create_server();
+        for method in [Method::POST, Method::PUT] {
+            let res = req(&svc, method.clone(), "EIO=4&transport=polling", "").await;
+            assert_eq!(res.status, 400, "{method}");
+        }
+    }
+
+    #[tokio::test]
+    async fn polling_unknown_sid() {
+        let svc = create_server();
+        let res = req(&svc, Method::GET, &polling("AAAAAAAAAAAAAAAA"), "").await;
+        assert_eq!(res.status, 400);
+    }
+
+    #[tokio::test]
+    async fn ws_handshake() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=4&transport=websocket").await;
+        check_open_packet(&recv_text(&mut ws).await, &[]);
+    }
+
+    #[tokio::test]
+    async fn ws_handshake_invalid_query() {
+        let svc = create_server();
+        for query in [
+            "transport=websocket",
+            "EIO=abc&transport=websocket",
+            "EIO=4",
+            "EIO=4&transport=abc",
+        ] {
+            assert_ws_rejected(&svc, query).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn polling_plain_text_packet() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc).await;
+        let res = req(&svc, Method::POST, &polling(&sid), "4hello").await;
+        assert_eq!((res.status, res.text()), (200, "ok"));
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!((res.status, res.text()), (200, "4hello"));
+    }
+
+    #[tokio::test]
+    async fn polling_several_plain_text_packets() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc).await;
+        let payload = "4test1\x1e4test2\x1e4test3";
+        let res = req(&svc, Method::POST, &polling(&sid), payload).await;
+        assert_eq!((res.status, res.text()), (200, "ok"));
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!((res.status, res.text()), (200, payload));
+    }
+
+    #[tokio::test]
+    async fn polling_plain_text_and_binary_packets() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc).await;
+        let payload = "4hello\x1ebAQIDBA==";
+        let res = req(&svc, Method::POST, &polling(&sid), payload).await;
+        assert_eq!((res.status, res.text()), (200, "ok"));
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!((res.status, res.text()), (200, payload));
+    }
+
+    #[tokio::test]
+    async fn polling_invalid_packet() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc).await;
+        if let Some(res) = try_req(&svc, Method::POST, &polling(&sid), "abc").await {
+            assert_eq!(res.status, 400);
+        }
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!(res.status, 400);
+    }
+
+    #[tokio::test]
+    async fn polling_duplicate_poll() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc).await;
+        let query = polling(&sid);
+        let (res1, res2) = tokio::join!(req(&svc, Method::GET, &query, ""), async {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            req(&svc, Method::GET, &format!("{query}&t=burst"), "").await
+        });
+        assert_eq!((res1.status, res1.text()), (200, "1"));
+        assert!([400, 500].contains(&res2.status), "{}", res2.status);
+
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!(res.status, 400);
+    }
+
+    #[tokio::test]
+    async fn ws_plain_text_packet() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=4&transport=websocket").await;
+        recv_text(&mut ws).await; // handshake
+        assert_eq!(recv_text(&mut ws).await, "2");
+        send(&mut ws, "4hello").await;
+        assert_eq!(recv_text(&mut ws).await, "4hello");
+    }
+
+    #[tokio::test]
+    async fn ws_binary_packet() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=4&transport=websocket").await;
+        recv_text(&mut ws).await; // handshake
+        assert_eq!(recv_text(&mut ws).await, "2");
+        send(&mut ws, vec![1, 2, 3, 4]).await;
+        assert_eq!(recv(&mut ws).await, Some(vec![1, 2, 3, 4].into()));
+    }
+
+    #[tokio::test]
+    async fn ws_invalid_packet() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=4&transport=websocket").await;
+        recv_text(&mut ws).await; // handshake
+        assert_eq!(recv_text(&mut ws).await, "2");
+        send(&mut ws, "abc").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn polling_heartbeat() {
+        let svc = create_server();
+        let res = req(&svc, Method::GET, "EIO=4&transport=polling", "").await;
+        let sid = check_open_packet(res.text(), &["websocket"]);
+        for _ in 0..3 {
+            let res = req(&svc, Method::GET, &polling(&sid), "").await;
+            assert_eq!((res.status, res.text()), (200, "2"));
+            let res = req(&svc, Method::POST, &polling(&sid), "3").await;
+            assert_eq!(res.status, 200);
+        }
+    }
+
+    #[tokio::test]
+    async fn polling_ping_timeout() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc).await;
+        tokio::time::sleep(Duration::from_millis(PING_INTERVAL + PING_TIMEOUT)).await;
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert!(
+            res.status == 400 || (res.status == 200 && res.text() == "1"),
+            "{} {}",
+            res.status,
+            res.text()
+        );
+    }
+
+    #[tokio::test]
+    async fn ws_heartbeat() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=4&transport=websocket").await;
+        recv_text(&mut ws).await; // handshake
+        for _ in 0..3 {
+            assert_eq!(recv_text(&mut ws).await, "2");
+            send(&mut ws, "3").await;
+        }
+    }
+
+    #[tokio::test]
+    async fn ws_ping_timeout() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=4&transport=websocket").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn polling_close() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc).await;
+        let query = polling(&sid);
+        let (poll, _) = tokio::join!(req(&svc, Method::GET, &query, ""), async {
+            // The close packet is sent once the polling request is pending
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            req(&svc, Method::POST, &query, "1").await
+        });
+        assert_eq!((poll.status, poll.text()), (200, "6"));
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!(res.status, 400);
+    }
+
+    #[tokio::test]
+    async fn ws_close() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=4&transport=websocket").await;
+        recv_text(&mut ws).await; // handshake
+        assert_eq!(recv_text(&mut ws).await, "2");
+        send(&mut ws, "1").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn upgrade() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc).await;
+        let mut ws = ws(&svc, &format!("EIO=4&transport=websocket&sid={sid}")).await;
+        send(&mut ws, "2probe").await;
+        assert_eq!(recv_text(&mut ws).await, "3probe");
+
+        // A noop packet cleanly ends the pending polling request
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!((res.status, res.text()), (200, "6"));
+
+        send(&mut ws, "5").await;
+        send(&mut ws, "4hello").await;
+        assert_eq!(recv_text(&mut ws).await, "4hello");
+    }
+
+    #[tokio::test]
+    async fn upgrade_ignores_polling() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc).await;
+        let mut ws = ws(&svc, &format!("EIO=4&transport=websocket&sid={sid}")).await;
+        send(&mut ws, "2probe").await;
+        send(&mut ws, "5").await;
+
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!(res.status, 400);
+
+        send(&mut ws, "4hello").await;
+        assert_eq!(recv_text(&mut ws).await, "3probe");
+        assert_eq!(recv_text(&mut ws).await, "4hello");
+    }
+
+    #[tokio::test]
+    async fn upgrade_ignores_websocket() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc).await;
+        let query = format!("EIO=4&transport=websocket&sid={sid}");
+        let mut ws = ws(&svc, &query).await;
+        send(&mut ws, "2probe").await;
+        send(&mut ws, "5").await;
+
+        assert_ws_rejected(&svc, &query).await;
+
+        send(&mut ws, "4hello").await;
+        assert_eq!(recv_text(&mut ws).await, "3probe");
+        assert_eq!(recv_text(&mut ws).await, "4hello");
+    }
+}
+
+#[cfg(feature = "v3")]
+mod v3 {
+    use super::*;
+
+    fn polling(sid: &str) -> String {
+        format!("EIO=3&transport=polling&sid={sid}")
+    }
+
+    /// Split a payload with a single packet into its length and its packet.
+    fn decode_payload(payload: &str) -> (&str, &str) {
+        payload.split_once(':').expect("invalid payload")
+    }
+
+    /// Open a polling session, with the binary packets encoded in base64 if `b64` is true.
+    async fn init_polling_session(svc: &EngineIoService<EchoHandler>, b64: bool) -> String {
+        let query = if b64 {
+            "EIO=3&transport=polling&b64=1"
+        } else {
+            "EIO=3&transport=polling"
+        };
+        let res = req(svc, Method::GET, query, "").await;
+        let (_, packet) = decode_payload(res.text());
+        check_open_packet(packet, &["websocket"])
+    }
+
+    #[tokio::test]
+    async fn polling_handshake() {
+        let svc = create_server();
+        let res = req(&svc, Method::GET, "EIO=3&transport=polling&b64=1", "").await;
+        assert_eq!(res.status, 200);
+        let (len, packet) = decode_payload(res.text());
+        assert_eq!(len, packet.chars().count().to_string());
+        check_open_packet(packet, &["websocket"]);
+    }
+
+    #[tokio::test]
+    async fn polling_handshake_invalid_transport() {
+        let svc = create_server();
+        for query in ["EIO=3", "EIO=3&transport=abc"] {
+            let res = req(&svc, Method::GET, query, "").await;
+            assert_eq!(res.status, 400, "{query}");
+        }
+    }
+
+    #[tokio::test]
+    async fn jsonp_handshake() {
+        let svc = create_server();
+        let res = req(&svc, Method::GET, "EIO=3&transport=polling&j=1", "").await;
+        assert_eq!(res.status, 200);
+        assert_eq!(
+            res.content_type.as_deref(),
+            Some("text/javascript; charset=UTF-8")
+        );
+        let text = res.text();
+        assert!(
+            text.starts_with("___eio[1](") && text.ends_with(");"),
+            "{text}"
+        );
+        let payload: String = serde_json::from_str(&text[10..text.len() - 2]).unwrap();
+        let (_, packet) = decode_payload(&payload);
+        check_open_packet(packet, &["websocket"]);
+    }
+
+    #[tokio::test]
+    async fn polling_handshake_invalid_method() {
+        let svc = create_server();
+        let res = req(&svc, Method::POST, "EIO=3&transport=polling", "").await;
+        assert_eq!(res.status, 400);
+    }
+
+    #[tokio::test]
+    async fn ws_handshake() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=3&transport=websocket").await;
+        check_open_packet(&recv_text(&mut ws).await, &[]);
+    }
+
+    #[tokio::test]
+    async fn ws_handshake_invalid_query() {
+        let svc = create_server();
+        for query in ["EIO=abc&transport=websocket", "EIO=3&transport=abc"] {
+            assert_ws_rejected(&svc, query).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn polling_plain_text_packet() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, true).await;
+        let res = req(&svc, Method::POST, &polling(&sid), "6:4hello").await;
+        assert_eq!((res.status, res.text()), (200, "ok"));
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!((res.status, res.text()), (200, "6:4hello"));
+    }
+
+    #[tokio::test]
+    async fn polling_several_plain_text_packets() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, true).await;
+        let payload = "6:4test16:4test26:4test3";
+        let res = req(&svc, Method::POST, &polling(&sid), payload).await;
+        assert_eq!((res.status, res.text()), (200, "ok"));
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!((res.status, res.text()), (200, payload));
+    }
+
+    #[tokio::test]
+    async fn polling_plain_text_and_b64_packets() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, true).await;
+        let payload = "6:4hello10:b4AQIDBA==";
+        let res = req(&svc, Method::POST, &polling(&sid), payload).await;
+        assert_eq!((res.status, res.text()), (200, "ok"));
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!((res.status, res.text()), (200, payload));
+    }
+
+    #[tokio::test]
+    async fn polling_plain_text_and_binary_packets() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, false).await;
+        let payload = "6:4hello10:b4AQIDBA==";
+        let res = req(&svc, Method::POST, &polling(&sid), payload).await;
+        assert_eq!((res.status, res.text()), (200, "ok"));
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!(res.status, 200);
+        // string packet: 0, its length digits, 255, the packet
+        // binary packet: 1, its length digits, 255, the packet type and the data
+        assert_eq!(
+            res.body.as_ref(),
+            [0, 6, 255, 52, 104, 101, 108, 108, 111, 1, 5, 255, 4, 1, 2, 3, 4]
+        );
+    }
+
+    #[tokio::test]
+    async fn polling_invalid_packet() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, true).await;
+        if let Some(res) = try_req(&svc, Method::POST, &polling(&sid), "abc").await {
+            assert_eq!(res.status, 400);
+        }
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!(res.status, 400);
+    }
+
+    #[tokio::test]
+    async fn ws_plain_text_packet() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=3&transport=websocket").await;
+        recv_text(&mut ws).await; // handshake
+        send(&mut ws, "4hello").await;
+        assert_eq!(recv_text(&mut ws).await, "4hello");
+    }
+
+    #[tokio::test]
+    async fn ws_binary_packet() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=3&transport=websocket").await;
+        recv_text(&mut ws).await; // handshake
+        send(&mut ws, vec![4, 1, 2, 3, 4]).await;
+        assert_eq!(recv(&mut ws).await, Some(vec![4, 1, 2, 3, 4].into()));
+    }
+
+    #[tokio::test]
+    async fn ws_b64_packet() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=3&transport=websocket&b64=1").await;
+        recv_text(&mut ws).await; // handshake
+        send(&mut ws, "b4AQIDBA==").await;
+        assert_eq!(recv_text(&mut ws).await, "b4AQIDBA==");
+    }
+
+    #[tokio::test]
+    async fn ws_invalid_packet() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=3&transport=websocket").await;
+        recv_text(&mut ws).await; // handshake
+        send(&mut ws, "abc").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn polling_heartbeat() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, true).await;
+        // With the v3 protocol the client sends the ping packets
+        for _ in 0..3 {
+            let res = req(&svc, Method::POST, &polling(&sid), "1:2").await;
+            assert_eq!(res.status, 200);
+            let res = req(&svc, Method::GET, &polling(&sid), "").await;
+            assert_eq!((res.status, res.text()), (200, "1:3"));
+        }
+    }
+
+    #[tokio::test]
+    async fn polling_ping_timeout() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, true).await;
+        let timeout = PING_INTERVAL + PING_TIMEOUT + 100;
+        tokio::time::sleep(Duration::from_millis(timeout)).await;
+        let res = req(&svc, Method::POST, &polling(&sid), "1:2").await;
+        assert_eq!(res.status, 400);
+    }
+
+    #[tokio::test]
+    async fn ws_heartbeat() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=3&transport=websocket").await;
+        recv_text(&mut ws).await; // handshake
+        for _ in 0..3 {
+            send(&mut ws, "2").await;
+            assert_eq!(recv_text(&mut ws).await, "3");
+        }
+    }
+
+    #[tokio::test]
+    async fn ws_ping_timeout() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=3&transport=websocket").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn polling_close() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, true).await;
+        let query = polling(&sid);
+        let (poll, _) = tokio::join!(req(&svc, Method::GET, &query, ""), async {
+            // The close packet is sent once the polling request is pending
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            req(&svc, Method::POST, &query, "1:1").await
+        });
+        assert_eq!((poll.status, poll.text()), (200, "1:6"));
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!(res.status, 400);
+    }
+
+    #[tokio::test]
+    async fn ws_close() {
+        let svc = create_server();
+        let mut ws = ws(&svc, "EIO=3&transport=websocket").await;
+        recv_text(&mut ws).await; // handshake
+        send(&mut ws, "1").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn upgrade() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, true).await;
+        let mut ws = ws(&svc, &format!("EIO=3&transport=websocket&sid={sid}")).await;
+        send(&mut ws, "2probe").await;
+        assert_eq!(recv_text(&mut ws).await, "3probe");
+
+        // A noop packet cleanly ends the pending polling request
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!((res.status, res.text()), (200, "1:6"));
+
+        send(&mut ws, "5").await;
+        send(&mut ws, "4hello").await;
+        assert_eq!(recv_text(&mut ws).await, "4hello");
+    }
+
+    #[tokio::test]
+    async fn upgrade_ignores_polling() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, true).await;
+        let mut ws = ws(&svc, &format!("EIO=3&transport=websocket&sid={sid}")).await;
+        send(&mut ws, "2probe").await;
+        assert_eq!(recv_text(&mut ws).await, "3probe");
+        send(&mut ws, "5").await;
+
+        let res = req(&svc, Method::GET, &polling(&sid), "").await;
+        assert_eq!(res.status, 400);
+
+        send(&mut ws, "4hello").await;
+        assert_eq!(recv_text(&mut ws).await, "4hello");
+    }
+
+    #[tokio::test]
+    async fn upgrade_ignores_websocket() {
+        let svc = create_server();
+        let sid = init_polling_session(&svc, true).await;
+        let query = format!("EIO=3&transport=websocket&sid={sid}");
+        let mut ws = ws(&svc, &query).await;
+        send(&mut ws, "2probe").await;
+        assert_eq!(recv_text(&mut ws).await, "3probe");
+        send(&mut ws, "5").await;
+
+        assert_ws_rejected(&svc, &query).await;
+
+        send(&mut ws, "4hello").await;
+        assert_eq!(recv_text(&mut ws).await, "4hello");
+    }
+}
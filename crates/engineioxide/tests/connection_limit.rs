@@ -0,0 +1,150 @@
+//! Tests for the global and per-IP connection limits:
+//! * A handshake exceeding a limit is rejected with a 503 status and a `Retry-After` header
+//! * The slot of a session is released when it is closed
+//! * The per-IP limit is only applied to the requests with a `SocketAddr` extension
+//! * The per-IP limit uses the ip forwarded by the trusted proxies
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use http::{Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use tower_service::Service;
+
+#[derive(Debug)]
+struct MyHandler;
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, _socket: Arc<Socket<()>>) {}
+    fn on_disconnect(&self, _socket: Arc<Socket<()>>, _reason: DisconnectReason) {}
+    fn on_message(self: &Arc<Self>, _msg: Str, _socket: Arc<Socket<()>>) {}
+    fn on_binary(self: &Arc<Self>, _data: Bytes, _socket: Arc<Socket<()>>) {}
+}
+
+async fn send_req(svc: &mut EngineIoService<MyHandler>, req: Request<String>) -> Response<String> {
+    let req = req.map(|body| Full::new(Bytes::from(body)));
+    let (parts, body) = svc.call(req).await.unwrap().into_parts();
+    let body = body.collect().await.unwrap().to_bytes();
+    Response::from_parts(parts, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn handshake_req(addr: Option<[u8; 4]>) -> Request<String> {
+    let mut req = Request::builder()
+        .method(Method::GET)
+        .uri("http://127.0.0.1/engine.io/?EIO=4&transport=polling")
+        .body(String::new())
+        .unwrap();
+    if let Some(ip) = addr {
+        req.extensions_mut().insert(SocketAddr::from((ip, 1234)));
+    }
+    req
+}
+
+fn close_req(sid: &str) -> Request<String> {
+    Request::builder()
+        .method(Method::POST)
+        .uri(format!(
+            "http://127.0.0.1/engine.io/?EIO=4&transport=polling&sid={sid}"
+        ))
+        .body("1".to_string())
+        .unwrap()
+}
+
+fn sid(res: &Response<String>) -> &str {
+    let sid = res.body().split(r#""sid":""#).nth(1).unwrap();
+    sid.split('"').next().unwrap()
+}
+
+#[tokio::test]
+pub async fn max_connections() {
+    let config = EngineIoConfig::builder()
+        .max_connections(2)
+        .connection_retry_after(Duration::from_millis(1500))
+        .build();
+    let mut svc = EngineIoService::with_config(Arc::new(MyHandler), config);
+
+    let res1 = send_req(&mut svc, handshake_req(None)).await;
+    assert_eq!(res1.status(), StatusCode::OK);
+    let res2 = send_req(&mut svc, handshake_req(Some([127, 0, 0, 1]))).await;
+    assert_eq!(res2.status(), StatusCode::OK);
+
+    let res = send_req(&mut svc, handshake_req(None)).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.headers()["Retry-After"], "2");
+    let body: serde_json::Value = serde_json::from_str(res.body()).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({ "code": 3, "message": "Too many connections" })
+    );
+
+    let counters = svc.config().connection_limits.counters();
+    assert_eq!(counters.open(), 2);
+    assert_eq!(counters.rejected(), 1);
+
+    // Closing a session releases its slot
+    let res = send_req(&mut svc, close_req(sid(&res1))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(svc.config().connection_limits.counters().open(), 1);
+    let res = send_req(&mut svc, handshake_req(None)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn max_connections_per_ip() {
+    let config = EngineIoConfig::builder().max_connections_per_ip(1).build();
+    let mut svc = EngineIoService::with_config(Arc::new(MyHandler), config);
+
+    let res1 = send_req(&mut svc, handshake_req(Some([127, 0, 0, 1]))).await;
+    assert_eq!(res1.status(), StatusCode::OK);
+    let res = send_req(&mut svc, handshake_req(Some([127, 0, 0, 1]))).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.headers()["Retry-After"], "5");
+
+    // Other ips and requests without ip are not limited
+    let res = send_req(&mut svc, handshake_req(Some([127, 0, 0, 2]))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = send_req(&mut svc, handshake_req(None)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let counters = svc.config().connection_limits.counters();
+    assert_eq!(counters.open_from([127, 0, 0, 1].into()), 1);
+    assert_eq!(counters.open(), 3);
+
+    send_req(&mut svc, close_req(sid(&res1))).await;
+    let res = send_req(&mut svc, handshake_req(Some([127, 0, 0, 1]))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+pub async fn max_connections_per_forwarded_ip() {
+    let config = EngineIoConfig::builder()
+        .max_connections_per_ip(1)
+        .trusted_proxies(["10.0.0.1".parse().unwrap()])
+        .build();
+    let mut svc = EngineIoService::with_config(Arc::new(MyHandler), config);
+    let forwarded_req = |ip: &str| {
+        let mut req = handshake_req(Some([10, 0, 0, 1]));
+        req.headers_mut()
+            .insert("X-Forwarded-For", ip.parse().unwrap());
+        req
+    };
+
+    let res = send_req(&mut svc, forwarded_req("1.1.1.1")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = send_req(&mut svc, forwarded_req("2.2.2.2")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = send_req(&mut svc, forwarded_req("1.1.1.1")).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let counters = svc.config().connection_limits.counters();
+    assert_eq!(counters.open_from([1, 1, 1, 1].into()), 1);
+    assert_eq!(counters.open_from([10, 0, 0, 1].into()), 0);
+}
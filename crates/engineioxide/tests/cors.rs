@@ -0,0 +1,110 @@
+//! Tests for the built-in CORS handling
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::{AllowedOrigins, CorsConfig, EngineIoConfig},
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use http::{header::*, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Empty};
+use tower_service::Service;
+
+#[derive(Debug, Clone)]
+struct MyHandler;
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, socket: Arc<Socket<()>>) {
+        println!("socket connect {}", socket.id);
+    }
+    fn on_disconnect(&self, socket: Arc<Socket<()>>, reason: DisconnectReason) {
+        println!("socket disconnect {}: {:?}", socket.id, reason);
+    }
+    fn on_message(self: &Arc<Self>, _msg: Str, _socket: Arc<Socket<()>>) {}
+    fn on_binary(self: &Arc<Self>, _data: Bytes, _socket: Arc<Socket<()>>) {}
+}
+
+fn create_server(cors: CorsConfig) -> EngineIoService<MyHandler> {
+    let config = EngineIoConfig::builder().cors(cors).build();
+    EngineIoService::with_config(Arc::new(MyHandler), config)
+}
+
+async fn send_req(
+    svc: &mut EngineIoService<MyHandler>,
+    method: Method,
+    headers: &[(HeaderName, &'static str)],
+) -> Response<Bytes> {
+    let mut req = Request::builder()
+        .method(method)
+        .uri("http://127.0.0.1/engine.io/?EIO=4&transport=polling");
+    for (name, value) in headers {
+        req = req.header(name, *value);
+    }
+    let req = req.body(Empty::<Bytes>::new()).unwrap();
+    let (parts, body) = svc.call(req).await.unwrap().into_parts();
+    Response::from_parts(parts, body.collect().await.unwrap().to_bytes())
+}
+
+#[tokio::test]
+pub async fn cors_preflight() {
+    let mut svc = create_server(CorsConfig {
+        allowed_origins: AllowedOrigins::List(vec!["https://example.com".into()]),
+        credentials: true,
+        max_age: Some(Duration::from_secs(3600)),
+    });
+
+    let res = send_req(
+        &mut svc,
+        Method::OPTIONS,
+        &[
+            (ORIGIN, "https://example.com"),
+            (ACCESS_CONTROL_REQUEST_METHOD, "POST"),
+            (ACCESS_CONTROL_REQUEST_HEADERS, "content-type"),
+        ],
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let headers = res.headers();
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "GET, POST");
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+    assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "3600");
+
+    // Origin not allowed
+    let res = send_req(
+        &mut svc,
+        Method::OPTIONS,
+        &[
+            (ORIGIN, "https://other.com"),
+            (ACCESS_CONTROL_REQUEST_METHOD, "POST"),
+        ],
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(!res.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+}
+
+#[tokio::test]
+pub async fn cors_polling_request() {
+    let mut svc = create_server(CorsConfig::default());
+
+    let res = send_req(&mut svc, Method::GET, &[(ORIGIN, "https://example.com")]).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    assert!(res.body().starts_with(b"0{\"sid\":"));
+
+    // Errors also carry CORS headers so the client can read them
+    let res = send_req(&mut svc, Method::POST, &[(ORIGIN, "https://example.com")]).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+
+    // Same origin requests are not modified
+    let res = send_req(&mut svc, Method::GET, &[]).await;
+    assert!(!res.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+}
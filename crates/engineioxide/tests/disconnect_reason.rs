@@ -4,16 +4,21 @@
 //! * Transport close
 //! * Multiple http polling
 //! * Packet parsing
+//! * Websocket message too large
+//! * Slow consumer
+//! * Server close with a close reason
 
 use std::{sync::Arc, time::Duration};
 
 use bytes::Bytes;
 use engineioxide::{
+    config::EngineIoConfig,
     handler::EngineIoHandler,
-    socket::{DisconnectReason, Socket},
+    service::EngineIoService,
+    socket::{CloseReason, DisconnectReason, Socket},
     Str,
 };
-use futures_util::SinkExt;
+use futures_util::{SinkExt, StreamExt};
 use tokio::sync::mpsc;
 
 mod fixture;
@@ -41,7 +46,11 @@ impl EngineIoHandler for MyHandler {
 
     fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
         println!("Ping pong message {:?}", msg);
-        socket.emit(msg).ok();
+        if msg == "close" {
+            socket.close_with_reason(CloseReason::new(4001, "closed by the server"));
+        } else {
+            socket.emit(msg).ok();
+        }
     }
 
     fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
@@ -152,6 +161,48 @@ pub async fn multiple_http_polling() {
     assert_eq!(data, DisconnectReason::MultipleHttpPollingError);
 }
 
+#[tokio::test]
+pub async fn aborted_http_polling() {
+    let (disconnect_tx, mut rx) = mpsc::channel(10);
+    let mut svc = create_server(MyHandler { disconnect_tx }).await;
+    let sid = create_polling_connection(&mut svc).await;
+    send_req(
+        &mut svc,
+        format!("transport=polling&sid={sid}"),
+        http::Method::GET,
+        None,
+    )
+    .await; // we eat the first ping from the server.
+
+    // The http server drops the request future when the client closes the connection
+    let poll = send_req(
+        &mut svc,
+        format!("transport=polling&sid={sid}"),
+        http::Method::GET,
+        None,
+    );
+    tokio::time::timeout(Duration::from_millis(10), poll)
+        .await
+        .unwrap_err();
+
+    send_req(
+        &mut svc,
+        format!("transport=polling&sid={sid}"),
+        http::Method::POST,
+        Some("4hello".into()),
+    )
+    .await;
+    let data = send_req(
+        &mut svc,
+        format!("transport=polling&sid={sid}"),
+        http::Method::GET,
+        None,
+    )
+    .await;
+    assert_eq!(data, "hello");
+    assert!(rx.try_recv().is_err());
+}
+
 #[tokio::test]
 pub async fn polling_packet_parsing() {
     let (disconnect_tx, mut rx) = mpsc::channel(10);
@@ -190,3 +241,98 @@ pub async fn ws_packet_parsing() {
 
     assert_eq!(data, DisconnectReason::PacketParsingError);
 }
+
+#[tokio::test]
+pub async fn ws_message_too_large() {
+    let (disconnect_tx, mut rx) = mpsc::channel(10);
+    let config = EngineIoConfig::builder().ws_max_message_size(10).build();
+    let mut svc = EngineIoService::with_config(Arc::new(MyHandler { disconnect_tx }), config);
+    let mut stream = create_ws_connection(&mut svc).await;
+    stream.next().await.unwrap().unwrap(); // Open packet
+
+    stream
+        .send(Message::Text("4aaaaaaaaaaaaaaaaaaaa".into()))
+        .await
+        .unwrap();
+
+    let data = tokio::time::timeout(Duration::from_millis(10), rx.recv())
+        .await
+        .expect("timeout waiting for DisconnectReason::PacketParsingError")
+        .unwrap();
+    assert_eq!(data, DisconnectReason::PacketParsingError);
+
+    // The connection is closed with a "message too big" close frame
+    let msg = tokio::time::timeout(Duration::from_millis(10), stream.next())
+        .await
+        .expect("timeout waiting for the close frame")
+        .unwrap()
+        .unwrap();
+    let Message::Close(Some(frame)) = msg else {
+        panic!("expected a close frame, got {msg:?}");
+    };
+    assert_eq!(u16::from(frame.code), 1009);
+}
+
+#[tokio::test]
+pub async fn ws_server_close() {
+    let (disconnect_tx, mut rx) = mpsc::channel(10);
+    let mut svc = create_server(MyHandler { disconnect_tx }).await;
+    let mut stream = create_ws_connection(&mut svc).await;
+    stream.next().await.unwrap().unwrap(); // Open packet
+
+    stream.send(Message::Text("4close".into())).await.unwrap();
+
+    let data = tokio::time::timeout(Duration::from_millis(10), rx.recv())
+        .await
+        .expect("timeout waiting for DisconnectReason::ServerClose")
+        .unwrap();
+    assert_eq!(data, DisconnectReason::ServerClose);
+
+    let msg = tokio::time::timeout(Duration::from_millis(10), stream.next())
+        .await
+        .expect("timeout waiting for the close frame")
+        .unwrap()
+        .unwrap();
+    let Message::Close(Some(frame)) = msg else {
+        panic!("expected a close frame with a reason: {msg:?}");
+    };
+    assert_eq!(u16::from(frame.code), 4001);
+    assert_eq!(frame.reason.as_str(), "closed by the server");
+}
+
+#[tokio::test]
+pub async fn polling_slow_consumer() {
+    let (disconnect_tx, mut rx) = mpsc::channel(10);
+    let config = EngineIoConfig::builder()
+        .max_buffer_size(1)
+        .slow_consumer_timeout(Duration::from_millis(50))
+        .build();
+    let mut svc = EngineIoService::with_config(Arc::new(MyHandler { disconnect_tx }), config);
+    let sid = create_polling_connection(&mut svc).await;
+
+    // The second echo can't be buffered because the client is not polling
+    send_req(
+        &mut svc,
+        format!("transport=polling&sid={sid}"),
+        http::Method::POST,
+        Some("4a\x1e4b".into()),
+    )
+    .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(rx.try_recv().is_err());
+
+    send_req(
+        &mut svc,
+        format!("transport=polling&sid={sid}"),
+        http::Method::POST,
+        Some("4c".into()),
+    )
+    .await;
+
+    let data = tokio::time::timeout(Duration::from_millis(10), rx.recv())
+        .await
+        .expect("timeout waiting for DisconnectReason::SlowConsumer")
+        .unwrap();
+
+    assert_eq!(data, DisconnectReason::SlowConsumer);
+}
@@ -0,0 +1,108 @@
+//! Tests for the handoff of polling requests between servers without sticky sessions
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    handoff::{HandoffRequest, HandoffResponse},
+    service::EngineIoService,
+    sid::Sid,
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::{BodyExt, Full};
+use tower_service::Service;
+
+/// A server that forwards the unknown sessions to its peer.
+#[derive(Debug, Default)]
+struct Node {
+    peer: OnceLock<Arc<Node>>,
+    sockets: RwLock<HashMap<Sid, Arc<Socket<()>>>>,
+}
+
+impl EngineIoHandler for Node {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, socket: Arc<Socket<()>>) {
+        self.sockets.write().unwrap().insert(socket.id, socket);
+    }
+    fn on_disconnect(&self, socket: Arc<Socket<()>>, _reason: DisconnectReason) {
+        self.sockets.write().unwrap().remove(&socket.id);
+    }
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+
+    async fn forward_polling(&self, sid: Sid, req: HandoffRequest) -> Option<HandoffResponse> {
+        let peer = self.peer.get()?;
+        let socket = peer.sockets.read().unwrap().get(&sid).cloned()?;
+        Some(socket.handoff(peer, req).await)
+    }
+}
+
+async fn send_req(
+    svc: &mut EngineIoService<Node>,
+    params: &str,
+    method: Method,
+    body: &'static str,
+) -> (StatusCode, String) {
+    let req = Request::builder()
+        .method(method)
+        .uri(format!("http://127.0.0.1/engine.io/?EIO=4&{params}"))
+        .body(Full::new(Bytes::from_static(body.as_bytes())))
+        .unwrap();
+    let res = svc.call(req).await.unwrap();
+    let status = res.status();
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+pub async fn polling_handoff() {
+    let config = EngineIoConfig::builder()
+        .ping_interval(Duration::from_millis(300))
+        .build();
+    let (a, b) = (Arc::new(Node::default()), Arc::new(Node::default()));
+    a.peer.set(b.clone()).ok();
+    let mut svc_a = EngineIoService::with_config(a, config.clone());
+    let mut svc_b = EngineIoService::with_config(b, config);
+
+    let (_, open) = send_req(&mut svc_b, "transport=polling", Method::GET, "").await;
+    let open: serde_json::Value = serde_json::from_str(&open[1..]).unwrap();
+    let params = format!("transport=polling&sid={}", open["sid"].as_str().unwrap());
+
+    // The first ping is sent right after the handshake
+    let (status, body) = send_req(&mut svc_a, &params, Method::GET, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "2");
+
+    let (status, body) = send_req(&mut svc_a, &params, Method::POST, "3\x1e4hello").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "ok");
+
+    let (status, body) = send_req(&mut svc_a, &params, Method::GET, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "4hello");
+}
+
+#[tokio::test]
+pub async fn polling_handoff_unknown_session() {
+    let a = Arc::new(Node::default());
+    a.peer.set(Arc::new(Node::default())).ok();
+    let mut svc = EngineIoService::new(a);
+
+    let params = format!("transport=polling&sid={}", Sid::new());
+    let (status, _) = send_req(&mut svc, &params, Method::GET, "").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,209 @@
+//! Tests for the synchronous and asynchronous handshake filters:
+//! * Rejected polling and websocket handshakes are answered with the status and JSON body of the rejection
+//! * No session is created for a rejected handshake
+//! * The requests of an accepted session are not filtered
+//! * Asynchronous filters are awaited before answering the handshake
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    handshake::{async_filter, HandshakeRejection},
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use http::{header::CONTENT_TYPE, request::Parts, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Empty};
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
+use tower_service::Service;
+
+#[derive(Debug)]
+struct MyHandler {
+    connections: Arc<AtomicUsize>,
+}
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, _socket: Arc<Socket<()>>) {
+        self.connections.fetch_add(1, Ordering::SeqCst);
+    }
+    fn on_disconnect(&self, _socket: Arc<Socket<()>>, _reason: DisconnectReason) {}
+    fn on_message(self: &Arc<Self>, _msg: Str, _socket: Arc<Socket<()>>) {}
+    fn on_binary(self: &Arc<Self>, _data: Bytes, _socket: Arc<Socket<()>>) {}
+}
+
+/// Only accept the handshakes with a `x-token` header, the others are rejected
+/// with a 503 status if the token is `maintenance` or a 403 status otherwise.
+fn filter(req: &Parts) -> Result<(), HandshakeRejection> {
+    match req.headers.get("x-token") {
+        Some(token) if token == "maintenance" => Err(HandshakeRejection::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            serde_json::json!({ "maintenance": true }),
+        )),
+        Some(_) => Ok(()),
+        None => Err(HandshakeRejection::forbidden()),
+    }
+}
+
+/// Simulate a token lookup, only the `secret` token is valid.
+fn allow_request(req: &Parts) -> impl std::future::Future<Output = Result<(), HandshakeRejection>> {
+    let token = req.headers.get("x-token").cloned();
+    async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        match token {
+            Some(token) if token == "secret" => Ok(()),
+            _ => Err(HandshakeRejection::with_message(
+                StatusCode::UNAUTHORIZED,
+                4,
+                "Invalid token",
+            )),
+        }
+    }
+}
+
+fn create_server() -> (EngineIoService<MyHandler>, Arc<AtomicUsize>) {
+    create_server_with(EngineIoConfig::builder().handshake_filter(filter).build())
+}
+
+fn create_server_with(config: EngineIoConfig) -> (EngineIoService<MyHandler>, Arc<AtomicUsize>) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let handler = MyHandler {
+        connections: connections.clone(),
+    };
+    let svc = EngineIoService::with_config(Arc::new(handler), config);
+    (svc, connections)
+}
+
+async fn send_req(
+    svc: &mut EngineIoService<MyHandler>,
+    req: http::request::Builder,
+) -> Response<String> {
+    let req = req.body(Empty::<Bytes>::new()).unwrap();
+    let (parts, body) = svc.call(req).await.unwrap().into_parts();
+    let body = body.collect().await.unwrap().to_bytes();
+    Response::from_parts(parts, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn polling_req(query: &str) -> http::request::Builder {
+    Request::builder()
+        .method(Method::GET)
+        .uri(format!("http://127.0.0.1/engine.io/?EIO=4&{query}"))
+}
+
+fn ws_req() -> http::request::Builder {
+    Request::builder()
+        .method(Method::GET)
+        .uri("ws://127.0.0.1/engine.io/?EIO=4&transport=websocket")
+        .header("Host", "127.0.0.1")
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", generate_key())
+}
+
+#[tokio::test]
+pub async fn polling_handshake_rejected() {
+    let (mut svc, connections) = create_server();
+
+    let res = send_req(&mut svc, polling_req("transport=polling")).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+    let body: serde_json::Value = serde_json::from_str(res.body()).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({ "code": 4, "message": "Forbidden" })
+    );
+
+    let req = polling_req("transport=polling").header("x-token", "maintenance");
+    let res = send_req(&mut svc, req).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.body(), r#"{"maintenance":true}"#);
+    assert_eq!(connections.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+pub async fn polling_handshake_accepted() {
+    let (mut svc, connections) = create_server();
+
+    let req = polling_req("transport=polling").header("x-token", "secret");
+    let res = send_req(&mut svc, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.body().starts_with('0'), "{}", res.body());
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    // The polling requests of the session don't need the token
+    let sid = res.body().split(r#""sid":""#).nth(1).unwrap();
+    let sid = sid.split('"').next().unwrap();
+    let res = send_req(
+        &mut svc,
+        polling_req(&format!("transport=polling&sid={sid}")),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.body(), "2");
+}
+
+#[tokio::test]
+pub async fn ws_handshake_rejected() {
+    let (mut svc, connections) = create_server();
+
+    let res = send_req(&mut svc, ws_req()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let body: serde_json::Value = serde_json::from_str(res.body()).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({ "code": 4, "message": "Forbidden" })
+    );
+
+    let res = send_req(&mut svc, ws_req().header("x-token", "maintenance")).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(connections.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+pub async fn allow_request_polling() {
+    let config = EngineIoConfig::builder()
+        .handshake_filter(async_filter(allow_request))
+        .build();
+    let (mut svc, connections) = create_server_with(config);
+
+    let req = polling_req("transport=polling").header("x-token", "wrong");
+    let res = send_req(&mut svc, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let body: serde_json::Value = serde_json::from_str(res.body()).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({ "code": 4, "message": "Invalid token" })
+    );
+    assert_eq!(connections.load(Ordering::SeqCst), 0);
+
+    let req = polling_req("transport=polling").header("x-token", "secret");
+    let res = send_req(&mut svc, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.body().starts_with('0'), "{}", res.body());
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+pub async fn allow_request_ws() {
+    let config = EngineIoConfig::builder()
+        .handshake_filter(async_filter(allow_request))
+        .build();
+    let (mut svc, connections) = create_server_with(config);
+
+    let res = send_req(&mut svc, ws_req()).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(connections.load(Ordering::SeqCst), 0);
+
+    let res = send_req(&mut svc, ws_req().header("x-token", "secret")).await;
+    assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+}
@@ -0,0 +1,124 @@
+//! Tests for the heartbeat settings of the sessions:
+//! * The websocket sessions can have their own ping interval and timeout
+//! * The pings of the idle sessions are backed off up to the idle ping interval
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use futures_util::{SinkExt, StreamExt};
+use tokio::time::Instant;
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+#[allow(dead_code)]
+mod fixture;
+
+use fixture::{create_ws_connection, send_req, StreamImpl};
+
+#[derive(Debug, Clone)]
+struct MyHandler;
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, _socket: Arc<Socket<()>>) {}
+    fn on_disconnect(&self, _socket: Arc<Socket<()>>, _reason: DisconnectReason) {}
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+async fn recv(ws: &mut WebSocketStream<StreamImpl>) -> String {
+    match tokio::time::timeout(Duration::from_secs(1), ws.next()).await {
+        Ok(Some(Ok(Message::Text(msg)))) => msg.to_string(),
+        msg => panic!("unexpected message: {msg:?}"),
+    }
+}
+
+/// Wait for the next ping, answer it and return the instant at which it was received.
+async fn pong(ws: &mut WebSocketStream<StreamImpl>) -> Instant {
+    assert_eq!(recv(ws).await, "2");
+    let at = Instant::now();
+    ws.send(Message::Text("3".into())).await.unwrap();
+    at
+}
+
+fn handshake_value(open: &str, field: &str) -> u64 {
+    let open: serde_json::Value = serde_json::from_str(open.trim_start_matches('0')).unwrap();
+    open[field].as_u64().unwrap()
+}
+
+#[tokio::test]
+pub async fn ws_heartbeat() {
+    let config = EngineIoConfig::builder()
+        .ping_interval(Duration::from_secs(1))
+        .ping_timeout(Duration::from_secs(1))
+        .ws_ping_interval(Duration::from_millis(50))
+        .ws_ping_timeout(Duration::from_millis(500))
+        .build();
+    let mut svc = EngineIoService::with_config(Arc::new(MyHandler), config);
+    let mut ws = create_ws_connection(&mut svc).await;
+    let open = recv(&mut ws).await;
+    assert_eq!(handshake_value(&open, "pingInterval"), 50);
+    assert_eq!(handshake_value(&open, "pingTimeout"), 500);
+    let first = pong(&mut ws).await;
+    let second = pong(&mut ws).await;
+    assert!(second - first < Duration::from_millis(500));
+}
+
+#[tokio::test]
+pub async fn polling_handshake() {
+    let config = EngineIoConfig::builder()
+        .ping_interval(Duration::from_millis(300))
+        .ping_timeout(Duration::from_millis(200))
+        .ws_ping_interval(Duration::from_secs(10))
+        .build();
+    let mut svc = EngineIoService::with_config(Arc::new(MyHandler), config);
+    let open = send_req(
+        &mut svc,
+        "transport=polling".to_string(),
+        http::Method::GET,
+        None,
+    )
+    .await;
+    assert_eq!(handshake_value(&open, "pingInterval"), 300);
+    assert_eq!(handshake_value(&open, "pingTimeout"), 200);
+}
+
+#[tokio::test]
+pub async fn idle_ping_backoff() {
+    let config = EngineIoConfig::builder()
+        .ping_interval(Duration::from_millis(20))
+        .ping_timeout(Duration::from_secs(1))
+        .idle_ping_interval(Duration::from_millis(160))
+        .build();
+    let mut svc = EngineIoService::with_config(Arc::new(MyHandler), config);
+    let mut ws = create_ws_connection(&mut svc).await;
+    let open = recv(&mut ws).await;
+    assert_eq!(handshake_value(&open, "pingInterval"), 160);
+
+    // 20ms, 40ms, 80ms, 160ms, 160ms
+    let mut last = pong(&mut ws).await;
+    for _ in 0..5 {
+        last = pong(&mut ws).await;
+    }
+    let backed_off = pong(&mut ws).await;
+    assert!(backed_off - last >= Duration::from_millis(120));
+
+    // A message resets the ping interval after the next ping
+    ws.send(Message::Text("4hello".into())).await.unwrap();
+    assert_eq!(recv(&mut ws).await, "4hello");
+    let next = pong(&mut ws).await;
+    let reset = pong(&mut ws).await;
+    assert!(reset - next < Duration::from_millis(120));
+}
@@ -0,0 +1,145 @@
+//! Tests for the polling transport over http/2:
+//! * Requests are multiplexed on a single connection without any `Connection` header
+//! * An aborted polling request releases the session for the next one
+//! * Websocket upgrades are rejected because they only exist with http/1.1
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use engineioxide::{
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use http::{Method, Request};
+use http_body_util::{BodyExt, Full};
+use hyper::client::conn::http2::SendRequest;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone)]
+struct MyHandler {
+    disconnect_tx: mpsc::UnboundedSender<DisconnectReason>,
+}
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, _socket: Arc<Socket<()>>) {}
+    fn on_disconnect(&self, _socket: Arc<Socket<()>>, reason: DisconnectReason) {
+        self.disconnect_tx.send(reason).ok();
+    }
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+/// Serve an engine.io service over an in-memory http/2 connection and return its client.
+async fn create_conn() -> (
+    SendRequest<Full<Bytes>>,
+    mpsc::UnboundedReceiver<DisconnectReason>,
+) {
+    let (disconnect_tx, disconnect_rx) = mpsc::unbounded_channel();
+    let svc = EngineIoService::new(Arc::new(MyHandler { disconnect_tx }));
+    let (client, server) = tokio::io::duplex(1 << 16);
+    tokio::spawn(
+        hyper::server::conn::http2::Builder::new(TokioExecutor::new())
+            .serve_connection(TokioIo::new(server), svc),
+    );
+    let (sender, conn) =
+        hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(client))
+            .await
+            .unwrap();
+    tokio::spawn(conn);
+    (sender, disconnect_rx)
+}
+
+fn req(method: Method, query: &str, body: &str) -> Request<Full<Bytes>> {
+    Request::builder()
+        .method(method)
+        .uri(format!("http://127.0.0.1/engine.io/?EIO=4&{query}"))
+        .body(Full::new(Bytes::copy_from_slice(body.as_bytes())))
+        .unwrap()
+}
+
+async fn send_req(
+    sender: &mut SendRequest<Full<Bytes>>,
+    method: Method,
+    query: &str,
+    body: &str,
+) -> (u16, String) {
+    let res = tokio::time::timeout(
+        Duration::from_millis(500),
+        sender.send_request(req(method, query, body)),
+    )
+    .await
+    .expect("timeout")
+    .unwrap();
+    let status = res.status().as_u16();
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+/// Open a polling session and return the query to poll it.
+async fn open_session(sender: &mut SendRequest<Full<Bytes>>) -> String {
+    let (status, open) = send_req(sender, Method::GET, "transport=polling", "").await;
+    assert_eq!(status, 200);
+    let sid = open.split(r#""sid":""#).nth(1).unwrap();
+    let sid = sid.split('"').next().unwrap();
+    let query = format!("transport=polling&sid={sid}");
+
+    // The first ping is sent right after the handshake
+    let (_, ping) = send_req(sender, Method::GET, &query, "").await;
+    assert_eq!(ping, "2");
+    query
+}
+
+#[tokio::test]
+pub async fn polling() {
+    let (mut sender, _) = create_conn().await;
+    let query = open_session(&mut sender).await;
+
+    let (status, body) = send_req(&mut sender, Method::POST, &query, "4hello").await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let (status, body) = send_req(&mut sender, Method::GET, &query, "").await;
+    assert_eq!((status, body.as_str()), (200, "4hello"));
+}
+
+#[tokio::test]
+pub async fn aborted_polling_request() {
+    let (mut sender, mut disconnect_rx) = create_conn().await;
+    let query = open_session(&mut sender).await;
+
+    // Dropping the response future resets the http/2 stream
+    let poll = sender.send_request(req(Method::GET, &query, ""));
+    tokio::time::timeout(Duration::from_millis(50), poll)
+        .await
+        .unwrap_err();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // The session is not considered as being polled twice
+    let (status, _) = send_req(&mut sender, Method::POST, &query, "4hello").await;
+    assert_eq!(status, 200);
+    let (status, body) = send_req(&mut sender, Method::GET, &query, "").await;
+    assert_eq!((status, body.as_str()), (200, "4hello"));
+    assert!(disconnect_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+pub async fn websocket_rejected() {
+    let (mut sender, _) = create_conn().await;
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri("http://127.0.0.1/engine.io/?EIO=4&transport=websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ==")
+        .body(Full::new(Bytes::new()))
+        .unwrap();
+    let res = sender.send_request(req).await.unwrap();
+    assert_eq!(res.status(), 400);
+}
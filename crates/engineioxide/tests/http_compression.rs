@@ -0,0 +1,154 @@
+//! Tests for the compression of polling payloads
+use std::{io::Read, sync::Arc};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::{EngineIoConfig, HttpCompressionConfig},
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use http::{header, Request, Response};
+use http_body_util::{BodyExt, Full};
+use tower_service::Service;
+
+#[derive(Debug, Clone)]
+struct MyHandler;
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, socket: Arc<Socket<()>>) {
+        println!("socket connect {}", socket.id);
+    }
+    fn on_disconnect(&self, socket: Arc<Socket<()>>, reason: DisconnectReason) {
+        println!("socket disconnect {}: {:?}", socket.id, reason);
+    }
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+async fn send_req(
+    svc: &mut EngineIoService<MyHandler>,
+    params: &str,
+    method: http::Method,
+    body: Option<String>,
+    accept_encoding: Option<&str>,
+) -> Response<Bytes> {
+    let mut req = Request::builder()
+        .method(method)
+        .uri(format!("http://127.0.0.1/engine.io/?EIO=4&{params}"));
+    if let Some(accept_encoding) = accept_encoding {
+        req = req.header(header::ACCEPT_ENCODING, accept_encoding);
+    }
+    let req = req
+        .body(Full::new(Bytes::from(body.unwrap_or_default())))
+        .unwrap();
+    let (parts, body) = svc.call(req).await.unwrap().into_parts();
+    Response::from_parts(parts, body.collect().await.unwrap().to_bytes())
+}
+
+async fn create_session(svc: &mut EngineIoService<MyHandler>) -> String {
+    let res = send_req(svc, "transport=polling", http::Method::GET, None, None).await;
+    let open: serde_json::Value = serde_json::from_slice(&res.body()[1..]).unwrap();
+    open["sid"].as_str().unwrap().to_string()
+}
+
+async fn echo(
+    svc: &mut EngineIoService<MyHandler>,
+    sid: &str,
+    msg: &str,
+    accept_encoding: Option<&str>,
+) -> Response<Bytes> {
+    let params = format!("transport=polling&sid={sid}");
+    let res = send_req(svc, &params, http::Method::POST, Some(msg.into()), None).await;
+    assert_eq!(res.body(), "ok");
+    send_req(svc, &params, http::Method::GET, None, accept_encoding).await
+}
+
+fn create_server() -> EngineIoService<MyHandler> {
+    let config = EngineIoConfig::builder()
+        .http_compression(HttpCompressionConfig {
+            threshold: 100,
+            ..Default::default()
+        })
+        .build();
+    EngineIoService::with_config(Arc::new(MyHandler), config)
+}
+
+#[tokio::test]
+pub async fn polling_gzip() {
+    let mut svc = create_server();
+    let sid = create_session(&mut svc).await;
+    let msg = format!("4{}", "hello world".repeat(20));
+
+    let res = echo(&mut svc, &sid, &msg, Some("gzip, deflate, br")).await;
+    assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+    assert_eq!(res.headers()[header::VARY], "Accept-Encoding");
+    assert_eq!(
+        res.headers()[header::CONTENT_LENGTH],
+        res.body().len().to_string()
+    );
+    assert!(res.body().len() < msg.len());
+    let mut body = String::new();
+    GzDecoder::new(&res.body()[..])
+        .read_to_string(&mut body)
+        .unwrap();
+    assert_eq!(body, msg);
+}
+
+#[tokio::test]
+pub async fn polling_deflate() {
+    let mut svc = create_server();
+    let sid = create_session(&mut svc).await;
+    let msg = format!("4{}", "hello world".repeat(20));
+
+    let res = echo(&mut svc, &sid, &msg, Some("deflate")).await;
+    assert_eq!(res.headers()[header::CONTENT_ENCODING], "deflate");
+    let mut body = String::new();
+    ZlibDecoder::new(&res.body()[..])
+        .read_to_string(&mut body)
+        .unwrap();
+    assert_eq!(body, msg);
+}
+
+#[tokio::test]
+pub async fn polling_gzip_large_payload() {
+    let mut svc = create_server();
+    let sid = create_session(&mut svc).await;
+    let msg = format!("4{}", "hello world".repeat(7_000));
+
+    let res = echo(&mut svc, &sid, &msg, Some("gzip")).await;
+    assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+    let mut body = String::new();
+    GzDecoder::new(&res.body()[..])
+        .read_to_string(&mut body)
+        .unwrap();
+    assert_eq!(body, msg);
+}
+
+#[tokio::test]
+pub async fn polling_no_compression() {
+    let mut svc = create_server();
+    let sid = create_session(&mut svc).await;
+
+    // Below the threshold
+    let res = echo(&mut svc, &sid, "4hello", Some("gzip")).await;
+    assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+    assert_eq!(res.headers()[header::VARY], "Accept-Encoding");
+    assert_eq!(res.body(), "4hello");
+
+    // Not accepted by the client
+    let msg = format!("4{}", "hello world".repeat(20));
+    let res = echo(&mut svc, &sid, &msg, None).await;
+    assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+    assert_eq!(res.body(), msg.as_str());
+}
@@ -0,0 +1,107 @@
+//! Tests for the metrics recorded with the `metrics` feature
+use std::sync::Arc;
+
+use bytes::Bytes;
+use engineioxide::{
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    metrics::*,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use http::Request;
+use http_body_util::{BodyExt, Full};
+use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+use tower_service::Service;
+
+#[derive(Debug, Clone)]
+struct MyHandler;
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, socket: Arc<Socket<()>>) {
+        println!("socket connect {}", socket.id);
+    }
+    fn on_disconnect(&self, socket: Arc<Socket<()>>, reason: DisconnectReason) {
+        println!("socket disconnect {}: {:?}", socket.id, reason);
+    }
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        // The second message is dropped because the buffer is full
+        socket.emit(msg.clone()).ok();
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+fn get(snapshotter: &Snapshotter, name: &str, labels: &[(&str, &str)]) -> Option<DebugValue> {
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .find(|(key, _, _, _)| {
+            let key = key.key();
+            key.name() == name
+                && key.labels().count() == labels.len()
+                && key.labels().all(|l| labels.contains(&(l.key(), l.value())))
+        })
+        .map(|(_, _, _, value)| value)
+}
+
+async fn send_req(
+    svc: &mut EngineIoService<MyHandler>,
+    params: &str,
+    method: http::Method,
+    body: &'static str,
+) -> String {
+    let req = Request::builder()
+        .method(method)
+        .uri(format!("http://127.0.0.1/engine.io/?EIO=4&{params}"))
+        .body(Full::new(Bytes::from_static(body.as_bytes())))
+        .unwrap();
+    let body = svc.call(req).await.unwrap().into_body();
+    String::from_utf8(body.collect().await.unwrap().to_bytes().to_vec()).unwrap()
+}
+
+#[tokio::test]
+pub async fn polling_metrics() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let _guard = metrics::set_default_local_recorder(&recorder);
+
+    let config = EngineIoConfig::builder().max_buffer_size(1).build();
+    let mut svc = EngineIoService::with_config(Arc::new(MyHandler), config);
+
+    let open = send_req(&mut svc, "transport=polling", http::Method::GET, "").await;
+    let open: serde_json::Value = serde_json::from_str(&open[1..]).unwrap();
+    let params = format!("transport=polling&sid={}", open["sid"].as_str().unwrap());
+    let sessions = get(&snapshotter, SESSIONS, &[("transport", "polling")]);
+    assert!(matches!(sessions, Some(DebugValue::Gauge(v)) if v.into_inner() == 1.0));
+
+    send_req(&mut svc, &params, http::Method::POST, "4hello").await;
+    let received = get(&snapshotter, PACKETS_RECEIVED, &[("type", "message")]);
+    assert_eq!(received, Some(DebugValue::Counter(1)));
+    assert_eq!(
+        get(&snapshotter, PACKETS_DROPPED, &[]),
+        Some(DebugValue::Counter(1))
+    );
+
+    let payload = send_req(&mut svc, &params, http::Method::GET, "").await;
+    assert_eq!(payload, "4hello");
+    // Histograms are drained by each snapshot so they are checked first
+    let size = get(&snapshotter, POLLING_PAYLOAD_SIZE, &[]);
+    let sent = get(&snapshotter, PACKETS_SENT, &[("type", "message")]);
+    assert_eq!(sent, Some(DebugValue::Counter(1)));
+    assert!(
+        matches!(size, Some(DebugValue::Histogram(v)) if v.len() == 1 && v[0].into_inner() == 6.0)
+    );
+
+    send_req(&mut svc, &params, http::Method::POST, "1").await;
+    let sessions = get(&snapshotter, SESSIONS, &[("transport", "polling")]);
+    assert!(matches!(sessions, Some(DebugValue::Gauge(v)) if v.into_inner() == 0.0));
+}
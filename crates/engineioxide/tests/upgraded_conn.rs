@@ -0,0 +1,80 @@
+//! Tests for websocket sessions over a connection given with the [`UpgradedConn`] extension,
+//! as done by the integrations with frameworks that are not based on hyper.
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use engineioxide::{
+    handler::EngineIoHandler,
+    service::{EngineIoService, UpgradedConn},
+    socket::{DisconnectReason, Socket},
+    Str,
+};
+use futures_util::{SinkExt, StreamExt};
+use http::Request;
+use http_body_util::Empty;
+use tokio::io::DuplexStream;
+use tokio_tungstenite::{
+    tungstenite::{handshake::client::generate_key, protocol::Role, Message},
+    WebSocketStream,
+};
+use tower_service::Service;
+
+#[derive(Debug, Clone)]
+struct MyHandler;
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, _socket: Arc<Socket<()>>) {}
+    fn on_disconnect(&self, _socket: Arc<Socket<()>>, _reason: DisconnectReason) {}
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+/// Receive the next message, skipping the pings that can be sent at any time.
+async fn recv(ws: &mut WebSocketStream<DuplexStream>) -> Message {
+    loop {
+        let msg = tokio::time::timeout(Duration::from_millis(200), ws.next())
+            .await
+            .expect("timeout")
+            .expect("stream closed")
+            .unwrap();
+        if msg != Message::Text("2".into()) {
+            return msg;
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn ws_over_upgraded_conn() {
+    let mut svc = EngineIoService::new(Arc::new(MyHandler));
+    let (client, server) = tokio::io::duplex(1 << 16);
+    let req = Request::builder()
+        .method("GET")
+        .header("Host", "127.0.0.1")
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", generate_key())
+        .uri("ws://127.0.0.1/engine.io/?EIO=4&transport=websocket")
+        .extension(UpgradedConn::new(server))
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    let res = svc.call(req).await.unwrap();
+    assert_eq!(res.status(), 101);
+
+    let mut ws = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+    let Message::Text(open) = recv(&mut ws).await else {
+        panic!("expected an open packet");
+    };
+    assert!(open.starts_with('0'), "{open}");
+
+    ws.send(Message::Text("4hello".into())).await.unwrap();
+    assert_eq!(recv(&mut ws).await, Message::Text("4hello".into()));
+}
@@ -0,0 +1,302 @@
+//! Tests for the engine.io v3 protocol quirks:
+//! * Websocket only clients that skip the polling handshake
+//! * Clients asking for base64 encoded binary packets with the `b64` query param
+//! * JSONP polling clients, selected with the `j` query param
+//!
+//! It also replays the payload fixtures of the official engine.io v3 test suite,
+//! also run end to end in `e2e/engineioxide/test-suites/v3.ts`.
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    ProtocolVersion, Str,
+};
+use futures_util::{SinkExt, StreamExt};
+use http::{Method, Request};
+use http_body_util::{BodyExt, Either, Empty, Full};
+use tokio::io::DuplexStream;
+use tokio_tungstenite::{
+    tungstenite::{handshake::client::generate_key, protocol::Role, Message},
+    WebSocketStream,
+};
+use tower_service::Service;
+
+#[derive(Debug, Clone)]
+struct MyHandler;
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, _socket: Arc<Socket<()>>) {}
+    fn on_disconnect(&self, _socket: Arc<Socket<()>>, _reason: DisconnectReason) {}
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+fn create_server() -> EngineIoService<MyHandler> {
+    let config = EngineIoConfig::builder()
+        .ping_interval(Duration::from_millis(300))
+        .ping_timeout(Duration::from_millis(200))
+        .build();
+    EngineIoService::with_config(Arc::new(MyHandler), config)
+}
+
+/// Open a websocket connection without any polling handshake.
+async fn create_ws_conn(
+    svc: &EngineIoService<MyHandler>,
+    query: &str,
+) -> WebSocketStream<DuplexStream> {
+    let (client, server) = tokio::io::duplex(1 << 16);
+    let parts = Request::builder()
+        .method("GET")
+        .header("Host", "127.0.0.1")
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", generate_key())
+        .uri(format!(
+            "ws://127.0.0.1/engine.io/?EIO=3&transport=websocket{query}"
+        ))
+        .body(())
+        .unwrap()
+        .into_parts()
+        .0;
+    tokio::spawn(svc.ws_init(server, ProtocolVersion::V3, None, parts));
+    WebSocketStream::from_raw_socket(client, Role::Client, None).await
+}
+
+async fn recv(ws: &mut WebSocketStream<DuplexStream>) -> Message {
+    tokio::time::timeout(Duration::from_millis(200), ws.next())
+        .await
+        .expect("timeout")
+        .expect("stream closed")
+        .unwrap()
+}
+
+async fn send_req(
+    svc: &mut EngineIoService<MyHandler>,
+    method: Method,
+    query: &str,
+    body: Option<&str>,
+) -> (u16, String) {
+    let (status, body) = send_raw_req(svc, method, query, body).await;
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+async fn send_raw_req(
+    svc: &mut EngineIoService<MyHandler>,
+    method: Method,
+    query: &str,
+    body: Option<&str>,
+) -> (u16, Bytes) {
+    let body = match body {
+        Some(b) => Either::Left(Full::new(VecDeque::from(b.as_bytes().to_vec()))),
+        None => Either::Right(Empty::<VecDeque<u8>>::new()),
+    };
+    let req = Request::builder()
+        .method(method)
+        .uri(format!("http://127.0.0.1/engine.io/?EIO=3&{query}"))
+        .body(body)
+        .unwrap();
+    let res = svc.call(req).await.unwrap();
+    let status = res.status().as_u16();
+    (status, res.into_body().collect().await.unwrap().to_bytes())
+}
+
+/// Open a polling session and return the query of its requests.
+async fn open_polling_session(svc: &mut EngineIoService<MyHandler>, query: &str) -> String {
+    let (status, open) =
+        send_req(svc, Method::GET, &format!("transport=polling{query}"), None).await;
+    assert_eq!(status, 200);
+    let sid = open
+        .split(r#""sid":""#)
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .unwrap();
+    format!("transport=polling{query}&sid={sid}")
+}
+
+/// The payloads of the official engine.io v3 test suite, echoed by the server
+/// to `b64` polling clients.
+const POLLING_FIXTURES: &[&str] = &[
+    "6:4hello",
+    "6:4test16:4test26:4test3",
+    "6:4hello10:b4AQIDBA==",
+];
+
+#[tokio::test]
+pub async fn polling_fixtures() {
+    let mut svc = create_server();
+    for &fixture in POLLING_FIXTURES {
+        let query = open_polling_session(&mut svc, "&b64=1").await;
+        let (status, body) = send_req(&mut svc, Method::POST, &query, Some(fixture)).await;
+        assert_eq!((status, body.as_str()), (200, "ok"), "{fixture}");
+        let (status, body) = send_req(&mut svc, Method::GET, &query, None).await;
+        assert_eq!((status, body.as_str()), (200, fixture));
+    }
+}
+
+#[tokio::test]
+pub async fn polling_binary_fixture() {
+    let mut svc = create_server();
+    let query = open_polling_session(&mut svc, "").await;
+    let fixture = "6:4hello10:b4AQIDBA==";
+    let (status, _) = send_req(&mut svc, Method::POST, &query, Some(fixture)).await;
+    assert_eq!(status, 200);
+    let (status, body) = send_raw_req(&mut svc, Method::GET, &query, None).await;
+    assert_eq!(status, 200);
+    // A string packet of 6 bytes: "4hello", and a binary packet of 5 bytes: 4 [1, 2, 3, 4]
+    assert_eq!(
+        body[..],
+        [0, 6, 255, 52, 104, 101, 108, 108, 111, 1, 5, 255, 4, 1, 2, 3, 4]
+    );
+}
+
+#[tokio::test]
+pub async fn polling_invalid_packet_fixture() {
+    let mut svc = create_server();
+    let query = open_polling_session(&mut svc, "&b64=1").await;
+    let (status, _) = send_req(&mut svc, Method::POST, &query, Some("abc")).await;
+    assert_eq!(status, 400);
+    let (status, _) = send_req(&mut svc, Method::GET, &query, None).await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+pub async fn websocket_fixtures() {
+    let svc = create_server();
+    let mut ws = create_ws_conn(&svc, "").await;
+    recv(&mut ws).await; // handshake
+    ws.send(Message::Text("4hello".into())).await.unwrap();
+    assert_eq!(recv(&mut ws).await, Message::Text("4hello".into()));
+    ws.send(Message::Binary(vec![4, 1, 2, 3, 4].into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        recv(&mut ws).await,
+        Message::Binary(vec![4, 1, 2, 3, 4].into())
+    );
+
+    let mut ws = create_ws_conn(&svc, "&b64=1").await;
+    recv(&mut ws).await; // handshake
+    ws.send(Message::Text("b4AQIDBA==".into())).await.unwrap();
+    assert_eq!(recv(&mut ws).await, Message::Text("b4AQIDBA==".into()));
+
+    let mut ws = create_ws_conn(&svc, "").await;
+    recv(&mut ws).await; // handshake
+    ws.send(Message::Text("abc".into())).await.unwrap();
+    let msg = tokio::time::timeout(Duration::from_millis(200), ws.next())
+        .await
+        .expect("timeout");
+    assert!(
+        matches!(msg, None | Some(Ok(Message::Close(_))) | Some(Err(_))),
+        "{msg:?}"
+    );
+}
+
+#[tokio::test]
+pub async fn websocket_only_client() {
+    let svc = create_server();
+    let mut ws = create_ws_conn(&svc, "").await;
+
+    let Message::Text(open) = recv(&mut ws).await else {
+        panic!("expected an open packet");
+    };
+    assert!(open.starts_with('0'), "{open}");
+    assert!(open.contains(r#""upgrades":[]"#), "{open}");
+
+    // With the v3 protocol the client sends the ping packets
+    ws.send(Message::Text("2".into())).await.unwrap();
+    assert_eq!(recv(&mut ws).await, Message::Text("3".into()));
+
+    ws.send(Message::Text("4hello".into())).await.unwrap();
+    assert_eq!(recv(&mut ws).await, Message::Text("4hello".into()));
+
+    // Binary frames are prefixed with the packet type
+    ws.send(Message::Binary(vec![4, 1, 2, 3].into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        recv(&mut ws).await,
+        Message::Binary(vec![4, 1, 2, 3].into())
+    );
+}
+
+#[tokio::test]
+pub async fn websocket_b64() {
+    let svc = create_server();
+    let mut ws = create_ws_conn(&svc, "&b64=1").await;
+    recv(&mut ws).await; // Open packet
+
+    // Binary packets are exchanged as base64 text frames
+    ws.send(Message::Text("b4AQID".into())).await.unwrap();
+    assert_eq!(recv(&mut ws).await, Message::Text("b4AQID".into()));
+}
+
+#[tokio::test]
+pub async fn websocket_base64_packet_without_b64() {
+    let svc = create_server();
+    let mut ws = create_ws_conn(&svc, "").await;
+    recv(&mut ws).await; // Open packet
+
+    // A base64 text frame is accepted even if the client did not ask for b64
+    ws.send(Message::Text("b4AQID".into())).await.unwrap();
+    assert_eq!(
+        recv(&mut ws).await,
+        Message::Binary(vec![4, 1, 2, 3].into())
+    );
+}
+
+#[tokio::test]
+pub async fn polling_b64() {
+    let mut svc = create_server();
+    let query = open_polling_session(&mut svc, "&b64=1").await;
+
+    let (status, _) = send_req(&mut svc, Method::POST, &query, Some("6:b4AQID")).await;
+    assert_eq!(status, 200);
+    let (_, body) = send_req(&mut svc, Method::GET, &query, None).await;
+    assert_eq!(body, "6:b4AQID");
+}
+
+#[tokio::test]
+pub async fn jsonp_polling() {
+    let mut svc = create_server();
+    let (status, open) = send_req(&mut svc, Method::GET, "transport=polling&j=0", None).await;
+    assert_eq!(status, 200);
+    assert!(open.starts_with(r#"___eio[0]("#), "{open}");
+    assert!(open.ends_with(");"), "{open}");
+    let sid = open
+        .split(r#"\"sid\":\""#)
+        .nth(1)
+        .and_then(|s| s.split('\\').next())
+        .unwrap()
+        .to_string();
+    let query = format!("transport=polling&j=0&sid={sid}");
+
+    let (status, body) = send_req(&mut svc, Method::POST, &query, Some("d=6%3A4hello")).await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let (_, body) = send_req(&mut svc, Method::GET, &query, None).await;
+    assert_eq!(body, r#"___eio[0]("6:4hello");"#);
+
+    // Newlines are escaped by the client and the payload is escaped in the response
+    let (status, _) = send_req(&mut svc, Method::POST, &query, Some(r"d=4:4a\nb")).await;
+    assert_eq!(status, 200);
+    let (_, body) = send_req(&mut svc, Method::GET, &query, None).await;
+    assert_eq!(body, r#"___eio[0]("4:4a\nb");"#);
+
+    // Binary packets are always base64 encoded
+    let (status, _) = send_req(&mut svc, Method::POST, &query, Some("d=6:b4AQID")).await;
+    assert_eq!(status, 200);
+    let (_, body) = send_req(&mut svc, Method::GET, &query, None).await;
+    assert_eq!(body, r#"___eio[0]("6:b4AQID");"#);
+}
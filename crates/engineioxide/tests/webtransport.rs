@@ -0,0 +1,130 @@
+//! Tests for the webtransport transport
+//! Sessions are simulated with an in-memory duplex stream.
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    Str, TransportType,
+};
+use http::Request;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, DuplexStream},
+    sync::mpsc,
+};
+
+#[derive(Debug, Clone)]
+struct MyHandler {
+    disconnect_tx: mpsc::Sender<DisconnectReason>,
+}
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, socket: Arc<Socket<()>>) {
+        println!("socket connect {}", socket.id);
+    }
+    fn on_disconnect(&self, socket: Arc<Socket<()>>, reason: DisconnectReason) {
+        println!("socket disconnect {}: {:?}", socket.id, reason);
+        self.disconnect_tx.try_send(reason).unwrap();
+    }
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+fn create_server(disconnect_tx: mpsc::Sender<DisconnectReason>) -> EngineIoService<MyHandler> {
+    let config = EngineIoConfig::builder()
+        .transports([TransportType::Polling, TransportType::WebTransport])
+        .build();
+    EngineIoService::with_config(Arc::new(MyHandler { disconnect_tx }), config)
+}
+
+fn create_session(svc: &EngineIoService<MyHandler>) -> DuplexStream {
+    let (client, server) = tokio::io::duplex(4096);
+    let parts = Request::new(()).into_parts().0;
+    tokio::spawn(svc.on_webtransport_session(parts, server));
+    client
+}
+
+async fn send(stream: &mut DuplexStream, data: &[u8], binary: bool) {
+    let flag = if binary { 0x80 } else { 0 };
+    stream.write_u8(data.len() as u8 | flag).await.unwrap();
+    stream.write_all(data).await.unwrap();
+}
+
+async fn recv(stream: &mut DuplexStream) -> (bool, Vec<u8>) {
+    let header = stream.read_u8().await.unwrap();
+    let len = match header & 0x7f {
+        126 => stream.read_u16().await.unwrap() as usize,
+        127 => stream.read_u64().await.unwrap() as usize,
+        len => len as usize,
+    };
+    let mut data = vec![0; len];
+    stream.read_exact(&mut data).await.unwrap();
+    (header & 0x80 == 0x80, data)
+}
+
+#[tokio::test]
+pub async fn webtransport_echo() {
+    let (disconnect_tx, mut rx) = mpsc::channel(10);
+    let svc = create_server(disconnect_tx);
+    let mut stream = create_session(&svc);
+
+    send(&mut stream, b"0", false).await;
+    let (binary, data) = recv(&mut stream).await;
+    assert!(!binary);
+    assert_eq!(data[0], b'0');
+    let open: serde_json::Value = serde_json::from_slice(&data[1..]).unwrap();
+    assert_eq!(open["upgrades"], serde_json::json!([]));
+
+    send(&mut stream, b"4hello", false).await;
+    assert_eq!(recv(&mut stream).await, (false, b"4hello".to_vec()));
+
+    send(&mut stream, &[1, 2, 3], true).await;
+    assert_eq!(recv(&mut stream).await, (true, vec![1, 2, 3]));
+
+    send(&mut stream, b"1", false).await;
+    let reason = tokio::time::timeout(Duration::from_millis(100), rx.recv())
+        .await
+        .expect("timeout waiting for DisconnectReason::TransportClose")
+        .unwrap();
+    assert_eq!(reason, DisconnectReason::TransportClose);
+}
+
+#[tokio::test]
+pub async fn webtransport_transport_close() {
+    let (disconnect_tx, mut rx) = mpsc::channel(10);
+    let svc = create_server(disconnect_tx);
+    let mut stream = create_session(&svc);
+
+    send(&mut stream, b"0", false).await;
+    recv(&mut stream).await;
+    drop(stream);
+
+    let reason = tokio::time::timeout(Duration::from_millis(100), rx.recv())
+        .await
+        .expect("timeout waiting for DisconnectReason::TransportClose")
+        .unwrap();
+    assert_eq!(reason, DisconnectReason::TransportClose);
+}
+
+#[tokio::test]
+pub async fn webtransport_not_allowed() {
+    let (disconnect_tx, _rx) = mpsc::channel(10);
+    let svc = EngineIoService::new(Arc::new(MyHandler { disconnect_tx }));
+    let mut stream = create_session(&svc);
+
+    // The session should be directly closed without sending anything
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).await.unwrap();
+    assert!(buf.is_empty());
+}
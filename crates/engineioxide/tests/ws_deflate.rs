@@ -0,0 +1,261 @@
+//! Tests for the permessage-deflate websocket extension
+//! Frames are manually encoded and decoded on the client side because the websocket client
+//! doesn't support the extension.
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::{DeflateConfig, EngineIoConfig},
+    handler::EngineIoHandler,
+    service::EngineIoService,
+    socket::{DisconnectReason, Socket},
+    ProtocolVersion, Str,
+};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use http::Request;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
+
+#[derive(Debug, Clone)]
+struct MyHandler;
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(self: Arc<Self>, socket: Arc<Socket<()>>) {
+        println!("socket connect {}", socket.id);
+    }
+    fn on_disconnect(&self, socket: Arc<Socket<()>>, reason: DisconnectReason) {
+        println!("socket disconnect {}: {:?}", socket.id, reason);
+    }
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<Socket<()>>) {
+        socket.emit(msg).ok();
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<Socket<()>>) {
+        socket.emit_binary(data).ok();
+    }
+}
+
+fn create_server(threshold: usize) -> EngineIoService<MyHandler> {
+    let config = EngineIoConfig::builder()
+        .ws_deflate(DeflateConfig {
+            threshold,
+            ..Default::default()
+        })
+        .build();
+    EngineIoService::with_config(Arc::new(MyHandler), config)
+}
+
+fn create_server_with_max_message_size(max: usize) -> EngineIoService<MyHandler> {
+    let config = EngineIoConfig::builder()
+        .ws_deflate(DeflateConfig::default())
+        .ws_max_message_size(max)
+        .build();
+    EngineIoService::with_config(Arc::new(MyHandler), config)
+}
+
+fn create_conn(svc: &EngineIoService<MyHandler>, extensions: Option<&str>) -> DuplexStream {
+    let (client, server) = tokio::io::duplex(1 << 16);
+    let mut req = Request::builder()
+        .method("GET")
+        .header("Host", "127.0.0.1")
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", generate_key())
+        .uri("ws://127.0.0.1/engine.io/?EIO=4&transport=websocket");
+    if let Some(extensions) = extensions {
+        req = req.header("Sec-WebSocket-Extensions", extensions);
+    }
+    let parts = req.body(()).unwrap().into_parts().0;
+    tokio::spawn(svc.ws_init(server, ProtocolVersion::V4, None, parts));
+    client
+}
+
+/// Send a masked text frame, compressed if a compressor is given.
+async fn send(stream: &mut DuplexStream, data: &[u8], compress: Option<&mut Compress>) {
+    let (rsv1, mut payload) = match compress {
+        Some(compress) => {
+            let mut out = Vec::with_capacity(data.len() + 64);
+            compress
+                .compress_vec(data, &mut out, FlushCompress::Sync)
+                .unwrap();
+            out.truncate(out.len() - 4);
+            (0x40, out)
+        }
+        None => (0, data.to_vec()),
+    };
+    let mask = [1, 2, 3, 4];
+    payload
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, b)| *b ^= mask[i % 4]);
+    match payload.len() {
+        len @ 0..=125 => stream.write_all(&[0x81 | rsv1, 0x80 | len as u8]).await,
+        len => {
+            stream.write_all(&[0x81 | rsv1, 0x80 | 126]).await.unwrap();
+            stream.write_u16(len.try_into().unwrap()).await
+        }
+    }
+    .unwrap();
+    stream.write_all(&mask).await.unwrap();
+    stream.write_all(&payload).await.unwrap();
+}
+
+/// Receive a text frame and inflate it if it is compressed.
+/// Returns whether the frame was compressed and the payload. Ping packets are skipped.
+async fn recv(stream: &mut DuplexStream, decompress: &mut Decompress) -> (bool, String) {
+    loop {
+        let (compressed, data) = recv_frame(stream, decompress).await;
+        if data != "2" {
+            return (compressed, data);
+        }
+    }
+}
+
+async fn recv_frame(stream: &mut DuplexStream, decompress: &mut Decompress) -> (bool, String) {
+    let (header, mut data) = read_frame(stream).await;
+    let rsv1 = header & 0x40 != 0;
+    if rsv1 {
+        data.extend_from_slice(&[0, 0, 0xff, 0xff]);
+        // Large enough for all the test messages
+        let mut out = Vec::with_capacity(1 << 20);
+        decompress
+            .decompress_vec(&data, &mut out, FlushDecompress::Sync)
+            .unwrap();
+        data = out;
+    }
+    (rsv1, String::from_utf8(data).unwrap())
+}
+
+/// Read a raw unmasked frame. Returns the first byte of its header and its payload.
+async fn read_frame(stream: &mut DuplexStream) -> (u8, Vec<u8>) {
+    let header = stream.read_u16().await.unwrap();
+    let len = match header & 0x7f {
+        126 => stream.read_u16().await.unwrap() as usize,
+        127 => stream.read_u64().await.unwrap() as usize,
+        len => len as usize,
+    };
+    let mut data = vec![0; len];
+    stream.read_exact(&mut data).await.unwrap();
+    ((header >> 8) as u8, data)
+}
+
+#[tokio::test]
+pub async fn ws_deflate_echo() {
+    let svc = create_server(0);
+    let mut stream = create_conn(&svc, Some("permessage-deflate; client_max_window_bits"));
+    let mut compress = Compress::new(Compression::default(), false);
+    let mut decompress = Decompress::new(false);
+
+    let (compressed, open) = recv(&mut stream, &mut decompress).await;
+    assert!(compressed);
+    assert!(open.starts_with("0{\"sid\":"));
+
+    // The compression context is kept between messages
+    for _ in 0..3 {
+        send(&mut stream, b"4hello world", Some(&mut compress)).await;
+        let res = tokio::time::timeout(
+            Duration::from_millis(100),
+            recv(&mut stream, &mut decompress),
+        )
+        .await
+        .expect("timeout waiting for echo");
+        assert_eq!(res, (true, "4hello world".to_string()));
+    }
+
+    // Uncompressed messages are still accepted
+    send(&mut stream, b"4hello", None).await;
+    assert_eq!(
+        recv(&mut stream, &mut decompress).await,
+        (true, "4hello".to_string())
+    );
+}
+
+#[tokio::test]
+pub async fn ws_deflate_large_message() {
+    let svc = create_server(0);
+    let mut stream = create_conn(&svc, Some("permessage-deflate"));
+    let mut compress = Compress::new(Compression::default(), false);
+    let mut decompress = Decompress::new(false);
+
+    recv(&mut stream, &mut decompress).await;
+
+    // Messages larger than 16KiB are still sent in a single compressed frame
+    let data = format!("4{}", "hello world ".repeat(2000));
+    send(&mut stream, data.as_bytes(), Some(&mut compress)).await;
+    let res = tokio::time::timeout(
+        Duration::from_millis(100),
+        recv(&mut stream, &mut decompress),
+    )
+    .await
+    .expect("timeout waiting for echo");
+    assert_eq!(res, (true, data));
+}
+
+#[tokio::test]
+pub async fn ws_deflate_threshold() {
+    let svc = create_server(10);
+    let mut stream = create_conn(&svc, Some("permessage-deflate"));
+    let mut compress = Compress::new(Compression::default(), false);
+    let mut decompress = Decompress::new(false);
+
+    recv(&mut stream, &mut decompress).await;
+
+    send(&mut stream, b"4hi", Some(&mut compress)).await;
+    assert_eq!(
+        recv(&mut stream, &mut decompress).await,
+        (false, "4hi".to_string())
+    );
+    send(&mut stream, b"4hello world", Some(&mut compress)).await;
+    assert_eq!(
+        recv(&mut stream, &mut decompress).await,
+        (true, "4hello world".to_string())
+    );
+}
+
+#[tokio::test]
+pub async fn ws_deflate_not_negotiated() {
+    let svc = create_server(0);
+    let mut stream = create_conn(&svc, None);
+    let mut decompress = Decompress::new(false);
+
+    let (compressed, open) = recv(&mut stream, &mut decompress).await;
+    assert!(!compressed);
+    assert!(open.starts_with("0{\"sid\":"));
+
+    send(&mut stream, b"4hello world", None).await;
+    assert_eq!(
+        recv(&mut stream, &mut decompress).await,
+        (false, "4hello world".to_string())
+    );
+}
+
+#[tokio::test]
+pub async fn ws_deflate_max_message_size() {
+    let svc = create_server_with_max_message_size(1024);
+    let mut stream = create_conn(&svc, Some("permessage-deflate"));
+    let mut compress = Compress::new(Compression::default(), false);
+    let mut decompress = Decompress::new(false);
+
+    recv(&mut stream, &mut decompress).await;
+
+    // A small compressed message is inflated past the max message size
+    let data = format!("4{}", "a".repeat(64 * 1024));
+    send(&mut stream, data.as_bytes(), Some(&mut compress)).await;
+
+    let code = tokio::time::timeout(Duration::from_millis(100), async {
+        loop {
+            let (header, data) = read_frame(&mut stream).await;
+            // Close frame
+            if header & 0x0f == 0x8 {
+                break u16::from_be_bytes([data[0], data[1]]);
+            }
+        }
+    })
+    .await
+    .expect("timeout waiting for the close frame");
+    assert_eq!(code, 1009);
+}
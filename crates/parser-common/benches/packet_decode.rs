@@ -30,7 +30,7 @@ fn to_value(data: impl serde::Serialize) -> Value {
 fn criterion_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("parser_common/decode_packet");
     let connect = CommonParser
-        .encode_default(&ConnectPacket { sid: Sid::ZERO })
+        .encode_default(&ConnectPacket::new(Sid::ZERO))
         .unwrap();
 
     group.bench_function("Decode packet connect on /", |b| {
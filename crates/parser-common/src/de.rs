@@ -1,6 +1,7 @@
 use std::io::Cursor;
 
 use bytes::Buf;
+use serde_json::value::RawValue;
 use socketioxide_core::{
     packet::{Packet, PacketData},
     parser::ParseError,
@@ -42,6 +43,10 @@ pub fn deserialize_packet(data: Str) -> Result<(Packet, Option<usize>), ParseErr
         b'1' => PacketData::Disconnect,
         b'2' => PacketData::Event(str(data), ack),
         b'3' => PacketData::EventAck(str(data), ack.ok_or(ParseError::InvalidPacketType)?),
+        b'4' => {
+            let (message, data) = read_connect_error(&data)?;
+            PacketData::ConnectError(message, data)
+        }
         b'5' => PacketData::BinaryEvent(str(data), ack),
         b'6' => PacketData::BinaryAck(str(data), ack.ok_or(ParseError::InvalidPacketType)?),
         _ => return Err(ParseError::InvalidPacketType),
@@ -49,6 +54,22 @@ pub fn deserialize_packet(data: Str) -> Result<(Packet, Option<usize>), ParseErr
     Ok((Packet { inner, ns }, attachments))
 }
 
+/// Connect error packets are only sent by the server,
+/// with a `{"message": "...", "data": ...}` payload where the data is optional.
+fn read_connect_error(data: &str) -> Result<(String, Option<Value>), ParseError> {
+    #[derive(serde::Deserialize)]
+    struct ErrorMessage<'a> {
+        message: String,
+        #[serde(borrow)]
+        data: Option<&'a RawValue>,
+    }
+    let err: ErrorMessage<'_> = serde_json::from_str(data).map_err(|_| ParseError::InvalidData)?;
+    let data = err
+        .data
+        .map(|data| Value::Str(Str::from(data.get().to_string()), None));
+    Ok((err.message, data))
+}
+
 fn read_attachments(reader: &mut Cursor<&str>) -> Option<usize> {
     let data = *reader.get_ref();
     let start_index = reader.position() as usize;
@@ -102,7 +123,7 @@ fn read_ack(reader: &mut Cursor<&str>) -> Option<i64> {
 
 #[cfg(test)]
 mod tests {
-    use socketioxide_core::parser::ParseError;
+    use socketioxide_core::{packet::PacketData, parser::ParseError, Value};
 
     use crate::de::deserialize_packet;
 
@@ -120,6 +141,26 @@ mod tests {
         assert!(matches!(err, Err(ParseError::InvalidPacketType)));
     }
 
+    #[test]
+    fn connect_error() {
+        let (packet, _) =
+            deserialize_packet(r#"4/custom,{"message":"not allowed"}"#.into()).unwrap();
+        assert_eq!(packet.ns, "/custom");
+        assert_eq!(
+            packet.inner,
+            PacketData::ConnectError("not allowed".into(), None)
+        );
+        let (packet, _) =
+            deserialize_packet(r#"4{"message":"not allowed","data":{"code":401}}"#.into()).unwrap();
+        let data = Value::Str(r#"{"code":401}"#.into(), None);
+        assert_eq!(
+            packet.inner,
+            PacketData::ConnectError("not allowed".into(), Some(data))
+        );
+        let err = deserialize_packet("4{}".into());
+        assert!(matches!(err, Err(ParseError::InvalidData)));
+    }
+
     #[test]
     fn ns_without_comma_end() {
         let (packet, _) = deserialize_packet("0/custom".into()).unwrap();
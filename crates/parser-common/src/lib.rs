@@ -1,7 +1,7 @@
 #![warn(
     clippy::all,
     clippy::todo,
-    clippy::empty_enum,
+    clippy::empty_enums,
     clippy::mem_forget,
     clippy::unused_self,
     clippy::filter_map_next,
@@ -10,7 +10,6 @@
     clippy::match_wildcard_for_single_variants,
     clippy::if_let_mutex,
     clippy::await_holding_lock,
-    clippy::match_on_vec_items,
     clippy::imprecise_flops,
     clippy::suboptimal_flops,
     clippy::lossy_float_literal,
@@ -55,7 +54,7 @@ mod value;
 
 /// Parse and serialize from and into the socket.io common packet format.
 /// See details in the [socket.io protocol doc](https://socket.io/fr/docs/v4/socket-io-protocol/#packet-encoding).
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct CommonParser;
 
 impl Parse for CommonParser {
@@ -145,6 +144,11 @@ impl Parse for CommonParser {
     fn read_event(self, value: &Value) -> Result<&str, ParserError> {
         value::read_event(value).map_err(ParserError::new)
     }
+
+    #[inline]
+    fn push_offset(self, value: &mut Value, offset: &str) -> Result<(), ParserError> {
+        value::push_offset(value, offset).map_err(ParserError::new)
+    }
 }
 
 /// Check if the binary packet is complete, it means that all payloads have been received
@@ -195,7 +199,7 @@ mod test {
         let sid = Sid::new();
         let payload = format!("0{}", json!({ "sid": sid }));
         let packet = decode(payload);
-        let value = to_connect_value(&ConnectPacket { sid });
+        let value = to_connect_value(&ConnectPacket::new(sid));
         assert_eq!(Packet::connect("/", Some(value.clone())), packet);
 
         let payload = format!("0/admin™,{}", json!({ "sid": sid }));
@@ -206,7 +210,7 @@ mod test {
     #[test]
     fn packet_encode_connect() {
         let sid = Sid::new();
-        let value = to_connect_value(&ConnectPacket { sid });
+        let value = to_connect_value(&ConnectPacket::new(sid));
         let payload = format!("0{}", json!({ "sid": sid }));
         let packet = encode(Packet::connect("/", Some(value.clone())));
         assert_eq!(packet, payload);
@@ -358,11 +362,11 @@ mod test {
     #[test]
     fn packet_encode_connect_error() {
         let payload = format!("4{}", json!({ "message": "Invalid namespace" }));
-        let packet = encode(Packet::connect_error("/", "Invalid namespace"));
+        let packet = encode(Packet::connect_error("/", "Invalid namespace", None));
         assert_eq!(packet, payload);
 
         let payload = format!("4/admin™,{}", json!({ "message": "Invalid namespace" }));
-        let packet = encode(Packet::connect_error("/admin™", "Invalid namespace"));
+        let packet = encode(Packet::connect_error("/admin™", "Invalid namespace", None));
         assert_eq!(packet, payload);
     }
 
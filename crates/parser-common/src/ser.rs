@@ -34,13 +34,14 @@ pub fn serialize_packet(packet: Packet) -> Value {
             serialize_data(&mut buffer, &data);
             bins
         }
-        PacketData::ConnectError(ref message) => {
-            #[derive(serde::Serialize)]
-            struct ErrorMessage<'a> {
-                message: &'a str,
+        PacketData::ConnectError(ref message, ref data) => {
+            buffer.put_slice(b"{\"message\":");
+            serde_json::to_writer((&mut buffer).writer(), message).unwrap();
+            if let Some(data) = data {
+                buffer.put_slice(b",\"data\":");
+                buffer.put_slice(data.as_str().unwrap().as_bytes());
             }
-            let data = serde_json::to_vec(&ErrorMessage { message }).unwrap();
-            buffer.put_slice(&data);
+            buffer.put_u8(b'}');
             None
         }
         PacketData::BinaryEvent(Value::Str(data, bins), ack) => {
@@ -146,7 +147,14 @@ fn get_size_hint(packet: &Packet) -> usize {
                 + ACK_PUNCTUATION_SIZE
                 + BINARY_PUNCTUATION_SIZE
         }
-        ConnectError(data) => data.len() + "{\"message\":\"\"}".len(),
+        ConnectError(message, data) => {
+            message.len()
+                + "{\"message\":\"\"}".len()
+                + data
+                    .as_ref()
+                    .map(|data| data.len() + ",\"data\":".len())
+                    .unwrap_or(0)
+        }
         data => unreachable!(
             "common parser should only serialize SocketIoValue::Str data: {:?}",
             data
@@ -185,7 +193,7 @@ mod tests {
     #[test]
     fn packet_size_hint() {
         let sid = Sid::new();
-        let value = to_connect_value(&ConnectPacket { sid });
+        let value = to_connect_value(&ConnectPacket::new(sid));
         let packet = Packet::connect("/", Some(value.clone()));
         assert_eq!(get_size_hint(&packet), serialize_packet(packet).len());
 
@@ -195,10 +203,14 @@ mod tests {
         let packet = Packet::connect("admin", None);
         assert_eq!(get_size_hint(&packet), serialize_packet(packet).len());
 
-        let packet = Packet::connect_error("/", "test".to_string());
+        let packet = Packet::connect_error("/", "test".to_string(), None);
+        assert_eq!(get_size_hint(&packet), serialize_packet(packet).len());
+
+        let data = to_connect_value(&json!({ "code": 401 }));
+        let packet = Packet::connect_error("/", "test".to_string(), Some(data));
         assert_eq!(get_size_hint(&packet), serialize_packet(packet).len());
 
-        let packet = Packet::connect_error("/admin", "test".to_string());
+        let packet = Packet::connect_error("/admin", "test".to_string(), None);
         assert_eq!(get_size_hint(&packet), serialize_packet(packet).len());
 
         let packet = Packet::disconnect("/");
@@ -58,12 +58,49 @@ pub fn read_event(data: &Value) -> serde_json::Result<&str> {
     de::read_event(data)
 }
 
+/// Append a string element at the end of a serialized array: `[event, ...data, offset]`.
+pub fn push_offset(data: &mut Value, offset: &str) -> serde_json::Result<()> {
+    let (data, _) = match data {
+        Value::Str(v, b) => (v, b),
+        Value::Bytes(_) => return Err(serde_json::Error::custom("unexpected binary data")),
+    };
+    let arr = data.as_str().trim_end();
+    let arr = arr
+        .strip_suffix(']')
+        .ok_or_else(|| serde_json::Error::custom("expected a json array"))?;
+    let mut buff = Vec::with_capacity(arr.len() + offset.len() + 4);
+    buff.extend_from_slice(arr.as_bytes());
+    if !arr.trim_end().ends_with('[') {
+        buff.push(b',');
+    }
+    serde_json::to_writer(&mut buff, offset)?;
+    buff.push(b']');
+    // SAFETY: the buffer is made of a valid utf8 str slice and a serialized json string.
+    *data = unsafe { Str::from_bytes_unchecked(Bytes::from(buff)) };
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde::de::DeserializeOwned;
     use serde_json::json;
 
+    #[test]
+    fn push_offset_value() {
+        let mut value = to_value(&json!({ "foo": "bar" }), Some("event")).unwrap();
+        push_offset(&mut value, "abc").unwrap();
+        assert_eq!(
+            value.as_str().unwrap().as_str(),
+            r#"["event",{"foo":"bar"},"abc"]"#
+        );
+        let mut value = Value::Str(Str::from("[]"), None);
+        push_offset(&mut value, "abc").unwrap();
+        assert_eq!(value.as_str().unwrap().as_str(), r#"["abc"]"#);
+        let mut value = Value::Str(Str::from("{}"), None);
+        assert!(push_offset(&mut value, "abc").is_err());
+    }
+
     fn to_str(data: impl Serialize, event: Option<&str>) -> Str {
         to_value(&data, event).unwrap().as_str().unwrap().clone()
     }
@@ -326,7 +363,8 @@ mod tests {
 
     #[test]
     fn from_value_any_binary() {
-        let data = json!(["event", { "data": "str", "bin": { "_placeholder": true, "num": 0 }, "complex": { "inner": true, "bin2": { "_placeholder": true, "num": 1 } } }]);
+        // keys are sorted so that the map order doesn't depend on the `preserve_order` feature of serde_json
+        let data = json!(["event", { "bin": { "_placeholder": true, "num": 0 }, "complex": { "bin2": { "_placeholder": true, "num": 1 }, "inner": true }, "data": "str" }]);
         let comp = rmpv::Value::Map(vec![
             (
                 rmpv::Value::String("bin".into()),
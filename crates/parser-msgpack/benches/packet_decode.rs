@@ -10,12 +10,12 @@ use socketioxide_parser_msgpack::MsgPackParser;
 fn encode(packet: Packet) -> Bytes {
     match MsgPackParser.encode(black_box(packet)) {
         Value::Str(_, _) => panic!("testing only returns bytes"),
-        Value::Bytes(d) => d.into(),
+        Value::Bytes(d) => d,
     }
 }
 fn decode(value: Bytes) -> Option<Packet> {
     MsgPackParser
-        .decode_bin(&Default::default(), black_box(value.into()))
+        .decode_bin(&Default::default(), black_box(value))
         .ok()
 }
 
@@ -30,7 +30,7 @@ fn to_value(data: impl serde::Serialize) -> Value {
 fn criterion_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("parser_msgpack/decode_packet");
     let connect = MsgPackParser
-        .encode_default(&ConnectPacket { sid: Sid::ZERO })
+        .encode_default(&ConnectPacket::new(Sid::ZERO))
         .unwrap();
 
     group.bench_function("Decode packet connect on /", |b| {
@@ -24,7 +24,7 @@ fn criterion_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("parser_msgpack/encode_packet");
 
     let connect = MsgPackParser
-        .encode_default(&ConnectPacket { sid: Sid::ZERO })
+        .encode_default(&ConnectPacket::new(Sid::ZERO))
         .unwrap();
 
     group.bench_function("Encode packet connect on /", |b| {
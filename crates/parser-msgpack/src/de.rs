@@ -13,6 +13,9 @@ use socketioxide_core::{
     Str, Value,
 };
 
+/// The maximum nesting depth of the data of a packet, to bound the recursion when skipping it.
+const MAX_DEPTH: usize = 128;
+
 pub fn deserialize_packet(buff: Bytes) -> Result<Packet, ParseError> {
     let mut reader = Cursor::new(buff);
     let maplen = read_map_len(&mut reader).map_err(|e| {
@@ -61,13 +64,9 @@ pub fn deserialize_packet(buff: Bytes) -> Result<Packet, ParseError> {
         2 => PacketData::Event(data, id),
         3 => PacketData::EventAck(data, id.ok_or(ParseError::InvalidAckId)?),
         4 => {
-            #[derive(serde::Deserialize)]
-            struct ErrorMessage {
-                message: String,
-            }
-            let ErrorMessage { message } =
-                rmp_serde::decode::from_slice(&buff[data_pos]).map_err(ParserError::new)?;
-            PacketData::ConnectError(message)
+            let (message, data) =
+                read_connect_error(buff.slice(data_pos)).map_err(ParserError::new)?;
+            PacketData::ConnectError(message, data)
         }
         5 => PacketData::BinaryEvent(data, id),
         6 => PacketData::BinaryAck(data, id.ok_or(ParseError::InvalidAckId)?),
@@ -107,7 +106,7 @@ fn parse_key_value(
                 .map(|b| Marker::from_u8(*b))       //TODO: use remaining_slice when stabilized (issue #86369)
             {
                 Some(Marker::Null) | None => {
-                    reader.advance(1);
+                    skip(reader, 1)?;
                     None
                 }
                 Some(_) => Some(decode::read_int::<i64, _>(reader)?),
@@ -117,6 +116,28 @@ fn parse_key_value(
     };
     Ok(())
 }
+/// Read a `{ message, data }` connect error map, the data is optional.
+fn read_connect_error(buff: Bytes) -> Result<(String, Option<Value>), DecodeError> {
+    let mut reader = Cursor::new(buff);
+    let maplen = read_map_len(&mut reader)?;
+    let mut message = None;
+    let mut data = None;
+    for _ in 0..maplen {
+        match read_str(&mut reader)? {
+            "message" => message = Some(read_str(&mut reader)?.to_string()),
+            "data" => {
+                let start = reader.position() as usize;
+                move_to_next_element(&mut reader)?;
+                let end = reader.position() as usize;
+                data = Some(Value::Bytes(reader.get_ref().slice(start..end)));
+            }
+            _ => move_to_next_element(&mut reader)?,
+        }
+    }
+    let message = message.ok_or(DecodeError::Uncategorized("missing message".into()))?;
+    Ok((message, data))
+}
+
 fn read_u16(rd: &mut Cursor<Bytes>) -> Result<u16, DecodeError> {
     let mut buff = [0u8; 2];
     rd.read_exact_buf(&mut buff)
@@ -137,13 +158,30 @@ fn read_u32(rd: &mut Cursor<Bytes>) -> Result<u32, DecodeError> {
 fn read_str(reader: &mut Cursor<Bytes>) -> Result<&str, DecodeError> {
     let len = decode::read_str_len(reader)? as usize;
     let start = reader.position() as usize;
-    let end = start + len;
+    skip(reader, len)?;
+    Ok(str::from_utf8(&reader.get_ref()[start..start + len])?)
+}
+
+/// Advance the reader, without going past the end of the data.
+fn skip(reader: &mut Cursor<Bytes>, len: usize) -> Result<(), DecodeError> {
+    if reader.remaining() < len {
+        let err = std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+        return Err(DecodeError::InvalidDataRead(err));
+    }
     reader.advance(len);
-    Ok(str::from_utf8(&reader.get_ref()[start..end])?)
+    Ok(())
 }
 
 /// Iterate over the next element
 fn move_to_next_element(reader: &mut Cursor<Bytes>) -> Result<(), DecodeError> {
+    skip_element(reader, 0)
+}
+
+/// Skip the next element, which is nested at the given depth.
+fn skip_element(reader: &mut Cursor<Bytes>, depth: usize) -> Result<(), DecodeError> {
+    if depth > MAX_DEPTH {
+        return Err(DecodeError::DepthLimitExceeded);
+    }
     let marker = decode::read_marker(reader)?;
     match marker {
         rmp::Marker::FixPos(_)
@@ -154,70 +192,70 @@ fn move_to_next_element(reader: &mut Cursor<Bytes>) -> Result<(), DecodeError> {
         | rmp::Marker::True => (),
         rmp::Marker::FixMap(n) => {
             for _ in 0..n * 2 {
-                move_to_next_element(reader)?
+                skip_element(reader, depth + 1)?
             }
         }
         rmp::Marker::FixArray(n) => {
             for _ in 0..n {
-                move_to_next_element(reader)?
+                skip_element(reader, depth + 1)?
             }
         }
-        rmp::Marker::FixStr(n) => reader.advance(n as usize),
-        rmp::Marker::FixExt1 => reader.advance(2),
-        rmp::Marker::FixExt2 => reader.advance(3),
-        rmp::Marker::FixExt4 => reader.advance(5),
-        rmp::Marker::FixExt8 => reader.advance(9),
-        rmp::Marker::FixExt16 => reader.advance(17),
-        rmp::Marker::U8 | rmp::Marker::I8 => reader.advance(1),
-        rmp::Marker::U16 | rmp::Marker::I16 => reader.advance(2),
-        rmp::Marker::F32 | rmp::Marker::U32 | rmp::Marker::I32 => reader.advance(4),
-        rmp::Marker::F64 | rmp::Marker::U64 | rmp::Marker::I64 => reader.advance(8),
+        rmp::Marker::FixStr(n) => skip(reader, n as usize)?,
+        rmp::Marker::FixExt1 => skip(reader, 2)?,
+        rmp::Marker::FixExt2 => skip(reader, 3)?,
+        rmp::Marker::FixExt4 => skip(reader, 5)?,
+        rmp::Marker::FixExt8 => skip(reader, 9)?,
+        rmp::Marker::FixExt16 => skip(reader, 17)?,
+        rmp::Marker::U8 | rmp::Marker::I8 => skip(reader, 1)?,
+        rmp::Marker::U16 | rmp::Marker::I16 => skip(reader, 2)?,
+        rmp::Marker::F32 | rmp::Marker::U32 | rmp::Marker::I32 => skip(reader, 4)?,
+        rmp::Marker::F64 | rmp::Marker::U64 | rmp::Marker::I64 => skip(reader, 8)?,
         rmp::Marker::Str8 | rmp::Marker::Bin8 => {
             let len = read_u8(reader)?;
-            reader.advance(len as usize)
+            skip(reader, len as usize)?
         }
         rmp::Marker::Str16 | rmp::Marker::Bin16 => {
             let len = read_u16(reader)?;
-            reader.advance(len as usize)
+            skip(reader, len as usize)?
         }
         rmp::Marker::Str32 | rmp::Marker::Bin32 => {
             let len = read_u32(reader)?;
-            reader.advance(len as usize)
+            skip(reader, len as usize)?
         }
         rmp::Marker::Ext8 => {
             let len = read_u8(reader)?;
-            reader.advance(len as usize)
+            skip(reader, len as usize)?
         }
         rmp::Marker::Ext16 => {
             let len = read_u16(reader)?;
-            reader.advance(len as usize)
+            skip(reader, len as usize)?
         }
         rmp::Marker::Ext32 => {
             let len = read_u32(reader)?;
-            reader.advance(len as usize)
+            skip(reader, len as usize)?
         }
         rmp::Marker::Array16 => {
             let arrlen = read_u16(reader)? as usize;
             for _ in 0..arrlen {
-                move_to_next_element(reader)?;
+                skip_element(reader, depth + 1)?;
             }
         }
         rmp::Marker::Array32 => {
             let arrlen = read_u32(reader)? as usize;
             for _ in 0..arrlen {
-                move_to_next_element(reader)?;
+                skip_element(reader, depth + 1)?;
             }
         }
         rmp::Marker::Map16 => {
             let maplen = read_u16(reader)? as usize;
             for _ in 0..maplen * 2 {
-                move_to_next_element(reader)?;
+                skip_element(reader, depth + 1)?;
             }
         }
         rmp::Marker::Map32 => {
             let len = read_u32(reader)? as usize;
             for _ in 0..len * 2 {
-                move_to_next_element(reader)?;
+                skip_element(reader, depth + 1)?;
             }
         }
     }
@@ -327,7 +365,7 @@ mod tests {
         let packet = deserialize_packet(Bytes::from(data)).unwrap();
         match packet {
             Packet {
-                inner: PacketData::ConnectError(error_message),
+                inner: PacketData::ConnectError(error_message, None),
                 ns,
             } => {
                 if ns == "/" {
@@ -338,6 +376,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn deserialize_connect_error_packet_with_data() {
+        let error = json!({ "message": "error_message", "data": { "code": 401 } });
+        let data = packet(4, "/", error, None);
+        let packet = deserialize_packet(Bytes::from(data)).unwrap();
+        let expected = rmp_serde::to_vec_named(&json!({ "code": 401 })).unwrap();
+        assert_eq!(
+            packet.inner,
+            PacketData::ConnectError("error_message".into(), Some(Value::Bytes(expected.into())))
+        );
+    }
+
     #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
     struct Data {
         binary_data: Bytes,
@@ -718,4 +768,31 @@ mod tests {
         let bytelen = reader.position() as usize;
         assert_eq!(bytelen, len);
     }
+
+    #[test]
+    pub fn truncated_packet() {
+        // A map with a key longer than the packet
+        assert!(deserialize_packet(Bytes::from_static(&[0x81, 0xba, 0x81])).is_err());
+        // A null id at the end of the packet
+        let mut data = vec![0x81];
+        rmp::encode::write_str(&mut data, "id").unwrap();
+        assert!(deserialize_packet(data.into()).is_err());
+
+        let data = vec![Marker::Bin8.to_u8(), 0x10, 0x01];
+        let mut reader = Cursor::new(data.into());
+        move_to_next_element(&mut reader).unwrap_err();
+    }
+
+    #[test]
+    pub fn data_max_depth() {
+        let mut data = vec![Marker::FixArray(1).to_u8(); MAX_DEPTH];
+        data.push(Marker::Null.to_u8());
+        let mut reader = Cursor::new(data.clone().into());
+        move_to_next_element(&mut reader).unwrap();
+
+        data.insert(0, Marker::FixArray(1).to_u8());
+        let mut reader = Cursor::new(data.into());
+        let err = move_to_next_element(&mut reader).unwrap_err();
+        assert!(matches!(err, DecodeError::DepthLimitExceeded));
+    }
 }
@@ -1,7 +1,7 @@
 #![warn(
     clippy::all,
     clippy::todo,
-    clippy::empty_enum,
+    clippy::empty_enums,
     clippy::mem_forget,
     clippy::unused_self,
     clippy::filter_map_next,
@@ -10,7 +10,6 @@
     clippy::match_wildcard_for_single_variants,
     clippy::if_let_mutex,
     clippy::await_holding_lock,
-    clippy::match_on_vec_items,
     clippy::imprecise_flops,
     clippy::suboptimal_flops,
     clippy::lossy_float_literal,
@@ -62,7 +61,7 @@ mod ser;
 mod value;
 
 /// The MsgPack parser
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MsgPackParser;
 
 impl Parse for MsgPackParser {
@@ -116,6 +115,10 @@ impl Parse for MsgPackParser {
     fn read_event(self, value: &Value) -> Result<&str, ParserError> {
         value::read_event(value).map_err(ParserError::new)
     }
+
+    fn push_offset(self, value: &mut Value, offset: &str) -> Result<(), ParserError> {
+        value::push_offset(value, offset).map_err(ParserError::new)
+    }
 }
 
 /// All the static binary data is generated from this script, using the official socket.io implementation:
@@ -160,7 +163,7 @@ mod tests {
         ];
 
         let sid = Sid::from_str("nwz3C8u7qysvgVqj").unwrap();
-        let sid = to_connect_value(&ConnectPacket { sid });
+        let sid = to_connect_value(&ConnectPacket::new(sid));
         let packet = encode(Packet::connect("/", Some(sid)));
         assert_eq!(DATA, packet.as_ref());
     }
@@ -173,7 +176,7 @@ mod tests {
             103, 86, 113, 106,
         ];
         let sid = Sid::from_str("nwz3C8u7qysvgVqj").unwrap();
-        let sid = to_connect_value(&ConnectPacket { sid });
+        let sid = to_connect_value(&ConnectPacket::new(sid));
         let packet = decode(DATA);
         assert_eq!(packet, Packet::connect("/", Some(sid)));
     }
@@ -187,7 +190,7 @@ mod tests {
         ];
 
         let sid = Sid::from_str("nwz3C8u7qysvgVqj").unwrap();
-        let sid = to_connect_value(&ConnectPacket { sid });
+        let sid = to_connect_value(&ConnectPacket::new(sid));
         let packet = encode(Packet::connect("/admin™", Some(sid)));
         assert_eq!(DATA, packet.as_ref());
     }
@@ -201,7 +204,7 @@ mod tests {
         ];
 
         let sid = Sid::from_str("nwz3C8u7qysvgVqj").unwrap();
-        let sid = to_connect_value(&ConnectPacket { sid });
+        let sid = to_connect_value(&ConnectPacket::new(sid));
         let packet = decode(DATA);
         assert_eq!(packet, Packet::connect("/admin™", Some(sid)));
     }
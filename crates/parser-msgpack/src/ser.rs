@@ -46,11 +46,15 @@ pub fn serialize_packet(packet: Packet) -> Vec<u8> {
             encode::write_str(&mut buff, "data").unwrap();
             buff.put_slice(&data)
         }
-        PacketData::ConnectError(data) => {
+        PacketData::ConnectError(message, data) => {
             encode::write_str(&mut buff, "data").unwrap();
-            encode::write_map_len(&mut buff, 1).unwrap();
+            encode::write_map_len(&mut buff, if data.is_some() { 2 } else { 1 }).unwrap();
             encode::write_str(&mut buff, "message").unwrap();
-            encode::write_str(&mut buff, &data).unwrap();
+            encode::write_str(&mut buff, &message).unwrap();
+            if let Some(Value::Bytes(data)) = data {
+                encode::write_str(&mut buff, "data").unwrap();
+                serialize_data(&data, &mut buff);
+            }
         }
         _ => (),
     };
@@ -80,7 +84,7 @@ fn get_packet_map_len(packet: &Packet) -> u32 {
         PacketData::Event(_, Some(_)) | PacketData::BinaryEvent(_, Some(_)) => 2,
         PacketData::Event(_, None) | PacketData::BinaryEvent(_, None) => 1,
         PacketData::EventAck(_, _) | PacketData::BinaryAck(_, _) => 2,
-        PacketData::ConnectError(_) => 1,
+        PacketData::ConnectError(_, _) => 1,
     }
 }
 
@@ -158,12 +162,30 @@ mod tests {
     fn serialize_packet_connect_error() {
         let data = serialize_packet(Packet {
             ns: "/error".into(),
-            inner: PacketData::ConnectError("Error message".into()),
+            inner: PacketData::ConnectError("Error message".into(), None),
         });
         assert_eq!(
             data,
             packet(4, "/error", json!({ "message": "Error message" }), None)
         );
+
+        let data = serialize_packet(Packet {
+            ns: "/error".into(),
+            inner: PacketData::ConnectError(
+                "Error message".into(),
+                Some(to_value(json!({ "code": 401 }))),
+            ),
+        });
+        #[derive(Serialize)]
+        struct ErrorMessage {
+            message: &'static str,
+            data: serde_json::Value,
+        }
+        let expected = ErrorMessage {
+            message: "Error message",
+            data: json!({ "code": 401 }),
+        };
+        assert_eq!(data, packet(4, "/error", expected, None));
     }
 
     #[test]
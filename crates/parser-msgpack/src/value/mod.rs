@@ -44,6 +44,25 @@ pub fn read_event(data: &Value) -> Result<&str, rmp_serde::decode::Error> {
     de::read_event(data)
 }
 
+/// Append a string element at the end of a serialized array: `[event, ...data, offset]`.
+/// The array header is rewritten to take into account the new element.
+pub fn push_offset(data: &mut Value, offset: &str) -> Result<(), rmp_serde::encode::Error> {
+    use rmp_serde::encode::Error;
+    let bytes = match data {
+        Value::Bytes(v) => v,
+        Value::Str(_, _) => return Err(serde::ser::Error::custom("unexpected string data")),
+    };
+    let mut rd: &[u8] = bytes;
+    let len = rmp::decode::read_array_len(&mut rd)
+        .map_err(|_| <Error as serde::ser::Error>::custom("expected a msgpack array"))?;
+    let mut buff = Vec::with_capacity(bytes.len() + offset.len() + 8);
+    rmp::encode::write_array_len(&mut buff, len + 1)?;
+    buff.extend_from_slice(rd);
+    rmp::encode::write_str(&mut buff, offset)?;
+    *bytes = buff.into();
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -67,6 +86,22 @@ mod tests {
         from_value::<T>(&Value::Bytes(to_vec_named(&data).unwrap().into()), false).unwrap()
     }
 
+    #[test]
+    fn push_offset_value() {
+        let mut value = to_value(&json!({ "foo": "bar" }), Some("event")).unwrap();
+        push_offset(&mut value, "abc").unwrap();
+        let expected = to_vec_named(&json!(["event", { "foo": "bar" }, "abc"])).unwrap();
+        assert_eq!(value.as_bytes().unwrap(), &expected);
+
+        // Header should be promoted from fixarray to array16
+        let arr: Vec<u8> = (0..15).collect();
+        let mut value = Value::Bytes(to_vec_named(&arr).unwrap().into());
+        push_offset(&mut value, "abc").unwrap();
+        let mut expected = json!(arr);
+        expected.as_array_mut().unwrap().push(json!("abc"));
+        assert_eq!(value.as_bytes().unwrap(), &to_vec_named(&expected).unwrap());
+    }
+
     const BIN: Bytes = Bytes::from_static(&[1, 2, 3, 4]);
     #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
     struct Data {
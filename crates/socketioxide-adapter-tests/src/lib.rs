@@ -0,0 +1,111 @@
+//! Common test suite of the remote adapters.
+//!
+//! Each adapter crate implements a [`Fixture`] spawning servers connected through a stub driver,
+//! usually with a [`StubFixture`](stub::StubFixture), and runs the whole suite with the [`test_suite`] macro:
+//! ```ignore
+//! mod fixture;
+//! socketioxide_adapter_tests::test_suite!(fixture::StubFixture);
+//! ```
+use std::{fmt, future::Future, time::Duration};
+
+use socketioxide::{
+    adapter::{Adapter, Emitter},
+    SocketIo,
+};
+use socketioxide_core::adapter::{CoreAdapter, DefinedAdapter};
+
+#[macro_export]
+macro_rules! timeout_rcv_err {
+    ($srx:expr) => {
+        tokio::time::timeout(std::time::Duration::from_millis(10), $srx.recv())
+            .await
+            .unwrap_err();
+    };
+}
+
+#[macro_export]
+macro_rules! timeout_rcv {
+    ($srx:expr) => {
+        TryInto::<String>::try_into(
+            tokio::time::timeout(std::time::Duration::from_millis(10), $srx.recv())
+                .await
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap()
+    };
+    ($srx:expr, $t:expr) => {
+        TryInto::<String>::try_into(
+            tokio::time::timeout(std::time::Duration::from_millis($t), $srx.recv())
+                .await
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap()
+    };
+}
+
+pub mod broadcast;
+pub mod local;
+pub mod rooms;
+pub mod sockets;
+pub mod stub;
+
+/// Spawns the servers of an adapter for the test suite.
+pub trait Fixture {
+    /// The adapter under test.
+    type Adapter: Adapter + DefinedAdapter + CoreAdapter<Emitter, InitRes = Self::InitRes>;
+    /// The future returned by the initialization of the adapter.
+    type InitRes: Future<Output = Result<(), Self::InitError>>;
+    /// The initialization error of the adapter.
+    type InitError: fmt::Debug;
+
+    /// Spawns a number of servers with a stub driver for testing.
+    /// Every server will be connected to every other server.
+    fn spawn_servers<const N: usize>() -> [SocketIo<Self::Adapter>; N];
+
+    /// Spawns a number of servers with a stub driver for testing.
+    /// Two additional servers that never respond are spawned to trigger a timeout when expecting N responses.
+    fn spawn_buggy_servers<const N: usize>(
+        timeout: Duration,
+    ) -> impl Future<Output = [SocketIo<Self::Adapter>; N]>;
+
+    /// Returns `true` if the error is a partial response error of the adapter.
+    fn is_partial(err: &<Self::Adapter as CoreAdapter<Emitter>>::Error) -> bool;
+}
+
+/// Generates a `#[tokio::test]` for each test of the suite, with the given [`Fixture`].
+#[macro_export]
+macro_rules! test_suite {
+    ($fixture:ty) => {
+        $crate::test_suite!(@tests $fixture;
+            broadcast::broadcast,
+            broadcast::broadcast_rooms,
+            broadcast::broadcast_with_ack,
+            broadcast::broadcast_with_ack_timeout,
+            local::local_fns,
+            rooms::all_rooms,
+            rooms::all_rooms_timeout,
+            rooms::add_sockets,
+            rooms::del_sockets,
+            sockets::fetch_sockets,
+            sockets::fetch_sockets_with_rooms,
+            sockets::fetch_sockets_timeout,
+            sockets::sockets_count,
+            sockets::sockets_count_timeout,
+            sockets::remote_socket_emit,
+            sockets::remote_socket_emit_with_ack,
+            sockets::remote_socket_join_leave,
+            sockets::remote_socket_disconnect,
+            sockets::to_socket,
+        );
+    };
+    (@tests $fixture:ty; $($module:ident::$name:ident),* $(,)?) => {
+        $(
+            #[tokio::test]
+            async fn $name() {
+                $crate::$module::$name::<$fixture>().await;
+            }
+        )*
+    };
+}
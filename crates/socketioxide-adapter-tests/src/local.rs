@@ -1,5 +1,6 @@
-//! Check that each adapter function with a broadcast options that is [`Local`] returns an immediate future
-mod fixture;
+//! Check that each adapter function with a broadcast options that is
+//! [`Local`](socketioxide_core::adapter::BroadcastFlags::Local) returns an immediate future
+use crate::Fixture;
 
 macro_rules! assert_now {
     ($fut:expr) => {
@@ -10,9 +11,8 @@ macro_rules! assert_now {
     };
 }
 
-#[tokio::test]
-async fn test_local_fns() {
-    let [io1, io2] = fixture::spawn_servers();
+pub async fn local_fns<F: Fixture>() {
+    let [io1, io2] = F::spawn_servers();
 
     io1.ns("/", || ()).await.unwrap();
     io2.ns("/", || ()).await.unwrap();
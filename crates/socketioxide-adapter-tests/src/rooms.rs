@@ -1,12 +1,12 @@
+//! Room tests.
 use std::time::Duration;
 
 use socketioxide::extract::SocketRef;
 
-mod fixture;
+use crate::Fixture;
 
-#[tokio::test]
-pub async fn all_rooms() {
-    let [io1, io2, io3] = fixture::spawn_servers();
+pub async fn all_rooms<F: Fixture>() {
+    let [io1, io2, io3] = F::spawn_servers();
     let handler = |rooms: &'static [&'static str]| move |socket: SocketRef<_>| socket.join(rooms);
 
     io1.ns("/", handler(&["room1", "room2"])).await.unwrap();
@@ -35,10 +35,9 @@ pub async fn all_rooms() {
     timeout_rcv_err!(&mut rx3);
 }
 
-#[tokio::test]
-pub async fn all_rooms_timeout() {
+pub async fn all_rooms_timeout<F: Fixture>() {
     const TIMEOUT: Duration = Duration::from_millis(50);
-    let [io1, io2, io3] = fixture::spawn_buggy_servers(TIMEOUT);
+    let [io1, io2, io3] = F::spawn_buggy_servers(TIMEOUT).await;
     let handler = |rooms: &'static [&'static str]| move |socket: SocketRef<_>| socket.join(rooms);
 
     io1.ns("/", handler(&["room1", "room2"])).await.unwrap();
@@ -58,7 +57,12 @@ pub async fn all_rooms_timeout() {
     const ROOMS: [&str; 3] = ["room1", "room2", "room3"];
     for io in [io1, io2, io3] {
         let now = std::time::Instant::now();
-        let mut rooms = io.rooms().await.unwrap();
+        let err = io.rooms().await.unwrap_err();
+        assert!(now.elapsed() >= TIMEOUT); // timeout time
+        assert!(F::is_partial(&err));
+
+        let now = std::time::Instant::now();
+        let mut rooms = io.allow_partial().rooms().await.unwrap();
         assert!(now.elapsed() >= TIMEOUT); // timeout time
         rooms.sort();
         assert_eq!(rooms, ROOMS);
@@ -68,10 +72,9 @@ pub async fn all_rooms_timeout() {
     timeout_rcv_err!(&mut rx2);
     timeout_rcv_err!(&mut rx3);
 }
-#[tokio::test]
-pub async fn add_sockets() {
+pub async fn add_sockets<F: Fixture>() {
     let handler = |room: &'static str| move |socket: SocketRef<_>| socket.join(room);
-    let [io1, io2] = fixture::spawn_servers();
+    let [io1, io2] = F::spawn_servers();
 
     io1.ns("/", handler("room1")).await.unwrap();
     io2.ns("/", handler("room3")).await.unwrap();
@@ -90,10 +93,9 @@ pub async fn add_sockets() {
     timeout_rcv_err!(&mut rx2);
 }
 
-#[tokio::test]
-pub async fn del_sockets() {
+pub async fn del_sockets<F: Fixture>() {
     let handler = |rooms: &'static [&'static str]| move |socket: SocketRef<_>| socket.join(rooms);
-    let [io1, io2] = fixture::spawn_servers();
+    let [io1, io2] = F::spawn_servers();
 
     io1.ns("/", handler(&["room1", "room2"])).await.unwrap();
     io2.ns("/", handler(&["room3", "room2"])).await.unwrap();
@@ -0,0 +1,300 @@
+//! Remote sockets tests.
+use std::{str::FromStr, time::Duration};
+
+use socketioxide::{
+    adapter::Adapter, extract::SocketRef, operators::BroadcastOperators, socket::RemoteSocket,
+    SocketIo,
+};
+use socketioxide_core::{adapter::RemoteSocketData, Sid, Str};
+use tokio::time::Instant;
+
+use crate::Fixture;
+
+fn extract_sid(data: &str) -> Sid {
+    let data = data
+        .split("\"sid\":\"")
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .unwrap();
+    Sid::from_str(data).unwrap()
+}
+async fn fetch_sockets_data<A: Adapter>(op: BroadcastOperators<A>) -> Vec<RemoteSocketData> {
+    let mut sockets = op
+        .fetch_sockets()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(RemoteSocket::into_data)
+        .collect::<Vec<_>>();
+    sockets.sort_by_key(|s| s.id);
+    sockets
+}
+fn create_expected_sockets<const N: usize, A: Adapter>(
+    ids: [Sid; N],
+    ios: [&SocketIo<A>; N],
+) -> [RemoteSocketData; N] {
+    let mut i = 0;
+    let mut sockets = ios.map(|io| {
+        let id = ids[i];
+        i += 1;
+        RemoteSocketData::new(id, io.config().server_id, Str::from("/"))
+    });
+    sockets.sort_by_key(|s| s.id);
+    sockets
+}
+
+pub async fn fetch_sockets<F: Fixture>() {
+    let [io1, io2, io3] = F::spawn_servers::<3>();
+
+    io1.ns("/", || ()).await.unwrap();
+    io2.ns("/", || ()).await.unwrap();
+    io3.ns("/", || ()).await.unwrap();
+
+    let (_, mut rx1) = io1.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+    let (_, mut rx3) = io3.new_dummy_sock("/", ()).await;
+
+    let id1 = extract_sid(&timeout_rcv!(&mut rx1));
+    let id2 = extract_sid(&timeout_rcv!(&mut rx2));
+    let id3 = extract_sid(&timeout_rcv!(&mut rx3));
+
+    let mut expected_sockets = create_expected_sockets([id1, id2, id3], [&io1, &io2, &io3]);
+    expected_sockets.sort_by_key(|s| s.id);
+
+    let sockets = fetch_sockets_data(io1.broadcast()).await;
+    assert_eq!(sockets, expected_sockets);
+
+    let sockets = fetch_sockets_data(io2.broadcast()).await;
+    assert_eq!(sockets, expected_sockets);
+
+    let sockets = fetch_sockets_data(io3.broadcast()).await;
+    assert_eq!(sockets, expected_sockets);
+}
+
+pub async fn fetch_sockets_with_rooms<F: Fixture>() {
+    let [io1, io2, io3] = F::spawn_servers::<3>();
+    let handler = |rooms: &'static [&'static str]| move |socket: SocketRef<_>| socket.join(rooms);
+
+    io1.ns("/", handler(&["room1", "room2"])).await.unwrap();
+    io2.ns("/", handler(&["room2", "room3"])).await.unwrap();
+    io3.ns("/", handler(&["room3", "room1"])).await.unwrap();
+
+    let (_, mut rx1) = io1.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+    let (_, mut rx3) = io3.new_dummy_sock("/", ()).await;
+
+    let id1 = extract_sid(&timeout_rcv!(&mut rx1));
+    let id2 = extract_sid(&timeout_rcv!(&mut rx2));
+    let id3 = extract_sid(&timeout_rcv!(&mut rx3));
+
+    let sockets = fetch_sockets_data(io1.to("room1")).await;
+    assert_eq!(sockets, create_expected_sockets([id1, id3], [&io1, &io3]));
+
+    let sockets = fetch_sockets_data(io1.to("room2")).await;
+    assert_eq!(sockets, create_expected_sockets([id1, id2], [&io1, &io2]));
+
+    let sockets = fetch_sockets_data(io1.to("room3")).await;
+    assert_eq!(sockets, create_expected_sockets([id2, id3], [&io2, &io3]));
+}
+
+pub async fn fetch_sockets_timeout<F: Fixture>() {
+    const TIMEOUT: Duration = Duration::from_millis(50);
+    let [io1, io2] = F::spawn_buggy_servers(TIMEOUT).await;
+
+    io1.ns("/", || ()).await.unwrap();
+    io2.ns("/", || ()).await.unwrap();
+
+    let (_, mut rx1) = io1.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+
+    timeout_rcv!(&mut rx1); // connect packet
+    timeout_rcv!(&mut rx2); // connect packet
+
+    let now = Instant::now();
+    io1.fetch_sockets().await.unwrap();
+    assert!(now.elapsed() >= TIMEOUT);
+}
+
+pub async fn sockets_count<F: Fixture>() {
+    let [io1, io2, io3] = F::spawn_servers::<3>();
+    let handler = |rooms: &'static [&'static str]| move |socket: SocketRef<_>| socket.join(rooms);
+
+    io1.ns("/", handler(&["room1", "room2"])).await.unwrap();
+    io2.ns("/", handler(&["room2", "room3"])).await.unwrap();
+    io3.ns("/", handler(&["room3", "room1"])).await.unwrap();
+
+    let (_, mut rx1) = io1.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+    let (_, mut rx3) = io3.new_dummy_sock("/", ()).await;
+
+    timeout_rcv!(&mut rx1); // connect packet
+    timeout_rcv!(&mut rx2); // connect packet
+    timeout_rcv!(&mut rx3); // connect packet
+
+    for io in [&io1, &io2, &io3] {
+        assert_eq!(io.sockets_count().await.unwrap(), 3);
+        assert_eq!(io.within("room1").sockets_count().await.unwrap(), 2);
+        assert_eq!(
+            io.within(["room1", "room3"]).sockets_count().await.unwrap(),
+            3
+        );
+        assert_eq!(io.local().sockets_count().await.unwrap(), 1);
+    }
+}
+
+pub async fn sockets_count_timeout<F: Fixture>() {
+    const TIMEOUT: Duration = Duration::from_millis(50);
+    let [io1, io2] = F::spawn_buggy_servers(TIMEOUT).await;
+
+    io1.ns("/", || ()).await.unwrap();
+    io2.ns("/", || ()).await.unwrap();
+
+    let (_, mut rx1) = io1.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+
+    timeout_rcv!(&mut rx1); // connect packet
+    timeout_rcv!(&mut rx2); // connect packet
+
+    let now = Instant::now();
+    io1.sockets_count().await.unwrap_err();
+    assert!(now.elapsed() >= TIMEOUT);
+
+    let now = Instant::now();
+    let count = io1.allow_partial().sockets_count().await.unwrap();
+    assert!(now.elapsed() >= TIMEOUT);
+    assert_eq!(count, 2);
+
+    let now = Instant::now();
+    let timeout = TIMEOUT * 2;
+    io1.timeout(timeout).sockets_count().await.unwrap_err();
+    assert!(now.elapsed() >= timeout);
+}
+
+pub async fn remote_socket_emit<F: Fixture>() {
+    let [io1, io2] = F::spawn_servers();
+
+    io1.ns("/", || ()).await.unwrap();
+    io2.ns("/", || ()).await.unwrap();
+
+    let (_, mut rx1) = io1.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+
+    timeout_rcv!(&mut rx1); // connect packet
+    timeout_rcv!(&mut rx2); // connect packet
+
+    let sockets = io1.fetch_sockets().await.unwrap();
+    for socket in sockets {
+        socket.emit("test", "hello").await.unwrap();
+    }
+
+    assert_eq!(timeout_rcv!(&mut rx1), r#"42["test","hello"]"#);
+    assert_eq!(timeout_rcv!(&mut rx2), r#"42["test","hello"]"#);
+}
+
+pub async fn remote_socket_emit_with_ack<F: Fixture>() {
+    let [io1, io2] = F::spawn_servers();
+
+    io1.ns("/", || ()).await.unwrap();
+    io2.ns("/", || ()).await.unwrap();
+
+    let (_, mut rx1) = io1.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+
+    timeout_rcv!(&mut rx1); // connect packet
+    timeout_rcv!(&mut rx2); // connect packet
+
+    let sockets = io1.fetch_sockets().await.unwrap();
+    for socket in sockets {
+        #[allow(unused_must_use)]
+        socket
+            .emit_with_ack::<_, ()>("test", "hello")
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(timeout_rcv!(&mut rx1), r#"421["test","hello"]"#);
+    assert_eq!(timeout_rcv!(&mut rx2), r#"421["test","hello"]"#);
+}
+
+pub async fn remote_socket_join_leave<F: Fixture>() {
+    let [io1, io2] = F::spawn_servers();
+
+    io1.ns("/", || ()).await.unwrap();
+    io2.ns("/", || ()).await.unwrap();
+
+    let (_, mut rx1) = io1.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+
+    let id1 = extract_sid(&timeout_rcv!(&mut rx1));
+    let id2 = extract_sid(&timeout_rcv!(&mut rx2));
+
+    // Room requests to a remote socket are sent to its server in order,
+    // so checking its rooms ensures that the previous request was handled.
+    let sockets = io1.fetch_sockets().await.unwrap();
+    for socket in &sockets {
+        socket.join(["room1", "room2"]).await.unwrap();
+        let mut rooms = socket.rooms().await.unwrap();
+        rooms.sort();
+        assert_eq!(rooms, ["room1", "room2"]);
+    }
+    let expected = create_expected_sockets([id1, id2], [&io1, &io2]);
+    assert_eq!(fetch_sockets_data(io1.to("room1")).await, expected);
+
+    for socket in &sockets {
+        socket.leave("room1").await.unwrap();
+        assert_eq!(socket.rooms().await.unwrap(), ["room2"]);
+    }
+    assert!(fetch_sockets_data(io1.to("room1")).await.is_empty());
+    assert_eq!(fetch_sockets_data(io1.to("room2")).await, expected);
+}
+
+pub async fn remote_socket_disconnect<F: Fixture>() {
+    let [io1, io2] = F::spawn_servers();
+
+    io1.ns("/", || ()).await.unwrap();
+    io2.ns("/", || ()).await.unwrap();
+
+    let (_, mut rx1) = io1.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+
+    timeout_rcv!(&mut rx1); // connect packet
+    timeout_rcv!(&mut rx2); // connect packet
+
+    let sockets = io1.fetch_sockets().await.unwrap();
+    assert_eq!(sockets.len(), 2);
+    for socket in sockets {
+        socket.disconnect().await.unwrap();
+    }
+
+    assert_eq!(timeout_rcv!(&mut rx1), "41");
+    assert_eq!(timeout_rcv!(&mut rx2), "41");
+    assert!(io1.fetch_sockets().await.unwrap().is_empty());
+}
+
+pub async fn to_socket<F: Fixture>() {
+    let [io1, io2] = F::spawn_servers();
+
+    io1.ns("/", || ()).await.unwrap();
+    io2.ns("/", || ()).await.unwrap();
+
+    let (_, mut rx1) = io1.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+
+    let id1 = extract_sid(&timeout_rcv!(&mut rx1));
+    let id2 = extract_sid(&timeout_rcv!(&mut rx2));
+
+    io1.to_socket(id2).emit("test", "hello").await.unwrap();
+    assert_eq!(timeout_rcv!(&mut rx2), r#"42["test","hello"]"#);
+    timeout_rcv_err!(&mut rx1);
+
+    let socket = io1.fetch_socket(id2).await.unwrap().unwrap();
+    assert_eq!(socket.data().server_id, io2.config().server_id);
+    assert!(io1.fetch_socket(Sid::new()).await.unwrap().is_none());
+
+    io1.to_socket(id2).join("room1").await.unwrap();
+    assert_eq!(socket.rooms().await.unwrap(), ["room1"]);
+
+    io2.to_socket(id1).disconnect().await.unwrap();
+    assert_eq!(timeout_rcv!(&mut rx1), "41");
+    timeout_rcv_err!(&mut rx2);
+}
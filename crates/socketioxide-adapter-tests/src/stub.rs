@@ -0,0 +1,177 @@
+//! Servers of a [`RemoteAdapter`] connected through an in-memory [`StubBus`].
+//!
+//! Each adapter crate wraps a [`StubBus`] in a stub driver of its backend and implements
+//! [`StubFixture`], which provides the [`Fixture`] of the test suite.
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use socketioxide::{adapter::Emitter, SocketIo};
+use socketioxide_core::remote::{
+    adapter::{Error, InitRes, RemoteAdapter, Transport},
+    MessageStream,
+};
+use tokio::sync::mpsc;
+
+use crate::Fixture;
+
+type Subscriber<M> = Box<dyn Fn(&M) + Send + Sync>;
+
+/// An in-memory bus dispatching the messages published by the stub drivers to their subscribers,
+/// like the backend of an adapter would do.
+///
+/// A mute bus never receives any message. The messages it publishes are retained and replayed to every
+/// new subscriber, so that the other servers receive its initial heartbeat.
+pub struct StubBus<M> {
+    subscribers: Arc<RwLock<Vec<Subscriber<M>>>>,
+    retained: Arc<RwLock<Vec<M>>>,
+    mute: bool,
+}
+
+impl<M: Clone + Send + Sync + 'static> StubBus<M> {
+    /// A bus sharing the same subscribers that never receives any message.
+    pub fn mute(&self) -> Self {
+        Self {
+            mute: true,
+            ..self.clone()
+        }
+    }
+
+    /// The number of messages published by the mute buses.
+    pub fn retained(&self) -> usize {
+        self.retained.read().unwrap().len()
+    }
+
+    /// Publish a message to all the subscribers.
+    pub fn publish(&self, msg: M) {
+        if self.mute {
+            self.retained.write().unwrap().push(msg.clone());
+        }
+        for subscriber in self.subscribers.read().unwrap().iter() {
+            subscriber(&msg);
+        }
+    }
+
+    /// Subscribe to the messages for which `filter_map` returns an item,
+    /// starting with the retained messages.
+    pub fn subscribe<T: Send + 'static>(
+        &self,
+        filter_map: impl Fn(&M) -> Option<T> + Send + Sync + 'static,
+    ) -> MessageStream<T> {
+        if self.mute {
+            return MessageStream::new_empty();
+        }
+        let (tx, rx) = mpsc::channel(255);
+        for msg in self.retained.read().unwrap().iter() {
+            if let Some(item) = filter_map(msg) {
+                tx.try_send(item).unwrap();
+            }
+        }
+        let subscriber = move |msg: &M| {
+            if let Some(item) = filter_map(msg) {
+                tx.try_send(item).ok();
+            }
+        };
+        self.subscribers.write().unwrap().push(Box::new(subscriber));
+        MessageStream::new(rx)
+    }
+}
+
+impl<M> Clone for StubBus<M> {
+    fn clone(&self) -> Self {
+        Self {
+            subscribers: self.subscribers.clone(),
+            retained: self.retained.clone(),
+            mute: self.mute,
+        }
+    }
+}
+
+impl<M> Default for StubBus<M> {
+    fn default() -> Self {
+        Self {
+            subscribers: Default::default(),
+            retained: Default::default(),
+            mute: false,
+        }
+    }
+}
+
+impl<M> std::fmt::Debug for StubBus<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StubBus").field("mute", &self.mute).finish()
+    }
+}
+
+/// The servers of a remote adapter whose driver is connected to a [`StubBus`].
+pub trait StubFixture {
+    /// The transport of the adapter, with the stub driver.
+    type Transport: Transport;
+    /// The messages dispatched by the bus.
+    type Message: Clone + Send + Sync + 'static;
+    /// The configuration of the adapter.
+    type Config: Clone + Default;
+
+    /// The adapter state of a server with a stub driver over the `bus`.
+    fn state(
+        bus: StubBus<Self::Message>,
+        config: Self::Config,
+    ) -> <Self::Transport as Transport>::State;
+
+    /// Set the request timeout of the `config`.
+    fn with_request_timeout(config: Self::Config, timeout: Duration) -> Self::Config;
+}
+
+/// The adapter under test of a [`StubFixture`].
+pub type StubAdapter<F> = RemoteAdapter<Emitter, <F as StubFixture>::Transport>;
+
+impl<F: StubFixture> Fixture for F {
+    type Adapter = StubAdapter<F>;
+    type InitRes = InitRes<<F::Transport as Transport>::InitError>;
+    type InitError = <F::Transport as Transport>::InitError;
+
+    fn spawn_servers<const N: usize>() -> [SocketIo<StubAdapter<F>>; N] {
+        spawn_servers_with_config::<F, N>(F::Config::default())
+    }
+
+    async fn spawn_buggy_servers<const N: usize>(
+        timeout: Duration,
+    ) -> [SocketIo<StubAdapter<F>>; N] {
+        let bus = StubBus::default();
+        let config = F::with_request_timeout(F::Config::default(), timeout);
+        for _ in 0..2 {
+            let io = spawn_server::<F>(F::state(bus.mute(), config.clone()));
+            io.ns("/", || ()).await.unwrap();
+            // keep the server running for the whole test.
+            std::mem::forget(io);
+        }
+        // wait for the initial heartbeats to be published.
+        while bus.retained() < 2 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        [0; N].map(|_| spawn_server::<F>(F::state(bus.clone(), config.clone())))
+    }
+
+    fn is_partial(err: &Error<<F::Transport as Transport>::Error>) -> bool {
+        matches!(err, Error::Partial(_))
+    }
+}
+
+/// Spawns a number of servers with a custom config, connected through the same bus.
+pub fn spawn_servers_with_config<F: StubFixture, const N: usize>(
+    config: F::Config,
+) -> [SocketIo<StubAdapter<F>>; N] {
+    let bus = StubBus::default();
+    [0; N].map(|_| spawn_server::<F>(F::state(bus.clone(), config.clone())))
+}
+
+/// Spawns a server with the given adapter state.
+pub fn spawn_server<F: StubFixture>(
+    state: <F::Transport as Transport>::State,
+) -> SocketIo<StubAdapter<F>> {
+    let (_svc, io) = SocketIo::builder()
+        .with_adapter::<StubAdapter<F>>(state)
+        .build_svc();
+    io
+}
@@ -0,0 +1,58 @@
+//! Acknowledgement related types.
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use serde::de::DeserializeOwned;
+use socketioxide_core::{errors::SocketError, parser::Parse, Value};
+use tokio::{sync::oneshot, time::Timeout};
+
+use crate::{errors::AckError, parser::Parser};
+
+pub(crate) type AckResult = Result<Value, AckError>;
+
+pin_project_lite::pin_project! {
+    /// A [`Future`] of the acknowledgement sent by the server,
+    /// returned by [`Socket::emit_with_ack`](crate::Socket::emit_with_ack).
+    ///
+    /// It resolves with an [`AckError::Timeout`] if the server did not answer
+    /// within the [`ack_timeout`](crate::ClientBuilder::ack_timeout).
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct AckFuture<T> {
+        #[pin]
+        rx: Timeout<oneshot::Receiver<AckResult>>,
+        parser: Parser,
+        _marker: PhantomData<fn() -> T>,
+    }
+}
+
+impl<T> AckFuture<T> {
+    pub(crate) fn new(rx: Timeout<oneshot::Receiver<AckResult>>, parser: Parser) -> Self {
+        Self {
+            rx,
+            parser,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Future for AckFuture<T> {
+    type Output = Result<T, AckError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = match ready!(this.rx.poll(cx)) {
+            Ok(Ok(Ok(mut value))) => this
+                .parser
+                .decode_value(&mut value, false)
+                .map_err(AckError::Decode),
+            Ok(Ok(Err(err))) => Err(err),
+            Ok(Err(_)) => Err(AckError::Socket(SocketError::Closed)),
+            Err(_) => Err(AckError::Timeout),
+        };
+        Poll::Ready(res)
+    }
+}
@@ -0,0 +1,515 @@
+//! The [`Client`] manages the engine.io connection to a server and multiplexes
+//! the namespace [`Socket`](crate::Socket)s over it.
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock, Weak,
+    },
+    time::Duration,
+};
+
+use engineioxide::client::{
+    Client as Engine, ClientBuilder as EngineBuilder, ClientConfig as EngineConfig, ClientEvent,
+    CloseReason,
+};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use socketioxide_core::{
+    packet::Packet,
+    parser::{Parse, ParseError, ParserState},
+    Str, Value,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    errors::ConnectError,
+    parser::{Parser, ParserConfig},
+    socket::{SocketBuilder, SocketInner},
+    TransportType,
+};
+
+/// Configuration of the socket.io [`Client`].
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// The path of the engine.io endpoint on the server.
+    ///
+    /// Defaults to `"/socket.io/"`.
+    pub req_path: Cow<'static, str>,
+
+    /// The transports to use, in the order in which they are tried.
+    ///
+    /// Defaults to polling then websocket.
+    pub transports: Vec<TransportType>,
+
+    /// Whether the client should try to upgrade a polling connection to websocket.
+    ///
+    /// Defaults to `true`.
+    pub upgrade: bool,
+
+    /// Headers added to every http request and to the websocket handshake.
+    pub extra_headers: HeaderMap,
+
+    /// Query parameters added to every http request and to the websocket handshake.
+    pub query: Vec<(String, String)>,
+
+    /// The amount of time to wait for a connection to the server or to a namespace.
+    ///
+    /// Defaults to 20 seconds.
+    pub connect_timeout: Duration,
+
+    /// The amount of time to wait for an acknowledgement from the server.
+    ///
+    /// Defaults to 5 seconds.
+    pub ack_timeout: Duration,
+
+    /// Whether the client should reconnect when the connection to the server is lost.
+    ///
+    /// Defaults to `true`.
+    pub reconnection: bool,
+
+    /// The number of reconnection attempts before giving up.
+    ///
+    /// Defaults to [`u32::MAX`].
+    pub reconnection_attempts: u32,
+
+    /// The delay before the first reconnection attempt. It is doubled after each failed attempt.
+    ///
+    /// Defaults to 1 second.
+    pub reconnection_delay: Duration,
+
+    /// The maximum delay between two reconnection attempts.
+    ///
+    /// Defaults to 5 seconds.
+    pub reconnection_delay_max: Duration,
+
+    /// The randomization factor applied to the reconnection delay, between 0 and 1.
+    ///
+    /// Defaults to 0.5.
+    pub randomization_factor: f64,
+
+    pub(crate) parser: Parser,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            req_path: "/socket.io/".into(),
+            transports: vec![TransportType::Polling, TransportType::Websocket],
+            upgrade: true,
+            extra_headers: HeaderMap::new(),
+            query: Vec::new(),
+            connect_timeout: Duration::from_secs(20),
+            ack_timeout: Duration::from_secs(5),
+            reconnection: true,
+            reconnection_attempts: u32::MAX,
+            reconnection_delay: Duration::from_secs(1),
+            reconnection_delay_max: Duration::from_secs(5),
+            randomization_factor: 0.5,
+            parser: Parser::default(),
+        }
+    }
+}
+
+impl ClientConfig {
+    fn engine_config(&self) -> EngineConfig {
+        EngineConfig {
+            req_path: self.req_path.clone(),
+            transports: self.transports.iter().map(|&t| t.into()).collect(),
+            upgrade: self.upgrade,
+            extra_headers: self.extra_headers.clone(),
+            query: self.query.clone(),
+        }
+    }
+
+    /// The delay to wait before the given reconnection attempt.
+    fn reconnection_delay(&self, attempt: u32) -> Duration {
+        let max = self.reconnection_delay_max;
+        let delay = (self.reconnection_delay)
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(max);
+        let factor = self.randomization_factor.clamp(0.0, 1.0);
+        let deviation = delay.mul_f64(rand::random::<f64>() * factor);
+        if rand::random() {
+            delay.saturating_add(deviation).min(max)
+        } else {
+            delay.saturating_sub(deviation)
+        }
+    }
+}
+
+/// A builder to create a [`Client`] connected to a server.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    url: String,
+    config: ClientConfig,
+}
+
+impl ClientBuilder {
+    /// Create a new [`ClientBuilder`] for the given server url.
+    ///
+    /// Only the `http` and `ws` schemes are supported. The path of the url is ignored,
+    /// use [`ClientBuilder::req_path`] to change the path of the engine.io endpoint
+    /// and [`Client::socket`] to connect to a namespace.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            config: ClientConfig::default(),
+        }
+    }
+
+    /// The path of the engine.io endpoint on the server.
+    ///
+    /// Defaults to `"/socket.io/"`.
+    pub fn req_path(mut self, req_path: impl Into<Cow<'static, str>>) -> Self {
+        self.config.req_path = req_path.into();
+        self
+    }
+
+    /// The transports to use, in the order in which they are tried.
+    /// If the first transport is polling, the connection is upgraded
+    /// to websocket when it is allowed.
+    ///
+    /// Defaults to polling then websocket.
+    pub fn transports<const N: usize>(mut self, transports: [TransportType; N]) -> Self {
+        self.config.transports = transports.to_vec();
+        self
+    }
+
+    /// Whether the client should try to upgrade a polling connection to websocket.
+    ///
+    /// Defaults to `true`.
+    pub fn upgrade(mut self, upgrade: bool) -> Self {
+        self.config.upgrade = upgrade;
+        self
+    }
+
+    /// Add a header to every http request and to the websocket handshake.
+    pub fn extra_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.config.extra_headers.insert(name, value);
+        self
+    }
+
+    /// Add a query parameter to every http request and to the websocket handshake.
+    pub fn query(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.query.push((key.into(), value.into()));
+        self
+    }
+
+    /// The amount of time to wait for a connection to the server or to a namespace.
+    ///
+    /// Defaults to 20 seconds.
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.config.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// The amount of time to wait for an acknowledgement from the server.
+    ///
+    /// Defaults to 5 seconds.
+    pub fn ack_timeout(mut self, ack_timeout: Duration) -> Self {
+        self.config.ack_timeout = ack_timeout;
+        self
+    }
+
+    /// Whether the client should reconnect when the connection to the server is lost.
+    /// Sockets disconnected by the server or by the client are not reconnected.
+    ///
+    /// Defaults to `true`.
+    pub fn reconnection(mut self, reconnection: bool) -> Self {
+        self.config.reconnection = reconnection;
+        self
+    }
+
+    /// The number of reconnection attempts before giving up.
+    ///
+    /// Defaults to [`u32::MAX`].
+    pub fn reconnection_attempts(mut self, reconnection_attempts: u32) -> Self {
+        self.config.reconnection_attempts = reconnection_attempts;
+        self
+    }
+
+    /// The delay before the first reconnection attempt and the maximum delay between two attempts.
+    /// The delay is doubled after each failed attempt.
+    ///
+    /// Defaults to 1 and 5 seconds.
+    pub fn reconnection_delay(mut self, delay: Duration, delay_max: Duration) -> Self {
+        self.config.reconnection_delay = delay;
+        self.config.reconnection_delay_max = delay_max;
+        self
+    }
+
+    /// The randomization factor applied to the reconnection delay, between 0 and 1.
+    ///
+    /// Defaults to 0.5.
+    pub fn randomization_factor(mut self, randomization_factor: f64) -> Self {
+        self.config.randomization_factor = randomization_factor;
+        self
+    }
+
+    /// The parser to use to encode and decode socket.io packets.
+    /// It should match the parser of the server.
+    ///
+    /// Defaults to the common parser.
+    pub fn with_parser(mut self, parser: ParserConfig) -> Self {
+        self.config.parser = parser.0;
+        self
+    }
+
+    /// Replace the whole configuration of the client.
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Open the engine.io connection to the server.
+    /// Namespaces can then be joined with [`Client::socket`].
+    pub async fn connect(self) -> Result<Client, ConnectError> {
+        let (engine, rx) = connect_engine(&self.url, &self.config).await?;
+        let inner = Arc::new(ClientInner {
+            config: self.config,
+            url: self.url,
+            engine: RwLock::new(Some(engine)),
+            sockets: RwLock::new(HashMap::new()),
+            closed: AtomicBool::new(false),
+        });
+        tokio::spawn(ClientInner::run(Arc::downgrade(&inner), rx));
+        Ok(Client(inner))
+    }
+}
+
+async fn connect_engine(
+    url: &str,
+    config: &ClientConfig,
+) -> Result<(Engine, mpsc::UnboundedReceiver<ClientEvent>), ConnectError> {
+    let builder = EngineBuilder::new(url).with_config(config.engine_config());
+    let (engine, rx) = tokio::time::timeout(config.connect_timeout, builder.connect())
+        .await
+        .map_err(|_| ConnectError::Timeout)??;
+    Ok((engine, rx))
+}
+
+/// A socket.io client connected to a server.
+///
+/// It holds the engine.io connection, reconnects it when it is lost,
+/// and routes the incoming packets to the [`Socket`](crate::Socket) of each namespace.
+///
+/// It can be cheaply cloned, the connection is closed when the client and all its sockets
+/// are dropped or when [`Client::close`] is called.
+#[derive(Debug, Clone)]
+pub struct Client(pub(crate) Arc<ClientInner>);
+
+impl Client {
+    /// Create a new [`ClientBuilder`] for the given server url.
+    pub fn builder(url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(url)
+    }
+
+    /// Connect to the given server url with the default configuration.
+    pub async fn connect(url: impl Into<String>) -> Result<Self, ConnectError> {
+        ClientBuilder::new(url).connect().await
+    }
+
+    /// Create a [`SocketBuilder`] to connect to the given namespace.
+    /// There can only be one connected socket per namespace.
+    pub fn socket(&self, ns: impl Into<Cow<'static, str>>) -> SocketBuilder {
+        SocketBuilder::new(self.0.clone(), ns.into())
+    }
+
+    /// The configuration of the client
+    pub fn config(&self) -> &ClientConfig {
+        &self.0.config
+    }
+
+    /// The engine.io session id, if the client is currently connected.
+    pub fn id(&self) -> Option<Str> {
+        self.0
+            .engine
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|e| e.sid().clone())
+    }
+
+    /// The transport currently used, if the client is connected.
+    pub fn transport(&self) -> Option<TransportType> {
+        self.0
+            .engine
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|e| e.transport().into())
+    }
+
+    /// Whether the engine.io connection is currently opened.
+    pub fn connected(&self) -> bool {
+        self.0.engine.read().unwrap().is_some()
+    }
+
+    /// Close the connection to the server. All the sockets are disconnected
+    /// and the client will not try to reconnect.
+    pub fn close(&self) {
+        self.0.closed.store(true, Ordering::Release);
+        if let Some(engine) = self.0.engine.read().unwrap().as_ref() {
+            engine.close();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ClientInner {
+    pub(crate) config: ClientConfig,
+    url: String,
+    engine: RwLock<Option<Engine>>,
+    pub(crate) sockets: RwLock<HashMap<Str, Arc<SocketInner>>>,
+    closed: AtomicBool,
+}
+
+impl ClientInner {
+    pub(crate) fn parser(&self) -> Parser {
+        self.config.parser
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Encode and send a packet through the engine.io connection.
+    /// If the client is not connected, the packet is given back.
+    pub(crate) fn send(&self, packet: Packet) -> Result<(), Packet> {
+        let engine = self.engine.read().unwrap();
+        let Some(engine) = engine.as_ref() else {
+            return Err(packet);
+        };
+        match self.parser().encode(packet) {
+            Value::Str(msg, bins) => {
+                engine.emit(msg).ok();
+                for bin in bins.into_iter().flatten() {
+                    engine.emit_binary(bin).ok();
+                }
+            }
+            Value::Bytes(bin) => {
+                engine.emit_binary(bin).ok();
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn remove_socket(&self, socket: &Arc<SocketInner>) {
+        let mut sockets = self.sockets.write().unwrap();
+        if sockets
+            .get(&socket.ns)
+            .is_some_and(|s| Arc::ptr_eq(s, socket))
+        {
+            sockets.remove(&socket.ns);
+        }
+    }
+
+    /// Route the engine.io events to the sockets and reconnect when the connection is lost.
+    async fn run(client: Weak<Self>, mut rx: mpsc::UnboundedReceiver<ClientEvent>) {
+        loop {
+            let state = ParserState::default();
+            let reason = loop {
+                let event = rx.recv().await;
+                let Some(client) = client.upgrade() else {
+                    return;
+                };
+                match event {
+                    Some(ClientEvent::Message(msg)) => {
+                        let packet = client.parser().decode_str(&state, msg);
+                        client.on_packet(packet);
+                    }
+                    Some(ClientEvent::Binary(bin)) => {
+                        let packet = client.parser().decode_bin(&state, bin);
+                        client.on_packet(packet);
+                    }
+                    Some(ClientEvent::Close(reason)) => break reason,
+                    None => break CloseReason::TransportError,
+                }
+            };
+
+            {
+                let Some(client) = client.upgrade() else {
+                    return;
+                };
+                client.engine.write().unwrap().take();
+                client.on_close(reason);
+                if client.is_closed() || !client.config.reconnection {
+                    return;
+                }
+            }
+            match Self::reconnect(&client).await {
+                Some(new_rx) => rx = new_rx,
+                None => return,
+            }
+        }
+    }
+
+    async fn reconnect(client: &Weak<Self>) -> Option<mpsc::UnboundedReceiver<ClientEvent>> {
+        let mut attempt = 0;
+        loop {
+            let delay = {
+                let client = client.upgrade()?;
+                if client.is_closed() || attempt >= client.config.reconnection_attempts {
+                    return None;
+                }
+                client.config.reconnection_delay(attempt)
+            };
+            tokio::time::sleep(delay).await;
+
+            let client = client.upgrade()?;
+            if client.is_closed() {
+                return None;
+            }
+            #[cfg(feature = "tracing")]
+            tracing::debug!(attempt, "reconnecting");
+            match connect_engine(&client.url, &client.config).await {
+                Ok((engine, rx)) => {
+                    client.engine.write().unwrap().replace(engine);
+                    let sockets: Vec<_> =
+                        client.sockets.read().unwrap().values().cloned().collect();
+                    for socket in sockets {
+                        socket.send_connect(&client);
+                    }
+                    return Some(rx);
+                }
+                Err(_e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(?_e, attempt, "reconnection failed");
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    fn on_packet(self: &Arc<Self>, packet: Result<Packet, ParseError>) {
+        let packet = match packet {
+            Ok(packet) => packet,
+            // Waiting for the binary attachments of the packet
+            Err(ParseError::NeedsMoreBinaryData) => return,
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(?_e, "invalid packet received");
+                return;
+            }
+        };
+        let socket = self.sockets.read().unwrap().get(&packet.ns).cloned();
+        let Some(socket) = socket else {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(ns = ?packet.ns, "packet received for an unknown namespace");
+            return;
+        };
+        socket.recv(self, packet.inner);
+    }
+
+    fn on_close(self: &Arc<Self>, reason: CloseReason) {
+        let sockets: Vec<_> = self.sockets.read().unwrap().values().cloned().collect();
+        let closed = self.is_closed() || !self.config.reconnection;
+        for socket in sockets {
+            socket.on_engine_close(self, reason.into(), closed);
+        }
+        if closed {
+            self.sockets.write().unwrap().clear();
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Error types of the socket.io client.
+
+pub use engineioxide::client::ClientError as EngineError;
+pub use socketioxide_core::{errors::SocketError, parser::ParserError};
+
+/// Error type for the connection to a server or to a namespace.
+#[derive(thiserror::Error, Debug)]
+pub enum ConnectError {
+    /// The engine.io connection to the server failed
+    #[error("engine.io connection error: {0}")]
+    Engine(#[from] EngineError),
+
+    /// The connection was not established in time
+    #[error("connection timeout")]
+    Timeout,
+
+    /// The server refused the connection to the namespace.
+    /// It contains the message of the `connect_error` packet.
+    #[error("connection refused: {0}")]
+    Refused(String),
+
+    /// The auth payload cannot be serialized
+    #[error("cannot serialize auth payload: {0:?}")]
+    Auth(#[from] ParserError),
+
+    /// The client was closed before the connection could be established
+    #[error("client closed")]
+    Closed,
+}
+
+/// Error type for sending operations.
+#[derive(thiserror::Error, Debug)]
+pub enum SendError {
+    /// An error occurred while serializing the packet.
+    #[error("Error serializing packet: {0:?}")]
+    Serialize(#[from] ParserError),
+
+    /// The socket was disconnected from its namespace and will not reconnect.
+    #[error("Error sending data through the engine.io socket: {0:?}")]
+    Socket(#[from] SocketError),
+}
+
+/// Error type for ack operations.
+#[derive(thiserror::Error, Debug)]
+pub enum AckError {
+    /// The ack response cannot be parsed
+    #[error("cannot deserialize packet from ack response: {0:?}")]
+    Decode(#[from] ParserError),
+
+    /// The ack response timed out
+    #[error("ack timeout error")]
+    Timeout,
+
+    /// The socket was disconnected before receiving the ack response
+    #[error("Error sending data through the engine.io socket: {0:?}")]
+    Socket(#[from] SocketError),
+}
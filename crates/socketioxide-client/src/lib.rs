@@ -0,0 +1,128 @@
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![warn(
+    clippy::all,
+    clippy::todo,
+    clippy::empty_enums,
+    clippy::mem_forget,
+    clippy::unused_self,
+    clippy::filter_map_next,
+    clippy::needless_continue,
+    clippy::needless_borrow,
+    clippy::match_wildcard_for_single_variants,
+    clippy::if_let_mutex,
+    clippy::await_holding_lock,
+    clippy::imprecise_flops,
+    clippy::suboptimal_flops,
+    clippy::lossy_float_literal,
+    clippy::rest_pat_in_fully_bound_structs,
+    clippy::fn_params_excessive_bools,
+    clippy::exit,
+    clippy::inefficient_to_string,
+    clippy::linkedlist,
+    clippy::macro_use_imports,
+    clippy::option_option,
+    clippy::verbose_file_reads,
+    clippy::unnested_or_patterns,
+    rust_2018_idioms,
+    future_incompatible,
+    nonstandard_style,
+    missing_docs
+)]
+
+//! # A socket.io client implementation sharing its protocol implementation with socketioxide.
+//!
+//! The engine.io connection is handled by the [`engineioxide::client`] module.
+//!
+//! It is primarily meant to write end-to-end tests of socketioxide servers
+//! and to communicate between rust services with socket.io.
+//!
+//! ## Features
+//! * Polling and websocket transports, with the upgrade from polling to websocket
+//! * Namespaces multiplexed over a single connection
+//! * Acknowledgements in both directions
+//! * Binary payloads
+//! * Automatic reconnection with an exponential backoff
+//! * Common and MsgPack (with the `msgpack` feature) parsers, the same as the server ones
+//!
+//! ## Example
+//! ```no_run
+//! use socketioxide_client::{Client, TransportType};
+//! # async fn doc_main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::builder("http://127.0.0.1:3000")
+//!     .transports([TransportType::Websocket])
+//!     .connect()
+//!     .await?;
+//!
+//! let socket = client
+//!     .socket("/chat")
+//!     .auth(&serde_json::json!({ "token": "secret" }))
+//!     .on("message", |socket, event| {
+//!         let msg: String = event.data().unwrap();
+//!         println!("received {msg} on {}", socket.ns());
+//!         // Answer the server if it requested an acknowledgement
+//!         event.ack("ok").ok();
+//!     })
+//!     .connect()
+//!     .await?;
+//!
+//! socket.emit("message", "hello")?;
+//! let res: String = socket.emit_with_ack("echo", "hello")?.await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Limitations
+//! * Only the socket.io protocol v5 (engine.io v4) is supported.
+//! * TLS is not supported, only the `http` and `ws` schemes can be used.
+//! * Handlers are called inline when packets are received, long running tasks should be spawned.
+pub mod ack;
+pub mod client;
+pub mod errors;
+pub mod parser;
+pub mod socket;
+
+pub use ack::AckFuture;
+pub use client::{Client, ClientBuilder, ClientConfig};
+pub use errors::{AckError, ConnectError, SendError};
+pub use parser::ParserConfig;
+pub use socket::{DisconnectReason, Event, Socket, SocketBuilder};
+pub use socketioxide_core::{Sid, Str};
+
+/// The transports available to connect to a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransportType {
+    /// Http long polling transport
+    Polling,
+    /// Websocket transport
+    Websocket,
+}
+
+impl From<TransportType> for engineioxide::TransportType {
+    fn from(transport: TransportType) -> Self {
+        match transport {
+            TransportType::Polling => engineioxide::TransportType::Polling,
+            TransportType::Websocket => engineioxide::TransportType::Websocket,
+        }
+    }
+}
+
+impl From<engineioxide::TransportType> for TransportType {
+    /// The engine.io client only opens polling or websocket connections.
+    fn from(transport: engineioxide::TransportType) -> Self {
+        if transport == engineioxide::TransportType::Websocket {
+            TransportType::Websocket
+        } else {
+            TransportType::Polling
+        }
+    }
+}
+
+impl TransportType {
+    /// The name of the transport in the engine.io protocol.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransportType::Polling => "polling",
+            TransportType::Websocket => "websocket",
+        }
+    }
+}
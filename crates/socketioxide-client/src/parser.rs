@@ -0,0 +1,134 @@
+//! The parsers available to encode and decode socket.io packets.
+//! They are the same parsers as the ones used by the socketioxide server.
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use socketioxide_core::{
+    packet::Packet,
+    parser::{Parse, ParseError, ParserError, ParserState},
+    Str, Value,
+};
+use socketioxide_parser_common::CommonParser;
+
+#[cfg(feature = "msgpack")]
+use socketioxide_parser_msgpack::MsgPackParser;
+
+/// The parser to use to encode and decode socket.io packets
+///
+/// Be sure that the selected parser matches the server parser.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParserConfig(pub(crate) Parser);
+
+impl ParserConfig {
+    /// Use a [`CommonParser`] to parse incoming and outgoing socket.io packets
+    pub fn common() -> Self {
+        ParserConfig(Parser::Common(CommonParser))
+    }
+
+    /// Use a [`MsgPackParser`] to parse incoming and outgoing socket.io packets
+    #[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+    #[cfg(feature = "msgpack")]
+    pub fn msgpack() -> Self {
+        ParserConfig(Parser::MsgPack(MsgPackParser))
+    }
+}
+
+/// All the parsers available.
+/// The [`Parse`] implementation is done over enum delegation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Parser {
+    Common(CommonParser),
+    #[cfg(feature = "msgpack")]
+    MsgPack(MsgPackParser),
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::Common(CommonParser)
+    }
+}
+
+impl Parse for Parser {
+    fn encode(self, packet: Packet) -> Value {
+        match self {
+            Parser::Common(p) => p.encode(packet),
+            #[cfg(feature = "msgpack")]
+            Parser::MsgPack(p) => p.encode(packet),
+        }
+    }
+
+    fn decode_str(self, state: &ParserState, data: Str) -> Result<Packet, ParseError> {
+        match self {
+            Parser::Common(p) => p.decode_str(state, data),
+            #[cfg(feature = "msgpack")]
+            Parser::MsgPack(p) => p.decode_str(state, data),
+        }
+    }
+
+    fn decode_bin(self, state: &ParserState, bin: Bytes) -> Result<Packet, ParseError> {
+        match self {
+            Parser::Common(p) => p.decode_bin(state, bin),
+            #[cfg(feature = "msgpack")]
+            Parser::MsgPack(p) => p.decode_bin(state, bin),
+        }
+    }
+
+    fn encode_value<T: ?Sized + Serialize>(
+        self,
+        data: &T,
+        event: Option<&str>,
+    ) -> Result<Value, ParserError> {
+        match self {
+            Parser::Common(p) => p.encode_value(data, event),
+            #[cfg(feature = "msgpack")]
+            Parser::MsgPack(p) => p.encode_value(data, event),
+        }
+    }
+
+    fn decode_value<'de, T: Deserialize<'de>>(
+        self,
+        value: &'de mut Value,
+        with_event: bool,
+    ) -> Result<T, ParserError> {
+        match self {
+            Parser::Common(p) => p.decode_value(value, with_event),
+            #[cfg(feature = "msgpack")]
+            Parser::MsgPack(p) => p.decode_value(value, with_event),
+        }
+    }
+
+    fn decode_default<'de, T: Deserialize<'de>>(
+        self,
+        value: Option<&'de Value>,
+    ) -> Result<T, ParserError> {
+        match self {
+            Parser::Common(p) => p.decode_default(value),
+            #[cfg(feature = "msgpack")]
+            Parser::MsgPack(p) => p.decode_default(value),
+        }
+    }
+
+    fn encode_default<T: ?Sized + Serialize>(self, data: &T) -> Result<Value, ParserError> {
+        match self {
+            Parser::Common(p) => p.encode_default(data),
+            #[cfg(feature = "msgpack")]
+            Parser::MsgPack(p) => p.encode_default(data),
+        }
+    }
+
+    fn read_event(self, value: &Value) -> Result<&str, ParserError> {
+        match self {
+            Parser::Common(p) => p.read_event(value),
+            #[cfg(feature = "msgpack")]
+            Parser::MsgPack(p) => p.read_event(value),
+        }
+    }
+
+    fn push_offset(self, value: &mut Value, offset: &str) -> Result<(), ParserError> {
+        match self {
+            Parser::Common(p) => p.push_offset(value, offset),
+            #[cfg(feature = "msgpack")]
+            Parser::MsgPack(p) => p.push_offset(value, offset),
+        }
+    }
+}
@@ -0,0 +1,502 @@
+//! A [`Socket`] is the connection of a [`Client`](crate::Client) to a namespace of the server.
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use engineioxide::client::CloseReason;
+use serde::{de::DeserializeOwned, Serialize};
+use socketioxide_core::{
+    errors::SocketError,
+    packet::{ConnectPacket, Packet, PacketData},
+    parser::{Parse, ParserError},
+    Sid, Str, Value,
+};
+use tokio::sync::oneshot;
+
+use crate::{
+    ack::{AckFuture, AckResult},
+    client::ClientInner,
+    errors::{AckError, ConnectError, SendError},
+};
+
+type EventHandler = Arc<dyn Fn(Socket, Event) + Send + Sync + 'static>;
+type ConnectHandler = Arc<dyn Fn(Socket) + Send + Sync + 'static>;
+type DisconnectHandler = Arc<dyn Fn(Socket, DisconnectReason) + Send + Sync + 'static>;
+
+/// All the possible reasons for a [`Socket`] to be disconnected from a namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The server disconnected the socket from the namespace
+    ServerNSDisconnect,
+
+    /// The socket was disconnected with [`Socket::disconnect`] or [`Client::close`](crate::Client::close)
+    ClientNSDisconnect,
+
+    /// The server closed the connection
+    TransportClose,
+
+    /// The connection was abruptly closed
+    TransportError,
+
+    /// The server did not send a PING packet in time
+    PingTimeout,
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DisconnectReason::*;
+        let str: &'static str = match self {
+            ServerNSDisconnect => "server has disconnected the socket from the namespace",
+            ClientNSDisconnect => "socket was manually disconnected from the namespace",
+            TransportClose => "server gracefully closed the connection",
+            TransportError => "The connection was abruptly closed",
+            PingTimeout => "server did not send a PING packet in time",
+        };
+        f.write_str(str)
+    }
+}
+
+impl From<CloseReason> for DisconnectReason {
+    fn from(reason: CloseReason) -> Self {
+        match reason {
+            CloseReason::TransportClose => DisconnectReason::TransportClose,
+            CloseReason::TransportError => DisconnectReason::TransportError,
+            CloseReason::PingTimeout => DisconnectReason::PingTimeout,
+            CloseReason::ClientClose => DisconnectReason::ClientNSDisconnect,
+        }
+    }
+}
+
+/// An event received from the server.
+#[derive(Debug)]
+pub struct Event {
+    socket: Socket,
+    data: Value,
+    ack: Option<i64>,
+}
+
+impl Event {
+    /// The name of the event.
+    pub fn name(&self) -> &str {
+        self.socket
+            .parser()
+            .read_event(&self.data)
+            .unwrap_or_default()
+    }
+
+    /// Deserialize the arguments of the event.
+    ///
+    /// * If `T` is a tuple-like type, all the arguments are deserialized.
+    /// * Otherwise only the first argument is deserialized.
+    pub fn data<T: DeserializeOwned>(&self) -> Result<T, ParserError> {
+        let mut data = self.data.clone();
+        self.socket.parser().decode_value(&mut data, true)
+    }
+
+    /// Whether the server expects an acknowledgement for this event.
+    pub fn needs_ack(&self) -> bool {
+        self.ack.is_some()
+    }
+
+    /// Send an acknowledgement to the server.
+    /// It does nothing if the server did not request an acknowledgement.
+    pub fn ack<T: ?Sized + Serialize>(self, data: &T) -> Result<(), SendError> {
+        let Some(ack) = self.ack else {
+            return Ok(());
+        };
+        let data = self.socket.parser().encode_value(data, None)?;
+        let packet = Packet::ack(self.socket.inner.ns.clone(), data, ack);
+        self.socket.send(packet)
+    }
+}
+
+/// A builder to connect a [`Socket`] to a namespace, returned by [`Client::socket`](crate::Client::socket).
+pub struct SocketBuilder {
+    client: Arc<ClientInner>,
+    ns: Str,
+    auth: Option<Result<Value, ParserError>>,
+    handlers: HashMap<Cow<'static, str>, EventHandler>,
+    connect_handler: Option<ConnectHandler>,
+    disconnect_handler: Option<DisconnectHandler>,
+}
+
+impl SocketBuilder {
+    pub(crate) fn new(client: Arc<ClientInner>, ns: Cow<'static, str>) -> Self {
+        let ns = match ns {
+            ns if ns.starts_with('/') => Str::from(ns),
+            ns => Str::from(format!("/{ns}")),
+        };
+        Self {
+            client,
+            ns,
+            auth: None,
+            handlers: HashMap::new(),
+            connect_handler: None,
+            disconnect_handler: None,
+        }
+    }
+
+    /// The auth payload sent to the server with each connection request.
+    pub fn auth<T: ?Sized + Serialize>(mut self, auth: &T) -> Self {
+        self.auth = Some(self.client.parser().encode_default(auth));
+        self
+    }
+
+    /// Register a handler for the given event.
+    ///
+    /// Handlers are called inline when packets are received,
+    /// long running tasks should be spawned.
+    pub fn on<F>(mut self, event: impl Into<Cow<'static, str>>, handler: F) -> Self
+    where
+        F: Fn(Socket, Event) + Send + Sync + 'static,
+    {
+        self.handlers.insert(event.into(), Arc::new(handler));
+        self
+    }
+
+    /// Register a handler called each time the socket is connected to the namespace,
+    /// including after a reconnection.
+    pub fn on_connect<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(Socket) + Send + Sync + 'static,
+    {
+        self.connect_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Register a handler called each time the socket is disconnected from the namespace.
+    pub fn on_disconnect<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(Socket, DisconnectReason) + Send + Sync + 'static,
+    {
+        self.disconnect_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Connect to the namespace and wait for the server to accept the connection.
+    pub async fn connect(self) -> Result<Socket, ConnectError> {
+        if self.client.is_closed() {
+            return Err(ConnectError::Closed);
+        }
+        let auth = self.auth.transpose()?;
+        let (tx, rx) = oneshot::channel();
+        let inner = Arc::new(SocketInner {
+            ns: self.ns,
+            auth,
+            handlers: RwLock::new(self.handlers),
+            connect_handler: self.connect_handler,
+            disconnect_handler: self.disconnect_handler,
+            id: RwLock::new(None),
+            state: Mutex::new(State {
+                connected: false,
+                active: true,
+                buffer: Vec::new(),
+                connect_tx: Some(tx),
+            }),
+            acks: Mutex::new(HashMap::new()),
+            ack_id: AtomicI64::new(0),
+        });
+        let old = (self.client.sockets.write().unwrap()).insert(inner.ns.clone(), inner.clone());
+        if let Some(old) = old {
+            old.close(&self.client, DisconnectReason::ClientNSDisconnect);
+        }
+        inner.send_connect(&self.client);
+
+        let timeout = self.client.config.connect_timeout;
+        let socket = Socket {
+            inner,
+            client: self.client,
+        };
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(Ok(()))) => Ok(socket),
+            Ok(Ok(Err(e))) => Err(e),
+            Ok(Err(_)) => Err(ConnectError::Closed),
+            Err(_) => {
+                socket.client.remove_socket(&socket.inner);
+                Err(ConnectError::Timeout)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SocketBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SocketBuilder")
+            .field("ns", &self.ns)
+            .field("auth", &self.auth)
+            .finish()
+    }
+}
+
+/// A socket connected to a namespace of the server.
+///
+/// It can be cheaply cloned. When the connection to the server is lost,
+/// packets are buffered and the socket is reconnected with the [`Client`](crate::Client).
+#[derive(Clone)]
+pub struct Socket {
+    inner: Arc<SocketInner>,
+    client: Arc<ClientInner>,
+}
+
+impl Socket {
+    /// The id of the socket, if it is connected.
+    /// A new id is assigned by the server on each connection.
+    pub fn id(&self) -> Option<Sid> {
+        *self.inner.id.read().unwrap()
+    }
+
+    /// The namespace of the socket.
+    pub fn ns(&self) -> &str {
+        &self.inner.ns
+    }
+
+    /// Whether the socket is currently connected to its namespace.
+    pub fn connected(&self) -> bool {
+        self.inner.state.lock().unwrap().connected
+    }
+
+    /// Register a handler for the given event, replacing any previous handler.
+    pub fn on<F>(&self, event: impl Into<Cow<'static, str>>, handler: F)
+    where
+        F: Fn(Socket, Event) + Send + Sync + 'static,
+    {
+        (self.inner.handlers.write().unwrap()).insert(event.into(), Arc::new(handler));
+    }
+
+    /// Remove the handler of the given event.
+    pub fn off(&self, event: &str) {
+        self.inner.handlers.write().unwrap().remove(event);
+    }
+
+    /// Emit an event to the server.
+    ///
+    /// * If the data is a tuple-like type, it is sent as multiple arguments.
+    /// * If the socket is not connected, the packet is buffered until the socket is connected.
+    pub fn emit<T: ?Sized + Serialize>(
+        &self,
+        event: impl AsRef<str>,
+        data: &T,
+    ) -> Result<(), SendError> {
+        let data = self.parser().encode_value(data, Some(event.as_ref()))?;
+        self.send(Packet::event(self.inner.ns.clone(), data))
+    }
+
+    /// Emit an event to the server and wait for its acknowledgement.
+    ///
+    /// The returned [`AckFuture`] resolves with the first argument of the acknowledgement
+    /// or with all of them if `V` is a tuple-like type.
+    pub fn emit_with_ack<T: ?Sized + Serialize, V: DeserializeOwned>(
+        &self,
+        event: impl AsRef<str>,
+        data: &T,
+    ) -> Result<AckFuture<V>, SendError> {
+        let data = self.parser().encode_value(data, Some(event.as_ref()))?;
+        let mut packet = Packet::event(self.inner.ns.clone(), data);
+        let id = self.inner.ack_id.fetch_add(1, Ordering::SeqCst) + 1;
+        packet.inner.set_ack_id(id);
+
+        let (tx, rx) = oneshot::channel();
+        self.inner.acks.lock().unwrap().insert(id, tx);
+        if let Err(e) = self.send(packet) {
+            self.inner.acks.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+        let rx = tokio::time::timeout(self.client.config.ack_timeout, rx);
+        Ok(AckFuture::new(rx, self.parser()))
+    }
+
+    /// Disconnect the socket from its namespace. It will not be reconnected.
+    pub fn disconnect(&self) {
+        let was_active = self.inner.state.lock().unwrap().active;
+        if was_active {
+            self.client
+                .send(Packet::disconnect(self.inner.ns.clone()))
+                .ok();
+            self.inner
+                .close(&self.client, DisconnectReason::ClientNSDisconnect);
+        }
+    }
+
+    fn parser(&self) -> crate::parser::Parser {
+        self.client.parser()
+    }
+
+    /// Send a packet or buffer it if the socket is not connected.
+    fn send(&self, packet: Packet) -> Result<(), SendError> {
+        let mut state = self.inner.state.lock().unwrap();
+        if !state.active {
+            return Err(SendError::Socket(SocketError::Closed));
+        }
+        if !state.connected {
+            state.buffer.push(packet);
+            return Ok(());
+        }
+        if let Err(packet) = self.client.send(packet) {
+            state.buffer.push(packet);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Socket")
+            .field("ns", &self.inner.ns)
+            .field("id", &self.id())
+            .finish()
+    }
+}
+
+struct State {
+    /// Whether the socket is connected to its namespace
+    connected: bool,
+    /// Whether the socket should be connected, it is false once the socket
+    /// is disconnected by the server or the client
+    active: bool,
+    /// Packets emitted while the socket is not connected
+    buffer: Vec<Packet>,
+    /// Notify the pending [`SocketBuilder::connect`] call
+    connect_tx: Option<oneshot::Sender<Result<(), ConnectError>>>,
+}
+
+pub(crate) struct SocketInner {
+    pub(crate) ns: Str,
+    auth: Option<Value>,
+    handlers: RwLock<HashMap<Cow<'static, str>, EventHandler>>,
+    connect_handler: Option<ConnectHandler>,
+    disconnect_handler: Option<DisconnectHandler>,
+    id: RwLock<Option<Sid>>,
+    state: Mutex<State>,
+    acks: Mutex<HashMap<i64, oneshot::Sender<AckResult>>>,
+    ack_id: AtomicI64,
+}
+
+impl SocketInner {
+    /// Send the connect packet with the auth payload.
+    /// If the client is not connected it will be sent once it is reconnected.
+    pub(crate) fn send_connect(&self, client: &ClientInner) {
+        client
+            .send(Packet::connect(self.ns.clone(), self.auth.clone()))
+            .ok();
+    }
+
+    pub(crate) fn recv(self: &Arc<Self>, client: &Arc<ClientInner>, packet: PacketData) {
+        match packet {
+            PacketData::Connect(data) => self.on_connect(client, data),
+            PacketData::ConnectError(message, _) => {
+                self.state.lock().unwrap().active = false;
+                client.remove_socket(self);
+                if let Some(tx) = self.state.lock().unwrap().connect_tx.take() {
+                    tx.send(Err(ConnectError::Refused(message))).ok();
+                }
+            }
+            PacketData::Disconnect => self.close(client, DisconnectReason::ServerNSDisconnect),
+            PacketData::Event(data, ack) | PacketData::BinaryEvent(data, ack) => {
+                let handler = client
+                    .parser()
+                    .read_event(&data)
+                    .ok()
+                    .and_then(|event| self.handlers.read().unwrap().get(event).cloned());
+                if let Some(handler) = handler {
+                    let socket = self.socket(client);
+                    handler(socket.clone(), Event { socket, data, ack });
+                }
+            }
+            PacketData::EventAck(data, ack) | PacketData::BinaryAck(data, ack) => {
+                if let Some(tx) = self.acks.lock().unwrap().remove(&ack) {
+                    tx.send(Ok(data)).ok();
+                }
+            }
+        }
+    }
+
+    fn on_connect(self: &Arc<Self>, client: &Arc<ClientInner>, data: Option<Value>) {
+        let sid = data.and_then(|data| {
+            let packet: Result<ConnectPacket, _> = client.parser().decode_default(Some(&data));
+            packet.ok().map(|packet| packet.sid)
+        });
+        *self.id.write().unwrap() = sid;
+        let connect_tx = {
+            let mut state = self.state.lock().unwrap();
+            state.connected = true;
+            for packet in state.buffer.drain(..) {
+                client.send(packet).ok();
+            }
+            state.connect_tx.take()
+        };
+        if let Some(tx) = connect_tx {
+            tx.send(Ok(())).ok();
+        }
+        if let Some(handler) = &self.connect_handler {
+            handler(self.socket(client));
+        }
+    }
+
+    /// The engine.io connection was lost. The socket will be reconnected
+    /// with the client unless `closed` is true.
+    pub(crate) fn on_engine_close(
+        self: &Arc<Self>,
+        client: &Arc<ClientInner>,
+        reason: DisconnectReason,
+        closed: bool,
+    ) {
+        if closed {
+            self.close(client, reason);
+            return;
+        }
+        let was_connected = std::mem::take(&mut self.state.lock().unwrap().connected);
+        self.id.write().unwrap().take();
+        self.fail_acks();
+        if was_connected {
+            if let Some(handler) = &self.disconnect_handler {
+                handler(self.socket(client), reason);
+            }
+        }
+    }
+
+    /// Disconnect the socket for good.
+    pub(crate) fn close(self: &Arc<Self>, client: &Arc<ClientInner>, reason: DisconnectReason) {
+        let was_connected = {
+            let mut state = self.state.lock().unwrap();
+            state.active = false;
+            state.buffer.clear();
+            state.connect_tx.take();
+            std::mem::take(&mut state.connected)
+        };
+        client.remove_socket(self);
+        self.fail_acks();
+        if was_connected {
+            if let Some(handler) = &self.disconnect_handler {
+                handler(self.socket(client), reason);
+            }
+        }
+    }
+
+    fn fail_acks(&self) {
+        for (_, tx) in self.acks.lock().unwrap().drain() {
+            tx.send(Err(AckError::Socket(SocketError::Closed))).ok();
+        }
+    }
+
+    fn socket(self: &Arc<Self>, client: &Arc<ClientInner>) -> Socket {
+        Socket {
+            inner: self.clone(),
+            client: client.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for SocketInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SocketInner")
+            .field("ns", &self.ns)
+            .field("id", &self.id)
+            .finish()
+    }
+}
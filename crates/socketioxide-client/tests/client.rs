@@ -0,0 +1,325 @@
+mod fixture;
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use fixture::{recv, Server};
+use serde_json::{json, Value};
+use socketioxide::{
+    extract::{AckSender, Data, SocketRef},
+    handler::ConnectHandler,
+};
+use socketioxide_client::{errors::EngineError, ConnectError, DisconnectReason, TransportType};
+use tokio::sync::mpsc;
+
+const TRANSPORTS: [TransportType; 2] = [TransportType::Polling, TransportType::Websocket];
+
+fn echo_handler(s: SocketRef) {
+    s.on("echo", |s: SocketRef, Data::<Value>(data)| {
+        s.emit("echo", &data).ok();
+    });
+    s.on("echo_ack", |Data::<Value>(data), ack: AckSender| {
+        ack.send(&data).ok();
+    });
+    s.on("multi_ack", |ack: AckSender| {
+        ack.send(&(1, "foo")).ok();
+    });
+}
+
+#[tokio::test]
+pub async fn emit() {
+    let server = Server::new().await;
+    server.io.ns("/", echo_handler);
+
+    for transport in TRANSPORTS {
+        let client = server.client(transport).connect().await.unwrap();
+        assert_eq!(client.transport(), Some(transport));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let socket = client
+            .socket("/")
+            .on("echo", move |_, event| {
+                tx.send(event.data::<String>().unwrap()).unwrap();
+            })
+            .connect()
+            .await
+            .unwrap();
+
+        assert!(socket.connected());
+        assert!(socket.id().is_some());
+        socket.emit("echo", "hello").unwrap();
+        assert_eq!(recv(&mut rx).await, "hello");
+        client.close();
+    }
+}
+
+#[tokio::test]
+pub async fn emit_with_ack() {
+    let server = Server::new().await;
+    server.io.ns("/", echo_handler);
+
+    for transport in TRANSPORTS {
+        let client = server.client(transport).connect().await.unwrap();
+        let socket = client.socket("/").connect().await.unwrap();
+
+        let res: String = socket
+            .emit_with_ack("echo_ack", "hello")
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(res, "hello");
+
+        let res: (i32, String) = socket
+            .emit_with_ack("multi_ack", &())
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(res, (1, "foo".to_string()));
+        client.close();
+    }
+}
+
+#[tokio::test]
+pub async fn ack_from_client() {
+    let server = Server::new().await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    server.io.ns("/", move |s: SocketRef| {
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let res = s
+                .emit_with_ack::<_, String>("ping", "foo")
+                .unwrap()
+                .await
+                .unwrap();
+            tx.send(res).unwrap();
+        });
+    });
+
+    for transport in TRANSPORTS {
+        let client = server.client(transport).connect().await.unwrap();
+        client
+            .socket("/")
+            .on("ping", |_, event| {
+                assert!(event.needs_ack());
+                let data: String = event.data().unwrap();
+                event.ack(&format!("{data}-pong")).unwrap();
+            })
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(recv(&mut rx).await, "foo-pong");
+        client.close();
+    }
+}
+
+#[tokio::test]
+pub async fn binary() {
+    let server = Server::new().await;
+    server.io.ns("/", |s: SocketRef| {
+        s.on("bin", |s: SocketRef, Data::<(String, Bytes)>(data)| {
+            s.emit("bin", &data).ok();
+        });
+    });
+
+    for transport in TRANSPORTS {
+        let client = server.client(transport).connect().await.unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let socket = client
+            .socket("/")
+            .on("bin", move |_, event| {
+                tx.send(event.data::<(String, Bytes)>().unwrap()).unwrap();
+            })
+            .connect()
+            .await
+            .unwrap();
+
+        let data = ("foo".to_string(), Bytes::from_static(&[1, 2, 3, 4]));
+        socket.emit("bin", &data).unwrap();
+        assert_eq!(recv(&mut rx).await, data);
+        client.close();
+    }
+}
+
+#[tokio::test]
+pub async fn namespaces_and_auth() {
+    let server = Server::new().await;
+    server.io.ns("/", || {});
+    #[derive(Debug)]
+    struct AuthError;
+    impl std::fmt::Display for AuthError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "invalid token")
+        }
+    }
+    let middleware = |Data(auth): Data<Value>| async move {
+        if auth["token"] == "secret" {
+            Ok(())
+        } else {
+            Err(AuthError)
+        }
+    };
+    server.io.ns("/chat", echo_handler.with(middleware));
+
+    for transport in TRANSPORTS {
+        let client = server.client(transport).connect().await.unwrap();
+        let main = client.socket("/").connect().await.unwrap();
+        let chat = client
+            .socket("chat")
+            .auth(&json!({ "token": "secret" }))
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(main.ns(), "/");
+        assert_eq!(chat.ns(), "/chat");
+
+        let res: String = chat
+            .emit_with_ack("echo_ack", "foo")
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(res, "foo");
+
+        let err = client
+            .socket("/chat")
+            .auth(&json!({ "token": "wrong" }))
+            .connect()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ConnectError::Refused(msg) if msg == "invalid token"));
+        client.close();
+    }
+}
+
+#[tokio::test]
+pub async fn upgrade() {
+    let server = Server::new().await;
+    server.io.ns("/", echo_handler);
+
+    let client = socketioxide_client::Client::connect(server.url())
+        .await
+        .unwrap();
+    assert_eq!(client.transport(), Some(TransportType::Websocket));
+    let socket = client.socket("/").connect().await.unwrap();
+    let res: String = socket
+        .emit_with_ack("echo_ack", "foo")
+        .unwrap()
+        .await
+        .unwrap();
+    assert_eq!(res, "foo");
+    client.close();
+}
+
+#[tokio::test]
+pub async fn server_disconnect() {
+    let server = Server::new().await;
+    server.io.ns("/", |s: SocketRef| {
+        s.on("disconnect_me", |s: SocketRef| {
+            s.disconnect().ok();
+        });
+    });
+
+    for transport in TRANSPORTS {
+        let client = server.client(transport).connect().await.unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let socket = client
+            .socket("/")
+            .on_disconnect(move |_, reason| tx.send(reason).unwrap())
+            .connect()
+            .await
+            .unwrap();
+        socket.emit("disconnect_me", &()).unwrap();
+        assert_eq!(recv(&mut rx).await, DisconnectReason::ServerNSDisconnect);
+        assert!(!socket.connected());
+        client.close();
+    }
+}
+
+#[tokio::test]
+pub async fn client_disconnect() {
+    let server = Server::new().await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    server.io.ns("/", move |s: SocketRef| {
+        let tx = tx.clone();
+        s.on_disconnect(move |reason: socketioxide::socket::DisconnectReason| {
+            tx.send(reason).unwrap();
+        });
+    });
+
+    for transport in TRANSPORTS {
+        let client = server.client(transport).connect().await.unwrap();
+        let socket = client.socket("/").connect().await.unwrap();
+        socket.disconnect();
+        assert!(!socket.connected());
+        assert_eq!(
+            recv(&mut rx).await,
+            socketioxide::socket::DisconnectReason::ClientNSDisconnect
+        );
+        assert!(socket.emit("foo", "bar").is_err());
+        client.close();
+    }
+}
+
+#[tokio::test]
+pub async fn reconnection() {
+    let server = Server::new().await;
+    server.io.ns("/", echo_handler);
+
+    // Upgraded websocket connections are not owned by the http connection task,
+    // so only polling connections can be killed by the fixture.
+    let client = server
+        .client(TransportType::Polling)
+        .upgrade(false)
+        .reconnection_delay(Duration::from_millis(10), Duration::from_millis(50))
+        .connect()
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let tx1 = tx.clone();
+    let socket = client
+        .socket("/")
+        .on_connect(move |_| tx.send(None).unwrap())
+        .on_disconnect(move |_, reason| tx1.send(Some(reason)).unwrap())
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(recv(&mut rx).await, None);
+    let sid = socket.id().unwrap();
+
+    server.kill_connections();
+    assert!(recv(&mut rx).await.is_some());
+    assert_eq!(recv(&mut rx).await, None);
+    assert!(socket.connected());
+    assert_ne!(socket.id().unwrap(), sid);
+
+    let res: String = socket
+        .emit_with_ack("echo_ack", "foo")
+        .unwrap()
+        .await
+        .unwrap();
+    assert_eq!(res, "foo");
+    client.close();
+}
+
+#[tokio::test]
+pub async fn connect_refused() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let err = socketioxide_client::Client::builder(format!("http://{addr}"))
+        .connect()
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, ConnectError::Engine(EngineError::Io(_))),
+        "{err:?}"
+    );
+
+    let err = socketioxide_client::Client::builder("https://127.0.0.1")
+        .connect()
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, ConnectError::Engine(EngineError::UnsupportedScheme(_))),
+        "{err:?}"
+    );
+}
@@ -0,0 +1,79 @@
+#![allow(dead_code)]
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hyper::server::conn::http1;
+use hyper_util::rt::TokioIo;
+use socketioxide::{SocketIo, SocketIoBuilder};
+use socketioxide_client::{Client, ClientBuilder, TransportType};
+use tokio::{net::TcpListener, sync::mpsc, task::AbortHandle};
+
+pub const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A socketioxide server listening on a random local port.
+pub struct Server {
+    pub io: SocketIo,
+    pub addr: SocketAddr,
+    conns: Arc<Mutex<Vec<AbortHandle>>>,
+}
+
+impl Server {
+    pub async fn new() -> Self {
+        Self::with_builder(SocketIo::builder()).await
+    }
+
+    pub async fn with_builder(builder: SocketIoBuilder) -> Self {
+        let (svc, io) = builder.build_svc();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let conns: Arc<Mutex<Vec<AbortHandle>>> = Default::default();
+        let conns1 = conns.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let svc = svc.clone();
+                let handle = tokio::spawn(async move {
+                    http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), svc)
+                        .with_upgrades()
+                        .await
+                        .ok();
+                });
+                conns1.lock().unwrap().push(handle.abort_handle());
+            }
+        });
+        Self { io, addr, conns }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn client(&self, transport: TransportType) -> ClientBuilder {
+        let builder = Client::builder(self.url());
+        match transport {
+            TransportType::Polling => builder.transports([TransportType::Polling]),
+            TransportType::Websocket => builder.transports([TransportType::Websocket]),
+        }
+    }
+
+    /// Abort all the opened http connections.
+    /// Upgraded websocket connections are not affected.
+    pub fn kill_connections(&self) {
+        for conn in self.conns.lock().unwrap().drain(..) {
+            conn.abort();
+        }
+    }
+}
+
+/// Receive a value from the channel or panic after [`TIMEOUT`].
+pub async fn recv<T>(rx: &mut mpsc::UnboundedReceiver<T>) -> T {
+    tokio::time::timeout(TIMEOUT, rx.recv())
+        .await
+        .expect("timeout")
+        .expect("channel closed")
+}
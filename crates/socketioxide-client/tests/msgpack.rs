@@ -0,0 +1,54 @@
+mod fixture;
+
+use bytes::Bytes;
+use fixture::{recv, Server};
+use socketioxide::{
+    extract::{AckSender, Data, SocketRef},
+    SocketIo,
+};
+use socketioxide_client::{ParserConfig, TransportType};
+use tokio::sync::mpsc;
+
+#[tokio::test]
+pub async fn msgpack() {
+    let builder = SocketIo::builder().with_parser(socketioxide::ParserConfig::msgpack());
+    let server = Server::with_builder(builder).await;
+    server.io.ns("/", |s: SocketRef| {
+        s.on("echo", |s: SocketRef, Data::<(String, Bytes)>(data)| {
+            s.emit("echo", &data).ok();
+        });
+        s.on("echo_ack", |Data::<String>(data), ack: AckSender| {
+            ack.send(&data).ok();
+        });
+    });
+
+    for transport in [TransportType::Polling, TransportType::Websocket] {
+        let client = server
+            .client(transport)
+            .with_parser(ParserConfig::msgpack())
+            .connect()
+            .await
+            .unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let socket = client
+            .socket("/")
+            .on("echo", move |_, event| {
+                tx.send(event.data::<(String, Bytes)>().unwrap()).unwrap();
+            })
+            .connect()
+            .await
+            .unwrap();
+
+        let data = ("foo".to_string(), Bytes::from_static(&[1, 2, 3]));
+        socket.emit("echo", &data).unwrap();
+        assert_eq!(recv(&mut rx).await, data);
+
+        let res: String = socket
+            .emit_with_ack("echo_ack", "bar")
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(res, "bar");
+        client.close();
+    }
+}
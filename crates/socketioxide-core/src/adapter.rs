@@ -2,6 +2,8 @@
 //!
 //! It is used to implement communication between socket.io servers to share messages and state.
 //!
+//! The [`DynAdapter`] trait is an object safe version of the [`CoreAdapter`] trait.
+//!
 //! The [`CoreLocalAdapter`] provide a local implementation that will allow any implementors to apply local
 //! operations (`broadcast_with_ack`, `broadcast`, `rooms`, etc...).
 use std::{
@@ -11,17 +13,18 @@ use std::{
     future::{self, Future},
     hash::Hash,
     slice,
-    sync::{Arc, RwLock},
-    time::Duration,
+    sync::{Arc, OnceLock, RwLock},
+    time::{Duration, Instant},
 };
 
+pub use engineioxide::handoff::{HandoffRequest, HandoffResponse};
 use engineioxide::{sid::Sid, Str};
-use futures_core::{FusedStream, Stream};
+use futures_core::{future::BoxFuture, stream::BoxStream, FusedStream, Stream};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use smallvec::SmallVec;
 
 use crate::{
-    errors::{AdapterError, BroadcastError, SocketError},
+    errors::{AdapterError, BroadcastError, PartialResponseError, SocketError},
     packet::Packet,
     parser::Parse,
     Uid, Value,
@@ -30,6 +33,29 @@ use crate::{
 /// A room identifier
 pub type Room = Cow<'static, str>;
 
+/// A creation or deletion of a local room, see [`CoreLocalAdapter::watch_rooms`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoomEvent {
+    /// The room is created.
+    Create(Room),
+    /// The room is deleted.
+    Delete(Room),
+}
+
+/// A watcher of the local rooms, set with [`CoreLocalAdapter::watch_rooms`].
+type RoomWatcher = Box<dyn Fn(RoomEvent) + Send + Sync + 'static>;
+
+/// The expiry of a room, set with [`CoreLocalAdapter::set_expiry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomExpiry {
+    /// The room expires once the duration elapsed, its sockets leaving it.
+    /// It is still deleted as soon as its last socket leaves it.
+    Ttl(Duration),
+    /// The room expires once it stayed empty for the duration, a grace period during which
+    /// it is kept instead of being deleted as soon as its last socket leaves it.
+    WhenEmpty(Duration),
+}
+
 /// Flags that can be used to modify the behavior of the broadcast methods.
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum BroadcastFlags {
@@ -37,6 +63,9 @@ pub enum BroadcastFlags {
     Local = 0x01,
     /// Broadcast to all clients except the sender
     Broadcast = 0x02,
+    /// Drop the packet for the sockets that cannot receive it right away
+    /// (full internal buffer or transport upgrade in progress)
+    Volatile = 0x04,
 }
 
 /// Options that can be used to modify the behavior of the broadcast methods.
@@ -87,6 +116,32 @@ impl BroadcastOptions {
     }
 }
 
+/// Options for the requests that aggregate the responses of all the servers,
+/// such as [`CoreAdapter::rooms`] or [`CoreAdapter::sockets_count`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RequestOptions {
+    /// The time to wait for the responses of the other servers.
+    /// If `None`, the default request timeout of the adapter is used.
+    pub timeout: Option<Duration>,
+    /// If `true`, the responses received before the timeout are returned even if some servers
+    /// didn't respond. Otherwise a [`PartialResponseError`] is returned.
+    pub allow_partial: bool,
+}
+impl RequestOptions {
+    /// Checks that all the expected servers responded, unless partial results are allowed.
+    pub fn check_responses(
+        &self,
+        expected: usize,
+        received: usize,
+    ) -> Result<(), PartialResponseError> {
+        if received < expected && !self.allow_partial {
+            Err(PartialResponseError { expected, received })
+        } else {
+            Ok(())
+        }
+    }
+}
+
 /// A trait for types that can be used as a room parameter.
 ///
 /// [`String`], [`Vec<String>`], [`Vec<&str>`], [`&'static str`](str) and const arrays are implemented by default.
@@ -191,6 +246,27 @@ pub trait SocketEmitter: Send + Sync + 'static {
     fn get_remote_sockets(&self, sids: BroadcastIter<'_>) -> Vec<RemoteSocketData>;
     /// Send data to the list of socket ids.
     fn send_many(&self, sids: BroadcastIter<'_>, data: Value) -> Result<(), Vec<SocketError>>;
+    /// Send data to the list of socket ids, silently dropping it for the sockets
+    /// that cannot receive it right away.
+    ///
+    /// The default implementation ignores the [`SocketError::InternalChannelFull`] errors of [`Self::send_many`].
+    fn send_many_volatile(
+        &self,
+        sids: BroadcastIter<'_>,
+        data: Value,
+    ) -> Result<(), Vec<SocketError>> {
+        self.send_many(sids, data).or_else(|errs| {
+            let errs: Vec<_> = errs
+                .into_iter()
+                .filter(|e| !matches!(e, SocketError::InternalChannelFull))
+                .collect();
+            if errs.is_empty() {
+                Ok(())
+            } else {
+                Err(errs)
+            }
+        })
+    }
     /// Send data to the list of socket ids and get a stream of acks and the number of expected acks.
     fn send_many_with_ack(
         &self,
@@ -208,6 +284,27 @@ pub trait SocketEmitter: Send + Sync + 'static {
     fn parser(&self) -> impl Parse;
     /// Get the unique server id.
     fn server_id(&self) -> Uid;
+    /// Handle a polling request forwarded by another server for the engine.io session `sid`.
+    /// Returns `None` if the session is not open on this server.
+    fn handoff(&self, sid: Sid, req: HandoffRequest)
+        -> BoxFuture<'static, Option<HandoffResponse>>;
+    /// Called when a local socket joins a room it was not in.
+    fn on_join_room(&self, _sid: Sid, _room: &Room) {}
+    /// Called when a local socket leaves a room it was in.
+    fn on_leave_room(&self, _sid: Sid, _room: &Room) {}
+    /// Called when a room is created because a first local socket joins it,
+    /// before the corresponding [`SocketEmitter::on_join_room`] call.
+    fn on_create_room(&self, _room: &Room) {}
+    /// Called when a room is deleted because its last local socket leaves it,
+    /// after the corresponding [`SocketEmitter::on_leave_room`] call.
+    fn on_delete_room(&self, _room: &Room) {}
+    /// Called when a room with a [`RoomExpiry`] may expire after `delay`.
+    /// [`CoreLocalAdapter::expire_room`] should then be called with the room.
+    ///
+    /// The default implementation does nothing, so the rooms never expire.
+    fn schedule_room_expiry(&self, _room: &Room, _delay: Duration) {}
+    /// Called when a room expires, after the [`SocketEmitter::on_delete_room`] call.
+    fn on_expire_room(&self, _room: &Room) {}
 }
 
 /// For static namespaces, the init response will be managed by the user.
@@ -322,14 +419,26 @@ pub trait CoreAdapter<E: SocketEmitter>: Sized + Send + Sync + 'static {
         )
     }
 
-    /// Fetches rooms that match the [`BroadcastOptions`]
+    /// Fetches rooms that match the [`BroadcastOptions`] on all the servers.
     fn rooms(
         &self,
         opts: BroadcastOptions,
+        req_opts: RequestOptions,
     ) -> impl Future<Output = Result<Vec<Room>, Self::Error>> + Send {
+        let _ = req_opts;
         future::ready(Ok(self.get_local().rooms(opts).into_iter().collect()))
     }
 
+    /// Counts the sockets that match the [`BroadcastOptions`] on all the servers.
+    fn sockets_count(
+        &self,
+        opts: BroadcastOptions,
+        req_opts: RequestOptions,
+    ) -> impl Future<Output = Result<usize, Self::Error>> + Send {
+        let _ = req_opts;
+        future::ready(Ok(self.get_local().sockets(opts).len()))
+    }
+
     /// Fetches remote sockets that match the [`BroadcastOptions`].
     fn fetch_sockets(
         &self,
@@ -338,6 +447,26 @@ pub trait CoreAdapter<E: SocketEmitter>: Sized + Send + Sync + 'static {
         future::ready(Ok(self.get_local().fetch_sockets(opts)))
     }
 
+    /// Forwards a polling request to the server owning the engine.io session `sid`,
+    /// so that http long-polling works without sticky sessions.
+    ///
+    /// Returns `None` if no server owns the session. By default there is no other server to forward to.
+    fn forward_polling(
+        &self,
+        sid: Sid,
+        req: HandoffRequest,
+    ) -> impl Future<Output = Result<Option<HandoffResponse>, Self::Error>> + Send {
+        let _ = (sid, req);
+        future::ready(Ok(None))
+    }
+
+    /// Returns the servers of the cluster known to be alive, including the current one.
+    ///
+    /// By default only the current server is returned.
+    fn nodes(&self) -> impl Future<Output = Result<Vec<NodeInfo>, Self::Error>> + Send {
+        future::ready(Ok(vec![self.get_local().node()]))
+    }
+
     /// Returns the local adapter. Used to enable default behaviors.
     fn get_local(&self) -> &CoreLocalAdapter<E>;
 
@@ -347,10 +476,216 @@ pub trait CoreAdapter<E: SocketEmitter>: Sized + Send + Sync + 'static {
     // fn restore_session(&self, sid: i64) -> Session;
 }
 
+/// An object safe version of the [`CoreAdapter`] trait, implemented for all the adapters.
+///
+/// It allows to use adapters of different types behind a pointer, e.g. `Arc<dyn DynAdapter<E>>`.
+/// The futures and the ack streams are boxed, and the errors of the adapter are converted to [`AdapterError`].
+///
+/// Its methods have the same names as the ones of [`CoreAdapter`],
+/// so only one of the two traits should be imported in a module.
+pub trait DynAdapter<E: SocketEmitter>: Send + Sync + 'static {
+    /// Closes the adapter.
+    fn close(&self) -> BoxFuture<'_, Result<(), AdapterError>>;
+
+    /// Returns the number of servers.
+    fn server_count(&self) -> BoxFuture<'_, Result<u16, AdapterError>>;
+
+    /// Broadcasts the packet to the sockets that match the [`BroadcastOptions`].
+    ///
+    /// The sockets the packet could not be delivered to are reported in the [`BroadcastError`].
+    fn broadcast(
+        &self,
+        packet: Packet,
+        opts: BroadcastOptions,
+    ) -> BoxFuture<'_, Result<(), BroadcastError>>;
+
+    /// Broadcasts the packet to the sockets that match the [`BroadcastOptions`]
+    /// and return a stream of ack responses.
+    fn broadcast_with_ack(
+        &self,
+        packet: Packet,
+        opts: BroadcastOptions,
+        timeout: Option<Duration>,
+    ) -> BoxFuture<'_, Result<BoxStream<'static, AckStreamItem<E::AckError>>, AdapterError>>;
+
+    /// Adds the sockets that match the [`BroadcastOptions`] to the rooms.
+    fn add_sockets(
+        &self,
+        opts: BroadcastOptions,
+        rooms: Vec<Room>,
+    ) -> BoxFuture<'_, Result<(), AdapterError>>;
+
+    /// Removes the sockets that match the [`BroadcastOptions`] from the rooms.
+    fn del_sockets(
+        &self,
+        opts: BroadcastOptions,
+        rooms: Vec<Room>,
+    ) -> BoxFuture<'_, Result<(), AdapterError>>;
+
+    /// Disconnects the sockets that match the [`BroadcastOptions`].
+    fn disconnect_socket(
+        &self,
+        opts: BroadcastOptions,
+    ) -> BoxFuture<'_, Result<(), BroadcastError>>;
+
+    /// Fetches rooms that match the [`BroadcastOptions`] on all the servers.
+    fn rooms(
+        &self,
+        opts: BroadcastOptions,
+        req_opts: RequestOptions,
+    ) -> BoxFuture<'_, Result<Vec<Room>, AdapterError>>;
+
+    /// Counts the sockets that match the [`BroadcastOptions`] on all the servers.
+    fn sockets_count(
+        &self,
+        opts: BroadcastOptions,
+        req_opts: RequestOptions,
+    ) -> BoxFuture<'_, Result<usize, AdapterError>>;
+
+    /// Fetches remote sockets that match the [`BroadcastOptions`].
+    fn fetch_sockets(
+        &self,
+        opts: BroadcastOptions,
+    ) -> BoxFuture<'_, Result<Vec<RemoteSocketData>, AdapterError>>;
+
+    /// Forwards a polling request to the server owning the engine.io session `sid`.
+    fn forward_polling(
+        &self,
+        sid: Sid,
+        req: HandoffRequest,
+    ) -> BoxFuture<'_, Result<Option<HandoffResponse>, AdapterError>>;
+
+    /// Returns the servers of the cluster known to be alive, including the current one.
+    fn nodes(&self) -> BoxFuture<'_, Result<Vec<NodeInfo>, AdapterError>>;
+
+    /// Returns the local adapter.
+    fn get_local(&self) -> &CoreLocalAdapter<E>;
+}
+
+impl<E: SocketEmitter, A: CoreAdapter<E>> DynAdapter<E> for A {
+    fn close(&self) -> BoxFuture<'_, Result<(), AdapterError>> {
+        Box::pin(async move { CoreAdapter::close(self).await.map_err(Into::into) })
+    }
+
+    fn server_count(&self) -> BoxFuture<'_, Result<u16, AdapterError>> {
+        Box::pin(async move { CoreAdapter::server_count(self).await.map_err(Into::into) })
+    }
+
+    fn broadcast(
+        &self,
+        packet: Packet,
+        opts: BroadcastOptions,
+    ) -> BoxFuture<'_, Result<(), BroadcastError>> {
+        Box::pin(CoreAdapter::broadcast(self, packet, opts))
+    }
+
+    fn broadcast_with_ack(
+        &self,
+        packet: Packet,
+        opts: BroadcastOptions,
+        timeout: Option<Duration>,
+    ) -> BoxFuture<'_, Result<BoxStream<'static, AckStreamItem<E::AckError>>, AdapterError>> {
+        Box::pin(async move {
+            match CoreAdapter::broadcast_with_ack(self, packet, opts, timeout).await {
+                Ok(stream) => Ok(Box::pin(stream) as BoxStream<'static, _>),
+                Err(err) => Err(err.into()),
+            }
+        })
+    }
+
+    fn add_sockets(
+        &self,
+        opts: BroadcastOptions,
+        rooms: Vec<Room>,
+    ) -> BoxFuture<'_, Result<(), AdapterError>> {
+        Box::pin(async move {
+            CoreAdapter::add_sockets(self, opts, rooms)
+                .await
+                .map_err(Into::into)
+        })
+    }
+
+    fn del_sockets(
+        &self,
+        opts: BroadcastOptions,
+        rooms: Vec<Room>,
+    ) -> BoxFuture<'_, Result<(), AdapterError>> {
+        Box::pin(async move {
+            CoreAdapter::del_sockets(self, opts, rooms)
+                .await
+                .map_err(Into::into)
+        })
+    }
+
+    fn disconnect_socket(
+        &self,
+        opts: BroadcastOptions,
+    ) -> BoxFuture<'_, Result<(), BroadcastError>> {
+        Box::pin(CoreAdapter::disconnect_socket(self, opts))
+    }
+
+    fn rooms(
+        &self,
+        opts: BroadcastOptions,
+        req_opts: RequestOptions,
+    ) -> BoxFuture<'_, Result<Vec<Room>, AdapterError>> {
+        Box::pin(async move {
+            CoreAdapter::rooms(self, opts, req_opts)
+                .await
+                .map_err(Into::into)
+        })
+    }
+
+    fn sockets_count(
+        &self,
+        opts: BroadcastOptions,
+        req_opts: RequestOptions,
+    ) -> BoxFuture<'_, Result<usize, AdapterError>> {
+        Box::pin(async move {
+            CoreAdapter::sockets_count(self, opts, req_opts)
+                .await
+                .map_err(Into::into)
+        })
+    }
+
+    fn fetch_sockets(
+        &self,
+        opts: BroadcastOptions,
+    ) -> BoxFuture<'_, Result<Vec<RemoteSocketData>, AdapterError>> {
+        Box::pin(async move {
+            CoreAdapter::fetch_sockets(self, opts)
+                .await
+                .map_err(Into::into)
+        })
+    }
+
+    fn forward_polling(
+        &self,
+        sid: Sid,
+        req: HandoffRequest,
+    ) -> BoxFuture<'_, Result<Option<HandoffResponse>, AdapterError>> {
+        Box::pin(async move {
+            CoreAdapter::forward_polling(self, sid, req)
+                .await
+                .map_err(Into::into)
+        })
+    }
+
+    fn nodes(&self) -> BoxFuture<'_, Result<Vec<NodeInfo>, AdapterError>> {
+        Box::pin(async move { CoreAdapter::nodes(self).await.map_err(Into::into) })
+    }
+
+    fn get_local(&self) -> &CoreLocalAdapter<E> {
+        CoreAdapter::get_local(self)
+    }
+}
+
 /// The default adapter. Store the state in memory.
 pub struct CoreLocalAdapter<E> {
     rooms: RwLock<HashMap<Room, HashSet<Sid>>>,
     sockets: RwLock<HashMap<Sid, HashSet<Room>>>,
+    expiries: RwLock<HashMap<Room, ExpiryState>>,
+    room_watcher: OnceLock<RoomWatcher>,
     emitter: E,
 }
 
@@ -360,6 +695,8 @@ impl<E: SocketEmitter> CoreLocalAdapter<E> {
         Self {
             rooms: RwLock::new(HashMap::new()),
             sockets: RwLock::new(HashMap::new()),
+            expiries: RwLock::new(HashMap::new()),
+            room_watcher: OnceLock::new(),
             emitter,
         }
     }
@@ -369,37 +706,147 @@ impl<E: SocketEmitter> CoreLocalAdapter<E> {
         let mut rooms = self.rooms.write().unwrap();
         rooms.clear();
         rooms.shrink_to_fit();
+        self.expiries.write().unwrap().clear();
     }
 
     /// Adds the socket to all the rooms.
     pub fn add_all(&self, sid: Sid, rooms: impl RoomParam) {
-        let mut rooms_map = self.rooms.write().unwrap();
-        let mut socket_map = self.sockets.write().unwrap();
-        for room in rooms.into_room_iter() {
-            rooms_map.entry(room.clone()).or_default().insert(sid);
-            socket_map.entry(sid).or_default().insert(room);
+        let mut changes = Vec::new();
+        {
+            let mut rooms_map = self.rooms.write().unwrap();
+            let mut socket_map = self.sockets.write().unwrap();
+            let mut expiries = self.expiries.write().unwrap();
+            for room in rooms.into_room_iter() {
+                join_room(&mut rooms_map, &mut expiries, sid, &room, &mut changes);
+                socket_map.entry(sid).or_default().insert(room);
+            }
         }
+        self.notify_room_changes(changes);
     }
 
     /// Removes the socket from the rooms.
     pub fn del(&self, sid: Sid, rooms: impl RoomParam) {
-        let mut rooms_map = self.rooms.write().unwrap();
-        let mut socket_map = self.sockets.write().unwrap();
-        for room in rooms.into_room_iter() {
-            remove_and_clean_entry(rooms_map.entry(room.clone()), &sid, || {
+        let mut changes = Vec::new();
+        {
+            let mut rooms_map = self.rooms.write().unwrap();
+            let mut socket_map = self.sockets.write().unwrap();
+            let mut expiries = self.expiries.write().unwrap();
+            for room in rooms.into_room_iter() {
                 socket_map.entry(sid).and_modify(|r| {
                     r.remove(&room);
                 });
-            });
+                leave_room(&mut rooms_map, &mut expiries, sid, room, &mut changes);
+            }
         }
+        self.notify_room_changes(changes);
     }
 
     /// Removes the socket from all the rooms.
     pub fn del_all(&self, sid: Sid) {
-        let mut rooms_map = self.rooms.write().unwrap();
-        if let Some(rooms) = self.sockets.write().unwrap().remove(&sid) {
-            for room in rooms {
-                remove_and_clean_entry(rooms_map.entry(room.clone()), &sid, || ());
+        let mut changes = Vec::new();
+        {
+            let mut rooms_map = self.rooms.write().unwrap();
+            if let Some(rooms) = self.sockets.write().unwrap().remove(&sid) {
+                let mut expiries = self.expiries.write().unwrap();
+                for room in rooms {
+                    leave_room(&mut rooms_map, &mut expiries, sid, room, &mut changes);
+                }
+            }
+        }
+        self.notify_room_changes(changes);
+    }
+
+    /// Sets the expiry of the rooms, creating them if they don't exist.
+    ///
+    /// The rooms expire when [`CoreLocalAdapter::expire_room`] is called once the delay
+    /// requested with [`SocketEmitter::schedule_room_expiry`] elapsed.
+    pub fn set_expiry(&self, rooms: impl RoomParam, expiry: RoomExpiry) {
+        let mut changes = Vec::new();
+        {
+            let mut rooms_map = self.rooms.write().unwrap();
+            let mut expiries = self.expiries.write().unwrap();
+            for room in rooms.into_room_iter() {
+                let sockets = match rooms_map.entry(room.clone()) {
+                    hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                    hash_map::Entry::Vacant(entry) => {
+                        changes.push(RoomChange::Create(room.clone()));
+                        entry.insert(HashSet::new())
+                    }
+                };
+                let delay = match expiry {
+                    RoomExpiry::Ttl(ttl) => Some(ttl),
+                    RoomExpiry::WhenEmpty(grace) => sockets.is_empty().then_some(grace),
+                };
+                let deadline = delay.map(|delay| Instant::now() + delay);
+                expiries.insert(room.clone(), ExpiryState { expiry, deadline });
+                if let Some(delay) = delay {
+                    changes.push(RoomChange::ScheduleExpiry(room, delay));
+                }
+            }
+        }
+        self.notify_room_changes(changes);
+    }
+
+    /// Expires the room if its expiry is due: its sockets leave it and it is deleted.
+    pub fn expire_room(&self, room: &Room) {
+        let mut changes = Vec::new();
+        {
+            let mut rooms_map = self.rooms.write().unwrap();
+            let mut socket_map = self.sockets.write().unwrap();
+            let mut expiries = self.expiries.write().unwrap();
+            let now = Instant::now();
+            let due = expiries
+                .get(room)
+                .and_then(|state| state.deadline)
+                .is_some_and(|deadline| deadline <= now);
+            if !due {
+                return;
+            }
+            expiries.remove(room);
+            if let Some(sids) = rooms_map.remove(room) {
+                for sid in sids {
+                    remove_and_clean_entry(socket_map.entry(sid), room, || ());
+                    changes.push(RoomChange::Leave(sid, room.clone()));
+                }
+                changes.push(RoomChange::Delete(room.clone()));
+            }
+            changes.push(RoomChange::Expire(room.clone()));
+        }
+        self.notify_room_changes(changes);
+    }
+
+    /// Sets a watcher called when a local room is created or deleted, after the emitter is notified.
+    /// It can be used by an adapter to subscribe to a channel for each of the local rooms.
+    ///
+    /// Only one watcher can be set, the next ones are ignored.
+    pub fn watch_rooms(&self, watcher: impl Fn(RoomEvent) + Send + Sync + 'static) {
+        self.room_watcher.set(Box::new(watcher)).ok();
+    }
+
+    /// Notify the emitter of the room changes, once the rooms are unlocked
+    /// so that the emitter can safely call the adapter.
+    fn notify_room_changes(&self, changes: Vec<RoomChange>) {
+        let watch = |event| {
+            if let Some(watcher) = self.room_watcher.get() {
+                watcher(event);
+            }
+        };
+        for change in changes {
+            match change {
+                RoomChange::Create(room) => {
+                    self.emitter.on_create_room(&room);
+                    watch(RoomEvent::Create(room));
+                }
+                RoomChange::Join(sid, room) => self.emitter.on_join_room(sid, &room),
+                RoomChange::Leave(sid, room) => self.emitter.on_leave_room(sid, &room),
+                RoomChange::Delete(room) => {
+                    self.emitter.on_delete_room(&room);
+                    watch(RoomEvent::Delete(room));
+                }
+                RoomChange::ScheduleExpiry(room, delay) => {
+                    self.emitter.schedule_room_expiry(&room, delay)
+                }
+                RoomChange::Expire(room) => self.emitter.on_expire_room(&room),
             }
         }
     }
@@ -418,7 +865,11 @@ impl<E: SocketEmitter> CoreLocalAdapter<E> {
         }
 
         let data = self.emitter.parser().encode(packet);
-        self.emitter.send_many(sids, data)
+        if opts.has_flag(BroadcastFlags::Volatile) {
+            self.emitter.send_many_volatile(sids, data)
+        } else {
+            self.emitter.send_many(sids, data)
+        }
     }
 
     /// Broadcasts the packet to the sockets that match the [`BroadcastOptions`] and return a stream of ack responses.
@@ -441,6 +892,14 @@ impl<E: SocketEmitter> CoreLocalAdapter<E> {
             .collect()
     }
 
+    /// Returns the [`NodeInfo`] of the current server.
+    pub fn node(&self) -> NodeInfo {
+        NodeInfo {
+            uid: self.server_id(),
+            sockets: self.emitter.get_all_sids(|_| true).len() as u32,
+        }
+    }
+
     /// Returns the sockets ids that match the [`BroadcastOptions`].
     pub fn fetch_sockets(&self, opts: BroadcastOptions) -> Vec<RemoteSocketData> {
         let rooms = self.rooms.read().unwrap();
@@ -461,36 +920,46 @@ impl<E: SocketEmitter> CoreLocalAdapter<E> {
     /// Adds the sockets that match the [`BroadcastOptions`] to the rooms.
     pub fn add_sockets(&self, opts: BroadcastOptions, rooms: impl RoomParam) {
         let rooms: Vec<Room> = rooms.into_room_iter().collect();
-        let mut room_map = self.rooms.write().unwrap();
-        let mut socket_map = self.sockets.write().unwrap();
-        // Here we have to collect sids, because we are going to modify the rooms map.
-        let sids = self.apply_opts(&opts, &room_map).collect::<Vec<_>>();
-        for sid in &sids {
-            let entry = socket_map.entry(*sid).or_default();
-            for room in &rooms {
-                entry.insert(room.clone());
-            }
-        }
-        for room in rooms {
-            let entry = room_map.entry(room).or_default();
+        let mut changes = Vec::new();
+        {
+            let mut room_map = self.rooms.write().unwrap();
+            let mut socket_map = self.sockets.write().unwrap();
+            // Here we have to collect sids, because we are going to modify the rooms map.
+            let sids = self.apply_opts(&opts, &room_map).collect::<Vec<_>>();
             for sid in &sids {
-                entry.insert(*sid);
+                let entry = socket_map.entry(*sid).or_default();
+                for room in &rooms {
+                    entry.insert(room.clone());
+                }
+            }
+            let mut expiries = self.expiries.write().unwrap();
+            for room in &rooms {
+                for sid in &sids {
+                    join_room(&mut room_map, &mut expiries, *sid, room, &mut changes);
+                }
             }
         }
+        self.notify_room_changes(changes);
     }
 
     /// Removes the sockets that match the [`BroadcastOptions`] from the rooms.
     pub fn del_sockets(&self, opts: BroadcastOptions, rooms: impl RoomParam) {
         let rooms: Vec<Room> = rooms.into_room_iter().collect();
-        let mut rooms_map = self.rooms.write().unwrap();
-        let mut socket_map = self.sockets.write().unwrap();
-        let sids = self.apply_opts(&opts, &rooms_map).collect::<Vec<_>>();
-        for room in rooms {
-            for sid in &sids {
-                remove_and_clean_entry(socket_map.entry(*sid), &room, || ());
-                remove_and_clean_entry(rooms_map.entry(room.clone()), sid, || ());
+        let mut changes = Vec::new();
+        {
+            let mut rooms_map = self.rooms.write().unwrap();
+            let mut socket_map = self.sockets.write().unwrap();
+            let sids = self.apply_opts(&opts, &rooms_map).collect::<Vec<_>>();
+            let mut expiries = self.expiries.write().unwrap();
+            for room in rooms {
+                for sid in &sids {
+                    remove_and_clean_entry(socket_map.entry(*sid), &room, || ());
+                    let room = room.clone();
+                    leave_room(&mut rooms_map, &mut expiries, *sid, room, &mut changes);
+                }
             }
         }
+        self.notify_room_changes(changes);
     }
 
     /// Disconnects the sockets that match the [`BroadcastOptions`].
@@ -525,6 +994,16 @@ impl<E: SocketEmitter> CoreLocalAdapter<E> {
     pub fn server_id(&self) -> Uid {
         self.emitter.server_id()
     }
+
+    /// Handle a polling request forwarded by another server for the engine.io session `sid`.
+    /// Returns `None` if the session is not open on this server.
+    pub fn handoff(
+        &self,
+        sid: Sid,
+        req: HandoffRequest,
+    ) -> BoxFuture<'static, Option<HandoffResponse>> {
+        self.emitter.handoff(sid, req)
+    }
 }
 
 /// The default broadcast iterator.
@@ -554,7 +1033,8 @@ impl Iterator for BroadcastRooms<'_> {
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             match self.flatten_iter.as_mut().and_then(Iterator::next) {
-                Some(sid) if !self.except.contains(sid) => return Some(*sid),
+                // A socket in several of the rooms must only be yielded once.
+                Some(sid) if self.except.insert(*sid) => return Some(*sid),
                 Some(_) => continue,
                 None => self.flatten_iter = None,
             }
@@ -577,8 +1057,8 @@ impl<E: SocketEmitter> CoreLocalAdapter<E> {
         let mut except = get_except_sids(&opts.except, rooms);
         // In case of broadcast flag + if the sender is set,
         // we should not broadcast to it.
-        if is_broadcast && opts.sid.is_some() {
-            except.insert(opts.sid.unwrap());
+        if let Some(sid) = opts.sid.filter(|_| is_broadcast) {
+            except.insert(sid);
         }
 
         if !opts.rooms.is_empty() {
@@ -606,25 +1086,101 @@ fn get_except_sids(except: &[Room], rooms: &HashMap<Room, HashSet<Sid>>) -> Hash
     except_sids
 }
 
+/// A change of the rooms of the local adapter, notified to the [`SocketEmitter`].
+enum RoomChange {
+    Create(Room),
+    Join(Sid, Room),
+    Leave(Sid, Room),
+    Delete(Room),
+    ScheduleExpiry(Room, Duration),
+    Expire(Room),
+}
+
+/// The expiry of a room and when it is due.
+struct ExpiryState {
+    expiry: RoomExpiry,
+    /// `None` while a [`RoomExpiry::WhenEmpty`] room is not empty.
+    deadline: Option<Instant>,
+}
+
+/// Add the socket to the room, creating it if needed, and record the changes.
+fn join_room(
+    rooms_map: &mut HashMap<Room, HashSet<Sid>>,
+    expiries: &mut HashMap<Room, ExpiryState>,
+    sid: Sid,
+    room: &Room,
+    changes: &mut Vec<RoomChange>,
+) {
+    let entry = rooms_map.entry(room.clone());
+    if matches!(entry, hash_map::Entry::Vacant(_)) {
+        changes.push(RoomChange::Create(room.clone()));
+    }
+    if entry.or_default().insert(sid) {
+        changes.push(RoomChange::Join(sid, room.clone()));
+        // The grace period of an empty room ends once a socket joins it
+        if let Some(state) = expiries.get_mut(room) {
+            if matches!(state.expiry, RoomExpiry::WhenEmpty(_)) {
+                state.deadline = None;
+            }
+        }
+    }
+}
+
+/// Remove the socket from the room, deleting it if it is empty, and record the changes.
+/// A [`RoomExpiry::WhenEmpty`] room is kept empty until its grace period elapsed.
+fn leave_room(
+    rooms_map: &mut HashMap<Room, HashSet<Sid>>,
+    expiries: &mut HashMap<Room, ExpiryState>,
+    sid: Sid,
+    room: Room,
+    changes: &mut Vec<RoomChange>,
+) {
+    if !remove_and_clean_entry(rooms_map.entry(room.clone()), &sid, || ()) {
+        return;
+    }
+    changes.push(RoomChange::Leave(sid, room.clone()));
+    if rooms_map.contains_key(&room) {
+        return;
+    }
+    match expiries.get_mut(&room) {
+        Some(ExpiryState {
+            expiry: RoomExpiry::WhenEmpty(grace),
+            deadline,
+        }) => {
+            let grace = *grace;
+            *deadline = Some(Instant::now() + grace);
+            rooms_map.insert(room.clone(), HashSet::new());
+            changes.push(RoomChange::ScheduleExpiry(room, grace));
+        }
+        _ => {
+            expiries.remove(&room);
+            changes.push(RoomChange::Delete(room));
+        }
+    }
+}
+
 /// Remove a field from a HashSet value and remove it if empty.
 /// Call `cleanup` fn if the entry exists
 #[inline]
+/// Remove an element from the set of the entry and remove the entry if the set is empty.
+/// Returns `true` if the element was in the set.
 fn remove_and_clean_entry<K, T: Hash + Eq>(
     entry: hash_map::Entry<'_, K, HashSet<T>>,
     el: &T,
     cleanup: impl FnOnce(),
-) {
+) -> bool {
     //TODO: use hashmap raw entry when stabilized to avoid entry clone.
     // https://github.com/rust-lang/rust/issues/56167
     match entry {
         hash_map::Entry::Occupied(mut entry) => {
-            entry.get_mut().remove(el);
+            let removed = entry.get_mut().remove(el);
             if entry.get().is_empty() {
                 entry.remove_entry();
             }
             cleanup();
+            removed
         }
-        hash_map::Entry::Vacant(_) => (),
+        hash_map::Entry::Vacant(_) => false,
     }
 }
 
@@ -675,8 +1231,18 @@ impl Iterator for InnerBroadcastIter<'_> {
     }
 }
 
+/// A server of the cluster, returned by [`CoreAdapter::nodes`].
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub struct NodeInfo {
+    /// The id of the server.
+    pub uid: Uid,
+    /// The number of sockets connected to the namespace on this server.
+    pub sockets: u32,
+}
+
 /// Represent the data of a remote socket.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Default, Clone)]
+#[non_exhaustive]
 pub struct RemoteSocketData {
     /// The id of the remote socket.
     pub id: Sid,
@@ -684,6 +1250,30 @@ pub struct RemoteSocketData {
     pub server_id: Uid,
     /// The namespace this socket is connected to.
     pub ns: Str,
+    /// The presence metadata of the socket, encoded with the parser of its server.
+    ///
+    /// It is omitted from the payload when it is not set so that the nodes
+    /// that do not know this field can still decode it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence: Option<Value>,
+}
+
+impl RemoteSocketData {
+    /// Create the data of a remote socket, without presence metadata.
+    pub fn new(id: Sid, server_id: Uid, ns: Str) -> Self {
+        Self {
+            id,
+            server_id,
+            ns,
+            presence: None,
+        }
+    }
+
+    /// Set the presence metadata of the socket.
+    pub fn with_presence(mut self, presence: Option<Value>) -> Self {
+        self.presence = presence;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -701,6 +1291,13 @@ mod test {
     struct StubSockets {
         sockets: HashSet<Sid>,
         path: Str,
+        /// The room membership changes, `true` for a join and `false` for a leave.
+        room_changes: std::sync::Mutex<Vec<(bool, Sid, Room)>>,
+        /// The created and deleted rooms, `true` for a creation and `false` for a deletion.
+        room_lifecycle: std::sync::Mutex<Vec<(bool, Room)>>,
+        /// The scheduled room expiries and the expired rooms.
+        scheduled_expiries: std::sync::Mutex<Vec<(Room, Duration)>>,
+        expired_rooms: std::sync::Mutex<Vec<Room>>,
     }
     impl StubSockets {
         fn new(sockets: &[Sid]) -> Self {
@@ -708,6 +1305,10 @@ mod test {
             Self {
                 sockets,
                 path: Str::from("/"),
+                room_changes: Default::default(),
+                room_lifecycle: Default::default(),
+                scheduled_expiries: Default::default(),
+                expired_rooms: Default::default(),
             }
         }
     }
@@ -745,12 +1346,8 @@ mod test {
         }
 
         fn get_remote_sockets(&self, sids: BroadcastIter<'_>) -> Vec<RemoteSocketData> {
-            sids.map(|id| RemoteSocketData {
-                id,
-                server_id: Uid::ZERO,
-                ns: self.path.clone(),
-            })
-            .collect()
+            sids.map(|id| RemoteSocketData::new(id, Uid::ZERO, self.path.clone()))
+                .collect()
         }
 
         fn send_many(&self, _: BroadcastIter<'_>, _: Value) -> Result<(), Vec<SocketError>> {
@@ -779,6 +1376,40 @@ mod test {
         fn server_id(&self) -> Uid {
             Uid::ZERO
         }
+        fn handoff(
+            &self,
+            _: Sid,
+            _: HandoffRequest,
+        ) -> BoxFuture<'static, Option<HandoffResponse>> {
+            Box::pin(future::ready(None))
+        }
+        fn on_join_room(&self, sid: Sid, room: &Room) {
+            let change = (true, sid, room.clone());
+            self.room_changes.lock().unwrap().push(change);
+        }
+        fn on_leave_room(&self, sid: Sid, room: &Room) {
+            let change = (false, sid, room.clone());
+            self.room_changes.lock().unwrap().push(change);
+        }
+        fn on_create_room(&self, room: &Room) {
+            self.room_lifecycle
+                .lock()
+                .unwrap()
+                .push((true, room.clone()));
+        }
+        fn on_delete_room(&self, room: &Room) {
+            self.room_lifecycle
+                .lock()
+                .unwrap()
+                .push((false, room.clone()));
+        }
+        fn schedule_room_expiry(&self, room: &Room, delay: Duration) {
+            let expiry = (room.clone(), delay);
+            self.scheduled_expiries.lock().unwrap().push(expiry);
+        }
+        fn on_expire_room(&self, room: &Room) {
+            self.expired_rooms.lock().unwrap().push(room.clone());
+        }
     }
 
     fn create_adapter<const S: usize>(sockets: [Sid; S]) -> CoreLocalAdapter<StubSockets> {
@@ -854,6 +1485,152 @@ mod test {
         }
     }
 
+    #[test]
+    fn room_hooks() {
+        let [sid1, sid2] = [Sid::new(), Sid::new()];
+        let adapter = create_adapter([sid1, sid2]);
+        adapter.add_all(sid1, ["room1"]);
+        // Joining a room twice or leaving a room the socket is not in is not a change.
+        adapter.add_all(sid1, ["room1"]);
+        adapter.del(sid2, "room1");
+        let mut opts = BroadcastOptions::new(sid1);
+        opts.rooms = smallvec!["room1".into()];
+        adapter.add_sockets(opts.clone(), "room2");
+        adapter.del_sockets(opts, "room2");
+        adapter.del_all(sid1);
+
+        let changes = adapter.emitter.room_changes.lock().unwrap();
+        assert_eq!(
+            *changes,
+            [
+                (true, sid1, Room::from("room1")),
+                (true, sid1, Room::from("room2")),
+                (false, sid1, Room::from("room2")),
+                (false, sid1, Room::from("room1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn watch_rooms() {
+        let sid = Sid::new();
+        let adapter = create_adapter([sid]);
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        adapter.watch_rooms(move |event| events_clone.lock().unwrap().push(event));
+        adapter.add_all(sid, ["room1", "room2"]);
+        adapter.del(sid, "room1");
+        adapter.add_all(sid, "room2");
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                RoomEvent::Create("room1".into()),
+                RoomEvent::Create("room2".into()),
+                RoomEvent::Delete("room1".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn room_lifecycle_hooks() {
+        let [sid1, sid2] = [Sid::new(), Sid::new()];
+        let adapter = create_adapter([sid1, sid2]);
+        adapter.add_all(sid1, ["room1", "room2"]);
+        // The room already exists
+        adapter.add_all(sid2, ["room1"]);
+        adapter.del(sid1, "room1");
+        adapter.del_all(sid1);
+        {
+            let lifecycle = adapter.emitter.room_lifecycle.lock().unwrap();
+            assert_eq!(
+                *lifecycle,
+                [
+                    (true, Room::from("room1")),
+                    (true, Room::from("room2")),
+                    (false, Room::from("room2")),
+                ]
+            );
+        }
+        let mut opts = BroadcastOptions::new(sid2);
+        opts.rooms = smallvec!["room1".into()];
+        adapter.add_sockets(opts.clone(), "room3");
+        adapter.del_sockets(opts, ["room1", "room3"]);
+
+        let lifecycle = adapter.emitter.room_lifecycle.lock().unwrap();
+        assert_eq!(
+            lifecycle[3..],
+            [
+                (true, Room::from("room3")),
+                (false, Room::from("room1")),
+                (false, Room::from("room3")),
+            ]
+        );
+    }
+
+    #[test]
+    fn room_expiry_when_empty() {
+        let sid = Sid::new();
+        let adapter = create_adapter([sid]);
+        let grace = Duration::from_millis(1);
+        let room = Room::from("room1");
+        adapter.set_expiry("room1", RoomExpiry::WhenEmpty(grace));
+        adapter.add_all(sid, "room1");
+        adapter.del(sid, "room1");
+        // The empty room is kept during the grace period
+        assert!(adapter.rooms.read().unwrap().contains_key("room1"));
+        assert_eq!(
+            *adapter.emitter.scheduled_expiries.lock().unwrap(),
+            [(room.clone(), grace), (room.clone(), grace)]
+        );
+
+        // The grace period ends once a socket joins the room
+        adapter.add_all(sid, "room1");
+        std::thread::sleep(grace);
+        adapter.expire_room(&room);
+        assert!(adapter.rooms.read().unwrap().contains_key("room1"));
+
+        adapter.del(sid, "room1");
+        std::thread::sleep(grace);
+        adapter.expire_room(&room);
+        assert!(adapter.rooms.read().unwrap().is_empty());
+        assert_eq!(
+            *adapter.emitter.room_lifecycle.lock().unwrap(),
+            [(true, room.clone()), (false, room.clone())]
+        );
+        assert_eq!(*adapter.emitter.expired_rooms.lock().unwrap(), [room]);
+    }
+
+    #[test]
+    fn room_expiry_ttl() {
+        let [sid1, sid2] = [Sid::new(), Sid::new()];
+        let adapter = create_adapter([sid1, sid2]);
+        adapter.add_all(sid1, ["room1", "room2"]);
+        adapter.add_all(sid2, "room1");
+        adapter.set_expiry("room1", RoomExpiry::Ttl(Duration::ZERO));
+        adapter.set_expiry("room2", RoomExpiry::Ttl(Duration::from_secs(60)));
+
+        // The expiry of room2 is not due
+        adapter.expire_room(&Room::from("room2"));
+        adapter.expire_room(&Room::from("room1"));
+        assert_eq!(adapter.socket_rooms(sid1), HashSet::from(["room2".into()]));
+        assert!(adapter.socket_rooms(sid2).is_empty());
+        {
+            let rooms_map = adapter.rooms.read().unwrap();
+            assert_eq!(rooms_map.keys().collect::<Vec<_>>(), ["room2"]);
+        }
+        {
+            let room_changes = adapter.emitter.room_changes.lock().unwrap();
+            assert_eq!(room_changes.iter().filter(|(join, ..)| !join).count(), 2);
+            let expired = adapter.emitter.expired_rooms.lock().unwrap();
+            assert_eq!(*expired, [Room::from("room1")]);
+        }
+
+        // A room with a ttl is still deleted when its last socket leaves it
+        adapter.del_all(sid1);
+        assert!(adapter.rooms.read().unwrap().is_empty());
+        assert!(adapter.expiries.read().unwrap().is_empty());
+    }
+
     #[test]
     fn socket_room() {
         let sid1 = Sid::new();
@@ -954,6 +1731,16 @@ mod test {
         assert!(sockets.contains(&socket2));
     }
 
+    #[test]
+    fn node() {
+        let adapter = create_adapter([Sid::new(), Sid::new()]);
+        let node = NodeInfo {
+            uid: Uid::ZERO,
+            sockets: 2,
+        };
+        assert_eq!(adapter.node(), node);
+    }
+
     #[test]
     fn disconnect_socket() {
         let socket0 = Sid::new();
@@ -1030,6 +1817,15 @@ mod test {
             .collect::<Vec<_>>();
         assert_eq!(sids, [sockets[1]]);
 
+        // sockets in several rooms are only returned once
+        let mut opts = BroadcastOptions::new(Sid::new());
+        opts.rooms = smallvec!["room2".into(), "room3".into()];
+        let mut sids = adapter
+            .apply_opts(&opts, &adapter.rooms.read().unwrap())
+            .collect::<Vec<_>>();
+        sids.sort();
+        assert_eq!(sids, sockets);
+
         let mut opts = BroadcastOptions::new(sockets[2]);
         opts.add_flag(BroadcastFlags::Broadcast);
         let mut sids = adapter
@@ -1059,4 +1855,48 @@ mod test {
             .collect::<Vec<_>>();
         assert_eq!(sids.len(), 1);
     }
+
+    #[test]
+    fn request_options_check_responses() {
+        let opts = RequestOptions::default();
+        assert!(opts.check_responses(2, 2).is_ok());
+        assert_eq!(
+            opts.check_responses(3, 1),
+            Err(PartialResponseError {
+                expected: 3,
+                received: 1
+            })
+        );
+        let opts = RequestOptions {
+            allow_partial: true,
+            ..Default::default()
+        };
+        assert!(opts.check_responses(3, 1).is_ok());
+    }
+
+    #[test]
+    fn remote_socket_data_wire_compat() {
+        /// The layout of the remote socket data before the presence field.
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct LegacyRemoteSocketData {
+            id: Sid,
+            server_id: Uid,
+            ns: Str,
+        }
+        let data = RemoteSocketData::new(Sid::new(), Uid::new(), Str::from("/"));
+        let legacy = LegacyRemoteSocketData {
+            id: data.id,
+            server_id: data.server_id,
+            ns: data.ns.clone(),
+        };
+
+        let encoded = rmp_serde::to_vec(&data).unwrap();
+        assert_eq!(encoded, rmp_serde::to_vec(&legacy).unwrap());
+        let decoded: LegacyRemoteSocketData = rmp_serde::from_slice(&encoded).unwrap();
+        assert_eq!(decoded, legacy);
+
+        let encoded = rmp_serde::to_vec(&legacy).unwrap();
+        let decoded: RemoteSocketData = rmp_serde::from_slice(&encoded).unwrap();
+        assert_eq!(decoded, data);
+    }
 }
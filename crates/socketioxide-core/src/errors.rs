@@ -1,11 +1,17 @@
 //! All the errors that can be returned by the crate. Mostly when using the [adapter](crate::adapter) module.
+//!
+//! Every error can be classified with an [`ErrorKind`], see the
+//! [`engineioxide::errors`] module for more details.
 use std::{convert::Infallible, fmt};
 
 use serde::{Deserialize, Serialize};
 
 use crate::parser::ParserError;
 
+pub use engineioxide::errors::ErrorKind;
+
 /// Error type when using the underlying engine.io socket
+#[non_exhaustive]
 #[derive(Debug, thiserror::Error, Serialize, Deserialize, Clone)]
 pub enum SocketError {
     /// The socket channel is full.
@@ -20,6 +26,16 @@ pub enum SocketError {
     Closed,
 }
 
+impl SocketError {
+    /// The [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SocketError::InternalChannelFull => ErrorKind::Buffer,
+            SocketError::Closed => ErrorKind::Transport,
+        }
+    }
+}
+
 /// Error type for the [`CoreAdapter`](crate::adapter::CoreAdapter) trait.
 #[derive(Debug, thiserror::Error)]
 pub struct AdapterError(#[from] pub Box<dyn std::error::Error + Send>);
@@ -28,13 +44,32 @@ impl fmt::Display for AdapterError {
         fmt::Display::fmt(&self.0, f)
     }
 }
+impl AdapterError {
+    /// The [`ErrorKind`] of this error, always [`ErrorKind::Adapter`].
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::Adapter
+    }
+}
 impl From<Infallible> for AdapterError {
     fn from(_: Infallible) -> Self {
         panic!("Infallible should never be constructed, this is a bug")
     }
 }
 
+/// Error returned when some servers didn't respond to a request before the timeout.
+///
+/// It can be avoided by allowing partial results with [`RequestOptions::allow_partial`](crate::adapter::RequestOptions).
+#[derive(Debug, thiserror::Error, Clone, Copy, PartialEq, Eq)]
+#[error("only {received} of {expected} servers responded before the timeout")]
+pub struct PartialResponseError {
+    /// The number of servers expected to respond.
+    pub expected: usize,
+    /// The number of servers that responded.
+    pub received: usize,
+}
+
 /// Error type for broadcast operations.
+#[non_exhaustive]
 #[derive(thiserror::Error, Debug)]
 pub enum BroadcastError {
     // This type should never constructed with an empty vector!
@@ -49,6 +84,25 @@ pub enum BroadcastError {
     /// An error occured while broadcasting to other nodes.
     #[error("Adapter error: {0}")]
     Adapter(#[from] AdapterError),
+
+    /// The operators of a persistent event don't only select a single user.
+    #[error("persistent operators must only select a single user")]
+    InvalidPersistent,
+}
+
+impl BroadcastError {
+    /// The [`ErrorKind`] of this error. For [`BroadcastError::Socket`],
+    /// it is the kind of the first socket error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            BroadcastError::Socket(errs) => {
+                errs.first().map_or(ErrorKind::Transport, SocketError::kind)
+            }
+            BroadcastError::Serialize(_) => ErrorKind::Serialization,
+            BroadcastError::Adapter(_) => ErrorKind::Adapter,
+            BroadcastError::InvalidPersistent => ErrorKind::InvalidInput,
+        }
+    }
 }
 
 impl From<Vec<SocketError>> for BroadcastError {
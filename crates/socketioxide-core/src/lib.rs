@@ -1,7 +1,7 @@
 #![warn(
     clippy::all,
     clippy::todo,
-    clippy::empty_enum,
+    clippy::empty_enums,
     clippy::mem_forget,
     clippy::unused_self,
     clippy::filter_map_next,
@@ -10,7 +10,6 @@
     clippy::match_wildcard_for_single_variants,
     clippy::if_let_mutex,
     clippy::await_holding_lock,
-    clippy::match_on_vec_items,
     clippy::imprecise_flops,
     clippy::suboptimal_flops,
     clippy::lossy_float_literal,
@@ -36,6 +35,8 @@ pub mod adapter;
 pub mod errors;
 pub mod packet;
 pub mod parser;
+#[cfg(feature = "remote-adapter")]
+pub mod remote;
 
 use std::{collections::VecDeque, ops::Deref};
 
@@ -44,7 +45,7 @@ pub use engineioxide::{sid::Sid, Str};
 use serde::{Deserialize, Serialize};
 
 /// Represents a unique identifier for a server.
-#[derive(Clone, Serialize, Deserialize, Debug, Copy, PartialEq, Eq, Default)]
+#[derive(Clone, Serialize, Deserialize, Debug, Copy, PartialEq, Eq, Hash, Default)]
 pub struct Uid(Sid);
 impl Deref for Uid {
     type Target = Sid;
@@ -71,7 +72,7 @@ impl Uid {
 /// or a string parser (e.g. [`CommonParser`](../socketioxide_parser_common/index.html))).
 ///
 /// If you want to deserialize this value to a specific type. You should manually call the `Data` extractor.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Value {
     /// A string payload that will be sent as a string engine.io packet.
     /// It can also contain adjacent binary payloads.
@@ -37,9 +37,14 @@ impl Packet {
 
 impl Packet {
     /// Create a connect error packet for the given namespace with a message
-    pub fn connect_error(ns: impl Into<Str>, message: impl Into<String>) -> Self {
+    /// and optional additional data, available as the `data` field of the error on the client side.
+    pub fn connect_error(
+        ns: impl Into<Str>,
+        message: impl Into<String>,
+        data: Option<Value>,
+    ) -> Self {
         Self {
-            inner: PacketData::ConnectError(message.into()),
+            inner: PacketData::ConnectError(message.into(), data),
             ns: ns.into(),
         }
     }
@@ -92,8 +97,9 @@ pub enum PacketData {
     Event(Value, Option<i64>),
     /// Event ack packet, to acknowledge an event
     EventAck(Value, i64),
-    /// Connect error packet, sent when the namespace is invalid
-    ConnectError(String),
+    /// Connect error packet with a message and optional data,
+    /// sent when the connection to the namespace is refused
+    ConnectError(String, Option<Value>),
     /// Binary event packet with optional ack id, to request an ack from the other side
     BinaryEvent(Value, Option<i64>),
     /// Binary ack packet, to acknowledge an event with binary data
@@ -108,7 +114,7 @@ impl PacketData {
             PacketData::Disconnect => 1,
             PacketData::Event(_, _) => 2,
             PacketData::EventAck(_, _) => 3,
-            PacketData::ConnectError(_) => 4,
+            PacketData::ConnectError(_, _) => 4,
             PacketData::BinaryEvent(_, _) => 5,
             PacketData::BinaryAck(_, _) => 6,
         }
@@ -134,9 +140,27 @@ impl PacketData {
 
 /// Connect packet sent by the client
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct ConnectPacket {
     /// The socket ID
     pub sid: Sid,
+    /// The private session ID used for connection state recovery.
+    /// It is only sent when connection state recovery is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<Sid>,
+}
+
+impl ConnectPacket {
+    /// Create a new connect packet for the given socket ID, without private session ID.
+    pub fn new(sid: Sid) -> Self {
+        Self { sid, pid: None }
+    }
+
+    /// Set the private session ID used for connection state recovery.
+    pub fn with_pid(mut self, pid: Option<Sid>) -> Self {
+        self.pid = pid;
+        self
+    }
 }
 
 impl Serialize for Packet {
@@ -154,7 +178,7 @@ impl Serialize for Packet {
             PacketData::Disconnect => (1, None, None, None),
             PacketData::Event(v, ack) => (2, Some(v), *ack, None),
             PacketData::EventAck(v, ack) => (3, Some(v), Some(*ack), None),
-            PacketData::ConnectError(e) => (4, None, None, Some(e)),
+            PacketData::ConnectError(e, v) => (4, v.as_ref(), None, Some(e)),
             PacketData::BinaryEvent(v, ack) => (5, Some(v), *ack, None),
             PacketData::BinaryAck(v, ack) => (6, Some(v), Some(*ack), None),
         };
@@ -185,7 +209,7 @@ impl<'de> Deserialize<'de> for Packet {
             1 => PacketData::Disconnect,
             2 => PacketData::Event(raw.data.ok_or(err("data"))?, raw.ack),
             3 => PacketData::EventAck(raw.data.ok_or(err("data"))?, raw.ack.ok_or(err("ack"))?),
-            4 => PacketData::ConnectError(raw.error.ok_or(err("error"))?),
+            4 => PacketData::ConnectError(raw.error.ok_or(err("error"))?, raw.data),
             5 => PacketData::BinaryEvent(raw.data.ok_or(err("data"))?, raw.ack),
             6 => PacketData::BinaryAck(raw.data.ok_or(err("data"))?, raw.ack.ok_or(err("ack"))?),
             i => return Err(serde::de::Error::custom(format!("invalid packet type {i}"))),
@@ -293,9 +317,13 @@ mod tests {
     fn packet_serde_connect_error() {
         let packet = Packet {
             ns: "/".into(),
-            inner: PacketData::ConnectError("connection_error".into()),
+            inner: PacketData::ConnectError("connection_error".into(), None),
         };
         assert_serde_packet(packet);
+
+        let data = Value::Str("{\"code\":401}".into(), None);
+        let packet = Packet::connect_error("/", "connection_error", Some(data));
+        assert_serde_packet(packet);
     }
 
     #[test]
@@ -98,6 +98,20 @@ pub trait Parse: Default + Copy {
     /// Try to read the event name from the given payload data.
     /// The event name should be the first element of the provided array according to the serde model.
     fn read_event(self, value: &Value) -> Result<&str, ParserError>;
+
+    /// Append the given offset string as the last element of the provided payload array.
+    /// This is used by the connection state recovery mechanism to tag each broadcasted event
+    /// so that a client can then communicate the last received event offset when reconnecting.
+    ///
+    /// The default implementation returns an error, so parsers that do not implement it
+    /// cannot be used with connection state recovery.
+    fn push_offset(self, value: &mut Value, offset: &str) -> Result<(), ParserError> {
+        let _ = (value, offset);
+        Err(ParserError::new(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "this parser does not support connection state recovery offsets",
+        )))
+    }
 }
 
 /// A parser error that wraps any error that can occur during parsing.
@@ -112,7 +126,12 @@ impl fmt::Display for ParserError {
         self.inner.fmt(f)
     }
 }
-impl std::error::Error for ParserError {}
+impl std::error::Error for ParserError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        // The inner error is displayed, so its source is the next one in the chain
+        self.inner.source()
+    }
+}
 impl Serialize for ParserError {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         self.inner.to_string().serialize(serializer)
@@ -135,6 +154,17 @@ impl ParserError {
             inner: Box::new(inner),
         }
     }
+
+    /// The [`ErrorKind`](crate::errors::ErrorKind) of this error,
+    /// always [`ErrorKind::Serialization`](crate::errors::ErrorKind::Serialization).
+    pub fn kind(&self) -> crate::errors::ErrorKind {
+        crate::errors::ErrorKind::Serialization
+    }
+
+    /// The wrapped error of the parser implementation (e.g. `serde_json::Error`).
+    pub fn inner(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        &*self.inner
+    }
 }
 /// Errors when parsing/serializing socket.io packets
 #[derive(thiserror::Error, Debug)]
@@ -572,5 +602,9 @@ pub mod test {
         fn read_event(self, _: &Value) -> Result<&str, ParserError> {
             Ok("")
         }
+
+        fn push_offset(self, _: &mut Value, _: &str) -> Result<(), ParserError> {
+            Err(stub_err())
+        }
     }
 }
@@ -0,0 +1,1003 @@
+//! The [`RemoteAdapter`], implementing the [`CoreAdapter`] for all the remote adapters.
+//!
+//! The remote adapters only differ by the way they carry the requests and the responses
+//! between the servers. Each of them provides a [`Transport`] over its backend (a pub/sub channel,
+//! a change stream, a topic...), while the [`RemoteAdapter`] handles the requests, the responses,
+//! the heartbeats and the deduplication the same way for all of them.
+//!
+//! Each server publishes its requests to all the servers or to a single target server,
+//! and the responses are always published to the server that sent the request.
+use std::{
+    collections::HashMap,
+    fmt,
+    future::{self, Future},
+    pin::Pin,
+    sync::{Arc, Mutex, Weak},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures_core::{future::BoxFuture, stream::BoxStream, Stream};
+use futures_util::StreamExt;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{sync::mpsc, task::AbortHandle, time};
+
+use super::{
+    dedup::Dedup,
+    request::{
+        read_req_id, RequestIn, RequestOut, RequestTypeIn, RequestTypeOut, Response, ResponseType,
+    },
+    stream::{AckStream, DropStream},
+    MessageStream, ResponseHandlers,
+};
+use crate::{
+    adapter::{
+        BroadcastFlags, BroadcastOptions, CoreAdapter, CoreLocalAdapter, DefinedAdapter,
+        HandoffRequest, HandoffResponse, NodeInfo, RemoteSocketData, RequestOptions, Room,
+        RoomEvent, RoomParam, SocketEmitter, Spawnable,
+    },
+    errors::{AdapterError, BroadcastError, PartialResponseError},
+    packet::Packet,
+    Sid, Uid,
+};
+
+/// A message received by a [`Transport`], still encoded.
+#[derive(Debug)]
+pub enum Incoming {
+    /// A request sent by a remote server.
+    Request(Vec<u8>),
+    /// A request replayed by the backend, published before the request timeout.
+    /// Only the requests that don't expect a response are handled.
+    Replayed(Vec<u8>),
+    /// A response to a request of this server.
+    Response(Vec<u8>),
+}
+
+/// The target of a request sent with a [`Transport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target<'a> {
+    /// All the servers of the namespace.
+    All,
+    /// A single server.
+    Server(Uid),
+    /// The servers having this room, only used by the transports [watching the rooms](Transport::watch_rooms).
+    Room(&'a str),
+}
+
+impl From<Option<Uid>> for Target<'_> {
+    fn from(uid: Option<Uid>) -> Self {
+        match uid {
+            Some(uid) => Target::Server(uid),
+            None => Target::All,
+        }
+    }
+}
+
+/// The options of the [`RemoteAdapter`], set from the config of each adapter.
+#[derive(Debug, Clone)]
+pub struct RemoteOptions {
+    /// The default timeout of the requests expecting responses.
+    pub request_timeout: Duration,
+    /// The channel size used to receive the ack responses.
+    pub ack_response_buffer: usize,
+    /// Whether the servers send heartbeats to track the other servers of the cluster.
+    /// Without heartbeats, the servers are only counted with the [`Transport::server_count`].
+    pub heartbeat: bool,
+    /// A remote server is considered disconnected if it didn't send a heartbeat during this duration.
+    pub hb_timeout: Duration,
+    /// The interval between the heartbeats of the current server.
+    pub hb_interval: Duration,
+    /// Whether the requests received twice are dropped.
+    pub dedup: bool,
+    /// The number of sequence numbers remembered for each server to detect the duplicated requests.
+    pub dedup_window: u64,
+}
+
+impl Default for RemoteOptions {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(5),
+            ack_response_buffer: 255,
+            heartbeat: true,
+            hb_timeout: Duration::from_secs(10),
+            hb_interval: Duration::from_secs(5),
+            dedup: false,
+            dedup_window: 1024,
+        }
+    }
+}
+
+/// The messaging backend of a [`RemoteAdapter`], created for each namespace.
+///
+/// A transport only carries the encoded requests and responses between the servers of the namespace.
+pub trait Transport: Send + Sync + 'static {
+    /// The state shared by the transports of all the namespaces, usually the adapter constructor.
+    type State: Send + Sync + 'static;
+    /// The error returned when sending a message.
+    type Error: std::error::Error + Send + 'static;
+    /// The error returned when initializing the transport.
+    type InitError: std::error::Error + Send + 'static;
+
+    /// Create the transport of the namespace `path`, for the server `uid`.
+    fn new(state: &Self::State, path: &str, uid: Uid) -> Self;
+
+    /// The options of the adapter.
+    fn options(&self) -> &RemoteOptions;
+
+    /// Start receiving the requests sent to all the servers and to this server,
+    /// and the responses sent to this server.
+    fn init(
+        &self,
+    ) -> impl Future<Output = Result<BoxStream<'static, Incoming>, Self::InitError>> + Send;
+
+    /// Stop receiving the messages, once the adapter is closed.
+    fn close(&self) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Send an encoded request.
+    ///
+    /// It returns an [`Error`] rather than a transport error, for the transports that encode
+    /// the requests in their own envelope.
+    fn send_req(
+        &self,
+        data: Vec<u8>,
+        target: Target<'_>,
+    ) -> impl Future<Output = Result<(), Error<Self::Error>>> + Send;
+
+    /// Send an encoded response to the server `target` that sent the request.
+    fn send_res(
+        &self,
+        data: Vec<u8>,
+        target: Uid,
+    ) -> impl Future<Output = Result<(), Error<Self::Error>>> + Send;
+
+    /// The number of servers of the namespace known by the backend, including the current one.
+    /// With the heartbeats, it is capped to the number of servers alive.
+    ///
+    /// By default the backend doesn't know the servers, and they are only counted with the heartbeats.
+    fn server_count(&self) -> impl Future<Output = Result<Option<u16>, Self::Error>> + Send {
+        future::ready(Ok(None))
+    }
+
+    /// Whether the broadcasts to a single room are sent to the [room](Target::Room).
+    /// The adapter then [subscribes](Transport::subscribe_room) to the local rooms.
+    fn watch_rooms(&self) -> bool {
+        false
+    }
+
+    /// Start receiving the requests sent to a local room, until it is unsubscribed.
+    fn subscribe_room(
+        &self,
+        room: &str,
+    ) -> impl Future<Output = Result<BoxStream<'static, Incoming>, Self::Error>> + Send {
+        let _ = room;
+        future::ready(Ok(futures_util::stream::empty().boxed()))
+    }
+
+    /// Stop receiving the requests sent to a deleted local room.
+    fn unsubscribe_room(&self, room: &str) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let _ = room;
+        future::ready(Ok(()))
+    }
+}
+
+/// Represent any error that might happen when using a remote adapter.
+#[derive(thiserror::Error)]
+pub enum Error<E> {
+    /// Transport error
+    #[error("driver error: {0}")]
+    Driver(E),
+    /// Packet decoding error
+    #[error("packet decoding error: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+    /// Packet encoding error
+    #[error("packet encoding error: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+    /// Some servers didn't respond before the timeout
+    #[error("{0}")]
+    Partial(#[from] PartialResponseError),
+}
+
+impl<E: fmt::Debug> fmt::Debug for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Driver(err) => write!(f, "Driver error: {:?}", err),
+            Self::Decode(err) => write!(f, "Decode error: {:?}", err),
+            Self::Encode(err) => write!(f, "Encode error: {:?}", err),
+            Self::Partial(err) => write!(f, "Partial response error: {:?}", err),
+        }
+    }
+}
+
+impl<E: std::error::Error + Send + 'static> From<Error<E>> for AdapterError {
+    fn from(err: Error<E>) -> Self {
+        AdapterError::from(Box::new(err) as Box<dyn std::error::Error + Send>)
+    }
+}
+
+/// The result of the init future.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct InitRes<E>(BoxFuture<'static, Result<(), E>>);
+
+impl<E> Future for InitRes<E> {
+    type Output = Result<(), E>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.as_mut().poll(cx)
+    }
+}
+impl<E: fmt::Display + Send + 'static> Spawnable for InitRes<E> {
+    fn spawn(self) {
+        tokio::spawn(async move {
+            if let Err(e) = self.0.await {
+                tracing::error!("error initializing adapter: {e}");
+            }
+        });
+    }
+}
+
+/// The remote adapter implementation, generic over the [`Transport`] carrying the messages.
+/// And over the [`SocketEmitter`] used to communicate with the local server. This allows to
+/// avoid cyclic dependencies between the adapter, `socketioxide-core` and `socketioxide` crates.
+pub struct RemoteAdapter<E, T> {
+    /// The transport of the namespace.
+    transport: T,
+    /// A unique identifier for the adapter to identify itself to the other servers.
+    uid: Uid,
+    /// The local adapter, used to manage local rooms and socket stores.
+    local: CoreLocalAdapter<E>,
+    /// A map of response handlers used to await for responses from the remote servers.
+    responses: Arc<Mutex<ResponseHandlers>>,
+    /// The last time a heartbeat was received from each remote server, with its number of sockets.
+    nodes_liveness: Mutex<HashMap<Uid, (Instant, u32)>>,
+    /// The tasks spawned by the adapter, aborted when the adapter is closed.
+    tasks: Mutex<Vec<AbortHandle>>,
+    /// The sequence numbers of the requests sent and received, with the deduplication enabled.
+    seqs: Dedup,
+}
+
+impl<E, T> DefinedAdapter for RemoteAdapter<E, T> {}
+impl<E: SocketEmitter, T: Transport> CoreAdapter<E> for RemoteAdapter<E, T> {
+    type Error = Error<T::Error>;
+    type State = T::State;
+    type AckStream = AckStream<E::AckStream>;
+    type InitRes = InitRes<T::InitError>;
+
+    fn new(state: &Self::State, local: CoreLocalAdapter<E>) -> Self {
+        let uid = local.server_id();
+        Self {
+            transport: T::new(state, local.path(), uid),
+            uid,
+            local,
+            responses: Arc::new(Mutex::new(HashMap::new())),
+            nodes_liveness: Mutex::new(HashMap::new()),
+            tasks: Mutex::new(Vec::new()),
+            seqs: Dedup::new(),
+        }
+    }
+
+    fn init(self: Arc<Self>, on_success: impl FnOnce() + Send + 'static) -> Self::InitRes {
+        let fut = async move {
+            let stream = self.transport.init().await?;
+            if self.transport.watch_rooms() {
+                let (tx, rx) = mpsc::unbounded_channel();
+                self.local.watch_rooms(move |event| {
+                    tx.send(event).ok();
+                });
+                tokio::spawn(Self::watch_rooms(Arc::downgrade(&self), rx));
+            }
+            if self.options().heartbeat {
+                let hb_task = tokio::spawn(self.clone().heartbeat_job());
+                self.tasks.lock().unwrap().push(hb_task.abort_handle());
+            }
+            let task = tokio::spawn(self.clone().pipe_stream(stream));
+            self.tasks.lock().unwrap().push(task.abort_handle());
+            on_success();
+            Ok(())
+        };
+        InitRes(Box::pin(fut))
+    }
+
+    async fn close(&self) -> Result<(), Self::Error> {
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+        self.transport.close().await.map_err(Error::Driver)
+    }
+
+    /// Get the number of servers known by the transport.
+    ///
+    /// With the heartbeats, the servers that didn't send a heartbeat during the last
+    /// [`hb_timeout`](RemoteOptions::hb_timeout) are not counted.
+    async fn server_count(&self) -> Result<u16, Self::Error> {
+        let count = self.transport.server_count().await.map_err(Error::Driver)?;
+        let alive = || self.remote_nodes().len() as u16 + 1;
+        Ok(match count {
+            Some(count) if self.options().heartbeat => count.min(alive()),
+            Some(count) => count,
+            None => alive(),
+        })
+    }
+
+    /// Get the servers that sent a heartbeat during the last [`hb_timeout`](RemoteOptions::hb_timeout),
+    /// with the number of sockets sent in their last heartbeat.
+    ///
+    /// Without heartbeats, only the current server is returned.
+    async fn nodes(&self) -> Result<Vec<NodeInfo>, Self::Error> {
+        let remote = self.remote_nodes();
+        Ok(std::iter::once(self.local.node()).chain(remote).collect())
+    }
+
+    /// Broadcast a packet to all the servers to send them through their sockets.
+    async fn broadcast(
+        &self,
+        packet: Packet,
+        opts: BroadcastOptions,
+    ) -> Result<(), BroadcastError> {
+        if !is_local_op(self.uid, &opts) {
+            let req = RequestOut::new(self.uid, RequestTypeOut::Broadcast(&packet), &opts);
+            let target = self.broadcast_target(&opts);
+            self.send_req(req, target)
+                .await
+                .map_err(AdapterError::from)?;
+        }
+
+        self.local.broadcast(packet, opts)?;
+        Ok(())
+    }
+
+    /// Broadcast a packet to all the servers to send them through their sockets.
+    ///
+    /// Returns a Stream that is a combination of the local ack stream and a remote [`MessageStream`].
+    /// Here is a specific protocol in order to know how many message the server expect to close
+    /// the stream at the right time:
+    /// * Get the number `n` of remote servers.
+    /// * Send the broadcast request.
+    /// * Expect `n` `BroadcastAckCount` response in the stream to know the number `m` of expected ack responses.
+    /// * Expect `sum(m)` broadcast counts sent by the servers.
+    ///
+    /// Example with 3 remote servers (n = 3):
+    /// ```text
+    /// +---+                   +---+                   +---+
+    /// | A |                   | B |                   | C |
+    /// +---+                   +---+                   +---+
+    ///   |                       |                       |
+    ///   |---BroadcastWithAck--->|                       |
+    ///   |---BroadcastWithAck--------------------------->|
+    ///   |                       |                       |
+    ///   |<-BroadcastAckCount(2)-|     (n = 2; m = 2)    |
+    ///   |<-BroadcastAckCount(2)-------(n = 2; m = 4)----|
+    ///   |                       |                       |
+    ///   |<----------------Ack---------------------------|
+    ///   |<----------------Ack---|                       |
+    ///   |                       |                       |
+    ///   |<----------------Ack---------------------------|
+    ///   |<----------------Ack---|                       |
+    async fn broadcast_with_ack(
+        &self,
+        packet: Packet,
+        opts: BroadcastOptions,
+        timeout: Option<Duration>,
+    ) -> Result<Self::AckStream, Self::Error> {
+        if is_local_op(self.uid, &opts) {
+            tracing::debug!(?opts, "broadcast with ack is local");
+            let (local, _) = self.local.broadcast_with_ack(packet, opts, timeout);
+            let stream = AckStream::new_local(local);
+            return Ok(stream);
+        }
+        let req = RequestOut::new(self.uid, RequestTypeOut::BroadcastWithAck(&packet), &opts);
+        let req_id = req.id;
+
+        let remote_serv_cnt = self.server_count().await?.saturating_sub(1);
+
+        let buffer = self.options().ack_response_buffer + remote_serv_cnt as usize;
+        let (tx, rx) = mpsc::channel(buffer);
+        self.responses.lock().unwrap().insert(req_id, tx);
+        let remote = MessageStream::new(rx);
+
+        self.send_req(req, opts.server_id.into()).await?;
+        let (local, _) = self.local.broadcast_with_ack(packet, opts, timeout);
+
+        Ok(AckStream::new(
+            local,
+            remote,
+            self.options().request_timeout,
+            remote_serv_cnt,
+            req_id,
+            self.responses.clone(),
+        ))
+    }
+
+    async fn disconnect_socket(&self, opts: BroadcastOptions) -> Result<(), BroadcastError> {
+        if !is_local_op(self.uid, &opts) {
+            let req = RequestOut::new(self.uid, RequestTypeOut::DisconnectSockets, &opts);
+            self.send_req(req, opts.server_id.into())
+                .await
+                .map_err(AdapterError::from)?;
+        }
+        self.local
+            .disconnect_socket(opts)
+            .map_err(BroadcastError::Socket)?;
+
+        Ok(())
+    }
+
+    async fn rooms(
+        &self,
+        opts: BroadcastOptions,
+        req_opts: RequestOptions,
+    ) -> Result<Vec<Room>, Self::Error> {
+        const PACKET_IDX: u8 = 2;
+
+        if is_local_op(self.uid, &opts) {
+            return Ok(self.local.rooms(opts).into_iter().collect());
+        }
+        let req = RequestOut::new(self.uid, RequestTypeOut::AllRooms, &opts);
+        let req_id = req.id;
+
+        // First get the remote stream because the transport might deliver
+        // the responses before subscription is done.
+        let (stream, expected) = self
+            .get_res::<()>(req_id, PACKET_IDX, opts.server_id, req_opts.timeout)
+            .await?;
+        self.send_req(req, opts.server_id.into()).await?;
+        let local = self.local.rooms(opts);
+        let (rooms, received) = stream
+            .filter_map(|item| future::ready(item.into_rooms()))
+            .fold((local, 0), |(mut acc, received), item| async move {
+                acc.extend(item);
+                (acc, received + 1)
+            })
+            .await;
+        req_opts.check_responses(expected, received)?;
+        Ok(Vec::from_iter(rooms))
+    }
+
+    async fn sockets_count(
+        &self,
+        opts: BroadcastOptions,
+        req_opts: RequestOptions,
+    ) -> Result<usize, Self::Error> {
+        const PACKET_IDX: u8 = 5;
+
+        if is_local_op(self.uid, &opts) {
+            return Ok(self.local.sockets(opts).len());
+        }
+        let req = RequestOut::new(self.uid, RequestTypeOut::SocketsCount, &opts);
+        let req_id = req.id;
+
+        // First get the remote stream because the transport might deliver
+        // the responses before subscription is done.
+        let (stream, expected) = self
+            .get_res::<()>(req_id, PACKET_IDX, opts.server_id, req_opts.timeout)
+            .await?;
+        self.send_req(req, opts.server_id.into()).await?;
+        let local = self.local.sockets(opts).len();
+        let (count, received) = stream
+            .filter_map(|item| future::ready(item.into_sockets_count()))
+            .fold((local, 0), |(count, received), item| {
+                future::ready((count + item as usize, received + 1))
+            })
+            .await;
+        req_opts.check_responses(expected, received)?;
+        Ok(count)
+    }
+
+    async fn add_sockets(
+        &self,
+        opts: BroadcastOptions,
+        rooms: impl RoomParam,
+    ) -> Result<(), Self::Error> {
+        let rooms: Vec<Room> = rooms.into_room_iter().collect();
+        if !is_local_op(self.uid, &opts) {
+            let req = RequestOut::new(self.uid, RequestTypeOut::AddSockets(&rooms), &opts);
+            self.send_req(req, opts.server_id.into()).await?;
+        }
+        self.local.add_sockets(opts, rooms);
+        Ok(())
+    }
+
+    async fn del_sockets(
+        &self,
+        opts: BroadcastOptions,
+        rooms: impl RoomParam,
+    ) -> Result<(), Self::Error> {
+        let rooms: Vec<Room> = rooms.into_room_iter().collect();
+        if !is_local_op(self.uid, &opts) {
+            let req = RequestOut::new(self.uid, RequestTypeOut::DelSockets(&rooms), &opts);
+            self.send_req(req, opts.server_id.into()).await?;
+        }
+        self.local.del_sockets(opts, rooms);
+        Ok(())
+    }
+
+    async fn fetch_sockets(
+        &self,
+        opts: BroadcastOptions,
+    ) -> Result<Vec<RemoteSocketData>, Self::Error> {
+        if is_local_op(self.uid, &opts) {
+            return Ok(self.local.fetch_sockets(opts));
+        }
+        const PACKET_IDX: u8 = 3;
+        let req = RequestOut::new(self.uid, RequestTypeOut::FetchSockets, &opts);
+        let req_id = req.id;
+        // First get the remote stream because the transport might deliver
+        // the responses before subscription is done.
+        let (remote, _) = self
+            .get_res::<RemoteSocketData>(req_id, PACKET_IDX, opts.server_id, None)
+            .await?;
+
+        self.send_req(req, opts.server_id.into()).await?;
+        let local = self.local.fetch_sockets(opts);
+        let sockets = remote
+            .filter_map(|item| future::ready(item.into_fetch_sockets()))
+            .fold(local, |mut acc, item| async move {
+                acc.extend(item);
+                acc
+            })
+            .await;
+        Ok(sockets)
+    }
+
+    async fn forward_polling(
+        &self,
+        sid: Sid,
+        mut req: HandoffRequest,
+    ) -> Result<Option<HandoffResponse>, Self::Error> {
+        const PACKET_IDX: u8 = 4;
+        // The owner must answer before the end of the request timeout.
+        if let HandoffRequest::Poll { timeout } = &mut req {
+            *timeout = std::cmp::min(*timeout, self.options().request_timeout / 2);
+        }
+        let opts = BroadcastOptions::default();
+        let req = RequestOut::new(self.uid, RequestTypeOut::Handoff(sid, &req), &opts);
+        let req_id = req.id;
+        // First get the remote stream because the transport might deliver
+        // the responses before subscription is done.
+        let (remote, _) = self.get_res::<()>(req_id, PACKET_IDX, None, None).await?;
+        self.send_req(req, Target::All).await?;
+        let res = remote.filter_map(|item| future::ready(item.into_handoff()));
+        futures_util::pin_mut!(res);
+        Ok(res.next().await)
+    }
+
+    fn get_local(&self) -> &CoreLocalAdapter<E> {
+        &self.local
+    }
+}
+
+impl<E: SocketEmitter, T: Transport> RemoteAdapter<E, T> {
+    #[inline]
+    fn options(&self) -> &RemoteOptions {
+        self.transport.options()
+    }
+
+    /// Get the target of a broadcast request.
+    ///
+    /// When the transport watches the rooms, the broadcasts to a single room
+    /// are sent to the servers having the room.
+    fn broadcast_target<'a>(&self, opts: &'a BroadcastOptions) -> Target<'a> {
+        match opts.rooms.as_slice() {
+            [room] if opts.server_id.is_none() && self.transport.watch_rooms() => {
+                Target::Room(room)
+            }
+            _ => opts.server_id.into(),
+        }
+    }
+
+    /// Subscribe to each local room and unsubscribe from it once the room is deleted.
+    /// It stops when the adapter is dropped.
+    async fn watch_rooms(adapter: Weak<Self>, mut rx: mpsc::UnboundedReceiver<RoomEvent>) {
+        while let Some(event) = rx.recv().await {
+            let Some(adapter) = adapter.upgrade() else {
+                break;
+            };
+            let res = match event {
+                RoomEvent::Create(room) => adapter.subscribe_room(&room).await,
+                RoomEvent::Delete(room) => adapter.transport.unsubscribe_room(&room).await,
+            };
+            if let Err(e) = res {
+                let ns = adapter.local.path();
+                let uid = adapter.uid;
+                tracing::warn!(?uid, ?ns, "room subscription error: {e}");
+            }
+        }
+    }
+
+    /// Subscribe to a room and handle its broadcast requests.
+    async fn subscribe_room(self: &Arc<Self>, room: &Room) -> Result<(), T::Error> {
+        let stream = self.transport.subscribe_room(room).await?;
+        // The stream ends when the room is unsubscribed.
+        tokio::spawn(Self::pipe_room_stream(Arc::downgrade(self), stream));
+        Ok(())
+    }
+
+    async fn pipe_room_stream(adapter: Weak<Self>, mut stream: BoxStream<'static, Incoming>) {
+        while let Some(item) = stream.next().await {
+            let Some(adapter) = adapter.upgrade() else {
+                break;
+            };
+            adapter.recv(item);
+        }
+    }
+
+    async fn pipe_stream(self: Arc<Self>, mut stream: BoxStream<'static, Incoming>) {
+        while let Some(item) = stream.next().await {
+            self.recv(item);
+        }
+    }
+
+    fn recv(self: &Arc<Self>, item: Incoming) {
+        let res = match item {
+            Incoming::Request(item) => self.recv_req(&item, false),
+            Incoming::Replayed(item) => self.recv_req(&item, true),
+            Incoming::Response(item) => {
+                self.recv_res(item);
+                Ok(())
+            }
+        };
+        if let Err(e) = res {
+            let ns = self.local.path();
+            let uid = self.uid;
+            tracing::warn!(?uid, ?ns, "request handler error: {e}");
+        }
+    }
+
+    /// Route a response to the handler of its request.
+    fn recv_res(&self, item: Vec<u8>) {
+        let req_id = read_req_id(&item);
+        tracing::trace!(?req_id, "extracted sid");
+        let handlers = self.responses.lock().unwrap();
+        if let Some(tx) = req_id.and_then(|id| handlers.get(&id)) {
+            if let Err(e) = tx.try_send(item) {
+                tracing::warn!("error sending response to handler: {e}");
+            }
+        } else {
+            tracing::warn!(?req_id, "could not find req handler");
+        }
+    }
+
+    /// Handle a generic request received by the transport.
+    /// Replayed requests are only handled if they don't expect a response.
+    fn recv_req(self: &Arc<Self>, item: &[u8], replayed: bool) -> Result<(), Error<T::Error>> {
+        let req: RequestIn = rmp_serde::from_slice(item)?;
+        if req.node_id == self.uid {
+            return Ok(());
+        }
+        if replayed && !is_replayable(&req.r#type) {
+            tracing::trace!(?req, "ignoring replayed request");
+            return Ok(());
+        }
+        let opts = self.options();
+        if opts.dedup && !self.seqs.recv(req.node_id, req.seq, opts.dedup_window) {
+            tracing::debug!(?req.node_id, ?req.seq, "duplicated request dropped");
+            return Ok(());
+        }
+
+        tracing::trace!(?req, "handling request");
+
+        match req.r#type {
+            RequestTypeIn::Broadcast(p) => self.recv_broadcast(req.opts, p),
+            RequestTypeIn::BroadcastWithAck(_) => self.clone().recv_broadcast_with_ack(req),
+            RequestTypeIn::DisconnectSockets => self.recv_disconnect_sockets(req),
+            RequestTypeIn::AllRooms => self.recv_rooms(req),
+            RequestTypeIn::AddSockets(rooms) => self.recv_add_sockets(req.opts, rooms),
+            RequestTypeIn::DelSockets(rooms) => self.recv_del_sockets(req.opts, rooms),
+            RequestTypeIn::FetchSockets => self.recv_fetch_sockets(req),
+            RequestTypeIn::SocketsCount => self.recv_sockets_count(req),
+            RequestTypeIn::Handoff(sid, r) => {
+                self.clone().recv_handoff(req.node_id, req.id, sid, r)
+            }
+            RequestTypeIn::Heartbeat(sockets) => self.recv_heartbeat(req, sockets),
+            RequestTypeIn::InitHeartbeat(sockets) => self.recv_init_heartbeat(req, sockets),
+        };
+        Ok(())
+    }
+
+    fn recv_broadcast(&self, opts: BroadcastOptions, packet: Packet) {
+        if let Err(e) = self.local.broadcast(packet, opts) {
+            let ns = self.local.path();
+            tracing::warn!(?self.uid, ?ns, "remote request broadcast handler: {:?}", e);
+        }
+    }
+
+    fn recv_disconnect_sockets(&self, req: RequestIn) {
+        if let Err(e) = self.local.disconnect_socket(req.opts) {
+            let ns = self.local.path();
+            tracing::warn!(
+                ?self.uid,
+                ?ns,
+                "remote request disconnect sockets handler: {:?}",
+                e
+            );
+        }
+    }
+
+    fn recv_broadcast_with_ack(self: Arc<Self>, req: RequestIn) {
+        let packet = match req.r#type {
+            RequestTypeIn::BroadcastWithAck(p) => p,
+            _ => unreachable!(),
+        };
+        let (stream, count) = self.local.broadcast_with_ack(packet, req.opts, None);
+        tokio::spawn(async move {
+            let on_err = |err| {
+                let ns = self.local.path();
+                tracing::warn!(
+                    ?self.uid,
+                    ?ns,
+                    "remote request broadcast with ack handler errors: {:?}",
+                    err
+                );
+            };
+            // First send the count of expected acks to the server that sent the request.
+            // This is used to keep track of the number of expected acks.
+            let res = Response {
+                r#type: ResponseType::<()>::BroadcastAckCount(count),
+                node_id: self.uid,
+            };
+            if let Err(err) = self.send_res(req.node_id, req.id, res).await {
+                on_err(err);
+                return;
+            }
+
+            // Then send the acks as they are received.
+            futures_util::pin_mut!(stream);
+            while let Some(ack) = stream.next().await {
+                let res = Response {
+                    r#type: ResponseType::BroadcastAck(ack),
+                    node_id: self.uid,
+                };
+                if let Err(err) = self.send_res(req.node_id, req.id, res).await {
+                    on_err(err);
+                    return;
+                }
+            }
+        });
+    }
+
+    fn recv_rooms(self: &Arc<Self>, req: RequestIn) {
+        let rooms = self.local.rooms(req.opts);
+        let res = Response {
+            r#type: ResponseType::<()>::AllRooms(rooms),
+            node_id: self.uid,
+        };
+        self.spawn_res(req.node_id, req.id, res, "rooms");
+    }
+
+    fn recv_sockets_count(self: &Arc<Self>, req: RequestIn) {
+        let count = self.local.sockets(req.opts).len() as u32;
+        let res = Response {
+            r#type: ResponseType::<()>::SocketsCount(count),
+            node_id: self.uid,
+        };
+        self.spawn_res(req.node_id, req.id, res, "sockets count");
+    }
+
+    fn recv_add_sockets(&self, opts: BroadcastOptions, rooms: Vec<Room>) {
+        self.local.add_sockets(opts, rooms);
+    }
+
+    fn recv_del_sockets(&self, opts: BroadcastOptions, rooms: Vec<Room>) {
+        self.local.del_sockets(opts, rooms);
+    }
+
+    fn recv_fetch_sockets(self: &Arc<Self>, req: RequestIn) {
+        let sockets = self.local.fetch_sockets(req.opts);
+        let res = Response {
+            node_id: self.uid,
+            r#type: ResponseType::FetchSockets(sockets),
+        };
+        self.spawn_res(req.node_id, req.id, res, "fetch sockets");
+    }
+
+    fn recv_handoff(self: Arc<Self>, node_id: Uid, req_id: Sid, sid: Sid, req: HandoffRequest) {
+        let handoff = self.local.handoff(sid, req);
+        tokio::spawn(async move {
+            // Every server answers so that the requester doesn't wait for the request timeout.
+            let res = Response {
+                node_id: self.uid,
+                r#type: ResponseType::<()>::Handoff(handoff.await),
+            };
+            if let Err(err) = self.send_res(node_id, req_id, res).await {
+                let ns = self.local.path();
+                tracing::warn!(?self.uid, ?ns, "remote request handoff handler: {:?}", err);
+            }
+        });
+    }
+
+    fn recv_heartbeat(&self, req: RequestIn, sockets: u32) {
+        tracing::trace!(?req.node_id, "heartbeat received");
+        let mut node_liveness = self.nodes_liveness.lock().unwrap();
+        node_liveness.insert(req.node_id, (Instant::now(), sockets));
+    }
+
+    fn recv_init_heartbeat(self: &Arc<Self>, req: RequestIn, sockets: u32) {
+        tracing::trace!(?req.node_id, "initial heartbeat detected, saying hello to the new node");
+        self.nodes_liveness
+            .lock()
+            .unwrap()
+            .insert(req.node_id, (Instant::now(), sockets));
+
+        let this = self.clone();
+        tokio::spawn(async move {
+            let opts = BroadcastOptions::default();
+            let sockets = this.local.node().sockets;
+            let res = RequestOut::new(this.uid, RequestTypeOut::Heartbeat(sockets), &opts);
+            let target = Target::Server(req.node_id);
+            if let Err(err) = this.send_req(res, target).await {
+                tracing::warn!(?this.uid, "remote request init heartbeat handler: {:?}", err);
+            }
+        });
+    }
+
+    /// Send an initial heartbeat so that the other servers reply with a heartbeat,
+    /// then send a heartbeat at each interval.
+    async fn heartbeat_job(self: Arc<Self>) {
+        let mut interval = time::interval(self.options().hb_interval);
+        interval.tick().await; // first tick yields immediately
+        let opts = BroadcastOptions::default();
+        let sockets = self.local.node().sockets;
+        let req = RequestOut::new(self.uid, RequestTypeOut::InitHeartbeat(sockets), &opts);
+        if let Err(err) = self.send_req(req, Target::All).await {
+            tracing::warn!(?self.uid, "error sending initial heartbeat: {:?}", err);
+        }
+        loop {
+            interval.tick().await;
+            // The servers that stopped sending heartbeats won't send requests anymore.
+            self.seqs.prune(self.options().hb_timeout);
+            let sockets = self.local.node().sockets;
+            let req = RequestOut::new(self.uid, RequestTypeOut::Heartbeat(sockets), &opts);
+            if let Err(err) = self.send_req(req, Target::All).await {
+                tracing::warn!(?self.uid, "error sending heartbeat: {:?}", err);
+            }
+        }
+    }
+
+    /// The remote servers that sent a heartbeat during the last [`hb_timeout`](RemoteOptions::hb_timeout).
+    fn remote_nodes(&self) -> Vec<NodeInfo> {
+        let timeout = self.options().hb_timeout;
+        let mut nodes = self.nodes_liveness.lock().unwrap();
+        nodes.retain(|_, (last, _)| last.elapsed() < timeout);
+        nodes
+            .iter()
+            .map(|(&uid, &(_, sockets))| NodeInfo { uid, sockets })
+            .collect()
+    }
+
+    async fn send_req(
+        &self,
+        mut req: RequestOut<'_>,
+        target: Target<'_>,
+    ) -> Result<(), Error<T::Error>> {
+        req.seq = self.seqs.next_seq();
+        tracing::trace!(?req, ?target, "sending request");
+        let req = rmp_serde::to_vec(&req)?;
+        self.transport.send_req(req, target).await
+    }
+
+    async fn send_res<D: Serialize + fmt::Debug>(
+        &self,
+        req_node_id: Uid,
+        req_id: Sid,
+        res: Response<D>,
+    ) -> Result<(), Error<T::Error>> {
+        tracing::trace!(?res, "sending response to {}", req_node_id);
+        // We send the req_id separated from the response object.
+        // This allows to partially decode the response and route by the req_id
+        // before fully deserializing it.
+        let res = rmp_serde::to_vec(&(req_id, res))?;
+        self.transport.send_res(res, req_node_id).await
+    }
+
+    /// Send a response from a spawned task, logging the errors with the `handler` name.
+    fn spawn_res<D: Serialize + fmt::Debug + Send + 'static>(
+        self: &Arc<Self>,
+        req_node_id: Uid,
+        req_id: Sid,
+        res: Response<D>,
+        handler: &'static str,
+    ) {
+        let this = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = this.send_res(req_node_id, req_id, res).await {
+                let ns = this.local.path();
+                tracing::warn!(?this.uid, ?ns, "remote request {handler} handler: {:?}", err);
+            }
+        });
+    }
+
+    /// Await for all the responses from the remote servers.
+    /// The number of expected responses is returned alongside the stream.
+    /// If `timeout` is `None`, the default request timeout is used.
+    async fn get_res<D: DeserializeOwned + fmt::Debug>(
+        &self,
+        req_id: Sid,
+        response_idx: u8,
+        target_uid: Option<Uid>,
+        timeout: Option<Duration>,
+    ) -> Result<(impl Stream<Item = Response<D>>, usize), Error<T::Error>> {
+        // Check for specific target node
+        let remote_serv_cnt = if target_uid.is_none() {
+            self.server_count().await?.saturating_sub(1) as usize
+        } else {
+            1
+        };
+        let (tx, rx) = mpsc::channel(std::cmp::max(remote_serv_cnt, 1));
+        self.responses.lock().unwrap().insert(req_id, tx);
+        let stream = MessageStream::new(rx)
+            .filter_map(|item| {
+                let data = match rmp_serde::from_slice::<(Sid, Response<D>)>(&item) {
+                    Ok((_, data)) => Some(data),
+                    Err(e) => {
+                        tracing::warn!("error decoding response: {e}");
+                        None
+                    }
+                };
+                future::ready(data)
+            })
+            .filter(move |item| future::ready(item.r#type.to_u8() == response_idx))
+            .take(remote_serv_cnt)
+            .take_until(time::sleep(
+                timeout.unwrap_or(self.options().request_timeout),
+            ));
+        let stream = DropStream::new(stream, self.responses.clone(), req_id);
+        Ok((stream, remote_serv_cnt))
+    }
+}
+
+/// A local operator is either something that is flagged as local or a request that should be specifically
+/// sent to the current server.
+#[inline]
+fn is_local_op(uid: Uid, opts: &BroadcastOptions) -> bool {
+    if opts.has_flag(BroadcastFlags::Local)
+        || (!opts.has_flag(BroadcastFlags::Broadcast)
+            && opts.server_id == Some(uid)
+            && opts.rooms.is_empty()
+            && opts.sid.is_some())
+    {
+        tracing::debug!(?opts, "operation is local");
+        true
+    } else {
+        false
+    }
+}
+
+/// Requests that don't expect a response and can be handled when replayed.
+fn is_replayable(req: &RequestTypeIn) -> bool {
+    matches!(
+        req,
+        RequestTypeIn::Broadcast(_)
+            | RequestTypeIn::DisconnectSockets
+            | RequestTypeIn::AddSockets(_)
+            | RequestTypeIn::DelSockets(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_local_op() {
+        let server_id = Uid::new();
+        let remote = RemoteSocketData::new(Sid::new(), server_id, "/".into());
+        let opts = BroadcastOptions::new_remote(&remote);
+        assert!(is_local_op(server_id, &opts));
+        assert!(!is_local_op(Uid::new(), &opts));
+        let opts = BroadcastOptions::new(Sid::new());
+        assert!(!is_local_op(Uid::new(), &opts));
+    }
+
+    #[test]
+    fn test_is_replayable() {
+        let packet = Packet::connect("/", None);
+        assert!(is_replayable(&RequestTypeIn::Broadcast(packet.clone())));
+        assert!(is_replayable(&RequestTypeIn::DisconnectSockets));
+        assert!(is_replayable(&RequestTypeIn::AddSockets(vec![])));
+        assert!(is_replayable(&RequestTypeIn::DelSockets(vec![])));
+        assert!(!is_replayable(&RequestTypeIn::BroadcastWithAck(packet)));
+        assert!(!is_replayable(&RequestTypeIn::AllRooms));
+        assert!(!is_replayable(&RequestTypeIn::FetchSockets));
+        assert!(!is_replayable(&RequestTypeIn::Heartbeat(0)));
+        assert!(!is_replayable(&RequestTypeIn::InitHeartbeat(0)));
+    }
+}
@@ -0,0 +1,148 @@
+//! Deduplication of the requests redelivered by the drivers, with per-server sequence numbers.
+//!
+//! Each request is published with a sequence number incremented by its server,
+//! so that the requests received twice can be dropped.
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::Uid;
+
+/// The sequence numbers of the requests sent and received by a server.
+#[derive(Debug, Default)]
+pub struct Dedup {
+    /// The sequence number of the last request sent.
+    seq: AtomicU64,
+    /// The sequence numbers recently received from each remote server.
+    seqs: Mutex<HashMap<Uid, SeqWindow>>,
+}
+
+impl Dedup {
+    /// Create a new deduplication state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the sequence number of the next request sent.
+    pub fn next_seq(&self) -> u64 {
+        self.seq.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Record the sequence number of a request received from a remote server.
+    /// Returns `false` if the request was already received. The requests without a
+    /// sequence number (sent by the servers of previous versions) are always accepted.
+    ///
+    /// The `window` is the number of sequence numbers remembered for each server.
+    pub fn recv(&self, node_id: Uid, seq: u64, window: u64) -> bool {
+        if seq == 0 {
+            return true;
+        }
+        let mut seqs = self.seqs.lock().unwrap();
+        seqs.entry(node_id)
+            .or_insert_with(SeqWindow::new)
+            .insert(seq, window)
+    }
+
+    /// Forget the servers that didn't send any request during the `timeout`.
+    pub fn prune(&self, timeout: Duration) {
+        self.seqs
+            .lock()
+            .unwrap()
+            .retain(|_, window| window.last_recv.elapsed() < timeout);
+    }
+}
+
+/// The sequence numbers recently received from a server.
+///
+/// The requests of a server may be received out of order when they are published on different channels,
+/// so the sequence numbers are remembered in a window below the highest one received.
+/// The sequence numbers older than the window are considered as already received.
+#[derive(Debug)]
+struct SeqWindow {
+    /// The highest sequence number received.
+    last: u64,
+    /// The sequence numbers received in the window.
+    seen: BTreeSet<u64>,
+    /// The last time a request was received.
+    last_recv: Instant,
+}
+
+impl SeqWindow {
+    fn new() -> Self {
+        Self {
+            last: 0,
+            seen: BTreeSet::new(),
+            last_recv: Instant::now(),
+        }
+    }
+
+    /// Record a sequence number. Returns `false` if it was already received
+    /// or if it is older than the window.
+    fn insert(&mut self, seq: u64, window: u64) -> bool {
+        self.last_recv = Instant::now();
+        if seq <= self.last.saturating_sub(window) || !self.seen.insert(seq) {
+            return false;
+        }
+        if seq > self.last {
+            self.last = seq;
+            let start = seq.saturating_sub(window);
+            self.seen = self.seen.split_off(&(start + 1));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates() {
+        let mut window = SeqWindow::new();
+        assert!(window.insert(1, 4));
+        assert!(window.insert(2, 4));
+        assert!(!window.insert(1, 4));
+        assert!(!window.insert(2, 4));
+    }
+
+    #[test]
+    fn out_of_order() {
+        let mut window = SeqWindow::new();
+        assert!(window.insert(10, 4));
+        assert!(window.insert(8, 4));
+        assert!(window.insert(9, 4));
+        assert!(!window.insert(8, 4));
+        // older than the window
+        assert!(!window.insert(6, 4));
+        assert!(window.insert(7, 4));
+
+        assert!(window.insert(20, 4));
+        assert!(!window.insert(10, 4));
+        assert!(!window.insert(16, 4));
+        assert!(window.insert(17, 4));
+        assert_eq!(window.seen.len(), 2);
+    }
+
+    #[test]
+    fn dedup() {
+        let dedup = Dedup::new();
+        assert_eq!(dedup.next_seq(), 1);
+        assert_eq!(dedup.next_seq(), 2);
+
+        let (uid1, uid2) = (Uid::new(), Uid::new());
+        assert!(dedup.recv(uid1, 1, 4));
+        assert!(!dedup.recv(uid1, 1, 4));
+        assert!(dedup.recv(uid2, 1, 4));
+        // requests without sequence number
+        assert!(dedup.recv(uid1, 0, 4));
+        assert!(dedup.recv(uid1, 0, 4));
+
+        dedup.prune(Duration::ZERO);
+        assert!(dedup.recv(uid1, 1, 4));
+    }
+}
@@ -0,0 +1,60 @@
+//! Shared building blocks of the remote adapters (redis, mongodb, nats, postgres, kafka...).
+//!
+//! All the remote adapters exchange the same msgpack encoded [requests](request::RequestOut)
+//! and [responses](request::Response) between the servers, whatever their transport is.
+//! The responses to a request are routed to a [`ResponseHandlers`] entry and merged with the local
+//! acknowledgements in an [`AckStream`](stream::AckStream).
+//! The [`RemoteAdapter`](adapter::RemoteAdapter) implements the adapter logic once for all of them,
+//! over the [`Transport`](adapter::Transport) of each adapter.
+//!
+//! This module is only available with the `remote-adapter` feature.
+use std::{collections::HashMap, pin::Pin, task};
+
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use tokio::sync::mpsc;
+
+use crate::Sid;
+
+pub mod adapter;
+pub mod dedup;
+pub mod request;
+pub mod stream;
+
+/// The response handlers of the pending requests of a server, indexed by request id.
+/// Each handler receives the raw responses sent by the remote servers.
+pub type ResponseHandlers = HashMap<Sid, mpsc::Sender<Vec<u8>>>;
+
+pin_project! {
+    /// A stream of raw messages received by a driver.
+    /// Messages are encoded with msgpack.
+    #[derive(Debug)]
+    pub struct MessageStream<T> {
+        #[pin]
+        rx: mpsc::Receiver<T>,
+    }
+}
+
+impl<T> MessageStream<T> {
+    /// Create a new empty message stream.
+    pub fn new_empty() -> Self {
+        // mpsc bounded channel requires buffer > 0
+        let (_, rx) = mpsc::channel(1);
+        Self { rx }
+    }
+    /// Create a new message stream from a receiver.
+    pub fn new(rx: mpsc::Receiver<T>) -> Self {
+        Self { rx }
+    }
+}
+
+impl<T> Stream for MessageStream<T> {
+    type Item = T;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Option<Self::Item>> {
+        self.project().rx.poll_recv(cx)
+    }
+}
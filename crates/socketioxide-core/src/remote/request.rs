@@ -1,14 +1,17 @@
-//! Custom request and response types for the Redis adapter.
+//! Custom request and response types exchanged between the servers by the remote adapters.
 //! Custom serialization/deserialization to reduce the size of the messages.
-use std::{collections::HashSet, str::FromStr};
+use std::{collections::HashSet, str::FromStr, time::Duration};
 
+use bytes::Bytes;
 use serde::{de::SeqAccess, Deserialize, Serialize};
-use socketioxide_core::{
-    adapter::{BroadcastOptions, Room},
+
+use crate::{
+    adapter::{BroadcastOptions, HandoffRequest, HandoffResponse, Room},
     packet::Packet,
     Sid, Uid, Value,
 };
 
+/// The type of a request sent to the other servers, borrowing its data.
 #[derive(Debug, PartialEq)]
 pub enum RequestTypeOut<'a> {
     /// Broadcast a packet to matching sockets.
@@ -25,6 +28,14 @@ pub enum RequestTypeOut<'a> {
     DelSockets(&'a Vec<Room>),
     /// Fetch socket data.
     FetchSockets,
+    /// Handle a polling request forwarded for an engine.io session.
+    Handoff(Sid, &'a HandoffRequest),
+    /// Count the matching sockets.
+    SocketsCount,
+    /// Notify the other servers that this server is alive, with its number of sockets.
+    Heartbeat(u32),
+    /// Notify the other servers that this server just started and ask them to send a heartbeat back.
+    InitHeartbeat(u32),
 }
 impl RequestTypeOut<'_> {
     fn to_u8(&self) -> u8 {
@@ -36,10 +47,15 @@ impl RequestTypeOut<'_> {
             Self::AddSockets(_) => 4,
             Self::DelSockets(_) => 5,
             Self::FetchSockets => 6,
+            Self::Handoff(..) => 7,
+            Self::SocketsCount => 8,
+            Self::Heartbeat(_) => 9,
+            Self::InitHeartbeat(_) => 10,
         }
     }
 }
 
+/// The type of a request received from another server.
 #[derive(Debug)]
 pub enum RequestTypeIn {
     /// Broadcast a packet to matching sockets.
@@ -56,20 +72,77 @@ pub enum RequestTypeIn {
     DelSockets(Vec<Room>),
     /// Fetch socket data.
     FetchSockets,
+    /// Handle a polling request forwarded for an engine.io session.
+    Handoff(Sid, HandoffRequest),
+    /// Count the matching sockets.
+    SocketsCount,
+    /// Notify the other servers that this server is alive, with its number of sockets.
+    Heartbeat(u32),
+    /// Notify the other servers that this server just started and ask them to send a heartbeat back.
+    InitHeartbeat(u32),
 }
 
+/// A polling request forwarded for an engine.io session.
+#[derive(Debug, Serialize, Deserialize)]
+struct RawHandoff {
+    sid: Sid,
+    /// The timeout in milliseconds of a poll request, `None` for a post request.
+    timeout: Option<u64>,
+    data: Bytes,
+    is_binary: bool,
+}
+impl RawHandoff {
+    fn new(sid: Sid, req: &HandoffRequest) -> Self {
+        match req {
+            HandoffRequest::Poll { timeout } => Self {
+                sid,
+                timeout: Some(timeout.as_millis() as u64),
+                data: Bytes::new(),
+                is_binary: false,
+            },
+            HandoffRequest::Post { data, is_binary } => Self {
+                sid,
+                timeout: None,
+                data: data.clone(),
+                is_binary: *is_binary,
+            },
+        }
+    }
+    fn into_request(self) -> RequestTypeIn {
+        let req = match self.timeout {
+            Some(timeout) => HandoffRequest::Poll {
+                timeout: Duration::from_millis(timeout),
+            },
+            None => HandoffRequest::Post {
+                data: self.data,
+                is_binary: self.is_binary,
+            },
+        };
+        RequestTypeIn::Handoff(self.sid, req)
+    }
+}
+
+/// A request sent to the other servers.
 #[derive(Debug, PartialEq)]
 pub struct RequestOut<'a> {
+    /// The uid of the server sending the request.
     pub node_id: Uid,
+    /// The id of the request, used to route the responses.
     pub id: Sid,
+    /// The sequence number of the request for its node, set when it is published.
+    pub seq: u64,
+    /// The type of the request.
     pub r#type: RequestTypeOut<'a>,
+    /// The options selecting the sockets targeted by the request.
     pub opts: &'a BroadcastOptions,
 }
 impl<'a> RequestOut<'a> {
+    /// Create a new request with a random id.
     pub fn new(node_id: Uid, r#type: RequestTypeOut<'a>, opts: &'a BroadcastOptions) -> Self {
         Self {
             node_id,
             id: Sid::new(),
+            seq: 0,
             r#type,
             opts,
         }
@@ -87,6 +160,9 @@ impl<'a> Serialize for RequestOut<'a> {
             packet: Option<&'a Packet>,
             rooms: Option<&'a Vec<Room>>,
             opts: &'a BroadcastOptions,
+            handoff: Option<RawHandoff>,
+            sockets: Option<u32>,
+            seq: u64,
         }
         let raw = RawRequest::<'a> {
             node_id: self.node_id,
@@ -101,16 +177,32 @@ impl<'a> Serialize for RequestOut<'a> {
                 _ => None,
             },
             opts: self.opts,
+            handoff: match &self.r#type {
+                RequestTypeOut::Handoff(sid, req) => Some(RawHandoff::new(*sid, req)),
+                _ => None,
+            },
+            sockets: match &self.r#type {
+                RequestTypeOut::Heartbeat(n) | RequestTypeOut::InitHeartbeat(n) => Some(*n),
+                _ => None,
+            },
+            seq: self.seq,
         };
         raw.serialize(serializer)
     }
 }
 
+/// A request received from another server.
 #[derive(Debug)]
 pub struct RequestIn {
+    /// The uid of the server that sent the request.
     pub node_id: Uid,
+    /// The id of the request, used to route the responses.
     pub id: Sid,
+    /// The sequence number of the request for its node, 0 if it was sent by a server of a previous version.
+    pub seq: u64,
+    /// The type of the request.
     pub r#type: RequestTypeIn,
+    /// The options selecting the sockets targeted by the request.
     pub opts: BroadcastOptions,
 }
 impl<'de> Deserialize<'de> for RequestIn {
@@ -123,6 +215,12 @@ impl<'de> Deserialize<'de> for RequestIn {
             packet: Option<Packet>,
             rooms: Option<Vec<Room>>,
             opts: BroadcastOptions,
+            handoff: Option<RawHandoff>,
+            // Not sent by the servers of previous versions.
+            #[serde(default)]
+            sockets: Option<u32>,
+            #[serde(default)]
+            seq: u64,
         }
         let raw = RawRequest::deserialize(deserializer)?;
         let err = |field| serde::de::Error::custom(format!("missing field: {}", field));
@@ -134,37 +232,57 @@ impl<'de> Deserialize<'de> for RequestIn {
             4 => RequestTypeIn::AddSockets(raw.rooms.ok_or(err("room"))?),
             5 => RequestTypeIn::DelSockets(raw.rooms.ok_or(err("room"))?),
             6 => RequestTypeIn::FetchSockets,
+            7 => raw.handoff.ok_or(err("handoff"))?.into_request(),
+            8 => RequestTypeIn::SocketsCount,
+            9 => RequestTypeIn::Heartbeat(raw.sockets.unwrap_or_default()),
+            10 => RequestTypeIn::InitHeartbeat(raw.sockets.unwrap_or_default()),
             _ => return Err(serde::de::Error::custom("invalid request type")),
         };
         Ok(Self {
             node_id: raw.node_id,
             id: raw.id,
+            seq: raw.seq,
             r#type,
             opts: raw.opts,
         })
     }
 }
 
+/// A response sent back to the server that emitted a request.
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct Response<D = ()> {
+    /// The uid of the server sending the response.
     pub node_id: Uid,
+    /// The type of the response.
     pub r#type: ResponseType<D>,
 }
 
+/// The type of a [`Response`].
 #[derive(Debug, PartialEq)]
 pub enum ResponseType<D = ()> {
+    /// An acknowledgement of a socket to a broadcast.
     BroadcastAck((Sid, Result<Value, D>)),
+    /// The number of acknowledgements that a server will send for a broadcast.
     BroadcastAckCount(u32),
+    /// The rooms of a server.
     AllRooms(HashSet<Room>),
+    /// The matching sockets of a server.
     FetchSockets(Vec<D>),
+    /// The response of a forwarded polling request, `None` if the session is not on this server.
+    Handoff(Option<HandoffResponse>),
+    /// The number of matching sockets of a server.
+    SocketsCount(u32),
 }
 impl<D> ResponseType<D> {
+    /// The code of the response type on the wire.
     pub fn to_u8(&self) -> u8 {
         match self {
             Self::BroadcastAck(_) => 0,
             Self::BroadcastAckCount(_) => 1,
             Self::AllRooms(_) => 2,
             Self::FetchSockets(_) => 3,
+            Self::Handoff(_) => 4,
+            Self::SocketsCount(_) => 5,
         }
     }
 }
@@ -175,6 +293,13 @@ impl<D: Serialize> Serialize for ResponseType<D> {
             Self::BroadcastAckCount(count) => (1, count).serialize(serializer),
             Self::AllRooms(rooms) => (2, rooms).serialize(serializer),
             Self::FetchSockets(sockets) => (3, sockets).serialize(serializer),
+            Self::Handoff(res) => {
+                let res = res
+                    .as_ref()
+                    .map(|res| (res.status.as_u16(), &res.data, res.is_binary));
+                (4, res).serialize(serializer)
+            }
+            Self::SocketsCount(count) => (5, count).serialize(serializer),
         }
     }
 }
@@ -199,6 +324,22 @@ impl<'de, D: Deserialize<'de>> Deserialize<'de> for ResponseType<D> {
                     1 => ResponseType::BroadcastAckCount(deser(&mut seq)?),
                     2 => ResponseType::AllRooms(deser(&mut seq)?),
                     3 => ResponseType::FetchSockets(deser(&mut seq)?),
+                    4 => {
+                        let res: Option<(u16, Bytes, bool)> = deser(&mut seq)?;
+                        let res = res
+                            .map(|(status, data, is_binary)| {
+                                let status = http::StatusCode::from_u16(status)
+                                    .map_err(serde::de::Error::custom)?;
+                                Ok(HandoffResponse {
+                                    status,
+                                    data,
+                                    is_binary,
+                                })
+                            })
+                            .transpose()?;
+                        ResponseType::Handoff(res)
+                    }
+                    5 => ResponseType::SocketsCount(deser(&mut seq)?),
                     _ => return Err(serde::de::Error::custom("invalid response type")),
                 };
                 Ok(el)
@@ -209,18 +350,35 @@ impl<'de, D: Deserialize<'de>> Deserialize<'de> for ResponseType<D> {
     }
 }
 impl<D> Response<D> {
+    /// Returns the rooms of an [`AllRooms`](ResponseType::AllRooms) response.
     pub fn into_rooms(self) -> Option<HashSet<Room>> {
         match self.r#type {
             ResponseType::AllRooms(rooms) => Some(rooms),
             _ => None,
         }
     }
+    /// Returns the sockets of a [`FetchSockets`](ResponseType::FetchSockets) response.
     pub fn into_fetch_sockets(self) -> Option<Vec<D>> {
         match self.r#type {
             ResponseType::FetchSockets(sockets) => Some(sockets),
             _ => None,
         }
     }
+    /// Returns the response of a [`Handoff`](ResponseType::Handoff) response,
+    /// `None` if the session was not found.
+    pub fn into_handoff(self) -> Option<HandoffResponse> {
+        match self.r#type {
+            ResponseType::Handoff(res) => res,
+            _ => None,
+        }
+    }
+    /// Returns the count of a [`SocketsCount`](ResponseType::SocketsCount) response.
+    pub fn into_sockets_count(self) -> Option<u32> {
+        match self.r#type {
+            ResponseType::SocketsCount(count) => Some(count),
+            _ => None,
+        }
+    }
 }
 
 /// Extract the request id from a data encoded as `[Sid, ...]`
@@ -247,6 +405,7 @@ mod tests {
             Self {
                 node_id: req.node_id,
                 id: req.id,
+                seq: req.seq,
                 opts: &req.opts,
                 r#type: match &req.r#type {
                     RequestTypeIn::Broadcast(p) => RequestTypeOut::Broadcast(p),
@@ -256,6 +415,10 @@ mod tests {
                     RequestTypeIn::AddSockets(r) => RequestTypeOut::AddSockets(r),
                     RequestTypeIn::DelSockets(r) => RequestTypeOut::DelSockets(r),
                     RequestTypeIn::FetchSockets => RequestTypeOut::FetchSockets,
+                    RequestTypeIn::Handoff(sid, req) => RequestTypeOut::Handoff(*sid, req),
+                    RequestTypeIn::SocketsCount => RequestTypeOut::SocketsCount,
+                    RequestTypeIn::Heartbeat(n) => RequestTypeOut::Heartbeat(*n),
+                    RequestTypeIn::InitHeartbeat(n) => RequestTypeOut::InitHeartbeat(*n),
                 },
             }
         }
@@ -306,6 +469,13 @@ mod tests {
         assert_request_serde(req);
     }
 
+    #[test]
+    fn request_sockets_count_serde() {
+        let opts = BroadcastOptions::new(Sid::new());
+        let req = RequestOut::new(Uid::new(), RequestTypeOut::SocketsCount, &opts);
+        assert_request_serde(req);
+    }
+
     #[test]
     fn request_fetch_sockets_serde() {
         let opts = BroadcastOptions::new(Sid::new());
@@ -313,6 +483,48 @@ mod tests {
         assert_request_serde(req);
     }
 
+    #[test]
+    fn request_heartbeat_serde() {
+        let opts = BroadcastOptions::default();
+        let req = RequestOut::new(Uid::new(), RequestTypeOut::Heartbeat(3), &opts);
+        assert_request_serde(req);
+        let req = RequestOut::new(Uid::new(), RequestTypeOut::InitHeartbeat(0), &opts);
+        assert_request_serde(req);
+    }
+
+    #[test]
+    fn request_seq_serde() {
+        let opts = BroadcastOptions::default();
+        let mut req = RequestOut::new(Uid::new(), RequestTypeOut::AllRooms, &opts);
+        req.seq = 42;
+        assert_request_serde(req);
+    }
+
+    #[test]
+    fn request_handoff_serde() {
+        let opts = BroadcastOptions::default();
+        let poll = HandoffRequest::Poll {
+            timeout: Duration::from_millis(2500),
+        };
+        let req = RequestOut::new(
+            Uid::new(),
+            RequestTypeOut::Handoff(Sid::new(), &poll),
+            &opts,
+        );
+        assert_request_serde(req);
+
+        let post = HandoffRequest::Post {
+            data: Bytes::from_static(b"4hello"),
+            is_binary: false,
+        };
+        let req = RequestOut::new(
+            Uid::new(),
+            RequestTypeOut::Handoff(Sid::new(), &post),
+            &opts,
+        );
+        assert_request_serde(req);
+    }
+
     #[test]
     fn response_serde_broadcast_ack() {
         let res = Response {
@@ -361,6 +573,28 @@ mod tests {
         assert_eq!(res, deserialized);
     }
 
+    #[test]
+    fn response_serde_handoff() {
+        let res = Response {
+            node_id: Uid::new(),
+            r#type: ResponseType::Handoff(Some(HandoffResponse::ok("4hello", false))),
+        };
+        let serialized = rmp_serde::to_vec(&res).unwrap();
+        let deserialized: Response = rmp_serde::from_slice(&serialized).unwrap();
+        assert_eq!(res, deserialized);
+    }
+
+    #[test]
+    fn response_serde_sockets_count() {
+        let res = Response {
+            node_id: Uid::new(),
+            r#type: ResponseType::SocketsCount(42),
+        };
+        let serialized = rmp_serde::to_vec(&res).unwrap();
+        let deserialized: Response = rmp_serde::from_slice(&serialized).unwrap();
+        assert_eq!(res, deserialized);
+    }
+
     #[test]
     fn read_req_id() {
         let sid = Sid::new();
@@ -1,3 +1,4 @@
+//! The streams of responses received from the remote servers for a request.
 use std::{
     fmt,
     pin::Pin,
@@ -10,14 +11,13 @@ use futures_core::{FusedStream, Stream};
 use futures_util::{stream::TakeUntil, StreamExt};
 use pin_project_lite::pin_project;
 use serde::de::DeserializeOwned;
-use socketioxide_core::{adapter::AckStreamItem, Sid};
 use tokio::time;
 
-use crate::{
-    drivers::MessageStream,
+use super::{
     request::{Response, ResponseType},
-    ResponseHandlers,
+    MessageStream, ResponseHandlers,
 };
+use crate::{adapter::AckStreamItem, Sid};
 
 pin_project! {
     /// A stream of acknowledgement messages received from the local and remote servers.
@@ -41,6 +41,9 @@ pin_project! {
 }
 
 impl<S> AckStream<S> {
+    /// Create a new ack stream merging the `local` acks with the responses received on `remote`
+    /// from `serv_cnt` servers until the `timeout` is reached.
+    /// The handler of the request is removed from the `handlers` when the stream is dropped.
     pub fn new(
         local: S,
         remote: MessageStream<Vec<u8>>,
@@ -59,6 +62,7 @@ impl<S> AckStream<S> {
             serv_cnt,
         }
     }
+    /// Create a new ack stream that only yields the `local` acks.
     pub fn new_local(local: S) -> Self {
         let handlers = Arc::new(Mutex::new(ResponseHandlers::new()));
         let remote = MessageStream::new_empty().take_until(time::sleep(Duration::ZERO));
@@ -200,6 +204,7 @@ pin_project! {
     }
 }
 impl<S> DropStream<S> {
+    /// Create a new stream that removes the `req_id` handler from the `handlers` when dropped.
     pub fn new(stream: S, handlers: Arc<Mutex<ResponseHandlers>>, req_id: Sid) -> Self {
         Self {
             stream,
@@ -220,3 +225,101 @@ impl<S: FusedStream> FusedStream for DropStream<S> {
         self.stream.is_terminated()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures_util::stream::{self, FusedStream, StreamExt};
+
+    use super::*;
+    use crate::{Str, Uid, Value};
+
+    fn new_stub_ack_stream(
+        remote: MessageStream<Vec<u8>>,
+        timeout: Duration,
+    ) -> AckStream<stream::Empty<AckStreamItem<()>>> {
+        AckStream::new(
+            stream::empty::<AckStreamItem<()>>(),
+            remote,
+            timeout,
+            2,
+            Sid::new(),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+    }
+
+    //TODO: test weird behaviours, packets out of orders, etc
+    #[tokio::test]
+    async fn ack_stream() {
+        let (tx, rx) = tokio::sync::mpsc::channel(255);
+        let remote = MessageStream::new(rx);
+        let stream = new_stub_ack_stream(remote, Duration::from_secs(10));
+        let node_id = Uid::new();
+        let req_id = Sid::new();
+
+        // The two servers will send 2 acks each.
+        let ack_cnt_res = Response::<()> {
+            node_id,
+            r#type: ResponseType::BroadcastAckCount(2),
+        };
+        tx.try_send(rmp_serde::to_vec(&(req_id, &ack_cnt_res)).unwrap())
+            .unwrap();
+        tx.try_send(rmp_serde::to_vec(&(req_id, &ack_cnt_res)).unwrap())
+            .unwrap();
+
+        let ack_res = Response::<String> {
+            node_id,
+            r#type: ResponseType::BroadcastAck((Sid::new(), Ok(Value::Str(Str::from(""), None)))),
+        };
+        for _ in 0..4 {
+            tx.try_send(rmp_serde::to_vec(&(req_id, &ack_res)).unwrap())
+                .unwrap();
+        }
+        futures_util::pin_mut!(stream);
+        for _ in 0..4 {
+            assert!(stream.next().await.is_some());
+        }
+        assert!(stream.is_terminated());
+    }
+
+    #[tokio::test]
+    async fn ack_stream_timeout() {
+        let (tx, rx) = tokio::sync::mpsc::channel(255);
+        let remote = MessageStream::new(rx);
+        let stream = new_stub_ack_stream(remote, Duration::from_millis(50));
+        let node_id = Uid::new();
+        let req_id = Sid::new();
+        // There will be only one ack count and then the stream will timeout.
+        let ack_cnt_res = Response::<()> {
+            node_id,
+            r#type: ResponseType::BroadcastAckCount(2),
+        };
+        tx.try_send(rmp_serde::to_vec(&(req_id, ack_cnt_res)).unwrap())
+            .unwrap();
+
+        futures_util::pin_mut!(stream);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(stream.next().await.is_none());
+        assert!(stream.is_terminated());
+    }
+
+    #[tokio::test]
+    async fn ack_stream_drop() {
+        let (tx, rx) = tokio::sync::mpsc::channel(255);
+        let remote = MessageStream::new(rx);
+        let handlers = Arc::new(Mutex::new(HashMap::new()));
+        let id = Sid::new();
+        handlers.lock().unwrap().insert(id, tx);
+        let stream = AckStream::new(
+            stream::empty::<AckStreamItem<()>>(),
+            remote,
+            Duration::from_secs(10),
+            2,
+            id,
+            handlers.clone(),
+        );
+        drop(stream);
+        assert!(handlers.lock().unwrap().is_empty(),);
+    }
+}
@@ -0,0 +1,40 @@
+use std::future::Future;
+
+/// A driver implementation for the [rdkafka](docs.rs/rdkafka) client.
+#[cfg(feature = "rdkafka")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rdkafka")))]
+pub mod rdkafka;
+
+pub use socketioxide_core::remote::MessageStream;
+
+/// The driver trait can be used to support different Kafka clients.
+/// It must share handlers/connection between its clones.
+pub trait Driver: Clone + Send + Sync + 'static {
+    /// The error type for the driver.
+    type Error: std::error::Error + Send + 'static;
+
+    /// Subscribe to a topic with the given consumer group, it will return a stream of message payloads.
+    ///
+    /// Each server uses its own consumer group so that it receives all the messages of the topic,
+    /// including the ones it produced itself. The size parameter is the buffer size of the stream.
+    fn subscribe(
+        &self,
+        topic: &str,
+        group_id: &str,
+        size: usize,
+    ) -> impl Future<Output = Result<MessageStream<Vec<u8>>, Self::Error>> + Send;
+
+    /// Unsubscribe from a topic.
+    fn unsubscribe(&self, topic: &str) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Produce a message to a topic.
+    ///
+    /// The key is the uid of the server producing the message. It must be used as the message key
+    /// so that all the messages of a server are kept in order.
+    fn publish(
+        &self,
+        topic: &str,
+        key: &str,
+        payload: Vec<u8>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
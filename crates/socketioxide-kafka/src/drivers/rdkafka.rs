@@ -0,0 +1,110 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use rdkafka::{
+    config::ClientConfig,
+    consumer::{Consumer, StreamConsumer},
+    error::KafkaError,
+    message::Message,
+    producer::{FutureProducer, FutureRecord},
+    util::Timeout,
+};
+use tokio::{sync::mpsc, task::AbortHandle};
+
+use super::{Driver, MessageStream};
+
+pub use rdkafka as rdkafka_client;
+
+/// A driver implementation for the [rdkafka](docs.rs/rdkafka) client.
+///
+/// All the messages are produced with a single shared producer.
+/// Each subscription creates a consumer with the given group id from the client config.
+/// Its messages are piped to the message stream by a dedicated task.
+#[derive(Clone)]
+pub struct RdKafkaDriver {
+    config: ClientConfig,
+    producer: FutureProducer,
+    subscriptions: Arc<Mutex<HashMap<String, AbortHandle>>>,
+}
+
+impl std::fmt::Debug for RdKafkaDriver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RdKafkaDriver")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl RdKafkaDriver {
+    /// Create a new rdkafka driver from a client config.
+    ///
+    /// The config must at least contain the `bootstrap.servers` property.
+    /// It is used to create the producer and the consumers, the `group.id` property is overridden
+    /// for each consumer.
+    pub fn new(config: ClientConfig) -> Result<Self, KafkaError> {
+        let producer = config.create()?;
+        Ok(Self {
+            config,
+            producer,
+            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+}
+
+impl Driver for RdKafkaDriver {
+    type Error = KafkaError;
+
+    async fn subscribe(
+        &self,
+        topic: &str,
+        group_id: &str,
+        size: usize,
+    ) -> Result<MessageStream<Vec<u8>>, Self::Error> {
+        let consumer: StreamConsumer = self.config.clone().set("group.id", group_id).create()?;
+        consumer.subscribe(&[topic])?;
+
+        let (tx, rx) = mpsc::channel(size);
+        let task = tokio::spawn(async move {
+            loop {
+                match consumer.recv().await {
+                    Ok(msg) => {
+                        let Some(payload) = msg.payload() else {
+                            continue;
+                        };
+                        if let Err(e) = tx.try_send(payload.to_vec()) {
+                            tracing::warn!("kafka consumer channel full {e}");
+                        }
+                    }
+                    Err(e) => tracing::warn!("kafka consumer error: {e}"),
+                }
+            }
+        });
+        let prev = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .insert(topic.to_string(), task.abort_handle());
+        if let Some(prev) = prev {
+            prev.abort();
+        }
+        Ok(MessageStream::new(rx))
+    }
+
+    async fn unsubscribe(&self, topic: &str) -> Result<(), Self::Error> {
+        if let Some(task) = self.subscriptions.lock().unwrap().remove(topic) {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    async fn publish(&self, topic: &str, key: &str, payload: Vec<u8>) -> Result<(), Self::Error> {
+        let record = FutureRecord::to(topic).key(key).payload(&payload);
+        self.producer
+            .send(record, Timeout::Never)
+            .await
+            .map_err(|(err, _)| err)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+//! The envelope of the requests and responses exchanged through the namespace topic.
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use socketioxide_core::Uid;
+
+/// The kind of an [`Item`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemKind {
+    /// A request sent to one or all the servers.
+    Request,
+    /// A response sent back to the server that emitted a request.
+    Response,
+}
+
+/// A message exchanged between the servers through the namespace topic.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    /// The uid of the server that produced the item.
+    pub uid: Uid,
+    /// The uid of the server targeted by the item, `None` if it targets all the servers.
+    pub target: Option<Uid>,
+    /// The kind of the item.
+    pub kind: ItemKind,
+    /// The time when the item was produced, in milliseconds since the unix epoch.
+    pub timestamp: u64,
+    /// The msgpack encoded request or response.
+    pub data: Bytes,
+}
@@ -0,0 +1,535 @@
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![warn(
+    clippy::all,
+    clippy::todo,
+    clippy::empty_enums,
+    clippy::mem_forget,
+    clippy::unused_self,
+    clippy::filter_map_next,
+    clippy::needless_continue,
+    clippy::needless_borrow,
+    clippy::match_wildcard_for_single_variants,
+    clippy::if_let_mutex,
+    clippy::await_holding_lock,
+    clippy::imprecise_flops,
+    clippy::suboptimal_flops,
+    clippy::lossy_float_literal,
+    clippy::rest_pat_in_fully_bound_structs,
+    clippy::fn_params_excessive_bools,
+    clippy::exit,
+    clippy::inefficient_to_string,
+    clippy::linkedlist,
+    clippy::macro_use_imports,
+    clippy::option_option,
+    clippy::verbose_file_reads,
+    clippy::unnested_or_patterns,
+    rust_2018_idioms,
+    future_incompatible,
+    nonstandard_style,
+    missing_docs
+)]
+
+//! # A Kafka adapter implementation for the socketioxide crate.
+//! The adapter is used to communicate with other nodes of the same application.
+//! This allows to broadcast messages to sockets connected on other servers,
+//! to get the list of rooms, to add or remove sockets from rooms, etc.
+//!
+//! To achieve this, the adapter produces and consumes messages on a [Kafka](https://kafka.apache.org/) topic
+//! per namespace. Each server consumes the topic with its own consumer group so that every message
+//! is fanned out to all the servers. Because Kafka retains the messages, a server using a stable
+//! consumer group (see [`KafkaAdapterConfig::with_group_id`]) replays the broadcasts it missed when it restarts.
+//!
+//! A message may be consumed twice, for example when a server restarts before committing its offsets.
+//! With the [`dedup`](KafkaAdapterConfig::dedup) option, each request is sent with a sequence number
+//! incremented by its server, and the requests already received are dropped.
+//!
+//! The [`Driver`] abstraction allows the use of any Kafka client.
+//! One implementation is provided:
+//! * [`RdKafkaDriver`](crate::drivers::rdkafka::RdKafkaDriver) for the [`rdkafka`] crate.
+//!
+//! You can also implement your own driver by implementing the [`Driver`] trait.
+//!
+//! ## Example with the [`rdkafka`] driver
+//! ```rust
+//! # use socketioxide::{SocketIo, extract::{SocketRef, Data}, adapter::Adapter};
+//! # use socketioxide_kafka::{KafkaAdapterCtr, KafkaAdapter, drivers::rdkafka::rdkafka_client::ClientConfig};
+//! # async fn doc_main() -> Result<(), Box<dyn std::error::Error>> {
+//! async fn on_connect<A: Adapter>(socket: SocketRef<A>) {
+//!     socket.join("room1");
+//!     socket.on("event", on_event);
+//!     let _ = socket.broadcast().emit("hello", "world").await.ok();
+//! }
+//! async fn on_event<A: Adapter>(socket: SocketRef<A>, Data(data): Data<String>) {}
+//!
+//! let mut config = ClientConfig::new();
+//! config.set("bootstrap.servers", "127.0.0.1:9092");
+//! let adapter = KafkaAdapterCtr::new_with_rdkafka(config)?;
+//! let (layer, io) = SocketIo::builder()
+//!     .with_adapter::<KafkaAdapter<_>>(adapter)
+//!     .build_layer();
+//! Ok(())
+//! # }
+//! ```
+//!
+//! ## How does it work?
+//!
+//! An adapter is created for each created namespace and it takes a corresponding [`CoreLocalAdapter`](socketioxide_core::adapter::CoreLocalAdapter).
+//! The `CoreLocalAdapter` allows to manage the local rooms and local sockets. The default `LocalAdapter`
+//! is simply a wrapper around this `CoreLocalAdapter`.
+//!
+//! The adapter is then initialized with the [`KafkaAdapter::init`](RemoteAdapter#method.init) method.
+//! This will subscribe to the namespace topic: `"{prefix}"` for the main namespace and `"{prefix}.{path}"`
+//! for the others, where the `/` of the path are replaced by `.` (e.g. `socket.io.admin.users` for `/admin/users`).
+//! The topics must exist or the brokers must be configured to create them automatically.
+//!
+//! Each message produced to this topic contains the uid of the sender, the uid of the targeted server
+//! if any and a msgpack encoded request or response. Responses will be always in the form `[req_id, data]`.
+//! This will allow the adapter to extract the request id and route the response to the approriate stream
+//! before deserializing the data.
+//!
+//! There are 10 types of requests:
+//! * Broadcast a packet to all the matching sockets.
+//! * Broadcast a packet to all the matching sockets and wait for a stream of acks.
+//! * Disconnect matching sockets.
+//! * Get all the rooms.
+//! * Add matching sockets to rooms.
+//! * Remove matching sockets to rooms.
+//! * Fetch all the remote sockets matching the options.
+//! * Handle a polling request for an engine.io session open on another server
+//!   (only used when the session handoff is enabled on the socket.io server).
+//! * Heartbeat
+//! * Initial heartbeat. When receiving a initial heartbeat all other servers reply a heartbeat immediately.
+//!
+//! When replaying old messages, only the requests that don't expect a response (broadcasts,
+//! disconnections and room changes) are handled. The other requests, older than the
+//! [`request_timeout`](KafkaAdapterConfig::request_timeout), are ignored.
+//!
+//! Kafka doesn't provide a simple way to count the consumers of a topic.
+//! Therefore each server emits a heartbeat at a regular interval and keeps track of the other servers
+//! that sent a heartbeat recently. This count is used to know how many responses to expect.
+//!
+//! For ack streams, the adapter will first send a `BroadcastAckCount` response to the server that sent the request,
+//! and then send the acks as they are received (more details in [`KafkaAdapter::broadcast_with_ack`](RemoteAdapter#method.broadcast_with_ack) fn).
+//!
+//! On the other side, each time an action has to be performed on the local server, the adapter will
+//! first broadcast a request to all the servers and then perform the action locally.
+
+use std::{
+    borrow::Cow,
+    fmt, future,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use drivers::Driver;
+use futures_core::stream::BoxStream;
+use futures_util::StreamExt;
+use item::{Item, ItemKind};
+use socketioxide_core::{
+    remote::adapter::{self, Incoming, RemoteAdapter, RemoteOptions, Target, Transport},
+    Uid,
+};
+
+/// Drivers are an abstraction over the Kafka client used by the adapter.
+/// You can use the provided implementation or implement your own.
+pub mod drivers;
+
+mod item;
+
+/// Represent any error that might happen when using this adapter.
+pub type Error<D> = adapter::Error<<D as Driver>::Error>;
+
+/// The configuration of the [`KafkaAdapter`].
+#[derive(Debug, Clone)]
+pub struct KafkaAdapterConfig {
+    /// The heartbeat timeout duration. If a remote node does not respond within this duration,
+    /// it will be considered disconnected. Default is 10 seconds.
+    pub hb_timeout: Duration,
+    /// The heartbeat interval duration. The current node will send a heartbeat to the
+    /// other nodes at this interval. Default is 5 seconds.
+    pub hb_interval: Duration,
+    /// The request timeout. It is mainly used when expecting response such as when using
+    /// `broadcast_with_ack` or `rooms`. Default is 5 seconds.
+    pub request_timeout: Duration,
+
+    /// The prefix used for the topics. Default is "socket.io".
+    pub prefix: Cow<'static, str>,
+
+    /// The consumer group used by this server. Default is `"{prefix}-{uid}"`,
+    /// a new group for each server start.
+    pub group_id: Option<Cow<'static, str>>,
+
+    /// The channel size used to receive ack responses. Default is 255.
+    ///
+    /// If you have a lot of servers/sockets and that you may miss acknowledgement because they arrive faster
+    /// than you poll them with the returned stream, you might want to increase this value.
+    pub ack_response_buffer: usize,
+
+    /// The channel size used to receive messages. Default is 1024.
+    ///
+    /// If your server is under heavy load, you might want to increase this value.
+    pub stream_buffer: usize,
+
+    /// Whether the requests consumed twice are dropped. Default is false.
+    ///
+    /// Each request is sent with a sequence number incremented by its server, so that the requests
+    /// received twice are ignored. It should be enabled when using a stable
+    /// [`group_id`](KafkaAdapterConfig::group_id), as replayed messages may have already been handled.
+    pub dedup: bool,
+
+    /// The number of sequence numbers remembered for each server to detect the duplicated requests.
+    /// Default is 1024.
+    ///
+    /// The requests of a server received after more than this number of its newer requests
+    /// are considered as duplicates and dropped.
+    pub dedup_window: u64,
+}
+impl KafkaAdapterConfig {
+    /// Create a new config.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Set the heartbeat timeout duration. Default is 10 seconds.
+    ///
+    /// If a remote node does not send a heartbeat within this duration, it will be considered disconnected.
+    pub fn with_hb_timeout(mut self, timeout: Duration) -> Self {
+        self.hb_timeout = timeout;
+        self
+    }
+
+    /// Set the heartbeat interval duration. Default is 5 seconds.
+    pub fn with_hb_interval(mut self, interval: Duration) -> Self {
+        self.hb_interval = interval;
+        self
+    }
+
+    /// Set the request timeout. Default is 5 seconds.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Set the prefix used for the topics. Default is "socket.io".
+    pub fn with_prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set a stable consumer group for this server. By default a new group `"{prefix}-{uid}"`
+    /// is used each time the server starts, so it only receives the messages produced after it started.
+    ///
+    /// With a stable group, a restarted server resumes from its last committed offsets and replays
+    /// the broadcasts it missed. Each server must have its own group, otherwise the messages
+    /// would be split between the servers sharing a group.
+    pub fn with_group_id(mut self, group_id: impl Into<Cow<'static, str>>) -> Self {
+        self.group_id = Some(group_id.into());
+        self
+    }
+
+    /// Set the channel size used to send ack responses. Default is 255.
+    ///
+    /// If you have a lot of servers/sockets and that you may miss acknowledgement because they arrive faster
+    /// than you poll them with the returned stream, you might want to increase this value.
+    pub fn with_ack_response_buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "buffer size must be greater than 0");
+        self.ack_response_buffer = buffer;
+        self
+    }
+
+    /// Set the channel size used to receive items. Default is 1024.
+    ///
+    /// If your server is under heavy load, you might want to increase this value.
+    pub fn with_stream_buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "buffer size must be greater than 0");
+        self.stream_buffer = buffer;
+        self
+    }
+
+    /// Enable or disable the deduplication of the requests consumed twice. Default is false.
+    ///
+    /// It should be enabled when using a stable [`group_id`](KafkaAdapterConfig::with_group_id).
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Set the number of sequence numbers remembered for each server to detect the duplicated requests.
+    /// Default is 1024.
+    pub fn with_dedup_window(mut self, window: u64) -> Self {
+        assert!(window > 0, "dedup window must be greater than 0");
+        self.dedup_window = window;
+        self
+    }
+}
+
+impl Default for KafkaAdapterConfig {
+    fn default() -> Self {
+        Self {
+            hb_timeout: Duration::from_secs(10),
+            hb_interval: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(5),
+            prefix: Cow::Borrowed("socket.io"),
+            group_id: None,
+            ack_response_buffer: 255,
+            stream_buffer: 1024,
+            dedup: false,
+            dedup_window: 1024,
+        }
+    }
+}
+
+/// The adapter constructor. For each namespace you define, a new adapter instance is created
+/// from this constructor.
+#[derive(Debug)]
+pub struct KafkaAdapterCtr<D> {
+    driver: D,
+    config: KafkaAdapterConfig,
+}
+
+#[cfg(feature = "rdkafka")]
+impl KafkaAdapterCtr<drivers::rdkafka::RdKafkaDriver> {
+    /// Create a new adapter constructor with the [`rdkafka`] driver and a default config.
+    ///
+    /// The client config must at least contain the `bootstrap.servers` property.
+    #[cfg_attr(docsrs, doc(cfg(feature = "rdkafka")))]
+    pub fn new_with_rdkafka(
+        config: rdkafka::ClientConfig,
+    ) -> Result<Self, rdkafka::error::KafkaError> {
+        Self::new_with_rdkafka_config(config, KafkaAdapterConfig::default())
+    }
+    /// Create a new adapter constructor with the [`rdkafka`] driver and a custom config.
+    #[cfg_attr(docsrs, doc(cfg(feature = "rdkafka")))]
+    pub fn new_with_rdkafka_config(
+        client_config: rdkafka::ClientConfig,
+        config: KafkaAdapterConfig,
+    ) -> Result<Self, rdkafka::error::KafkaError> {
+        let driver = drivers::rdkafka::RdKafkaDriver::new(client_config)?;
+        Ok(Self::new_with_driver(driver, config))
+    }
+}
+impl<D: Driver> KafkaAdapterCtr<D> {
+    /// Create a new adapter constructor with a custom Kafka driver and a config.
+    ///
+    /// You can implement your own driver by implementing the [`Driver`] trait with any Kafka client.
+    /// Check the [`drivers`] module for more information.
+    pub fn new_with_driver(driver: D, config: KafkaAdapterConfig) -> KafkaAdapterCtr<D> {
+        KafkaAdapterCtr { driver, config }
+    }
+}
+
+/// The kafka adapter with the rdkafka driver.
+#[cfg_attr(docsrs, doc(cfg(feature = "rdkafka")))]
+#[cfg(feature = "rdkafka")]
+pub type KafkaAdapter<E> = CustomKafkaAdapter<E, drivers::rdkafka::RdKafkaDriver>;
+
+/// The kafka adapter implementation.
+/// It is generic over the [`Driver`] used to communicate with the Kafka brokers.
+/// And over the [`SocketEmitter`](socketioxide_core::adapter::SocketEmitter) used to communicate
+/// with the local server. This allows to avoid cyclic dependencies between the adapter,
+/// `socketioxide-core` and `socketioxide` crates.
+pub type CustomKafkaAdapter<E, D> = RemoteAdapter<E, KafkaTransport<D>>;
+
+/// The kafka [`Transport`] of the [`CustomKafkaAdapter`], created for each namespace.
+///
+/// All the servers of a namespace share a single topic. The messages older than the request timeout
+/// are replayed messages, and the servers are only counted with the heartbeats.
+pub struct KafkaTransport<D> {
+    /// The driver used by the adapter. This is used to communicate with the Kafka brokers.
+    /// All the kafka adapter instances share the same driver.
+    driver: D,
+    /// The configuration of the adapter.
+    config: KafkaAdapterConfig,
+    /// The options of the remote adapter, from the configuration.
+    opts: RemoteOptions,
+    /// The namespace path.
+    path: String,
+    /// A unique identifier for the adapter to identify itself in the Kafka topic.
+    uid: Uid,
+    /// The topic of the namespace shared by all the servers.
+    topic: String,
+}
+
+impl<D: Driver> Transport for KafkaTransport<D> {
+    type State = KafkaAdapterCtr<D>;
+    type Error = D::Error;
+    type InitError = InitError<D>;
+
+    fn new(state: &Self::State, path: &str, uid: Uid) -> Self {
+        let config = state.config.clone();
+        let opts = RemoteOptions {
+            request_timeout: config.request_timeout,
+            ack_response_buffer: config.ack_response_buffer,
+            hb_timeout: config.hb_timeout,
+            hb_interval: config.hb_interval,
+            dedup: config.dedup,
+            dedup_window: config.dedup_window,
+            ..Default::default()
+        };
+        Self {
+            driver: state.driver.clone(),
+            topic: topic_name(&config.prefix, path),
+            config,
+            opts,
+            path: path.to_string(),
+            uid,
+        }
+    }
+
+    fn options(&self) -> &RemoteOptions {
+        &self.opts
+    }
+
+    async fn init(&self) -> Result<BoxStream<'static, Incoming>, InitError<D>> {
+        if !is_valid_ns(&self.path) || self.topic.len() > MAX_TOPIC_LEN {
+            return Err(InitError::MalformedNamespace);
+        }
+        let group_id = match &self.config.group_id {
+            Some(group_id) => group_id.to_string(),
+            None => format!("{}-{}", self.config.prefix, self.uid),
+        };
+        let stream = self
+            .driver
+            .subscribe(&self.topic, &group_id, self.config.stream_buffer)
+            .await
+            .map_err(InitError::Driver)?;
+
+        let uid = self.uid;
+        let request_timeout = self.config.request_timeout.as_millis() as u64;
+        let stream = stream.filter_map(move |payload| {
+            let item: Item = match rmp_serde::from_slice(&payload) {
+                Ok(item) => item,
+                Err(e) => {
+                    tracing::warn!("error decoding item: {e}");
+                    return future::ready(None);
+                }
+            };
+            // Each server receives all the messages of the topic, including its own.
+            if item.uid == uid || item.target.is_some_and(|t| t != uid) {
+                return future::ready(None);
+            }
+            let age = now_millis().saturating_sub(item.timestamp);
+            let stale = age > request_timeout;
+            let incoming = match item.kind {
+                ItemKind::Request if stale => Some(Incoming::Replayed(item.data.into())),
+                ItemKind::Request => Some(Incoming::Request(item.data.into())),
+                ItemKind::Response if stale => {
+                    tracing::trace!(?age, "ignoring stale response");
+                    None
+                }
+                ItemKind::Response => Some(Incoming::Response(item.data.into())),
+            };
+            future::ready(incoming)
+        });
+        Ok(stream.boxed())
+    }
+
+    async fn close(&self) -> Result<(), D::Error> {
+        self.driver.unsubscribe(&self.topic).await
+    }
+
+    async fn send_req(&self, data: Vec<u8>, target: Target<'_>) -> Result<(), Error<D>> {
+        let target = match target {
+            Target::Server(uid) => Some(uid),
+            Target::All | Target::Room(_) => None,
+        };
+        self.publish(ItemKind::Request, target, data).await
+    }
+
+    async fn send_res(&self, data: Vec<u8>, target: Uid) -> Result<(), Error<D>> {
+        self.publish(ItemKind::Response, Some(target), data).await
+    }
+}
+
+/// Error that can happen when initializing the adapter.
+#[derive(thiserror::Error)]
+pub enum InitError<D: Driver> {
+    /// Driver error.
+    #[error("driver error: {0}")]
+    Driver(D::Error),
+    /// Malformed namespace path.
+    #[error(
+        "malformed namespace path, it must only contain ascii alphanumerics, '/', '_' and '-'"
+    )]
+    MalformedNamespace,
+}
+impl<D: Driver> fmt::Debug for InitError<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Driver(err) => fmt::Debug::fmt(err, f),
+            Self::MalformedNamespace => write!(f, "Malformed namespace path"),
+        }
+    }
+}
+/// The result of the init future.
+pub type InitRes<D> = adapter::InitRes<InitError<D>>;
+
+impl<D: Driver> KafkaTransport<D> {
+    /// Wrap the data in an [`Item`] and produce it to the topic, keyed by the uid of this server.
+    async fn publish(
+        &self,
+        kind: ItemKind,
+        target: Option<Uid>,
+        data: Vec<u8>,
+    ) -> Result<(), Error<D>> {
+        let item = Item {
+            uid: self.uid,
+            target,
+            kind,
+            timestamp: now_millis(),
+            data: data.into(),
+        };
+        let payload = rmp_serde::to_vec(&item)?;
+        self.driver
+            .publish(&self.topic, &self.uid.to_string(), payload)
+            .await
+            .map_err(adapter::Error::Driver)
+    }
+}
+
+/// Kafka topic names are limited to 249 characters.
+const MAX_TOPIC_LEN: usize = 249;
+
+/// Build the topic name of a namespace.
+fn topic_name(prefix: &str, path: &str) -> String {
+    match path.trim_start_matches('/') {
+        "" => prefix.to_string(),
+        path => format!("{}.{}", prefix, path.replace('/', ".")),
+    }
+}
+
+/// Checks if the namespace path can be converted to a valid topic name.
+/// The `.` are rejected to avoid collisions between `/a.b` and `/a/b`.
+fn is_valid_ns(path: &str) -> bool {
+    path.chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-'))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_name() {
+        assert_eq!(topic_name("socket.io", "/"), "socket.io");
+        assert_eq!(topic_name("socket.io", "/admin"), "socket.io.admin");
+        assert_eq!(
+            topic_name("socket.io", "/admin/users"),
+            "socket.io.admin.users"
+        );
+    }
+
+    #[test]
+    fn test_is_valid_ns() {
+        assert!(is_valid_ns("/"));
+        assert!(is_valid_ns("/admin/chat-1_2"));
+        assert!(!is_valid_ns("/admin.chat"));
+        assert!(!is_valid_ns("/admin chat"));
+        assert!(!is_valid_ns("/é"));
+    }
+}
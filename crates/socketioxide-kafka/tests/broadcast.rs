@@ -0,0 +1,31 @@
+use socketioxide::{adapter::Adapter, extract::SocketRef};
+use socketioxide_adapter_tests::{timeout_rcv, timeout_rcv_err};
+use socketioxide_kafka::KafkaAdapterConfig;
+mod fixture;
+
+#[tokio::test]
+pub async fn broadcast_dedup() {
+    async fn handler<A: Adapter>(socket: SocketRef<A>) {
+        // delay to ensure all socket/servers are connected
+        tokio::time::sleep(tokio::time::Duration::from_millis(1)).await;
+        socket.broadcast().emit("test", &2).await.unwrap();
+    }
+
+    let config = KafkaAdapterConfig::new().with_dedup(true);
+    let [io1, io2] = fixture::spawn_redelivering_servers(config);
+
+    io1.ns("/", handler).await.unwrap();
+    io2.ns("/", handler).await.unwrap();
+
+    let ((_tx1, mut rx1), (_tx2, mut rx2)) =
+        tokio::join!(io1.new_dummy_sock("/", ()), io2.new_dummy_sock("/", ()));
+
+    timeout_rcv!(&mut rx1); // Connect "/" packet
+    timeout_rcv!(&mut rx2); // Connect "/" packet
+
+    assert_eq!(timeout_rcv!(&mut rx1), r#"42["test",2]"#);
+    assert_eq!(timeout_rcv!(&mut rx2), r#"42["test",2]"#);
+
+    timeout_rcv_err!(&mut rx1);
+    timeout_rcv_err!(&mut rx2);
+}
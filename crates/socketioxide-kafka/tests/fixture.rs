@@ -0,0 +1,86 @@
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use socketioxide::SocketIo;
+use socketioxide_adapter_tests::stub::{self, StubAdapter, StubBus};
+use socketioxide_kafka::{
+    drivers::{Driver, MessageStream},
+    KafkaAdapterConfig, KafkaAdapterCtr, KafkaTransport,
+};
+
+/// The fixture of the common adapter test suite.
+pub struct StubFixture;
+impl stub::StubFixture for StubFixture {
+    type Transport = KafkaTransport<StubDriver>;
+    type Message = (String, Vec<u8>);
+    type Config = KafkaAdapterConfig;
+
+    fn state(
+        bus: StubBus<(String, Vec<u8>)>,
+        config: KafkaAdapterConfig,
+    ) -> KafkaAdapterCtr<StubDriver> {
+        let driver = StubDriver {
+            bus,
+            redeliver: false,
+        };
+        KafkaAdapterCtr::new_with_driver(driver, config)
+    }
+    fn with_request_timeout(config: KafkaAdapterConfig, timeout: Duration) -> KafkaAdapterConfig {
+        config.with_request_timeout(timeout)
+    }
+}
+
+/// Spawns a number of servers with a stub driver delivering each message twice for testing.
+pub fn spawn_redelivering_servers<const N: usize>(
+    config: KafkaAdapterConfig,
+) -> [SocketIo<StubAdapter<StubFixture>>; N] {
+    let driver = StubDriver {
+        bus: StubBus::default(),
+        redeliver: true,
+    };
+    [0; N].map(|_| {
+        let ctr = KafkaAdapterCtr::new_with_driver(driver.clone(), config.clone());
+        stub::spawn_server::<StubFixture>(ctr)
+    })
+}
+
+/// A driver that fans out the produced messages to all the consumers of their topic,
+/// including the producer, like kafka would do with a consumer group per server.
+///
+/// A redelivering driver delivers each message twice.
+#[derive(Debug, Clone)]
+pub struct StubDriver {
+    bus: StubBus<(String, Vec<u8>)>,
+    redeliver: bool,
+}
+
+impl Driver for StubDriver {
+    type Error = std::convert::Infallible;
+
+    async fn subscribe(
+        &self,
+        topic: &str,
+        _: &str,
+        _: usize,
+    ) -> Result<MessageStream<Vec<u8>>, Self::Error> {
+        let topic = topic.to_string();
+        let stream = self.bus.subscribe(move |(t, payload): &(String, Vec<u8>)| {
+            (*t == topic).then(|| payload.clone())
+        });
+        Ok(stream)
+    }
+
+    async fn unsubscribe(&self, _: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn publish(&self, topic: &str, _: &str, payload: Vec<u8>) -> Result<(), Self::Error> {
+        let msg = (topic.to_string(), payload);
+        if self.redeliver {
+            self.bus.publish(msg.clone());
+        }
+        self.bus.publish(msg);
+        Ok(())
+    }
+}
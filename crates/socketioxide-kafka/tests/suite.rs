@@ -0,0 +1,3 @@
+mod fixture;
+
+socketioxide_adapter_tests::test_suite!(fixture::StubFixture);
@@ -0,0 +1,205 @@
+#![warn(
+    clippy::all,
+    clippy::todo,
+    clippy::empty_enums,
+    clippy::mem_forget,
+    clippy::unused_self,
+    clippy::filter_map_next,
+    clippy::needless_continue,
+    clippy::needless_borrow,
+    clippy::match_wildcard_for_single_variants,
+    clippy::if_let_mutex,
+    clippy::await_holding_lock,
+    clippy::imprecise_flops,
+    clippy::suboptimal_flops,
+    clippy::lossy_float_literal,
+    clippy::rest_pat_in_fully_bound_structs,
+    clippy::fn_params_excessive_bools,
+    clippy::exit,
+    clippy::inefficient_to_string,
+    clippy::linkedlist,
+    clippy::macro_use_imports,
+    clippy::option_option,
+    clippy::verbose_file_reads,
+    clippy::unnested_or_patterns,
+    rust_2018_idioms,
+    future_incompatible,
+    nonstandard_style,
+    missing_docs
+)]
+
+//! Procedural macros for the [`socketioxide`](https://docs.rs/socketioxide) crate.
+//!
+//! They should not be used directly, they are re-exported by `socketioxide` with the `macros` feature flag.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields, LitStr};
+
+/// Derive the `SocketEvents` trait for an enum.
+///
+/// Each variant is mapped to an event. The event name is the variant name in `snake_case`,
+/// with acronyms kept together (`HTTPRequest` is mapped to `http_request`),
+/// unless it is renamed with the `#[event(rename = "...")]` attribute.
+///
+/// * Unit variants are events without any data.
+/// * Variants with a single field use it as the event data.
+/// * Variants with multiple fields are events with multiple arguments.
+///
+/// See the `socketioxide::typed` module for more details.
+#[proc_macro_derive(SocketEvents, attributes(event))]
+pub fn derive_socket_events(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "SocketEvents can only be derived for enums",
+        ));
+    };
+
+    if data.variants.is_empty() {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "SocketEvents can't be derived for an enum without variants",
+        ));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let private = quote!(::socketioxide::typed::__private);
+
+    let mut events = Vec::with_capacity(data.variants.len());
+    let mut event_arms = Vec::with_capacity(data.variants.len());
+    let mut encode_arms = Vec::with_capacity(data.variants.len());
+    let mut decode_arms = Vec::with_capacity(data.variants.len());
+
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let event = event_name(variant)?;
+        if events.iter().any(|e: &LitStr| e.value() == event.value()) {
+            return Err(syn::Error::new(event.span(), "duplicate event name"));
+        }
+
+        match &variant.fields {
+            Fields::Unit => {
+                event_arms.push(quote!(Self::#ident => #event));
+                encode_arms.push(quote! {
+                    Self::#ident => parser.encode_value(&[(); 0], Some(#event))
+                });
+                decode_arms.push(quote!(#event => Ok(Self::#ident)));
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                event_arms.push(quote!(Self::#ident(..) => #event));
+                encode_arms.push(quote! {
+                    Self::#ident(data) => parser.encode_value(data, Some(#event))
+                });
+                decode_arms.push(quote! {
+                    #event => parser.decode_value::<#ty>(value, true).map(Self::#ident)
+                });
+            }
+            Fields::Unnamed(fields) => {
+                let tys = fields.unnamed.iter().map(|f| &f.ty);
+                let args: Vec<Ident> = (0..fields.unnamed.len())
+                    .map(|i| format_ident!("arg{}", i))
+                    .collect();
+                event_arms.push(quote!(Self::#ident(..) => #event));
+                encode_arms.push(quote! {
+                    Self::#ident(#(#args),*) => parser.encode_value(&(#(#args),*), Some(#event))
+                });
+                decode_arms.push(quote! {
+                    #event => parser
+                        .decode_value::<(#(#tys),*)>(value, true)
+                        .map(|(#(#args),*)| Self::#ident(#(#args),*))
+                });
+            }
+            Fields::Named(fields) => {
+                return Err(syn::Error::new(
+                    fields.span(),
+                    "SocketEvents variants with named fields are not supported, use a struct as the variant data",
+                ));
+            }
+        }
+        events.push(event);
+    }
+
+    let unknown = quote! {
+        _ => Err(#private::ParserError::new(#private::UnknownEventError(event.to_string())))
+    };
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::socketioxide::typed::SocketEvents for #name #ty_generics #where_clause {
+            const EVENTS: &'static [&'static str] = &[#(#events),*];
+
+            fn event(&self) -> &'static str {
+                match self {
+                    #(#event_arms,)*
+                }
+            }
+
+            fn encode<P: #private::Parse>(
+                &self,
+                parser: P,
+            ) -> Result<#private::Value, #private::ParserError> {
+                match self {
+                    #(#encode_arms,)*
+                }
+            }
+
+            fn decode<P: #private::Parse>(
+                parser: P,
+                event: &str,
+                value: &mut #private::Value,
+            ) -> Result<Self, #private::ParserError> {
+                match event {
+                    #(#decode_arms,)*
+                    #unknown
+                }
+            }
+        }
+    })
+}
+
+/// Get the event name of a variant from the `#[event(rename = "...")]` attribute
+/// or from the `snake_case` variant name.
+fn event_name(variant: &syn::Variant) -> syn::Result<LitStr> {
+    let mut rename = None;
+    for attr in variant.attrs.iter().filter(|a| a.path().is_ident("event")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported event attribute, expected `rename`"))
+            }
+        })?;
+    }
+    Ok(rename
+        .unwrap_or_else(|| LitStr::new(&to_snake_case(&variant.ident.to_string()), variant.span())))
+}
+
+/// Convert a `PascalCase` name to `snake_case`, keeping acronyms together
+/// (e.g. `HTTPRequest` becomes `http_request`).
+fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if !prev.is_uppercase() || next_is_lower {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
@@ -0,0 +1,123 @@
+//! Tests for the `SocketEvents` derive macro
+use std::time::Duration;
+
+use engineioxide::Packet::*;
+use serde::{Deserialize, Serialize};
+use socketioxide::{
+    extract::{Event, SocketRef},
+    typed::SocketEvents,
+    SocketIo,
+};
+use socketioxide_core::{parser::Parse, Value};
+use socketioxide_parser_common::CommonParser;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Message {
+    user: String,
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, SocketEvents)]
+enum ChatEvent {
+    Chat(Message),
+    UserTyping(String, bool),
+    #[event(rename = "clear")]
+    ClearHistory,
+}
+
+#[derive(Debug, Clone, PartialEq, SocketEvents)]
+enum AcronymEvent {
+    HTTPRequest,
+    GetURL,
+    Utf8Text,
+}
+
+fn str_value(value: Value) -> String {
+    match value {
+        Value::Str(data, _) => data.to_string(),
+        Value::Bytes(_) => panic!("expected string value"),
+    }
+}
+
+#[test]
+fn event_names() {
+    assert_eq!(ChatEvent::EVENTS, &["chat", "user_typing", "clear"]);
+    assert_eq!(ChatEvent::ClearHistory.event(), "clear");
+    assert_eq!(
+        ChatEvent::UserTyping("foo".into(), true).event(),
+        "user_typing"
+    );
+    assert_eq!(
+        AcronymEvent::EVENTS,
+        &["http_request", "get_url", "utf8_text"]
+    );
+}
+
+#[test]
+fn encode_events() {
+    let parser = CommonParser;
+    let chat = ChatEvent::Chat(Message {
+        user: "foo".into(),
+        text: "hello".into(),
+    });
+    assert_eq!(
+        str_value(chat.encode(parser).unwrap()),
+        r#"["chat",{"user":"foo","text":"hello"}]"#
+    );
+    let typing = ChatEvent::UserTyping("foo".into(), true);
+    assert_eq!(
+        str_value(typing.encode(parser).unwrap()),
+        r#"["user_typing","foo",true]"#
+    );
+    let clear = ChatEvent::ClearHistory;
+    assert_eq!(str_value(clear.encode(parser).unwrap()), r#"["clear"]"#);
+}
+
+#[test]
+fn decode_events() {
+    let parser = CommonParser;
+    let events = [
+        ChatEvent::Chat(Message {
+            user: "foo".into(),
+            text: "hello".into(),
+        }),
+        ChatEvent::UserTyping("foo".into(), false),
+        ChatEvent::ClearHistory,
+    ];
+    for event in events {
+        let mut value = event.encode(parser).unwrap();
+        let name = parser.read_event(&value).unwrap().to_owned();
+        assert_eq!(ChatEvent::decode(parser, &name, &mut value).unwrap(), event);
+    }
+
+    let mut value = Value::Str(r#"["unknown",1]"#.into(), None);
+    let err = ChatEvent::decode(parser, "unknown", &mut value).unwrap_err();
+    assert_eq!(err.to_string(), "unknown event: unknown");
+}
+
+#[tokio::test]
+async fn typed_handler() {
+    let (_svc, io) = SocketIo::new_svc();
+    io.ns("/", |socket: SocketRef| {
+        socket.on_typed::<ChatEvent, _, _>(|socket: SocketRef, Event(event): Event<ChatEvent>| {
+            // Echo the event back with the clear event
+            socket.emit_typed(&event).unwrap();
+            socket.emit_typed(&ChatEvent::ClearHistory).unwrap();
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    srx.recv().await.unwrap(); // socket.io connect packet
+
+    let msg = r#"2["user_typing","foo",true]"#;
+    stx.send(Message(msg.into())).await.unwrap();
+    let recv = tokio::time::timeout(Duration::from_millis(200), srx.recv()).await;
+    assert_eq!(recv.unwrap().unwrap(), Message(msg.into()));
+    let recv = tokio::time::timeout(Duration::from_millis(200), srx.recv()).await;
+    assert_eq!(recv.unwrap().unwrap(), Message(r#"2["clear"]"#.into()));
+
+    // Events with an invalid payload are ignored
+    stx.send(Message(r#"2["chat",1]"#.into())).await.unwrap();
+    let recv = tokio::time::timeout(Duration::from_millis(50), srx.recv()).await;
+    assert!(recv.is_err());
+}
@@ -0,0 +1,58 @@
+use std::future::Future;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use socketioxide_core::Uid;
+
+/// A driver implementation for the [mongodb](docs.rs/mongodb) change stream backend.
+#[cfg(feature = "mongodb")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mongodb")))]
+pub mod mongodb;
+
+pub use socketioxide_core::remote::MessageStream;
+
+/// The kind of an [`Item`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemKind {
+    /// A request sent to one or all the servers.
+    Request,
+    /// A response sent back to the server that emitted a request.
+    Response,
+}
+
+/// A document exchanged between the servers through the collection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    /// The namespace of the adapter that emitted the item.
+    pub ns: String,
+    /// The uid of the server that emitted the item.
+    pub uid: Uid,
+    /// The uid of the server targeted by the item, `None` if it targets all the servers.
+    pub target: Option<Uid>,
+    /// The kind of the item.
+    pub kind: ItemKind,
+    /// The msgpack encoded request or response.
+    pub data: Bytes,
+}
+
+/// The driver trait can be used to support different MongoDB clients or change stream backends.
+/// It must share handlers/connection between its clones.
+pub trait Driver: Clone + Send + Sync + 'static {
+    /// The error type for the driver.
+    type Error: std::error::Error + Send + 'static;
+
+    /// Watch the items inserted for the namespace `ns`, it will return a stream of items.
+    ///
+    /// Only the items emitted by other servers and that target either all the servers or
+    /// the server `uid` must be returned. The size parameter is the buffer size of the stream.
+    fn watch(
+        &self,
+        uid: Uid,
+        ns: &str,
+        size: usize,
+    ) -> impl Future<Output = Result<MessageStream<Item>, Self::Error>> + Send;
+
+    /// Emit an item to the other servers.
+    fn emit(&self, item: &Item) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
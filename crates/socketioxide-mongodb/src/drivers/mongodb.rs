@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use mongodb::{
+    bson::{self, doc, Document},
+    error::ErrorKind,
+    options::IndexOptions,
+    Collection, Database, IndexModel,
+};
+use socketioxide_core::Uid;
+use tokio::sync::mpsc;
+
+use super::{Driver, Item, MessageStream};
+
+pub use mongodb as mongodb_client;
+
+/// The error code returned by MongoDB when creating a collection that already exists.
+const NAMESPACE_EXISTS: i32 = 48;
+
+/// How the items emitted by the adapter are removed from the collection.
+#[derive(Debug, Clone)]
+pub enum MessageExpirationMode {
+    /// Each item is removed after the given duration with a [TTL index](https://www.mongodb.com/docs/manual/core/index-ttl/)
+    /// on its `createdAt` field.
+    ///
+    /// The TTL monitor runs every 60 seconds, so items may stay a bit longer in the collection.
+    Ttl(Duration),
+    /// The items are stored in a [capped collection](https://www.mongodb.com/docs/manual/core/capped-collections/)
+    /// of the given size in bytes. The oldest items are removed when the collection is full.
+    ///
+    /// It has no effect if the collection already exists.
+    Capped(u64),
+}
+
+impl Default for MessageExpirationMode {
+    fn default() -> Self {
+        Self::Ttl(Duration::from_secs(60))
+    }
+}
+
+/// A driver implementation for the [mongodb](docs.rs/mongodb) change stream backend.
+///
+/// The items are inserted in a collection shared by all the servers and each server
+/// watches the inserted items with a [change stream](https://www.mongodb.com/docs/manual/changeStreams/).
+/// Change streams require a replica set or a sharded cluster.
+#[derive(Debug, Clone)]
+pub struct MongoDbDriver {
+    collec: Collection<Document>,
+}
+
+impl MongoDbDriver {
+    /// Create a new mongodb driver from a database and a collection name.
+    ///
+    /// The collection is set up with the given [`MessageExpirationMode`]
+    /// so that the emitted items don't accumulate in the database.
+    pub async fn new(
+        db: Database,
+        collection: &str,
+        mode: MessageExpirationMode,
+    ) -> Result<Self, mongodb::error::Error> {
+        match mode {
+            MessageExpirationMode::Ttl(ttl) => {
+                let options = IndexOptions::builder().expire_after(ttl).build();
+                let index = IndexModel::builder()
+                    .keys(doc! { "createdAt": 1 })
+                    .options(options)
+                    .build();
+                db.collection::<Document>(collection)
+                    .create_index(index)
+                    .await?;
+            }
+            MessageExpirationMode::Capped(size) => {
+                let res = db
+                    .create_collection(collection)
+                    .capped(true)
+                    .size(size)
+                    .await;
+                if let Err(e) = res {
+                    if !is_namespace_exists(&e) {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        let collec = db.collection(collection);
+        Ok(Self { collec })
+    }
+}
+
+fn is_namespace_exists(err: &mongodb::error::Error) -> bool {
+    matches!(*err.kind, ErrorKind::Command(ref err) if err.code == NAMESPACE_EXISTS)
+}
+
+impl Driver for MongoDbDriver {
+    type Error = mongodb::error::Error;
+
+    async fn watch(
+        &self,
+        uid: Uid,
+        ns: &str,
+        size: usize,
+    ) -> Result<MessageStream<Item>, Self::Error> {
+        let uid = bson::to_bson(&uid)?;
+        let pipeline = [doc! {
+            "$match": {
+                "operationType": "insert",
+                "fullDocument.ns": ns,
+                "fullDocument.uid": { "$ne": &uid },
+                "$or": [
+                    { "fullDocument.target": null },
+                    { "fullDocument.target": &uid },
+                ],
+            }
+        }];
+        let mut stream = self.collec.watch().pipeline(pipeline).await?;
+        let (tx, rx) = mpsc::channel(size);
+        tokio::spawn(async move {
+            while let Some(event) = stream.next().await {
+                let doc = match event {
+                    Ok(event) => event.full_document,
+                    Err(e) => {
+                        tracing::error!("error reading change stream: {e}");
+                        continue;
+                    }
+                };
+                match doc.map(bson::from_document::<Item>).transpose() {
+                    Ok(Some(item)) => {
+                        if let Err(e) = tx.try_send(item) {
+                            tracing::warn!("mongodb change stream channel full {e}");
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("error decoding item: {e}"),
+                }
+                if tx.is_closed() {
+                    break;
+                }
+            }
+        });
+        Ok(MessageStream::new(rx))
+    }
+
+    async fn emit(&self, item: &Item) -> Result<(), Self::Error> {
+        let mut doc = bson::to_document(item)?;
+        doc.insert("createdAt", bson::DateTime::now());
+        self.collec.insert_one(doc).await?;
+        Ok(())
+    }
+}
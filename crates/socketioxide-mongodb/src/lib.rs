@@ -0,0 +1,383 @@
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![warn(
+    clippy::all,
+    clippy::todo,
+    clippy::empty_enums,
+    clippy::mem_forget,
+    clippy::unused_self,
+    clippy::filter_map_next,
+    clippy::needless_continue,
+    clippy::needless_borrow,
+    clippy::match_wildcard_for_single_variants,
+    clippy::if_let_mutex,
+    clippy::await_holding_lock,
+    clippy::imprecise_flops,
+    clippy::suboptimal_flops,
+    clippy::lossy_float_literal,
+    clippy::rest_pat_in_fully_bound_structs,
+    clippy::fn_params_excessive_bools,
+    clippy::exit,
+    clippy::inefficient_to_string,
+    clippy::linkedlist,
+    clippy::macro_use_imports,
+    clippy::option_option,
+    clippy::verbose_file_reads,
+    clippy::unnested_or_patterns,
+    rust_2018_idioms,
+    future_incompatible,
+    nonstandard_style,
+    missing_docs
+)]
+
+//! # A MongoDB adapter implementation for the socketioxide crate.
+//! The adapter is used to communicate with other nodes of the same application.
+//! This allows to broadcast messages to sockets connected on other servers,
+//! to get the list of rooms, to add or remove sockets from rooms, etc.
+//!
+//! To achieve this, the adapter inserts its requests and responses as documents in a MongoDB collection
+//! shared by all the servers and watches the inserted documents with
+//! [change streams](https://www.mongodb.com/docs/manual/changeStreams/).
+//! It is the equivalent of the [`@socket.io/mongo-adapter`](https://socket.io/docs/v4/mongo-adapter/) package
+//! for the javascript implementation.
+//!
+//! The [`Driver`] abstraction allows the use of any MongoDB client.
+//! One implementation is provided:
+//! * [`MongoDbDriver`](crate::drivers::mongodb::MongoDbDriver) for the [`mongodb`] crate.
+//!
+//! You can also implement your own driver by implementing the [`Driver`] trait.
+//!
+//! <div class="warning">
+//!     Change streams are only available with replica sets or sharded clusters.
+//!     A standalone MongoDB server must be converted to a single node replica set.
+//! </div>
+//!
+//! ## Message expiration
+//! The documents inserted in the collection don't need to be kept once they have been received
+//! by the other servers. The [`MessageExpirationMode`](crate::drivers::mongodb::MessageExpirationMode)
+//! of the driver sets how they are removed:
+//! * With a [TTL index](https://www.mongodb.com/docs/manual/core/index-ttl/) on the `createdAt` field (default).
+//! * With a [capped collection](https://www.mongodb.com/docs/manual/core/capped-collections/).
+//!
+//! ## Example with the [`mongodb`] driver
+//! ```rust
+//! # use socketioxide::{SocketIo, extract::{SocketRef, Data}, adapter::Adapter};
+//! # use socketioxide_mongodb::{MongoDbAdapterCtr, MongoDbAdapter};
+//! # async fn doc_main() -> Result<(), Box<dyn std::error::Error>> {
+//! async fn on_connect<A: Adapter>(socket: SocketRef<A>) {
+//!     socket.join("room1");
+//!     socket.on("event", on_event);
+//!     let _ = socket.broadcast().emit("hello", "world").await.ok();
+//! }
+//! async fn on_event<A: Adapter>(socket: SocketRef<A>, Data(data): Data<String>) {}
+//!
+//! let client = mongodb::Client::with_uri_str("mongodb://127.0.0.1:27017/?replicaSet=rs0").await?;
+//! let adapter = MongoDbAdapterCtr::new_with_mongodb(client.database("test")).await?;
+//! let (layer, io) = SocketIo::builder()
+//!     .with_adapter::<MongoDbAdapter<_>>(adapter)
+//!     .build_layer();
+//! Ok(())
+//! # }
+//! ```
+//!
+//! ## How does it work?
+//!
+//! An adapter is created for each created namespace and it takes a corresponding [`CoreLocalAdapter`](socketioxide_core::adapter::CoreLocalAdapter).
+//! The `CoreLocalAdapter` allows to manage the local rooms and local sockets. The default `LocalAdapter`
+//! is simply a wrapper around this `CoreLocalAdapter`.
+//!
+//! The adapter is then initialized with the [`MongoDbAdapter::init`](RemoteAdapter#method.init) method.
+//! This will watch the documents inserted for its namespace that are targeted either to all the servers
+//! or to this specific server. Each document is an [`Item`] containing
+//! a msgpack encoded request or response. Responses will be always in the form `[req_id, data]`.
+//! This will allow the adapter to extract the request id and route the response to the approriate stream
+//! before deserializing the data.
+//!
+//! There are 10 types of requests:
+//! * Broadcast a packet to all the matching sockets.
+//! * Broadcast a packet to all the matching sockets and wait for a stream of acks.
+//! * Disconnect matching sockets.
+//! * Get all the rooms.
+//! * Add matching sockets to rooms.
+//! * Remove matching sockets to rooms.
+//! * Fetch all the remote sockets matching the options.
+//! * Handle a polling request for an engine.io session open on another server
+//!   (only used when the session handoff is enabled on the socket.io server).
+//! * Heartbeat
+//! * Initial heartbeat. When receiving a initial heartbeat all other servers reply a heartbeat immediately.
+//!
+//! MongoDB doesn't provide a way to count the servers watching the collection.
+//! Therefore each server emits a heartbeat at a regular interval and keeps track of the other servers
+//! that sent a heartbeat recently. This count is used to know how many responses to expect.
+//! The heartbeats also carry the number of sockets of each server, listed with `io.cluster_nodes()`.
+//!
+//! For ack streams, the adapter will first send a `BroadcastAckCount` response to the server that sent the request,
+//! and then send the acks as they are received (more details in [`MongoDbAdapter::broadcast_with_ack`](RemoteAdapter#method.broadcast_with_ack) fn).
+//!
+//! On the other side, each time an action has to be performed on the local server, the adapter will
+//! first broadcast a request to all the servers and then perform the action locally.
+
+use std::{fmt, time::Duration};
+
+use drivers::{Driver, Item, ItemKind};
+use futures_core::stream::BoxStream;
+use futures_util::StreamExt;
+use socketioxide_core::{
+    remote::adapter::{self, Incoming, RemoteAdapter, RemoteOptions, Target, Transport},
+    Uid,
+};
+
+/// Drivers are an abstraction over the MongoDB client used by the adapter.
+/// You can use the provided implementation or implement your own.
+pub mod drivers;
+
+/// Represent any error that might happen when using this adapter.
+pub type Error<D> = adapter::Error<<D as Driver>::Error>;
+
+/// The configuration of the [`MongoDbAdapter`].
+#[derive(Debug, Clone)]
+pub struct MongoDbAdapterConfig {
+    /// The heartbeat timeout duration. If a remote node does not respond within this duration,
+    /// it will be considered disconnected. Default is 10 seconds.
+    pub hb_timeout: Duration,
+    /// The heartbeat interval duration. The current node will send a heartbeat to the
+    /// other nodes at this interval. Default is 5 seconds.
+    pub hb_interval: Duration,
+    /// The request timeout. It is mainly used when expecting response such as when using
+    /// `broadcast_with_ack` or `rooms`. Default is 5 seconds.
+    pub request_timeout: Duration,
+
+    /// The channel size used to receive ack responses. Default is 255.
+    ///
+    /// If you have a lot of servers/sockets and that you may miss acknowledgement because they arrive faster
+    /// than you poll them with the returned stream, you might want to increase this value.
+    pub ack_response_buffer: usize,
+
+    /// The channel size used to receive items. Default is 1024.
+    ///
+    /// If your server is under heavy load, you might want to increase this value.
+    pub stream_buffer: usize,
+}
+impl MongoDbAdapterConfig {
+    /// Create a new config.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Set the heartbeat timeout duration. Default is 10 seconds.
+    ///
+    /// If a remote node does not send a heartbeat within this duration, it will be considered disconnected.
+    pub fn with_hb_timeout(mut self, timeout: Duration) -> Self {
+        self.hb_timeout = timeout;
+        self
+    }
+
+    /// Set the heartbeat interval duration. Default is 5 seconds.
+    pub fn with_hb_interval(mut self, interval: Duration) -> Self {
+        self.hb_interval = interval;
+        self
+    }
+
+    /// Set the request timeout. Default is 5 seconds.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Set the channel size used to send ack responses. Default is 255.
+    ///
+    /// If you have a lot of servers/sockets and that you may miss acknowledgement because they arrive faster
+    /// than you poll them with the returned stream, you might want to increase this value.
+    pub fn with_ack_response_buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "buffer size must be greater than 0");
+        self.ack_response_buffer = buffer;
+        self
+    }
+
+    /// Set the channel size used to receive items. Default is 1024.
+    ///
+    /// If your server is under heavy load, you might want to increase this value.
+    pub fn with_stream_buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "buffer size must be greater than 0");
+        self.stream_buffer = buffer;
+        self
+    }
+}
+
+impl Default for MongoDbAdapterConfig {
+    fn default() -> Self {
+        Self {
+            hb_timeout: Duration::from_secs(10),
+            hb_interval: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(5),
+            ack_response_buffer: 255,
+            stream_buffer: 1024,
+        }
+    }
+}
+
+/// The adapter constructor. For each namespace you define, a new adapter instance is created
+/// from this constructor.
+#[derive(Debug)]
+pub struct MongoDbAdapterCtr<D> {
+    driver: D,
+    config: MongoDbAdapterConfig,
+}
+
+#[cfg(feature = "mongodb")]
+impl MongoDbAdapterCtr<drivers::mongodb::MongoDbDriver> {
+    /// The name of the collection used by the [`mongodb`] driver constructors.
+    pub const COLLECTION: &'static str = "socket.io-adapter-events";
+
+    /// Create a new adapter constructor with the [`mongodb`] driver and a default config.
+    ///
+    /// The items are stored in the `socket.io-adapter-events` collection of the database and
+    /// expire with the default [`MessageExpirationMode`](drivers::mongodb::MessageExpirationMode).
+    /// Use [`MongoDbDriver::new`](drivers::mongodb::MongoDbDriver::new) and
+    /// [`MongoDbAdapterCtr::new_with_driver`] to customize them.
+    #[cfg_attr(docsrs, doc(cfg(feature = "mongodb")))]
+    pub async fn new_with_mongodb(db: mongodb::Database) -> mongodb::error::Result<Self> {
+        Self::new_with_mongodb_config(db, MongoDbAdapterConfig::default()).await
+    }
+    /// Create a new adapter constructor with the [`mongodb`] driver and a custom config.
+    #[cfg_attr(docsrs, doc(cfg(feature = "mongodb")))]
+    pub async fn new_with_mongodb_config(
+        db: mongodb::Database,
+        config: MongoDbAdapterConfig,
+    ) -> mongodb::error::Result<Self> {
+        let mode = drivers::mongodb::MessageExpirationMode::default();
+        let driver = drivers::mongodb::MongoDbDriver::new(db, Self::COLLECTION, mode).await?;
+        Ok(Self::new_with_driver(driver, config))
+    }
+}
+impl<D: Driver> MongoDbAdapterCtr<D> {
+    /// Create a new adapter constructor with a custom MongoDB driver and a config.
+    ///
+    /// You can implement your own driver by implementing the [`Driver`] trait with any MongoDB client.
+    /// Check the [`drivers`] module for more information.
+    pub fn new_with_driver(driver: D, config: MongoDbAdapterConfig) -> MongoDbAdapterCtr<D> {
+        MongoDbAdapterCtr { driver, config }
+    }
+}
+
+/// The mongodb adapter with the mongodb driver.
+#[cfg_attr(docsrs, doc(cfg(feature = "mongodb")))]
+#[cfg(feature = "mongodb")]
+pub type MongoDbAdapter<E> = CustomMongoDbAdapter<E, drivers::mongodb::MongoDbDriver>;
+/// The mongodb adapter implementation.
+/// It is generic over the [`Driver`] used to communicate with the MongoDB server.
+/// And over the [`SocketEmitter`](socketioxide_core::adapter::SocketEmitter) used to communicate
+/// with the local server. This allows to avoid cyclic dependencies between the adapter,
+/// `socketioxide-core` and `socketioxide` crates.
+pub type CustomMongoDbAdapter<E, D> = RemoteAdapter<E, MongoDbTransport<D>>;
+
+/// The mongodb [`Transport`] of the [`CustomMongoDbAdapter`], created for each namespace.
+///
+/// MongoDB doesn't provide a way to count the servers watching the collection,
+/// so the servers are only counted with the heartbeats.
+pub struct MongoDbTransport<D> {
+    /// The driver used by the adapter. This is used to communicate with the MongoDB server.
+    /// All the mongodb adapter instances share the same driver.
+    driver: D,
+    /// The configuration of the adapter.
+    config: MongoDbAdapterConfig,
+    /// The options of the remote adapter, from the configuration.
+    opts: RemoteOptions,
+    /// The namespace path.
+    ns: String,
+    /// A unique identifier for the adapter to identify itself in the MongoDB server.
+    uid: Uid,
+}
+
+impl<D: Driver> Transport for MongoDbTransport<D> {
+    type State = MongoDbAdapterCtr<D>;
+    type Error = D::Error;
+    type InitError = InitError<D>;
+
+    fn new(state: &Self::State, path: &str, uid: Uid) -> Self {
+        let config = state.config.clone();
+        let opts = RemoteOptions {
+            request_timeout: config.request_timeout,
+            ack_response_buffer: config.ack_response_buffer,
+            hb_timeout: config.hb_timeout,
+            hb_interval: config.hb_interval,
+            ..Default::default()
+        };
+        Self {
+            driver: state.driver.clone(),
+            config,
+            opts,
+            ns: path.to_string(),
+            uid,
+        }
+    }
+
+    fn options(&self) -> &RemoteOptions {
+        &self.opts
+    }
+
+    async fn init(&self) -> Result<BoxStream<'static, Incoming>, InitError<D>> {
+        let stream = self
+            .driver
+            .watch(self.uid, &self.ns, self.config.stream_buffer)
+            .await
+            .map_err(InitError::Driver)?;
+        let stream = stream.map(|item| match item.kind {
+            ItemKind::Request => Incoming::Request(item.data.into()),
+            ItemKind::Response => Incoming::Response(item.data.into()),
+        });
+        Ok(stream.boxed())
+    }
+
+    async fn close(&self) -> Result<(), D::Error> {
+        Ok(())
+    }
+
+    async fn send_req(&self, data: Vec<u8>, target: Target<'_>) -> Result<(), Error<D>> {
+        let target = match target {
+            Target::Server(uid) => Some(uid),
+            Target::All | Target::Room(_) => None,
+        };
+        self.emit(ItemKind::Request, target, data).await
+    }
+
+    async fn send_res(&self, data: Vec<u8>, target: Uid) -> Result<(), Error<D>> {
+        self.emit(ItemKind::Response, Some(target), data).await
+    }
+}
+
+impl<D: Driver> MongoDbTransport<D> {
+    async fn emit(
+        &self,
+        kind: ItemKind,
+        target: Option<Uid>,
+        data: Vec<u8>,
+    ) -> Result<(), Error<D>> {
+        let item = Item {
+            ns: self.ns.clone(),
+            uid: self.uid,
+            target,
+            kind,
+            data: data.into(),
+        };
+        self.driver
+            .emit(&item)
+            .await
+            .map_err(adapter::Error::Driver)
+    }
+}
+
+/// Error that can happen when initializing the adapter.
+#[derive(thiserror::Error)]
+pub enum InitError<D: Driver> {
+    /// Driver error.
+    #[error("driver error: {0}")]
+    Driver(D::Error),
+}
+impl<D: Driver> fmt::Debug for InitError<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Driver(err) => fmt::Debug::fmt(err, f),
+        }
+    }
+}
+/// The result of the init future.
+pub type InitRes<D> = adapter::InitRes<InitError<D>>;
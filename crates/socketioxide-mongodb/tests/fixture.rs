@@ -0,0 +1,68 @@
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use socketioxide::SocketIo;
+use socketioxide_adapter_tests::stub::{self, StubAdapter, StubBus};
+use socketioxide_core::Uid;
+use socketioxide_mongodb::{
+    drivers::{Driver, Item, MessageStream},
+    MongoDbAdapterConfig, MongoDbAdapterCtr, MongoDbTransport,
+};
+
+/// The fixture of the common adapter test suite.
+pub struct StubFixture;
+impl stub::StubFixture for StubFixture {
+    type Transport = MongoDbTransport<StubDriver>;
+    type Message = Item;
+    type Config = MongoDbAdapterConfig;
+
+    fn state(bus: StubBus<Item>, config: MongoDbAdapterConfig) -> MongoDbAdapterCtr<StubDriver> {
+        MongoDbAdapterCtr::new_with_driver(StubDriver { bus }, config)
+    }
+    fn with_request_timeout(
+        config: MongoDbAdapterConfig,
+        timeout: Duration,
+    ) -> MongoDbAdapterConfig {
+        config.with_request_timeout(timeout)
+    }
+}
+
+/// Spawns a number of servers with a stub driver and a custom config for testing.
+pub fn spawn_servers_with_config<const N: usize>(
+    config: MongoDbAdapterConfig,
+) -> [SocketIo<StubAdapter<StubFixture>>; N] {
+    stub::spawn_servers_with_config::<StubFixture, N>(config)
+}
+
+/// A driver that dispatches the emitted items to the matching watchers, like a change stream would do.
+#[derive(Debug, Clone)]
+pub struct StubDriver {
+    bus: StubBus<Item>,
+}
+
+fn is_target(uid: Uid, ns: &str, item: &Item) -> bool {
+    item.ns == ns && item.uid != uid && item.target.map_or(true, |target| target == uid)
+}
+
+impl Driver for StubDriver {
+    type Error = std::convert::Infallible;
+
+    async fn watch(
+        &self,
+        uid: Uid,
+        ns: &str,
+        _: usize,
+    ) -> Result<MessageStream<Item>, Self::Error> {
+        let ns = ns.to_string();
+        let stream = self
+            .bus
+            .subscribe(move |item: &Item| is_target(uid, &ns, item).then(|| item.clone()));
+        Ok(stream)
+    }
+
+    async fn emit(&self, item: &Item) -> Result<(), Self::Error> {
+        self.bus.publish(item.clone());
+        Ok(())
+    }
+}
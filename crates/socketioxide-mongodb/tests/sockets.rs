@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use socketioxide::adapter::NodeInfo;
+use socketioxide_adapter_tests::timeout_rcv;
+use socketioxide_mongodb::MongoDbAdapterConfig;
+
+mod fixture;
+
+#[tokio::test]
+pub async fn cluster_nodes() {
+    let config = MongoDbAdapterConfig::new().with_hb_interval(Duration::from_millis(10));
+    let [io1, io2] = fixture::spawn_servers_with_config(config);
+
+    io1.ns("/", || ()).await.unwrap();
+    io2.ns("/", || ()).await.unwrap();
+
+    let (_, mut rx1) = io2.new_dummy_sock("/", ()).await;
+    let (_, mut rx2) = io2.new_dummy_sock("/", ()).await;
+
+    timeout_rcv!(&mut rx1); // connect packet
+    timeout_rcv!(&mut rx2); // connect packet
+
+    // wait for a heartbeat with the new socket count
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let nodes = io1.cluster_nodes().await.unwrap();
+    let node1 = NodeInfo {
+        uid: io1.config().server_id,
+        sockets: 0,
+    };
+    let node2 = NodeInfo {
+        uid: io2.config().server_id,
+        sockets: 2,
+    };
+    assert_eq!(nodes, [node1, node2]);
+}
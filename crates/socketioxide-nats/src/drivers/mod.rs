@@ -0,0 +1,45 @@
+use std::future::Future;
+
+/// A driver implementation for the [async-nats](docs.rs/async-nats) client.
+#[cfg(feature = "adapter-nats")]
+#[cfg_attr(docsrs, doc(cfg(feature = "adapter-nats")))]
+pub mod nats;
+
+pub use socketioxide_core::remote::MessageStream;
+
+/// A message received from a subject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// The subject the message was published to.
+    pub subject: String,
+    /// The subject to which the responses to this message must be published.
+    pub reply: Option<String>,
+    /// The msgpack encoded payload.
+    pub payload: Vec<u8>,
+}
+
+/// The driver trait can be used to support different NATS clients.
+/// It must share handlers/connection between its clones.
+pub trait Driver: Clone + Send + Sync + 'static {
+    /// The error type for the driver.
+    type Error: std::error::Error + Send + 'static;
+
+    /// Publish a message to a subject, with an optional reply subject for the responses.
+    fn publish(
+        &self,
+        subject: String,
+        reply: Option<String>,
+        payload: Vec<u8>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Subscribe to a subject, it will return a stream of messages.
+    /// The size parameter is the buffer size of the stream.
+    fn subscribe(
+        &self,
+        subject: String,
+        size: usize,
+    ) -> impl Future<Output = Result<MessageStream<Message>, Self::Error>> + Send;
+
+    /// Unsubscribe from a subject.
+    fn unsubscribe(&self, subject: String) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use futures_util::StreamExt;
+use tokio::{sync::mpsc, task::AbortHandle};
+
+use super::{Driver, Message, MessageStream};
+
+pub use async_nats as nats_client;
+
+/// An error type for the nats driver.
+#[derive(Debug, thiserror::Error)]
+pub enum NatsError {
+    /// Error when publishing a message.
+    #[error("publish error: {0}")]
+    Publish(#[from] async_nats::PublishError),
+    /// Error when subscribing to a subject.
+    #[error("subscribe error: {0}")]
+    Subscribe(#[from] async_nats::SubscribeError),
+}
+
+/// A driver implementation for the [async-nats](docs.rs/async-nats) client.
+///
+/// Each subscription is piped to its message stream by a dedicated task.
+/// Unsubscribing aborts this task, which drops the subscriber and unsubscribes from the subject.
+#[derive(Debug, Clone)]
+pub struct NatsDriver {
+    client: async_nats::Client,
+    subscriptions: Arc<Mutex<HashMap<String, AbortHandle>>>,
+}
+
+impl NatsDriver {
+    /// Create a new nats driver from a nats client.
+    pub fn new(client: async_nats::Client) -> Self {
+        Self {
+            client,
+            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl Driver for NatsDriver {
+    type Error = NatsError;
+
+    async fn publish(
+        &self,
+        subject: String,
+        reply: Option<String>,
+        payload: Vec<u8>,
+    ) -> Result<(), Self::Error> {
+        match reply {
+            Some(reply) => {
+                self.client
+                    .publish_with_reply(subject, reply, payload.into())
+                    .await?
+            }
+            None => self.client.publish(subject, payload.into()).await?,
+        };
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        subject: String,
+        size: usize,
+    ) -> Result<MessageStream<Message>, Self::Error> {
+        let mut subscriber = self.client.subscribe(subject.clone()).await?;
+        let (tx, rx) = mpsc::channel(size);
+        let task = tokio::spawn(async move {
+            while let Some(msg) = subscriber.next().await {
+                let msg = Message {
+                    subject: msg.subject.to_string(),
+                    reply: msg.reply.map(|reply| reply.to_string()),
+                    payload: msg.payload.into(),
+                };
+                if let Err(e) = tx.try_send(msg) {
+                    tracing::warn!("nats subscription channel full {e}");
+                }
+            }
+        });
+        let prev = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .insert(subject, task.abort_handle());
+        if let Some(prev) = prev {
+            prev.abort();
+        }
+        Ok(MessageStream::new(rx))
+    }
+
+    async fn unsubscribe(&self, subject: String) -> Result<(), Self::Error> {
+        if let Some(task) = self.subscriptions.lock().unwrap().remove(&subject) {
+            task.abort();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,437 @@
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![warn(
+    clippy::all,
+    clippy::todo,
+    clippy::empty_enums,
+    clippy::mem_forget,
+    clippy::unused_self,
+    clippy::filter_map_next,
+    clippy::needless_continue,
+    clippy::needless_borrow,
+    clippy::match_wildcard_for_single_variants,
+    clippy::if_let_mutex,
+    clippy::await_holding_lock,
+    clippy::imprecise_flops,
+    clippy::suboptimal_flops,
+    clippy::lossy_float_literal,
+    clippy::rest_pat_in_fully_bound_structs,
+    clippy::fn_params_excessive_bools,
+    clippy::exit,
+    clippy::inefficient_to_string,
+    clippy::linkedlist,
+    clippy::macro_use_imports,
+    clippy::option_option,
+    clippy::verbose_file_reads,
+    clippy::unnested_or_patterns,
+    rust_2018_idioms,
+    future_incompatible,
+    nonstandard_style,
+    missing_docs
+)]
+
+//! # A NATS adapter implementation for the socketioxide crate.
+//! The adapter is used to communicate with other nodes of the same application.
+//! This allows to broadcast messages to sockets connected on other servers,
+//! to get the list of rooms, to add or remove sockets from rooms, etc.
+//!
+//! To achieve this, the adapter uses the [core NATS](https://docs.nats.io/nats-concepts/core-nats)
+//! publish/subscribe and request/reply patterns to communicate with other servers.
+//!
+//! The [`Driver`] abstraction allows the use of any NATS client.
+//! One implementation is provided:
+//! * [`NatsDriver`](crate::drivers::nats::NatsDriver) for the [`async_nats`] crate,
+//!   enabled with the `adapter-nats` feature.
+//!
+//! You can also implement your own driver by implementing the [`Driver`] trait.
+//!
+//! ## Example with the [`async_nats`] driver
+//! ```rust
+//! # use socketioxide::{SocketIo, extract::{SocketRef, Data}, adapter::Adapter};
+//! # use socketioxide_nats::{NatsAdapterCtr, NatsAdapter};
+//! # async fn doc_main() -> Result<(), Box<dyn std::error::Error>> {
+//! async fn on_connect<A: Adapter>(socket: SocketRef<A>) {
+//!     socket.join("room1");
+//!     socket.on("event", on_event);
+//!     let _ = socket.broadcast().emit("hello", "world").await.ok();
+//! }
+//! async fn on_event<A: Adapter>(socket: SocketRef<A>, Data(data): Data<String>) {}
+//!
+//! let client = async_nats::connect("nats://127.0.0.1:4222").await?;
+//! let adapter = NatsAdapterCtr::new_with_nats(client);
+//! let (layer, io) = SocketIo::builder()
+//!     .with_adapter::<NatsAdapter<_>>(adapter)
+//!     .build_layer();
+//! Ok(())
+//! # }
+//! ```
+//!
+//! ## How does it work?
+//!
+//! An adapter is created for each created namespace and it takes a corresponding [`CoreLocalAdapter`](socketioxide_core::adapter::CoreLocalAdapter).
+//! The `CoreLocalAdapter` allows to manage the local rooms and local sockets. The default `LocalAdapter`
+//! is simply a wrapper around this `CoreLocalAdapter`.
+//!
+//! The adapter is then initialized with the [`NatsAdapter::init`](RemoteAdapter#method.init) method.
+//! This will subscribe to 3 subjects:
+//! * `"{prefix}.request.{namespace}"`: A global subject to receive broadcasted requests.
+//! * `"{prefix}.request.{namespace}.{uid}"`: A specific subject to receive requests only for this server.
+//! * `"{prefix}.response.{namespace}.{uid}"`: A specific subject to receive responses only for this server.
+//!   Each request is published with this subject as its reply subject, the other servers then
+//!   publish their responses to it. Messages sent to this subject will be always in the form `[req_id, data]`.
+//!   This will allow the adapter to extract the request id and route the response to the approriate stream
+//!   before deserializing the data.
+//!
+//! All messages are encoded with msgpack.
+//!
+//! There are 10 types of requests:
+//! * Broadcast a packet to all the matching sockets.
+//! * Broadcast a packet to all the matching sockets and wait for a stream of acks.
+//! * Disconnect matching sockets.
+//! * Get all the rooms.
+//! * Add matching sockets to rooms.
+//! * Remove matching sockets to rooms.
+//! * Fetch all the remote sockets matching the options.
+//! * Handle a polling request for an engine.io session open on another server
+//!   (only used when the session handoff is enabled on the socket.io server).
+//! * Heartbeat
+//! * Initial heartbeat. When receiving a initial heartbeat all other servers reply a heartbeat immediately.
+//!
+//! Core NATS doesn't provide a way to count the subscribers of a subject.
+//! Therefore each server emits a heartbeat at a regular interval and keeps track of the other servers
+//! that sent a heartbeat recently. This count is used to know how many responses to expect.
+//!
+//! For ack streams, the adapter will first send a `BroadcastAckCount` response to the server that sent the request,
+//! and then send the acks as they are received (more details in [`NatsAdapter::broadcast_with_ack`](RemoteAdapter#method.broadcast_with_ack) fn).
+//!
+//! On the other side, each time an action has to be performed on the local server, the adapter will
+//! first broadcast a request to all the servers and then perform the action locally.
+
+use std::{borrow::Cow, fmt, future, time::Duration};
+
+use drivers::{Driver, Message, MessageStream};
+use futures_core::stream::BoxStream;
+use futures_util::StreamExt;
+use socketioxide_core::{
+    remote::adapter::{self, Incoming, RemoteAdapter, RemoteOptions, Target, Transport},
+    Uid,
+};
+
+/// Drivers are an abstraction over the NATS client used by the adapter.
+/// You can use the provided implementation or implement your own.
+pub mod drivers;
+
+/// Represent any error that might happen when using this adapter.
+pub type Error<D> = adapter::Error<<D as Driver>::Error>;
+
+/// The configuration of the [`NatsAdapter`].
+#[derive(Debug, Clone)]
+pub struct NatsAdapterConfig {
+    /// The heartbeat timeout duration. If a remote node does not respond within this duration,
+    /// it will be considered disconnected. Default is 10 seconds.
+    pub hb_timeout: Duration,
+    /// The heartbeat interval duration. The current node will send a heartbeat to the
+    /// other nodes at this interval. Default is 5 seconds.
+    pub hb_interval: Duration,
+    /// The request timeout. It is mainly used when expecting response such as when using
+    /// `broadcast_with_ack` or `rooms`. Default is 5 seconds.
+    pub request_timeout: Duration,
+
+    /// The prefix used for the subjects. Default is "socket.io".
+    pub prefix: Cow<'static, str>,
+
+    /// The channel size used to receive ack responses. Default is 255.
+    ///
+    /// If you have a lot of servers/sockets and that you may miss acknowledgement because they arrive faster
+    /// than you poll them with the returned stream, you might want to increase this value.
+    pub ack_response_buffer: usize,
+
+    /// The channel size used to receive messages. Default is 1024.
+    ///
+    /// If your server is under heavy load, you might want to increase this value.
+    pub stream_buffer: usize,
+}
+impl NatsAdapterConfig {
+    /// Create a new config.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Set the heartbeat timeout duration. Default is 10 seconds.
+    ///
+    /// If a remote node does not send a heartbeat within this duration, it will be considered disconnected.
+    pub fn with_hb_timeout(mut self, timeout: Duration) -> Self {
+        self.hb_timeout = timeout;
+        self
+    }
+
+    /// Set the heartbeat interval duration. Default is 5 seconds.
+    pub fn with_hb_interval(mut self, interval: Duration) -> Self {
+        self.hb_interval = interval;
+        self
+    }
+
+    /// Set the request timeout. Default is 5 seconds.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Set the prefix used for the subjects. Default is "socket.io".
+    pub fn with_prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set the channel size used to send ack responses. Default is 255.
+    ///
+    /// If you have a lot of servers/sockets and that you may miss acknowledgement because they arrive faster
+    /// than you poll them with the returned stream, you might want to increase this value.
+    pub fn with_ack_response_buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "buffer size must be greater than 0");
+        self.ack_response_buffer = buffer;
+        self
+    }
+
+    /// Set the channel size used to receive items. Default is 1024.
+    ///
+    /// If your server is under heavy load, you might want to increase this value.
+    pub fn with_stream_buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "buffer size must be greater than 0");
+        self.stream_buffer = buffer;
+        self
+    }
+}
+
+impl Default for NatsAdapterConfig {
+    fn default() -> Self {
+        Self {
+            hb_timeout: Duration::from_secs(10),
+            hb_interval: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(5),
+            prefix: Cow::Borrowed("socket.io"),
+            ack_response_buffer: 255,
+            stream_buffer: 1024,
+        }
+    }
+}
+
+/// The adapter constructor. For each namespace you define, a new adapter instance is created
+/// from this constructor.
+#[derive(Debug)]
+pub struct NatsAdapterCtr<D> {
+    driver: D,
+    config: NatsAdapterConfig,
+}
+
+#[cfg(feature = "adapter-nats")]
+impl NatsAdapterCtr<drivers::nats::NatsDriver> {
+    /// Create a new adapter constructor with the [`async_nats`] driver and a default config.
+    #[cfg_attr(docsrs, doc(cfg(feature = "adapter-nats")))]
+    pub fn new_with_nats(client: async_nats::Client) -> Self {
+        Self::new_with_nats_config(client, NatsAdapterConfig::default())
+    }
+    /// Create a new adapter constructor with the [`async_nats`] driver and a custom config.
+    #[cfg_attr(docsrs, doc(cfg(feature = "adapter-nats")))]
+    pub fn new_with_nats_config(client: async_nats::Client, config: NatsAdapterConfig) -> Self {
+        let driver = drivers::nats::NatsDriver::new(client);
+        Self::new_with_driver(driver, config)
+    }
+}
+impl<D: Driver> NatsAdapterCtr<D> {
+    /// Create a new adapter constructor with a custom NATS driver and a config.
+    ///
+    /// You can implement your own driver by implementing the [`Driver`] trait with any NATS client.
+    /// Check the [`drivers`] module for more information.
+    pub fn new_with_driver(driver: D, config: NatsAdapterConfig) -> NatsAdapterCtr<D> {
+        NatsAdapterCtr { driver, config }
+    }
+}
+
+/// The nats adapter with the async-nats driver.
+#[cfg_attr(docsrs, doc(cfg(feature = "adapter-nats")))]
+#[cfg(feature = "adapter-nats")]
+pub type NatsAdapter<E> = CustomNatsAdapter<E, drivers::nats::NatsDriver>;
+
+/// The nats adapter implementation.
+/// It is generic over the [`Driver`] used to communicate with the NATS server.
+/// And over the [`SocketEmitter`](socketioxide_core::adapter::SocketEmitter) used to communicate
+/// with the local server. This allows to avoid cyclic dependencies between the adapter,
+/// `socketioxide-core` and `socketioxide` crates.
+pub type CustomNatsAdapter<E, D> = RemoteAdapter<E, NatsTransport<D>>;
+
+/// The nats [`Transport`] of the [`CustomNatsAdapter`], created for each namespace.
+///
+/// Core NATS doesn't provide a way to count the subscribers of a subject,
+/// so the servers are only counted with the heartbeats.
+pub struct NatsTransport<D> {
+    /// The driver used by the adapter. This is used to communicate with the NATS server.
+    /// All the nats adapter instances share the same driver.
+    driver: D,
+    /// The configuration of the adapter.
+    config: NatsAdapterConfig,
+    /// The options of the remote adapter, from the configuration.
+    opts: RemoteOptions,
+    /// The namespace path.
+    path: String,
+    /// A unique identifier for the adapter to identify itself in the NATS server.
+    uid: Uid,
+    /// The request subject used to broadcast requests to all the servers.
+    /// format: `{prefix}.request.{path}`.
+    req_subject: String,
+    /// The subject on which this server receives responses, set as the reply subject of its requests.
+    /// format: `{prefix}.response.{path}.{uid}`.
+    res_subject: String,
+}
+
+impl<D: Driver> Transport for NatsTransport<D> {
+    type State = NatsAdapterCtr<D>;
+    type Error = D::Error;
+    type InitError = InitError<D>;
+
+    fn new(state: &Self::State, path: &str, uid: Uid) -> Self {
+        let config = state.config.clone();
+        let opts = RemoteOptions {
+            request_timeout: config.request_timeout,
+            ack_response_buffer: config.ack_response_buffer,
+            hb_timeout: config.hb_timeout,
+            hb_interval: config.hb_interval,
+            ..Default::default()
+        };
+        Self {
+            driver: state.driver.clone(),
+            req_subject: format!("{}.request.{}", config.prefix, path),
+            res_subject: format!("{}.response.{}.{}", config.prefix, path, uid),
+            config,
+            opts,
+            path: path.to_string(),
+            uid,
+        }
+    }
+
+    fn options(&self) -> &RemoteOptions {
+        &self.opts
+    }
+
+    async fn init(&self) -> Result<BoxStream<'static, Incoming>, InitError<D>> {
+        if !is_valid_ns(&self.path) {
+            return Err(InitError::MalformedNamespace);
+        }
+        let global_stream = self.subscribe(self.req_subject.clone()).await?;
+        let specific_stream = self.subscribe(self.get_req_subject(Some(self.uid))).await?;
+        let response_stream = self.subscribe(self.res_subject.clone()).await?;
+        let stream = futures_util::stream::select(global_stream, specific_stream);
+        let stream = futures_util::stream::select(stream, response_stream);
+
+        let req_subject = self.req_subject.clone();
+        let specific_subject = self.get_req_subject(Some(self.uid));
+        let res_subject = self.res_subject.clone();
+        let stream = stream.filter_map(move |msg| {
+            let item = if msg.subject == req_subject || msg.subject == specific_subject {
+                Some(Incoming::Request(msg.payload))
+            } else if msg.subject == res_subject {
+                Some(Incoming::Response(msg.payload))
+            } else {
+                tracing::warn!("unexpected message/subject: {}", msg.subject);
+                None
+            };
+            future::ready(item)
+        });
+        Ok(stream.boxed())
+    }
+
+    async fn close(&self) -> Result<(), D::Error> {
+        tokio::try_join!(
+            self.driver.unsubscribe(self.req_subject.clone()),
+            self.driver
+                .unsubscribe(self.get_req_subject(Some(self.uid))),
+            self.driver.unsubscribe(self.res_subject.clone())
+        )?;
+        Ok(())
+    }
+
+    async fn send_req(&self, data: Vec<u8>, target: Target<'_>) -> Result<(), Error<D>> {
+        let subject = match target {
+            Target::Server(uid) => self.get_req_subject(Some(uid)),
+            Target::All | Target::Room(_) => self.get_req_subject(None),
+        };
+        let reply = Some(self.res_subject.clone());
+        self.driver
+            .publish(subject, reply, data)
+            .await
+            .map_err(adapter::Error::Driver)
+    }
+
+    async fn send_res(&self, data: Vec<u8>, target: Uid) -> Result<(), Error<D>> {
+        let subject = format!("{}.response.{}.{}", self.config.prefix, self.path, target);
+        self.driver
+            .publish(subject, None, data)
+            .await
+            .map_err(adapter::Error::Driver)
+    }
+}
+
+/// Error that can happen when initializing the adapter.
+#[derive(thiserror::Error)]
+pub enum InitError<D: Driver> {
+    /// Driver error.
+    #[error("driver error: {0}")]
+    Driver(D::Error),
+    /// Malformed namespace path.
+    #[error("malformed namespace path, it must not contain '.', '*', '>' or whitespaces")]
+    MalformedNamespace,
+}
+impl<D: Driver> fmt::Debug for InitError<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Driver(err) => fmt::Debug::fmt(err, f),
+            Self::MalformedNamespace => write!(f, "Malformed namespace path"),
+        }
+    }
+}
+/// The result of the init future.
+pub type InitRes<D> = adapter::InitRes<InitError<D>>;
+
+impl<D: Driver> NatsTransport<D> {
+    /// Build a request subject for a request.
+    ///
+    /// If we know the target server id, we can build a subject specific to this server.
+    /// Otherwise, we use the default request subject that will broadcast the request to all the servers.
+    fn get_req_subject(&self, node_id: Option<Uid>) -> String {
+        match node_id {
+            Some(uid) => format!("{}.{}", self.req_subject, uid),
+            None => self.req_subject.clone(),
+        }
+    }
+
+    /// Little wrapper to map the error type.
+    #[inline]
+    async fn subscribe(&self, subject: String) -> Result<MessageStream<Message>, InitError<D>> {
+        tracing::trace!(?subject, "subscribing to");
+        self.driver
+            .subscribe(subject, self.config.stream_buffer)
+            .await
+            .map_err(InitError::Driver)
+    }
+}
+
+/// Checks if the namespace path can be used as a subject token.
+fn is_valid_ns(path: &str) -> bool {
+    let is_invalid = |c: char| matches!(c, '.' | '*' | '>') || c.is_whitespace();
+    !path.is_empty() && !path.contains(is_invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_ns() {
+        assert!(is_valid_ns("/"));
+        assert!(is_valid_ns("/admin"));
+        assert!(is_valid_ns("/admin/chat-1"));
+        assert!(!is_valid_ns(""));
+        assert!(!is_valid_ns("/admin.chat"));
+        assert!(!is_valid_ns("/*"));
+        assert!(!is_valid_ns("/>"));
+        assert!(!is_valid_ns("/admin chat"));
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use socketioxide_adapter_tests::stub::{self, StubBus};
+use socketioxide_nats::{
+    drivers::{Driver, Message, MessageStream},
+    NatsAdapterConfig, NatsAdapterCtr, NatsTransport,
+};
+
+/// The fixture of the common adapter test suite.
+pub struct StubFixture;
+impl stub::StubFixture for StubFixture {
+    type Transport = NatsTransport<StubDriver>;
+    type Message = Message;
+    type Config = NatsAdapterConfig;
+
+    fn state(bus: StubBus<Message>, config: NatsAdapterConfig) -> NatsAdapterCtr<StubDriver> {
+        NatsAdapterCtr::new_with_driver(StubDriver { bus }, config)
+    }
+    fn with_request_timeout(config: NatsAdapterConfig, timeout: Duration) -> NatsAdapterConfig {
+        config.with_request_timeout(timeout)
+    }
+}
+
+/// A driver that dispatches the published messages to the subscribers of their subject,
+/// like a NATS server would do.
+#[derive(Debug, Clone)]
+pub struct StubDriver {
+    bus: StubBus<Message>,
+}
+
+impl Driver for StubDriver {
+    type Error = std::convert::Infallible;
+
+    async fn publish(
+        &self,
+        subject: String,
+        reply: Option<String>,
+        payload: Vec<u8>,
+    ) -> Result<(), Self::Error> {
+        self.bus.publish(Message {
+            subject,
+            reply,
+            payload,
+        });
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        subject: String,
+        _: usize,
+    ) -> Result<MessageStream<Message>, Self::Error> {
+        let stream = self
+            .bus
+            .subscribe(move |msg: &Message| (msg.subject == subject).then(|| msg.clone()));
+        Ok(stream)
+    }
+
+    async fn unsubscribe(&self, _: String) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
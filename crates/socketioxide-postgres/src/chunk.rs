@@ -0,0 +1,224 @@
+//! Postgres notification payloads are limited to 8000 bytes.
+//! Therefore the messages are split into chunks that are base64 encoded and sent in separate notifications.
+//! They are then reassembled by the receiving servers.
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use socketioxide_core::Uid;
+
+/// The maximum size of the data carried by a single chunk.
+///
+/// A notification payload must be shorter than 8000 bytes. Once base64 encoded,
+/// a payload of 5997 bytes is 7996 bytes long. We keep a margin for the chunk header.
+pub const MAX_CHUNK_SIZE: usize = 5900;
+
+/// The kind of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MessageKind {
+    /// A request sent to one or all the servers.
+    Request,
+    /// A response sent back to the server that emitted a request.
+    Response,
+}
+
+/// The header of a message, repeated in each of its chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+    /// The uid of the server that sent the message.
+    pub uid: Uid,
+    /// The uid of the server targeted by the message, `None` if it targets all the servers.
+    pub target: Option<Uid>,
+    /// The kind of the message.
+    pub kind: MessageKind,
+}
+
+/// A chunk of a message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub header: Header,
+    /// The id of the message, unique for the server that sent it.
+    pub id: u32,
+    /// The index of this chunk in the message.
+    pub idx: u32,
+    /// The number of chunks of the message.
+    pub count: u32,
+    /// A part of the msgpack encoded message.
+    pub data: Bytes,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChunkError {
+    #[error("base64 decoding error: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("chunk decoding error: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+}
+
+impl Chunk {
+    /// Decode a chunk from a notification payload.
+    pub fn decode(payload: &str) -> Result<Self, ChunkError> {
+        let data = STANDARD.decode(payload)?;
+        Ok(rmp_serde::from_slice(&data)?)
+    }
+}
+
+/// Split a message into encoded chunks of at most `chunk_size` bytes of data.
+pub fn split(
+    header: Header,
+    id: u32,
+    data: &[u8],
+    chunk_size: usize,
+) -> Result<Vec<String>, rmp_serde::encode::Error> {
+    let count = data.len().div_ceil(chunk_size).max(1) as u32;
+    let mut payloads = Vec::with_capacity(count as usize);
+    for idx in 0..count {
+        let start = idx as usize * chunk_size;
+        let end = std::cmp::min(start + chunk_size, data.len());
+        let chunk = Chunk {
+            header,
+            id,
+            idx,
+            count,
+            data: Bytes::copy_from_slice(&data[start..end]),
+        };
+        payloads.push(STANDARD.encode(rmp_serde::to_vec(&chunk)?));
+    }
+    Ok(payloads)
+}
+
+#[derive(Debug)]
+struct Partial {
+    chunks: Vec<Option<Bytes>>,
+    remaining: usize,
+    created: Instant,
+}
+
+/// Reassemble the chunks received from the other servers.
+///
+/// Messages that are not complete after the given timeout are discarded.
+#[derive(Debug)]
+pub struct Reassembler {
+    partials: HashMap<(Uid, u32), Partial>,
+    timeout: Duration,
+}
+
+impl Reassembler {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            partials: HashMap::new(),
+            timeout,
+        }
+    }
+
+    /// Push a chunk and return the complete message if it was the last missing chunk.
+    pub fn push(&mut self, chunk: Chunk) -> Option<(Header, Vec<u8>)> {
+        if chunk.count == 1 {
+            return Some((chunk.header, chunk.data.into()));
+        }
+        if chunk.idx >= chunk.count {
+            tracing::warn!(?chunk.idx, ?chunk.count, "invalid chunk index");
+            return None;
+        }
+
+        let timeout = self.timeout;
+        self.partials.retain(|_, p| p.created.elapsed() < timeout);
+
+        let key = (chunk.header.uid, chunk.id);
+        let partial = self.partials.entry(key).or_insert_with(|| Partial {
+            chunks: vec![None; chunk.count as usize],
+            remaining: chunk.count as usize,
+            created: Instant::now(),
+        });
+        if partial.chunks.len() != chunk.count as usize {
+            tracing::warn!(?chunk.count, "chunk count mismatch");
+            return None;
+        }
+        let slot = &mut partial.chunks[chunk.idx as usize];
+        if slot.is_none() {
+            *slot = Some(chunk.data);
+            partial.remaining -= 1;
+        }
+
+        if partial.remaining == 0 {
+            let partial = self.partials.remove(&key)?;
+            let data = partial.chunks.into_iter().flatten().flatten().collect();
+            Some((chunk.header, data))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> Header {
+        Header {
+            uid: Uid::new(),
+            target: Some(Uid::new()),
+            kind: MessageKind::Response,
+        }
+    }
+
+    #[test]
+    fn split_reassemble() {
+        let data: Vec<u8> = (0..20_000).map(|i| i as u8).collect();
+        let payloads = split(header(), 1, &data, MAX_CHUNK_SIZE).unwrap();
+        assert_eq!(payloads.len(), 4);
+        assert!(payloads.iter().all(|p| p.len() < 8000));
+
+        let mut reassembler = Reassembler::new(Duration::from_secs(5));
+        let mut chunks: Vec<_> = payloads.iter().map(|p| Chunk::decode(p).unwrap()).collect();
+        chunks.reverse();
+        let last = chunks.pop().unwrap();
+        for chunk in chunks {
+            assert!(reassembler.push(chunk).is_none());
+        }
+        let (_, res) = reassembler.push(last).unwrap();
+        assert_eq!(res, data);
+        assert!(reassembler.partials.is_empty());
+    }
+
+    #[test]
+    fn split_small() {
+        let header = header();
+        let payloads = split(header, 1, b"hello", MAX_CHUNK_SIZE).unwrap();
+        assert_eq!(payloads.len(), 1);
+        let chunk = Chunk::decode(&payloads[0]).unwrap();
+        let mut reassembler = Reassembler::new(Duration::from_secs(5));
+        assert_eq!(reassembler.push(chunk), Some((header, b"hello".to_vec())));
+    }
+
+    #[test]
+    fn duplicated_chunk() {
+        let data = vec![1; 30];
+        let payloads = split(header(), 1, &data, 10).unwrap();
+        let mut reassembler = Reassembler::new(Duration::from_secs(5));
+        let chunk = Chunk::decode(&payloads[0]).unwrap();
+        assert!(reassembler.push(chunk.clone()).is_none());
+        assert!(reassembler.push(chunk).is_none());
+        assert!(reassembler
+            .push(Chunk::decode(&payloads[1]).unwrap())
+            .is_none());
+        let (_, res) = reassembler
+            .push(Chunk::decode(&payloads[2]).unwrap())
+            .unwrap();
+        assert_eq!(res, data);
+    }
+
+    #[test]
+    fn expired_partial() {
+        let data = vec![1; 30];
+        let payloads = split(header(), 1, &data, 10).unwrap();
+        let mut reassembler = Reassembler::new(Duration::ZERO);
+        for payload in &payloads {
+            assert!(reassembler.push(Chunk::decode(payload).unwrap()).is_none());
+        }
+    }
+}
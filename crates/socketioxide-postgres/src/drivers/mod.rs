@@ -0,0 +1,37 @@
+use std::future::Future;
+
+/// A driver implementation for the [tokio-postgres](docs.rs/tokio-postgres) client.
+#[cfg(feature = "postgres")]
+#[cfg_attr(docsrs, doc(cfg(feature = "postgres")))]
+pub mod postgres;
+
+pub use socketioxide_core::remote::MessageStream;
+
+/// The driver trait can be used to support different Postgres clients.
+/// It must share handlers/connection between its clones.
+///
+/// Like with Postgres `LISTEN/NOTIFY`, a driver listening to a channel must also receive the
+/// notifications it sent itself. The payloads sent by the adapter are always below the
+/// 8000 bytes limit of postgres notifications.
+pub trait Driver: Clone + Send + Sync + 'static {
+    /// The error type for the driver.
+    type Error: std::error::Error + Send + 'static;
+
+    /// Listen to a channel, it will return a stream of notification payloads.
+    /// The size parameter is the buffer size of the stream.
+    fn listen(
+        &self,
+        channel: &str,
+        size: usize,
+    ) -> impl Future<Output = Result<MessageStream<String>, Self::Error>> + Send;
+
+    /// Stop listening to a channel.
+    fn unlisten(&self, channel: &str) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Send a notification with the given payload to a channel.
+    fn notify(
+        &self,
+        channel: &str,
+        payload: &str,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
@@ -0,0 +1,124 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use futures_util::{stream, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::mpsc,
+};
+use tokio_postgres::{AsyncMessage, Client, Connection};
+
+use super::{Driver, MessageStream};
+
+pub use tokio_postgres as postgres_client;
+
+type HandlerMap = HashMap<String, mpsc::Sender<String>>;
+
+/// A driver implementation for the [tokio-postgres](docs.rs/tokio-postgres) client.
+///
+/// The notifications are read by polling the [`Connection`] of the client in a dedicated task.
+/// Therefore a connection used with this driver must not be spawned elsewhere.
+#[derive(Debug, Clone)]
+pub struct PostgresDriver {
+    client: Arc<Client>,
+    handlers: Arc<RwLock<HandlerMap>>,
+}
+
+impl PostgresDriver {
+    /// Create a new postgres driver from a client and its connection.
+    ///
+    /// The connection is spawned by the driver to dispatch the received notifications.
+    pub fn new<S, T>(client: Client, connection: Connection<S, T>) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let handlers = Arc::new(RwLock::new(HashMap::new()));
+        tokio::spawn(pipe_notifications(connection, handlers.clone()));
+        Self {
+            client: Arc::new(client),
+            handlers,
+        }
+    }
+}
+
+async fn pipe_notifications<S, T>(
+    mut connection: Connection<S, T>,
+    handlers: Arc<RwLock<HandlerMap>>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+    while let Some(msg) = messages.next().await {
+        match msg {
+            Ok(AsyncMessage::Notification(notif)) => {
+                let handlers = handlers.read().unwrap();
+                if let Some(tx) = handlers.get(notif.channel()) {
+                    if let Err(e) = tx.try_send(notif.payload().to_string()) {
+                        tracing::warn!("postgres notification channel full {e}");
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("postgres connection error: {e}");
+                break;
+            }
+        }
+    }
+}
+
+/// Quote a channel name so that it can be used as an identifier in a `LISTEN` query.
+fn quote_ident(channel: &str) -> String {
+    format!("\"{}\"", channel.replace('"', "\"\""))
+}
+
+impl Driver for PostgresDriver {
+    type Error = tokio_postgres::Error;
+
+    async fn listen(
+        &self,
+        channel: &str,
+        size: usize,
+    ) -> Result<MessageStream<String>, Self::Error> {
+        let (tx, rx) = mpsc::channel(size);
+        // Register the handler first so that no notification is missed.
+        self.handlers
+            .write()
+            .unwrap()
+            .insert(channel.to_string(), tx);
+        let query = format!("LISTEN {}", quote_ident(channel));
+        if let Err(e) = self.client.batch_execute(&query).await {
+            self.handlers.write().unwrap().remove(channel);
+            return Err(e);
+        }
+        Ok(MessageStream::new(rx))
+    }
+
+    async fn unlisten(&self, channel: &str) -> Result<(), Self::Error> {
+        self.handlers.write().unwrap().remove(channel);
+        let query = format!("UNLISTEN {}", quote_ident(channel));
+        self.client.batch_execute(&query).await
+    }
+
+    async fn notify(&self, channel: &str, payload: &str) -> Result<(), Self::Error> {
+        self.client
+            .execute("SELECT pg_notify($1, $2)", &[&channel, &payload])
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_channel() {
+        assert_eq!(quote_ident("socket.io#/"), r#""socket.io#/""#);
+        assert_eq!(quote_ident(r#"a"b"#), r#""a""b""#);
+    }
+}
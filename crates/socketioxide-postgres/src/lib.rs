@@ -0,0 +1,459 @@
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![warn(
+    clippy::all,
+    clippy::todo,
+    clippy::empty_enums,
+    clippy::mem_forget,
+    clippy::unused_self,
+    clippy::filter_map_next,
+    clippy::needless_continue,
+    clippy::needless_borrow,
+    clippy::match_wildcard_for_single_variants,
+    clippy::if_let_mutex,
+    clippy::await_holding_lock,
+    clippy::imprecise_flops,
+    clippy::suboptimal_flops,
+    clippy::lossy_float_literal,
+    clippy::rest_pat_in_fully_bound_structs,
+    clippy::fn_params_excessive_bools,
+    clippy::exit,
+    clippy::inefficient_to_string,
+    clippy::linkedlist,
+    clippy::macro_use_imports,
+    clippy::option_option,
+    clippy::verbose_file_reads,
+    clippy::unnested_or_patterns,
+    rust_2018_idioms,
+    future_incompatible,
+    nonstandard_style,
+    missing_docs
+)]
+
+//! # A Postgres adapter implementation for the socketioxide crate.
+//! The adapter is used to communicate with other nodes of the same application.
+//! This allows to broadcast messages to sockets connected on other servers,
+//! to get the list of rooms, to add or remove sockets from rooms, etc.
+//!
+//! To achieve this, the adapter uses the postgres [`LISTEN/NOTIFY`](https://www.postgresql.org/docs/current/sql-notify.html)
+//! feature to communicate with other servers. It is well suited for small clusters that already run
+//! Postgres and don't want to add another service such as Redis.
+//!
+//! The [`Driver`] abstraction allows the use of any Postgres client.
+//! One implementation is provided:
+//! * [`PostgresDriver`](crate::drivers::postgres::PostgresDriver) for the [`tokio_postgres`] crate.
+//!
+//! You can also implement your own driver by implementing the [`Driver`] trait.
+//!
+//! ## Example with the [`tokio_postgres`] driver
+//! ```rust
+//! # use socketioxide::{SocketIo, extract::{SocketRef, Data}, adapter::Adapter};
+//! # use socketioxide_postgres::{PostgresAdapterCtr, PostgresAdapter};
+//! # async fn doc_main() -> Result<(), Box<dyn std::error::Error>> {
+//! async fn on_connect<A: Adapter>(socket: SocketRef<A>) {
+//!     socket.join("room1");
+//!     socket.on("event", on_event);
+//!     let _ = socket.broadcast().emit("hello", "world").await.ok();
+//! }
+//! async fn on_event<A: Adapter>(socket: SocketRef<A>, Data(data): Data<String>) {}
+//!
+//! let (client, connection) =
+//!     tokio_postgres::connect("host=localhost user=postgres", tokio_postgres::NoTls).await?;
+//! let adapter = PostgresAdapterCtr::new_with_postgres(client, connection);
+//! let (layer, io) = SocketIo::builder()
+//!     .with_adapter::<PostgresAdapter<_>>(adapter)
+//!     .build_layer();
+//! Ok(())
+//! # }
+//! ```
+//!
+//! ## How does it work?
+//!
+//! An adapter is created for each created namespace and it takes a corresponding [`CoreLocalAdapter`](socketioxide_core::adapter::CoreLocalAdapter).
+//! The `CoreLocalAdapter` allows to manage the local rooms and local sockets. The default `LocalAdapter`
+//! is simply a wrapper around this `CoreLocalAdapter`.
+//!
+//! The adapter is then initialized with the [`PostgresAdapter::init`](RemoteAdapter#method.init) method.
+//! This will listen to the `"{prefix}#{namespace}"` channel, shared by all the servers.
+//! Each message sent to this channel contains a header with the uid of the sender, the uid of the targeted server
+//! if any and a msgpack encoded request or response. Responses will be always in the form `[req_id, data]`.
+//! This will allow the adapter to extract the request id and route the response to the approriate stream
+//! before deserializing the data.
+//!
+//! Postgres limits the notification payloads to 8000 bytes. Therefore messages are split into
+//! base64 encoded chunks that are reassembled by the receiving servers.
+//!
+//! There are 10 types of requests:
+//! * Broadcast a packet to all the matching sockets.
+//! * Broadcast a packet to all the matching sockets and wait for a stream of acks.
+//! * Disconnect matching sockets.
+//! * Get all the rooms.
+//! * Add matching sockets to rooms.
+//! * Remove matching sockets to rooms.
+//! * Fetch all the remote sockets matching the options.
+//! * Handle a polling request for an engine.io session open on another server
+//!   (only used when the session handoff is enabled on the socket.io server).
+//! * Heartbeat
+//! * Initial heartbeat. When receiving a initial heartbeat all other servers reply a heartbeat immediately.
+//!
+//! Postgres doesn't provide a simple way to count the servers listening to a channel.
+//! Therefore each server emits a heartbeat at a regular interval and keeps track of the other servers
+//! that sent a heartbeat recently. This count is used to know how many responses to expect.
+//!
+//! For ack streams, the adapter will first send a `BroadcastAckCount` response to the server that sent the request,
+//! and then send the acks as they are received (more details in [`PostgresAdapter::broadcast_with_ack`](RemoteAdapter#method.broadcast_with_ack) fn).
+//!
+//! On the other side, each time an action has to be performed on the local server, the adapter will
+//! first broadcast a request to all the servers and then perform the action locally.
+
+use std::{
+    borrow::Cow,
+    fmt, future,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use chunk::{Chunk, Header, MessageKind, Reassembler};
+use drivers::Driver;
+use futures_core::stream::BoxStream;
+use futures_util::StreamExt;
+use socketioxide_core::{
+    remote::adapter::{self, Incoming, RemoteAdapter, RemoteOptions, Target, Transport},
+    Uid,
+};
+
+/// Drivers are an abstraction over the Postgres client used by the adapter.
+/// You can use the provided implementation or implement your own.
+pub mod drivers;
+
+mod chunk;
+
+/// Represent any error that might happen when using this adapter.
+pub type Error<D> = adapter::Error<<D as Driver>::Error>;
+
+/// The configuration of the [`PostgresAdapter`].
+#[derive(Debug, Clone)]
+pub struct PostgresAdapterConfig {
+    /// The heartbeat timeout duration. If a remote node does not respond within this duration,
+    /// it will be considered disconnected. Default is 10 seconds.
+    pub hb_timeout: Duration,
+    /// The heartbeat interval duration. The current node will send a heartbeat to the
+    /// other nodes at this interval. Default is 5 seconds.
+    pub hb_interval: Duration,
+    /// The request timeout. It is mainly used when expecting response such as when using
+    /// `broadcast_with_ack` or `rooms`. Default is 5 seconds.
+    pub request_timeout: Duration,
+
+    /// The prefix used for the channels. Default is "socket.io".
+    pub prefix: Cow<'static, str>,
+
+    /// The maximum size of the data sent in a single notification. Default is 5900 bytes.
+    ///
+    /// Bigger messages are split into multiple notifications. Once base64 encoded, a chunk of 5900 bytes
+    /// fits in the 8000 bytes limit of postgres notifications, so it is also the maximum value.
+    pub chunk_size: usize,
+
+    /// The channel size used to receive ack responses. Default is 255.
+    ///
+    /// If you have a lot of servers/sockets and that you may miss acknowledgement because they arrive faster
+    /// than you poll them with the returned stream, you might want to increase this value.
+    pub ack_response_buffer: usize,
+
+    /// The channel size used to receive notifications. Default is 1024.
+    ///
+    /// If your server is under heavy load, you might want to increase this value.
+    pub stream_buffer: usize,
+}
+impl PostgresAdapterConfig {
+    /// Create a new config.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Set the heartbeat timeout duration. Default is 10 seconds.
+    ///
+    /// If a remote node does not send a heartbeat within this duration, it will be considered disconnected.
+    pub fn with_hb_timeout(mut self, timeout: Duration) -> Self {
+        self.hb_timeout = timeout;
+        self
+    }
+
+    /// Set the heartbeat interval duration. Default is 5 seconds.
+    pub fn with_hb_interval(mut self, interval: Duration) -> Self {
+        self.hb_interval = interval;
+        self
+    }
+
+    /// Set the request timeout. Default is 5 seconds.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Set the prefix used for the channels. Default is "socket.io".
+    ///
+    /// The channel name `"{prefix}#{namespace}"` must not be longer than 63 bytes.
+    pub fn with_prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set the maximum size of the data sent in a single notification. Default is 5900 bytes.
+    ///
+    /// Bigger messages are split into multiple notifications.
+    pub fn with_chunk_size(mut self, size: usize) -> Self {
+        assert!(
+            size > 0 && size <= chunk::MAX_CHUNK_SIZE,
+            "chunk size must be between 1 and {}",
+            chunk::MAX_CHUNK_SIZE
+        );
+        self.chunk_size = size;
+        self
+    }
+
+    /// Set the channel size used to send ack responses. Default is 255.
+    ///
+    /// If you have a lot of servers/sockets and that you may miss acknowledgement because they arrive faster
+    /// than you poll them with the returned stream, you might want to increase this value.
+    pub fn with_ack_response_buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "buffer size must be greater than 0");
+        self.ack_response_buffer = buffer;
+        self
+    }
+
+    /// Set the channel size used to receive items. Default is 1024.
+    ///
+    /// If your server is under heavy load, you might want to increase this value.
+    pub fn with_stream_buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "buffer size must be greater than 0");
+        self.stream_buffer = buffer;
+        self
+    }
+}
+
+impl Default for PostgresAdapterConfig {
+    fn default() -> Self {
+        Self {
+            hb_timeout: Duration::from_secs(10),
+            hb_interval: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(5),
+            prefix: Cow::Borrowed("socket.io"),
+            chunk_size: chunk::MAX_CHUNK_SIZE,
+            ack_response_buffer: 255,
+            stream_buffer: 1024,
+        }
+    }
+}
+
+/// The adapter constructor. For each namespace you define, a new adapter instance is created
+/// from this constructor.
+#[derive(Debug)]
+pub struct PostgresAdapterCtr<D> {
+    driver: D,
+    config: PostgresAdapterConfig,
+}
+
+#[cfg(feature = "postgres")]
+impl PostgresAdapterCtr<drivers::postgres::PostgresDriver> {
+    /// Create a new adapter constructor with the [`tokio_postgres`] driver and a default config.
+    ///
+    /// The connection is spawned by the driver, it must not be spawned elsewhere.
+    #[cfg_attr(docsrs, doc(cfg(feature = "postgres")))]
+    pub fn new_with_postgres<S, T>(
+        client: tokio_postgres::Client,
+        connection: tokio_postgres::Connection<S, T>,
+    ) -> Self
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        Self::new_with_postgres_config(client, connection, PostgresAdapterConfig::default())
+    }
+    /// Create a new adapter constructor with the [`tokio_postgres`] driver and a custom config.
+    ///
+    /// The connection is spawned by the driver, it must not be spawned elsewhere.
+    #[cfg_attr(docsrs, doc(cfg(feature = "postgres")))]
+    pub fn new_with_postgres_config<S, T>(
+        client: tokio_postgres::Client,
+        connection: tokio_postgres::Connection<S, T>,
+        config: PostgresAdapterConfig,
+    ) -> Self
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        let driver = drivers::postgres::PostgresDriver::new(client, connection);
+        Self::new_with_driver(driver, config)
+    }
+}
+impl<D: Driver> PostgresAdapterCtr<D> {
+    /// Create a new adapter constructor with a custom Postgres driver and a config.
+    ///
+    /// You can implement your own driver by implementing the [`Driver`] trait with any Postgres client.
+    /// Check the [`drivers`] module for more information.
+    pub fn new_with_driver(driver: D, config: PostgresAdapterConfig) -> PostgresAdapterCtr<D> {
+        PostgresAdapterCtr { driver, config }
+    }
+}
+
+/// The postgres adapter with the tokio-postgres driver.
+#[cfg_attr(docsrs, doc(cfg(feature = "postgres")))]
+#[cfg(feature = "postgres")]
+pub type PostgresAdapter<E> = CustomPostgresAdapter<E, drivers::postgres::PostgresDriver>;
+
+/// The postgres adapter implementation.
+/// It is generic over the [`Driver`] used to communicate with the Postgres server.
+/// And over the [`SocketEmitter`](socketioxide_core::adapter::SocketEmitter) used to communicate
+/// with the local server. This allows to avoid cyclic dependencies between the adapter,
+/// `socketioxide-core` and `socketioxide` crates.
+pub type CustomPostgresAdapter<E, D> = RemoteAdapter<E, PostgresTransport<D>>;
+
+/// The postgres [`Transport`] of the [`CustomPostgresAdapter`], created for each namespace.
+///
+/// All the servers of a namespace share a single channel, the messages are split into chunks
+/// that fit in postgres notifications. The servers are only counted with the heartbeats.
+pub struct PostgresTransport<D> {
+    /// The driver used by the adapter. This is used to communicate with the Postgres server.
+    /// All the postgres adapter instances share the same driver.
+    driver: D,
+    /// The configuration of the adapter.
+    config: PostgresAdapterConfig,
+    /// The options of the remote adapter, from the configuration.
+    opts: RemoteOptions,
+    /// A unique identifier for the adapter to identify itself in the Postgres server.
+    uid: Uid,
+    /// The channel shared by all the servers for this namespace.
+    /// format: `{prefix}#{path}`.
+    channel: String,
+    /// The id of the next message sent by this server, used to reassemble the chunks.
+    msg_id: AtomicU32,
+}
+
+impl<D: Driver> Transport for PostgresTransport<D> {
+    type State = PostgresAdapterCtr<D>;
+    type Error = D::Error;
+    type InitError = InitError<D>;
+
+    fn new(state: &Self::State, path: &str, uid: Uid) -> Self {
+        let config = state.config.clone();
+        let opts = RemoteOptions {
+            request_timeout: config.request_timeout,
+            ack_response_buffer: config.ack_response_buffer,
+            hb_timeout: config.hb_timeout,
+            hb_interval: config.hb_interval,
+            ..Default::default()
+        };
+        Self {
+            driver: state.driver.clone(),
+            channel: format!("{}#{}", config.prefix, path),
+            config,
+            opts,
+            uid,
+            msg_id: AtomicU32::new(0),
+        }
+    }
+
+    fn options(&self) -> &RemoteOptions {
+        &self.opts
+    }
+
+    async fn init(&self) -> Result<BoxStream<'static, Incoming>, InitError<D>> {
+        if self.channel.len() > MAX_CHANNEL_LEN {
+            return Err(InitError::ChannelTooLong(self.channel.clone()));
+        }
+        let stream = self
+            .driver
+            .listen(&self.channel, self.config.stream_buffer)
+            .await
+            .map_err(InitError::Driver)?;
+
+        let uid = self.uid;
+        let mut reassembler = Reassembler::new(self.config.request_timeout);
+        let stream = stream.filter_map(move |payload| {
+            let chunk = match Chunk::decode(&payload) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    tracing::warn!("error decoding notification: {e}");
+                    return future::ready(None);
+                }
+            };
+            let header = chunk.header;
+            // Postgres also sends the notifications to the server that sent them.
+            if header.uid == uid || header.target.is_some_and(|t| t != uid) {
+                return future::ready(None);
+            }
+            let item = reassembler
+                .push(chunk)
+                .map(|(header, data)| match header.kind {
+                    MessageKind::Request => Incoming::Request(data),
+                    MessageKind::Response => Incoming::Response(data),
+                });
+            future::ready(item)
+        });
+        Ok(stream.boxed())
+    }
+
+    async fn close(&self) -> Result<(), D::Error> {
+        self.driver.unlisten(&self.channel).await
+    }
+
+    async fn send_req(&self, data: Vec<u8>, target: Target<'_>) -> Result<(), Error<D>> {
+        let target = match target {
+            Target::Server(uid) => Some(uid),
+            Target::All | Target::Room(_) => None,
+        };
+        self.notify(MessageKind::Request, target, &data).await
+    }
+
+    async fn send_res(&self, data: Vec<u8>, target: Uid) -> Result<(), Error<D>> {
+        self.notify(MessageKind::Response, Some(target), &data)
+            .await
+    }
+}
+
+/// Error that can happen when initializing the adapter.
+#[derive(thiserror::Error)]
+pub enum InitError<D: Driver> {
+    /// Driver error.
+    #[error("driver error: {0}")]
+    Driver(D::Error),
+    /// The channel name is longer than the 63 bytes allowed by postgres.
+    #[error("channel name {0} is longer than 63 bytes")]
+    ChannelTooLong(String),
+}
+impl<D: Driver> fmt::Debug for InitError<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Driver(err) => fmt::Debug::fmt(err, f),
+            Self::ChannelTooLong(channel) => write!(f, "Channel too long: {channel}"),
+        }
+    }
+}
+/// The result of the init future.
+pub type InitRes<D> = adapter::InitRes<InitError<D>>;
+
+impl<D: Driver> PostgresTransport<D> {
+    /// Split a message into chunks and send them in order on the channel.
+    async fn notify(
+        &self,
+        kind: MessageKind,
+        target: Option<Uid>,
+        data: &[u8],
+    ) -> Result<(), Error<D>> {
+        let header = Header {
+            uid: self.uid,
+            target,
+            kind,
+        };
+        let id = self.msg_id.fetch_add(1, Ordering::Relaxed);
+        let payloads = chunk::split(header, id, data, self.config.chunk_size)?;
+        for payload in payloads {
+            self.driver
+                .notify(&self.channel, &payload)
+                .await
+                .map_err(adapter::Error::Driver)?;
+        }
+        Ok(())
+    }
+}
+
+/// Postgres identifiers, and therefore channel names, are limited to 63 bytes.
+const MAX_CHANNEL_LEN: usize = 63;
@@ -0,0 +1,30 @@
+use socketioxide::{adapter::Adapter, extract::SocketRef};
+use socketioxide_adapter_tests::{timeout_rcv, timeout_rcv_err};
+mod fixture;
+
+#[tokio::test]
+pub async fn broadcast_chunked() {
+    async fn handler<A: Adapter>(socket: SocketRef<A>) {
+        // delay to ensure all socket/servers are connected
+        tokio::time::sleep(tokio::time::Duration::from_millis(1)).await;
+        let data = "a".repeat(20_000);
+        socket.broadcast().emit("test", &data).await.unwrap();
+    }
+
+    let [io1, io2] = fixture::spawn_servers();
+
+    io1.ns("/", handler).await.unwrap();
+    io2.ns("/", || ()).await.unwrap();
+
+    let ((_tx1, mut rx1), (_tx2, mut rx2)) =
+        tokio::join!(io1.new_dummy_sock("/", ()), io2.new_dummy_sock("/", ()));
+
+    timeout_rcv!(&mut rx1); // Connect "/" packet
+    timeout_rcv!(&mut rx2); // Connect "/" packet
+
+    let expected = format!(r#"42["test","{}"]"#, "a".repeat(20_000));
+    assert_eq!(timeout_rcv!(&mut rx2), expected);
+
+    timeout_rcv_err!(&mut rx1);
+    timeout_rcv_err!(&mut rx2);
+}
@@ -0,0 +1,68 @@
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use socketioxide::SocketIo;
+use socketioxide_adapter_tests::stub::{self, StubAdapter, StubBus};
+use socketioxide_postgres::{
+    drivers::{Driver, MessageStream},
+    PostgresAdapterConfig, PostgresAdapterCtr, PostgresTransport,
+};
+
+/// The fixture of the common adapter test suite.
+pub struct StubFixture;
+impl stub::StubFixture for StubFixture {
+    type Transport = PostgresTransport<StubDriver>;
+    type Message = (String, String);
+    type Config = PostgresAdapterConfig;
+
+    fn state(
+        bus: StubBus<(String, String)>,
+        config: PostgresAdapterConfig,
+    ) -> PostgresAdapterCtr<StubDriver> {
+        PostgresAdapterCtr::new_with_driver(StubDriver { bus }, config)
+    }
+    fn with_request_timeout(
+        config: PostgresAdapterConfig,
+        timeout: Duration,
+    ) -> PostgresAdapterConfig {
+        config.with_request_timeout(timeout)
+    }
+}
+
+/// Spawns a number of servers with a stub driver for testing.
+/// Every server will be connected to every other server.
+pub fn spawn_servers<const N: usize>() -> [SocketIo<StubAdapter<StubFixture>>; N] {
+    stub::spawn_servers_with_config::<StubFixture, N>(PostgresAdapterConfig::default())
+}
+
+/// A driver that dispatches the notifications to all the listeners of their channel,
+/// including the sender, like postgres would do.
+#[derive(Debug, Clone)]
+pub struct StubDriver {
+    bus: StubBus<(String, String)>,
+}
+
+impl Driver for StubDriver {
+    type Error = std::convert::Infallible;
+
+    async fn listen(&self, channel: &str, _: usize) -> Result<MessageStream<String>, Self::Error> {
+        let channel = channel.to_string();
+        let stream = self
+            .bus
+            .subscribe(move |(chan, payload): &(String, String)| {
+                (*chan == channel).then(|| payload.clone())
+            });
+        Ok(stream)
+    }
+
+    async fn unlisten(&self, _: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn notify(&self, channel: &str, payload: &str) -> Result<(), Self::Error> {
+        assert!(payload.len() < 8000, "notification payload too long");
+        self.bus.publish((channel.to_string(), payload.to_string()));
+        Ok(())
+    }
+}
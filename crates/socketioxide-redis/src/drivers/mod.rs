@@ -1,52 +1,21 @@
-use std::{future::Future, pin::Pin, task};
-
-use futures_core::Stream;
-use pin_project_lite::pin_project;
-use tokio::sync::mpsc;
+use std::future::Future;
 
 /// A driver implementation for the [redis](docs.rs/redis) pub/sub backend.
 #[cfg(feature = "redis")]
 #[cfg_attr(docsrs, doc(cfg(feature = "redis")))]
 pub mod redis;
 
+/// A driver implementation for the [redis](docs.rs/redis) streams backend.
+#[cfg(feature = "redis")]
+#[cfg_attr(docsrs, doc(cfg(feature = "redis")))]
+pub mod streams;
+
 /// A driver implementation for the [fred](docs.rs/fred) pub/sub backend.
 #[cfg(feature = "fred")]
 #[cfg_attr(docsrs, doc(cfg(feature = "fred")))]
 pub mod fred;
 
-pin_project! {
-    /// A stream of raw messages received from a channel.
-    /// Messages are encoded with msgpack.
-    #[derive(Debug)]
-    pub struct MessageStream<T> {
-        #[pin]
-        rx: mpsc::Receiver<T>,
-    }
-}
-
-impl<T> MessageStream<T> {
-    /// Create a new empty message stream.
-    pub fn new_empty() -> Self {
-        // mpsc bounded channel requires buffer > 0
-        let (_, rx) = mpsc::channel(1);
-        Self { rx }
-    }
-    /// Create a new message stream from a receiver.
-    pub fn new(rx: mpsc::Receiver<T>) -> Self {
-        Self { rx }
-    }
-}
-
-impl<T> Stream for MessageStream<T> {
-    type Item = T;
-
-    fn poll_next(
-        self: Pin<&mut Self>,
-        cx: &mut task::Context<'_>,
-    ) -> task::Poll<Option<Self::Item>> {
-        self.project().rx.poll_recv(cx)
-    }
-}
+pub use socketioxide_core::remote::MessageStream;
 
 /// A message item that can be returned from a channel.
 pub type ChanItem = (String, Vec<u8>);
@@ -0,0 +1,314 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use redis::{
+    aio::MultiplexedConnection,
+    streams::{
+        StreamInfoConsumersReply, StreamInfoGroupsReply, StreamMaxlen, StreamReadOptions,
+        StreamReadReply,
+    },
+    AsyncCommands, RedisResult,
+};
+use socketioxide_core::Uid;
+use tokio::{sync::mpsc, task::AbortHandle};
+
+use super::{redis::RedisError, ChanItem, Driver, MessageStream};
+
+/// Destroy the consumer group of the server and remove the stream once it has no more groups.
+const LEAVE_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    return 0
+end
+redis.call('XGROUP', 'DESTROY', KEYS[1], ARGV[1])
+if #redis.call('XINFO', 'GROUPS', KEYS[1]) == 0 then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// The configuration of the [`StreamsDriver`].
+#[derive(Debug, Clone)]
+pub struct StreamsConfig {
+    /// The name of the consumer group of the server. It must be unique for each server.
+    /// Default is a random name.
+    ///
+    /// The position of the server in the streams is stored by its consumer group. Set a name that
+    /// stays the same across the restarts of the server to replay the messages published while it was down.
+    pub group: Cow<'static, str>,
+
+    /// The approximate maximum number of messages kept in each stream. Default is 10 000.
+    pub max_len: usize,
+
+    /// The maximum number of messages read from a stream at once. Default is 100.
+    pub read_count: usize,
+
+    /// The maximum duration of a blocking read on a stream. Default is 1 second.
+    ///
+    /// A server is considered gone once it didn't read a stream for twice this duration.
+    pub block_timeout: Duration,
+}
+
+impl StreamsConfig {
+    /// Create a new config.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the name of the consumer group of the server. Default is a random name.
+    ///
+    /// Set a name that stays the same across the restarts of the server to replay the messages
+    /// published while it was down. It must be unique for each server.
+    pub fn with_group(mut self, group: impl Into<Cow<'static, str>>) -> Self {
+        self.group = group.into();
+        self
+    }
+
+    /// Set the approximate maximum number of messages kept in each stream. Default is 10 000.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        assert!(max_len > 0, "max len must be greater than 0");
+        self.max_len = max_len;
+        self
+    }
+
+    /// Set the maximum number of messages read from a stream at once. Default is 100.
+    pub fn with_read_count(mut self, count: usize) -> Self {
+        assert!(count > 0, "read count must be greater than 0");
+        self.read_count = count;
+        self
+    }
+
+    /// Set the maximum duration of a blocking read on a stream. Default is 1 second.
+    pub fn with_block_timeout(mut self, timeout: Duration) -> Self {
+        self.block_timeout = timeout;
+        self
+    }
+}
+
+impl Default for StreamsConfig {
+    fn default() -> Self {
+        Self {
+            group: Cow::Owned(Uid::new().to_string()),
+            max_len: 10_000,
+            read_count: 100,
+            block_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+/// A driver implementation for the [redis](docs.rs/redis) streams backend.
+///
+/// Each channel is a stream, read by each server through its own consumer group:
+/// * The messages published while a server is disconnected from redis, or down if its
+///   [group](StreamsConfig::group) is stable across restarts, are read once it is back.
+/// * The messages are acknowledged once received by the adapter, the unacknowledged ones
+///   are read again when the server subscribes to the stream.
+/// * The streams are trimmed to keep approximately [`max_len`](StreamsConfig::max_len) messages.
+///
+/// A server closing gracefully removes its consumer groups, the messages published
+/// until it subscribes again are not kept for it.
+///
+/// Each subscribed channel uses a dedicated connection for its blocking reads.
+#[derive(Clone)]
+pub struct StreamsDriver {
+    client: redis::Client,
+    conn: MultiplexedConnection,
+    config: Arc<StreamsConfig>,
+    readers: Arc<Mutex<HashMap<String, AbortHandle>>>,
+}
+
+impl StreamsDriver {
+    /// Create a new redis streams driver from a redis client and a default config.
+    pub async fn new(client: &redis::Client) -> Result<Self, redis::RedisError> {
+        Self::new_with_config(client, StreamsConfig::default()).await
+    }
+
+    /// Create a new redis streams driver from a redis client and a custom config.
+    pub async fn new_with_config(
+        client: &redis::Client,
+        config: StreamsConfig,
+    ) -> Result<Self, redis::RedisError> {
+        let conn = client.get_multiplexed_async_connection().await?;
+        Ok(Self {
+            client: client.clone(),
+            conn,
+            config: Arc::new(config),
+            readers: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+}
+
+/// Create the consumer group of the server, reading the messages published from now on.
+/// If the group already exists, the server resumes from its last read message.
+async fn create_group(
+    conn: &mut MultiplexedConnection,
+    chan: &str,
+    group: &str,
+) -> RedisResult<()> {
+    match conn
+        .xgroup_create_mkstream::<_, _, _, ()>(chan, group, "$")
+        .await
+    {
+        Err(e) if e.code() != Some("BUSYGROUP") => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Extract the messages and their ids from a read reply.
+/// The messages trimmed from the stream while they were pending have no data and are only acknowledged.
+fn read_entries(reply: StreamReadReply) -> (Vec<ChanItem>, Vec<String>) {
+    let mut msgs = Vec::new();
+    let mut ids = Vec::new();
+    for key in reply.keys {
+        for entry in key.ids {
+            if let Some(data) = entry.get::<Vec<u8>>("data") {
+                msgs.push((key.key.clone(), data));
+            }
+            ids.push(entry.id);
+        }
+    }
+    (msgs, ids)
+}
+
+/// Read the messages of a stream with the consumer group of the server.
+/// The pending messages of the group are read first, then the new ones.
+/// It stops once the message stream is dropped or the reader is aborted.
+async fn read_stream(
+    mut conn: MultiplexedConnection,
+    chan: String,
+    config: Arc<StreamsConfig>,
+    tx: mpsc::Sender<ChanItem>,
+) {
+    let group = &*config.group;
+    let opts = StreamReadOptions::default()
+        .group(group, group)
+        .count(config.read_count)
+        .block(config.block_timeout.as_millis() as usize);
+    let mut id = "0";
+    while !tx.is_closed() {
+        let reply: Option<StreamReadReply> = match conn.xread_options(&[&chan], &[id], &opts).await
+        {
+            Ok(reply) => reply,
+            Err(e) => {
+                tracing::warn!(chan, "error reading redis stream: {e}");
+                // The group was removed, it is created again from the last message.
+                if e.code() == Some("NOGROUP") {
+                    create_group(&mut conn, &chan, group).await.ok();
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let (msgs, ids) = read_entries(reply.unwrap_or_default());
+        if ids.is_empty() {
+            id = ">";
+            continue;
+        }
+        for msg in msgs {
+            if tx.send(msg).await.is_err() {
+                return;
+            }
+        }
+        if let Err(e) = conn.xack::<_, _, _, ()>(&chan, group, &ids).await {
+            tracing::warn!(chan, "error acknowledging redis stream messages: {e}");
+        }
+    }
+}
+
+impl Driver for StreamsDriver {
+    type Error = RedisError;
+
+    async fn publish(&self, chan: String, val: Vec<u8>) -> Result<(), Self::Error> {
+        // Streams without any consumer group are not created.
+        redis::cmd("XADD")
+            .arg(chan)
+            .arg("NOMKSTREAM")
+            .arg(StreamMaxlen::Approx(self.config.max_len))
+            .arg("*")
+            .arg("data")
+            .arg(val)
+            .query_async::<redis::Value>(&mut self.conn.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        chan: String,
+        size: usize,
+    ) -> Result<MessageStream<ChanItem>, Self::Error> {
+        // Blocking reads would block all the other commands of a shared connection.
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        create_group(&mut conn, &chan, &self.config.group).await?;
+        let (tx, rx) = mpsc::channel(size);
+        let reader = tokio::spawn(read_stream(conn, chan.clone(), self.config.clone(), tx));
+        if let Some(prev) = self
+            .readers
+            .lock()
+            .unwrap()
+            .insert(chan, reader.abort_handle())
+        {
+            prev.abort();
+        }
+        Ok(MessageStream::new(rx))
+    }
+
+    async fn unsubscribe(&self, chan: String) -> Result<(), Self::Error> {
+        if let Some(reader) = self.readers.lock().unwrap().remove(&chan) {
+            reader.abort();
+        }
+        redis::cmd("EVAL")
+            .arg(LEAVE_SCRIPT)
+            .arg(1)
+            .arg(chan)
+            .arg(&*self.config.group)
+            .query_async::<redis::Value>(&mut self.conn.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn num_serv(&self, chan: &str) -> Result<u16, Self::Error> {
+        let mut conn = self.conn.clone();
+        let max_idle = 2 * self.config.block_timeout.as_millis() as usize;
+        let groups: StreamInfoGroupsReply = conn.xinfo_groups(chan).await?;
+        let mut count = 0;
+        for group in groups.groups {
+            let consumers: StreamInfoConsumersReply =
+                conn.xinfo_consumers(chan, group.name).await?;
+            if consumers.consumers.iter().any(|c| c.idle <= max_idle) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::streams::{StreamId, StreamKey};
+
+    use super::*;
+
+    #[test]
+    fn read_reply_entries() {
+        let entry = |id: &str, data: Option<&str>| StreamId {
+            id: id.to_string(),
+            map: data
+                .map(|data| ("data".to_string(), redis::Value::BulkString(data.into())))
+                .into_iter()
+                .collect(),
+        };
+        let reply = StreamReadReply {
+            keys: vec![StreamKey {
+                key: "test".to_string(),
+                ids: vec![entry("1-0", Some("foo")), entry("2-0", None)],
+            }],
+        };
+        let (msgs, ids) = read_entries(reply);
+        assert_eq!(msgs, vec![("test".to_string(), b"foo".to_vec())]);
+        assert_eq!(ids, vec!["1-0", "2-0"]);
+    }
+}
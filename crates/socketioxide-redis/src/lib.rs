@@ -2,7 +2,7 @@
 #![warn(
     clippy::all,
     clippy::todo,
-    clippy::empty_enum,
+    clippy::empty_enums,
     clippy::mem_forget,
     clippy::unused_self,
     clippy::filter_map_next,
@@ -11,7 +11,6 @@
     clippy::match_wildcard_for_single_variants,
     clippy::if_let_mutex,
     clippy::await_holding_lock,
-    clippy::match_on_vec_items,
     clippy::imprecise_flops,
     clippy::suboptimal_flops,
     clippy::lossy_float_literal,
@@ -43,6 +42,8 @@
 //! * [`RedisDriver`](crate::drivers::redis::RedisDriver) for the [`redis`] crate with a standalone redis.
 //! * [`ClusterDriver`](crate::drivers::redis::ClusterDriver) for the [`redis`] crate with a redis cluster.
 //! * [`FredDriver`](crate::drivers::fred::FredDriver) for the [`fred`] crate with a standalone/cluster redis.
+//! * [`StreamsDriver`](crate::drivers::streams::StreamsDriver) for the [`redis`] crate with
+//!   [redis streams](https://redis.io/docs/latest/develop/data-types/streams/) instead of pub/sub.
 //!
 //! When using redis clusters, the drivers employ [sharded pub/sub](https://redis.io/docs/latest/develop/interact/pubsub/#sharded-pubsub)
 //! to distribute the load across Redis nodes.
@@ -127,26 +128,87 @@
 //! # }
 //! ```
 //!
+//! ## Sharded pub/sub with a channel per room
+//! By default, every broadcast is published on the request channel of the namespace and received by all
+//! the servers. With the [`SubscriptionMode::Dynamic`] mode, the broadcasts to a single room are published
+//! on a channel specific to the room, only subscribed by the servers having sockets in this room.
+//!
+//! Combined with a driver using [sharded pub/sub](https://redis.io/docs/latest/develop/interact/pubsub/#sharded-pubsub)
+//! (`SSUBSCRIBE`/`SPUBLISH`), like the [`ClusterDriver`](crate::drivers::redis::ClusterDriver)
+//! or the [`FredDriver`](crate::drivers::fred::FredDriver), the room channels are distributed across the
+//! nodes of the cluster, to scale beyond the throughput of a single pub/sub channel.
+//! It is the equivalent of the sharded mode of the `@socket.io/redis-adapter` package.
+//!
+//! ```rust
+//! # use socketioxide::SocketIo;
+//! # use socketioxide_redis::{RedisAdapterCtr, ClusterAdapter, RedisAdapterConfig, SubscriptionMode};
+//! # async fn doc_main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = redis::cluster::ClusterClient::new(["redis://127.0.0.1:6379?protocol=resp3"])?;
+//! let config = RedisAdapterConfig::new().with_subscription_mode(SubscriptionMode::Dynamic);
+//! let adapter = RedisAdapterCtr::new_with_cluster_config(&client, config).await?;
+//!
+//! let (layer, io) = SocketIo::builder()
+//!     .with_adapter::<ClusterAdapter<_>>(adapter)
+//!     .build_layer();
+//! Ok(())
+//! # }
+//! ```
+//!
+//! ## Redis streams with replay
+//! With pub/sub, the messages published while a server is disconnected from redis are lost.
+//! The [`StreamsDriver`](crate::drivers::streams::StreamsDriver) publishes the messages on
+//! [redis streams](https://redis.io/docs/latest/develop/data-types/streams/), read by each server
+//! with its own consumer group. The messages missed during a brief outage are read once the server
+//! is back, as long as they are kept by the streams.
+//! It is the equivalent of the `@socket.io/redis-streams-adapter` package.
+//!
+//! A message may be read twice, for example when a server stops reading a stream before
+//! acknowledging its messages. With the [`dedup`](RedisAdapterConfig::dedup) option, each request
+//! is sent with a sequence number incremented by its server, and the requests already received are dropped,
+//! so that the clients never receive a broadcasted event twice.
+//!
+//! ```rust
+//! # use socketioxide::SocketIo;
+//! # use socketioxide_redis::{RedisAdapterCtr, RedisAdapterConfig, StreamsAdapter};
+//! # use socketioxide_redis::drivers::streams::{StreamsConfig, StreamsDriver};
+//! # async fn doc_main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = redis::Client::open("redis://127.0.0.1:6379?protocol=RESP3")?;
+//! let config = StreamsConfig::new()
+//!     // A stable name per server, to replay the messages missed during a restart
+//!     .with_group("server-1")
+//!     // Keep around 100 000 messages in each stream
+//!     .with_max_len(100_000);
+//! let driver = StreamsDriver::new_with_config(&client, config).await?;
+//! let config = RedisAdapterConfig::new().with_dedup(true);
+//! let adapter = RedisAdapterCtr::new_with_driver(driver, config);
+//!
+//! let (layer, io) = SocketIo::builder()
+//!     .with_adapter::<StreamsAdapter<_>>(adapter)
+//!     .build_layer();
+//! Ok(())
+//! # }
+//! ```
+//!
 //! Check the [`chat example`](https://github.com/Totodore/socketioxide/tree/main/examples/chat)
 //! for more complete examples.
 //!
 //! ## How does it work?
 //!
-//! An adapter is created for each created namespace and it takes a corresponding [`CoreLocalAdapter`].
-//! The [`CoreLocalAdapter`] allows to manage the local rooms and local sockets. The default `LocalAdapter`
-//! is simply a wrapper around this [`CoreLocalAdapter`].
+//! An adapter is created for each created namespace and it takes a corresponding [`CoreLocalAdapter`](socketioxide_core::adapter::CoreLocalAdapter).
+//! The `CoreLocalAdapter` allows to manage the local rooms and local sockets. The default `LocalAdapter`
+//! is simply a wrapper around this `CoreLocalAdapter`.
 //!
-//! The adapter is then initialized with the [`RedisAdapter::init`] method.
+//! The adapter is then initialized with the [`RedisAdapter::init`](RemoteAdapter#method.init) method.
 //! This will subscribe to 3 channels:
 //! * `"{prefix}-request#{namespace}#"`: A global channel to receive broadcasted requests.
 //! * `"{prefix}-request#{namespace}#{uid}#"`: A specific channel to receive requests only for this server.
 //! * `"{prefix}-response#{namespace}#{uid}#"`: A specific channel to receive responses only for this server.
-//!     Messages sent to this channel will be always in the form `[req_id, data]`. This will allow the adapter to extract the request id
-//!     and route the response to the approriate stream before deserializing the data.
+//!   Messages sent to this channel will be always in the form `[req_id, data]`. This will allow the adapter to extract the request id
+//!   and route the response to the approriate stream before deserializing the data.
 //!
 //! All messages are encoded with msgpack.
 //!
-//! There are 7 types of requests:
+//! There are 10 types of requests:
 //! * Broadcast a packet to all the matching sockets.
 //! * Broadcast a packet to all the matching sockets and wait for a stream of acks.
 //! * Disconnect matching sockets.
@@ -154,83 +216,55 @@
 //! * Add matching sockets to rooms.
 //! * Remove matching sockets to rooms.
 //! * Fetch all the remote sockets matching the options.
+//! * Handle a polling request for an engine.io session open on another server
+//!   (only used when the session handoff is enabled on the socket.io server).
+//! * Heartbeat
+//! * Initial heartbeat. When receiving an initial heartbeat all other servers reply a heartbeat immediately.
+//!
+//! The number of servers, used to know how many responses to expect, is the number of subscribers
+//! of the request channel. Each server also emits a heartbeat at a regular interval with its number of
+//! sockets, so that the servers that stopped responding are not counted and are not waited for.
+//! The servers that sent a heartbeat recently are listed with `io.cluster_nodes()`.
 //!
 //! For ack streams, the adapter will first send a `BroadcastAckCount` response to the server that sent the request,
-//! and then send the acks as they are received (more details in [`RedisAdapter::broadcast_with_ack`] fn).
+//! and then send the acks as they are received (more details in [`RedisAdapter::broadcast_with_ack`](RemoteAdapter#method.broadcast_with_ack) fn).
+//!
+//! With the [`SubscriptionMode::Dynamic`] mode, the adapter also subscribes to a channel for each of its local rooms:
+//! * `"{prefix}-request#{namespace}#room#{room}#"`: A channel to receive the broadcasts to this room only.
 //!
 //! On the other side, each time an action has to be performed on the local server, the adapter will
 //! first broadcast a request to all the servers and then perform the action locally.
 
-use std::{
-    borrow::Cow,
-    collections::HashMap,
-    fmt,
-    future::{self, Future},
-    pin::Pin,
-    sync::{Arc, Mutex},
-    task::{Context, Poll},
-    time::Duration,
-};
+use std::{borrow::Cow, collections::HashSet, fmt, future, sync::Mutex, time::Duration};
 
 use drivers::{ChanItem, Driver, MessageStream};
-use futures_core::Stream;
+use futures_core::stream::BoxStream;
 use futures_util::StreamExt;
-use request::{
-    read_req_id, RequestIn, RequestOut, RequestTypeIn, RequestTypeOut, Response, ResponseType,
-};
-use serde::{de::DeserializeOwned, Serialize};
 use socketioxide_core::{
-    adapter::{
-        BroadcastFlags, BroadcastOptions, CoreAdapter, CoreLocalAdapter, DefinedAdapter,
-        RemoteSocketData, Room, RoomParam, SocketEmitter, Spawnable,
-    },
-    errors::{AdapterError, BroadcastError},
-    packet::Packet,
-    Sid, Uid,
+    remote::adapter::{self, Incoming, RemoteAdapter, RemoteOptions, Target, Transport},
+    Uid,
 };
-use stream::{AckStream, DropStream};
-use tokio::{sync::mpsc, time};
 
 /// Drivers are an abstraction over the pub/sub backend used by the adapter.
 /// You can use the provided implementation or implement your own.
 pub mod drivers;
 
-mod request;
-mod stream;
-
 /// Represent any error that might happen when using this adapter.
-#[derive(thiserror::Error)]
-pub enum Error<R: Driver> {
-    /// Redis driver error
-    #[error("driver error: {0}")]
-    Driver(R::Error),
-    /// Packet encoding error
-    #[error("packet encoding error: {0}")]
-    Decode(#[from] rmp_serde::decode::Error),
-    /// Packet decoding error
-    #[error("packet decoding error: {0}")]
-    Encode(#[from] rmp_serde::encode::Error),
-}
-
-impl<R: Driver> Error<R> {
-    fn from_driver(err: R::Error) -> Self {
-        Self::Driver(err)
-    }
-}
-impl<R: Driver> fmt::Debug for Error<R> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Driver(err) => write!(f, "Driver error: {:?}", err),
-            Self::Decode(err) => write!(f, "Decode error: {:?}", err),
-            Self::Encode(err) => write!(f, "Encode error: {:?}", err),
-        }
-    }
-}
-
-impl<R: Driver> From<Error<R>> for AdapterError {
-    fn from(err: Error<R>) -> Self {
-        AdapterError::from(Box::new(err) as Box<dyn std::error::Error + Send>)
-    }
+pub type Error<R> = adapter::Error<<R as Driver>::Error>;
+
+/// The subscription mode of the adapter, set with [`RedisAdapterConfig::with_subscription_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubscriptionMode {
+    /// The broadcasts are published on the request channel of the namespace,
+    /// received by all the servers.
+    #[default]
+    Static,
+    /// The broadcasts to a single room are published on a channel specific to the room.
+    /// Each server subscribes to the channels of its local rooms, created and deleted with the rooms.
+    ///
+    /// **Note**: a broadcast published right after a socket joins a new room on another server
+    /// may be missed by this server, until it is subscribed to the channel of the room.
+    Dynamic,
 }
 
 /// The configuration of the [`RedisAdapter`].
@@ -253,6 +287,37 @@ pub struct RedisAdapterConfig {
     ///
     /// If your server is under heavy load, you might want to increase this value.
     pub stream_buffer: usize,
+
+    /// The subscription mode of the adapter. Default is [`SubscriptionMode::Static`].
+    pub subscription_mode: SubscriptionMode,
+
+    /// Whether the servers send heartbeats to track the other servers of the cluster. Default is true.
+    ///
+    /// Without heartbeats, the servers are counted with the subscribers of the request channel,
+    /// including the servers that stopped responding while keeping their connection to redis open.
+    pub heartbeat: bool,
+
+    /// The heartbeat timeout duration. If a remote node does not send a heartbeat within this duration,
+    /// it will be considered disconnected. Default is 10 seconds.
+    pub hb_timeout: Duration,
+
+    /// The heartbeat interval duration. The current node will send a heartbeat to the
+    /// other nodes at this interval. Default is 5 seconds.
+    pub hb_interval: Duration,
+
+    /// Whether the requests redelivered by the driver are dropped. Default is false.
+    ///
+    /// Each request is sent with a sequence number incremented by its server, so that the requests
+    /// received twice are ignored. It should be enabled with the drivers that may deliver a message
+    /// more than once, like the [`StreamsDriver`](drivers::streams::StreamsDriver).
+    pub dedup: bool,
+
+    /// The number of sequence numbers remembered for each server to detect the duplicated requests.
+    /// Default is 1024.
+    ///
+    /// The requests of a server received after more than this number of its newer requests
+    /// are considered as duplicates and dropped.
+    pub dedup_window: u64,
 }
 impl RedisAdapterConfig {
     /// Create a new config.
@@ -289,6 +354,55 @@ impl RedisAdapterConfig {
         self.stream_buffer = buffer;
         self
     }
+
+    /// Set the subscription mode of the adapter. Default is [`SubscriptionMode::Static`].
+    ///
+    /// With [`SubscriptionMode::Dynamic`], the broadcasts to a single room are published on a channel
+    /// specific to the room. It should be used with a driver using sharded pub/sub.
+    pub fn with_subscription_mode(mut self, mode: SubscriptionMode) -> Self {
+        self.subscription_mode = mode;
+        self
+    }
+
+    /// Enable or disable the heartbeats. Default is true.
+    ///
+    /// Without heartbeats, the servers are counted with the subscribers of the request channel,
+    /// including the servers that stopped responding while keeping their connection to redis open.
+    pub fn with_heartbeat(mut self, heartbeat: bool) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Set the heartbeat timeout duration. Default is 10 seconds.
+    ///
+    /// If a remote node does not send a heartbeat within this duration, it will be considered disconnected.
+    pub fn with_hb_timeout(mut self, timeout: Duration) -> Self {
+        self.hb_timeout = timeout;
+        self
+    }
+
+    /// Set the heartbeat interval duration. Default is 5 seconds.
+    pub fn with_hb_interval(mut self, interval: Duration) -> Self {
+        self.hb_interval = interval;
+        self
+    }
+
+    /// Enable or disable the deduplication of the requests redelivered by the driver. Default is false.
+    ///
+    /// It should be enabled with the drivers that may deliver a message more than once,
+    /// like the [`StreamsDriver`](drivers::streams::StreamsDriver).
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Set the number of sequence numbers remembered for each server to detect the duplicated requests.
+    /// Default is 1024.
+    pub fn with_dedup_window(mut self, window: u64) -> Self {
+        assert!(window > 0, "dedup window must be greater than 0");
+        self.dedup_window = window;
+        self
+    }
 }
 
 impl Default for RedisAdapterConfig {
@@ -298,6 +412,12 @@ impl Default for RedisAdapterConfig {
             prefix: Cow::Borrowed("socket.io"),
             ack_response_buffer: 255,
             stream_buffer: 1024,
+            subscription_mode: SubscriptionMode::Static,
+            heartbeat: true,
+            hb_timeout: Duration::from_secs(10),
+            hb_interval: Duration::from_secs(5),
+            dedup: false,
+            dedup_window: 1024,
         }
     }
 }
@@ -327,6 +447,28 @@ impl RedisAdapterCtr<drivers::redis::RedisDriver> {
         Ok(Self::new_with_driver(driver, config))
     }
 }
+#[cfg(feature = "redis")]
+impl RedisAdapterCtr<drivers::streams::StreamsDriver> {
+    /// Create a new adapter constructor with the [`redis`] streams driver and a default config,
+    /// with the [deduplication](RedisAdapterConfig::dedup) of the redelivered requests enabled.
+    #[cfg_attr(docsrs, doc(cfg(feature = "redis")))]
+    pub async fn new_with_streams(client: &redis::Client) -> redis::RedisResult<Self> {
+        let config = RedisAdapterConfig::default().with_dedup(true);
+        Self::new_with_streams_config(client, config).await
+    }
+    /// Create a new adapter constructor with the [`redis`] streams driver and a custom config.
+    ///
+    /// To configure the streams, create the [`StreamsDriver`](drivers::streams::StreamsDriver)
+    /// and use [`RedisAdapterCtr::new_with_driver`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "redis")))]
+    pub async fn new_with_streams_config(
+        client: &redis::Client,
+        config: RedisAdapterConfig,
+    ) -> redis::RedisResult<Self> {
+        let driver = drivers::streams::StreamsDriver::new(client).await?;
+        Ok(Self::new_with_driver(driver, config))
+    }
+}
 #[cfg(feature = "redis-cluster")]
 impl RedisAdapterCtr<drivers::redis::ClusterDriver> {
     /// Create a new adapter constructor with the [`redis`] driver and a default config.
@@ -376,8 +518,6 @@ impl<R: Driver> RedisAdapterCtr<R> {
     }
 }
 
-pub(crate) type ResponseHandlers = HashMap<Sid, mpsc::Sender<Vec<u8>>>;
-
 /// The redis adapter with the fred driver.
 #[cfg_attr(docsrs, doc(cfg(feature = "fred")))]
 #[cfg(feature = "fred")]
@@ -388,278 +528,158 @@ pub type FredAdapter<E> = CustomRedisAdapter<E, drivers::fred::FredDriver>;
 #[cfg(feature = "redis")]
 pub type RedisAdapter<E> = CustomRedisAdapter<E, drivers::redis::RedisDriver>;
 
+/// The redis adapter with the redis streams driver.
+#[cfg_attr(docsrs, doc(cfg(feature = "redis")))]
+#[cfg(feature = "redis")]
+pub type StreamsAdapter<E> = CustomRedisAdapter<E, drivers::streams::StreamsDriver>;
+
 /// The redis adapter with the redis cluster driver.
 #[cfg_attr(docsrs, doc(cfg(feature = "redis-cluster")))]
 #[cfg(feature = "redis-cluster")]
 pub type ClusterAdapter<E> = CustomRedisAdapter<E, drivers::redis::ClusterDriver>;
-
 /// The redis adapter implementation.
 /// It is generic over the [`Driver`] used to communicate with the redis server.
-/// And over the [`SocketEmitter`] used to communicate with the local server. This allows to
-/// avoid cyclic dependencies between the adapter, `socketioxide-core` and `socketioxide` crates.
-pub struct CustomRedisAdapter<E, R> {
+/// And over the [`SocketEmitter`](socketioxide_core::adapter::SocketEmitter) used to communicate
+/// with the local server. This allows to avoid cyclic dependencies between the adapter,
+/// `socketioxide-core` and `socketioxide` crates.
+pub type CustomRedisAdapter<E, R> = RemoteAdapter<E, RedisTransport<R>>;
+
+/// The redis [`Transport`] of the [`CustomRedisAdapter`], created for each namespace.
+pub struct RedisTransport<R> {
     /// The driver used by the adapter. This is used to communicate with the redis server.
     /// All the redis adapter instances share the same driver.
     driver: R,
     /// The configuration of the adapter.
     config: RedisAdapterConfig,
-    /// A unique identifier for the adapter to identify itself in the redis server.
+    /// The options of the remote adapter, from the configuration.
+    opts: RemoteOptions,
+    /// The namespace path.
+    path: String,
+    /// The unique identifier of the server.
     uid: Uid,
-    /// The local adapter, used to manage local rooms and socket stores.
-    local: CoreLocalAdapter<E>,
     /// The request channel used to broadcast requests to all the servers.
     /// format: `{prefix}-request#{path}#`.
     req_chan: String,
-    /// A map of response handlers used to await for responses from the remote servers.
-    responses: Arc<Mutex<ResponseHandlers>>,
+    /// The subscribed room channels, with the [`SubscriptionMode::Dynamic`] mode.
+    room_chans: Mutex<HashSet<String>>,
 }
 
-impl<E, R> DefinedAdapter for CustomRedisAdapter<E, R> {}
-impl<E: SocketEmitter, R: Driver> CoreAdapter<E> for CustomRedisAdapter<E, R> {
-    type Error = Error<R>;
+impl<R: Driver> Transport for RedisTransport<R> {
     type State = RedisAdapterCtr<R>;
-    type AckStream = AckStream<E::AckStream>;
-    type InitRes = InitRes<R>;
-
-    fn new(state: &Self::State, local: CoreLocalAdapter<E>) -> Self {
-        let req_chan = format!("{}-request#{}#", state.config.prefix, local.path());
-        let uid = local.server_id();
+    type Error = R::Error;
+    type InitError = InitError<R>;
+
+    fn new(state: &Self::State, path: &str, uid: Uid) -> Self {
+        let config = state.config.clone();
+        let opts = RemoteOptions {
+            request_timeout: config.request_timeout,
+            ack_response_buffer: config.ack_response_buffer,
+            heartbeat: config.heartbeat,
+            hb_timeout: config.hb_timeout,
+            hb_interval: config.hb_interval,
+            dedup: config.dedup,
+            dedup_window: config.dedup_window,
+        };
         Self {
-            local,
-            req_chan,
-            uid,
             driver: state.driver.clone(),
-            config: state.config.clone(),
-            responses: Arc::new(Mutex::new(HashMap::new())),
+            req_chan: format!("{}-request#{}#", config.prefix, path),
+            config,
+            opts,
+            path: path.to_string(),
+            uid,
+            room_chans: Mutex::new(HashSet::new()),
         }
     }
 
-    fn init(self: Arc<Self>, on_success: impl FnOnce() + Send + 'static) -> Self::InitRes {
-        let fut = async move {
-            check_ns(self.local.path())?;
-            let global_stream = self.subscribe(self.req_chan.clone()).await?;
-            let specific_stream = self.subscribe(self.get_req_chan(Some(self.uid))).await?;
-            let response_chan = format!(
-                "{}-response#{}#{}#",
-                &self.config.prefix,
-                self.local.path(),
-                self.uid
-            );
+    fn options(&self) -> &RemoteOptions {
+        &self.opts
+    }
 
-            let response_stream = self.subscribe(response_chan.clone()).await?;
-            let stream = futures_util::stream::select(global_stream, specific_stream);
-            let stream = futures_util::stream::select(stream, response_stream);
-            tokio::spawn(self.pipe_stream(stream, response_chan));
-            on_success();
-            Ok(())
-        };
-        InitRes(Box::pin(fut))
+    async fn init(&self) -> Result<BoxStream<'static, Incoming>, InitError<R>> {
+        check_ns(&self.path)?;
+        let global_stream = self.subscribe(self.req_chan.clone()).await?;
+        let specific_stream = self.subscribe(self.get_req_chan(Some(self.uid))).await?;
+        let response_chan = self.get_res_chan(self.uid);
+        let response_stream = self.subscribe(response_chan.clone()).await?;
+
+        let stream = futures_util::stream::select(global_stream, specific_stream);
+        let stream = futures_util::stream::select(stream, response_stream);
+        let req_chan = self.req_chan.clone();
+        let stream = stream.filter_map(move |(chan, item)| {
+            let item = if chan.starts_with(&req_chan) {
+                Some(Incoming::Request(item))
+            } else if chan == response_chan {
+                Some(Incoming::Response(item))
+            } else {
+                tracing::warn!("unexpected message/channel: {chan}");
+                None
+            };
+            future::ready(item)
+        });
+        Ok(stream.boxed())
     }
 
-    async fn close(&self) -> Result<(), Self::Error> {
-        let response_chan = format!(
-            "{}-response#{}#{}#",
-            &self.config.prefix,
-            self.local.path(),
-            self.uid
-        );
+    async fn close(&self) -> Result<(), R::Error> {
         tokio::try_join!(
             self.driver.unsubscribe(self.req_chan.clone()),
             self.driver.unsubscribe(self.get_req_chan(Some(self.uid))),
-            self.driver.unsubscribe(response_chan)
-        )
-        .map_err(Error::from_driver)?;
-
-        Ok(())
-    }
-
-    /// Get the number of servers by getting the number of subscribers to the request channel.
-    async fn server_count(&self) -> Result<u16, Self::Error> {
-        let count = self
-            .driver
-            .num_serv(&self.req_chan)
-            .await
-            .map_err(Error::from_driver)?;
+            self.driver.unsubscribe(self.get_res_chan(self.uid))
+        )?;
 
-        Ok(count)
-    }
-
-    /// Broadcast a packet to all the servers to send them through their sockets.
-    async fn broadcast(
-        &self,
-        packet: Packet,
-        opts: BroadcastOptions,
-    ) -> Result<(), BroadcastError> {
-        if !is_local_op(self.uid, &opts) {
-            let req = RequestOut::new(self.uid, RequestTypeOut::Broadcast(&packet), &opts);
-            self.send_req(req, opts.server_id)
-                .await
-                .map_err(AdapterError::from)?;
+        let room_chans = std::mem::take(&mut *self.room_chans.lock().unwrap());
+        for chan in room_chans {
+            self.driver.unsubscribe(chan).await?;
         }
 
-        self.local.broadcast(packet, opts)?;
         Ok(())
     }
 
-    /// Broadcast a packet to all the servers to send them through their sockets.
-    ///
-    /// Returns a Stream that is a combination of the local ack stream and a remote [`MessageStream`].
-    /// Here is a specific protocol in order to know how many message the server expect to close
-    /// the stream at the right time:
-    /// * Get the number `n` of remote servers.
-    /// * Send the broadcast request.
-    /// * Expect `n` `BroadcastAckCount` response in the stream to know the number `m` of expected ack responses.
-    /// * Expect `sum(m)` broadcast counts sent by the servers.
-    ///
-    /// Example with 3 remote servers (n = 3):
-    /// ```text
-    /// +---+                   +---+                   +---+
-    /// | A |                   | B |                   | C |
-    /// +---+                   +---+                   +---+
-    ///   |                       |                       |
-    ///   |---BroadcastWithAck--->|                       |
-    ///   |---BroadcastWithAck--------------------------->|
-    ///   |                       |                       |
-    ///   |<-BroadcastAckCount(2)-|     (n = 2; m = 2)    |
-    ///   |<-BroadcastAckCount(2)-------(n = 2; m = 4)----|
-    ///   |                       |                       |
-    ///   |<----------------Ack---------------------------|
-    ///   |<----------------Ack---|                       |
-    ///   |                       |                       |
-    ///   |<----------------Ack---------------------------|
-    ///   |<----------------Ack---|                       |
-    async fn broadcast_with_ack(
-        &self,
-        packet: Packet,
-        opts: BroadcastOptions,
-        timeout: Option<Duration>,
-    ) -> Result<Self::AckStream, Self::Error> {
-        if is_local_op(self.uid, &opts) {
-            tracing::debug!(?opts, "broadcast with ack is local");
-            let (local, _) = self.local.broadcast_with_ack(packet, opts, timeout);
-            let stream = AckStream::new_local(local);
-            return Ok(stream);
-        }
-        let req = RequestOut::new(self.uid, RequestTypeOut::BroadcastWithAck(&packet), &opts);
-        let req_id = req.id;
-
-        let remote_serv_cnt = self.server_count().await?.saturating_sub(1);
-
-        let (tx, rx) = mpsc::channel(self.config.ack_response_buffer + remote_serv_cnt as usize);
-        self.responses.lock().unwrap().insert(req_id, tx);
-        let remote = MessageStream::new(rx);
-
-        self.send_req(req, opts.server_id).await?;
-        let (local, _) = self.local.broadcast_with_ack(packet, opts, timeout);
-
-        Ok(AckStream::new(
-            local,
-            remote,
-            self.config.request_timeout,
-            remote_serv_cnt,
-            req_id,
-            self.responses.clone(),
-        ))
-    }
-
-    async fn disconnect_socket(&self, opts: BroadcastOptions) -> Result<(), BroadcastError> {
-        if !is_local_op(self.uid, &opts) {
-            let req = RequestOut::new(self.uid, RequestTypeOut::DisconnectSockets, &opts);
-            self.send_req(req, opts.server_id)
-                .await
-                .map_err(AdapterError::from)?;
-        }
-        self.local
-            .disconnect_socket(opts)
-            .map_err(BroadcastError::Socket)?;
-
-        Ok(())
+    async fn send_req(&self, data: Vec<u8>, target: Target<'_>) -> Result<(), Error<R>> {
+        let chan = match target {
+            Target::All => self.get_req_chan(None),
+            Target::Server(uid) => self.get_req_chan(Some(uid)),
+            Target::Room(room) => self.get_room_chan(room),
+        };
+        self.driver
+            .publish(chan, data)
+            .await
+            .map_err(adapter::Error::Driver)
     }
 
-    async fn rooms(&self, opts: BroadcastOptions) -> Result<Vec<Room>, Self::Error> {
-        const PACKET_IDX: u8 = 2;
-
-        if is_local_op(self.uid, &opts) {
-            return Ok(self.local.rooms(opts).into_iter().collect());
-        }
-        let req = RequestOut::new(self.uid, RequestTypeOut::AllRooms, &opts);
-        let req_id = req.id;
-
-        // First get the remote stream because redis might send
-        // the responses before subscription is done.
-        let stream = self
-            .get_res::<()>(req_id, PACKET_IDX, opts.server_id)
-            .await?;
-        self.send_req(req, opts.server_id).await?;
-        let local = self.local.rooms(opts);
-        let rooms = stream
-            .filter_map(|item| future::ready(item.into_rooms()))
-            .fold(local, |mut acc, item| async move {
-                acc.extend(item);
-                acc
-            })
-            .await;
-        Ok(Vec::from_iter(rooms))
+    async fn send_res(&self, data: Vec<u8>, target: Uid) -> Result<(), Error<R>> {
+        let chan = self.get_res_chan(target);
+        self.driver
+            .publish(chan, data)
+            .await
+            .map_err(adapter::Error::Driver)
     }
 
-    async fn add_sockets(
-        &self,
-        opts: BroadcastOptions,
-        rooms: impl RoomParam,
-    ) -> Result<(), Self::Error> {
-        let rooms: Vec<Room> = rooms.into_room_iter().collect();
-        if !is_local_op(self.uid, &opts) {
-            let req = RequestOut::new(self.uid, RequestTypeOut::AddSockets(&rooms), &opts);
-            self.send_req(req, opts.server_id).await?;
-        }
-        self.local.add_sockets(opts, rooms);
-        Ok(())
+    /// Get the number of servers by getting the number of subscribers to the request channel.
+    async fn server_count(&self) -> Result<Option<u16>, R::Error> {
+        self.driver.num_serv(&self.req_chan).await.map(Some)
     }
 
-    async fn del_sockets(
-        &self,
-        opts: BroadcastOptions,
-        rooms: impl RoomParam,
-    ) -> Result<(), Self::Error> {
-        let rooms: Vec<Room> = rooms.into_room_iter().collect();
-        if !is_local_op(self.uid, &opts) {
-            let req = RequestOut::new(self.uid, RequestTypeOut::DelSockets(&rooms), &opts);
-            self.send_req(req, opts.server_id).await?;
-        }
-        self.local.del_sockets(opts, rooms);
-        Ok(())
+    /// With the [`SubscriptionMode::Dynamic`] mode, the broadcasts to a single room
+    /// are published on the channel of the room.
+    fn watch_rooms(&self) -> bool {
+        self.config.subscription_mode == SubscriptionMode::Dynamic
     }
 
-    async fn fetch_sockets(
-        &self,
-        opts: BroadcastOptions,
-    ) -> Result<Vec<RemoteSocketData>, Self::Error> {
-        if is_local_op(self.uid, &opts) {
-            return Ok(self.local.fetch_sockets(opts));
-        }
-        const PACKET_IDX: u8 = 3;
-        let req = RequestOut::new(self.uid, RequestTypeOut::FetchSockets, &opts);
-        let req_id = req.id;
-        // First get the remote stream because redis might send
-        // the responses before subscription is done.
-        let remote = self
-            .get_res::<RemoteSocketData>(req_id, PACKET_IDX, opts.server_id)
+    async fn subscribe_room(&self, room: &str) -> Result<BoxStream<'static, Incoming>, R::Error> {
+        let chan = self.get_room_chan(room);
+        tracing::trace!(?chan, "subscribing to");
+        let stream = self
+            .driver
+            .subscribe(chan.clone(), self.config.stream_buffer)
             .await?;
-
-        self.send_req(req, opts.server_id).await?;
-        let local = self.local.fetch_sockets(opts);
-        let sockets = remote
-            .filter_map(|item| future::ready(item.into_fetch_sockets()))
-            .fold(local, |mut acc, item| async move {
-                acc.extend(item);
-                acc
-            })
-            .await;
-        Ok(sockets)
+        self.room_chans.lock().unwrap().insert(chan);
+        Ok(stream.map(|(_, item)| Incoming::Request(item)).boxed())
     }
 
-    fn get_local(&self) -> &CoreLocalAdapter<E> {
-        &self.local
+    async fn unsubscribe_room(&self, room: &str) -> Result<(), R::Error> {
+        let chan = self.get_room_chan(room);
+        self.room_chans.lock().unwrap().remove(&chan);
+        self.driver.unsubscribe(chan).await
     }
 }
 
@@ -682,33 +702,14 @@ impl<D: Driver> fmt::Debug for InitError<D> {
     }
 }
 /// The result of the init future.
-#[must_use = "futures do nothing unless you `.await` or poll them"]
-pub struct InitRes<D: Driver>(futures_core::future::BoxFuture<'static, Result<(), InitError<D>>>);
-
-impl<D: Driver> Future for InitRes<D> {
-    type Output = Result<(), InitError<D>>;
-
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.0.as_mut().poll(cx)
-    }
-}
-impl<D: Driver> Spawnable for InitRes<D> {
-    fn spawn(self) {
-        tokio::spawn(async move {
-            if let Err(e) = self.0.await {
-                tracing::error!("error initializing adapter: {e}");
-            }
-        });
-    }
-}
+pub type InitRes<D> = adapter::InitRes<InitError<D>>;
 
-impl<E: SocketEmitter, R: Driver> CustomRedisAdapter<E, R> {
+impl<R: Driver> RedisTransport<R> {
     /// Build a response channel for a request.
     ///
     /// The uid is used to identify the server that sent the request.
-    /// The req_id is used to identify the request.
     fn get_res_chan(&self, uid: Uid) -> String {
-        let path = self.local.path();
+        let path = &self.path;
         let prefix = &self.config.prefix;
         format!("{}-response#{}#{}#", prefix, path, uid)
     }
@@ -723,221 +724,9 @@ impl<E: SocketEmitter, R: Driver> CustomRedisAdapter<E, R> {
         }
     }
 
-    async fn pipe_stream(
-        self: Arc<Self>,
-        mut stream: impl Stream<Item = ChanItem> + Unpin,
-        response_chan: String,
-    ) {
-        while let Some((chan, item)) = stream.next().await {
-            if chan.starts_with(&self.req_chan) {
-                if let Err(e) = self.recv_req(item) {
-                    let ns = self.local.path();
-                    let uid = self.uid;
-                    tracing::warn!(?uid, ?ns, "request handler error: {e}");
-                }
-            } else if chan == response_chan {
-                let req_id = read_req_id(&item);
-                tracing::trace!(?req_id, ?chan, ?response_chan, "extracted sid");
-                let handlers = self.responses.lock().unwrap();
-                if let Some(tx) = req_id.and_then(|id| handlers.get(&id)) {
-                    if let Err(e) = tx.try_send(item) {
-                        tracing::warn!("error sending response to handler: {e}");
-                    }
-                } else {
-                    tracing::warn!(?req_id, "could not find req handler");
-                }
-            } else {
-                tracing::warn!("unexpected message/channel: {chan}");
-            }
-        }
-    }
-
-    /// Handle a generic request received from the request channel.
-    fn recv_req(self: &Arc<Self>, item: Vec<u8>) -> Result<(), Error<R>> {
-        let req: RequestIn = rmp_serde::from_slice(&item)?;
-        if req.node_id == self.uid {
-            return Ok(());
-        }
-
-        tracing::trace!(?req, "handling request");
-
-        match req.r#type {
-            RequestTypeIn::Broadcast(p) => self.recv_broadcast(req.opts, p),
-            RequestTypeIn::BroadcastWithAck(_) => self.clone().recv_broadcast_with_ack(req),
-            RequestTypeIn::DisconnectSockets => self.recv_disconnect_sockets(req),
-            RequestTypeIn::AllRooms => self.recv_rooms(req),
-            RequestTypeIn::AddSockets(rooms) => self.recv_add_sockets(req.opts, rooms),
-            RequestTypeIn::DelSockets(rooms) => self.recv_del_sockets(req.opts, rooms),
-            RequestTypeIn::FetchSockets => self.recv_fetch_sockets(req),
-        };
-        Ok(())
-    }
-
-    fn recv_broadcast(&self, opts: BroadcastOptions, packet: Packet) {
-        if let Err(e) = self.local.broadcast(packet, opts) {
-            let ns = self.local.path();
-            tracing::warn!(?self.uid, ?ns, "remote request broadcast handler: {:?}", e);
-        }
-    }
-
-    fn recv_disconnect_sockets(&self, req: RequestIn) {
-        if let Err(e) = self.local.disconnect_socket(req.opts) {
-            let ns = self.local.path();
-            tracing::warn!(
-                ?self.uid,
-                ?ns,
-                "remote request disconnect sockets handler: {:?}",
-                e
-            );
-        }
-    }
-
-    fn recv_broadcast_with_ack(self: Arc<Self>, req: RequestIn) {
-        let packet = match req.r#type {
-            RequestTypeIn::BroadcastWithAck(p) => p,
-            _ => unreachable!(),
-        };
-        let (stream, count) = self.local.broadcast_with_ack(packet, req.opts, None);
-        tokio::spawn(async move {
-            let on_err = |err| {
-                let ns = self.local.path();
-                tracing::warn!(
-                    ?self.uid,
-                    ?ns,
-                    "remote request broadcast with ack handler errors: {:?}",
-                    err
-                );
-            };
-            // First send the count of expected acks to the server that sent the request.
-            // This is used to keep track of the number of expected acks.
-            let res = Response {
-                r#type: ResponseType::<()>::BroadcastAckCount(count),
-                node_id: self.uid,
-            };
-            if let Err(err) = self.send_res(req.node_id, req.id, res).await {
-                on_err(err);
-                return;
-            }
-
-            // Then send the acks as they are received.
-            futures_util::pin_mut!(stream);
-            while let Some(ack) = stream.next().await {
-                let res = Response {
-                    r#type: ResponseType::BroadcastAck(ack),
-                    node_id: self.uid,
-                };
-                if let Err(err) = self.send_res(req.node_id, req.id, res).await {
-                    on_err(err);
-                    return;
-                }
-            }
-        });
-    }
-
-    fn recv_rooms(&self, req: RequestIn) {
-        let rooms = self.local.rooms(req.opts);
-        let res = Response {
-            r#type: ResponseType::<()>::AllRooms(rooms),
-            node_id: self.uid,
-        };
-        let fut = self.send_res(req.node_id, req.id, res);
-        let ns = self.local.path().clone();
-        let uid = self.uid;
-        tokio::spawn(async move {
-            if let Err(err) = fut.await {
-                tracing::warn!(?uid, ?ns, "remote request rooms handler: {:?}", err);
-            }
-        });
-    }
-
-    fn recv_add_sockets(&self, opts: BroadcastOptions, rooms: Vec<Room>) {
-        self.local.add_sockets(opts, rooms);
-    }
-
-    fn recv_del_sockets(&self, opts: BroadcastOptions, rooms: Vec<Room>) {
-        self.local.del_sockets(opts, rooms);
-    }
-    fn recv_fetch_sockets(&self, req: RequestIn) {
-        let sockets = self.local.fetch_sockets(req.opts);
-        let res = Response {
-            node_id: self.uid,
-            r#type: ResponseType::FetchSockets(sockets),
-        };
-        let fut = self.send_res(req.node_id, req.id, res);
-        let ns = self.local.path().clone();
-        let uid = self.uid;
-        tokio::spawn(async move {
-            if let Err(err) = fut.await {
-                tracing::warn!(?uid, ?ns, "remote request fetch sockets handler: {:?}", err);
-            }
-        });
-    }
-
-    async fn send_req(&self, req: RequestOut<'_>, target_uid: Option<Uid>) -> Result<(), Error<R>> {
-        tracing::trace!(?req, "sending request");
-        let req = rmp_serde::to_vec(&req)?;
-        let chan = self.get_req_chan(target_uid);
-        self.driver
-            .publish(chan, req)
-            .await
-            .map_err(Error::from_driver)?;
-
-        Ok(())
-    }
-
-    fn send_res<D: Serialize + fmt::Debug>(
-        &self,
-        req_node_id: Uid,
-        req_id: Sid,
-        res: Response<D>,
-    ) -> impl Future<Output = Result<(), Error<R>>> + Send + 'static {
-        let chan = self.get_res_chan(req_node_id);
-        tracing::trace!(?res, "sending response to {}", &chan);
-        // We send the req_id separated from the response object.
-        // This allows to partially decode the response and route by the req_id
-        // before fully deserializing it.
-        let res = rmp_serde::to_vec(&(req_id, res));
-        let driver = self.driver.clone();
-        async move {
-            driver
-                .publish(chan, res?)
-                .await
-                .map_err(Error::from_driver)?;
-            Ok(())
-        }
-    }
-
-    /// Await for all the responses from the remote servers.
-    async fn get_res<D: DeserializeOwned + fmt::Debug>(
-        &self,
-        req_id: Sid,
-        response_idx: u8,
-        target_uid: Option<Uid>,
-    ) -> Result<impl Stream<Item = Response<D>>, Error<R>> {
-        // Check for specific target node
-        let remote_serv_cnt = if target_uid.is_none() {
-            self.server_count().await?.saturating_sub(1) as usize
-        } else {
-            1
-        };
-        let (tx, rx) = mpsc::channel(std::cmp::max(remote_serv_cnt, 1));
-        self.responses.lock().unwrap().insert(req_id, tx);
-        let stream = MessageStream::new(rx)
-            .filter_map(|item| {
-                let data = match rmp_serde::from_slice::<(Sid, Response<D>)>(&item) {
-                    Ok((_, data)) => Some(data),
-                    Err(e) => {
-                        tracing::warn!("error decoding response: {e}");
-                        None
-                    }
-                };
-                future::ready(data)
-            })
-            .filter(move |item| future::ready(item.r#type.to_u8() == response_idx))
-            .take(remote_serv_cnt)
-            .take_until(time::sleep(self.config.request_timeout));
-        let stream = DropStream::new(stream, self.responses.clone(), req_id);
-        Ok(stream)
+    /// Build the channel of a room, with the [`SubscriptionMode::Dynamic`] mode.
+    fn get_room_chan(&self, room: &str) -> String {
+        format!("{}room#{}#", self.req_chan, room)
     }
 
     /// Little wrapper to map the error type.
@@ -951,23 +740,6 @@ impl<E: SocketEmitter, R: Driver> CustomRedisAdapter<E, R> {
     }
 }
 
-/// A local operator is either something that is flagged as local or a request that should be specifically
-/// sent to the current server.
-#[inline]
-fn is_local_op(uid: Uid, opts: &BroadcastOptions) -> bool {
-    if opts.has_flag(BroadcastFlags::Local)
-        || (!opts.has_flag(BroadcastFlags::Broadcast)
-            && opts.server_id == Some(uid)
-            && opts.rooms.is_empty()
-            && opts.sid.is_some())
-    {
-        tracing::debug!(?opts, "operation is local");
-        true
-    } else {
-        false
-    }
-}
-
 /// Checks if the namespace path is valid
 /// Panics if the path is empty or contains a `#`
 fn check_ns<D: Driver>(path: &str) -> Result<(), InitError<D>> {
@@ -981,8 +753,6 @@ fn check_ns<D: Driver>(path: &str) -> Result<(), InitError<D>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use futures_util::stream::{self, FusedStream, StreamExt};
-    use socketioxide_core::{adapter::AckStreamItem, Str, Value};
     use std::convert::Infallible;
 
     #[derive(Clone)]
@@ -1010,108 +780,6 @@ mod tests {
             Ok(0)
         }
     }
-    fn new_stub_ack_stream(
-        remote: MessageStream<Vec<u8>>,
-        timeout: Duration,
-    ) -> AckStream<stream::Empty<AckStreamItem<()>>> {
-        AckStream::new(
-            stream::empty::<AckStreamItem<()>>(),
-            remote,
-            timeout,
-            2,
-            Sid::new(),
-            Arc::new(Mutex::new(HashMap::new())),
-        )
-    }
-
-    //TODO: test weird behaviours, packets out of orders, etc
-    #[tokio::test]
-    async fn ack_stream() {
-        let (tx, rx) = tokio::sync::mpsc::channel(255);
-        let remote = MessageStream::new(rx);
-        let stream = new_stub_ack_stream(remote, Duration::from_secs(10));
-        let node_id = Uid::new();
-        let req_id = Sid::new();
-
-        // The two servers will send 2 acks each.
-        let ack_cnt_res = Response::<()> {
-            node_id,
-            r#type: ResponseType::BroadcastAckCount(2),
-        };
-        tx.try_send(rmp_serde::to_vec(&(req_id, &ack_cnt_res)).unwrap())
-            .unwrap();
-        tx.try_send(rmp_serde::to_vec(&(req_id, &ack_cnt_res)).unwrap())
-            .unwrap();
-
-        let ack_res = Response::<String> {
-            node_id,
-            r#type: ResponseType::BroadcastAck((Sid::new(), Ok(Value::Str(Str::from(""), None)))),
-        };
-        for _ in 0..4 {
-            tx.try_send(rmp_serde::to_vec(&(req_id, &ack_res)).unwrap())
-                .unwrap();
-        }
-        futures_util::pin_mut!(stream);
-        for _ in 0..4 {
-            assert!(stream.next().await.is_some());
-        }
-        assert!(stream.is_terminated());
-    }
-
-    #[tokio::test]
-    async fn ack_stream_timeout() {
-        let (tx, rx) = tokio::sync::mpsc::channel(255);
-        let remote = MessageStream::new(rx);
-        let stream = new_stub_ack_stream(remote, Duration::from_millis(50));
-        let node_id = Uid::new();
-        let req_id = Sid::new();
-        // There will be only one ack count and then the stream will timeout.
-        let ack_cnt_res = Response::<()> {
-            node_id,
-            r#type: ResponseType::BroadcastAckCount(2),
-        };
-        tx.try_send(rmp_serde::to_vec(&(req_id, ack_cnt_res)).unwrap())
-            .unwrap();
-
-        futures_util::pin_mut!(stream);
-        tokio::time::sleep(Duration::from_millis(50)).await;
-        assert!(stream.next().await.is_none());
-        assert!(stream.is_terminated());
-    }
-
-    #[tokio::test]
-    async fn ack_stream_drop() {
-        let (tx, rx) = tokio::sync::mpsc::channel(255);
-        let remote = MessageStream::new(rx);
-        let handlers = Arc::new(Mutex::new(HashMap::new()));
-        let id = Sid::new();
-        handlers.lock().unwrap().insert(id, tx);
-        let stream = AckStream::new(
-            stream::empty::<AckStreamItem<()>>(),
-            remote,
-            Duration::from_secs(10),
-            2,
-            id,
-            handlers.clone(),
-        );
-        drop(stream);
-        assert!(handlers.lock().unwrap().is_empty(),);
-    }
-
-    #[test]
-    fn test_is_local_op() {
-        let server_id = Uid::new();
-        let remote = RemoteSocketData {
-            id: Sid::new(),
-            server_id,
-            ns: "/".into(),
-        };
-        let opts = BroadcastOptions::new_remote(&remote);
-        assert!(is_local_op(server_id, &opts));
-        assert!(!is_local_op(Uid::new(), &opts));
-        let opts = BroadcastOptions::new(Sid::new());
-        assert!(!is_local_op(Uid::new(), &opts));
-    }
 
     #[test]
     fn check_ns_error() {
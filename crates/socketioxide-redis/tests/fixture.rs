@@ -9,14 +9,46 @@ use std::{
 use tokio::sync::mpsc;
 
 use socketioxide::{adapter::Emitter, SocketIo};
+use socketioxide_adapter_tests::Fixture;
 use socketioxide_redis::{
     drivers::{Driver, MessageStream},
-    CustomRedisAdapter, RedisAdapterConfig, RedisAdapterCtr,
+    CustomRedisAdapter, Error, InitError, InitRes, RedisAdapterConfig, RedisAdapterCtr,
 };
 
+/// The fixture of the common adapter test suite.
+pub struct StubFixture;
+impl Fixture for StubFixture {
+    type Adapter = CustomRedisAdapter<Emitter, StubDriver>;
+    type InitRes = InitRes<StubDriver>;
+    type InitError = InitError<StubDriver>;
+
+    fn spawn_servers<const N: usize>() -> [SocketIo<Self::Adapter>; N] {
+        spawn_servers()
+    }
+    async fn spawn_buggy_servers<const N: usize>(
+        timeout: Duration,
+    ) -> [SocketIo<Self::Adapter>; N] {
+        spawn_buggy_servers(timeout)
+    }
+    fn is_partial(err: &Error<StubDriver>) -> bool {
+        matches!(err, Error::<StubDriver>::Partial(_))
+    }
+}
+
 /// Spawns a number of servers with a stub driver for testing.
 /// Every server will be connected to every other server.
 pub fn spawn_servers<const N: usize>() -> [SocketIo<CustomRedisAdapter<Emitter, StubDriver>>; N] {
+    spawn_servers_with_config(RedisAdapterConfig::default()).map(|(io, _)| io)
+}
+
+/// Spawns a number of servers with a stub driver and a custom config for testing.
+/// The driver of each server is returned alongside it.
+pub fn spawn_servers_with_config<const N: usize>(
+    config: RedisAdapterConfig,
+) -> [(
+    SocketIo<CustomRedisAdapter<Emitter, StubDriver>>,
+    StubDriver,
+); N] {
     let sync_buff = Arc::new(RwLock::new(Vec::with_capacity(N)));
 
     [0; N].map(|_| {
@@ -35,7 +67,36 @@ pub fn spawn_servers<const N: usize>() -> [SocketIo<CustomRedisAdapter<Emitter,
             }
         });
 
-        let adapter = RedisAdapterCtr::new_with_driver(driver, RedisAdapterConfig::default());
+        let adapter = RedisAdapterCtr::new_with_driver(driver.clone(), config.clone());
+        let (_svc, io) = SocketIo::builder()
+            .with_adapter::<CustomRedisAdapter<_, _>>(adapter)
+            .build_svc();
+        (io, driver)
+    })
+}
+
+/// Spawns a number of servers with a stub driver delivering each message twice for testing.
+pub fn spawn_redelivering_servers<const N: usize>(
+    config: RedisAdapterConfig,
+) -> [SocketIo<CustomRedisAdapter<Emitter, StubDriver>>; N] {
+    let sync_buff = Arc::new(RwLock::new(Vec::with_capacity(N)));
+
+    [0; N].map(|_| {
+        let (driver, mut rx, tx) = StubDriver::new(N as u16);
+
+        // pipe messages twice to all other servers
+        sync_buff.write().unwrap().push(tx);
+        let sync_buff = sync_buff.clone();
+        tokio::spawn(async move {
+            while let Some((chan, data)) = rx.recv().await {
+                for tx in sync_buff.read().unwrap().iter() {
+                    tx.try_send((chan.clone(), data.clone())).unwrap();
+                    tx.try_send((chan.clone(), data.clone())).unwrap();
+                }
+            }
+        });
+
+        let adapter = RedisAdapterCtr::new_with_driver(driver, config.clone());
         let (_svc, io) = SocketIo::builder()
             .with_adapter::<CustomRedisAdapter<_, _>>(adapter)
             .build_svc();
@@ -47,6 +108,18 @@ pub fn spawn_servers<const N: usize>() -> [SocketIo<CustomRedisAdapter<Emitter,
 /// The internal server count is set to N + 2 to trigger a timeout when expecting N responses.
 pub fn spawn_buggy_servers<const N: usize>(
     timeout: Duration,
+) -> [SocketIo<CustomRedisAdapter<Emitter, StubDriver>>; N] {
+    // The heartbeats would exclude the fake servers from the server count.
+    let config = RedisAdapterConfig::new()
+        .with_request_timeout(timeout)
+        .with_heartbeat(false);
+    spawn_buggy_servers_with_config(config)
+}
+
+/// Spawns a number of servers with a stub driver and a custom config for testing.
+/// The internal server count is set to N + 2 to trigger a timeout when expecting N responses.
+pub fn spawn_buggy_servers_with_config<const N: usize>(
+    config: RedisAdapterConfig,
 ) -> [SocketIo<CustomRedisAdapter<Emitter, StubDriver>>; N] {
     let sync_buff = Arc::new(RwLock::new(Vec::with_capacity(N)));
 
@@ -66,8 +139,7 @@ pub fn spawn_buggy_servers<const N: usize>(
             }
         });
 
-        let config = RedisAdapterConfig::new().with_request_timeout(timeout);
-        let adapter = RedisAdapterCtr::new_with_driver(driver, config);
+        let adapter = RedisAdapterCtr::new_with_driver(driver, config.clone());
         let (_svc, io) = SocketIo::builder()
             .with_adapter::<CustomRedisAdapter<_, _>>(adapter)
             .build_svc();
@@ -144,34 +216,3 @@ impl Driver for StubDriver {
         Ok(self.num_serv)
     }
 }
-
-#[macro_export]
-macro_rules! timeout_rcv_err {
-    ($srx:expr) => {
-        tokio::time::timeout(std::time::Duration::from_millis(10), $srx.recv())
-            .await
-            .unwrap_err();
-    };
-}
-
-#[macro_export]
-macro_rules! timeout_rcv {
-    ($srx:expr) => {
-        TryInto::<String>::try_into(
-            tokio::time::timeout(std::time::Duration::from_millis(10), $srx.recv())
-                .await
-                .unwrap()
-                .unwrap(),
-        )
-        .unwrap()
-    };
-    ($srx:expr, $t:expr) => {
-        TryInto::<String>::try_into(
-            tokio::time::timeout(std::time::Duration::from_millis($t), $srx.recv())
-                .await
-                .unwrap()
-                .unwrap(),
-        )
-        .unwrap()
-    };
-}
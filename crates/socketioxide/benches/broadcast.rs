@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use socketioxide::SocketIo;
+use tokio::runtime::Runtime;
+
+/// Create a server with `n` connected sockets, half of them in `room1`.
+/// The packets sent to the sockets are drained by background tasks.
+/// The buffer is large so that the emissions are rarely dropped when the tasks lag behind.
+async fn create_server(n: usize) -> SocketIo {
+    let (_, io) = SocketIo::builder().max_buffer_size(4096).build_svc();
+    io.ns("/", || ());
+    for _ in 0..n {
+        let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+        tokio::spawn(async move {
+            let _stx = stx;
+            while srx.recv().await.is_some() {}
+        });
+    }
+    for socket in io.sockets().iter().step_by(2) {
+        socket.join("room1");
+    }
+    io
+}
+
+fn bench_broadcast(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let data = serde_json::json!({ "name": "foo", "values": [1, 2, 3, 4, 5] });
+    let mut group = c.benchmark_group("broadcast");
+    for n in [10, 100, 1000] {
+        let io = rt.block_on(create_server(n));
+        group.bench_with_input(BenchmarkId::new("emit", n), &io, |b, io| {
+            b.iter(|| rt.block_on(io.emit("test", black_box(&data))).ok())
+        });
+        group.bench_with_input(BenchmarkId::new("emit_to_room", n), &io, |b, io| {
+            b.iter(|| {
+                rt.block_on(io.to("room1").emit("test", black_box(&data)))
+                    .ok()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_broadcast);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use socketioxide::{test::TestClient, SocketIo};
+use tokio::runtime::Runtime;
+
+/// Connection storms: `n` clients connecting to a namespace and disconnecting concurrently
+/// on a multi-threaded runtime, contending on the socket map of the namespace.
+fn bench_connect(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let (_, io) = SocketIo::new_svc();
+    io.ns("/", || ());
+    let mut group = c.benchmark_group("connect");
+    for n in [100, 1000, 10000] {
+        group.bench_with_input(BenchmarkId::new("connect_disconnect", n), &n, |b, &n| {
+            b.iter(|| {
+                rt.block_on(async {
+                    let tasks: Vec<_> = (0..n)
+                        .map(|_| {
+                            let io = io.clone();
+                            tokio::spawn(async move {
+                                let client = TestClient::connect(&io, "/").await.unwrap();
+                                client.disconnect().await;
+                            })
+                        })
+                        .collect();
+                    for task in tasks {
+                        task.await.unwrap();
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_connect);
+criterion_main!(benches);
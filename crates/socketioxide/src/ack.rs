@@ -1,5 +1,6 @@
 //! Acknowledgement related types and functions.
 use std::{
+    collections::{hash_set, HashSet},
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
@@ -10,7 +11,10 @@ use engineioxide::sid::Sid;
 use futures_core::{FusedFuture, FusedStream, Future, Stream};
 use futures_util::stream::FuturesUnordered;
 use serde::de::DeserializeOwned;
-use tokio::{sync::oneshot::Receiver, time::Timeout};
+use tokio::{
+    sync::oneshot::Receiver,
+    time::{Sleep, Timeout},
+};
 
 use crate::{
     adapter::{Adapter, LocalAdapter},
@@ -66,6 +70,8 @@ pin_project_lite::pin_project! {
     /// an [`AckError::Timeout`]. If the data sent by the client is not deserializable as `T`,
     /// an [`AckError::Decode`] will be yielded.
     ///
+    /// An additional deadline can be set on an already created [`AckStream`] with [`AckStream::timeout`].
+    ///
     /// An [`AckStream`] can be created from:
     /// * The [`SocketRef::emit_with_ack`] method, in this case there will be only one ack response.
     /// * The [`Operator::emit_with_ack`] method, in this case there will be as many ack response
@@ -94,12 +100,24 @@ pin_project_lite::pin_project! {
     ///         .await
     ///         .unwrap()
     ///         .for_each(|(id, ack)| async move { println!("Ack: {} {:?}", id, ack); }).await;
+    ///
+    ///     // We wait at most 1 second for the acknowledgement
+    ///     let ack = socket.emit_with_ack::<_, String>("test", "test")
+    ///         .unwrap()
+    ///         .timeout(std::time::Duration::from_secs(1))
+    ///         .await;
     /// });
     /// ```
     #[must_use = "futures and streams do nothing unless you `.await` or poll them"]
     pub struct AckStream<T, A: Adapter = LocalAdapter> {
         #[pin]
         inner: A::AckStream,
+        #[pin]
+        deadline: Option<Sleep>,
+        // The sockets that did not respond yet.
+        pending: HashSet<Sid>,
+        // The sockets that did not respond before the deadline, once it is reached.
+        expired: Option<hash_set::IntoIter<Sid>>,
         parser: Parser,
         _marker: std::marker::PhantomData<T>,
     }
@@ -139,9 +157,9 @@ impl AckInnerStream {
     ///
     /// The [`AckInnerStream`] will wait for the default timeout specified in the config
     /// (5s by default) if no custom timeout is specified.
-    pub fn broadcast<'a, A: Adapter>(
+    pub fn broadcast<A: Adapter>(
         packet: Packet,
-        sockets: impl Iterator<Item = &'a Arc<Socket<A>>>,
+        sockets: impl Iterator<Item = Arc<Socket<A>>>,
         duration: Duration,
     ) -> (Self, u32) {
         let rxs = FuturesUnordered::new();
@@ -212,37 +230,84 @@ impl FusedStream for AckInnerStream {
 
 // ==== impl AckStream ====
 impl<T, A: Adapter> AckStream<T, A> {
-    pub(crate) fn new(inner: A::AckStream, parser: Parser) -> Self {
+    pub(crate) fn new(
+        inner: A::AckStream,
+        parser: Parser,
+        targets: impl IntoIterator<Item = Sid>,
+    ) -> Self {
         AckStream {
             inner,
+            deadline: None,
+            pending: targets.into_iter().collect(),
+            expired: None,
             parser,
             _marker: std::marker::PhantomData,
         }
     }
+
+    /// Set a deadline for the acknowledgement(s) to be received, starting from now.
+    ///
+    /// Once the deadline is reached:
+    /// * When used as a [`Future`], an [`AckError::Timeout`] is yielded.
+    /// * When used as a [`Stream`], an [`AckError::Timeout`] is yielded for each socket that did not
+    ///   respond yet, then the stream ends.
+    ///
+    /// **Note**: with a remote adapter, the sockets of the other servers are only known once they respond.
+    /// No [`AckError::Timeout`] is yielded at the deadline for the remote sockets that did not respond.
+    ///
+    /// **Note**: this does not extend the timeout set when emitting the message
+    /// (with the [`timeout`](crate::operators::ConfOperators::timeout) operator or the
+    /// [`ack_timeout`](crate::SocketIoBuilder::ack_timeout) option), it only adds a new deadline.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(tokio::time::sleep(timeout));
+        self
+    }
 }
 
 impl<T: DeserializeOwned, A: Adapter> Stream for AckStream<T, A> {
     type Item = (Sid, AckResult<T>);
 
-    #[inline]
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let parser = self.parser;
-        self.project()
-            .inner
-            .poll_next(cx)
-            .map(|v| v.map(|(s, v)| (s, map_ack_response(v, parser))))
+        let mut project = self.project();
+        if let Some(expired) = project.expired {
+            return Poll::Ready(expired.next().map(|sid| (sid, Err(AckError::Timeout))));
+        }
+        if let Poll::Ready(v) = project.inner.poll_next(cx) {
+            return Poll::Ready(v.map(|(sid, v)| {
+                project.pending.remove(&sid);
+                (sid, map_ack_response(v, parser))
+            }));
+        }
+        match project.deadline.as_mut().as_pin_mut().map(|d| d.poll(cx)) {
+            Some(Poll::Ready(())) => {
+                project.deadline.set(None);
+                let mut expired = std::mem::take(project.pending).into_iter();
+                let next = expired.next().map(|sid| (sid, Err(AckError::Timeout)));
+                *project.expired = Some(expired);
+                Poll::Ready(next)
+            }
+            _ => Poll::Pending,
+        }
     }
 
     #[inline(always)]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.inner.size_hint()
+        if let Some(expired) = &self.expired {
+            expired.size_hint()
+        } else {
+            self.inner.size_hint()
+        }
     }
 }
 
 impl<T: DeserializeOwned, A: Adapter> FusedStream for AckStream<T, A> {
     #[inline(always)]
     fn is_terminated(&self) -> bool {
-        FusedStream::is_terminated(&self.inner)
+        match &self.expired {
+            Some(expired) => expired.len() == 0,
+            None => FusedStream::is_terminated(&self.inner),
+        }
     }
 }
 
@@ -251,9 +316,8 @@ impl<T: DeserializeOwned, A: Adapter> Future for AckStream<T, A> {
 
     #[inline]
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let parser = self.parser;
-        match self.project().inner.poll_next(cx) {
-            Poll::Ready(Some(v)) => Poll::Ready(map_ack_response(v.1, parser)),
+        match Stream::poll_next(self, cx) {
+            Poll::Ready(Some((_, v))) => Poll::Ready(v),
             Poll::Pending => Poll::Pending,
             Poll::Ready(None) => Poll::Ready(Err(AckError::Timeout)),
         }
@@ -263,7 +327,7 @@ impl<T: DeserializeOwned, A: Adapter> Future for AckStream<T, A> {
 impl<T: DeserializeOwned, A: Adapter> FusedFuture for AckStream<T, A> {
     #[inline(always)]
     fn is_terminated(&self) -> bool {
-        FusedStream::is_terminated(&self.inner)
+        FusedStream::is_terminated(self)
     }
 }
 
@@ -299,7 +363,13 @@ mod test {
     }
     impl<T: DeserializeOwned> From<AckInnerStream> for AckStream<T, LocalAdapter> {
         fn from(val: AckInnerStream) -> Self {
-            Self::new(val, Parser::default())
+            let targets: Vec<Sid> = match &val {
+                AckInnerStream::Stream { rxs } => {
+                    Pin::new(rxs).iter_pin_ref().map(|rx| rx.id).collect()
+                }
+                AckInnerStream::Fut { rx, .. } => vec![rx.id],
+            };
+            Self::new(val, Parser::default(), targets)
         }
     }
     const TIMEOUT: Duration = Duration::from_secs(5);
@@ -312,7 +382,7 @@ mod test {
         packet.inner.set_ack_id(1);
         let socks = vec![&socket, &socket2];
         let stream: AckStream<String, LocalAdapter> =
-            AckInnerStream::broadcast(packet, socks.into_iter(), TIMEOUT)
+            AckInnerStream::broadcast(packet, socks.into_iter().cloned(), TIMEOUT)
                 .0
                 .into();
 
@@ -341,6 +411,46 @@ mod test {
         assert!(stream.next().await.is_none());
     }
 
+    #[tokio::test]
+    async fn ack_fut_with_deadline() {
+        let (_tx, rx) = tokio::sync::oneshot::channel();
+        let sid = Sid::new();
+        let stream: AckStream<String, LocalAdapter> =
+            AckInnerStream::send(rx, Duration::from_secs(10), sid).into();
+        let stream = stream.timeout(Duration::from_millis(10));
+
+        assert!(matches!(stream.await.unwrap_err(), AckError::Timeout));
+    }
+
+    #[tokio::test]
+    async fn ack_stream_with_deadline() {
+        let socket = create_socket();
+        let socket2 = create_socket();
+        let mut packet = get_packet();
+        packet.inner.set_ack_id(1);
+        let socks = vec![&socket, &socket2];
+        let stream: AckStream<String, LocalAdapter> =
+            AckInnerStream::broadcast(packet, socks.into_iter().cloned(), TIMEOUT)
+                .0
+                .into();
+        let stream = stream.timeout(Duration::from_millis(10));
+        let (sid1, sid2) = (socket.id, socket2.id);
+
+        socket
+            .recv(Packet::ack("test", value("test"), 1).inner)
+            .unwrap();
+        futures_util::pin_mut!(stream);
+
+        let (sid, res) = stream.next().await.unwrap();
+        assert_eq!(sid, sid1);
+        assert_eq!(res.unwrap(), "test");
+        let (sid, res) = stream.next().await.unwrap();
+        assert_eq!(sid, sid2);
+        assert!(matches!(res.unwrap_err(), AckError::Timeout));
+        assert!(stream.next().await.is_none());
+        assert!(FusedStream::is_terminated(&*stream));
+    }
+
     #[tokio::test]
     async fn ack_fut() {
         let (tx, rx) = tokio::sync::oneshot::channel();
@@ -360,7 +470,7 @@ mod test {
         packet.inner.set_ack_id(1);
         let socks = vec![&socket, &socket2];
         let stream: AckStream<String, LocalAdapter> =
-            AckInnerStream::broadcast(packet, socks.into_iter(), TIMEOUT)
+            AckInnerStream::broadcast(packet, socks.into_iter().cloned(), TIMEOUT)
                 .0
                 .into();
 
@@ -419,7 +529,7 @@ mod test {
         packet.inner.set_ack_id(1);
         let socks = vec![&socket, &socket2];
         let stream: AckStream<String, LocalAdapter> =
-            AckInnerStream::broadcast(packet, socks.into_iter(), TIMEOUT)
+            AckInnerStream::broadcast(packet, socks.into_iter().cloned(), TIMEOUT)
                 .0
                 .into();
 
@@ -476,10 +586,13 @@ mod test {
         let mut packet = get_packet();
         packet.inner.set_ack_id(1);
         let socks = vec![&socket, &socket2];
-        let stream: AckStream<String, LocalAdapter> =
-            AckInnerStream::broadcast(packet, socks.into_iter(), Duration::from_millis(10))
-                .0
-                .into();
+        let stream: AckStream<String, LocalAdapter> = AckInnerStream::broadcast(
+            packet,
+            socks.into_iter().cloned(),
+            Duration::from_millis(10),
+        )
+        .0
+        .into();
 
         socket
             .recv(Packet::ack("test", value("test"), 1).inner)
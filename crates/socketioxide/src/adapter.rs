@@ -3,13 +3,22 @@
 //! The default adapter is the [`LocalAdapter`], which stores the state in memory.
 //! Other adapters can be made to share the state between multiple servers.
 
+use engineioxide::sid::Sid;
 use socketioxide_core::{
-    adapter::{BroadcastOptions, CoreAdapter, CoreLocalAdapter, DefinedAdapter, SocketEmitter},
+    adapter::{
+        BroadcastOptions, CoreAdapter, CoreLocalAdapter, DefinedAdapter, Room, SocketEmitter,
+    },
     packet::Packet,
 };
-use std::{convert::Infallible, sync::Arc, time::Duration};
+use std::{
+    convert::Infallible,
+    fmt,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 pub use crate::ns::Emitter;
+pub use socketioxide_core::adapter::{DynAdapter, NodeInfo, RoomExpiry};
 pub use socketioxide_core::errors::AdapterError;
 /// An adapter is responsible for managing the state of the namespace.
 /// This adapter can be implemented to share the state between multiple servers.
@@ -42,7 +51,7 @@ impl CoreAdapter<Emitter> for LocalAdapter {
         opts: BroadcastOptions,
         timeout: Option<Duration>,
     ) -> Result<Self::AckStream, Self::Error> {
-        Ok(self.get_local().broadcast_with_ack(packet, opts, timeout).0)
+        Ok(self.0.broadcast_with_ack(packet, opts, timeout).0)
     }
 
     fn get_local(&self) -> &CoreLocalAdapter<Emitter> {
@@ -50,3 +59,63 @@ impl CoreAdapter<Emitter> for LocalAdapter {
     }
 }
 impl DefinedAdapter for LocalAdapter {}
+
+// === Room listeners ===
+
+/// A listener of the room joins and leaves, registered with
+/// [`SocketIo::on_join_room`](crate::SocketIo::on_join_room) or
+/// [`SocketIo::on_leave_room`](crate::SocketIo::on_leave_room).
+type RoomListener = Box<dyn Fn(&str, Sid, &Room) + Send + Sync + 'static>;
+/// A listener of the room creations, deletions and expiries, registered with
+/// [`SocketIo::on_create_room`](crate::SocketIo::on_create_room),
+/// [`SocketIo::on_delete_room`](crate::SocketIo::on_delete_room) or
+/// [`SocketIo::on_expire_room`](crate::SocketIo::on_expire_room).
+type RoomLifecycleListener = Box<dyn Fn(&str, &Room) + Send + Sync + 'static>;
+
+/// The room listeners of a server, called by the [`Emitter`] of each namespace.
+#[derive(Default)]
+pub(crate) struct RoomListeners {
+    pub join: RwLock<Vec<RoomListener>>,
+    pub leave: RwLock<Vec<RoomListener>>,
+    pub create: RwLock<Vec<RoomLifecycleListener>>,
+    pub delete: RwLock<Vec<RoomLifecycleListener>>,
+    pub expire: RwLock<Vec<RoomLifecycleListener>>,
+}
+
+impl RoomListeners {
+    pub fn joined(&self, ns: &str, sid: Sid, room: &Room) {
+        self.join
+            .read()
+            .unwrap()
+            .iter()
+            .for_each(|f| f(ns, sid, room));
+    }
+    pub fn left(&self, ns: &str, sid: Sid, room: &Room) {
+        self.leave
+            .read()
+            .unwrap()
+            .iter()
+            .for_each(|f| f(ns, sid, room));
+    }
+    pub fn created(&self, ns: &str, room: &Room) {
+        self.create.read().unwrap().iter().for_each(|f| f(ns, room));
+    }
+    pub fn deleted(&self, ns: &str, room: &Room) {
+        self.delete.read().unwrap().iter().for_each(|f| f(ns, room));
+    }
+    pub fn expired(&self, ns: &str, room: &Room) {
+        self.expire.read().unwrap().iter().for_each(|f| f(ns, room));
+    }
+}
+
+impl fmt::Debug for RoomListeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoomListeners")
+            .field("join", &self.join.read().unwrap().len())
+            .field("leave", &self.leave.read().unwrap().len())
+            .field("create", &self.create.read().unwrap().len())
+            .field("delete", &self.delete.read().unwrap().len())
+            .field("expire", &self.expire.read().unwrap().len())
+            .finish()
+    }
+}
@@ -0,0 +1,758 @@
+//! Support of the [Admin UI](https://socket.io/docs/v4/admin-ui/) protocol.
+//!
+//! Once enabled with [`SocketIoBuilder::with_admin_ui`](crate::SocketIoBuilder), a special namespace
+//! (`/admin` by default) is registered on the server. The official [dashboard](https://admin.socket.io)
+//! can then connect to it to inspect the sockets, rooms and events of the server, and to make sockets
+//! join or leave rooms, emit events to them or disconnect them.
+//!
+//! ```
+//! # use socketioxide::{SocketIo, admin::AdminUiConfig};
+//! let (layer, io) = SocketIo::builder()
+//!     .with_admin_ui(AdminUiConfig::new().with_basic_auth("admin", "changeit"))
+//!     .build_layer();
+//! ```
+//!
+//! The dashboard must authenticate with the credentials set with [`AdminUiConfig::with_basic_auth`].
+//! The authentication can only be disabled explicitly with [`AdminUiConfig::without_auth`],
+//! for example on a server only reachable from a private network.
+//!
+//! ## Modes
+//! * In [`AdminUiMode::Development`] mode, the dashboard receives all the sockets when it connects.
+//!   It is then notified of every connection, disconnection, room change and event received or sent by a socket.
+//! * In [`AdminUiMode::Production`] mode, only the server statistics are sent, so that the sockets
+//!   are not tracked.
+//!
+//! ## Notifications
+//! The notifications are forwarded to the dashboards through a bounded buffer of
+//! [`EVENT_BUFFER_SIZE`] notifications. When the dashboards can't keep up, the new notifications are
+//! dropped until the buffer has room again.
+//!
+//! ## Clustering
+//! The admin namespace uses the adapter of the server. Therefore, with a clustered adapter,
+//! the statistics and the notifications of all the servers reach the dashboard.
+//! However, the sockets sent when the dashboard connects are only the ones of the server it is connected to.
+//! Commands targeting socket ids are also only applied to the sockets of this server,
+//! whereas commands targeting rooms are applied to the whole cluster.
+//!
+//! ## Limitations
+//! * The remote address and the auth payload of the sockets are not displayed.
+//! * The aggregated events chart of the production dashboard is not supported.
+//! * The `close` flag of the disconnect command is ignored, only the namespace connection is closed.
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use engineioxide::{sid::Sid, Str};
+use serde::{
+    de::{SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::json;
+use socketioxide_core::{
+    adapter::{Room, Spawnable},
+    parser::Parse,
+    Value,
+};
+use subtle::ConstantTimeEq;
+use tokio::sync::mpsc;
+
+use crate::{
+    adapter::Adapter,
+    client::Client,
+    extract::{Data, SocketRef, TryData},
+    handler::ConnectHandler,
+    operators::BroadcastOperators,
+    socket::{DisconnectReason, Socket},
+    BroadcastError, SocketIo, TransportType,
+};
+
+/// The maximum number of notifications waiting to be forwarded to the dashboards.
+pub const EVENT_BUFFER_SIZE: usize = 1024;
+
+/// The mode of the admin UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AdminUiMode {
+    /// All the sockets and their events are sent to the dashboard.
+    #[default]
+    Development,
+    /// Only the server statistics are sent to the dashboard.
+    Production,
+}
+
+/// The configuration of the admin UI.
+///
+/// ```
+/// # use socketioxide::admin::{AdminUiConfig, AdminUiMode};
+/// # use std::time::Duration;
+/// let config = AdminUiConfig::new()
+///     .with_namespace("/dashboard")
+///     .with_basic_auth("admin", "changeit")
+///     .with_mode(AdminUiMode::Production)
+///     .with_stats_interval(Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AdminUiConfig {
+    namespace: Cow<'static, str>,
+    auth: Option<(String, String)>,
+    insecure: bool,
+    readonly: bool,
+    mode: AdminUiMode,
+    server_id: Option<String>,
+    stats_interval: Duration,
+}
+
+impl Default for AdminUiConfig {
+    fn default() -> Self {
+        Self {
+            namespace: Cow::Borrowed("/admin"),
+            auth: None,
+            insecure: false,
+            readonly: false,
+            mode: AdminUiMode::default(),
+            server_id: None,
+            stats_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+impl AdminUiConfig {
+    /// Create a new admin UI configuration with the default values.
+    ///
+    /// Credentials must then be set with [`with_basic_auth`](Self::with_basic_auth),
+    /// unless the authentication is explicitly disabled with [`without_auth`](Self::without_auth).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The namespace the dashboard connects to.
+    ///
+    /// Defaults to `/admin`.
+    pub fn with_namespace(mut self, namespace: impl Into<Cow<'static, str>>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// Require the dashboard to authenticate with the given credentials.
+    ///
+    /// Either credentials or [`without_auth`](Self::without_auth) are required
+    /// to enable the admin UI.
+    pub fn with_basic_auth(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.auth = Some((username.into(), password.into()));
+        self.insecure = false;
+        self
+    }
+
+    /// Allow any dashboard to connect without credentials.
+    ///
+    /// **Warning**: anyone reaching the server can then inspect it and, unless the admin UI is
+    /// [`readonly`](Self::with_readonly), make sockets join rooms, emit events or disconnect them.
+    pub fn without_auth(mut self) -> Self {
+        self.auth = None;
+        self.insecure = true;
+        self
+    }
+
+    /// Whether the dashboards are authenticated or the authentication was explicitly disabled.
+    pub(crate) fn has_auth(&self) -> bool {
+        self.auth.is_some() || self.insecure
+    }
+
+    /// Only allow the dashboard to inspect the server and not to send commands
+    /// (emit, join, leave, disconnect).
+    ///
+    /// Defaults to `false`.
+    pub fn with_readonly(mut self, readonly: bool) -> Self {
+        self.readonly = readonly;
+        self
+    }
+
+    /// The [`AdminUiMode`] of the admin UI.
+    ///
+    /// Defaults to [`AdminUiMode::Development`].
+    pub fn with_mode(mut self, mode: AdminUiMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The identifier of this server displayed by the dashboard.
+    ///
+    /// Defaults to the [`SocketIoConfig::server_id`](crate::SocketIoConfig).
+    pub fn with_server_id(mut self, server_id: impl Into<String>) -> Self {
+        self.server_id = Some(server_id.into());
+        self
+    }
+
+    /// The interval at which the server statistics are sent to the dashboard.
+    ///
+    /// Defaults to 2 seconds.
+    pub fn with_stats_interval(mut self, interval: Duration) -> Self {
+        self.stats_interval = interval;
+        self
+    }
+
+    fn features(&self) -> Vec<&'static str> {
+        let mut features = Vec::new();
+        if !self.readonly {
+            features.extend([
+                "EMIT",
+                "JOIN",
+                "LEAVE",
+                "DISCONNECT",
+                "MJOIN",
+                "MLEAVE",
+                "MDISCONNECT",
+            ]);
+        }
+        if self.mode == AdminUiMode::Development {
+            features.push("ALL_EVENTS");
+        }
+        features
+    }
+}
+
+/// A notification sent to the dashboards.
+#[derive(Debug)]
+enum AdminEvent {
+    SocketConnected(serde_json::Value, String),
+    SocketDisconnected(Str, Sid, String, String),
+    RoomJoined(Str, Room, Sid, String),
+    RoomLeft(Str, Room, Sid, String),
+    EventReceived(Str, Sid, Vec<serde_json::Value>, String),
+    EventSent(Str, Sid, Vec<serde_json::Value>, String),
+}
+
+impl AdminEvent {
+    async fn emit<A: Adapter>(self, op: BroadcastOperators<A>) -> Result<(), BroadcastError> {
+        match self {
+            AdminEvent::SocketConnected(socket, ts) => {
+                op.emit("socket_connected", &(socket, ts)).await
+            }
+            AdminEvent::SocketDisconnected(nsp, sid, reason, ts) => {
+                op.emit("socket_disconnected", &(nsp.as_str(), sid, reason, ts))
+                    .await
+            }
+            AdminEvent::RoomJoined(nsp, room, sid, ts) => {
+                op.emit("room_joined", &(nsp.as_str(), room, sid, ts)).await
+            }
+            AdminEvent::RoomLeft(nsp, room, sid, ts) => {
+                op.emit("room_left", &(nsp.as_str(), room, sid, ts)).await
+            }
+            AdminEvent::EventReceived(nsp, sid, args, ts) => {
+                op.emit("event_received", &(nsp.as_str(), sid, args, ts))
+                    .await
+            }
+            AdminEvent::EventSent(nsp, sid, args, ts) => {
+                op.emit("event_sent", &(nsp.as_str(), sid, args, ts)).await
+            }
+        }
+    }
+}
+
+/// Collects the notifications of the namespaces and forwards them to the dashboards.
+///
+/// The notifications are only collected once a dashboard connected in development mode.
+#[derive(Debug)]
+pub(crate) struct AdminSink {
+    config: AdminUiConfig,
+    tx: mpsc::Sender<AdminEvent>,
+    rx: Mutex<Option<mpsc::Receiver<AdminEvent>>>,
+    active: AtomicBool,
+    created: Instant,
+}
+
+impl AdminSink {
+    pub(crate) fn new(config: AdminUiConfig) -> Self {
+        let (tx, rx) = mpsc::channel(EVENT_BUFFER_SIZE);
+        Self {
+            config,
+            tx,
+            rx: Mutex::new(Some(rx)),
+            active: AtomicBool::new(false),
+            created: Instant::now(),
+        }
+    }
+
+    /// The namespace of the dashboards.
+    pub(crate) fn namespace(&self) -> &str {
+        &self.config.namespace
+    }
+
+    /// Queue a notification for the dashboards, it is dropped if they are lagging.
+    fn send(&self, event: impl FnOnce(String) -> AdminEvent) {
+        if self.active.load(Ordering::Relaxed) {
+            if let Err(_e) = self.tx.try_send(event(timestamp(SystemTime::now()))) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("admin ui notification dropped: {_e}");
+            }
+        }
+    }
+
+    pub(crate) fn socket_connected<A: Adapter>(&self, socket: &Socket<A>) {
+        self.send(|ts| AdminEvent::SocketConnected(serialize_socket(socket), ts));
+    }
+    pub(crate) fn socket_disconnected(&self, nsp: &Str, sid: Sid, reason: DisconnectReason) {
+        self.send(|ts| AdminEvent::SocketDisconnected(nsp.clone(), sid, reason.to_string(), ts));
+    }
+    pub(crate) fn room_joined(&self, nsp: &Str, sid: Sid, room: &Room) {
+        self.send(|ts| AdminEvent::RoomJoined(nsp.clone(), room.clone(), sid, ts));
+    }
+    pub(crate) fn room_left(&self, nsp: &Str, sid: Sid, room: &Room) {
+        self.send(|ts| AdminEvent::RoomLeft(nsp.clone(), room.clone(), sid, ts));
+    }
+    /// `data` is the encoded event, including its name.
+    pub(crate) fn event_received<A: Adapter>(&self, socket: &Socket<A>, data: &Value) {
+        self.send(|ts| {
+            let args = decode_args(socket, data);
+            AdminEvent::EventReceived(socket.ns.path.clone(), socket.id, args, ts)
+        });
+    }
+    /// `data` is the encoded event, including its name.
+    pub(crate) fn event_sent<A: Adapter>(&self, socket: &Socket<A>, data: &Value) {
+        self.send(|ts| {
+            let args = decode_args(socket, data);
+            AdminEvent::EventSent(socket.ns.path.clone(), socket.id, args, ts)
+        });
+    }
+
+    /// Start forwarding the notifications and the server statistics to the dashboards
+    /// if it is not already done.
+    fn start<A: Adapter>(self: &Arc<Self>, io: &SocketIo<A>) {
+        let Some(mut rx) = self.rx.lock().unwrap().take() else {
+            return;
+        };
+        if self.config.mode == AdminUiMode::Development {
+            self.active.store(true, Ordering::Relaxed);
+        }
+
+        let client = Arc::downgrade(io.client());
+        let path = self.config.namespace.clone();
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                let Some(client) = client.upgrade() else {
+                    break;
+                };
+                let Some(op) = SocketIo::from(client).of(&path) else {
+                    continue;
+                };
+                if let Err(_e) = event.emit(op).await {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("could not forward admin ui event: {_e}");
+                }
+            }
+        });
+
+        let client = Arc::downgrade(io.client());
+        let sink = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(sink.config.stats_interval);
+            loop {
+                interval.tick().await;
+                let Some(client) = client.upgrade() else {
+                    break;
+                };
+                let stats = sink.server_stats(&client);
+                let Some(op) = SocketIo::from(client).of(sink.namespace()) else {
+                    continue;
+                };
+                if let Err(_e) = op.emit("server_stats", &stats).await {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("could not emit admin ui server stats: {_e}");
+                }
+            }
+        });
+    }
+
+    fn server_stats<A: Adapter>(&self, client: &Client<A>) -> serde_json::Value {
+        let nsps = client.get_all_ns();
+        let mut clients = HashSet::new();
+        let mut polling_clients = HashSet::new();
+        let mut namespaces = Vec::with_capacity(nsps.len());
+        for ns in nsps {
+            let sockets = ns.get_sockets();
+            for socket in &sockets {
+                clients.insert(socket.id);
+                if socket.transport_type() == TransportType::Polling {
+                    polling_clients.insert(socket.id);
+                }
+            }
+            namespaces.push(json!({ "name": ns.path.as_str(), "socketsCount": sockets.len() }));
+        }
+        let server_id = match &self.config.server_id {
+            Some(id) => id.clone(),
+            None => client.config.server_id.to_string(),
+        };
+        json!({
+            "serverId": server_id,
+            "hostname": std::env::var("HOSTNAME").unwrap_or_default(),
+            "pid": std::process::id(),
+            "uptime": self.created.elapsed().as_secs_f64(),
+            "clientsCount": clients.len(),
+            "pollingClientsCount": polling_clients.len(),
+            "aggregatedEvents": [],
+            "namespaces": namespaces,
+        })
+    }
+}
+
+/// Register the admin namespace on the server.
+pub(crate) fn register<A: Adapter>(io: &SocketIo<A>, sink: Arc<AdminSink>) {
+    let path = sink.config.namespace.clone();
+    let auth = sink.config.auth.clone();
+    let client = io.client().clone();
+    let handler = move |socket: SocketRef<A>, io: SocketIo<A>| on_connect(socket, io, &sink);
+    match auth {
+        Some(credentials) => {
+            let middleware =
+                move |TryData(auth): TryData<AdminAuth>| check_auth(auth.ok(), &credentials);
+            client.add_ns(path, handler.with(middleware)).spawn();
+        }
+        None => client.add_ns(path, handler).spawn(),
+    }
+}
+
+/// The auth payload sent by the dashboard.
+#[derive(Deserialize)]
+struct AdminAuth {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug)]
+struct InvalidCredentials;
+impl std::fmt::Display for InvalidCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid credentials")
+    }
+}
+
+fn check_auth(
+    auth: Option<AdminAuth>,
+    (username, password): &(String, String),
+) -> Result<(), InvalidCredentials> {
+    match auth {
+        // Both fields are always compared to not leak which one is wrong
+        Some(auth)
+            if (auth.username.as_bytes().ct_eq(username.as_bytes())
+                & auth.password.as_bytes().ct_eq(password.as_bytes()))
+            .into() =>
+        {
+            Ok(())
+        }
+        _ => Err(InvalidCredentials),
+    }
+}
+
+fn on_connect<A: Adapter>(socket: SocketRef<A>, io: SocketIo<A>, sink: &Arc<AdminSink>) {
+    sink.start(&io);
+    let config = &sink.config;
+    let features = json!({ "supportedFeatures": config.features() });
+    socket.emit("config", &features).ok();
+    if config.mode == AdminUiMode::Development {
+        let sockets: Vec<_> = (io.client().get_all_ns().iter())
+            .filter(|ns| ns.path.as_str() != sink.namespace())
+            .flat_map(|ns| ns.get_sockets())
+            .map(|socket| serialize_socket(&socket))
+            .collect();
+        socket.emit("all_sockets", &sockets).ok();
+    }
+    if !config.readonly {
+        socket.on("emit", on_emit::<A>);
+        socket.on("join", on_join::<A>);
+        socket.on("leave", on_leave::<A>);
+        socket.on("_disconnect", on_disconnect::<A>);
+    }
+}
+
+/// The sockets targeted by a command, either a socket id, a room or a list of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Filter {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The sockets matching a [`Filter`]:
+/// the sockets of this server for the socket ids and the sockets of the cluster for the rooms.
+struct Targets<A: Adapter> {
+    sockets: Vec<SocketRef<A>>,
+    rooms: Option<BroadcastOperators<A>>,
+}
+
+impl<A: Adapter> Targets<A> {
+    fn new(io: &SocketIo<A>, nsp: &str, filter: Option<Filter>) -> Option<Self> {
+        let op = io.of(nsp)?;
+        let filter = match filter {
+            Some(Filter::One(el)) => vec![el],
+            Some(Filter::Many(els)) => els,
+            None => {
+                return Some(Targets {
+                    sockets: Vec::new(),
+                    rooms: Some(op),
+                })
+            }
+        };
+        let mut sockets = Vec::new();
+        let mut rooms = Vec::new();
+        for el in filter {
+            match el.parse().ok().and_then(|sid| op.get_socket(sid)) {
+                Some(socket) => sockets.push(socket),
+                None => rooms.push(el),
+            }
+        }
+        let rooms = (!rooms.is_empty()).then(|| op.within(rooms));
+        Some(Targets { sockets, rooms })
+    }
+}
+
+/// `emit(nsp, filter, event, ...args)`
+async fn on_emit<A: Adapter>(io: SocketIo<A>, Data(Args(args)): Data<Args>) {
+    let mut args = args.into_iter();
+    let (Some(nsp), Some(filter), Some(event)) = (args.next(), args.next(), args.next()) else {
+        return;
+    };
+    let (Ok(nsp), Ok(filter), Ok(event)) = (
+        serde_json::from_value::<String>(nsp),
+        serde_json::from_value(filter),
+        serde_json::from_value::<String>(event),
+    ) else {
+        return;
+    };
+    let Some(targets) = Targets::new(&io, &nsp, filter) else {
+        return;
+    };
+    let args = Args(args.collect());
+    for socket in targets.sockets {
+        socket.emit(&event, &args).ok();
+    }
+    if let Some(op) = targets.rooms {
+        op.emit(&event, &args).await.ok();
+    }
+}
+
+/// `join(nsp, room, filter)`
+async fn on_join<A: Adapter>(
+    io: SocketIo<A>,
+    Data((nsp, room, filter)): Data<(String, String, Option<Filter>)>,
+) {
+    let Some(targets) = Targets::new(&io, &nsp, filter) else {
+        return;
+    };
+    for socket in targets.sockets {
+        socket.join(room.clone());
+    }
+    if let Some(op) = targets.rooms {
+        op.join(room).await.ok();
+    }
+}
+
+/// `leave(nsp, room, filter)`
+async fn on_leave<A: Adapter>(
+    io: SocketIo<A>,
+    Data((nsp, room, filter)): Data<(String, String, Option<Filter>)>,
+) {
+    let Some(targets) = Targets::new(&io, &nsp, filter) else {
+        return;
+    };
+    for socket in targets.sockets {
+        socket.leave(room.clone());
+    }
+    if let Some(op) = targets.rooms {
+        op.leave(room).await.ok();
+    }
+}
+
+/// `_disconnect(nsp, close, filter)`
+async fn on_disconnect<A: Adapter>(
+    io: SocketIo<A>,
+    Data((nsp, _close, filter)): Data<(String, bool, Option<Filter>)>,
+) {
+    let Some(targets) = Targets::new(&io, &nsp, filter) else {
+        return;
+    };
+    for socket in targets.sockets {
+        socket.disconnect().ok();
+    }
+    if let Some(op) = targets.rooms {
+        op.disconnect().await.ok();
+    }
+}
+
+/// The arguments of an event, (de)serialized as a tuple so that they are handled as separate arguments
+/// rather than as a single array argument.
+struct Args(Vec<serde_json::Value>);
+impl Serialize for Args {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(self.0.len())?;
+        for arg in &self.0 {
+            tuple.serialize_element(arg)?;
+        }
+        tuple.end()
+    }
+}
+impl<'de> Deserialize<'de> for Args {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ArgsVisitor;
+        impl<'de> Visitor<'de> for ArgsVisitor {
+            type Value = Args;
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a sequence of arguments")
+            }
+            fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Args, S::Error> {
+                let mut args = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+                while let Some(arg) = seq.next_element()? {
+                    args.push(arg);
+                }
+                Ok(Args(args))
+            }
+        }
+        deserializer.deserialize_tuple(usize::MAX, ArgsVisitor)
+    }
+}
+
+/// Decode the event name and the arguments of an encoded event.
+fn decode_args<A: Adapter>(socket: &Socket<A>, data: &Value) -> Vec<serde_json::Value> {
+    let mut data = data.clone();
+    match socket.parser.decode_value(&mut data, false) {
+        Ok(Args(args)) => args,
+        Err(_) => Vec::new(),
+    }
+}
+
+fn serialize_socket<A: Adapter>(socket: &Socket<A>) -> serde_json::Value {
+    let parts = socket.req_parts();
+    let headers: serde_json::Map<_, _> = parts
+        .headers
+        .iter()
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            (name.to_string(), value.into())
+        })
+        .collect();
+    let query: serde_json::Map<_, _> = (parts.uri.query().unwrap_or_default().split('&'))
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key.to_string(), value.into())
+        })
+        .collect();
+    let issued = socket
+        .connected_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let transport: &'static str = socket.transport_type().into();
+    json!({
+        "id": socket.id,
+        "clientId": socket.id,
+        "transport": transport,
+        "nsp": socket.ns(),
+        "data": {},
+        "handshake": {
+            "headers": headers,
+            "time": timestamp(socket.connected_at),
+            "address": null,
+            "xdomain": parts.headers.contains_key(http::header::ORIGIN),
+            "secure": parts.uri.scheme() == Some(&http::uri::Scheme::HTTPS),
+            "issued": issued,
+            "url": parts.uri.to_string(),
+            "query": query,
+            "auth": {},
+        },
+        "rooms": socket.rooms(),
+    })
+}
+
+/// Format a time as an ISO 8601 string in UTC, the javascript `Date` serialization format.
+fn timestamp(time: SystemTime) -> String {
+    let time = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = time.as_secs();
+    let (days, secs) = ((secs / 86_400) as i64, secs % 86_400);
+    // Convert the days since the epoch to a civil date.
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        time.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_timestamp() {
+        let time =
+            |secs, millis| UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_millis(millis);
+        assert_eq!(timestamp(time(0, 0)), "1970-01-01T00:00:00.000Z");
+        assert_eq!(timestamp(time(951_782_400, 7)), "2000-02-29T00:00:00.007Z");
+        assert_eq!(
+            timestamp(time(1_700_000_000, 123)),
+            "2023-11-14T22:13:20.123Z"
+        );
+    }
+
+    #[test]
+    fn auth_required() {
+        assert!(!AdminUiConfig::new().has_auth());
+        assert!(AdminUiConfig::new().without_auth().has_auth());
+        assert!(AdminUiConfig::new().with_basic_auth("a", "b").has_auth());
+    }
+
+    #[test]
+    fn check_credentials() {
+        let credentials = ("admin".to_string(), "secret".to_string());
+        let auth = |username: &str, password: &str| AdminAuth {
+            username: username.to_string(),
+            password: password.to_string(),
+        };
+        assert!(check_auth(Some(auth("admin", "secret")), &credentials).is_ok());
+        assert!(check_auth(Some(auth("admin", "secre")), &credentials).is_err());
+        assert!(check_auth(Some(auth("root", "secret")), &credentials).is_err());
+        assert!(check_auth(None, &credentials).is_err());
+    }
+
+    #[test]
+    fn features() {
+        let config = AdminUiConfig::new();
+        assert_eq!(config.features().len(), 8);
+        let config = config
+            .with_readonly(true)
+            .with_mode(AdminUiMode::Production);
+        assert!(config.features().is_empty());
+    }
+
+    #[test]
+    fn serialize_args() {
+        let args = Args(vec![json!(1), json!("foo")]);
+        assert_eq!(serde_json::to_string(&args).unwrap(), r#"[1,"foo"]"#);
+    }
+
+    #[test]
+    fn deserialize_args() {
+        let Args(args) = serde_json::from_str(r#"[1,"foo",[2]]"#).unwrap();
+        assert_eq!(args, [json!(1), json!("foo"), json!([2])]);
+    }
+}
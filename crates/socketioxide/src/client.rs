@@ -1,10 +1,14 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Duration;
 
 use bytes::Bytes;
 use engineioxide::handler::EngineIoHandler;
+use engineioxide::handoff::{HandoffRequest, HandoffResponse};
 use engineioxide::socket::{DisconnectReason as EIoDisconnectReason, Socket as EIoSocket};
 use engineioxide::Str;
 use futures_util::{FutureExt, TryFutureExt};
@@ -20,10 +24,12 @@ use crate::{
     adapter::Adapter,
     errors::Error,
     handler::ConnectHandler,
+    handoff::Sessions,
     ns::{Namespace, NamespaceCtr},
+    operators::BroadcastOperators,
     parser::{ParseError, Parser},
     socket::DisconnectReason,
-    ProtocolVersion, SocketIo, SocketIoConfig,
+    NamespaceConfig, ProtocolVersion, SocketIo, SocketIoConfig,
 };
 
 pub struct Client<A: Adapter> {
@@ -31,6 +37,10 @@ pub struct Client<A: Adapter> {
     nsps: RwLock<HashMap<Str, Arc<Namespace<A>>>>,
     router: RwLock<Router<NamespaceCtr<A>>>,
     adapter_state: A::State,
+    /// Set when the server is shutting down, new engine.io sessions are then rejected.
+    closing: AtomicBool,
+    /// The engine.io sessions open on this server if session handoff is enabled.
+    sessions: Arc<Sessions<A>>,
 
     #[cfg(feature = "state")]
     pub(crate) state: state::TypeMap![Send + Sync],
@@ -52,6 +62,8 @@ impl<A: Adapter> Client<A> {
             nsps: RwLock::new(HashMap::new()),
             router: RwLock::new(Router::new()),
             adapter_state,
+            closing: AtomicBool::new(false),
+            sessions: Arc::default(),
             #[cfg(feature = "state")]
             state,
         }
@@ -77,11 +89,32 @@ impl<A: Adapter> Client<A> {
                 }
             };
 
+        let parser = Self::session_parser(esocket).unwrap_or(self.parser());
         if let Some(ns) = self.get_ns(ns_path) {
+            if ns.parser != parser {
+                self.send_connect_error(esocket, ns_path, "Invalid parser");
+                return;
+            }
             tokio::spawn(connect(ns, esocket.clone()));
-        } else if let Ok(Match { value: ns_ctr, .. }) = self.router.read().unwrap().at(ns_path) {
+        } else if parser != self.parser() {
+            self.send_connect_error(esocket, ns_path, "Invalid parser");
+        } else if let Ok(Match {
+            value: ns_ctr,
+            params,
+        }) = self.router.read().unwrap().at(ns_path)
+        {
             let path = Str::copy_from_slice(ns_path);
-            let ns = ns_ctr.get_new_ns(path.clone(), &self.adapter_state, &self.config);
+            let params = params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            let ns = ns_ctr.get_new_ns(
+                path.clone(),
+                params,
+                &self.adapter_state,
+                &self.sessions,
+                &self.config,
+            );
             let this = self.clone();
             let esocket = esocket.clone();
             let adapter = ns.adapter.clone();
@@ -98,26 +131,88 @@ impl<A: Adapter> Client<A> {
             );
             esocket.close(EIoDisconnectReason::TransportClose);
         } else {
-            let path = Str::copy_from_slice(ns_path);
-            let packet = self
-                .parser()
-                .encode(Packet::connect_error(path, "Invalid namespace"));
-            let _ = match packet {
-                Value::Str(p, _) => esocket.emit(p).map_err(|_e| {
-                    #[cfg(feature = "tracing")]
-                    tracing::error!("error while sending invalid namespace packet: {}", _e);
-                }),
-                Value::Bytes(p) => esocket.emit_binary(p).map_err(|_e| {
-                    #[cfg(feature = "tracing")]
-                    tracing::error!("error while sending invalid namespace packet: {}", _e);
-                }),
-            };
+            self.send_connect_error(esocket, ns_path, "Invalid namespace");
+        }
+    }
+
+    /// Send a connect error packet to a client that can't connect to a namespace
+    fn send_connect_error(
+        &self,
+        esocket: &Arc<engineioxide::Socket<SocketData<A>>>,
+        ns_path: &str,
+        message: &str,
+    ) {
+        let path = Str::copy_from_slice(ns_path);
+        let mut packet = Packet::connect_error(path, message, None);
+        self.config.interceptors.outbound(&mut packet);
+        let parser = Self::session_parser(esocket).unwrap_or(self.parser());
+        let _ = match parser.encode(packet) {
+            Value::Str(p, _) => esocket.emit(p).map_err(|_e| {
+                #[cfg(feature = "tracing")]
+                tracing::error!("error while sending connect error packet: {}", _e);
+            }),
+            Value::Bytes(p) => esocket.emit_binary(p).map_err(|_e| {
+                #[cfg(feature = "tracing")]
+                tracing::error!("error while sending connect error packet: {}", _e);
+            }),
+        };
+    }
+
+    /// The parser of a session, if it is already known
+    fn session_parser(esocket: &engineioxide::Socket<SocketData<A>>) -> Option<Parser> {
+        esocket.data.parser.get().copied()
+    }
+
+    /// Decode an incoming packet with the parser of the session.
+    ///
+    /// Until the parser of the session is known, the parsers of the namespaces configured
+    /// with their own parser are tried first: the session uses the parser of the namespace
+    /// targeted by its first connect packet, or the parser of the server otherwise.
+    fn decode(
+        &self,
+        socket: &EIoSocket<SocketData<A>>,
+        decode: impl Fn(Parser, &ParserState) -> Result<Packet, ParseError>,
+    ) -> Result<Packet, ParseError> {
+        if let Some(parser) = Self::session_parser(socket) {
+            return decode(parser, &socket.data.parser_state);
+        }
+        let mut parsers: Vec<Parser> = Vec::new();
+        for ns in self.nsps.read().unwrap().values() {
+            if ns.parser != self.parser() && !parsers.contains(&ns.parser) {
+                parsers.push(ns.parser);
+            }
+        }
+        for parser in parsers {
+            if let Ok(packet) = decode(parser, &ParserState::default()) {
+                let is_connect = matches!(packet.inner, PacketData::Connect(_));
+                if is_connect
+                    && self
+                        .get_ns(&packet.ns)
+                        .is_some_and(|ns| ns.parser == parser)
+                {
+                    socket.data.parser.set(parser).ok();
+                    return Ok(packet);
+                }
+            }
+        }
+        let res = decode(self.parser(), &socket.data.parser_state);
+        if matches!(res, Ok(_) | Err(ParseError::NeedsMoreBinaryData)) {
+            socket.data.parser.set(self.parser()).ok();
         }
+        res
     }
 
     /// Propagate a packet to its target namespace
     fn sock_propagate_packet(&self, packet: Packet, sid: Sid) -> Result<(), Error> {
         if let Some(ns) = self.get_ns(&packet.ns) {
+            if ns
+                .max_payload
+                .is_some_and(|max| payload_size(&packet.inner) > max)
+            {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(?sid, "packet payload too large for namespace {}", ns.path);
+                return Err(Error::PayloadTooLarge);
+            }
             ns.recv(sid, packet.inner)
         } else {
             #[cfg(feature = "tracing")]
@@ -127,7 +222,10 @@ impl<A: Adapter> Client<A> {
     }
 
     /// Spawn a task that will close the socket if it is not connected to a namespace
-    /// after the [`SocketIoConfig::connect_timeout`] duration
+    /// after the [`SocketIoConfig::connect_timeout`] duration.
+    ///
+    /// The task is cancelled once the socket is connected to a namespace
+    /// (i.e. once it passed the connect middlewares) or when the socket is closed.
     fn spawn_connect_timeout_task(&self, socket: Arc<EIoSocket<SocketData<A>>>) {
         #[cfg(feature = "tracing")]
         tracing::debug!("spawning connect timeout task");
@@ -145,6 +243,20 @@ impl<A: Adapter> Client<A> {
 
     /// Adds a new namespace handler
     pub fn add_ns<C, T>(self: Arc<Self>, path: Cow<'static, str>, callback: C) -> A::InitRes
+    where
+        C: ConnectHandler<A, T>,
+        T: Send + Sync + 'static,
+    {
+        self.add_ns_with_config(path, NamespaceConfig::default(), callback)
+    }
+
+    /// Adds a new namespace handler with a config overriding the server config
+    pub fn add_ns_with_config<C, T>(
+        self: Arc<Self>,
+        path: Cow<'static, str>,
+        ns_config: NamespaceConfig,
+        callback: C,
+    ) -> A::InitRes
     where
         C: ConnectHandler<A, T>,
         T: Send + Sync + 'static,
@@ -153,7 +265,14 @@ impl<A: Adapter> Client<A> {
         tracing::debug!("adding namespace {}", path);
 
         let ns_path = Str::from(&path);
-        let ns = Namespace::new(ns_path.clone(), callback, &self.adapter_state, &self.config);
+        let ns = Namespace::new(
+            ns_path.clone(),
+            callback,
+            &self.adapter_state,
+            &self.sessions,
+            &self.config,
+            &ns_config,
+        );
         let adapter = ns.adapter.clone();
         let on_success = move || {
             self.nsps.write().unwrap().insert(ns_path, ns);
@@ -192,6 +311,10 @@ impl<A: Adapter> Client<A> {
         self.nsps.read().unwrap().get(path).cloned()
     }
 
+    pub(crate) fn get_all_ns(&self) -> Vec<Arc<Namespace<A>>> {
+        self.nsps.read().unwrap().values().cloned().collect()
+    }
+
     /// Closes all engine.io connections and all clients
     #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
     pub(crate) async fn close(&self) {
@@ -207,25 +330,94 @@ impl<A: Adapter> Client<A> {
         tracing::debug!("all namespaces closed");
     }
 
+    /// Gracefully shutdown the server:
+    /// * New engine.io sessions are rejected.
+    /// * The [`SocketIoConfig::shutdown_event`] is emitted to all sockets.
+    /// * Sockets have up to `grace` to disconnect by themselves.
+    /// * All the remaining sockets are closed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub(crate) async fn shutdown(&self, grace: Duration) {
+        self.closing.store(true, Ordering::SeqCst);
+        let nsps = self.get_all_ns();
+
+        if let Some(event) = &self.config.shutdown_event {
+            let grace_ms = grace.as_millis() as u64;
+            for ns in nsps {
+                let _err = BroadcastOperators::new(ns, self.parser())
+                    .broadcast()
+                    .emit(event, &grace_ms)
+                    .await;
+                #[cfg(feature = "tracing")]
+                if let Err(err) = _err {
+                    tracing::debug!(?err, "could not emit shutdown event");
+                }
+            }
+        }
+
+        let all_disconnected = async {
+            loop {
+                // Created before the check so that a removal right after it is not missed
+                let removed = self.config.socket_removed.notified();
+                let empty = self
+                    .nsps
+                    .read()
+                    .unwrap()
+                    .values()
+                    .all(|ns| ns.get_sockets().is_empty());
+                if empty {
+                    break;
+                }
+                removed.await;
+            }
+        };
+        if tokio::time::timeout(grace, all_disconnected).await.is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("shutdown grace period elapsed, closing remaining sockets");
+        }
+        self.close().await;
+    }
+
     pub(crate) fn parser(&self) -> Parser {
         self.config.parser
     }
 }
 
+/// The size in bytes of the payload of an incoming packet, binary attachments included.
+fn payload_size(packet: &PacketData) -> usize {
+    match packet {
+        PacketData::Event(value, _)
+        | PacketData::EventAck(value, _)
+        | PacketData::BinaryEvent(value, _)
+        | PacketData::BinaryAck(value, _) => match value {
+            Value::Str(data, bins) => {
+                data.len() + bins.iter().flatten().map(Bytes::len).sum::<usize>()
+            }
+            Value::Bytes(data) => data.len(),
+        },
+        _ => 0,
+    }
+}
+
 pub struct SocketData<A: Adapter> {
     pub parser_state: ParserState,
+    /// The total size of the binary attachments received for the current partial packet
+    pub attachments_size: AtomicUsize,
     /// Channel used to notify the socket that it has been connected to a namespace for v5
     pub connect_recv_tx: Mutex<Option<oneshot::Sender<()>>>,
 
     /// Used to store the [`SocketIo`] instance so it can be accessed by any sockets
     pub io: OnceLock<SocketIo<A>>,
+    /// The parser of the session, set when its first packet is decoded
+    pub(crate) parser: OnceLock<Parser>,
 }
 impl<A: Adapter> Default for SocketData<A> {
     fn default() -> Self {
         Self {
             parser_state: ParserState::default(),
+            attachments_size: AtomicUsize::new(0),
             connect_recv_tx: Mutex::new(None),
             io: OnceLock::new(),
+            parser: OnceLock::new(),
         }
     }
 }
@@ -233,7 +425,9 @@ impl<A: Adapter> fmt::Debug for SocketData<A> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("SocketData")
             .field("parser_state", &self.parser_state)
+            .field("attachments_size", &self.attachments_size)
             .field("connect_recv_tx", &self.connect_recv_tx)
+            .field("parser", &self.parser)
             .finish()
     }
 }
@@ -241,24 +435,40 @@ impl<A: Adapter> fmt::Debug for SocketData<A> {
 impl<A: Adapter> EngineIoHandler for Client<A> {
     type Data = SocketData<A>;
 
+    fn accepts_sessions(&self) -> bool {
+        !self.closing.load(Ordering::SeqCst)
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, socket), fields(sid = socket.id.to_string())))]
     fn on_connect(self: Arc<Self>, socket: Arc<EIoSocket<SocketData<A>>>) {
+        if self.closing.load(Ordering::SeqCst) {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("server is shutting down, rejecting new eio socket");
+            socket.close(EIoDisconnectReason::ClosingServer);
+            return;
+        }
         socket.data.io.set(SocketIo::from(self.clone())).ok();
+        if self.config.session_handoff {
+            self.sessions.insert(socket.clone());
+        }
 
         #[cfg(feature = "tracing")]
         tracing::debug!("eio socket connect");
 
         let protocol: ProtocolVersion = socket.protocol.into();
 
+        // The connect timeout also applies to the v4 protocol,
+        // in case the connect middlewares of the root namespace reject the client.
+        self.spawn_connect_timeout_task(socket.clone());
+
         // Connecting the client to the default namespace is mandatory if the SocketIO protocol is v4.
         // Because we connect by default to the root namespace, we should ensure before that the root namespace is defined
-        match protocol {
-            ProtocolVersion::V4 => {
-                #[cfg(feature = "tracing")]
-                tracing::debug!("connecting to default namespace for v4");
-                self.sock_connect(None, "/", &socket);
-            }
-            ProtocolVersion::V5 => self.spawn_connect_timeout_task(socket),
+        if protocol == ProtocolVersion::V4 {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("connecting to default namespace for v4");
+            let parser = self.get_ns("/").map_or(self.parser(), |ns| ns.parser);
+            socket.data.parser.set(parser).ok();
+            self.sock_connect(None, "/", &socket);
         }
     }
 
@@ -266,6 +476,11 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
     fn on_disconnect(&self, socket: Arc<EIoSocket<SocketData<A>>>, reason: EIoDisconnectReason) {
         #[cfg(feature = "tracing")]
         tracing::debug!("eio socket disconnected");
+        if self.config.session_handoff {
+            self.sessions.remove(socket.id);
+        }
+        // Drop the sender to end the connect timeout task and release the socket.
+        socket.data.connect_recv_tx.lock().unwrap().take();
         let socks: Vec<_> = self
             .nsps
             .read()
@@ -286,9 +501,13 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
     fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<EIoSocket<SocketData<A>>>) {
         #[cfg(feature = "tracing")]
         tracing::debug!("received message: {:?}", msg);
-        let packet = match self.parser().decode_str(&socket.data.parser_state, msg) {
+        let mut packet = match self.decode(&socket, |p, state| p.decode_str(state, msg.clone())) {
             Ok(packet) => packet,
-            Err(ParseError::NeedsMoreBinaryData) => return,
+            Err(ParseError::NeedsMoreBinaryData) => {
+                // A new packet with binary attachments is being received
+                socket.data.attachments_size.store(0, Ordering::Relaxed);
+                return;
+            }
             Err(_e) => {
                 #[cfg(feature = "tracing")]
                 tracing::debug!("socket deserialization error: {}", _e);
@@ -299,6 +518,7 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
         #[cfg(feature = "tracing")]
         tracing::debug!("Packet: {:?}", packet);
 
+        self.config.interceptors.inbound(&mut packet);
         let res: Result<(), Error> = match packet.inner {
             PacketData::Connect(auth) => {
                 self.sock_connect(auth, &packet.ns, &socket);
@@ -325,8 +545,23 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
     fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<EIoSocket<SocketData<A>>>) {
         #[cfg(feature = "tracing")]
         tracing::debug!("received binary: {:?}", &data);
-        let packet = match self.parser().decode_bin(&socket.data.parser_state, data) {
-            Ok(packet) => packet,
+        if let Some(max) = self.config.max_attachments_size {
+            let size = socket
+                .data
+                .attachments_size
+                .fetch_add(data.len(), Ordering::Relaxed);
+            if size + data.len() > max {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("binary attachments too large, closing socket {}", socket.id);
+                socket.close(EIoDisconnectReason::PacketParsingError);
+                return;
+            }
+        }
+        let mut packet = match self.decode(&socket, |p, state| p.decode_bin(state, data.clone())) {
+            Ok(packet) => {
+                socket.data.attachments_size.store(0, Ordering::Relaxed);
+                packet
+            }
             Err(ParseError::NeedsMoreBinaryData) => return,
             Err(_e) => {
                 #[cfg(feature = "tracing")]
@@ -336,6 +571,7 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
             }
         };
 
+        self.config.interceptors.inbound(&mut packet);
         let res: Result<(), Error> = match packet.inner {
             PacketData::Connect(auth) => {
                 self.sock_connect(auth, &packet.ns, &socket);
@@ -355,6 +591,29 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
             }
         }
     }
+
+    /// When session handoff is enabled, polling requests for sessions that are not open on this server
+    /// are forwarded through the adapter of the main namespace, or of any namespace if it is not defined.
+    fn forward_polling(
+        &self,
+        sid: Sid,
+        req: HandoffRequest,
+    ) -> impl Future<Output = Option<HandoffResponse>> + Send {
+        let ns = if self.config.session_handoff {
+            let nsps = self.nsps.read().unwrap();
+            nsps.get("/").or_else(|| nsps.values().next()).cloned()
+        } else {
+            None
+        };
+        async move {
+            let res = ns?.adapter.forward_polling(sid, req).await;
+            #[cfg(feature = "tracing")]
+            if let Err(e) = &res {
+                tracing::debug!(?sid, "error forwarding polling request: {e}");
+            }
+            res.ok().flatten()
+        }
+    }
 }
 impl<A: Adapter> std::fmt::Debug for Client<A> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -366,13 +625,13 @@ impl<A: Adapter> std::fmt::Debug for Client<A> {
     }
 }
 
-#[doc(hidden)]
-#[cfg(feature = "__test_harness")]
+#[cfg(any(feature = "__test_harness", feature = "test-client"))]
 impl<A: Adapter> Client<A> {
-    pub async fn new_dummy_sock(
+    /// Create an engine.io socket without any transport, piped to the returned channels:
+    /// the packets sent to the first one are handled by the server
+    /// and the packets sent by the server are received on the second one.
+    pub(crate) fn new_piped_sock(
         self: Arc<Self>,
-        ns: &'static str,
-        auth: impl serde::Serialize,
     ) -> (
         tokio::sync::mpsc::Sender<engineioxide::Packet>,
         tokio::sync::mpsc::Receiver<engineioxide::Packet>,
@@ -404,19 +663,35 @@ impl<A: Adapter> Client<A> {
                 }
             }
         });
+        (tx1, rx)
+    }
+}
+
+#[doc(hidden)]
+#[cfg(feature = "__test_harness")]
+impl<A: Adapter> Client<A> {
+    pub async fn new_dummy_sock(
+        self: Arc<Self>,
+        ns: &'static str,
+        auth: impl serde::Serialize,
+    ) -> (
+        tokio::sync::mpsc::Sender<engineioxide::Packet>,
+        tokio::sync::mpsc::Receiver<engineioxide::Packet>,
+    ) {
+        let (tx, rx) = self.new_piped_sock();
         let parser = crate::parser::Parser::default();
         let val = parser.encode(Packet {
             ns: ns.into(),
             inner: PacketData::Connect(Some(parser.encode_default(&auth).unwrap())),
         });
         if let Value::Str(s, _) = val {
-            self.on_message(s, esock.clone());
+            tx.send(engineioxide::Packet::Message(s)).await.unwrap();
         }
 
         // wait for the socket to be connected to the namespace
         tokio::time::sleep(std::time::Duration::from_millis(10)).await;
 
-        (tx1, rx)
+        (tx, rx)
     }
 }
 
@@ -447,7 +722,14 @@ mod test {
     #[tokio::test]
     async fn get_ns() {
         let client = create_client();
-        let ns = Namespace::new(Str::from("/"), || {}, &client.adapter_state, &client.config);
+        let ns = Namespace::new(
+            Str::from("/"),
+            || {},
+            &client.adapter_state,
+            &client.sessions,
+            &client.config,
+            &NamespaceConfig::default(),
+        );
         client.nsps.write().unwrap().insert(Str::from("/"), ns);
         assert!(client.get_ns("/").is_some());
     }
@@ -489,4 +771,63 @@ mod test {
             .await
             .unwrap_err();
     }
+
+    #[tokio::test]
+    async fn connect_timeout_middleware_fail() {
+        let client = create_client();
+        let middleware = || Err::<(), _>("unauthorized");
+        client
+            .clone()
+            .add_ns("/admin".into(), { || {} }.with(middleware));
+        let (close_tx, mut close_rx) = mpsc::channel(1);
+        let close_fn = Box::new(move |_, reason| close_tx.try_send(reason).unwrap());
+        let sock = EIoSocket::new_dummy(Sid::new(), close_fn);
+        client.clone().on_connect(sock.clone());
+        client.on_message("0/admin,".into(), sock.clone());
+        // The socket is closed because it never passed the middleware
+        let res = tokio::time::timeout(CONNECT_TIMEOUT * 2, close_rx.recv())
+            .await
+            .unwrap();
+        assert_eq!(res, Some(EIoDisconnectReason::TransportClose));
+    }
+
+    #[tokio::test]
+    async fn session_parser() {
+        let client = create_client();
+        let sock = EIoSocket::new_dummy(Sid::new(), Box::new(|_, _| {}));
+        client.clone().on_connect(sock.clone());
+        assert!(sock.data.parser.get().is_none());
+        client.on_message("0".into(), sock.clone());
+        assert_eq!(sock.data.parser.get(), Some(&Parser::default()));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[tokio::test]
+    async fn session_parser_from_ns_config() {
+        let client = create_client();
+        let msgpack = Parser::MsgPack(Default::default());
+        let config = NamespaceConfig::new().with_parser(crate::ParserConfig::msgpack());
+        client
+            .clone()
+            .add_ns_with_config("/msgpack".into(), config, || {});
+        let sock = EIoSocket::new_dummy(Sid::new(), Box::new(|_, _| {}));
+        client.clone().on_connect(sock.clone());
+        let packet = msgpack.encode(Packet::connect("/msgpack", None));
+        client.on_binary(packet.as_bytes().unwrap().clone(), sock.clone());
+        assert_eq!(sock.data.parser.get(), Some(&msgpack));
+    }
+
+    #[tokio::test]
+    async fn connect_timeout_cancelled_on_disconnect() {
+        let client = create_client();
+        let (close_tx, mut close_rx) = mpsc::channel(1);
+        let close_fn = Box::new(move |_, reason| close_tx.try_send(reason).unwrap());
+        let sock = EIoSocket::new_dummy(Sid::new(), close_fn);
+        client.clone().on_connect(sock.clone());
+        client.on_disconnect(sock.clone(), EIoDisconnectReason::TransportClose);
+        // The timeout task is cancelled and doesn't try to close the socket.
+        tokio::time::timeout(CONNECT_TIMEOUT * 2, close_rx.recv())
+            .await
+            .unwrap_err();
+    }
 }
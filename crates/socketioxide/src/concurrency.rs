@@ -0,0 +1,95 @@
+//! Limit of the number of event handlers running concurrently.
+use std::sync::{Arc, OnceLock};
+
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+
+/// The scope of a [`ConcurrencyLimit`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConcurrencyScope {
+    /// The limit is applied to the handlers of each socket.
+    #[default]
+    Socket,
+    /// The limit is shared by the handlers of all the sockets of a namespace.
+    Namespace,
+}
+
+/// A limit of the number of event handlers running concurrently,
+/// set with [`SocketIoBuilder::max_concurrent_handlers`](crate::SocketIoBuilder::max_concurrent_handlers).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConcurrencyLimit {
+    /// The maximum number of handlers running at the same time.
+    pub max: usize,
+    /// The scope of the limit.
+    pub scope: ConcurrencyScope,
+}
+
+impl ConcurrencyLimit {
+    /// Create a new [`ConcurrencyLimit`].
+    ///
+    /// # Panics
+    /// If `max` is 0.
+    pub fn new(max: usize, scope: ConcurrencyScope) -> Self {
+        assert!(
+            max > 0,
+            "the maximum number of concurrent handlers must be > 0"
+        );
+        Self { max, scope }
+    }
+}
+
+/// A job dispatching an event to its handler, called with the permit of the handler.
+pub(crate) type HandlerJob = Box<dyn FnOnce(OwnedSemaphorePermit) + Send>;
+
+/// The queue of the events waiting for a handler permit.
+///
+/// Events are dispatched in order, each one once a permit is available.
+/// The permit is held until the handler returns, or until the future of an async handler completes.
+pub(crate) struct HandlerQueue {
+    max: usize,
+    /// The maximum number of queued events.
+    size: usize,
+    /// Its worker task is spawned with the first event.
+    tx: OnceLock<mpsc::Sender<HandlerJob>>,
+}
+
+impl HandlerQueue {
+    pub fn new(max: usize, size: usize) -> Self {
+        Self {
+            max,
+            size,
+            tx: OnceLock::new(),
+        }
+    }
+
+    /// Queue a job. The job is given back if the queue is full.
+    pub fn push(&self, job: HandlerJob) -> Result<(), HandlerJob> {
+        let tx = self.tx.get_or_init(|| {
+            let (tx, rx) = mpsc::channel(self.size);
+            tokio::spawn(run_queue(rx, Arc::new(Semaphore::new(self.max))));
+            tx
+        });
+        tx.try_send(job).map_err(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for HandlerQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandlerQueue")
+            .field("max", &self.max)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+/// Dispatch the queued events once a permit is available.
+/// It stops when the queue is dropped.
+async fn run_queue(mut rx: mpsc::Receiver<HandlerJob>, permits: Arc<Semaphore>) {
+    while let Some(job) = rx.recv().await {
+        // The semaphore is never closed
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            break;
+        };
+        job(permit);
+    }
+}
@@ -1,12 +1,13 @@
 use engineioxide::{sid::Sid, socket::DisconnectReason as EIoDisconnectReason};
 use serde::{Deserialize, Serialize};
+use socketioxide_core::Value;
 use std::fmt::Debug;
 use tokio::time::error::Elapsed;
 
 pub use matchit::InsertError as NsInsertError;
 
 pub use crate::parser::ParserError;
-pub use socketioxide_core::errors::{AdapterError, BroadcastError, SocketError};
+pub use socketioxide_core::errors::{AdapterError, BroadcastError, ErrorKind, SocketError};
 
 /// Error type for socketio
 #[derive(thiserror::Error, Debug)]
@@ -23,6 +24,9 @@ pub enum Error {
     #[error("cannot find socketio socket")]
     SocketGone(Sid),
 
+    #[error("packet payload too large")]
+    PayloadTooLarge,
+
     #[error("adapter error: {0}")]
     Adapter(#[from] AdapterError),
 }
@@ -30,6 +34,7 @@ pub enum Error {
 pub(crate) struct ConnectFail;
 
 /// Error type for ack operations.
+#[non_exhaustive]
 #[derive(thiserror::Error, Debug, Serialize, Deserialize)]
 pub enum AckError {
     /// The ack response cannot be parsed
@@ -46,6 +51,7 @@ pub enum AckError {
 }
 
 /// Error type for sending operations.
+#[non_exhaustive]
 #[derive(thiserror::Error, Debug)]
 pub enum SendError {
     /// An error occurred while serializing the packet.
@@ -55,9 +61,48 @@ pub enum SendError {
     /// Error sending/receiving data through the engine.io socket
     #[error("Error sending data through the engine.io socket: {0:?}")]
     Socket(#[from] SocketError),
+
+    /// The internal packet buffer of the socket is full so the packet was not sent.
+    /// It contains the encoded payload that could not be delivered.
+    ///
+    /// The buffer size can be increased with [`SocketIoBuilder::max_buffer_size`](crate::SocketIoBuilder::max_buffer_size)
+    /// or [`Socket::emit_async`](crate::socket::Socket::emit_async) can be used to wait for space in the buffer.
+    #[error("internal packet buffer full")]
+    BufferFull(Value),
+}
+
+impl SendError {
+    /// Convert a [`SocketError`] returned when reserving space for `data` to a [`SendError`].
+    pub(crate) fn with_payload(err: SocketError, data: Value) -> Self {
+        match err {
+            SocketError::InternalChannelFull => SendError::BufferFull(data),
+            err => SendError::Socket(err),
+        }
+    }
+
+    /// The [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SendError::Serialize(_) => ErrorKind::Serialization,
+            SendError::Socket(err) => err.kind(),
+            SendError::BufferFull(_) => ErrorKind::Buffer,
+        }
+    }
+}
+
+impl AckError {
+    /// The [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            AckError::Decode(_) => ErrorKind::Serialization,
+            AckError::Timeout => ErrorKind::Timeout,
+            AckError::Socket(err) => err.kind(),
+        }
+    }
 }
 
 /// Error type for the [`emit_with_ack`](crate::operators::BroadcastOperators::emit_with_ack) method.
+#[non_exhaustive]
 #[derive(thiserror::Error, Debug)]
 pub enum EmitWithAckError {
     /// An error occurred while encoding the data.
@@ -68,6 +113,38 @@ pub enum EmitWithAckError {
     Adapter(#[from] Box<dyn std::error::Error + Send>),
 }
 
+/// Error type for the [`presence`](crate::SocketIo::presence) method.
+#[non_exhaustive]
+#[derive(thiserror::Error, Debug)]
+pub enum PresenceError {
+    /// The presence metadata of a member cannot be decoded.
+    #[error("Error decoding presence metadata: {0:?}")]
+    Decode(#[from] ParserError),
+    /// An error occurred while fetching the members from other nodes.
+    #[error("Adapter error: {0:?}")]
+    Adapter(#[from] Box<dyn std::error::Error + Send>),
+}
+
+impl EmitWithAckError {
+    /// The [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            EmitWithAckError::Encode(_) => ErrorKind::Serialization,
+            EmitWithAckError::Adapter(_) => ErrorKind::Adapter,
+        }
+    }
+}
+
+impl PresenceError {
+    /// The [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            PresenceError::Decode(_) => ErrorKind::Serialization,
+            PresenceError::Adapter(_) => ErrorKind::Adapter,
+        }
+    }
+}
+
 impl From<Elapsed> for AckError {
     fn from(_: Elapsed) -> Self {
         Self::Timeout
@@ -83,7 +160,9 @@ impl From<&Error> for Option<EIoDisconnectReason> {
         use EIoDisconnectReason::*;
         match value {
             Error::SocketGone(_) => Some(TransportClose),
-            Error::InvalidPacketType | Error::InvalidEventName => Some(PacketParsingError),
+            Error::InvalidPacketType | Error::InvalidEventName | Error::PayloadTooLarge => {
+                Some(PacketParsingError)
+            }
             Error::Adapter(_) | Error::InvalidNamespace => None,
         }
     }
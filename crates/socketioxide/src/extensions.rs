@@ -9,6 +9,9 @@
 //!
 //! You can use the [`Extension`](crate::extract::Extension) or
 //! [`MaybeExtension`](crate::extract::MaybeExtension) extractor to extract an extension of the given type.
+//!
+//! A removal hook can be attached to an extension with [`Extensions::insert_with_hook`].
+//! It is called when the extension is dropped by the map, e.g. when the socket is disconnected and dropped.
 
 use std::collections::HashMap;
 use std::fmt;
@@ -21,8 +24,35 @@ use std::{
 /// TypeMap value
 type AnyVal = Box<dyn Any + Send + Sync>;
 
+/// A hook called with the value when it is dropped by the map.
+type RemoveHook = Box<dyn FnOnce(AnyVal) + Send + Sync>;
+
+/// A value stored in the map with its optional removal hook.
+struct Entry {
+    val: AnyVal,
+    on_remove: Option<RemoveHook>,
+}
+impl Entry {
+    fn new<T: Send + Sync + 'static>(val: T) -> Self {
+        Self {
+            val: Box::new(val),
+            on_remove: None,
+        }
+    }
+    /// Get back the inner value without calling the removal hook.
+    fn into_inner<T: 'static>(self) -> Option<T> {
+        self.val.downcast().ok().map(|boxed| *boxed)
+    }
+    /// Drop the value and call the removal hook if there is one.
+    fn notify(self) {
+        if let Some(on_remove) = self.on_remove {
+            on_remove(self.val);
+        }
+    }
+}
+
 /// The [`AnyHashMap`] is a [`HashMap`] that uses `TypeId` as keys and `Any` as values.
-type AnyHashMap = RwLock<HashMap<TypeId, AnyVal, BuildHasherDefault<IdHasher>>>;
+type AnyHashMap = RwLock<HashMap<TypeId, Entry, BuildHasherDefault<IdHasher>>>;
 
 // With TypeIds as keys, there's no need to hash them. They are already hashes
 // themselves, coming from the compiler. The IdHasher just holds the u64 of
@@ -56,6 +86,8 @@ impl Hasher for IdHasher {
 ///
 /// You can use the [`Extension`](crate::extract::Extension) or
 /// [`MaybeExtension`](crate::extract::MaybeExtension) extractor to extract an extension of the given type.
+///
+/// Extensions are keyed by their type, use a newtype if you need to store multiple values of the same type.
 #[derive(Default)]
 pub struct Extensions {
     /// The underlying map
@@ -91,8 +123,130 @@ impl Extensions {
         self.map
             .write()
             .unwrap()
-            .insert(TypeId::of::<T>(), Box::new(val))
-            .and_then(|v| v.downcast().ok().map(|boxed| *boxed))
+            .insert(TypeId::of::<T>(), Entry::new(val))
+            .and_then(Entry::into_inner)
+    }
+
+    /// Insert a type into the `Extensions` with a hook called with the value
+    /// when it is dropped by the map:
+    /// * when the `Extensions` are [cleared](Extensions::clear).
+    /// * when the `Extensions` are dropped, for example after the socket is disconnected
+    ///   and all the handlers holding a reference to it have returned.
+    ///
+    /// The hook is not called if the value is returned to the caller with
+    /// [`Extensions::insert`] or [`Extensions::remove`].
+    ///
+    /// If a extension of this type already existed, it will be returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use socketioxide::extensions::Extensions;
+    /// # use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+    /// #[derive(Clone)]
+    /// struct Session(usize);
+    ///
+    /// let removed = Arc::new(AtomicUsize::new(0));
+    /// let ext = Extensions::new();
+    /// let removed1 = removed.clone();
+    /// ext.insert_with_hook(Session(1), move |session: Session| {
+    ///     removed1.store(session.0, Ordering::SeqCst);
+    /// });
+    /// drop(ext);
+    /// assert_eq!(removed.load(Ordering::SeqCst), 1);
+    /// ```
+    pub fn insert_with_hook<T, F>(&self, val: T, on_remove: F) -> Option<T>
+    where
+        T: Send + Sync + Clone + 'static,
+        F: FnOnce(T) + Send + Sync + 'static,
+    {
+        let on_remove: RemoveHook = Box::new(move |val: AnyVal| {
+            if let Ok(val) = val.downcast::<T>() {
+                on_remove(*val);
+            }
+        });
+        let entry = Entry {
+            val: Box::new(val),
+            on_remove: Some(on_remove),
+        };
+        self.map
+            .write()
+            .unwrap()
+            .insert(TypeId::of::<T>(), entry)
+            .and_then(Entry::into_inner)
+    }
+
+    /// Get a cloned value of a type previously inserted in the `Extensions`
+    /// or insert the value returned by the given closure if there is none.
+    /// The map is locked for the duration of the closure call,
+    /// so it must not access the `Extensions` itself.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use socketioxide::extensions::Extensions;
+    /// let ext = Extensions::new();
+    /// assert_eq!(ext.get_or_insert_with(|| 5i32), 5);
+    /// assert_eq!(ext.get_or_insert_with(|| 6i32), 5);
+    /// ```
+    pub fn get_or_insert_with<T, F>(&self, f: F) -> T
+    where
+        T: Send + Sync + Clone + 'static,
+        F: FnOnce() -> T,
+    {
+        self.map
+            .write()
+            .unwrap()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Entry::new(f()))
+            .val
+            .downcast_ref::<T>()
+            .cloned()
+            .expect("extension type mismatch")
+    }
+
+    /// Update in place a value of a type previously inserted in the `Extensions`.
+    /// The map is locked for the duration of the closure call,
+    /// so it must not access the `Extensions` itself.
+    ///
+    /// Returns the result of the closure or `None` if there is no value of this type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use socketioxide::extensions::Extensions;
+    /// let ext = Extensions::new();
+    /// assert_eq!(ext.update(|v: &mut i32| *v += 1), None);
+    /// ext.insert(5i32);
+    /// assert_eq!(ext.update(|v: &mut i32| { *v += 1; *v }), Some(6));
+    /// assert_eq!(ext.get::<i32>(), Some(6));
+    /// ```
+    pub fn update<T, F, R>(&self, f: F) -> Option<R>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&mut T) -> R,
+    {
+        self.map
+            .write()
+            .unwrap()
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|entry| entry.val.downcast_mut::<T>())
+            .map(f)
+    }
+
+    /// Check whether a value of the given type is in the `Extensions`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use socketioxide::extensions::Extensions;
+    /// let ext = Extensions::new();
+    /// assert!(!ext.contains::<i32>());
+    /// ext.insert(5i32);
+    /// assert!(ext.contains::<i32>());
+    /// ```
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.read().unwrap().contains_key(&TypeId::of::<T>())
     }
 
     /// Get a cloned value of a type previously inserted in the `Extensions`.
@@ -112,7 +266,7 @@ impl Extensions {
             .read()
             .unwrap()
             .get(&TypeId::of::<T>())
-            .and_then(|v| v.downcast_ref::<T>())
+            .and_then(|entry| entry.val.downcast_ref::<T>())
             .cloned()
     }
 
@@ -134,10 +288,11 @@ impl Extensions {
             .write()
             .unwrap()
             .remove(&TypeId::of::<T>())
-            .and_then(|v| v.downcast().ok().map(|boxed| *boxed))
+            .and_then(Entry::into_inner)
     }
 
     /// Clear the `Extensions` of all inserted extensions.
+    /// The removal hooks of the extensions are called.
     ///
     /// # Example
     ///
@@ -151,7 +306,9 @@ impl Extensions {
     /// ```
     #[inline]
     pub fn clear(&self) {
-        self.map.write().unwrap().clear();
+        // The hooks are called without holding the lock so that they can access the extensions.
+        let entries = std::mem::take(&mut *self.map.write().unwrap());
+        entries.into_values().for_each(Entry::notify);
     }
 
     /// Check whether the extension set is empty or not.
@@ -187,6 +344,16 @@ impl Extensions {
     }
 }
 
+impl Drop for Extensions {
+    fn drop(&mut self) {
+        let map = match self.map.get_mut() {
+            Ok(map) => map,
+            Err(e) => e.into_inner(),
+        };
+        map.drain().for_each(|(_, entry)| entry.notify());
+    }
+}
+
 impl fmt::Debug for Extensions {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Extensions").finish()
@@ -218,3 +385,27 @@ fn test_extensions() {
     assert!(extensions.get::<bool>().is_none());
     assert_eq!(extensions.get(), Some(MyType(10)));
 }
+
+#[test]
+fn test_extensions_hooks() {
+    use std::sync::{Arc, Mutex};
+    let removed = Arc::new(Mutex::new(Vec::new()));
+    let hook = |removed: &Arc<Mutex<Vec<i32>>>| {
+        let removed = removed.clone();
+        move |v: i32| removed.lock().unwrap().push(v)
+    };
+
+    let extensions = Extensions::new();
+    extensions.insert_with_hook(1i32, hook(&removed));
+    // The replaced value is returned and the hook is not called
+    assert_eq!(extensions.insert_with_hook(2i32, hook(&removed)), Some(1));
+    assert_eq!(extensions.update(|v: &mut i32| *v += 1), Some(()));
+    extensions.clear();
+    assert_eq!(*removed.lock().unwrap(), [3]);
+
+    extensions.insert_with_hook(4i32, hook(&removed));
+    assert_eq!(extensions.remove::<i32>(), Some(4));
+    extensions.insert_with_hook(5i32, hook(&removed));
+    drop(extensions);
+    assert_eq!(*removed.lock().unwrap(), [3, 5]);
+}
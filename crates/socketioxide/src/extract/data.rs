@@ -1,9 +1,13 @@
 use std::convert::Infallible;
+use std::fmt;
+use std::marker::PhantomData;
 use std::sync::Arc;
 
 use crate::handler::{FromConnectParts, FromMessageParts};
 use crate::{adapter::Adapter, socket::Socket};
-use serde::de::DeserializeOwned;
+use bytes::Bytes;
+use serde::de::{self, DeserializeOwned, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
 use socketioxide_core::parser::{Parse, ParserError};
 use socketioxide_core::Value;
 
@@ -105,5 +109,119 @@ where
     }
 }
 
+/// An Extractor that returns the deserialized auth payload of the connect event.
+///
+/// Contrary to [`Data`], if a deserialization error occurs, the connection is rejected
+/// before the socket is connected to the namespace and a `connect_error` packet
+/// with an [`AuthError`] message is sent to the client.
+///
+/// ### Example
+/// ```
+/// # use socketioxide::{SocketIo, extract::{SocketRef, Auth}};
+/// #[derive(serde::Deserialize)]
+/// struct Credentials {
+///     token: String,
+/// }
+/// let (_, io) = SocketIo::new_svc();
+/// io.ns("/", |socket: SocketRef, Auth(creds): Auth<Credentials>| {
+///     println!("socket {} connected with token {}", socket.id, creds.token);
+/// });
+/// ```
+pub struct Auth<T>(pub T);
+
+/// The error returned by the [`Auth`] extractor when the auth payload is invalid.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid auth payload: {0}")]
+pub struct AuthError(#[from] pub ParserError);
+
+impl<T, A> FromConnectParts<A> for Auth<T>
+where
+    T: DeserializeOwned,
+    A: Adapter,
+{
+    type Error = AuthError;
+    fn from_connect_parts(s: &Arc<Socket<A>>, auth: &Option<Value>) -> Result<Self, AuthError> {
+        Ok(Auth(s.parser.decode_default(auth.as_ref())?))
+    }
+
+    fn check_connect_parts(s: &Arc<Socket<A>>, auth: &Option<Value>) -> Result<(), AuthError> {
+        Self::from_connect_parts(s, auth).map(|_| ())
+    }
+}
+
+/// An Extractor that returns the deserialized first argument of the event
+/// along with the binary attachments that follow it.
+///
+/// The binary attachments are returned as is, without being round-tripped through a generic value
+/// with placeholder objects. If a deserialization error occurs, the handler won't be called
+/// and an error log will be printed if the `tracing` feature is enabled.
+///
+/// It is the counterpart of the [`bin`](crate::operators::ConfOperators::bin) operator.
+///
+/// ### Example
+/// ```
+/// # use socketioxide::{SocketIo, extract::{SocketRef, BinData}};
+/// #[derive(serde::Deserialize)]
+/// struct File {
+///     name: String,
+/// }
+/// let (_, io) = SocketIo::new_svc();
+/// io.ns("/", |socket: SocketRef| {
+///     // Received from a client with `socket.emit("upload", { name: "file" }, buffer1, buffer2)`
+///     socket.on("upload", |BinData(file, bins): BinData<File>| {
+///         println!("received {} with {} chunks", file.name, bins.len());
+///     });
+/// });
+/// ```
+pub struct BinData<T>(pub T, pub Vec<Bytes>);
+
+impl<T, A> FromMessageParts<A> for BinData<T>
+where
+    T: DeserializeOwned,
+    A: Adapter,
+{
+    type Error = ParserError;
+    fn from_message_parts(
+        s: &Arc<Socket<A>>,
+        v: &mut Value,
+        _: &Option<i64>,
+    ) -> Result<Self, Self::Error> {
+        s.parser
+            .decode_value::<BinArgs<T>>(v, true)
+            .map(|BinArgs(data, bins)| BinData(data, bins))
+    }
+}
+
+/// The event arguments: a data argument followed by binary attachments.
+/// It is deserialized as a tuple so that the parsers provide all the arguments of the event.
+struct BinArgs<T>(T, Vec<Bytes>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for BinArgs<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BinArgsVisitor<T>(PhantomData<T>);
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for BinArgsVisitor<T> {
+            type Value = BinArgs<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a data argument followed by binary attachments")
+            }
+
+            fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Self::Value, S::Error> {
+                let data = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let mut bins = Vec::new();
+                while let Some(bin) = seq.next_element::<Bytes>()? {
+                    bins.push(bin);
+                }
+                Ok(BinArgs(data, bins))
+            }
+        }
+        deserializer.deserialize_tuple(usize::MAX, BinArgsVisitor(PhantomData))
+    }
+}
+
 super::__impl_deref!(TryData<T>: Result<T, ParserError>);
 super::__impl_deref!(Data);
+super::__impl_deref!(BinData);
+super::__impl_deref!(Auth);
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use crate::handler::FromMessageParts;
+use crate::typed::SocketEvents;
+use crate::{adapter::Adapter, socket::Socket};
+use socketioxide_core::parser::{Parse, ParserError};
+use socketioxide_core::Value;
+
+/// An Extractor that decodes the received message to a [typed event](crate::typed) `E`.
+/// If the event is not part of `E` or if a deserialization error occurs, the handler won't be called
+/// and an error log will be printed if the `tracing` feature is enabled.
+///
+/// It is usually used with [`Socket::on_typed`](crate::socket::Socket::on_typed).
+pub struct Event<E>(pub E);
+
+impl<E, A> FromMessageParts<A> for Event<E>
+where
+    E: SocketEvents,
+    A: Adapter,
+{
+    type Error = ParserError;
+    fn from_message_parts(
+        s: &Arc<Socket<A>>,
+        v: &mut Value,
+        _: &Option<i64>,
+    ) -> Result<Self, Self::Error> {
+        let event = s.parser.read_event(v)?.to_owned();
+        E::decode(s.parser, &event, v).map(Event)
+    }
+}
+
+super::__impl_deref!(Event);
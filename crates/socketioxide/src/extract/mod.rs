@@ -9,18 +9,30 @@
 //! * [`TryData`]: extracts and deserialize from the any received data but with a `Result` type in case of error:
 //!     - for [`ConnectHandler`] and [`ConnectMiddleware`]: extracts and deserialize from the incoming auth data
 //!     - for [`MessageHandler`]: extracts and deserialize from the incoming message data
+//! * [`BinData`]: extracts and deserialize the first argument of a message along with the binary attachments that follow it,
+//!   if a deserialization error occurs the handler won't be called.
+//! * [`Auth`]: extracts and deserialize the auth payload of a connect event. If a deserialization error occurs,
+//!   the connection is rejected with a `connect_error` packet.
 //! * [`SocketRef`]: extracts a reference to the [`Socket`](crate::socket::Socket).
 //! * [`SocketIo`](crate::SocketIo): extracts a reference to the whole socket.io server context.
-//! * [`AckSender`]: Can be used to send an ack response to the current message event.
+//! * [`AckSender`]: Can be used to send an ack response to the current message event,
+//!   or several responses with [`AckSender::stream`].
 //! * [`ProtocolVersion`](crate::ProtocolVersion): extracts the protocol version.
 //! * [`TransportType`](crate::TransportType): extracts the transport type.
 //! * [`DisconnectReason`](crate::socket::DisconnectReason): extracts the reason of the disconnection.
+//! * [`HttpParts`]: extracts the parts of the HTTP request that opened the connection (headers, uri, extensions...).
+//! * [`NsParams`]: extracts the params captured from the path of a [dynamic namespace](crate::SocketIo::dyn_ns).
+//! * [`EventSuffix`]: extracts the rest of the event name after the prefix of a handler registered with
+//!   [`Socket::on_prefix`](crate::socket::Socket::on_prefix).
+//! * [`EventParams`]: extracts and deserializes the params captured by the route of a
+//!   [`Router`](crate::router::Router) handler.
 //! * [`State`]: extracts a [`Clone`] of a state previously set with [`SocketIoBuilder::with_state`](crate::io::SocketIoBuilder).
 //! * [`Extension`]: extracts an extension of the given type stored on the called socket by cloning it.
 //! * [`MaybeExtension`]: extracts an extension of the given type if it exists or [`None`] otherwise.
 //! * [`HttpExtension`]: extracts an http extension of the given type coming from the request
 //!   (Similar to axum's [`extract::Extension`](https://docs.rs/axum/latest/axum/struct.Extension.html).
 //! * [`MaybeHttpExtension`]: extracts an http extension of the given type if it exists or [`None`] otherwise.
+//! * [`Event`]: extracts and decodes a [typed event](crate::typed) along with its name (only available with the `macros` feature).
 //!
 //! ### You can also implement your own Extractor!
 //! Implement the [`FromConnectParts`], [`FromMessageParts`], [`FromMessage`] and [`FromDisconnectParts`] traits
@@ -105,11 +117,18 @@ mod data;
 mod extensions;
 mod socket;
 
+#[cfg(feature = "macros")]
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+mod event;
+
 #[cfg(feature = "state")]
 #[cfg_attr(docsrs, doc(cfg(feature = "state")))]
 mod state;
 
 pub use data::*;
+#[cfg(feature = "macros")]
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+pub use event::*;
 pub use extensions::*;
 pub use socket::*;
 #[cfg(feature = "state")]
@@ -5,11 +5,17 @@ use std::sync::Arc;
 use crate::{
     adapter::{Adapter, LocalAdapter},
     handler::{FromConnectParts, FromDisconnectParts, FromMessageParts},
+    router::ParamsDeserializer,
     socket::{DisconnectReason, Socket},
     SendError, SocketIo,
 };
-use serde::Serialize;
-use socketioxide_core::{errors::SocketError, packet::Packet, parser::Parse, Value};
+use serde::{de, de::DeserializeOwned, Serialize};
+use socketioxide_core::{
+    errors::SocketError,
+    packet::Packet,
+    parser::{Parse, ParserError},
+    Value,
+};
 
 /// An Extractor that returns a reference to a [`Socket`].
 ///
@@ -74,6 +80,13 @@ impl<A: Adapter> SocketRef<A> {
     pub fn disconnect(self) -> Result<(), SocketError> {
         self.0.disconnect()
     }
+
+    /// Disconnect the socket from the current namespace once the packets already emitted are sent,
+    /// see [`Socket::disconnect_after_flush`].
+    #[inline(always)]
+    pub async fn disconnect_after_flush(self) -> Result<(), SocketError> {
+        self.0.disconnect_after_flush().await
+    }
 }
 impl<A: Adapter> fmt::Debug for SocketRef<A> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -107,20 +120,34 @@ impl<A: Adapter> AckSender<A> {
 
     /// Send the ack response to the client.
     pub fn send<T: Serialize + ?Sized>(self, data: &T) -> Result<(), SendError> {
+        self.send_ref(data)
+    }
+
+    /// Switch to the streamed ack mode to send several responses to the current event,
+    /// for example the pages of a query result. See [`AckStreamSender`] for more details.
+    ///
+    /// The client must support the streamed acks, a standard socket.io client only
+    /// handles the first response.
+    pub fn stream(self) -> AckStreamSender<A> {
+        AckStreamSender { ack: Some(self) }
+    }
+
+    fn send_ref<T: Serialize + ?Sized>(&self, data: &T) -> Result<(), SendError> {
         use crate::socket::PermitExt;
+        use engineioxide::PacketPriority;
         if let Some(ack_id) = self.ack_id {
-            let permit = match self.socket.reserve() {
+            let data = self.socket.parser.encode_value(data, None)?;
+            let permit = match self.socket.reserve_with_priority(PacketPriority::High) {
                 Ok(permit) => permit,
                 Err(e) => {
                     #[cfg(feature = "tracing")]
                     tracing::debug!("sending error during emit message: {e:?}");
-                    return Err(SendError::Socket(e));
+                    return Err(SendError::with_payload(e, data));
                 }
             };
             let ns = self.socket.ns.path.clone();
-            let data = self.socket.parser.encode_value(data, None)?;
             let packet = Packet::ack(ns, data, ack_id);
-            permit.send(packet, self.socket.parser);
+            permit.send(packet, &self.socket);
             Ok(())
         } else {
             Ok(())
@@ -128,6 +155,65 @@ impl<A: Adapter> AckSender<A> {
     }
 }
 
+/// A sender of a streamed ack, created with [`AckSender::stream`].
+///
+/// Each chunk is sent as an ack packet with the ack id of the event and the arguments `[chunk, false]`.
+/// The end of the stream is marked with a last ack packet with the arguments `[null, true]`,
+/// the client must not expect more responses after it. It is sent with [`AckStreamSender::end`]
+/// or when the sender is dropped.
+///
+/// If the client sent a normal message without expecting an ack, the sender will do nothing.
+///
+/// # Example
+/// ```
+/// # use socketioxide::{SocketIo, extract::*};
+/// let (_, io) = SocketIo::new_svc();
+/// io.ns("/", |s: SocketRef| {
+///     s.on("query", |Data::<u32>(count), ack: AckSender| async move {
+///         let ack = ack.stream();
+///         for page in 0..count {
+///             let rows: Vec<String> = (0..10).map(|i| format!("row {}", page * 10 + i)).collect();
+///             if ack.send(&rows).is_err() {
+///                 return;
+///             }
+///         }
+///         ack.end().ok();
+///     });
+/// });
+/// ```
+pub struct AckStreamSender<A: Adapter = LocalAdapter> {
+    ack: Option<AckSender<A>>,
+}
+
+impl<A: Adapter> AckStreamSender<A> {
+    /// Send a chunk of the ack response to the client.
+    pub fn send<T: Serialize + ?Sized>(&self, chunk: &T) -> Result<(), SendError> {
+        match &self.ack {
+            Some(ack) => ack.send_ref(&(chunk, false)),
+            None => Ok(()),
+        }
+    }
+
+    /// End the stream by sending the final marker to the client.
+    pub fn end(mut self) -> Result<(), SendError> {
+        match self.ack.take() {
+            Some(ack) => ack.send_ref(&((), true)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<A: Adapter> Drop for AckStreamSender<A> {
+    fn drop(&mut self) {
+        if let Some(ack) = self.ack.take() {
+            if let Err(_e) = ack.send_ref(&((), true)) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("error sending the end of the streamed ack: {_e:?}");
+            }
+        }
+    }
+}
+
 impl<A: Adapter> FromConnectParts<A> for crate::ProtocolVersion {
     type Error = Infallible;
     fn from_connect_parts(s: &Arc<Socket<A>>, _: &Option<Value>) -> Result<Self, Infallible> {
@@ -209,3 +295,214 @@ impl<A: Adapter> FromDisconnectParts<A> for SocketIo<A> {
         Ok(s.get_io().clone())
     }
 }
+
+/// An Extractor that returns the params captured from the path of a
+/// [dynamic namespace](crate::SocketIo::dyn_ns).
+///
+/// For a static namespace, there are no params.
+///
+/// ### Example
+/// ```
+/// # use socketioxide::{SocketIo, extract::{SocketRef, NsParams}};
+/// let (_, io) = SocketIo::new_svc();
+/// io.dyn_ns("/room/{room_id}", |socket: SocketRef, params: NsParams| {
+///     let room_id = params.get("room_id").unwrap();
+///     println!("socket connected to room {room_id}");
+/// }).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct NsParams(Arc<[(String, String)]>);
+
+impl NsParams {
+    /// Get the value of the param with the given name.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Iterate over all the captured params as `(name, value)` pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns true if there are no captured params.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<A: Adapter> FromConnectParts<A> for NsParams {
+    type Error = Infallible;
+    fn from_connect_parts(s: &Arc<Socket<A>>, _: &Option<Value>) -> Result<Self, Infallible> {
+        Ok(NsParams(s.ns.params.clone()))
+    }
+}
+impl<A: Adapter> FromMessageParts<A> for NsParams {
+    type Error = Infallible;
+    fn from_message_parts(
+        s: &Arc<Socket<A>>,
+        _: &mut Value,
+        _: &Option<i64>,
+    ) -> Result<Self, Infallible> {
+        Ok(NsParams(s.ns.params.clone()))
+    }
+}
+impl<A: Adapter> FromDisconnectParts<A> for NsParams {
+    type Error = Infallible;
+    fn from_disconnect_parts(s: &Arc<Socket<A>>, _: DisconnectReason) -> Result<Self, Infallible> {
+        Ok(NsParams(s.ns.params.clone()))
+    }
+}
+
+/// An Extractor that returns the rest of the event name after the prefix of a handler
+/// registered with [`Socket::on_prefix`].
+///
+/// In a handler registered with [`Socket::on`], it returns the whole event name.
+///
+/// ### Example
+/// ```
+/// # use socketioxide::{SocketIo, extract::{SocketRef, EventSuffix}};
+/// let (_, io) = SocketIo::new_svc();
+/// io.ns("/", |socket: SocketRef| {
+///     socket.on_prefix("admin:", |EventSuffix(command)| {
+///         println!("admin command: {command}");
+///     });
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventSuffix(pub String);
+
+impl<A: Adapter> FromMessageParts<A> for EventSuffix {
+    type Error = ParserError;
+    fn from_message_parts(
+        s: &Arc<Socket<A>>,
+        v: &mut Value,
+        _: &Option<i64>,
+    ) -> Result<Self, ParserError> {
+        let event = s.parser.read_event(v)?;
+        let suffix = match s.matched_prefix(event) {
+            Some(prefix) => &event[prefix.len()..],
+            None => event,
+        };
+        Ok(EventSuffix(suffix.to_owned()))
+    }
+}
+super::__impl_deref!(EventSuffix: String);
+
+/// An Extractor that deserializes the params captured by the route of a [`Router`] handler.
+///
+/// The params can be deserialized to a struct with a field for each param, to a tuple with
+/// the params in the order of the route pattern, or to a single value for routes with a single param.
+/// If a deserialization error occurs, the handler won't be called and an error log will be printed
+/// if the `tracing` feature is enabled.
+///
+/// See the [`router`](crate::router) module doc for more details.
+///
+/// ### Example
+/// ```
+/// # use socketioxide::{SocketIo, router::Router, extract::{SocketRef, EventParams}};
+/// let router = Router::new()
+///     .route("room/:room/message/:id", |EventParams((room, id)): EventParams<(String, u32)>| {
+///         println!("message {id} in room {room}");
+///     });
+///
+/// let (_, io) = SocketIo::new_svc();
+/// io.ns("/", move |s: SocketRef| s.on_router(router.clone()));
+/// ```
+///
+/// [`Router`]: crate::router::Router
+#[derive(Debug, Clone)]
+pub struct EventParams<T>(pub T);
+
+impl<T, A> FromMessageParts<A> for EventParams<T>
+where
+    T: DeserializeOwned,
+    A: Adapter,
+{
+    type Error = de::value::Error;
+    fn from_message_parts(
+        s: &Arc<Socket<A>>,
+        v: &mut Value,
+        _: &Option<i64>,
+    ) -> Result<Self, Self::Error> {
+        let event = s.parser.read_event(v).map_err(de::Error::custom)?;
+        let params = s.route_params(event).unwrap_or_default();
+        T::deserialize(ParamsDeserializer(&params)).map(EventParams)
+    }
+}
+super::__impl_deref!(EventParams);
+
+/// An Extractor that gives access to the parts of the HTTP request that opened the underlying
+/// engine.io connection (headers, uri, method, http extensions...).
+/// It dereferences to [`http::request::Parts`].
+///
+/// It can be used for auth by header or IP-based logic in connect handlers and middlewares.
+/// TLS or connection info can be retrieved through the http [extensions](http::Extensions)
+/// if your http server inserts them in the request.
+///
+/// It is generic over the [`Adapter`] type. If you plan to use it with another adapter than the default,
+/// make sure to have a handler that is [generic over the adapter type](crate#adapters).
+///
+/// ### Example
+/// ```
+/// # use socketioxide::{SocketIo, extract::{SocketRef, HttpParts}};
+/// fn auth_middleware(req: HttpParts) -> Result<(), &'static str> {
+///     match req.headers.get("Authorization") {
+///         Some(token) if token == "secret" => Ok(()),
+///         _ => Err("unauthorized"),
+///     }
+/// }
+/// fn handler(socket: SocketRef, req: HttpParts) {
+///     println!("socket connected from {:?} with uri {}", req.peer_addr(), req.uri);
+/// }
+/// let (_, io) = SocketIo::new_svc();
+/// io.ns("/", handler.with(auth_middleware));
+/// # use socketioxide::handler::ConnectHandler;
+/// ```
+pub struct HttpParts<A: Adapter = LocalAdapter>(Arc<Socket<A>>);
+
+impl<A: Adapter> HttpParts<A> {
+    /// The address of the remote peer, if a [`SocketAddr`](std::net::SocketAddr)
+    /// http extension was inserted in the request by your http server.
+    pub fn peer_addr(&self) -> Option<std::net::SocketAddr> {
+        self.0.req_parts().extensions.get().copied()
+    }
+}
+
+impl<A: Adapter> FromConnectParts<A> for HttpParts<A> {
+    type Error = Infallible;
+    fn from_connect_parts(s: &Arc<Socket<A>>, _: &Option<Value>) -> Result<Self, Infallible> {
+        Ok(HttpParts(s.clone()))
+    }
+}
+impl<A: Adapter> FromMessageParts<A> for HttpParts<A> {
+    type Error = Infallible;
+    fn from_message_parts(
+        s: &Arc<Socket<A>>,
+        _: &mut Value,
+        _: &Option<i64>,
+    ) -> Result<Self, Infallible> {
+        Ok(HttpParts(s.clone()))
+    }
+}
+impl<A: Adapter> FromDisconnectParts<A> for HttpParts<A> {
+    type Error = Infallible;
+    fn from_disconnect_parts(s: &Arc<Socket<A>>, _: DisconnectReason) -> Result<Self, Infallible> {
+        Ok(HttpParts(s.clone()))
+    }
+}
+
+impl<A: Adapter> std::ops::Deref for HttpParts<A> {
+    type Target = http::request::Parts;
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        self.0.req_parts()
+    }
+}
+impl<A: Adapter> fmt::Debug for HttpParts<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.0.req_parts(), f)
+    }
+}
@@ -18,8 +18,11 @@
 //! Middlewares can be sync or async and can be chained.
 //! They are defined with the [`ConnectMiddleware`] trait which is automatically implemented for any
 //! closure with up to 16 arguments with the following signature:
-//! * `FnOnce(*args) -> Result<(), E> where E: Display`
-//! * `async FnOnce(*args) -> Result<(), E> where E: Display`
+//! * `FnOnce(*args) -> Result<(), E> where E: Into<ConnectError>`
+//! * `async FnOnce(*args) -> Result<(), E> where E: Into<ConnectError>`
+//!
+//! Any [`Display`](std::fmt::Display) error converts into a [`ConnectError`]. Return a [`ConnectError`]
+//! with [`ConnectError::with_data`] to send additional data to the client in the `connect_error` packet.
 //!
 //! Arguments must implement the [`FromConnectParts`] trait in the exact same way than handlers.
 //!
@@ -109,23 +112,102 @@
 //! io.ns("/", handler.with(middleware).with(other_middleware));
 //! ```
 
+use std::fmt;
 use std::pin::Pin;
 use std::sync::Arc;
 
-use crate::{adapter::Adapter, socket::Socket};
+use crate::{adapter::Adapter, parser::Parser, socket::Socket};
 use futures_core::Future;
-use socketioxide_core::Value;
+use serde::Serialize;
+use socketioxide_core::{
+    parser::{Parse, ParserError},
+    Value,
+};
 
 use super::MakeErasedHandler;
 
 /// A Type Erased [`ConnectHandler`] so it can be stored in a HashMap
 pub(crate) type BoxedConnectHandler<A> = Box<dyn ErasedConnectHandler<A>>;
 
-type MiddlewareRes = Result<(), Box<dyn std::fmt::Display + Send>>;
+type MiddlewareRes = Result<(), ConnectError>;
 type MiddlewareResFut<'a> = Pin<Box<dyn Future<Output = MiddlewareRes> + Send + 'a>>;
 
+/// The error returned by a [`ConnectMiddleware`] to refuse the connection to the namespace.
+///
+/// It is sent to the client in the `connect_error` packet, with a `message`
+/// and an optional `data` payload, like the socket.io `next(err)` with `err.data`.
+///
+/// Any error implementing [`Display`](std::fmt::Display) can be converted into a [`ConnectError`]
+/// without data. To attach data, build it with [`ConnectError::new`] and [`ConnectError::with_data`].
+///
+/// # Example
+/// ```rust
+/// # use socketioxide::handler::{ConnectError, ConnectHandler};
+/// # use socketioxide::extract::*;
+/// # use socketioxide::SocketIo;
+/// fn middleware(Data(token): Data<String>) -> Result<(), ConnectError> {
+///     if token != "secret" {
+///         let data = serde_json::json!({ "reason": "invalid token" });
+///         Err(ConnectError::new("unauthorized").with_data(data))
+///     } else {
+///         Ok(())
+///     }
+/// }
+///
+/// let (_, io) = SocketIo::new_layer();
+/// io.ns("/", (|s: SocketRef| ()).with(middleware));
+/// ```
+pub struct ConnectError {
+    message: String,
+    data: Option<ConnectErrorData>,
+}
+
+type ConnectErrorData = Box<dyn FnOnce(Parser) -> Result<Value, ParserError> + Send>;
+
+impl ConnectError {
+    /// Create a new [`ConnectError`] with the given message and without data.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Attach a serializable data payload to the error.
+    /// It is serialized with the parser of the socket when the `connect_error` packet is sent.
+    pub fn with_data<T: Serialize + Send + 'static>(mut self, data: T) -> Self {
+        self.data = Some(Box::new(move |parser| parser.encode_default(&data)));
+        self
+    }
+
+    /// The message of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Split the error into its message and its data serialized with the given parser.
+    pub(crate) fn into_parts(self, parser: Parser) -> (String, Result<Option<Value>, ParserError>) {
+        let data = self.data.map(|data| data(parser)).transpose();
+        (self.message, data)
+    }
+}
+
+impl<E: fmt::Display> From<E> for ConnectError {
+    fn from(err: E) -> Self {
+        Self::new(err.to_string())
+    }
+}
+
+impl fmt::Debug for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectError")
+            .field("message", &self.message)
+            .field("data", &self.data.is_some())
+            .finish()
+    }
+}
+
 pub(crate) trait ErasedConnectHandler<A: Adapter>: Send + Sync + 'static {
-    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self, s), fields(id = ?s.id)))]
     fn call(&self, s: Arc<Socket<A>>, auth: Option<Value>);
     fn call_middleware<'a>(
         &'a self,
@@ -157,11 +239,20 @@ pub trait FromConnectParts<A: Adapter>: Sized {
     /// Extract the arguments from the connect event.
     /// If it fails, the handler is not called
     fn from_connect_parts(s: &Arc<Socket<A>>, auth: &Option<Value>) -> Result<Self, Self::Error>;
+
+    /// Check the connect event before the socket is connected to the namespace,
+    /// when the extractor is used in a [`ConnectHandler`].
+    /// If it fails, the connection is rejected and a `connect_error` packet is sent to the client.
+    ///
+    /// By default it does nothing and the extraction errors only prevent the handler from being called.
+    fn check_connect_parts(_s: &Arc<Socket<A>>, _auth: &Option<Value>) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 /// Define a middleware for the connect event.
 /// It is implemented for closures with up to 16 arguments.
-/// They must implement the [`FromConnectParts`] trait and return `Result<(), E> where E: Display`.
+/// They must implement the [`FromConnectParts`] trait and return `Result<(), E> where E: Into<ConnectError>`.
 ///
 /// * See the [`connect`](super::connect) module doc for more details on connect middlewares.
 /// * See the [`extract`](crate::extract) module doc for more details on available extractors.
@@ -169,7 +260,7 @@ pub trait FromConnectParts<A: Adapter>: Sized {
     since(1.78),
     diagnostic::on_unimplemented(
         note = "This function is not a ConnectMiddleware. Check that:
-* It is a clonable sync or async `FnOnce` that returns `Result<(), E> where E: Display`.
+* It is a clonable sync or async `FnOnce` that returns `Result<(), E> where E: Into<ConnectError>`.
 * All its arguments are valid connect extractors.
 * If you use a custom adapter, it must be generic over the adapter type.
 See `https://docs.rs/socketioxide/latest/socketioxide/extract/index.html` for details.\n",
@@ -307,6 +398,7 @@ where
     H: ConnectHandler<A, T> + Send + Sync + 'static,
     T: Send + Sync + 'static,
 {
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self, s), fields(id = ?s.id)))]
     fn call(&self, s: Arc<Socket<A>>, auth: Option<Value>) {
         self.handler.call(s, auth);
     }
@@ -341,7 +433,10 @@ where
         s: Arc<Socket<A>>,
         auth: &'a Option<Value>,
     ) -> MiddlewareResFut<'a> {
-        Box::pin(async move { self.middleware.call(s, auth).await })
+        Box::pin(async move {
+            self.middleware.call(s.clone(), auth).await?;
+            self.handler.call_middleware(s, auth).await
+        })
     }
 
     fn with<M2, T2>(self, next: M2) -> impl ConnectHandler<A, T>
@@ -420,6 +515,23 @@ mod private {
     pub enum Async {}
 }
 
+/// Run the [`FromConnectParts::check_connect_parts`] fn of each extractor of a handler.
+macro_rules! check_connect_parts {
+    ($s:ident, $auth:ident, $($ty:ident),*) => {{
+        let res: MiddlewareRes = 'check: {
+            $(
+                if let Err(e) = $ty::check_connect_parts(&$s, $auth) {
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!("connect extractor check failed: {}", e);
+                    break 'check Err(e.into());
+                }
+            )*
+            Ok(())
+        };
+        res
+    }};
+}
+
 macro_rules! impl_handler_async {
     (
         [$($ty:ident),*]
@@ -445,8 +557,17 @@ macro_rules! impl_handler_async {
                 )*
 
                 let fut = (self.clone())($($ty,)*);
-                tokio::spawn(fut);
+                super::spawn(fut);
+
+            }
 
+            fn call_middleware<'a>(
+                &'a self,
+                s: Arc<Socket<A>>,
+                auth: &'a Option<Value>,
+            ) -> MiddlewareResFut<'a> {
+                let res = check_connect_parts!(s, auth, $($ty),*);
+                Box::pin(async move { res })
             }
         }
     };
@@ -477,6 +598,15 @@ macro_rules! impl_handler {
 
                 (self.clone())($($ty,)*);
             }
+
+            fn call_middleware<'a>(
+                &'a self,
+                s: Arc<Socket<A>>,
+                auth: &'a Option<Value>,
+            ) -> MiddlewareResFut<'a> {
+                let res = check_connect_parts!(s, auth, $($ty),*);
+                Box::pin(async move { res })
+            }
         }
     };
 }
@@ -491,7 +621,7 @@ macro_rules! impl_middleware_async {
             F: FnOnce($($ty,)*) -> Fut + Send + Sync + Clone + 'static,
             Fut: Future<Output = Result<(), E>> + Send + 'static,
             A: Adapter,
-            E: std::fmt::Display + Send + 'static,
+            E: Into<ConnectError> + Send + 'static,
             $( $ty: FromConnectParts<A> + Send, )*
         {
             async fn call<'a>(
@@ -505,16 +635,17 @@ macro_rules! impl_middleware_async {
                         Err(e) => {
                             #[cfg(feature = "tracing")]
                             tracing::error!("Error while extracting data: {}", e);
-                            return Err(Box::new(e) as _);
+                            return Err(e.into());
                         },
                     };
                 )*
 
                 let res = (self.clone())($($ty,)*).await;
                 if let Err(e) = res {
+                    let e: ConnectError = e.into();
                     #[cfg(feature = "tracing")]
-                    tracing::trace!("middleware returned error: {}", e);
-                    Err(Box::new(e) as _)
+                    tracing::trace!("middleware returned error: {}", e.message());
+                    Err(e)
                 } else {
                     Ok(())
                 }
@@ -532,7 +663,7 @@ macro_rules! impl_middleware {
         where
             F: FnOnce($($ty,)*) -> Result<(), E> + Send + Sync + Clone + 'static,
             A: Adapter,
-            E: std::fmt::Display + Send + 'static,
+            E: Into<ConnectError> + Send + 'static,
             $( $ty: FromConnectParts<A> + Send, )*
         {
             async fn call<'a>(
@@ -546,16 +677,17 @@ macro_rules! impl_middleware {
                         Err(e) => {
                             #[cfg(feature = "tracing")]
                             tracing::error!("Error while extracting data: {}", e);
-                            return Err(Box::new(e) as _);
+                            return Err(e.into());
                         },
                     };
                 )*
 
                 let res = (self.clone())($($ty,)*);
                 if let Err(e) = res {
+                    let e: ConnectError = e.into();
                     #[cfg(feature = "tracing")]
-                    tracing::trace!("middleware returned error: {}", e);
-                    Err(Box::new(e) as _)
+                    tracing::trace!("middleware returned error: {}", e.message());
+                    Err(e)
                 } else {
                     Ok(())
                 }
@@ -174,7 +174,7 @@ macro_rules! impl_handler_async {
                 )*
 
                 let fut = (self.clone())($($ty,)*);
-                tokio::spawn(fut);
+                super::spawn(fut);
 
             }
         }
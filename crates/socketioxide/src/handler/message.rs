@@ -52,6 +52,73 @@
 //! });
 //! ```
 //!
+//! ## Example with handlers returning an ack
+//! Handlers can return any type that implements [`IntoAck`], such as `Result<T, E>`
+//! where `T` and `E` are serializable. If the client expects an acknowledgement,
+//! the returned value is automatically sent as the ack response.
+//! ```rust
+//! # use socketioxide::SocketIo;
+//! # use socketioxide::extract::*;
+//! let (svc, io) = SocketIo::new_svc();
+//! io.ns("/", |s: SocketRef| {
+//!     s.on("add", |Data::<(i32, i32)>((a, b))| a.checked_add(b).ok_or("overflow"));
+//!     s.on("get_user", |Data::<String>(id)| async move {
+//!         tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+//!         Ok::<_, String>(format!("user {id}"))
+//!     });
+//! });
+//! ```
+//!
+//! ## Example with a blocking handler
+//! Sync handlers are called by the task driving the connection of the socket.
+//! CPU-bound or blocking handlers (e.g. image processing or compression) can be wrapped in
+//! [`Blocking`] so that they run on the blocking thread pool of the runtime with
+//! [`spawn_blocking`](tokio::task::spawn_blocking), rather than stalling the other sockets.
+//! ```rust
+//! # use socketioxide::{SocketIo, handler::Blocking};
+//! # use socketioxide::extract::*;
+//! fn checksum(data: &[u8]) -> u32 {
+//!     data.iter().fold(0u32, |acc, b| acc.rotate_left(5) ^ *b as u32)
+//! }
+//! let (svc, io) = SocketIo::new_svc();
+//! io.ns("/", |s: SocketRef| {
+//!     s.on("checksum", Blocking(|Data::<String>(data)| Ok::<_, ()>(checksum(data.as_bytes()))));
+//! });
+//! ```
+//!
+//! ## Example with guards
+//! A handler can be wrapped with [guards](MessageGuard) with [`MessageHandler::guard`].
+//! They are called before the handler and can reject the event. In this case the handler
+//! is not called and, if the client expects an acknowledgement, the error is sent as
+//! the ack response: `{ "error": err }`.
+//!
+//! Guards can be sync or async and can be chained. They are defined with the [`MessageGuard`] trait
+//! which is automatically implemented for any closure with up to 16 arguments with the following signature:
+//! * `FnOnce(*args) -> Result<(), E> where E: Serialize`
+//! * `async FnOnce(*args) -> Result<(), E> where E: Serialize`
+//!
+//! Arguments must implement the [`FromMessageParts`] trait, they don't consume the message
+//! so the handler can still extract the data of the event.
+//! ```rust
+//! # use socketioxide::{SocketIo, handler::MessageHandler};
+//! # use socketioxide::extract::*;
+//! fn is_admin(s: SocketRef) -> Result<(), &'static str> {
+//!     match s.rooms().iter().any(|room| *room == "admins") {
+//!         true => Ok(()),
+//!         false => Err("forbidden"),
+//!     }
+//! }
+//! fn ban(Data(user): Data<String>) {
+//!     println!("banning {user}");
+//! }
+//!
+//! let (svc, io) = SocketIo::new_svc();
+//! io.ns("/", |s: SocketRef| {
+//!     s.on("admin:ban", ban.guard(is_admin));
+//!     s.on("admin:kick", (|Data::<String>(user)| println!("kicking {user}")).guard(is_admin));
+//! });
+//! ```
+//!
 //! ## Example with an async non-anonymous handler
 //! ```rust
 //! # use socketioxide::SocketIo;
@@ -71,15 +138,18 @@
 use std::sync::Arc;
 
 use futures_core::Future;
+use futures_util::{future::Either, FutureExt};
 use socketioxide_core::Value;
 
 use crate::adapter::Adapter;
+use crate::extract::AckSender;
 use crate::socket::Socket;
 
 use super::MakeErasedHandler;
 
-/// A Type Erased [`MessageHandler`] so it can be stored in a HashMap
-pub(crate) type BoxedMessageHandler<A> = Box<dyn ErasedMessageHandler<A>>;
+/// A Type Erased [`MessageHandler`] so it can be stored in a HashMap.
+/// It is shared so that it can be called without holding the lock of the map.
+pub(crate) type SharedMessageHandler<A> = Arc<dyn ErasedMessageHandler<A>>;
 
 pub(crate) trait ErasedMessageHandler<A: Adapter>: Send + Sync + 'static {
     fn call(&self, s: Arc<Socket<A>>, v: Value, ack_id: Option<i64>);
@@ -94,7 +164,7 @@ pub(crate) trait ErasedMessageHandler<A: Adapter>: Send + Sync + 'static {
     since(1.78),
     diagnostic::on_unimplemented(
         note = "This function is not a MessageHandler. Check that:
-* It is a clonable sync or async `FnOnce` that returns nothing.
+* It is a clonable sync or async `FnOnce` that returns nothing or a type implementing `IntoAck`.
 * All its arguments are valid message extractors.
 * If you use a custom adapter, it must be generic over the adapter type.
 See `https://docs.rs/socketioxide/latest/socketioxide/extract/index.html` for details.\n",
@@ -105,20 +175,156 @@ pub trait MessageHandler<A: Adapter, T>: Send + Sync + 'static {
     /// Call the handler with the given arguments
     fn call(&self, s: Arc<Socket<A>>, v: Value, ack_id: Option<i64>);
 
+    /// Wraps this [`MessageHandler`] with a [`MessageGuard`] called before it.
+    /// If the guard rejects the event, the handler is not called and the error
+    /// is sent as the ack response if the client expects one.
+    ///
+    /// The new provided guard will be called before the current ones.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use socketioxide::{SocketIo, handler::MessageHandler};
+    /// # use socketioxide::extract::*;
+    /// fn is_admin(s: SocketRef) -> Result<(), &'static str> {
+    ///     match s.rooms().iter().any(|room| *room == "admins") {
+    ///         true => Ok(()),
+    ///         false => Err("forbidden"),
+    ///     }
+    /// }
+    ///
+    /// // Guards can be sync or async
+    /// async fn is_known_user(Data(user): Data<String>) -> Result<(), String> {
+    ///     tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    ///     Err(format!("unknown user {user}"))
+    /// }
+    ///
+    /// fn ban(Data(user): Data<String>) -> Result<String, String> {
+    ///     Ok(format!("{user} banned"))
+    /// }
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |s: SocketRef| s.on("admin:ban", ban.guard(is_known_user).guard(is_admin)));
+    /// ```
+    fn guard<G, T1>(self, guard: G) -> impl MessageHandler<A, T>
+    where
+        Self: Sized,
+        G: MessageGuard<A, T1>,
+        T: Send + Sync + 'static,
+        T1: Send + Sync + 'static,
+    {
+        GuardedMessageHandler {
+            handler: Arc::new(self),
+            guard,
+            phantom: std::marker::PhantomData,
+        }
+    }
+
+    #[doc(hidden)]
+    fn phantom(&self) -> std::marker::PhantomData<T> {
+        std::marker::PhantomData
+    }
+}
+
+/// Sends the rejection of a [`MessageGuard`] as the ack response, if the client expects one.
+type GuardRejection<A> = Box<dyn FnOnce(Option<AckSender<A>>) + Send>;
+type GuardRes<A> = Result<(), GuardRejection<A>>;
+
+/// Define a guard for a message event.
+/// It is implemented for closures with up to 16 arguments.
+/// They must implement the [`FromMessageParts`] trait and return `Result<(), E> where E: Serialize`.
+///
+/// * See the [`message`](super::message) module doc for more details on message guards.
+/// * See the [`extract`](crate::extract) module doc for more details on available extractors.
+#[rustversion::attr(
+    since(1.78),
+    diagnostic::on_unimplemented(
+        note = "This function is not a MessageGuard. Check that:
+* It is a clonable sync or async `FnOnce` that returns `Result<(), E> where E: Serialize`.
+* All its arguments are valid message extractors implementing `FromMessageParts`.
+* If you use a custom adapter, it must be generic over the adapter type.
+See `https://docs.rs/socketioxide/latest/socketioxide/extract/index.html` for details.\n",
+        label = "Invalid MessageGuard"
+    )
+)]
+pub trait MessageGuard<A: Adapter, T>: Send + Sync + 'static {
+    /// Call the guard with the given arguments.
+    /// The arguments are extracted before the returned future is polled.
+    fn call(
+        &self,
+        s: &Arc<Socket<A>>,
+        v: &mut Value,
+        ack_id: &Option<i64>,
+    ) -> impl Future<Output = GuardRes<A>> + Send + 'static;
+
     #[doc(hidden)]
     fn phantom(&self) -> std::marker::PhantomData<T> {
         std::marker::PhantomData
     }
 }
 
+struct GuardedMessageHandler<H, G, T1> {
+    handler: Arc<H>,
+    guard: G,
+    phantom: std::marker::PhantomData<T1>,
+}
+
+impl<A, H, G, T, T1> MessageHandler<A, T> for GuardedMessageHandler<H, G, T1>
+where
+    A: Adapter,
+    H: MessageHandler<A, T>,
+    G: MessageGuard<A, T1>,
+    T: Send + Sync + 'static,
+    T1: Send + Sync + 'static,
+{
+    fn call(&self, s: Arc<Socket<A>>, mut v: Value, ack_id: Option<i64>) {
+        let mut res = Box::pin(self.guard.call(&s, &mut v, &ack_id));
+        // Sync guards are resolved right away so that sync handlers are still
+        // called in the order of the events.
+        match (&mut res).now_or_never() {
+            Some(res) => call_if_allowed(&*self.handler, res, s, v, ack_id),
+            None => {
+                let handler = self.handler.clone();
+                super::spawn(async move {
+                    call_if_allowed(&*handler, res.await, s, v, ack_id);
+                });
+            }
+        }
+    }
+}
+
+fn call_if_allowed<A: Adapter, T>(
+    handler: &impl MessageHandler<A, T>,
+    res: GuardRes<A>,
+    s: Arc<Socket<A>>,
+    v: Value,
+    ack_id: Option<i64>,
+) {
+    match res {
+        Ok(()) => handler.call(s, v, ack_id),
+        Err(reject) => reject(ack_sender(&s, ack_id)),
+    }
+}
+
+/// Build the [`GuardRejection`] sending the error returned by a guard.
+fn guard_rejection<A: Adapter, E>(err: E) -> GuardRejection<A>
+where
+    E: serde::Serialize + Send + 'static,
+{
+    Box::new(move |ack| {
+        if let Some(ack) = ack {
+            Err::<(), E>(err).send_ack(ack);
+        }
+    })
+}
+
 impl<A, T, H> MakeErasedHandler<H, A, T>
 where
     T: Send + Sync + 'static,
     H: MessageHandler<A, T>,
     A: Adapter,
 {
-    pub fn new_message_boxed(inner: H) -> Box<dyn ErasedMessageHandler<A>> {
-        Box::new(MakeErasedHandler::new(inner))
+    pub fn new_message_shared(inner: H) -> SharedMessageHandler<A> {
+        Arc::new(MakeErasedHandler::new(inner))
     }
 }
 impl<A, T, H> ErasedMessageHandler<A> for MakeErasedHandler<H, A, T>
@@ -134,6 +340,32 @@ where
     }
 }
 
+/// A wrapper marking a [`MessageHandler`] as heavy, so that it is called on the blocking
+/// thread pool of the runtime with [`spawn_blocking`](tokio::task::spawn_blocking).
+///
+/// It is meant for sync handlers doing CPU-bound or blocking work. The arguments are extracted
+/// and the handler is called on the blocking thread, and the returned value is sent as the ack
+/// response like for any other handler.
+///
+/// **Note**: Like async handlers, blocking handlers of the same socket may run concurrently,
+/// so the order of the events is not guaranteed.
+///
+/// See the [module level documentation](self#example-with-a-blocking-handler) for an example.
+#[derive(Debug, Clone, Copy)]
+pub struct Blocking<H>(pub H);
+
+impl<A, H, T> MessageHandler<A, (private::Blocking, T)> for Blocking<H>
+where
+    H: MessageHandler<A, T> + Clone,
+    T: Send + Sync + 'static,
+    A: Adapter,
+{
+    fn call(&self, s: Arc<Socket<A>>, v: Value, ack_id: Option<i64>) {
+        let handler = self.0.clone();
+        super::spawn_blocking(move || handler.call(s, v, ack_id));
+    }
+}
+
 mod private {
     #[derive(Debug, Clone, Copy)]
     pub enum ViaParts {}
@@ -145,6 +377,63 @@ mod private {
     pub enum Sync {}
     #[derive(Debug, Clone, Copy)]
     pub enum Async {}
+    #[derive(Debug, Clone, Copy)]
+    pub enum Blocking {}
+    #[derive(Debug, Clone, Copy)]
+    pub enum Guard {}
+}
+
+/// A trait for the values returned by a [`MessageHandler`].
+///
+/// If the client expects an acknowledgement for the received event,
+/// the returned value is sent as the ack response. Otherwise it is dropped.
+///
+/// It is implemented for:
+/// * `()`: nothing is sent, you can still use the [`AckSender`] extractor to send the ack manually.
+/// * `Result<T, E>` where `T` and `E` are serializable: `Ok(data)` is sent as `data`
+///   and `Err(err)` is sent as `{ "error": err }`.
+#[rustversion::attr(
+    since(1.78),
+    diagnostic::on_unimplemented(
+        note = "Message handlers must return `()` or `Result<T, E>` where `T` and `E` are serializable.",
+        label = "Invalid message handler return type"
+    )
+)]
+pub trait IntoAck: Send + 'static {
+    /// Send the value with the given [`AckSender`].
+    fn send_ack<A: Adapter>(self, ack: AckSender<A>);
+}
+
+impl IntoAck for () {
+    #[inline(always)]
+    fn send_ack<A: Adapter>(self, _: AckSender<A>) {}
+}
+
+impl<T, E> IntoAck for Result<T, E>
+where
+    T: serde::Serialize + Send + 'static,
+    E: serde::Serialize + Send + 'static,
+{
+    fn send_ack<A: Adapter>(self, ack: AckSender<A>) {
+        #[derive(serde::Serialize)]
+        struct AckError<E> {
+            error: E,
+        }
+        let res = match self {
+            Ok(data) => ack.send(&data),
+            Err(error) => ack.send(&AckError { error }),
+        };
+        if let Err(_e) = res {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("error sending handler ack response: {_e:?}");
+        }
+    }
+}
+
+/// Build the [`AckSender`] used to send the value returned by a handler, if the client expects an ack.
+#[inline(always)]
+fn ack_sender<A: Adapter>(s: &Arc<Socket<A>>, ack_id: Option<i64>) -> Option<AckSender<A>> {
+    ack_id.map(|id| AckSender::new(s.clone(), Some(id)))
 }
 
 /// A trait used to extract arguments from the message event.
@@ -212,26 +501,37 @@ where
 }
 
 /// Empty Async handler
-impl<A, F, Fut> MessageHandler<A, (private::Async,)> for F
+impl<A, F, Fut, R> MessageHandler<A, (private::Async, R)> for F
 where
     F: FnOnce() -> Fut + Send + Sync + Clone + 'static,
-    Fut: Future<Output = ()> + Send + 'static,
+    Fut: Future<Output = R> + Send + 'static,
+    R: IntoAck,
     A: Adapter,
 {
-    fn call(&self, _: Arc<Socket<A>>, _: Value, _: Option<i64>) {
+    fn call(&self, s: Arc<Socket<A>>, _: Value, ack_id: Option<i64>) {
+        let ack = ack_sender(&s, ack_id);
         let fut = (self.clone())();
-        tokio::spawn(fut);
+        super::spawn(async move {
+            let res = fut.await;
+            if let Some(ack) = ack {
+                res.send_ack(ack);
+            }
+        });
     }
 }
 
 /// Empty Sync handler
-impl<A, F> MessageHandler<A, (private::Sync,)> for F
+impl<A, F, R> MessageHandler<A, (private::Sync, R)> for F
 where
-    F: FnOnce() + Send + Sync + Clone + 'static,
+    F: FnOnce() -> R + Send + Sync + Clone + 'static,
+    R: IntoAck,
     A: Adapter,
 {
-    fn call(&self, _: Arc<Socket<A>>, _: Value, _: Option<i64>) {
-        (self.clone())();
+    fn call(&self, s: Arc<Socket<A>>, _: Value, ack_id: Option<i64>) {
+        let res = (self.clone())();
+        if let Some(ack) = ack_sender(&s, ack_id) {
+            res.send_ack(ack);
+        }
     }
 }
 
@@ -240,10 +540,11 @@ macro_rules! impl_async_handler {
         [$($ty:ident),*], $last:ident
     ) => {
         #[allow(non_snake_case, unused)]
-        impl<A, F, M, $($ty,)* $last, Fut> MessageHandler<A, (private::Async, M, $($ty,)* $last,)> for F
+        impl<A, F, M, R, $($ty,)* $last, Fut> MessageHandler<A, (private::Async, M, R, $($ty,)* $last,)> for F
         where
             F: FnOnce($($ty,)* $last,) -> Fut + Send + Sync + Clone + 'static,
-            Fut: Future<Output = ()> + Send + 'static,
+            Fut: Future<Output = R> + Send + 'static,
+            R: IntoAck,
             A: Adapter,
             $( $ty: FromMessageParts<A> + Send, )*
             $last: FromMessage<A, M> + Send,
@@ -259,6 +560,7 @@ macro_rules! impl_async_handler {
                         },
                     };
                 )*
+                let ack = ack_sender(&s, ack_id);
                 let last = match $last::from_message(s, v, ack_id) {
                     Ok(v) => v,
                     Err(_e) => {
@@ -269,7 +571,12 @@ macro_rules! impl_async_handler {
                 };
 
                 let fut = (self.clone())($($ty,)* last);
-                tokio::spawn(fut);
+                super::spawn(async move {
+                    let res = fut.await;
+                    if let Some(ack) = ack {
+                        res.send_ack(ack);
+                    }
+                });
             }
         }
     };
@@ -279,9 +586,10 @@ macro_rules! impl_handler {
         [$($ty:ident),*], $last:ident
     ) => {
         #[allow(non_snake_case, unused)]
-        impl<A, F, M, $($ty,)* $last> MessageHandler<A, (private::Sync, M, $($ty,)* $last,)> for F
+        impl<A, F, M, R, $($ty,)* $last> MessageHandler<A, (private::Sync, M, R, $($ty,)* $last,)> for F
         where
-            F: FnOnce($($ty,)* $last,) + Send + Sync + Clone + 'static,
+            F: FnOnce($($ty,)* $last,) -> R + Send + Sync + Clone + 'static,
+            R: IntoAck,
             A: Adapter,
             $( $ty: FromMessageParts<A> + Send, )*
             $last: FromMessage<A, M> + Send,
@@ -297,6 +605,7 @@ macro_rules! impl_handler {
                         },
                     };
                 )*
+                let ack = ack_sender(&s, ack_id);
                 let last = match $last::from_message(s, v, ack_id) {
                     Ok(v) => v,
                     Err(_e) => {
@@ -306,12 +615,122 @@ macro_rules! impl_handler {
                     },
                 };
 
-                (self.clone())($($ty,)* last);
+                let res = (self.clone())($($ty,)* last);
+                if let Some(ack) = ack {
+                    res.send_ack(ack);
+                }
+            }
+        }
+    };
+}
+
+/// Extract the arguments of a guard. If an extraction fails the event is rejected
+/// without ack response, like handlers that are not called.
+macro_rules! extract_guard_parts {
+    ($s:ident, $v:ident, $ack_id:ident, $wrap:path, $($ty:ident),*) => {
+        $(
+            let $ty = match $ty::from_message_parts($s, $v, $ack_id) {
+                Ok(v) => v,
+                Err(_e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("Error while extracting data: {}", _e);
+                    let reject: GuardRejection<A> = Box::new(|_| ());
+                    return $wrap(std::future::ready(Err(reject)));
+                },
+            };
+        )*
+    };
+}
+
+macro_rules! impl_guard {
+    (
+        [$($ty:ident),*]
+    ) => {
+        #[allow(non_snake_case, unused)]
+        impl<A, F, E, $($ty,)*> MessageGuard<A, (private::Guard, private::Sync, $($ty,)*)> for F
+        where
+            F: FnOnce($($ty,)*) -> Result<(), E> + Send + Sync + Clone + 'static,
+            E: serde::Serialize + Send + 'static,
+            A: Adapter,
+            $( $ty: FromMessageParts<A> + Send, )*
+        {
+            fn call(
+                &self,
+                s: &Arc<Socket<A>>,
+                v: &mut Value,
+                ack_id: &Option<i64>,
+            ) -> impl Future<Output = GuardRes<A>> + Send + 'static {
+                extract_guard_parts!(s, v, ack_id, std::convert::identity, $($ty),*);
+                let res = (self.clone())($($ty,)*).map_err(|e| {
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!("message guard rejected the event");
+                    guard_rejection::<A, E>(e)
+                });
+                std::future::ready(res)
             }
         }
     };
 }
 
+macro_rules! impl_guard_async {
+    (
+        [$($ty:ident),*]
+    ) => {
+        #[allow(non_snake_case, unused)]
+        impl<A, F, Fut, E, $($ty,)*> MessageGuard<A, (private::Guard, private::Async, $($ty,)*)> for F
+        where
+            F: FnOnce($($ty,)*) -> Fut + Send + Sync + Clone + 'static,
+            Fut: Future<Output = Result<(), E>> + Send + 'static,
+            E: serde::Serialize + Send + 'static,
+            A: Adapter,
+            $( $ty: FromMessageParts<A> + Send, )*
+        {
+            fn call(
+                &self,
+                s: &Arc<Socket<A>>,
+                v: &mut Value,
+                ack_id: &Option<i64>,
+            ) -> impl Future<Output = GuardRes<A>> + Send + 'static {
+                extract_guard_parts!(s, v, ack_id, Either::Left, $($ty),*);
+                let fut = (self.clone())($($ty,)*);
+                Either::<std::future::Ready<_>, _>::Right(async move {
+                    fut.await.map_err(|e| {
+                        #[cfg(feature = "tracing")]
+                        tracing::trace!("message guard rejected the event");
+                        guard_rejection::<A, E>(e)
+                    })
+                })
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+macro_rules! all_the_guard_tuples {
+    ($name:ident) => {
+        $name!([]);
+        $name!([T1]);
+        $name!([T1, T2]);
+        $name!([T1, T2, T3]);
+        $name!([T1, T2, T3, T4]);
+        $name!([T1, T2, T3, T4, T5]);
+        $name!([T1, T2, T3, T4, T5, T6]);
+        $name!([T1, T2, T3, T4, T5, T6, T7]);
+        $name!([T1, T2, T3, T4, T5, T6, T7, T8]);
+        $name!([T1, T2, T3, T4, T5, T6, T7, T8, T9]);
+        $name!([T1, T2, T3, T4, T5, T6, T7, T8, T9, T10]);
+        $name!([T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11]);
+        $name!([T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12]);
+        $name!([T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13]);
+        $name!([T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14]);
+        $name!([T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15]);
+        $name!([T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12, T13, T14, T15, T16]);
+    };
+}
+
+all_the_guard_tuples!(impl_guard);
+all_the_guard_tuples!(impl_guard_async);
+
 #[rustfmt::skip]
 macro_rules! all_the_tuples {
     ($name:ident) => {
@@ -1,18 +1,100 @@
 //! Functions and types used to handle incoming connections and messages.
 //! There is three main types of handlers: [connect], [message] and [disconnect].
 //! All handlers can be async or not.
+//!
+//! ## Panics
+//! A panic in a handler is caught so that it doesn't take down the task driving the connection
+//! of the socket. It is reported as a [`HandlerPanic`] to the hooks registered with
+//! [`SocketIo::on_handler_error`](crate::SocketIo::on_handler_error), and the socket can be
+//! disconnected with [`SocketIoBuilder::disconnect_on_handler_panic`](crate::SocketIoBuilder::disconnect_on_handler_panic).
+//! The panics of async and [`Blocking`] handlers are only caught when one of these options is used, otherwise
+//! they are handled by the tokio runtime, which only aborts the task of the handler.
+use std::{
+    any::Any,
+    borrow::Cow,
+    cell::RefCell,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, RwLock},
+};
+
+use futures_util::FutureExt;
+use socketioxide_core::{Sid, Str};
+use tokio::sync::OwnedSemaphorePermit;
+
+use crate::{adapter::Adapter, socket::Socket};
+
 pub mod connect;
 pub mod disconnect;
 pub mod message;
 
 pub(crate) use connect::BoxedConnectHandler;
-pub use connect::{ConnectHandler, ConnectMiddleware, FromConnectParts};
+pub use connect::{ConnectError, ConnectHandler, ConnectMiddleware, FromConnectParts};
 pub(crate) use disconnect::BoxedDisconnectHandler;
 pub use disconnect::{DisconnectHandler, FromDisconnectParts};
-pub(crate) use message::BoxedMessageHandler;
-pub use message::{FromMessage, FromMessageParts, MessageHandler};
+pub(crate) use message::SharedMessageHandler;
+pub use message::{Blocking, FromMessage, FromMessageParts, IntoAck, MessageGuard, MessageHandler};
 pub use socketioxide_core::Value;
 
+/// Spawn the future of an async handler.
+///
+/// With the `tracing` feature flag, the future is instrumented with the current span
+/// so that it stays attached to the span of the received packet.
+///
+/// If the handler is called with [`call_guarded`] and panics are reported,
+/// the panics of the future are caught and reported.
+/// If the handler holds a [concurrency](crate::SocketIoBuilder::max_concurrent_handlers) permit,
+/// it is released once the future completes.
+pub(crate) fn spawn<F>(fut: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    let permit = PERMIT.with(|permit| permit.borrow_mut().take());
+    let fut = async move {
+        fut.await;
+        drop(permit);
+    };
+    #[cfg(feature = "tracing")]
+    let fut = tracing::Instrument::in_current_span(fut);
+    match REPORTER.with(|reporter| reporter.borrow().clone()) {
+        Some(reporter) => tokio::spawn(async move {
+            if let Err(payload) = AssertUnwindSafe(fut).catch_unwind().await {
+                reporter(payload);
+            }
+        }),
+        None => tokio::spawn(fut),
+    };
+}
+
+/// Run a blocking handler on the blocking thread pool of the runtime.
+///
+/// Like [`spawn`], it is attached to the current span, its panics are reported
+/// if the handler is called with [`call_guarded`] and panics are reported,
+/// and it holds the concurrency permit of the handler.
+pub(crate) fn spawn_blocking<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    let permit = PERMIT.with(|permit| permit.borrow_mut().take());
+    let f = move || {
+        f();
+        drop(permit);
+    };
+    #[cfg(feature = "tracing")]
+    let f = {
+        let span = tracing::Span::current();
+        move || span.in_scope(f)
+    };
+    let reporter = REPORTER.with(|reporter| reporter.borrow().clone());
+    tokio::task::spawn_blocking(move || match reporter {
+        Some(reporter) => {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+                reporter(payload);
+            }
+        }
+        None => f(),
+    });
+}
+
 /// A struct used to erase the type of [`ConnectHandler`] or [`MessageHandler`] so it can be stored in a map
 pub(crate) struct MakeErasedHandler<H, A, T> {
     handler: H,
@@ -37,3 +119,118 @@ impl<H: Clone, A, T> Clone for MakeErasedHandler<H, A, T> {
         }
     }
 }
+
+/// Reports the panic of a spawned handler future.
+type PanicReporter = Arc<dyn Fn(Box<dyn Any + Send>) + Send + Sync>;
+
+thread_local! {
+    /// The reporter of the handler currently called on this thread,
+    /// captured by the futures of async handlers when they are spawned.
+    static REPORTER: RefCell<Option<PanicReporter>> = const { RefCell::new(None) };
+
+    /// The concurrency permit of the handler currently called on this thread,
+    /// moved to the future of an async handler when it is spawned.
+    static PERMIT: RefCell<Option<OwnedSemaphorePermit>> = const { RefCell::new(None) };
+}
+
+/// Call a handler of a socket and catch its panics.
+///
+/// If panics are reported for the namespace of the socket, the futures spawned
+/// by the handler also catch their panics.
+///
+/// The concurrency permit, if any, is held until the handler returns or until
+/// the future it spawned completes.
+pub(crate) fn call_guarded<A: Adapter>(
+    socket: &Arc<Socket<A>>,
+    handler: HandlerKind,
+    permit: Option<OwnedSemaphorePermit>,
+    call: impl FnOnce(),
+) {
+    let reporter = socket.ns.reports_handler_panics().then(|| {
+        let socket = socket.clone();
+        let handler = handler.clone();
+        Arc::new(move |payload| socket.on_handler_panic(handler.clone(), payload)) as PanicReporter
+    });
+    let prev = REPORTER.with(|current| current.replace(reporter));
+    let prev_permit = PERMIT.with(|current| current.replace(permit));
+    let res = panic::catch_unwind(AssertUnwindSafe(call));
+    REPORTER.with(|current| *current.borrow_mut() = prev);
+    // The permit of a sync handler is released here
+    PERMIT.with(|current| *current.borrow_mut() = prev_permit);
+    if let Err(payload) = res {
+        socket.on_handler_panic(handler, payload);
+    }
+}
+
+/// The handler in which a panic was caught, see [`HandlerPanic`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandlerKind {
+    /// The connect handler of the namespace.
+    Connect,
+    /// The message handler of the given event.
+    Message(Cow<'static, str>),
+    /// The disconnect handler of the socket.
+    Disconnect,
+}
+
+/// A panic caught in a handler of a socket, reported to the hooks registered with
+/// [`SocketIo::on_handler_error`](crate::SocketIo::on_handler_error).
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct HandlerPanic {
+    /// The namespace of the socket.
+    pub ns: Str,
+    /// The id of the socket.
+    pub sid: Sid,
+    /// The handler that panicked.
+    pub handler: HandlerKind,
+    /// The panic message, if the panic payload is a string.
+    pub message: Option<String>,
+}
+
+impl HandlerPanic {
+    pub(crate) fn new(ns: Str, sid: Sid, handler: HandlerKind, payload: &(dyn Any + Send)) -> Self {
+        let message = match payload.downcast_ref::<&'static str>() {
+            Some(msg) => Some((*msg).to_owned()),
+            None => payload.downcast_ref::<String>().cloned(),
+        };
+        Self {
+            ns,
+            sid,
+            handler,
+            message,
+        }
+    }
+}
+
+type HandlerErrorHook = Box<dyn Fn(&HandlerPanic) + Send + Sync>;
+
+/// The hooks called when a handler panics,
+/// registered with [`SocketIo::on_handler_error`](crate::SocketIo::on_handler_error).
+#[derive(Default)]
+pub(crate) struct HandlerErrorHooks(RwLock<Vec<HandlerErrorHook>>);
+
+impl HandlerErrorHooks {
+    pub(crate) fn push(&self, hook: HandlerErrorHook) {
+        self.0.write().unwrap().push(hook);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.read().unwrap().is_empty()
+    }
+
+    pub(crate) fn report(&self, panic: &HandlerPanic) {
+        for hook in self.0.read().unwrap().iter() {
+            hook(panic);
+        }
+    }
+}
+
+impl std::fmt::Debug for HandlerErrorHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("HandlerErrorHooks")
+            .field(&self.0.read().unwrap().len())
+            .finish()
+    }
+}
@@ -0,0 +1,50 @@
+//! Session handoff between servers for http long-polling without sticky sessions.
+use std::sync::Arc;
+
+use engineioxide::{
+    handoff::{HandoffRequest, HandoffResponse},
+    sharded::ShardedMap,
+    sid::Sid,
+    Socket as EIoSocket,
+};
+use futures_core::future::BoxFuture;
+
+use crate::{adapter::Adapter, client::SocketData};
+
+/// The engine.io sessions open on this server, used to handle the polling requests
+/// forwarded by other servers.
+pub(crate) struct Sessions<A: Adapter> {
+    sessions: ShardedMap<Sid, Arc<EIoSocket<SocketData<A>>>>,
+}
+
+impl<A: Adapter> Sessions<A> {
+    pub fn insert(&self, socket: Arc<EIoSocket<SocketData<A>>>) {
+        self.sessions.insert(socket.id, socket);
+    }
+
+    pub fn remove(&self, sid: Sid) {
+        self.sessions.remove(&sid);
+    }
+
+    /// Handle a forwarded request if the session is open on this server.
+    pub fn handoff(
+        &self,
+        sid: Sid,
+        req: HandoffRequest,
+    ) -> BoxFuture<'static, Option<HandoffResponse>> {
+        let socket = self.sessions.get(&sid);
+        Box::pin(async move {
+            let socket = socket?;
+            let client = socket.data.io.get()?.client().clone();
+            Some(socket.handoff(&client, req).await)
+        })
+    }
+}
+
+impl<A: Adapter> Default for Sessions<A> {
+    fn default() -> Self {
+        Self {
+            sessions: ShardedMap::new(),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! ## [actix-web](https://docs.rs/actix-web/latest/actix_web/) integration.
+//!
+//! actix-web is not based on tower and hyper, so the [`SocketIoService`] can't be mounted directly.
+//! The [`ActixService`] converts the actix-web requests and responses and bridges the
+//! websocket connections upgraded by actix-web to the socket.io service.
+//!
+//! The service should be registered at the root of the application because it is mounted
+//! on the [`req_path`](crate::SocketIoBuilder::req_path) of the socket.io server.
+//! A resource is registered for each of its path prefixes when the service is registered,
+//! so a [matcher](crate::ReqPath::matcher) or a path changed at runtime are not supported.
+//!
+//! By default, actix-web keeps processing a request when the client closes its side of the
+//! connection. A polling request abandoned by its client would then keep waiting for the next
+//! packets of the session and steal them from the next polling request. The server should be
+//! built with [`h1_allow_half_closed(false)`](actix_web::HttpServer::h1_allow_half_closed)
+//! so that the abandoned polling requests are aborted as soon as their connection is closed.
+//!
+//! #### Example
+//! ```no_run
+//! use actix_web::{web, App, HttpServer};
+//! use socketioxide::{extract::*, integrations::actix::ActixService, SocketIo};
+//!
+//! async fn run() -> std::io::Result<()> {
+//!     let (svc, io) = SocketIo::new_svc();
+//!     io.ns("/", |s: SocketRef| {
+//!         s.on("message", |s: SocketRef, Data::<String>(msg)| {
+//!             s.emit("message-back", &msg).ok();
+//!         });
+//!     });
+//!
+//!     HttpServer::new(move || {
+//!         App::new()
+//!             .service(ActixService::new(svc.clone()))
+//!             .route("/", web::get().to(|| async { "Hello, World!" }))
+//!     })
+//!     .h1_allow_half_closed(false)
+//!     .bind(("127.0.0.1", 3000))?
+//!     .run()
+//!     .await
+//! }
+//! ```
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    body::SizedStream,
+    dev::{AppService, HttpServiceFactory},
+    http::StatusCode,
+    web, HttpRequest, HttpResponse,
+};
+use bytes::{Bytes, BytesMut};
+use engineioxide::service::{NotFoundService, UpgradedConn};
+use futures_core::Stream;
+use futures_util::StreamExt;
+use http::{header, HeaderName, HeaderValue, Method, Request, Version};
+use http_body::{Body, Frame};
+use hyper::service::Service as HyperSvc;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    sync::mpsc,
+};
+
+use super::BodyStream;
+use crate::{
+    adapter::{Adapter, LocalAdapter},
+    service::SocketIoService,
+};
+
+/// The size of the buffer used to bridge websocket connections.
+const WS_BUFFER_SIZE: usize = 64 * 1024;
+
+/// An actix-web [`HttpServiceFactory`] serving a [`SocketIoService`]
+/// on the [`req_path`](crate::SocketIoBuilder::req_path) of the socket.io server.
+///
+/// See the [module level documentation](self) for an example.
+pub struct ActixService<A: Adapter = LocalAdapter> {
+    svc: SocketIoService<NotFoundService, A>,
+}
+
+impl<A: Adapter> ActixService<A> {
+    /// Create a new [`ActixService`] from a [`SocketIoService`].
+    pub fn new(svc: SocketIoService<NotFoundService, A>) -> Self {
+        Self { svc }
+    }
+}
+
+impl<A: Adapter> Clone for ActixService<A> {
+    fn clone(&self) -> Self {
+        Self {
+            svc: self.svc.clone(),
+        }
+    }
+}
+
+impl<A: Adapter> std::fmt::Debug for ActixService<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActixService").finish()
+    }
+}
+
+impl<A: Adapter> HttpServiceFactory for ActixService<A> {
+    fn register(self, config: &mut AppService) {
+        for prefix in self.svc.engine_config().req_path.prefixes() {
+            let path = format!("{prefix}{{tail}}*");
+            let svc = self.svc.clone();
+            web::resource(path)
+                .to(move |req: HttpRequest, payload: web::Payload| {
+                    handle(svc.clone(), req, payload)
+                })
+                .register(config);
+        }
+    }
+}
+
+/// Convert the actix-web request, call the socket.io service and convert back its response.
+async fn handle<A: Adapter>(
+    svc: SocketIoService<NotFoundService, A>,
+    req: HttpRequest,
+    mut payload: web::Payload,
+) -> HttpResponse {
+    let mut builder = Request::builder()
+        .method(Method::from_bytes(req.method().as_str().as_bytes()).unwrap_or_default())
+        .uri(req.uri().to_string())
+        .version(match req.version() {
+            actix_web::http::Version::HTTP_09 => Version::HTTP_09,
+            actix_web::http::Version::HTTP_10 => Version::HTTP_10,
+            actix_web::http::Version::HTTP_2 => Version::HTTP_2,
+            actix_web::http::Version::HTTP_3 => Version::HTTP_3,
+            _ => Version::HTTP_11,
+        });
+    for (name, value) in req.headers() {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_str().as_bytes()),
+            HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            builder = builder.header(name, value);
+        }
+    }
+
+    // The actix-web payload is not `Send`, so it is forwarded from a local task.
+    // With an upgrade request, the payload carries the raw bytes of the connection
+    // and the response body streams the bytes written by the server.
+    let (body, upgraded) = if req.head().upgrade() {
+        let (conn, upgraded) = tokio::io::duplex(WS_BUFFER_SIZE);
+        let (rd, mut wr) = tokio::io::split(conn);
+        actix_web::rt::spawn(async move {
+            while let Some(Ok(chunk)) = payload.next().await {
+                if wr.write_all(&chunk).await.is_err() {
+                    break;
+                }
+            }
+            wr.shutdown().await.ok();
+        });
+        builder = builder.extension(UpgradedConn::new(upgraded));
+        (ChannelBody::empty(), Some(rd))
+    } else {
+        let (tx, rx) = mpsc::channel(1);
+        actix_web::rt::spawn(async move {
+            while let Some(chunk) = payload.next().await {
+                let chunk = chunk.map_err(|e| io::Error::other(e.to_string()));
+                if tx.send(chunk).await.is_err() {
+                    break;
+                }
+            }
+        });
+        (ChannelBody(rx), None)
+    };
+
+    let req = match builder.body(body) {
+        Ok(req) => req,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+    let Ok(res) = svc.call(req).await;
+    let (parts, body) = res.into_parts();
+
+    let status = StatusCode::from_u16(parts.status.as_u16()).unwrap_or(StatusCode::OK);
+    let mut res = HttpResponse::build(status);
+    let is_upgrade = parts.status == http::StatusCode::SWITCHING_PROTOCOLS;
+    for (name, value) in &parts.headers {
+        // These headers are set by actix-web from the response body and the upgrade flag.
+        let skipped = [
+            header::CONTENT_LENGTH,
+            header::TRANSFER_ENCODING,
+            header::CONNECTION,
+            header::UPGRADE,
+        ];
+        if !skipped.contains(name) {
+            res.append_header((name.as_str(), value.as_bytes()));
+        }
+    }
+
+    match upgraded {
+        Some(rd) if is_upgrade => {
+            res.upgrade("websocket");
+            res.streaming(reader_stream(rd))
+        }
+        _ => {
+            let len = parts
+                .headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|len| len.to_str().ok()?.parse().ok())
+                .or_else(|| body.size_hint().exact());
+            let stream = BodyStream::new(body);
+            match len {
+                Some(len) => res.body(SizedStream::new(len, stream)),
+                None => res.streaming(stream),
+            }
+        }
+    }
+}
+
+/// A request body receiving the chunks of the actix-web payload.
+#[derive(Debug)]
+struct ChannelBody(mpsc::Receiver<Result<Bytes, io::Error>>);
+impl ChannelBody {
+    fn empty() -> Self {
+        Self(mpsc::channel(1).1)
+    }
+}
+impl Body for ChannelBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        self.0
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+    }
+}
+
+/// A stream of the bytes written by the server to the upgraded connection.
+fn reader_stream<R: AsyncRead + Unpin>(rd: R) -> impl Stream<Item = io::Result<Bytes>> {
+    futures_util::stream::unfold(Some(rd), |rd| async move {
+        let mut rd = rd?;
+        let mut buf = BytesMut::with_capacity(8 * 1024);
+        match rd.read_buf(&mut buf).await {
+            Ok(0) => None,
+            Ok(_) => Some((Ok(buf.freeze()), Some(rd))),
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
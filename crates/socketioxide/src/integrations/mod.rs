@@ -0,0 +1,61 @@
+//! ## Integrations with http frameworks that are not based on tower or hyper v1.
+//!
+//! Each integration is enabled with the feature flag of the framework.
+//! Frameworks supporting tower or hyper services can directly use the [`SocketIoService`] or the
+//! [`SocketIoLayer`].
+//!
+//! [`SocketIoService`]: crate::service::SocketIoService
+//! [`SocketIoLayer`]: crate::layer::SocketIoLayer
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use http_body::Body;
+use pin_project_lite::pin_project;
+
+#[cfg(feature = "actix")]
+#[cfg_attr(docsrs, doc(cfg(feature = "actix")))]
+pub mod actix;
+#[cfg(feature = "rocket")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rocket")))]
+pub mod rocket;
+#[cfg(feature = "salvo")]
+#[cfg_attr(docsrs, doc(cfg(feature = "salvo")))]
+pub mod salvo;
+#[cfg(feature = "warp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "warp")))]
+pub mod warp;
+
+pin_project! {
+    /// A stream of the data frames of a response body.
+    struct BodyStream<B> {
+        #[pin]
+        body: B,
+    }
+}
+impl<B> BodyStream<B> {
+    fn new(body: B) -> Self {
+        Self { body }
+    }
+}
+impl<B: Body<Data = Bytes>> Stream for BodyStream<B> {
+    type Item = Result<Bytes, B::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut body = self.project().body;
+        loop {
+            match ready!(body.as_mut().poll_frame(cx)) {
+                Some(Ok(frame)) => {
+                    if let Ok(data) = frame.into_data() {
+                        return Poll::Ready(Some(Ok(data)));
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! ## [rocket](https://docs.rs/rocket/latest/rocket/) integration.
+//!
+//! rocket is not based on tower and hyper v1, so the [`SocketIoService`] can't be mounted directly.
+//! The [`RocketService`] is a rocket [`Handler`] serving the GET and POST requests on the
+//! [`req_path`](crate::SocketIoBuilder::req_path) of the socket.io server.
+//!
+//! It can either be attached as a [`Fairing`] that mounts its routes when rocket ignites,
+//! or be mounted at the root of the application as a list of routes.
+//! The routes are created for each of the path prefixes of the server, so a
+//! [matcher](crate::ReqPath::matcher) or a path changed at runtime are not supported.
+//!
+//! Websocket connections are upgraded with the rocket upgrade API and the raw bytes
+//! of the connection are then forwarded to the socket.io server.
+//!
+//! #### Example
+//! ```no_run
+//! use socketioxide::{extract::*, integrations::rocket::RocketService, SocketIo};
+//!
+//! async fn run() -> Result<(), rocket::Error> {
+//!     let (svc, io) = SocketIo::new_svc();
+//!     io.ns("/", |s: SocketRef| {
+//!         s.on("message", |s: SocketRef, Data::<String>(msg)| {
+//!             s.emit("message-back", &msg).ok();
+//!         });
+//!     });
+//!
+//!     rocket::build()
+//!         .attach(RocketService::new(svc))
+//!         .launch()
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+use std::{
+    io::{self, Cursor},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use engineioxide::service::{NotFoundService, UpgradedConn};
+use futures_util::{future, TryStreamExt};
+use http::{header, Request};
+use http_body::{Body, Frame};
+use hyper::service::Service as HyperSvc;
+use rocket::{
+    data::{ByteUnit, IoHandler, IoStream},
+    fairing::{self, Fairing, Info, Kind},
+    http::{Method, Status},
+    route::{Handler, Outcome},
+    Build, Data, Response, Rocket, Route,
+};
+use tokio::io::DuplexStream;
+
+use super::BodyStream;
+use crate::{
+    adapter::{Adapter, LocalAdapter},
+    service::SocketIoService,
+};
+
+/// The size of the buffer used to bridge websocket connections.
+const WS_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A rocket [`Handler`] and [`Fairing`] serving a [`SocketIoService`]
+/// on the [`req_path`](crate::SocketIoBuilder::req_path) of the socket.io server.
+///
+/// See the [module level documentation](self) for an example.
+pub struct RocketService<A: Adapter = LocalAdapter> {
+    svc: SocketIoService<NotFoundService, A>,
+}
+
+impl<A: Adapter> RocketService<A> {
+    /// Create a new [`RocketService`] from a [`SocketIoService`].
+    pub fn new(svc: SocketIoService<NotFoundService, A>) -> Self {
+        Self { svc }
+    }
+}
+
+impl<A: Adapter> Clone for RocketService<A> {
+    fn clone(&self) -> Self {
+        Self {
+            svc: self.svc.clone(),
+        }
+    }
+}
+
+impl<A: Adapter> std::fmt::Debug for RocketService<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RocketService").finish()
+    }
+}
+
+/// The GET and POST routes of the socket.io server. They should be mounted at the root
+/// of the application because they already include the [`req_path`](crate::SocketIoBuilder::req_path).
+impl<A: Adapter> From<RocketService<A>> for Vec<Route> {
+    fn from(svc: RocketService<A>) -> Self {
+        let prefixes = svc.svc.engine_config().req_path.prefixes();
+        let mut routes = Vec::with_capacity(prefixes.len() * 2);
+        for prefix in prefixes {
+            let path = format!("{}/<_..>", prefix.trim_end_matches('/'));
+            routes.push(Route::new(Method::Get, &path, svc.clone()));
+            routes.push(Route::new(Method::Post, &path, svc.clone()));
+        }
+        routes
+    }
+}
+
+#[rocket::async_trait]
+impl<A: Adapter> Fairing for RocketService<A> {
+    fn info(&self) -> Info {
+        Info {
+            name: "socket.io",
+            kind: Kind::Ignite,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        Ok(rocket.mount("/", self.clone()))
+    }
+}
+
+#[rocket::async_trait]
+impl<A: Adapter> Handler for RocketService<A> {
+    async fn handle<'r>(&self, req: &'r rocket::Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        let mut builder = Request::builder()
+            .method(req.method().as_str())
+            .uri(req.uri().to_string());
+        for h in req.headers().iter() {
+            if let (Ok(name), Ok(value)) = (
+                header::HeaderName::from_bytes(h.name().as_str().as_bytes()),
+                header::HeaderValue::from_str(h.value()),
+            ) {
+                builder = builder.header(name, value);
+            }
+        }
+
+        let is_upgrade = req
+            .headers()
+            .get_one("upgrade")
+            .is_some_and(|proto| proto.eq_ignore_ascii_case("websocket"));
+        let (body, conn) = if is_upgrade {
+            let (conn, upgraded) = tokio::io::duplex(WS_BUFFER_SIZE);
+            builder = builder.extension(UpgradedConn::new(upgraded));
+            (FullBody(None), Some(conn))
+        } else {
+            // The rocket data stream borrows the request, so the payload is buffered.
+            // It can't be larger than the max payload of the engine.io server anyway.
+            let limit = ByteUnit::from(self.svc.engine_config().max_payload);
+            match data.open(limit).into_bytes().await {
+                Ok(bytes) if bytes.is_complete() => {
+                    (FullBody(Some(bytes.into_inner().into())), None)
+                }
+                Ok(_) => return Outcome::Error(Status::PayloadTooLarge),
+                Err(_) => return Outcome::Error(Status::BadRequest),
+            }
+        };
+
+        let req = match builder.body(body) {
+            Ok(req) => req,
+            Err(_) => return Outcome::Error(Status::BadRequest),
+        };
+        let Ok(res) = self.svc.call(req).await;
+        let (parts, body) = res.into_parts();
+
+        let mut res = Response::build();
+        let upgraded = parts.status == http::StatusCode::SWITCHING_PROTOCOLS;
+        if !upgraded {
+            res.status(Status::new(parts.status.as_u16()));
+        }
+        for (name, value) in &parts.headers {
+            // These headers are set by rocket from the response body and the upgrade handler.
+            let skipped = [
+                header::CONTENT_LENGTH,
+                header::TRANSFER_ENCODING,
+                header::CONNECTION,
+                header::UPGRADE,
+            ];
+            if let (false, Ok(value)) = (skipped.contains(name), value.to_str()) {
+                res.raw_header_adjoin(name.as_str().to_owned(), value.to_owned());
+            }
+        }
+
+        match conn {
+            Some(conn) if upgraded => {
+                res.upgrade("websocket", ConnBridge(conn));
+            }
+            _ => {
+                let body = BodyStream::new(body)
+                    .try_fold(BytesMut::new(), |mut buf, chunk| {
+                        buf.extend_from_slice(&chunk);
+                        future::ready(Ok(buf))
+                    })
+                    .await;
+                match body {
+                    Ok(body) => res.sized_body(body.len(), Cursor::new(body.freeze())),
+                    Err(_) => return Outcome::Error(Status::InternalServerError),
+                };
+            }
+        }
+        Outcome::Success(res.finalize())
+    }
+}
+
+/// Forward the raw bytes between the connection upgraded by rocket and the socket.io server.
+struct ConnBridge(DuplexStream);
+
+#[rocket::async_trait]
+impl IoHandler for ConnBridge {
+    async fn io(self: Pin<Box<Self>>, mut io: IoStream) -> io::Result<()> {
+        let mut conn = Pin::into_inner(self).0;
+        tokio::io::copy_bidirectional(&mut io, &mut conn).await?;
+        Ok(())
+    }
+}
+
+/// A request body with the payload buffered from the rocket data stream.
+#[derive(Debug)]
+struct FullBody(Option<Bytes>);
+impl Body for FullBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        Poll::Ready(self.0.take().map(|data| Ok(Frame::data(data))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.0.is_none()
+    }
+}
@@ -0,0 +1,111 @@
+//! ## [salvo](https://docs.rs/salvo/latest/salvo/) integration.
+//!
+//! The [`SalvoHandler`] is a salvo [`Handler`] serving a [`SocketIoService`]. It can be mounted on any
+//! router path, as long as it is the [`req_path`](crate::SocketIoBuilder::req_path) of the socket.io server.
+//!
+//! The extensions of the salvo [`Request`] are given to the socket.io server, so that
+//! the values inserted by previous handlers are available in the connect handlers
+//! through the [`HttpExtension`](crate::extract::HttpExtension) extractor
+//! or the [`req_parts`](crate::socket::Socket::req_parts) of the socket.
+//!
+//! #### Example
+//! ```no_run
+//! use salvo_core::prelude::*;
+//! use socketioxide::{extract::*, integrations::salvo::SalvoHandler, SocketIo};
+//!
+//! async fn run() {
+//!     let (svc, io) = SocketIo::new_svc();
+//!     io.ns("/", |s: SocketRef| {
+//!         s.on("message", |s: SocketRef, Data::<String>(msg)| {
+//!             s.emit("message-back", &msg).ok();
+//!         });
+//!     });
+//!
+//!     let router = Router::new().push(Router::with_path("socket.io/{**}").goal(SalvoHandler::new(svc)));
+//!     let acceptor = TcpListener::new("127.0.0.1:3000").bind().await;
+//!     Server::new(acceptor).serve(router).await;
+//! }
+//! ```
+use std::collections::VecDeque;
+
+use engineioxide::service::NotFoundService;
+use futures_util::{future, TryStreamExt};
+use http::{Request as HttpRequest, StatusCode};
+use hyper::service::Service as HyperSvc;
+use salvo_core::{async_trait, http::ResBody, Depot, FlowCtrl, Handler, Request, Response};
+
+use super::BodyStream;
+use crate::{
+    adapter::{Adapter, LocalAdapter},
+    service::SocketIoService,
+};
+
+/// A salvo [`Handler`] serving a [`SocketIoService`].
+///
+/// See the [module level documentation](self) for an example.
+pub struct SalvoHandler<A: Adapter = LocalAdapter> {
+    svc: SocketIoService<NotFoundService, A>,
+}
+
+impl<A: Adapter> SalvoHandler<A> {
+    /// Create a new [`SalvoHandler`] from a [`SocketIoService`].
+    pub fn new(svc: SocketIoService<NotFoundService, A>) -> Self {
+        Self { svc }
+    }
+}
+
+impl<A: Adapter> Clone for SalvoHandler<A> {
+    fn clone(&self) -> Self {
+        Self {
+            svc: self.svc.clone(),
+        }
+    }
+}
+
+impl<A: Adapter> std::fmt::Debug for SalvoHandler<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SalvoHandler").finish()
+    }
+}
+
+#[async_trait]
+impl<A: Adapter> Handler for SalvoHandler<A> {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        let mut builder = HttpRequest::builder()
+            .method(req.method().clone())
+            .uri(req.uri().clone())
+            .version(req.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = req.headers().clone();
+        }
+        // The extensions also hold the hyper upgrade of websocket requests.
+        if let Some(extensions) = builder.extensions_mut() {
+            *extensions = std::mem::take(req.extensions_mut());
+        }
+        let Ok(http_req) = builder.body(req.take_body()) else {
+            res.status_code(StatusCode::BAD_REQUEST);
+            return;
+        };
+
+        let Ok(http_res) = self.svc.call(http_req).await;
+        let (parts, body) = http_res.into_parts();
+        let chunks = BodyStream::new(body)
+            .try_fold(VecDeque::new(), |mut chunks, chunk| {
+                chunks.push_back(chunk);
+                future::ready(Ok(chunks))
+            })
+            .await;
+        let Ok(chunks) = chunks;
+
+        res.status_code(parts.status);
+        *res.headers_mut() = parts.headers;
+        res.body(ResBody::Chunks(chunks));
+        ctrl.skip_rest();
+    }
+}
@@ -0,0 +1,222 @@
+//! ## [warp](https://docs.rs/warp/latest/warp/) integration.
+//!
+//! The [`filter`] fn exposes the [`SocketIoService`] as a warp [`Filter`] matching the requests on the
+//! [`req_path`](crate::SocketIoBuilder::req_path) of the socket.io server.
+//!
+//! Websocket connections are upgraded with the [`warp::ws()`] filter. The messages are then forwarded
+//! to the socket.io server through an in-memory websocket connection. Therefore the websocket
+//! settings of warp apply and the permessage-deflate extension is not negotiated.
+//!
+//! #### Example
+//! ```no_run
+//! use socketioxide::{extract::*, integrations::warp::filter, SocketIo};
+//! use warp::Filter;
+//!
+//! async fn run() {
+//!     let (svc, io) = SocketIo::new_svc();
+//!     io.ns("/", |s: SocketRef| {
+//!         s.on("message", |s: SocketRef, Data::<String>(msg)| {
+//!             s.emit("message-back", &msg).ok();
+//!         });
+//!     });
+//!
+//!     let hello = warp::path::end().map(|| "Hello, World!");
+//!     let routes = filter(svc).or(hello);
+//!     warp::serve(routes).run(([127, 0, 0, 1], 3000)).await;
+//! }
+//! ```
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use engineioxide::service::{NotFoundService, UpgradedConn};
+use futures_core::Stream;
+use futures_util::{future, SinkExt, StreamExt, TryStreamExt};
+use http::{header, HeaderMap, Method, Request, StatusCode};
+use http_body::{Body, Frame};
+use hyper::service::Service as HyperSvc;
+use tokio::io::DuplexStream;
+use tokio_tungstenite::{
+    tungstenite::{protocol::Role, Message},
+    WebSocketStream,
+};
+use warp::{
+    filters::{path::FullPath, ws::Ws},
+    reject::Rejection,
+    reply::{Reply, Response},
+    ws::WebSocket,
+    Filter,
+};
+
+use super::BodyStream;
+use crate::{adapter::Adapter, service::SocketIoService};
+
+/// The size of the buffer used to bridge websocket connections.
+const WS_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Create a warp [`Filter`] serving a [`SocketIoService`]
+/// on the [`req_path`](crate::SocketIoBuilder::req_path) of the socket.io server.
+///
+/// Requests on other paths are rejected with a not found rejection so that they can be
+/// handled by other filters.
+///
+/// See the [module level documentation](self) for an example.
+pub fn filter<A: Adapter>(
+    svc: SocketIoService<NotFoundService, A>,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let req_path = svc.engine_config().req_path.clone();
+    let max_message_size = svc.engine_config().ws_max_message_size;
+    let path = warp::path::full().and_then(move |path: FullPath| {
+        let res = if req_path.matches(path.as_str()) {
+            Ok(path)
+        } else {
+            Err(warp::reject::not_found())
+        };
+        future::ready(res)
+    });
+    let query = warp::query::raw().or(warp::any().map(String::new)).unify();
+    let ws = warp::ws()
+        .map(move |ws: Ws| match max_message_size {
+            Some(max) => Some(ws.max_message_size(max).max_frame_size(max)),
+            None => Some(ws),
+        })
+        .or(warp::any().map(|| None))
+        .unify();
+
+    path.and(warp::method())
+        .and(query)
+        .and(warp::header::headers_cloned())
+        .and(ws)
+        .and(warp::body::stream())
+        .and_then(move |path, method, query, headers, ws, body| {
+            let svc = svc.clone();
+            async move { Ok::<_, Rejection>(handle(svc, path, method, query, headers, ws, body).await) }
+        })
+}
+
+/// Convert the warp request, call the socket.io service and convert back its response.
+async fn handle<A: Adapter>(
+    svc: SocketIoService<NotFoundService, A>,
+    path: FullPath,
+    method: Method,
+    query: String,
+    mut headers: HeaderMap,
+    ws: Option<Ws>,
+    body: impl Stream<Item = Result<impl Buf, warp::Error>> + Send + 'static,
+) -> Response {
+    let uri = if query.is_empty() {
+        path.as_str().to_string()
+    } else {
+        format!("{}?{}", path.as_str(), query)
+    };
+    let mut builder = Request::builder().method(method).uri(uri);
+
+    let (body, conn) = if ws.is_some() {
+        // The extensions of the websocket connection are negotiated by warp.
+        headers.remove(header::SEC_WEBSOCKET_EXTENSIONS);
+        let (conn, upgraded) = tokio::io::duplex(WS_BUFFER_SIZE);
+        builder = builder.extension(UpgradedConn::new(upgraded));
+        (
+            StreamBody(Box::pin(futures_util::stream::empty())),
+            Some(conn),
+        )
+    } else {
+        let body = body.map_ok(|mut buf| buf.copy_to_bytes(buf.remaining()));
+        (StreamBody(Box::pin(body)), None)
+    };
+    if let Some(req_headers) = builder.headers_mut() {
+        *req_headers = headers;
+    }
+
+    let req = match builder.body(body) {
+        Ok(req) => req,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    let Ok(res) = svc.call(req).await;
+
+    match (ws, conn) {
+        (Some(ws), Some(conn)) if res.status() == StatusCode::SWITCHING_PROTOCOLS => ws
+            .on_upgrade(move |socket| bridge(socket, conn))
+            .into_response(),
+        _ => {
+            let (parts, body) = res.into_parts();
+            let body = BodyStream::new(body)
+                .try_fold(BytesMut::new(), |mut buf, chunk| {
+                    buf.extend_from_slice(&chunk);
+                    future::ready(Ok(buf))
+                })
+                .await;
+            match body {
+                Ok(body) => Response::from_parts(parts, body.freeze().into()),
+                Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
+        }
+    }
+}
+
+/// Forward the messages between the warp websocket and the in-memory websocket connection
+/// of the socket.io server.
+async fn bridge(socket: WebSocket, conn: DuplexStream) {
+    let (mut tx, mut rx) = WebSocketStream::from_raw_socket(conn, Role::Client, None)
+        .await
+        .split();
+    let (mut socket_tx, mut socket_rx) = socket.split();
+
+    let client_to_server = async move {
+        while let Some(Ok(msg)) = socket_rx.next().await {
+            let msg = if msg.is_text() {
+                Message::Text(msg.to_str().unwrap_or_default().into())
+            } else if msg.is_binary() {
+                Message::Binary(msg.into_bytes())
+            } else if msg.is_close() {
+                break;
+            } else {
+                continue;
+            };
+            if tx.send(msg).await.is_err() {
+                break;
+            }
+        }
+        tx.close().await.ok();
+    };
+    let server_to_client = async move {
+        while let Some(Ok(msg)) = rx.next().await {
+            let msg = match msg {
+                Message::Text(msg) => warp::ws::Message::text(msg.as_str()),
+                Message::Binary(data) => warp::ws::Message::binary(data),
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            if socket_tx.send(msg).await.is_err() {
+                break;
+            }
+        }
+        socket_tx.close().await.ok();
+    };
+    future::join(client_to_server, server_to_client).await;
+}
+
+/// A request body streaming the chunks of the warp body.
+struct StreamBody(Pin<Box<dyn Stream<Item = Result<Bytes, warp::Error>> + Send>>);
+impl fmt::Debug for StreamBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamBody").finish()
+    }
+}
+impl Body for StreamBody {
+    type Data = Bytes;
+    type Error = warp::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, warp::Error>>> {
+        self.0
+            .as_mut()
+            .poll_next(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+    }
+}
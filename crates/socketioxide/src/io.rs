@@ -1,14 +1,19 @@
 use std::{borrow::Cow, fmt, sync::Arc, time::Duration};
 
+use bytes::Bytes;
 use engineioxide::{
-    config::{EngineIoConfig, EngineIoConfigBuilder},
+    client_ip::IpRange,
+    config::{CorsConfig, EngineIoConfig, EngineIoConfigBuilder, ReqPath},
+    handshake::HandshakeFilter,
+    rate_limit::{RateLimit, RateLimitPolicy},
     service::NotFoundService,
-    sid::Sid,
+    sid::{Sid, SidGenerator},
     TransportType,
 };
-use serde::Serialize;
+use serde::{de::DeserializeOwned, Serialize};
 use socketioxide_core::{
     adapter::{DefinedAdapter, Room, RoomParam},
+    parser::Parse,
     Uid,
 };
 use socketioxide_parser_common::CommonParser;
@@ -17,16 +22,23 @@ use socketioxide_parser_msgpack::MsgPackParser;
 
 use crate::{
     ack::AckStream,
-    adapter::{Adapter, LocalAdapter},
+    adapter::{Adapter, DynAdapter, Emitter, LocalAdapter, NodeInfo, RoomListeners},
     client::Client,
+    concurrency::{ConcurrencyLimit, ConcurrencyScope},
     extract::SocketRef,
-    handler::ConnectHandler,
+    handler::{ConnectHandler, HandlerErrorHooks, HandlerPanic},
     layer::SocketIoLayer,
+    offline::OfflineQueueConfig,
     operators::BroadcastOperators,
+    packet::{Interceptors, Packet},
     parser::Parser,
+    presence::{PresenceConfig, PresenceMember},
+    recovery::RecoveryConfig,
+    rpc::{Rpc, RpcService},
     service::SocketIoService,
     socket::RemoteSocket,
-    BroadcastError, EmitWithAckError,
+    user::UserRegistry,
+    BroadcastError, EmitWithAckError, PresenceError,
 };
 
 /// The parser to use to encode and decode socket.io packets
@@ -49,6 +61,111 @@ impl ParserConfig {
     }
 }
 
+/// The configuration of a namespace registered with [`SocketIo::ns_with_config`],
+/// overriding the server configuration for the sockets of this namespace.
+///
+/// Every option that is not set falls back to the server configuration.
+/// ```
+/// # use socketioxide::{SocketIo, NamespaceConfig, RateLimitPolicy, extract::SocketRef};
+/// # use std::time::Duration;
+/// let (_, io) = SocketIo::new_svc();
+/// let config = NamespaceConfig::new()
+///     .ack_timeout(Duration::from_secs(30))
+///     .max_payload(10_000_000)
+///     .max_events_per_second(5, RateLimitPolicy::Queue);
+/// io.ns_with_config("/heavy", config, |s: SocketRef| {});
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NamespaceConfig {
+    pub(crate) ack_timeout: Option<Duration>,
+    pub(crate) max_payload: Option<usize>,
+    pub(crate) parser: Option<Parser>,
+    pub(crate) max_events_per_second: Option<RateLimit>,
+    pub(crate) ordered: bool,
+}
+
+impl NamespaceConfig {
+    /// Create a new [`NamespaceConfig`] without any override.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The amount of time to wait for an acknowledgement from the sockets of the namespace.
+    ///
+    /// Defaults to the [`ack_timeout`](SocketIoBuilder::ack_timeout) of the server.
+    #[inline]
+    pub fn ack_timeout(mut self, ack_timeout: Duration) -> Self {
+        self.ack_timeout = Some(ack_timeout);
+        self
+    }
+
+    /// The maximum size in bytes of the payload of a packet received by the namespace,
+    /// binary attachments included. If a client sends more, its connection is closed.
+    ///
+    /// The [`max_payload`](SocketIoBuilder::max_payload) of the server is checked first
+    /// for any packet, so it should be at least as large as the largest namespace limit.
+    ///
+    /// Defaults to `None` (no limit other than the transport limits).
+    #[inline]
+    pub fn max_payload(mut self, max_payload: usize) -> Self {
+        self.max_payload = Some(max_payload);
+        self
+    }
+
+    /// The parser used by the clients connecting to the namespace.
+    ///
+    /// A connection uses a single parser for all its namespaces: it is the parser of the
+    /// namespace of the first connect packet of the client. Connecting to a namespace with
+    /// another parser from the same connection is rejected with a `connect_error` packet.
+    ///
+    /// Defaults to the [`parser`](SocketIoBuilder::with_parser) of the server.
+    #[inline]
+    pub fn with_parser(mut self, parser: ParserConfig) -> Self {
+        self.parser = Some(parser.0);
+        self
+    }
+
+    /// Limit the number of events received per second by each socket of the namespace.
+    /// See [`SocketIoBuilder::max_events_per_second`] for more details.
+    ///
+    /// Defaults to the [`max_events_per_second`](SocketIoBuilder::max_events_per_second) of the server.
+    ///
+    /// # Panics
+    /// If `per_second` is 0.
+    #[inline]
+    pub fn max_events_per_second(mut self, per_second: u32, policy: RateLimitPolicy) -> Self {
+        self.max_events_per_second = Some(RateLimit::new(per_second, policy));
+        self
+    }
+
+    /// Handle the events of each socket of the namespace one after the other, in the order
+    /// they were received. The handler of an event is called once the handler of the previous
+    /// event returned or, for async handlers, once its future completed.
+    ///
+    /// It can be used by applications whose events depend on each other, such as state machines.
+    /// It takes precedence over the [`max_concurrent_handlers`](SocketIoBuilder::max_concurrent_handlers)
+    /// limit of the server.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, NamespaceConfig, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns_with_config("/game", NamespaceConfig::new().ordered(), |s: SocketRef| {
+    ///     // "move" events are applied in order, even if the handler is async
+    ///     s.on("move", |Data::<String>(mv)| async move {
+    ///         println!("applying move {mv}");
+    ///     });
+    /// });
+    /// ```
+    #[inline]
+    pub fn ordered(mut self) -> Self {
+        self.ordered = true;
+        self
+    }
+}
+
 /// Configuration for Socket.IO & Engine.IO
 #[derive(Debug, Clone)]
 pub struct SocketIoConfig {
@@ -61,15 +178,118 @@ pub struct SocketIoConfig {
     pub ack_timeout: Duration,
 
     /// The amount of time before disconnecting a client that has not successfully joined a namespace.
+    /// A client that never sends a valid connect packet or that is rejected by the connect middlewares
+    /// is disconnected once this delay is elapsed.
     ///
     /// Defaults to 45 seconds.
     pub connect_timeout: Duration,
 
+    /// The maximum amount of time to wait for the packets already emitted to a socket to be
+    /// sent before closing it, with [`Socket::disconnect_after_flush`](crate::socket::Socket::disconnect_after_flush)
+    /// or when the server is closed.
+    ///
+    /// Defaults to 1 second.
+    pub disconnect_flush_timeout: Duration,
+
     /// The parser to use to encode and decode socket.io packets
     pub(crate) parser: Parser,
 
     /// A global server identifier
     pub server_id: Uid,
+
+    /// The connection state recovery configuration.
+    /// If set, the server will persist disconnected sessions and broadcasted packets
+    /// so that reconnecting clients can recover their rooms and missed packets.
+    ///
+    /// Defaults to `None` (disabled).
+    pub connection_state_recovery: Option<RecoveryConfig>,
+
+    /// The presence configuration.
+    /// If set, the join and leave deltas of the rooms are emitted to their presence subscribers.
+    ///
+    /// Defaults to `None` (disabled).
+    pub presence: Option<PresenceConfig>,
+
+    /// The offline queue configuration.
+    /// If set, the persistent events emitted to an offline user are queued
+    /// and delivered to its next connected socket.
+    ///
+    /// Defaults to `None` (disabled).
+    pub offline_queue: Option<OfflineQueueConfig>,
+
+    /// The prefix of the rooms tracking the sockets of each user of the [`UserRegistry`].
+    ///
+    /// Defaults to `"user:"`.
+    pub user_room_prefix: Cow<'static, str>,
+
+    /// The event emitted to all the sockets when calling [`SocketIo::shutdown`].
+    /// The payload of the event is the grace period in milliseconds.
+    ///
+    /// Defaults to `None` (no event is emitted).
+    pub shutdown_event: Option<Cow<'static, str>>,
+
+    /// The maximum total size in bytes of the binary attachments of a single incoming packet.
+    /// If a client sends more, its connection is closed.
+    ///
+    /// Defaults to `None` (no limit other than the transport limits).
+    pub max_attachments_size: Option<usize>,
+
+    /// The maximum number of events received per second by each socket of a namespace.
+    ///
+    /// Defaults to `None` (no limit).
+    pub max_events_per_second: Option<RateLimit>,
+
+    /// The maximum number of event handlers running concurrently, for each socket
+    /// or for each namespace.
+    ///
+    /// Defaults to `None` (no limit).
+    pub max_concurrent_handlers: Option<ConcurrencyLimit>,
+
+    /// The maximum number of events of a handler queue waiting for a handler to complete,
+    /// when the handlers are [limited](SocketIoBuilder::max_concurrent_handlers) or
+    /// [ordered](NamespaceConfig::ordered). When the queue is full, the socket
+    /// that received the event is disconnected with [`DisconnectReason::HandlerQueueFull`](crate::socket::DisconnectReason::HandlerQueueFull).
+    ///
+    /// Defaults to 1024.
+    pub handler_queue_size: usize,
+
+    /// Forward the http polling requests targeting sessions open on other servers through the adapter,
+    /// so that deployments without sticky sessions work with the polling transport.
+    ///
+    /// Defaults to `false`.
+    pub session_handoff: bool,
+
+    /// Read a PROXY protocol (v1 or v2) header at the start of the connections accepted by the
+    /// standalone server, to get the address of the client rather than the one of the proxy.
+    ///
+    /// Defaults to `false`.
+    #[cfg(feature = "server")]
+    pub proxy_protocol: bool,
+
+    /// Disconnect a socket when one of its connect or message handlers panics.
+    /// The panic is still reported to the [`SocketIo::on_handler_error`] hooks.
+    ///
+    /// Defaults to `false`.
+    pub disconnect_on_handler_panic: bool,
+
+    /// The admin UI sink, set with [`SocketIoBuilder::with_admin_ui`].
+    #[cfg(feature = "admin-ui")]
+    pub(crate) admin_ui: Option<Arc<crate::admin::AdminSink>>,
+
+    /// The packet interceptors, registered with [`SocketIo::intercept_inbound`]
+    /// and [`SocketIo::intercept_outbound`].
+    pub(crate) interceptors: Arc<Interceptors>,
+
+    /// The room listeners, registered with [`SocketIo::on_join_room`], [`SocketIo::on_leave_room`],
+    /// [`SocketIo::on_create_room`], [`SocketIo::on_delete_room`] and [`SocketIo::on_expire_room`].
+    pub(crate) room_listeners: Arc<RoomListeners>,
+
+    /// The hooks called when a handler panics, registered with [`SocketIo::on_handler_error`].
+    pub(crate) handler_errors: Arc<HandlerErrorHooks>,
+
+    /// Notified when a socket is removed from a namespace, to wait for the sockets
+    /// to disconnect during a [`SocketIo::shutdown`].
+    pub(crate) socket_removed: Arc<tokio::sync::Notify>,
 }
 
 impl Default for SocketIoConfig {
@@ -81,8 +301,28 @@ impl Default for SocketIoConfig {
             },
             ack_timeout: Duration::from_secs(5),
             connect_timeout: Duration::from_secs(45),
+            disconnect_flush_timeout: Duration::from_secs(1),
             parser: Parser::default(),
             server_id: Uid::new(),
+            connection_state_recovery: None,
+            presence: None,
+            offline_queue: None,
+            user_room_prefix: Cow::Borrowed("user:"),
+            shutdown_event: None,
+            max_attachments_size: None,
+            max_events_per_second: None,
+            max_concurrent_handlers: None,
+            handler_queue_size: 1024,
+            session_handoff: false,
+            #[cfg(feature = "server")]
+            proxy_protocol: false,
+            disconnect_on_handler_panic: false,
+            #[cfg(feature = "admin-ui")]
+            admin_ui: None,
+            interceptors: Arc::default(),
+            room_listeners: Arc::default(),
+            handler_errors: Arc::default(),
+            socket_removed: Arc::default(),
         }
     }
 }
@@ -113,9 +353,23 @@ impl SocketIoBuilder<LocalAdapter> {
 impl<A: Adapter> SocketIoBuilder<A> {
     /// The path to listen for socket.io requests on.
     ///
+    /// It can be a single path, a list of paths to mount the server at several paths,
+    /// or a matcher function, see [`ReqPath`]. It can be changed once the server is running
+    /// with [`SocketIo::set_req_path`].
+    ///
     /// Defaults to "/socket.io".
+    /// ```
+    /// # use socketioxide::{SocketIo, ReqPath};
+    /// let (layer, io) = SocketIo::builder()
+    ///     .req_path(["/socket.io", "/legacy/socket.io"])
+    ///     .build_layer();
+    ///
+    /// let (layer, io) = SocketIo::builder()
+    ///     .req_path(ReqPath::matcher(|path| path.ends_with("/socket.io/")))
+    ///     .build_layer();
+    /// ```
     #[inline]
-    pub fn req_path(mut self, req_path: impl Into<Cow<'static, str>>) -> Self {
+    pub fn req_path(mut self, req_path: impl Into<ReqPath>) -> Self {
         self.engine_config_builder = self.engine_config_builder.req_path(req_path);
         self
     }
@@ -138,6 +392,40 @@ impl<A: Adapter> SocketIoBuilder<A> {
         self
     }
 
+    /// The ping interval of the connections opened with the websocket transport.
+    /// Connections opened with the polling transport keep the [`ping_interval`](Self::ping_interval)
+    /// even after being upgraded.
+    ///
+    /// Defaults to the [`ping_interval`](Self::ping_interval).
+    #[inline]
+    pub fn ws_ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.engine_config_builder = self.engine_config_builder.ws_ping_interval(ping_interval);
+        self
+    }
+
+    /// The ping timeout of the connections opened with the websocket transport.
+    ///
+    /// Defaults to the [`ping_timeout`](Self::ping_timeout).
+    #[inline]
+    pub fn ws_ping_timeout(mut self, ping_timeout: Duration) -> Self {
+        self.engine_config_builder = self.engine_config_builder.ws_ping_timeout(ping_timeout);
+        self
+    }
+
+    /// Back off the pings sent to idle connections, up to `max_interval` between two pings.
+    ///
+    /// The ping interval of a connection is doubled after each ping during which no packet was
+    /// received from the client, and goes back to the [`ping_interval`](Self::ping_interval)
+    /// as soon as a packet is received. The `max_interval` is advertised to the clients
+    /// so that they don't consider the connection closed between two spaced pings.
+    ///
+    /// Defaults to `None` (pings are always sent every [`ping_interval`](Self::ping_interval)).
+    #[inline]
+    pub fn idle_ping_interval(mut self, max_interval: Duration) -> Self {
+        self.engine_config_builder = self.engine_config_builder.idle_ping_interval(max_interval);
+        self
+    }
+
     /// The maximum number of packets that can be buffered per connection before being emitted to the client.
     /// If the buffer if full the `emit()` method will return an error
     ///
@@ -148,6 +436,18 @@ impl<A: Adapter> SocketIoBuilder<A> {
         self
     }
 
+    /// The maximum number of bytes of the packets buffered per connection before being emitted to the client.
+    /// Once the limit is reached, the `emit()` method will return an error like when the buffer is full.
+    ///
+    /// Defaults to `None` (only the number of packets is limited).
+    #[inline]
+    pub fn max_buffer_bytes(mut self, max_buffer_bytes: usize) -> Self {
+        self.engine_config_builder = self
+            .engine_config_builder
+            .max_buffer_bytes(max_buffer_bytes);
+        self
+    }
+
     /// The maximum size of a payload in bytes.
     /// If a payload is bigger than this value the `emit()` method will return an error.
     ///
@@ -171,6 +471,78 @@ impl<A: Adapter> SocketIoBuilder<A> {
         self
     }
 
+    /// The maximum payload size of a binary websocket frame written by the server.
+    /// Larger binary messages are split into fragmented frames so that they are written
+    /// chunk by chunk rather than being fully copied into the websocket write buffer.
+    ///
+    /// Defaults to `None` (binary messages are never fragmented).
+    #[inline]
+    pub fn ws_max_frame_size(mut self, ws_max_frame_size: usize) -> Self {
+        self.engine_config_builder = self
+            .engine_config_builder
+            .ws_max_frame_size(ws_max_frame_size);
+        self
+    }
+
+    /// The maximum size of a websocket message received from the client.
+    /// The connection is closed if a larger message is received.
+    ///
+    /// Defaults to `None` (64MiB, the limit of the websocket implementation).
+    #[inline]
+    pub fn ws_max_message_size(mut self, ws_max_message_size: usize) -> Self {
+        self.engine_config_builder = self
+            .engine_config_builder
+            .ws_max_message_size(ws_max_message_size);
+        self
+    }
+
+    /// Enable the permessage-deflate extension on the websocket transport.
+    /// It will be negotiated with clients that support it.
+    ///
+    /// Messages smaller than [`DeflateConfig::threshold`](crate::DeflateConfig) are not compressed.
+    #[cfg(feature = "ws-deflate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ws-deflate")))]
+    #[inline]
+    pub fn ws_deflate(mut self, ws_deflate: crate::DeflateConfig) -> Self {
+        self.engine_config_builder = self.engine_config_builder.ws_deflate(ws_deflate);
+        self
+    }
+
+    /// Enable the compression of polling payloads with gzip or deflate
+    /// for clients that accept it.
+    ///
+    /// Payloads smaller than [`HttpCompressionConfig::threshold`](crate::HttpCompressionConfig)
+    /// are not compressed.
+    #[cfg(feature = "http-compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http-compression")))]
+    #[inline]
+    pub fn http_compression(mut self, http_compression: crate::HttpCompressionConfig) -> Self {
+        self.engine_config_builder = self
+            .engine_config_builder
+            .http_compression(http_compression);
+        self
+    }
+
+    /// Enable CORS handling for the socket.io endpoint.
+    /// Preflight (`OPTIONS`) requests are answered and CORS headers are added to the responses
+    /// of allowed origins, so no external CORS layer is needed.
+    ///
+    /// ```
+    /// # use socketioxide::{SocketIo, CorsConfig, AllowedOrigins};
+    /// let (_, io) = SocketIo::builder()
+    ///     .cors(CorsConfig {
+    ///         allowed_origins: AllowedOrigins::List(vec!["https://example.com".into()]),
+    ///         credentials: true,
+    ///         ..Default::default()
+    ///     })
+    ///     .build_svc();
+    /// ```
+    #[inline]
+    pub fn cors(mut self, cors: CorsConfig) -> Self {
+        self.engine_config_builder = self.engine_config_builder.cors(cors);
+        self
+    }
+
     /// Allowed transports on this server
     ///
     /// The `transports` array should have a size of 1 or 2
@@ -183,41 +555,409 @@ impl<A: Adapter> SocketIoBuilder<A> {
         self
     }
 
+    /// The generator of the socket ids, see [`SidGenerator`](crate::socket::SidGenerator).
+    /// It can be used to embed a node identifier or a timestamp in the socket ids.
+    ///
+    /// Defaults to [`RandomSidGenerator`](crate::socket::RandomSidGenerator).
+    #[inline]
+    pub fn sid_generator(mut self, generator: impl SidGenerator) -> Self {
+        self.engine_config_builder = self.engine_config_builder.sid_generator(generator);
+        self
+    }
+
+    /// A filter called with the http parts of every engine.io handshake request, before any
+    /// session or socket is created. A rejected handshake is answered with the status and JSON
+    /// body of the [`HandshakeRejection`](crate::HandshakeRejection), without reaching any
+    /// socket.io middleware. See [`HandshakeFilter`](crate::HandshakeFilter).
+    ///
+    /// It is the cheapest place to reject the connections in maintenance mode or from banned ips.
+    /// The filter can also authorize the connections with some io, like the `allowRequest` option
+    /// of the javascript server, see [`async_filter`](crate::async_filter).
+    #[inline]
+    pub fn handshake_filter(mut self, filter: impl HandshakeFilter) -> Self {
+        self.engine_config_builder = self.engine_config_builder.handshake_filter(filter);
+        self
+    }
+
     /// The amount of time the server will wait for an acknowledgement from the client before closing the connection.
     ///
     /// Defaults to 5 seconds.
     #[inline]
-    pub fn ack_timeout(mut self, ack_timeout: Duration) -> Self {
-        self.config.ack_timeout = ack_timeout;
+    pub fn ack_timeout(mut self, ack_timeout: Duration) -> Self {
+        self.config.ack_timeout = ack_timeout;
+        self
+    }
+
+    /// The amount of time before disconnecting a client that has not successfully joined a namespace.
+    ///
+    /// It protects the server against idle connections that are never authenticated:
+    /// the underlying engine.io session is closed if the client doesn't send a valid connect packet
+    /// or doesn't pass the [connect middlewares](crate::handler::connect#middlewares) of
+    /// any namespace within this delay.
+    ///
+    /// Defaults to 45 seconds.
+    /// ```
+    /// # use socketioxide::SocketIo;
+    /// # use std::time::Duration;
+    /// let (layer, io) = SocketIo::builder()
+    ///     .connect_timeout(Duration::from_secs(10))
+    ///     .build_layer();
+    /// ```
+    #[inline]
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.config.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// The maximum amount of time to wait for the packets already emitted to a socket to be
+    /// sent before closing it, with [`Socket::disconnect_after_flush`](crate::socket::Socket::disconnect_after_flush)
+    /// or when the server is closed. Once elapsed, the socket is closed anyway and the
+    /// packets that were not sent are dropped.
+    ///
+    /// Defaults to 1 second.
+    #[inline]
+    pub fn disconnect_flush_timeout(mut self, timeout: Duration) -> Self {
+        self.config.disconnect_flush_timeout = timeout;
+        self
+    }
+
+    /// The prefix of the rooms tracking the sockets of each user of the [`UserRegistry`].
+    /// The rooms starting with this prefix should not be used for other purposes.
+    /// See the [`user`](crate::user) module doc for more details.
+    ///
+    /// Defaults to `"user:"`.
+    #[inline]
+    pub fn user_room_prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        self.config.user_room_prefix = prefix.into();
+        self
+    }
+
+    /// The event emitted to all the sockets when calling [`SocketIo::shutdown`].
+    /// The payload of the event is the grace period in milliseconds.
+    ///
+    /// Defaults to `None` (no event is emitted).
+    #[inline]
+    pub fn shutdown_event(mut self, event: impl Into<Cow<'static, str>>) -> Self {
+        self.config.shutdown_event = Some(event.into());
+        self
+    }
+
+    /// The maximum total size in bytes of the binary attachments of a single incoming packet.
+    /// If a client sends more, its connection is closed.
+    ///
+    /// Defaults to `None` (no limit other than the transport limits).
+    #[inline]
+    pub fn max_attachments_size(mut self, max_attachments_size: usize) -> Self {
+        self.config.max_attachments_size = Some(max_attachments_size);
+        self
+    }
+
+    /// Limit the number of events received per second by each socket of a namespace
+    /// with a token bucket of `per_second` tokens. The `policy` sets what to do with the events
+    /// received when the limit is exceeded:
+    /// * [`RateLimitPolicy::Drop`]: the event is dropped.
+    /// * [`RateLimitPolicy::Queue`]: the event is delayed until the limit allows it.
+    ///   At most `2 * per_second` events (a full burst and one second of delayed events)
+    ///   are queued, the next ones are dropped.
+    /// * [`RateLimitPolicy::Disconnect`]: the socket is disconnected from the namespace
+    ///   with the [`DisconnectReason::RateLimitExceeded`](crate::socket::DisconnectReason::RateLimitExceeded) reason.
+    ///
+    /// Acknowledgements are not limited.
+    ///
+    /// Defaults to `None` (no limit).
+    ///
+    /// # Panics
+    /// If `per_second` is 0.
+    #[inline]
+    pub fn max_events_per_second(mut self, per_second: u32, policy: RateLimitPolicy) -> Self {
+        self.config.max_events_per_second = Some(RateLimit::new(per_second, policy));
+        self
+    }
+
+    /// Limit the number of event handlers running concurrently, for each socket or for all
+    /// the sockets of each namespace depending on the [`ConcurrencyScope`].
+    ///
+    /// A handler is running until it returns or, for async handlers, until its future completes.
+    /// The events received when the limit is reached are queued and dispatched in order once a
+    /// handler completes. The size of the queue is set with
+    /// [`handler_queue_size`](Self::handler_queue_size).
+    ///
+    /// With a limit of 1 for each socket, the events of a socket are handled one after the other,
+    /// in the order they were received.
+    ///
+    /// Acknowledgements are not limited.
+    ///
+    /// Defaults to `None` (no limit).
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, ConcurrencyScope};
+    /// // At most 10 handlers running at the same time for each namespace
+    /// let (_, io) = SocketIo::builder()
+    ///     .max_concurrent_handlers(10, ConcurrencyScope::Namespace)
+    ///     .build_svc();
+    /// ```
+    ///
+    /// # Panics
+    /// If `max` is 0.
+    #[inline]
+    pub fn max_concurrent_handlers(mut self, max: usize, scope: ConcurrencyScope) -> Self {
+        self.config.max_concurrent_handlers = Some(ConcurrencyLimit::new(max, scope));
+        self
+    }
+
+    /// The maximum number of events of a handler queue waiting for a handler to complete,
+    /// when the handlers are [limited](Self::max_concurrent_handlers) or
+    /// [ordered](NamespaceConfig::ordered). The queue is shared by the sockets of a namespace
+    /// with a [`ConcurrencyScope::Namespace`] limit.
+    ///
+    /// When the queue is full, the event is dropped and the socket that received it is
+    /// disconnected with [`DisconnectReason::HandlerQueueFull`](crate::socket::DisconnectReason::HandlerQueueFull).
+    ///
+    /// Defaults to 1024.
+    ///
+    /// # Panics
+    /// If `size` is 0.
+    #[inline]
+    pub fn handler_queue_size(mut self, size: usize) -> Self {
+        assert!(size > 0, "the handler queue size must be > 0");
+        self.config.handler_queue_size = size;
+        self
+    }
+
+    /// Limit the number of message and binary engine.io packets received per second
+    /// for each underlying session, across all its namespaces.
+    /// With the [`RateLimitPolicy::Disconnect`] policy, the whole connection is closed.
+    ///
+    /// Defaults to `None` (no limit).
+    ///
+    /// # Panics
+    /// If `per_second` is 0.
+    #[inline]
+    pub fn max_packets_per_second(mut self, per_second: u32, policy: RateLimitPolicy) -> Self {
+        self.engine_config_builder = self
+            .engine_config_builder
+            .max_packets_per_second(per_second, policy);
+        self
+    }
+
+    /// The maximum number of engine.io sessions open at the same time. A handshake exceeding it
+    /// is rejected with a `503 Service Unavailable` status and a `Retry-After` header.
+    ///
+    /// The number of open sessions is available with
+    /// [`ConnectionLimits::counters`](engineioxide::connection_limit::ConnectionLimits::counters)
+    /// on the [`engine_config`](SocketIoConfig::engine_config).
+    ///
+    /// Defaults to `None` (no limit).
+    /// ```
+    /// # use socketioxide::SocketIo;
+    /// let (layer, io) = SocketIo::builder()
+    ///     .max_connections(10_000)
+    ///     .max_connections_per_ip(10)
+    ///     .build_layer();
+    /// let counters = io.config().engine_config.connection_limits.counters();
+    /// assert_eq!(counters.open(), 0);
+    /// ```
+    #[inline]
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.engine_config_builder = self.engine_config_builder.max_connections(max);
+        self
+    }
+
+    /// The maximum number of engine.io sessions open at the same time from the same ip.
+    /// A handshake exceeding it is rejected with a `503 Service Unavailable` status
+    /// and a `Retry-After` header.
+    ///
+    /// The ip is read from the [`SocketAddr`](std::net::SocketAddr) http extension of the request,
+    /// which should be inserted by your http server, or from the forwarded headers of the
+    /// [`trusted_proxies`](Self::trusted_proxies).
+    ///
+    /// Defaults to `None` (no limit).
+    #[inline]
+    pub fn max_connections_per_ip(mut self, max: usize) -> Self {
+        self.engine_config_builder = self.engine_config_builder.max_connections_per_ip(max);
+        self
+    }
+
+    /// The delay sent in the `Retry-After` header of the handshakes rejected by
+    /// [`max_connections`](Self::max_connections) or [`max_connections_per_ip`](Self::max_connections_per_ip).
+    ///
+    /// Defaults to 5 seconds.
+    #[inline]
+    pub fn connection_retry_after(mut self, retry_after: Duration) -> Self {
+        self.engine_config_builder = self
+            .engine_config_builder
+            .connection_retry_after(retry_after);
+        self
+    }
+
+    /// The reverse proxies allowed to forward the ip of the clients with the `Forwarded`
+    /// or `X-Forwarded-For` headers. If the peer of a connection is one of them, the ip
+    /// of the client is read from these headers.
+    ///
+    /// The resolved ip is available with [`Socket::remote_addr`](crate::socket::Socket::remote_addr)
+    /// and is used by the [`max_connections_per_ip`](Self::max_connections_per_ip) limit.
+    ///
+    /// Defaults to an empty list (the ip of the peer of the connection is always used).
+    /// ```
+    /// # use socketioxide::SocketIo;
+    /// let (layer, io) = SocketIo::builder()
+    ///     .trusted_proxies(["10.0.0.0/8".parse().unwrap(), "127.0.0.1".parse().unwrap()])
+    ///     .build_layer();
+    /// ```
+    #[inline]
+    pub fn trusted_proxies(mut self, proxies: impl IntoIterator<Item = IpRange>) -> Self {
+        self.engine_config_builder = self.engine_config_builder.trusted_proxies(proxies);
+        self
+    }
+
+    /// Read a PROXY protocol (v1 or v2) header at the start of the connections accepted by the
+    /// [standalone server](SocketIo::serve), as sent by load balancers like HAProxy or AWS NLB.
+    /// The address of the client found in the header is then used in place of the address of
+    /// the load balancer.
+    ///
+    /// **Note**: Connections without a valid header are closed, so only enable it if all the
+    /// connections go through a load balancer sending the header.
+    ///
+    /// Defaults to `false`.
+    #[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+    #[cfg(feature = "server")]
+    #[inline]
+    pub fn proxy_protocol(mut self, enabled: bool) -> Self {
+        self.config.proxy_protocol = enabled;
+        self
+    }
+
+    /// Close the connections whose buffer stays full for longer than `timeout` with the
+    /// [`DisconnectReason::SlowConsumer`](crate::socket::DisconnectReason::SlowConsumer) reason,
+    /// rather than dropping their packets indefinitely.
+    ///
+    /// The session is closed on the first failed emit after the timeout.
+    ///
+    /// Defaults to `None` (slow consumers are never closed).
+    #[inline]
+    pub fn slow_consumer_timeout(mut self, timeout: Duration) -> Self {
+        self.engine_config_builder = self.engine_config_builder.slow_consumer_timeout(timeout);
+        self
+    }
+
+    /// Forward the http polling requests targeting sessions open on other servers through the adapter,
+    /// so that deployments without sticky sessions work with the polling transport.
+    ///
+    /// When a polling request hits a server that does not know its session, it is forwarded with
+    /// the adapter of the main namespace `/` (or of any namespace if it is not defined) to the server
+    /// owning the session, which handles it and sends back the response. The adapter must support
+    /// it (e.g. the redis adapter), the forwarded requests are otherwise rejected as with the
+    /// local adapter. Websocket connections are not affected.
+    ///
+    /// Defaults to `false`.
+    #[inline]
+    pub fn session_handoff(mut self, enabled: bool) -> Self {
+        self.config.session_handoff = enabled;
+        self
+    }
+
+    /// Disconnect a socket when one of its connect or message handlers panics, with the
+    /// [`DisconnectReason::HandlerPanic`](crate::socket::DisconnectReason::HandlerPanic) reason.
+    /// Otherwise the panic is only reported and the socket stays connected.
+    ///
+    /// See [`SocketIo::on_handler_error`] for more details.
+    ///
+    /// Defaults to `false`.
+    #[inline]
+    pub fn disconnect_on_handler_panic(mut self, enabled: bool) -> Self {
+        self.config.disconnect_on_handler_panic = enabled;
+        self
+    }
+
+    /// Sets a custom [`SocketIoConfig`] created previously for this [`SocketIoBuilder`]
+    #[inline]
+    pub fn with_config(mut self, config: SocketIoConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Set a custom [`ParserConfig`] for this [`SocketIoBuilder`]
+    /// ```
+    /// # use socketioxide::{SocketIo, ParserConfig};
+    /// # #[cfg(feature = "msgpack")]
+    /// let (layer, io) = SocketIo::builder()
+    ///     .with_parser(ParserConfig::msgpack())
+    ///     .build_layer();
+    /// ```
+    #[inline]
+    pub fn with_parser(mut self, parser: ParserConfig) -> Self {
+        self.config.parser = parser.0;
+        self
+    }
+
+    /// Enable connection state recovery with the given [`RecoveryConfig`].
+    /// See the [`recovery`](crate::recovery) module doc for more details.
+    /// ```
+    /// # use socketioxide::{SocketIo, recovery::RecoveryConfig};
+    /// # use std::time::Duration;
+    /// let (layer, io) = SocketIo::builder()
+    ///     .with_connection_state_recovery(RecoveryConfig::new(Duration::from_secs(120)))
+    ///     .build_layer();
+    /// ```
+    #[inline]
+    pub fn with_connection_state_recovery(mut self, config: RecoveryConfig) -> Self {
+        self.config.connection_state_recovery = Some(config);
         self
     }
 
-    /// The amount of time before disconnecting a client that has not successfully joined a namespace.
-    ///
-    /// Defaults to 45 seconds.
+    /// Enable the presence subsystem with the given [`PresenceConfig`].
+    /// See the [`presence`](crate::presence) module doc for more details.
+    /// ```
+    /// # use socketioxide::{SocketIo, presence::PresenceConfig};
+    /// let (layer, io) = SocketIo::builder()
+    ///     .with_presence(PresenceConfig::default().event("presence"))
+    ///     .build_layer();
+    /// ```
     #[inline]
-    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
-        self.config.connect_timeout = connect_timeout;
+    pub fn with_presence(mut self, config: PresenceConfig) -> Self {
+        self.config.presence = Some(config);
         self
     }
 
-    /// Sets a custom [`SocketIoConfig`] created previously for this [`SocketIoBuilder`]
+    /// Enable the offline message queue with the given [`OfflineQueueConfig`].
+    /// See the [`offline`](crate::offline) module doc for more details.
+    /// ```
+    /// # use socketioxide::{SocketIo, offline::OfflineQueueConfig};
+    /// # use std::time::Duration;
+    /// let (layer, io) = SocketIo::builder()
+    ///     .with_offline_queue(OfflineQueueConfig::new(Duration::from_secs(3600), 50))
+    ///     .build_layer();
+    /// ```
     #[inline]
-    pub fn with_config(mut self, config: SocketIoConfig) -> Self {
-        self.config = config;
+    pub fn with_offline_queue(mut self, config: OfflineQueueConfig) -> Self {
+        self.config.offline_queue = Some(config);
         self
     }
 
-    /// Set a custom [`ParserConfig`] for this [`SocketIoBuilder`]
+    /// Serve the [Admin UI](crate::admin) protocol on a dedicated namespace
+    /// so that the official dashboard can inspect and manage the server.
     /// ```
-    /// # use socketioxide::{SocketIo, ParserConfig};
-    /// let (io, layer) = SocketIo::builder()
-    ///     .with_parser(ParserConfig::msgpack())
+    /// # use socketioxide::{SocketIo, admin::AdminUiConfig};
+    /// let (layer, io) = SocketIo::builder()
+    ///     .with_admin_ui(AdminUiConfig::new().with_basic_auth("admin", "changeit"))
     ///     .build_layer();
     /// ```
+    ///
+    /// # Panics
+    /// If the config has no [`basic auth`](crate::admin::AdminUiConfig::with_basic_auth)
+    /// and the authentication was not explicitly disabled with
+    /// [`without_auth`](crate::admin::AdminUiConfig::without_auth).
     #[inline]
-    pub fn with_parser(mut self, parser: ParserConfig) -> Self {
-        self.config.parser = parser.0;
+    #[cfg_attr(docsrs, doc(cfg(feature = "admin-ui")))]
+    #[cfg(feature = "admin-ui")]
+    pub fn with_admin_ui(mut self, config: crate::admin::AdminUiConfig) -> Self {
+        assert!(
+            config.has_auth(),
+            "the admin ui requires credentials, set them with `AdminUiConfig::with_basic_auth` \
+            or explicitly disable the authentication with `AdminUiConfig::without_auth`"
+        );
+        self.config.admin_ui = Some(Arc::new(crate::admin::AdminSink::new(config)));
         self
     }
 
@@ -256,7 +996,7 @@ impl<A: Adapter> SocketIoBuilder<A> {
             #[cfg(feature = "state")]
             self.state,
         );
-        (layer, SocketIo(client))
+        (layer, SocketIo::init(client))
     }
 
     /// Build a [`SocketIoService`] and a [`SocketIo`] instance that
@@ -272,7 +1012,7 @@ impl<A: Adapter> SocketIoBuilder<A> {
             #[cfg(feature = "state")]
             self.state,
         );
-        (svc, SocketIo(client))
+        (svc, SocketIo::init(client))
     }
 
     /// Build a [`SocketIoService`] and a [`SocketIo`] instance with an inner service that
@@ -290,7 +1030,7 @@ impl<A: Adapter> SocketIoBuilder<A> {
             #[cfg(feature = "state")]
             self.state,
         );
-        (svc, SocketIo(client))
+        (svc, SocketIo::init(client))
     }
 }
 
@@ -340,12 +1080,41 @@ impl SocketIo<LocalAdapter> {
 }
 
 impl<A: Adapter> SocketIo<A> {
+    /// Create the [`SocketIo`] instance of a new server and register its internal namespaces.
+    fn init(client: Arc<Client<A>>) -> Self {
+        let io = SocketIo(client);
+        #[cfg(feature = "admin-ui")]
+        if let Some(sink) = io.config().admin_ui.clone() {
+            crate::admin::register(&io, sink);
+        }
+        io
+    }
+
     /// Return a reference to the [`SocketIoConfig`] used by this [`SocketIo`] instance
     #[inline]
     pub fn config(&self) -> &SocketIoConfig {
         &self.0.config
     }
 
+    /// # Change the path on which the socket.io requests are handled.
+    ///
+    /// The new path applies to the next requests. The sessions already opened are kept,
+    /// but their next requests must be sent on the new path.
+    ///
+    /// The routes registered for the actix-web and rocket integrations are not changed,
+    /// because they are set when the service is mounted.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::SocketIo;
+    /// let (svc, io) = SocketIo::new_svc();
+    /// io.set_req_path(["/socket.io", "/v2/socket.io"]);
+    /// assert!(io.config().engine_config.req_path.matches("/v2/socket.io/"));
+    /// ```
+    pub fn set_req_path(&self, req_path: impl Into<ReqPath>) {
+        self.0.config.engine_config.req_path.set(req_path);
+    }
+
     /// # Register a [`ConnectHandler`] for the given dynamic namespace.
     ///
     /// You can specify dynamic parts in the path by using the `{name}` syntax.
@@ -354,7 +1123,8 @@ impl<A: Adapter> SocketIo<A> {
     /// For more info about namespace routing, see the [matchit] router documentation.
     ///
     /// The dynamic namespace will create a child namespace for any path that matches the given pattern
-    /// with the given handler.
+    /// with the given handler. The captured params can be retrieved with the
+    /// [`NsParams`](crate::extract::NsParams) extractor.
     ///
     /// * See the [`connect`](crate::handler::connect) module doc for more details on connect handler.
     /// * See the [`extract`](crate::extract) module doc for more details on available extractors.
@@ -415,6 +1185,117 @@ impl<A: Adapter> SocketIo<A> {
         self.0.close().await;
     }
 
+    /// # Gracefully shutdown the server with a grace period.
+    ///
+    /// * New engine.io handshakes are rejected with a `503 Service Unavailable` status.
+    /// * If set, the [`shutdown_event`](SocketIoBuilder::shutdown_event) is emitted to all the sockets
+    ///   with the grace period in milliseconds as payload.
+    /// * Sockets have up to `grace` to disconnect by themselves (e.g. after flushing their state).
+    /// * All the remaining connections are then closed like with [`SocketIo::close`].
+    ///
+    /// The returned future resolves once all the sockets are disconnected.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::SocketIo;
+    /// # use std::time::Duration;
+    /// # async fn doc() {
+    /// let (_, io) = SocketIo::builder()
+    ///     .shutdown_event("shutting_down")
+    ///     .build_svc();
+    /// // ... on SIGTERM:
+    /// io.shutdown(Duration::from_secs(10)).await;
+    /// # }
+    /// ```
+    #[inline]
+    pub async fn shutdown(&self, grace: Duration) {
+        self.0.shutdown(grace).await;
+    }
+
+    /// # Serve the socket.io server on the given address with a minimal http server.
+    ///
+    /// This is useful if you don't need any http framework. The socket.io service is mounted
+    /// on the [`req_path`](SocketIoBuilder::req_path) and any other request gets a 404 response.
+    /// Connections are served with http/1.1 or with http/2 for clients using prior knowledge.
+    ///
+    /// The returned future only resolves if the address can't be bound.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// # async fn doc() -> std::io::Result<()> {
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |s: SocketRef| {
+    ///     s.emit("hello", "world").ok();
+    /// });
+    /// io.serve("0.0.0.0:3000").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+    #[cfg(feature = "server")]
+    pub async fn serve(&self, addr: impl tokio::net::ToSocketAddrs) -> std::io::Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        self.serve_listener(listener).await
+    }
+
+    /// # Serve the socket.io server on an already bound [`TcpListener`](tokio::net::TcpListener).
+    ///
+    /// See [`SocketIo::serve`] for more details.
+    #[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+    #[cfg(feature = "server")]
+    pub async fn serve_listener(&self, listener: tokio::net::TcpListener) -> std::io::Result<()> {
+        crate::server::serve(
+            self.0.clone(),
+            listener,
+            #[cfg(feature = "server-tls")]
+            None,
+        )
+        .await
+    }
+
+    /// # Serve the socket.io server over TLS on the given address with a minimal http server.
+    ///
+    /// This is the same as [`SocketIo::serve`] but the connections are secured with rustls so
+    /// clients can directly connect with `https://` and `wss://` urls.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use socketioxide::{SocketIo, TlsConfig, extract::SocketRef};
+    /// # async fn doc() -> std::io::Result<()> {
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |s: SocketRef| {
+    ///     s.emit("hello", "world").ok();
+    /// });
+    /// let tls = TlsConfig::from_pem_file("cert.pem", "key.pem").await?;
+    /// io.serve_tls("0.0.0.0:443", tls).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "server-tls")))]
+    #[cfg(feature = "server-tls")]
+    pub async fn serve_tls(
+        &self,
+        addr: impl tokio::net::ToSocketAddrs,
+        tls: crate::TlsConfig,
+    ) -> std::io::Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        self.serve_listener_tls(listener, tls).await
+    }
+
+    /// # Serve the socket.io server over TLS on an already bound [`TcpListener`](tokio::net::TcpListener).
+    ///
+    /// See [`SocketIo::serve_tls`] for more details.
+    #[cfg_attr(docsrs, doc(cfg(feature = "server-tls")))]
+    #[cfg(feature = "server-tls")]
+    pub async fn serve_listener_tls(
+        &self,
+        listener: tokio::net::TcpListener,
+        tls: crate::TlsConfig,
+    ) -> std::io::Result<()> {
+        crate::server::serve(self.0.clone(), listener, Some(tls)).await
+    }
+
     // Chaining operators fns
 
     /// # Select a specific namespace to perform operations on.
@@ -443,6 +1324,30 @@ impl<A: Adapter> SocketIo<A> {
         self.get_op(path.as_ref())
     }
 
+    /// # Get the adapter of a namespace as a [`DynAdapter`] trait object.
+    ///
+    /// It allows to use the adapter without knowing its type,
+    /// for example to store together the adapters of servers using different adapters.
+    /// Returns `None` if the namespace is not found.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, adapter::DynAdapter};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", || {});
+    ///
+    /// async fn servers(io: SocketIo) {
+    ///     let adapter = io.adapter("/").unwrap();
+    ///     let count = adapter.server_count().await.unwrap();
+    ///     println!("{count} servers");
+    /// }
+    /// ```
+    pub fn adapter(&self, path: impl AsRef<str>) -> Option<Arc<dyn DynAdapter<Emitter>>> {
+        self.0
+            .get_ns(path.as_ref())
+            .map(|ns| ns.adapter.clone() as _)
+    }
+
     /// _Alias for `io.of("/").unwrap().to()`_. If the **default namespace "/" is not found** this fn will panic!
     #[doc = include_str!("../docs/operators/to.md")]
     #[inline]
@@ -450,6 +1355,13 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op().to(rooms)
     }
 
+    /// _Alias for `io.of("/").unwrap().to_socket()`_. If the **default namespace "/" is not found** this fn will panic!
+    #[doc = include_str!("../docs/operators/to_socket.md")]
+    #[inline]
+    pub fn to_socket(&self, sid: Sid) -> BroadcastOperators<A> {
+        self.get_default_op().to_socket(sid)
+    }
+
     /// _Alias for `io.of("/").unwrap().within()`_. If the **default namespace "/" is not found** this fn will panic!
     #[doc = include_str!("../docs/operators/within.md")]
     #[inline]
@@ -478,6 +1390,27 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op().timeout(timeout)
     }
 
+    /// _Alias for `io.of("/").unwrap().volatile()`_. If the **default namespace "/" is not found** this fn will panic!
+    #[doc = include_str!("../docs/operators/volatile.md")]
+    #[inline]
+    pub fn volatile(&self) -> BroadcastOperators<A> {
+        self.get_default_op().volatile()
+    }
+
+    /// _Alias for `io.of("/").unwrap().bin()`_. If the **default namespace "/" is not found** this fn will panic!
+    #[doc = include_str!("../docs/operators/bin.md")]
+    #[inline]
+    pub fn bin(&self, bins: impl IntoIterator<Item = impl Into<Bytes>>) -> BroadcastOperators<A> {
+        self.get_default_op().bin(bins)
+    }
+
+    /// _Alias for `io.of("/").unwrap().allow_partial()`_. If the **default namespace "/" is not found** this fn will panic!
+    #[doc = include_str!("../docs/operators/allow_partial.md")]
+    #[inline]
+    pub fn allow_partial(&self) -> BroadcastOperators<A> {
+        self.get_default_op().allow_partial()
+    }
+
     /// _Alias for `io.of("/").unwrap().emit()`_. If the **default namespace "/" is not found** this fn will panic!
     #[doc = include_str!("../docs/operators/emit.md")]
     #[inline]
@@ -514,6 +1447,31 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op().fetch_sockets().await
     }
 
+    /// # Get the servers of the cluster.
+    ///
+    /// Returns the servers known to be alive, including the current one, with the number of sockets
+    /// connected to the default namespace "/" on each of them. With the default [`LocalAdapter`],
+    /// only the current server is returned. Distributed adapters track the other servers with heartbeats.
+    ///
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::SocketIo;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", || {});
+    ///
+    /// async fn show_nodes(io: SocketIo) {
+    ///     for node in io.cluster_nodes().await.unwrap() {
+    ///         println!("server {} has {} sockets", node.uid, node.sockets);
+    ///     }
+    /// }
+    /// ```
+    pub async fn cluster_nodes(&self) -> Result<Vec<NodeInfo>, A::Error> {
+        let ns = self.0.get_ns("/").expect("default namespace not found");
+        ns.adapter.nodes().await
+    }
+
     /// _Alias for `io.of("/").unwrap().disconnect()`_. If the **default namespace "/" is not found** this fn will panic!
     #[doc = include_str!("../docs/operators/disconnect.md")]
     #[inline]
@@ -534,6 +1492,13 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op().rooms().await
     }
 
+    /// _Alias for `io.of("/").unwrap().sockets_count()`_. If the **default namespace "/" is not found** this fn will panic!
+    #[doc = include_str!("../docs/operators/sockets_count.md")]
+    #[inline]
+    pub async fn sockets_count(&self) -> Result<usize, A::Error> {
+        self.get_default_op().sockets_count().await
+    }
+
     /// _Alias for `io.of("/").unwrap().rooms()`_. If the **default namespace "/" is not found** this fn will panic!
     #[doc = include_str!("../docs/operators/leave.md")]
     #[inline]
@@ -548,6 +1513,47 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op().get_socket(sid)
     }
 
+    /// _Alias for `io.of("/").unwrap().fetch_socket()`_. If the **default namespace "/" is not found** this fn will panic!
+    #[doc = include_str!("../docs/operators/fetch_socket.md")]
+    #[inline]
+    pub async fn fetch_socket(&self, sid: Sid) -> Result<Option<RemoteSocket<A>>, A::Error> {
+        self.get_default_op().fetch_socket(sid).await
+    }
+
+    /// # Get the [`UserRegistry`] of the default namespace "/".
+    /// See the [`user`](crate::user) module doc for more details.
+    ///
+    /// # Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    #[inline]
+    pub fn users(&self) -> UserRegistry<A> {
+        self.users_of("/").expect("default namespace not found")
+    }
+
+    /// # Get the [`UserRegistry`] of the given namespace.
+    /// Returns `None` if the namespace is not found.
+    /// See the [`user`](crate::user) module doc for more details.
+    pub fn users_of(&self, path: impl AsRef<str>) -> Option<UserRegistry<A>> {
+        self.0.get_ns(path.as_ref()).map(UserRegistry::new)
+    }
+
+    /// _Alias for `io.users().to(user)`_. If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// # Select all the sockets of the given user, on all the servers.
+    /// See the [`user`](crate::user) module doc for more details.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::SocketIo;
+    /// async fn notify(io: SocketIo, user_id: u64) {
+    ///     io.to_user(user_id).emit("notification", "hello").await.ok();
+    /// }
+    /// ```
+    #[inline]
+    pub fn to_user(&self, user: impl std::fmt::Display) -> BroadcastOperators<A> {
+        self.users().to(user)
+    }
+
     /// _Alias for `io.of("/").unwrap().broadcast()`_. If the **default namespace "/" is not found** this fn will panic!
     #[doc = include_str!("../docs/operators/broadcast.md")]
     #[inline]
@@ -555,8 +1561,255 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op()
     }
 
+    /// # Register an interceptor called with every packet received from a client.
+    ///
+    /// It is called once the packet is decoded, before it is dispatched to its namespace,
+    /// and can observe or mutate it. See the [`packet`](crate::packet) module doc for more details.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, packet::{Packet, PacketData}};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.intercept_inbound(|packet: &mut Packet| {
+    ///     if let PacketData::Event(_, _) = packet.inner {
+    ///         println!("event received on namespace {}", packet.ns);
+    ///     }
+    /// });
+    /// ```
+    pub fn intercept_inbound(&self, interceptor: impl Fn(&mut Packet) + Send + Sync + 'static) {
+        self.config()
+            .interceptors
+            .add_inbound(Box::new(interceptor));
+    }
+
+    /// # Register an interceptor called with every packet sent to a client.
+    ///
+    /// It is called before the packet is encoded and can observe or mutate it.
+    /// A broadcasted packet is intercepted once, before being passed to the adapter.
+    /// See the [`packet`](crate::packet) module doc for more details.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, packet::{Packet, PacketData}};
+    /// let (_, io) = SocketIo::new_svc();
+    /// // Audit the connect errors sent to the clients
+    /// io.intercept_outbound(|packet: &mut Packet| {
+    ///     if let PacketData::ConnectError(msg, _) = &packet.inner {
+    ///         println!("connection to {} refused: {}", packet.ns, msg);
+    ///     }
+    /// });
+    /// ```
+    pub fn intercept_outbound(&self, interceptor: impl Fn(&mut Packet) + Send + Sync + 'static) {
+        self.config()
+            .interceptors
+            .add_outbound(Box::new(interceptor));
+    }
+
+    /// # Register a listener called when a socket of this server joins a room.
+    ///
+    /// It is called with the namespace path, the socket id and the room, only if the socket
+    /// was not already in the room. When the socket is the first to join the room, the
+    /// [`on_create_room`](Self::on_create_room) listeners are called before.
+    ///
+    /// The listeners are called synchronously, after the rooms are updated, by the task
+    /// that joined the room. They can use the [`SocketIo`] instance, e.g. to emit to the room.
+    ///
+    /// **Note**: With an adapter shared between multiple servers, each server only calls its
+    /// listeners for its own sockets.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.on_join_room(|ns, sid, room| println!("socket {sid} joined {room} in {ns}"));
+    /// io.ns("/", |s: SocketRef| s.join("lobby"));
+    /// ```
+    pub fn on_join_room(&self, listener: impl Fn(&str, Sid, &Room) + Send + Sync + 'static) {
+        let listeners = &self.config().room_listeners;
+        listeners.join.write().unwrap().push(Box::new(listener));
+    }
+
+    /// # Register a listener called when a socket of this server leaves a room.
+    ///
+    /// It is called with the namespace path, the socket id and the room, only if the socket
+    /// was in the room, including when the socket is disconnected. When the socket was the last
+    /// in the room, the [`on_delete_room`](Self::on_delete_room) listeners are called after.
+    ///
+    /// See [`on_join_room`](Self::on_join_room) for more details.
+    pub fn on_leave_room(&self, listener: impl Fn(&str, Sid, &Room) + Send + Sync + 'static) {
+        let listeners = &self.config().room_listeners;
+        listeners.leave.write().unwrap().push(Box::new(listener));
+    }
+
+    /// # Register a listener called when a room is created on this server.
+    ///
+    /// It is called with the namespace path and the room when a first socket of this server joins it.
+    ///
+    /// See [`on_join_room`](Self::on_join_room) for more details.
+    pub fn on_create_room(&self, listener: impl Fn(&str, &Room) + Send + Sync + 'static) {
+        let listeners = &self.config().room_listeners;
+        listeners.create.write().unwrap().push(Box::new(listener));
+    }
+
+    /// # Register a listener called when a room is deleted on this server.
+    ///
+    /// It is called with the namespace path and the room when the last socket of this server leaves it.
+    /// It can be used to clean up the resources associated with the room.
+    ///
+    /// See [`on_join_room`](Self::on_join_room) for more details.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use std::{collections::HashMap, sync::{Arc, Mutex}};
+    /// // The history of the messages of each room
+    /// let history = Arc::new(Mutex::new(HashMap::<String, Vec<String>>::new()));
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.on_delete_room(move |_ns, room| {
+    ///     history.lock().unwrap().remove(room.as_ref());
+    /// });
+    /// ```
+    pub fn on_delete_room(&self, listener: impl Fn(&str, &Room) + Send + Sync + 'static) {
+        let listeners = &self.config().room_listeners;
+        listeners.delete.write().unwrap().push(Box::new(listener));
+    }
+
+    /// # Register a listener called when a room expires on this server.
+    ///
+    /// It is called with the namespace path and the room when the
+    /// [`RoomExpiry`](crate::adapter::RoomExpiry) of the room, set with
+    /// [`BroadcastOperators::set_expiry`], is due. When the room still exists, its sockets
+    /// leave it and the [`on_delete_room`](Self::on_delete_room) listeners are called before.
+    ///
+    /// See [`on_join_room`](Self::on_join_room) for more details.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*, adapter::RoomExpiry};
+    /// # use std::time::Duration;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.on_expire_room(|_ns, room| println!("lobby {room} expired"));
+    /// io.ns("/", |s: SocketRef, io: SocketIo| {
+    ///     s.join("lobby-1");
+    ///     // The lobby is deleted once it stayed empty for 30 seconds
+    ///     io.to("lobby-1").set_expiry(RoomExpiry::WhenEmpty(Duration::from_secs(30)));
+    /// });
+    /// ```
+    pub fn on_expire_room(&self, listener: impl Fn(&str, &Room) + Send + Sync + 'static) {
+        let listeners = &self.config().room_listeners;
+        listeners.expire.write().unwrap().push(Box::new(listener));
+    }
+
+    /// # Register a hook called when a handler of a socket panics.
+    ///
+    /// Panics in the connect, message and disconnect handlers are caught so that they don't
+    /// take down the task driving the connection. The hook is called with a [`HandlerPanic`]
+    /// describing the socket, the handler and the panic message.
+    ///
+    /// The panics of the futures of async handlers are only caught once a hook is registered
+    /// or [`disconnect_on_handler_panic`](SocketIoBuilder::disconnect_on_handler_panic) is enabled.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.on_handler_error(|panic| {
+    ///     eprintln!("handler {:?} of socket {} panicked: {:?}", panic.handler, panic.sid, panic.message);
+    /// });
+    /// io.ns("/", |s: SocketRef| {
+    ///     s.on("divide", |Data::<(u32, u32)>((a, b))| println!("{}", a / b));
+    /// });
+    /// ```
+    pub fn on_handler_error(&self, hook: impl Fn(&HandlerPanic) + Send + Sync + 'static) {
+        self.config().handler_errors.push(Box::new(hook));
+    }
+
+    /// # Get the members of a room of the default namespace, with their presence metadata.
+    ///
+    /// The members are aggregated across all the servers through the adapter.
+    /// The metadata of each member is the one set with
+    /// [`Socket::set_presence_meta`](crate::socket::Socket::set_presence_meta),
+    /// deserialized to `T`. It is `None` for the members that did not set any metadata.
+    ///
+    /// See the [`presence`](crate::presence) module doc for more details.
+    ///
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// #[derive(Debug, serde::Serialize, serde::Deserialize)]
+    /// struct Status {
+    ///     away: bool,
+    /// }
+    /// async fn handler(socket: SocketRef, io: SocketIo) {
+    ///     socket.set_presence_meta(Status { away: false }).unwrap();
+    ///     socket.join("room1");
+    ///     for member in io.presence::<Status>("room1").await.unwrap() {
+    ///         println!("{} on {}: {:?}", member.id, member.server_id, member.meta);
+    ///     }
+    /// }
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", handler);
+    /// ```
+    pub async fn presence<T: DeserializeOwned>(
+        &self,
+        room: impl RoomParam,
+    ) -> Result<Vec<PresenceMember<T>>, PresenceError> {
+        let op = self.get_default_op();
+        let parser = self.0.parser();
+        let sockets = op
+            .within(room)
+            .fetch_sockets()
+            .await
+            .map_err(|e| PresenceError::Adapter(Box::new(e)))?;
+        sockets
+            .into_iter()
+            .map(|socket| {
+                let data = socket.into_data();
+                let meta = match &data.presence {
+                    Some(meta) => Some(parser.decode_default(Some(meta))?),
+                    None => None,
+                };
+                Ok(PresenceMember {
+                    id: data.id,
+                    server_id: data.server_id,
+                    meta,
+                })
+            })
+            .collect()
+    }
+
+    /// # Get a typed [`Rpc`] facade to call and serve the methods of an [`RpcService`].
+    ///
+    /// Its timeout defaults to the [`ack_timeout`](SocketIoBuilder::ack_timeout) of the server.
+    /// See the [`rpc`](crate::rpc) module doc for more details.
+    #[inline]
+    pub fn rpc<S: RpcService>(&self) -> Rpc<S, A> {
+        Rpc::new(self.0.config.ack_timeout)
+    }
+
+    /// Get a clone of the global state of type `T` registered with [`SocketIoBuilder::with_state`].
+    /// Returns `None` if no state of this type was registered.
+    ///
+    /// This is useful to access the state outside of handlers. Inside handlers,
+    /// prefer the [`State`](crate::extract::State) extractor.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::SocketIo;
+    /// #[derive(Clone)]
+    /// struct AppState(usize);
+    ///
+    /// let (_, io) = SocketIo::builder().with_state(AppState(42)).build_svc();
+    /// assert_eq!(io.get_state::<AppState>().map(|s| s.0), Some(42));
+    /// assert!(io.get_state::<String>().is_none());
+    /// ```
+    #[inline]
+    #[cfg_attr(docsrs, doc(cfg(feature = "state")))]
     #[cfg(feature = "state")]
-    pub(crate) fn get_state<T: Clone + 'static>(&self) -> Option<T> {
+    pub fn get_state<T: Clone + 'static>(&self) -> Option<T> {
         self.0.state.try_get::<T>().cloned()
     }
 
@@ -690,6 +1943,34 @@ impl<A: DefinedAdapter + Adapter> SocketIo<A> {
     {
         self.0.clone().add_ns(path.into(), callback)
     }
+
+    /// Same as [`SocketIo::ns`] but with a [`NamespaceConfig`] overriding
+    /// the server configuration for the sockets of this namespace.
+    /// ```
+    /// # use socketioxide::{SocketIo, NamespaceConfig, extract::SocketRef};
+    /// # use std::time::Duration;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |s: SocketRef| {});
+    /// // Uploads are slow to acknowledge and may carry large files
+    /// let config = NamespaceConfig::new()
+    ///     .ack_timeout(Duration::from_secs(60))
+    ///     .max_payload(50_000_000);
+    /// io.ns_with_config("/uploads", config, |s: SocketRef| {});
+    /// ```
+    pub fn ns_with_config<C, T>(
+        &self,
+        path: impl Into<Cow<'static, str>>,
+        config: NamespaceConfig,
+        callback: C,
+    ) -> A::InitRes
+    where
+        C: ConnectHandler<A, T>,
+        T: Send + Sync + 'static,
+    {
+        self.0
+            .clone()
+            .add_ns_with_config(path.into(), config, callback)
+    }
 }
 
 impl<A: Adapter> fmt::Debug for SocketIo<A> {
@@ -707,6 +1988,12 @@ impl<A: Adapter> From<Arc<Client<A>>> for SocketIo<A> {
         SocketIo(client)
     }
 }
+impl<A: Adapter> SocketIo<A> {
+    /// The engine.io handler of this instance.
+    pub(crate) fn client(&self) -> &Arc<Client<A>> {
+        &self.0
+    }
+}
 
 #[doc(hidden)]
 #[cfg(feature = "__test_harness")]
@@ -746,6 +2033,31 @@ mod tests {
         let _ = io.get_default_op();
     }
 
+    #[tokio::test]
+    async fn dyn_adapter() {
+        let (_, io) = SocketIo::new_svc();
+        io.ns("/", || {});
+        assert!(io.adapter("/test").is_none());
+        let adapter = io.adapter("/").unwrap();
+        assert_eq!(adapter.server_count().await.unwrap(), 1);
+        let opts = socketioxide_core::adapter::BroadcastOptions::default();
+        assert!(adapter.fetch_sockets(opts).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cluster_nodes() {
+        let (_, io) = SocketIo::new_svc();
+        io.ns("/", || {});
+        let (_, mut rx) = io.new_dummy_sock("/", ()).await;
+        rx.recv().await.unwrap();
+        let nodes = io.cluster_nodes().await.unwrap();
+        let node = NodeInfo {
+            uid: io.config().server_id,
+            sockets: 1,
+        };
+        assert_eq!(nodes, [node]);
+    }
+
     #[test]
     fn get_op() {
         let (_, io) = SocketIo::new_svc();
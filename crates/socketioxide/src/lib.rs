@@ -8,7 +8,7 @@
 #![warn(
     clippy::all,
     clippy::todo,
-    clippy::empty_enum,
+    clippy::empty_enums,
     clippy::mem_forget,
     clippy::unused_self,
     clippy::filter_map_next,
@@ -17,7 +17,6 @@
     clippy::match_wildcard_for_single_variants,
     clippy::if_let_mutex,
     clippy::await_holding_lock,
-    clippy::match_on_vec_items,
     clippy::imprecise_flops,
     clippy::suboptimal_flops,
     clippy::lossy_float_literal,
@@ -64,15 +63,22 @@
 //! * Acknowledgements
 //! * Common and Msgpack parsers
 //! * Polling & Websocket transports
+//! * Connection state recovery
+//! * Presence tracking
+//! * Offline message queues
+//! * Typed events
 //!
 //! ## Compatibility
 //! Because it works as a tower [`layer`](tower_layer::Layer)/[`service`](tower_service::Service) or an hyper [`service`](hyper::service::Service)
 //! you can use it with any http server frameworks that works with tower/hyper:
 //! * [Axum](https://docs.rs/axum/latest/axum/)
-//! * [Warp](https://docs.rs/warp/latest/warp/) (Not supported with socketioxide >= 0.9.0 as long as warp doesn't migrate to hyper v1)
 //! * [Hyper](https://docs.rs/hyper/latest/hyper/)
 //! * [Salvo](https://docs.rs/salvo/latest/salvo/)
 //!
+//! It can also be used with [actix-web](https://docs.rs/actix-web/latest/actix_web/),
+//! [warp](https://docs.rs/warp/latest/warp/) and [rocket](https://docs.rs/rocket/latest/rocket/)
+//! through the `actix`, `warp` and `rocket` feature flags, see the `integrations` module.
+//!
 //! Check the [examples](http://github.com/totodore/socketioxide/tree/main/examples) for
 //! more details on frameworks integration.
 //!
@@ -247,8 +253,23 @@
 //! If the data can't be serialized, a [`ParserError`] will be returned.
 //!
 //! If the socket is disconnected or the internal channel is full, a [`SendError`] will be returned.
+//! When the channel is full, the [`SendError::BufferFull`] variant gives back the undelivered payload.
+//! [`Socket::emit_async`](socket::Socket::emit_async) can also be used to wait for space in the channel rather than failing.
 //! Moreover, a tracing log will be emitted if the `tracing` feature is enabled.
 //!
+//! All the error types are non exhaustive and have a `kind()` method returning an [`ErrorKind`]
+//! (transport, protocol, serialization, buffer...), so that failures can be handled by class:
+//! ```
+//! # use socketioxide::{extract::SocketRef, ErrorKind};
+//! fn handler(socket: SocketRef) {
+//!     match socket.emit("hello", "world") {
+//!         Ok(()) => {}
+//!         Err(e) if e.kind() == ErrorKind::Buffer => println!("client too slow: {e}"),
+//!         Err(e) => println!("emit failed ({}): {e}", e.kind().code()),
+//!     }
+//! }
+//! ```
+//!
 //! #### Emitting with operators
 //! To configure the emit, you can chain [`Operators`](operators) methods to the emit call. With that you can easily configure the following options:
 //! * rooms: emit, join, leave to specific rooms
@@ -266,6 +287,8 @@
 //! They are implemented with the [`AckSender`] extractor.
 //! You can send an ack response with an optional binary payload with the [`AckSender::send`] method.
 //! If the client doesn't send an ack id to respond to, the [`AckSender::send`] method will do nothing.
+//! Several responses can be sent to the same event with [`AckSender::stream`], for clients
+//! supporting the streamed acks.
 //!
 //! #### Client acknowledgements
 //! If you want to emit/broadcast a message and await for a/many client(s) acknowledgment(s) you can use:
@@ -341,10 +364,25 @@
 //!
 //! ## [Feature flags](#feature-flags)
 //! * `v4`: enable support for the socket.io protocol v4
-//! * `tracing`: enable logging with [`tracing`] calls
+//! * `tracing`: enable logging with [`tracing`] calls and record spans for sessions, packets, handlers and emits
+//! * `otel`: set the OpenTelemetry context propagated in the request headers (e.g. `traceparent`) as the parent of the session spans.
+//!   The [`tracing`] spans should then be exported with `tracing-opentelemetry`
 //! * `extensions`: enable per-socket state with the [`extensions`] module
 //! * `state`: enable global state management
 //! * `msgpack`: enable msgpack custom parser
+//! * `ws-deflate`: enable the permessage-deflate websocket extension, see [`SocketIoBuilder::ws_deflate`]
+//! * `http-compression`: enable the gzip/deflate compression of polling payloads, see [`SocketIoBuilder::http_compression`]
+//! * `macros`: enable typed events with the [`typed`] module and the `SocketEvents` derive macro
+//! * `metrics`: record session, packet and acknowledgement metrics with the `metrics` crate, see the [`metrics`] module
+//! * `admin-ui`: serve the [Admin UI](https://socket.io/docs/v4/admin-ui/) protocol, see the [`admin`] module
+//! * `state-sync`: emit the changes of a state shared by the members of rooms as JSON patches, see the [`state_sync`] module
+//! * `actix`: serve socket.io with actix-web, see the [`integrations`] module
+//! * `warp`: serve socket.io as a warp filter, see the [`integrations`] module
+//! * `salvo`: serve socket.io with a salvo handler, see the [`integrations`] module
+//! * `rocket`: serve socket.io with a rocket fairing or routes, see the [`integrations`] module
+//! * `server`: serve socket.io with a minimal standalone http server, see [`SocketIo::serve`]
+//! * `server-tls`: serve socket.io over TLS with the standalone server, see [`SocketIo::serve_tls`]
+//! * `test-client`: test the handlers with an in-process client, see the [`test`](mod@test) module
 //!
 //! [`Adapter`]: adapter::Adapter
 //! [`LocalAdapter`]: adapter::LocalAdapter
@@ -362,6 +400,7 @@
 //! [`FromConnectParts`]: handler::FromConnectParts
 //! [`AckSender`]: extract::AckSender
 //! [`AckSender::send`]: extract::AckSender#method.send
+//! [`AckSender::stream`]: extract::AckSender#method.stream
 //! [`io`]: SocketIo
 
 #[cfg_attr(docsrs, doc(cfg(feature = "extensions")))]
@@ -370,25 +409,73 @@ pub mod extensions;
 
 pub mod ack;
 pub mod adapter;
+#[cfg_attr(docsrs, doc(cfg(feature = "admin-ui")))]
+#[cfg(feature = "admin-ui")]
+pub mod admin;
 pub mod extract;
 pub mod handler;
+#[cfg(any(
+    feature = "actix",
+    feature = "warp",
+    feature = "salvo",
+    feature = "rocket"
+))]
+pub mod integrations;
 pub mod layer;
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod offline;
 pub mod operators;
+pub mod packet;
+pub mod presence;
+pub mod recovery;
+pub mod router;
+pub mod rpc;
 pub mod service;
 pub mod socket;
+#[cfg_attr(docsrs, doc(cfg(feature = "state-sync")))]
+#[cfg(feature = "state-sync")]
+pub mod state_sync;
+pub mod tenant;
+#[cfg_attr(docsrs, doc(cfg(feature = "test-client")))]
+#[cfg(feature = "test-client")]
+pub mod test;
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+#[cfg(feature = "macros")]
+pub mod typed;
+pub mod user;
 
+pub use concurrency::{ConcurrencyLimit, ConcurrencyScope};
+pub use engineioxide::client_ip::IpRange;
+#[cfg(feature = "ws-deflate")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ws-deflate")))]
+pub use engineioxide::config::DeflateConfig;
+#[cfg(feature = "http-compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http-compression")))]
+pub use engineioxide::config::HttpCompressionConfig;
+pub use engineioxide::config::{AllowedOrigins, CorsConfig, ReqPath};
+pub use engineioxide::handshake::{async_filter, AsyncFilter, HandshakeFilter, HandshakeRejection};
+pub use engineioxide::rate_limit::{RateLimit, RateLimitPolicy};
 pub use engineioxide::TransportType;
 pub use errors::{
-    AckError, AdapterError, BroadcastError, EmitWithAckError, NsInsertError, ParserError,
-    SendError, SocketError,
+    AckError, AdapterError, BroadcastError, EmitWithAckError, ErrorKind, NsInsertError,
+    ParserError, PresenceError, SendError, SocketError,
 };
-pub use io::{ParserConfig, SocketIo, SocketIoBuilder, SocketIoConfig};
+pub use io::{NamespaceConfig, ParserConfig, SocketIo, SocketIoBuilder, SocketIoConfig};
+#[cfg(feature = "server-tls")]
+pub use server::TlsConfig;
 
 mod client;
+mod concurrency;
 mod errors;
+mod handoff;
 mod io;
 mod ns;
 mod parser;
+mod rate_limit;
+#[cfg(feature = "server")]
+mod server;
 
 /// Socket.IO protocol version.
 /// It is accessible with the [`Socket::protocol`](socket::Socket) method or as an extractor
@@ -0,0 +1,25 @@
+//! Metrics exposed through the [`metrics`](https://docs.rs/metrics) crate facade.
+//!
+//! They are only recorded if a metrics recorder is installed by the application,
+//! e.g. with [`metrics-exporter-prometheus`](https://docs.rs/metrics-exporter-prometheus)
+//! to scrape them with Prometheus.
+//!
+//! | Name | Type | Description |
+//! |------|------|-------------|
+//! | [`ACK_LATENCY`] | histogram | The time in seconds between emitting a packet with an ack and receiving the client acknowledgement |
+//!
+//! The engine.io metrics (open sessions, packets sent and received, dropped packets, polling payload sizes)
+//! are also recorded, see the [`engineioxide::metrics`] module.
+
+use std::time::Duration;
+
+use metrics::histogram;
+
+pub use engineioxide::metrics::*;
+
+/// The time in seconds between emitting a packet with an ack and receiving the client acknowledgement.
+pub const ACK_LATENCY: &str = "socketio_ack_latency_seconds";
+
+pub(crate) fn ack_received(latency: Duration) {
+    histogram!(ACK_LATENCY).record(latency.as_secs_f64());
+}
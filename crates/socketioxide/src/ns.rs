@@ -1,27 +1,44 @@
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock, Weak},
+    borrow::Cow,
+    sync::{Arc, Weak},
     time::Duration,
 };
 
+#[cfg(feature = "admin-ui")]
+use crate::admin::AdminSink;
 use crate::{
     ack::AckInnerStream,
-    adapter::Adapter,
+    adapter::{Adapter, RoomListeners},
     client::SocketData,
+    concurrency::{ConcurrencyLimit, ConcurrencyScope, HandlerQueue},
     errors::{ConnectFail, Error},
-    handler::{BoxedConnectHandler, ConnectHandler, MakeErasedHandler},
-    parser::Parser,
+    handler::{
+        self, BoxedConnectHandler, ConnectHandler, HandlerErrorHooks, HandlerKind,
+        MakeErasedHandler,
+    },
+    handoff::Sessions,
+    offline::{OfflineQueueConfig, QueuedMessage},
+    packet::Interceptors,
+    parser::{Parser, ParserError},
+    presence::{self, DeltaKind, PresenceConfig, PresenceMeta},
+    recovery::{PersistedPacket, RecoveryAuth, RecoveryConfig, Session},
     socket::{DisconnectReason, Socket},
-    ProtocolVersion, SocketIoConfig,
+    user::UserRegistry,
+    NamespaceConfig, ProtocolVersion, SocketIoConfig,
 };
-use engineioxide::{sid::Sid, Str};
+use engineioxide::{rate_limit::RateLimit, sharded::ShardedMap, sid::Sid, PacketPriority, Str};
+use futures_core::future::BoxFuture;
 use socketioxide_core::{
-    adapter::{BroadcastIter, CoreLocalAdapter, RemoteSocketData, SocketEmitter},
-    errors::SocketError,
+    adapter::{
+        BroadcastIter, BroadcastOptions, CoreLocalAdapter, HandoffRequest, HandoffResponse,
+        RemoteSocketData, Room, SocketEmitter,
+    },
+    errors::{AdapterError, BroadcastError, SocketError},
     packet::{ConnectPacket, Packet, PacketData},
     parser::Parse,
     Uid, Value,
 };
+use tokio::sync::Notify;
 
 /// A [`Namespace`] constructor used for dynamic namespaces
 /// A namespace constructor only hold a common handler that will be cloned
@@ -32,9 +49,44 @@ pub struct NamespaceCtr<A: Adapter> {
 pub struct Namespace<A: Adapter> {
     pub path: Str,
     pub(crate) adapter: Arc<A>,
-    parser: Parser,
+    /// The parser of the sockets of the namespace.
+    pub(crate) parser: Parser,
+    /// The amount of time to wait for an acknowledgement from the sockets of the namespace.
+    pub(crate) ack_timeout: Duration,
+    /// The maximum size of the payload of the packets received by the namespace.
+    pub(crate) max_payload: Option<usize>,
     handler: BoxedConnectHandler<A>,
-    sockets: RwLock<HashMap<Sid, Arc<Socket<A>>>>,
+    sockets: ShardedMap<Sid, Arc<Socket<A>>>,
+    recovery: Option<RecoveryConfig>,
+    presence: Option<PresenceConfig>,
+    offline_queue: Option<OfflineQueueConfig>,
+    /// The prefix of the rooms of the [`UserRegistry`](crate::user::UserRegistry).
+    pub(crate) user_room_prefix: Cow<'static, str>,
+    /// The rate limit of the events received by each socket of the namespace.
+    pub(crate) rate_limit: Option<RateLimit>,
+    /// The limit of the number of event handlers running concurrently.
+    concurrency_limit: Option<ConcurrencyLimit>,
+    /// The handler queue shared by the sockets, with a [`ConcurrencyScope::Namespace`] limit.
+    handler_queue: Option<Arc<HandlerQueue>>,
+    /// The maximum number of events waiting in a handler queue.
+    handler_queue_size: usize,
+    /// Handle the events of each socket in order.
+    ordered: bool,
+    /// The params captured from the path pattern of a dynamic namespace.
+    pub(crate) params: Arc<[(String, String)]>,
+    /// The engine.io sessions of the server, used to handle forwarded polling requests.
+    sessions: Arc<Sessions<A>>,
+    /// The admin UI sink notified of the namespace activity, `None` for the admin namespace itself.
+    #[cfg(feature = "admin-ui")]
+    pub(crate) admin: Option<Arc<AdminSink>>,
+    /// The packet interceptors of the server.
+    pub(crate) interceptors: Arc<Interceptors>,
+    /// The hooks called when a handler of a socket panics.
+    pub(crate) handler_errors: Arc<HandlerErrorHooks>,
+    /// Disconnect the sockets whose connect or message handler panics.
+    pub(crate) disconnect_on_handler_panic: bool,
+    /// Notified when a socket is removed.
+    socket_removed: Arc<Notify>,
 }
 
 /// ===== impl NamespaceCtr =====
@@ -51,11 +103,22 @@ impl<A: Adapter> NamespaceCtr<A> {
     pub fn get_new_ns(
         &self,
         path: Str,
+        params: Arc<[(String, String)]>,
         adapter_state: &A::State,
+        sessions: &Arc<Sessions<A>>,
         config: &SocketIoConfig,
     ) -> Arc<Namespace<A>> {
         let handler = self.handler.boxed_clone();
-        Namespace::new_boxed(path, handler, adapter_state, config)
+        let ns_config = NamespaceConfig::default();
+        Namespace::new_boxed(
+            path,
+            handler,
+            params,
+            adapter_state,
+            sessions,
+            config,
+            &ns_config,
+        )
     }
 }
 
@@ -64,40 +127,92 @@ impl<A: Adapter> Namespace<A> {
         path: Str,
         handler: C,
         adapter_state: &A::State,
+        sessions: &Arc<Sessions<A>>,
         config: &SocketIoConfig,
+        ns_config: &NamespaceConfig,
     ) -> Arc<Self>
     where
         C: ConnectHandler<A, T> + Send + Sync + 'static,
         T: Send + Sync + 'static,
     {
         let handler = MakeErasedHandler::new_ns_boxed(handler);
-        Self::new_boxed(path, handler, adapter_state, config)
+        Self::new_boxed(
+            path,
+            handler,
+            Arc::new([]),
+            adapter_state,
+            sessions,
+            config,
+            ns_config,
+        )
     }
 
     fn new_boxed(
         path: Str,
         handler: BoxedConnectHandler<A>,
+        params: Arc<[(String, String)]>,
         adapter_state: &A::State,
+        sessions: &Arc<Sessions<A>>,
         config: &SocketIoConfig,
+        ns_config: &NamespaceConfig,
     ) -> Arc<Self> {
-        let parser = config.parser;
-        let ack_timeout = config.ack_timeout;
-        let uid = config.server_id;
+        let parser = ns_config.parser.unwrap_or(config.parser);
+        let ack_timeout = ns_config.ack_timeout.unwrap_or(config.ack_timeout);
+        #[cfg(feature = "admin-ui")]
+        let admin = (config.admin_ui.clone()).filter(|sink| sink.namespace() != path.as_str());
+        #[cfg(feature = "admin-ui")]
+        let emitter_admin = admin.clone();
         Arc::new_cyclic(|ns| Self {
             path: path.clone(),
             handler,
             parser,
-            sockets: HashMap::new().into(),
+            ack_timeout,
+            max_payload: ns_config.max_payload,
+            sockets: ShardedMap::new(),
+            recovery: config.connection_state_recovery.clone(),
+            presence: config.presence.clone(),
+            offline_queue: config.offline_queue.clone(),
+            user_room_prefix: config.user_room_prefix.clone(),
+            rate_limit: ns_config
+                .max_events_per_second
+                .or(config.max_events_per_second),
+            concurrency_limit: config.max_concurrent_handlers,
+            handler_queue: config
+                .max_concurrent_handlers
+                .filter(|limit| limit.scope == ConcurrencyScope::Namespace)
+                .map(|limit| Arc::new(HandlerQueue::new(limit.max, config.handler_queue_size))),
+            handler_queue_size: config.handler_queue_size,
+            ordered: ns_config.ordered,
+            params,
+            sessions: sessions.clone(),
+            #[cfg(feature = "admin-ui")]
+            admin,
+            interceptors: config.interceptors.clone(),
+            handler_errors: config.handler_errors.clone(),
+            disconnect_on_handler_panic: config.disconnect_on_handler_panic,
+            socket_removed: config.socket_removed.clone(),
             adapter: Arc::new(A::new(
                 adapter_state,
-                CoreLocalAdapter::new(Emitter::new(ns.clone(), parser, path, ack_timeout, uid)),
+                CoreLocalAdapter::new(Emitter::new(
+                    ns.clone(),
+                    parser,
+                    path,
+                    ack_timeout,
+                    config,
+                    #[cfg(feature = "admin-ui")]
+                    emitter_admin,
+                )),
             )),
         })
     }
 
     /// Connects a socket to a namespace.
     ///
-    /// Middlewares are first called to check if the connection is allowed.
+    /// If connection state recovery is enabled and the client provided a valid session,
+    /// the session is restored first.
+    ///
+    /// Middlewares are then called to check if the connection is allowed
+    /// (unless the session was recovered and middlewares should be skipped).
     /// * If the handler returns an error, a connect_error packet is sent to the client.
     /// * If the handler returns Ok, a connect packet is sent to the client and the handler `is` called.
     pub(crate) async fn connect(
@@ -106,30 +221,55 @@ impl<A: Adapter> Namespace<A> {
         esocket: Arc<engineioxide::Socket<SocketData<A>>>,
         auth: Option<Value>,
     ) -> Result<(), ConnectFail> {
-        let socket: Arc<Socket<A>> =
-            Socket::new(sid, self.clone(), esocket.clone(), self.parser).into();
-
-        if let Err(e) = self.handler.call_middleware(socket.clone(), &auth).await {
-            #[cfg(feature = "tracing")]
-            tracing::trace!(ns = self.path.as_str(), ?socket.id, "emitting connect_error packet");
-
-            let data = e.to_string();
-            if let Err(_e) = socket.send(Packet::connect_error(self.path.clone(), data)) {
+        let session = self.restore_session(&auth).await;
+        let pid = match (&self.recovery, &session) {
+            (Some(_), Some(session)) => Some(session.pid),
+            (Some(_), None) => Some(Sid::new()),
+            (None, _) => None,
+        };
+        let socket: Arc<Socket<A>> = Socket::new(sid, self.clone(), esocket.clone(), self.parser)
+            .with_recovery(pid, session.is_some())
+            .into();
+
+        let skip_middlewares =
+            session.is_some() && self.recovery.as_ref().is_some_and(|r| r.skip_middlewares);
+        if !skip_middlewares {
+            if let Err(e) = self.handler.call_middleware(socket.clone(), &auth).await {
                 #[cfg(feature = "tracing")]
-                tracing::debug!("error sending connect_error packet: {:?}, closing conn", _e);
-                esocket.close(engineioxide::DisconnectReason::PacketParsingError);
+                tracing::trace!(ns = self.path.as_str(), ?socket.id, "emitting connect_error packet");
+
+                let (message, data) = e.into_parts(self.parser);
+                let data = data.unwrap_or_else(|_e| {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("error serializing connect_error data: {:?}", _e);
+                    None
+                });
+                let packet = Packet::connect_error(self.path.clone(), message, data);
+                if let Err(_e) = socket.send(packet) {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("error sending connect_error packet: {:?}, closing conn", _e);
+                    esocket.close(engineioxide::DisconnectReason::PacketParsingError);
+                }
+                return Err(ConnectFail);
             }
-            return Err(ConnectFail);
         }
 
-        self.sockets.write().unwrap().insert(sid, socket.clone());
+        self.sockets.insert(sid, socket.clone());
         #[cfg(feature = "tracing")]
         tracing::trace!(?socket.id, ?self.path, "socket added to namespace");
 
+        if let Some(session) = &session {
+            socket.join(session.rooms.clone());
+        }
+
         let protocol = esocket.protocol.into();
-        let payload = ConnectPacket { sid: socket.id };
+        let payload = ConnectPacket::new(socket.id).with_pid(pid);
         let payload = match protocol {
-            ProtocolVersion::V5 => Some(self.parser.encode_default(&payload).unwrap()),
+            ProtocolVersion::V5 => Some(
+                socket
+                    .take_connect_payload()
+                    .unwrap_or_else(|| self.parser.encode_default(&payload).unwrap()),
+            ),
             ProtocolVersion::V4 => None,
         };
         if let Err(_e) = socket.send(Packet::connect(self.path.clone(), payload)) {
@@ -139,44 +279,270 @@ impl<A: Adapter> Namespace<A> {
             return Err(ConnectFail);
         }
 
+        if let Some(session) = session {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(?socket.id, ?self.path, "sending {} missed packets", session.missed_packets.len());
+            for data in session.missed_packets {
+                if let Err(_e) = socket.send(Packet::event(self.path.clone(), data)) {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("error sending missed packet: {:?}", _e);
+                }
+            }
+        }
+
         socket.set_connected(true);
-        self.handler.call(socket, auth);
+        #[cfg(feature = "admin-ui")]
+        if let Some(admin) = &self.admin {
+            admin.socket_connected(&socket);
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            parent: esocket.span(),
+            "socketio.connect",
+            ns = %self.path,
+        )
+        .entered();
+        handler::call_guarded(&socket.clone(), HandlerKind::Connect, None, || {
+            self.handler.call(socket, auth)
+        });
+
+        Ok(())
+    }
+
+    /// Try to restore a session with the recovery data provided by the client in its auth payload.
+    async fn restore_session(&self, auth: &Option<Value>) -> Option<Session> {
+        let recovery = self.recovery.as_ref()?;
+        let auth: RecoveryAuth = self.parser.decode_default(auth.as_ref()).ok()?;
+        let session = recovery
+            .store
+            .restore_session(auth.pid, &auth.offset)
+            .await
+            .filter(|s| s.ns == self.path);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(pid = ?auth.pid, ?self.path, recovered = session.is_some(), "session recovery attempt");
+        session
+    }
+
+    /// Get the handler queue of a new socket, if the number of concurrent handlers is limited
+    /// or if the events are handled in order.
+    pub(crate) fn handler_queue(&self) -> Option<Arc<HandlerQueue>> {
+        if self.ordered {
+            return Some(Arc::new(HandlerQueue::new(1, self.handler_queue_size)));
+        }
+        match self.concurrency_limit? {
+            ConcurrencyLimit {
+                scope: ConcurrencyScope::Socket,
+                max,
+            } => Some(Arc::new(HandlerQueue::new(max, self.handler_queue_size))),
+            ConcurrencyLimit {
+                scope: ConcurrencyScope::Namespace,
+                ..
+            } => self.handler_queue.clone(),
+        }
+    }
 
+    /// Check if the panics of the handlers are reported to hooks or disconnect the sockets.
+    pub(crate) fn reports_handler_panics(&self) -> bool {
+        self.disconnect_on_handler_panic || !self.handler_errors.is_empty()
+    }
+
+    /// Persist the session of a disconnected socket if connection state recovery is enabled
+    /// and if the disconnection reason allows it.
+    pub(crate) fn persist_session(&self, socket: &Socket<A>, reason: DisconnectReason) {
+        let (Some(recovery), Some(pid)) = (&self.recovery, socket.pid) else {
+            return;
+        };
+        if !reason.is_recoverable() {
+            return;
+        }
+        let session = Session {
+            pid,
+            sid: socket.id,
+            ns: self.path.clone(),
+            rooms: socket.rooms(),
+            missed_packets: Vec::new(),
+        };
+        let store = recovery.store.clone();
+        tokio::spawn(async move { store.persist_session(session).await });
+    }
+
+    /// Tag an event packet with a unique offset and persist it if connection state recovery is enabled.
+    pub(crate) async fn persist_packet(
+        &self,
+        packet: &mut Packet,
+        opts: &BroadcastOptions,
+    ) -> Result<(), ParserError> {
+        let Some(recovery) = &self.recovery else {
+            return Ok(());
+        };
+        let data = match &mut packet.inner {
+            PacketData::Event(data, None) | PacketData::BinaryEvent(data, None) => data,
+            _ => return Ok(()),
+        };
+        let offset = Sid::new().to_string();
+        self.parser.push_offset(data, &offset)?;
+        let packet = PersistedPacket {
+            offset,
+            ns: self.path.clone(),
+            opts: opts.clone(),
+            data: data.clone(),
+        };
+        recovery.store.persist_packet(packet).await;
         Ok(())
     }
 
+    /// Queue an event packet for a user if the offline queue is enabled
+    /// and if the user has no connected socket on any server.
+    ///
+    /// Returns `true` if the packet was queued, it must then not be broadcast: it is delivered
+    /// when the user connects, or right away to the sockets selected by `opts` if the user
+    /// connected while it was being queued.
+    pub(crate) async fn queue_offline_packet(
+        self: &Arc<Self>,
+        user: &str,
+        packet: &Packet,
+        opts: &BroadcastOptions,
+    ) -> Result<bool, BroadcastError> {
+        let Some(offline) = &self.offline_queue else {
+            return Ok(false);
+        };
+        let data = match &packet.inner {
+            PacketData::Event(data, None) | PacketData::BinaryEvent(data, None) => data,
+            _ => return Ok(false),
+        };
+        let users = UserRegistry::new(self.clone());
+        if users
+            .is_online(user)
+            .await
+            .map_err(Into::<AdapterError>::into)?
+        {
+            return Ok(false);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::trace!(?self.path, user, "queuing packet for offline user");
+        let message = QueuedMessage {
+            ns: self.path.clone(),
+            user: user.to_owned(),
+            data: data.clone(),
+        };
+        offline.store.push(message).await;
+
+        // The user may have connected, and taken its queue, between the check and the push.
+        // Its sockets have then joined the user room, so the queue is flushed to them.
+        if users
+            .is_online(user)
+            .await
+            .map_err(Into::<AdapterError>::into)?
+        {
+            for data in offline.store.take(&self.path, user).await {
+                let packet = Packet::event(self.path.clone(), data);
+                self.adapter.broadcast(packet, opts.clone()).await?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Send the packets queued for a user to one of its sockets, if the offline queue is enabled.
+    /// Nothing is sent if the socket is not connected to the namespace yet.
+    pub(crate) fn deliver_offline_packets(&self, sid: Sid, user: String) {
+        let Some(offline) = &self.offline_queue else {
+            return;
+        };
+        let Ok(socket) = self.get_socket(sid) else {
+            return;
+        };
+        let store = offline.store.clone();
+        let path = self.path.clone();
+        tokio::spawn(async move {
+            let packets = store.take(&path, &user).await;
+            #[cfg(feature = "tracing")]
+            tracing::trace!(?socket.id, ?path, user, "sending {} queued packets", packets.len());
+            for data in packets {
+                if let Err(_e) = socket.send(Packet::event(path.clone(), data)) {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("error sending queued packet: {:?}", _e);
+                }
+            }
+        });
+    }
+
+    /// The presence configuration of the server, if presence is enabled.
+    pub(crate) fn presence(&self) -> Option<&PresenceConfig> {
+        self.presence.as_ref()
+    }
+
+    /// Emits a presence delta of a room to its subscribers, if presence is enabled.
+    /// The delta is broadcasted through the adapter in a separate task.
+    pub(crate) fn emit_presence_delta(
+        self: &Arc<Self>,
+        kind: DeltaKind,
+        sid: Sid,
+        room: &Room,
+        meta: Option<Arc<dyn PresenceMeta>>,
+    ) {
+        let Some(presence) = &self.presence else {
+            return;
+        };
+        if presence.is_subscribers_room(room) {
+            return;
+        }
+        let event = presence.event.as_ref();
+        let data = match meta {
+            Some(meta) if kind != DeltaKind::Leave => {
+                meta.encode_delta(self.parser, event, room, kind, sid)
+            }
+            _ => presence::encode_empty_delta(self.parser, event, room, kind, sid),
+        };
+        let data = match data {
+            Ok(data) => data,
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(?sid, ?room, "could not encode presence delta: {_e}");
+                return;
+            }
+        };
+        let mut packet = Packet::event(self.path.clone(), data);
+        self.interceptors.outbound(&mut packet);
+        let mut opts = BroadcastOptions::default();
+        opts.rooms.push(presence.subscribers_room(room));
+
+        let ns = self.clone();
+        tokio::spawn(async move {
+            if let Err(_e) = ns.adapter.broadcast(packet, opts).await {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("could not broadcast presence delta: {_e}");
+            }
+        });
+    }
+
     /// Removes a socket from a namespace
     pub fn remove_socket(&self, sid: Sid) {
         #[cfg(feature = "tracing")]
         tracing::trace!(?sid, ?self.path, "removing socket from namespace");
 
-        self.sockets.write().unwrap().remove(&sid);
+        self.sockets.remove(&sid);
         self.adapter.get_local().del_all(sid);
+        self.socket_removed.notify_waiters();
     }
 
     pub fn has(&self, sid: Sid) -> bool {
-        self.sockets.read().unwrap().values().any(|s| s.id == sid)
+        self.sockets.contains_key(&sid)
     }
 
     pub fn recv(&self, sid: Sid, packet: PacketData) -> Result<(), Error> {
         match packet {
             PacketData::Connect(_) => unreachable!("connect packets should be handled before"),
-            PacketData::ConnectError(_) => Err(Error::InvalidPacketType),
+            PacketData::ConnectError(..) => Err(Error::InvalidPacketType),
             packet => self.get_socket(sid)?.recv(packet),
         }
     }
 
     pub fn get_socket(&self, sid: Sid) -> Result<Arc<Socket<A>>, Error> {
-        self.sockets
-            .read()
-            .unwrap()
-            .get(&sid)
-            .cloned()
-            .ok_or(Error::SocketGone(sid))
+        self.sockets.get(&sid).ok_or(Error::SocketGone(sid))
     }
 
     pub fn get_sockets(&self) -> Vec<Arc<Socket<A>>> {
-        self.sockets.read().unwrap().values().cloned().collect()
+        self.sockets.values()
     }
 
     /// Closes the entire namespace :
@@ -188,7 +554,7 @@ impl<A: Adapter> Namespace<A> {
     /// This function is using .await points only when called with [`DisconnectReason::ClosingServer`]
     pub async fn close(&self, reason: DisconnectReason) {
         use futures_util::future;
-        let sockets = self.sockets.read().unwrap().clone();
+        let sockets = self.sockets.values();
 
         #[cfg(feature = "tracing")]
         tracing::debug!(?self.path, "closing {} sockets in namespace", sockets.len());
@@ -196,9 +562,9 @@ impl<A: Adapter> Namespace<A> {
         if reason == DisconnectReason::ClosingServer {
             // When closing the underlying transport, this will indirectly close the socket
             // Therefore there is no need to manually call `s.close()`.
-            future::join_all(sockets.values().map(|s| s.close_underlying_transport())).await;
+            future::join_all(sockets.iter().map(|s| s.close_underlying_transport())).await;
         } else {
-            for s in sockets.into_values() {
+            for s in sockets {
                 let _sid = s.id;
                 s.close(reason);
             }
@@ -223,6 +589,13 @@ trait InnerEmitter: Send + Sync + 'static {
     fn get_all_sids(&self, filter: &dyn Fn(&Sid) -> bool) -> Vec<Sid>;
     /// Send data to the list of socket ids.
     fn send_many(&self, sids: BroadcastIter<'_>, data: Value) -> Result<(), Vec<SocketError>>;
+    /// Send data to the list of socket ids, dropping it for the sockets
+    /// that are upgrading or have a full buffer.
+    fn send_many_volatile(
+        &self,
+        sids: BroadcastIter<'_>,
+        data: Value,
+    ) -> Result<(), Vec<SocketError>>;
     /// Send data to the list of socket ids and get a stream of acks.
     fn send_many_with_ack(
         &self,
@@ -232,33 +605,33 @@ trait InnerEmitter: Send + Sync + 'static {
     ) -> (AckInnerStream, u32);
     /// Disconnect all the sockets in the list.
     fn disconnect_many(&self, sids: Vec<Sid>) -> Result<(), Vec<SocketError>>;
+    /// Handle a polling request forwarded for an engine.io session of this server.
+    fn handoff(&self, sid: Sid, req: HandoffRequest)
+        -> BoxFuture<'static, Option<HandoffResponse>>;
+    /// Emit the presence delta of a socket that joined or left a room.
+    fn on_presence_change(self: Arc<Self>, kind: DeltaKind, sid: Sid, room: &Room);
+    /// Expire the room if its expiry is due.
+    fn expire_room(&self, room: &Room);
 }
 
 impl<A: Adapter> InnerEmitter for Namespace<A> {
     fn get_remote_sockets(&self, sids: BroadcastIter<'_>, uid: Uid) -> Vec<RemoteSocketData> {
-        let sockets = self.sockets.read().unwrap();
-        sids.filter_map(|sid| sockets.get(&sid))
-            .map(|socket| RemoteSocketData {
-                id: socket.id,
-                ns: self.path.clone(),
-                server_id: uid,
+        sids.filter_map(|sid| self.sockets.get(&sid))
+            .map(|socket| {
+                RemoteSocketData::new(socket.id, uid, self.path.clone())
+                    .with_presence(socket.encode_presence_meta())
             })
             .collect()
     }
     fn get_all_sids(&self, filter: &dyn Fn(&Sid) -> bool) -> Vec<Sid> {
-        self.sockets
-            .read()
-            .unwrap()
-            .keys()
-            .filter(|id| filter(id))
-            .copied()
-            .collect()
+        let mut sids = self.sockets.keys();
+        sids.retain(|id| filter(id));
+        sids
     }
 
     fn send_many(&self, sids: BroadcastIter<'_>, data: Value) -> Result<(), Vec<SocketError>> {
-        let sockets = self.sockets.read().unwrap();
         let errs: Vec<SocketError> = sids
-            .filter_map(|sid| sockets.get(&sid))
+            .filter_map(|sid| self.sockets.get(&sid))
             .filter_map(|socket| socket.send_raw(data.clone()).err())
             .collect();
         if errs.is_empty() {
@@ -268,14 +641,35 @@ impl<A: Adapter> InnerEmitter for Namespace<A> {
         }
     }
 
+    fn send_many_volatile(
+        &self,
+        sids: BroadcastIter<'_>,
+        data: Value,
+    ) -> Result<(), Vec<SocketError>> {
+        let errs: Vec<SocketError> = sids
+            .filter_map(|sid| self.sockets.get(&sid))
+            .filter(|socket| !socket.is_upgrading())
+            .filter_map(|socket| {
+                socket
+                    .send_raw_with_priority(data.clone(), PacketPriority::Low)
+                    .err()
+            })
+            .filter(|err| !matches!(err, SocketError::InternalChannelFull))
+            .collect();
+        if errs.is_empty() {
+            Ok(())
+        } else {
+            Err(errs)
+        }
+    }
+
     fn send_many_with_ack(
         &self,
         sids: BroadcastIter<'_>,
         packet: Packet,
         timeout: Duration,
     ) -> (AckInnerStream, u32) {
-        let sockets_map = self.sockets.read().unwrap();
-        let sockets = sids.filter_map(|sid| sockets_map.get(&sid));
+        let sockets = sids.filter_map(|sid| self.sockets.get(&sid));
         AckInnerStream::broadcast(packet, sockets, timeout)
     }
 
@@ -283,18 +677,10 @@ impl<A: Adapter> InnerEmitter for Namespace<A> {
         if sids.is_empty() {
             return Ok(());
         }
-        // Here we can't take a ref because this would cause a deadlock.
-        // Ideally the disconnect / closing process should be refactored to avoid this.
-        let sockets = {
-            let sock_map = self.sockets.read().unwrap();
-            sids.into_iter()
-                .filter_map(|sid| sock_map.get(&sid))
-                .cloned()
-                .collect::<Vec<_>>()
-        };
-
-        let errs = sockets
+        // The sockets are cloned out of the map so no lock is held while they remove themselves.
+        let errs = sids
             .into_iter()
+            .filter_map(|sid| self.sockets.get(&sid))
             .filter_map(|socket| socket.disconnect().err())
             .collect::<Vec<_>>();
         if errs.is_empty() {
@@ -303,6 +689,23 @@ impl<A: Adapter> InnerEmitter for Namespace<A> {
             Err(errs)
         }
     }
+    fn handoff(
+        &self,
+        sid: Sid,
+        req: HandoffRequest,
+    ) -> BoxFuture<'static, Option<HandoffResponse>> {
+        self.sessions.handoff(sid, req)
+    }
+    fn on_presence_change(self: Arc<Self>, kind: DeltaKind, sid: Sid, room: &Room) {
+        if self.presence.is_none() {
+            return;
+        }
+        let meta = self.get_socket(sid).ok().and_then(|s| s.presence_meta());
+        self.emit_presence_delta(kind, sid, room, meta);
+    }
+    fn expire_room(&self, room: &Room) {
+        self.adapter.get_local().expire_room(room);
+    }
 }
 
 /// Internal interface implementor to apply global operations on a namespace.
@@ -314,6 +717,9 @@ pub struct Emitter {
     path: Str,
     ack_timeout: Duration,
     uid: Uid,
+    room_listeners: Arc<RoomListeners>,
+    #[cfg(feature = "admin-ui")]
+    admin: Option<Arc<AdminSink>>,
 }
 
 impl Emitter {
@@ -322,14 +728,18 @@ impl Emitter {
         parser: Parser,
         path: Str,
         ack_timeout: Duration,
-        uid: Uid,
+        config: &SocketIoConfig,
+        #[cfg(feature = "admin-ui")] admin: Option<Arc<AdminSink>>,
     ) -> Self {
         Self {
             ns,
             parser,
             path,
             ack_timeout,
-            uid,
+            uid: config.server_id,
+            room_listeners: config.room_listeners.clone(),
+            #[cfg(feature = "admin-ui")]
+            admin,
         }
     }
 }
@@ -358,6 +768,17 @@ impl SocketEmitter for Emitter {
         }
     }
 
+    fn send_many_volatile(
+        &self,
+        sids: BroadcastIter<'_>,
+        data: Value,
+    ) -> Result<(), Vec<SocketError>> {
+        match self.ns.upgrade() {
+            Some(ns) => ns.send_many_volatile(sids, data),
+            None => Ok(()),
+        }
+    }
+
     fn send_many_with_ack(
         &self,
         sids: BroadcastIter<'_>,
@@ -385,24 +806,78 @@ impl SocketEmitter for Emitter {
     fn path(&self) -> &Str {
         &self.path
     }
+    fn handoff(
+        &self,
+        sid: Sid,
+        req: HandoffRequest,
+    ) -> BoxFuture<'static, Option<HandoffResponse>> {
+        match self.ns.upgrade() {
+            Some(ns) => ns.handoff(sid, req),
+            None => Box::pin(std::future::ready(None)),
+        }
+    }
+    fn on_join_room(&self, sid: Sid, room: &Room) {
+        #[cfg(feature = "admin-ui")]
+        if let Some(admin) = &self.admin {
+            admin.room_joined(&self.path, sid, room);
+        }
+        self.room_listeners.joined(&self.path, sid, room);
+        if let Some(ns) = self.ns.upgrade() {
+            ns.on_presence_change(DeltaKind::Join, sid, room);
+        }
+    }
+    fn on_leave_room(&self, sid: Sid, room: &Room) {
+        #[cfg(feature = "admin-ui")]
+        if let Some(admin) = &self.admin {
+            admin.room_left(&self.path, sid, room);
+        }
+        self.room_listeners.left(&self.path, sid, room);
+        if let Some(ns) = self.ns.upgrade() {
+            ns.on_presence_change(DeltaKind::Leave, sid, room);
+        }
+    }
+    fn on_create_room(&self, room: &Room) {
+        self.room_listeners.created(&self.path, room);
+    }
+    fn on_delete_room(&self, room: &Room) {
+        self.room_listeners.deleted(&self.path, room);
+    }
+    fn schedule_room_expiry(&self, room: &Room, delay: Duration) {
+        let ns = self.ns.clone();
+        let room = room.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Some(ns) = ns.upgrade() {
+                ns.expire_room(&room);
+            }
+        });
+    }
+    fn on_expire_room(&self, room: &Room) {
+        self.room_listeners.expired(&self.path, room);
+    }
 }
 
 #[doc(hidden)]
 #[cfg(feature = "__test_harness")]
 impl Namespace<crate::adapter::LocalAdapter> {
     pub fn new_dummy<const S: usize>(sockets: [Sid; S]) -> Arc<Self> {
-        let ns = Namespace::new("/".into(), || {}, &(), &SocketIoConfig::default());
+        let ns = Namespace::new(
+            "/".into(),
+            || {},
+            &(),
+            &Default::default(),
+            &SocketIoConfig::default(),
+            &NamespaceConfig::default(),
+        );
         for sid in sockets {
             ns.sockets
-                .write()
-                .unwrap()
                 .insert(sid, Socket::new_dummy(sid, ns.clone()).into());
         }
         ns
     }
 
     pub fn clean_dummy_sockets(&self) {
-        self.sockets.write().unwrap().clear();
+        self.sockets.clear();
     }
 }
 
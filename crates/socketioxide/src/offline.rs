@@ -0,0 +1,250 @@
+//! Offline message queue related types.
+//!
+//! When enabled with [`SocketIoBuilder::with_offline_queue`](crate::SocketIoBuilder::with_offline_queue),
+//! the events emitted to a user with the [`persistent`](crate::operators::BroadcastOperators::persistent)
+//! flag are stored if the user has no connected socket on any server. They are then delivered,
+//! in order, to the next socket bound to this user with [`Socket::set_user`](crate::socket::Socket::set_user).
+//!
+//! The [`OfflineStore`] trait can be implemented to store the queued messages
+//! in an external store. By default, a [`MemoryOfflineStore`] is used.
+//! Stores should discard the messages older than their time-to-live and cap the size of each queue.
+//!
+//! **Note**: Only events emitted with [`UserRegistry::to`](crate::user::UserRegistry::to)
+//! (or [`SocketIo::to_user`](crate::SocketIo::to_user)) and [`emit`](crate::operators::BroadcastOperators::emit)
+//! are queued. Events emitted with an acknowledgement are not.
+//!
+//! # Example
+//! ```
+//! # use socketioxide::{SocketIo, extract::*, offline::OfflineQueueConfig};
+//! # use std::time::Duration;
+//! let (_, io) = SocketIo::builder()
+//!     .with_offline_queue(OfflineQueueConfig::new(Duration::from_secs(3600), 50))
+//!     .build_svc();
+//!
+//! io.ns("/", |socket: SocketRef, Data(user_id): Data<String>| {
+//!     // The messages queued while the user was offline are sent to this socket
+//!     socket.set_user(&user_id);
+//! });
+//!
+//! async fn notify(io: SocketIo, user_id: u64) {
+//!     io.to_user(user_id).persistent().emit("notification", "hello").await.ok();
+//! }
+//! ```
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures_util::future::{self, BoxFuture};
+use socketioxide_core::{Str, Value};
+
+/// An event queued for a user that had no connected socket when it was emitted.
+#[derive(Debug, Clone)]
+pub struct QueuedMessage {
+    /// The namespace the event was emitted to.
+    pub ns: Str,
+    /// The id of the user the event was emitted to.
+    pub user: String,
+    /// The event payload, including the event name.
+    pub data: Value,
+}
+
+/// A store used to queue the messages of offline users.
+///
+/// Implementations are responsible for discarding expired messages and capping the size of the queues.
+pub trait OfflineStore: fmt::Debug + Send + Sync + 'static {
+    /// Queue a message for an offline user.
+    fn push(&self, message: QueuedMessage) -> BoxFuture<'_, ()>;
+
+    /// Remove and return all the messages queued for a user of a namespace, in emission order.
+    ///
+    /// Expired messages should not be returned.
+    fn take(&self, ns: &str, user: &str) -> BoxFuture<'_, Vec<Value>>;
+}
+
+type Queue = VecDeque<(Instant, Value)>;
+
+struct Queues {
+    queues: HashMap<(Str, String), Queue>,
+    last_sweep: Instant,
+}
+
+/// The default in-memory [`OfflineStore`].
+///
+/// When a queue is full, its oldest message is dropped to make room for the new one.
+/// Only the queue of the user is pruned when a message is pushed or taken, the queues of the
+/// other users are swept at most once per time-to-live.
+pub struct MemoryOfflineStore {
+    ttl: Duration,
+    max_messages: usize,
+    queues: Mutex<Queues>,
+}
+
+impl MemoryOfflineStore {
+    /// Create a new in-memory store that keeps messages for the given `ttl`
+    /// and at most `max_messages` messages per user.
+    pub fn new(ttl: Duration, max_messages: usize) -> Self {
+        Self {
+            ttl,
+            max_messages,
+            queues: Mutex::new(Queues {
+                queues: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    /// Drop the expired messages at the front of a queue.
+    fn prune(&self, queue: &mut Queue, now: Instant) {
+        while queue
+            .front()
+            .is_some_and(|(t, _)| now.duration_since(*t) > self.ttl)
+        {
+            queue.pop_front();
+        }
+    }
+
+    /// Drop the expired messages of all the queues if the last sweep is older than the ttl.
+    fn sweep(&self, queues: &mut Queues, now: Instant) {
+        if now.duration_since(queues.last_sweep) < self.ttl {
+            return;
+        }
+        queues.last_sweep = now;
+        queues.queues.retain(|_, queue| {
+            self.prune(queue, now);
+            !queue.is_empty()
+        });
+    }
+}
+
+impl fmt::Debug for MemoryOfflineStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryOfflineStore")
+            .field("ttl", &self.ttl)
+            .field("max_messages", &self.max_messages)
+            .field("users", &self.queues.lock().unwrap().queues.len())
+            .finish()
+    }
+}
+
+impl OfflineStore for MemoryOfflineStore {
+    fn push(&self, message: QueuedMessage) -> BoxFuture<'_, ()> {
+        if self.max_messages > 0 {
+            let now = Instant::now();
+            let mut queues = self.queues.lock().unwrap();
+            self.sweep(&mut queues, now);
+            let queue = queues.queues.entry((message.ns, message.user)).or_default();
+            self.prune(queue, now);
+            if queue.len() >= self.max_messages {
+                queue.pop_front();
+            }
+            queue.push_back((now, message.data));
+        }
+        Box::pin(future::ready(()))
+    }
+
+    fn take(&self, ns: &str, user: &str) -> BoxFuture<'_, Vec<Value>> {
+        let now = Instant::now();
+        let mut queues = self.queues.lock().unwrap();
+        self.sweep(&mut queues, now);
+        let messages = match queues
+            .queues
+            .remove(&(Str::from(ns.to_owned()), user.to_owned()))
+        {
+            Some(mut queue) => {
+                self.prune(&mut queue, now);
+                queue.into_iter().map(|(_, data)| data).collect()
+            }
+            None => Vec::new(),
+        };
+        Box::pin(future::ready(messages))
+    }
+}
+
+/// Configuration for the offline message queue.
+#[derive(Debug, Clone)]
+pub struct OfflineQueueConfig {
+    pub(crate) store: Arc<dyn OfflineStore>,
+}
+
+impl OfflineQueueConfig {
+    /// Create a new offline queue config with an in-memory [`MemoryOfflineStore`].
+    /// Messages will be kept for the given `ttl` and at most `max_messages` messages will be kept per user.
+    pub fn new(ttl: Duration, max_messages: usize) -> Self {
+        Self::with_store(MemoryOfflineStore::new(ttl, max_messages))
+    }
+
+    /// Create a new offline queue config with a custom [`OfflineStore`].
+    pub fn with_store(store: impl OfflineStore) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+}
+
+impl Default for OfflineQueueConfig {
+    /// An offline queue config with a [`MemoryOfflineStore`] that keeps
+    /// at most 100 messages per user for 24 hours.
+    fn default() -> Self {
+        Self::new(Duration::from_secs(24 * 3600), 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(user: &str, data: &str) -> QueuedMessage {
+        QueuedMessage {
+            ns: Str::from("/"),
+            user: user.to_string(),
+            data: Value::Str(Str::from(data.to_string()), None),
+        }
+    }
+
+    async fn take(store: &MemoryOfflineStore, user: &str) -> Vec<String> {
+        let messages = store.take("/", user).await;
+        messages
+            .iter()
+            .map(|v| v.as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn memory_store_caps_queues() {
+        let store = MemoryOfflineStore::new(Duration::from_secs(60), 2);
+        for data in ["1", "2", "3"] {
+            store.push(message("alice", data)).await;
+        }
+        store.push(message("bob", "4")).await;
+
+        assert_eq!(take(&store, "alice").await, ["2", "3"]);
+        assert!(take(&store, "alice").await.is_empty());
+        assert_eq!(take(&store, "bob").await, ["4"]);
+    }
+
+    #[tokio::test]
+    async fn memory_store_expires_messages() {
+        let store = MemoryOfflineStore::new(Duration::ZERO, 10);
+        store.push(message("alice", "1")).await;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(take(&store, "alice").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn memory_store_sweeps_other_queues() {
+        let store = MemoryOfflineStore::new(Duration::from_millis(20), 10);
+        store.push(message("alice", "1")).await;
+        store.push(message("bob", "2")).await;
+        assert_eq!(store.queues.lock().unwrap().queues.len(), 2);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        // Pushing for bob sweeps the expired queue of alice
+        store.push(message("bob", "3")).await;
+        let queues = store.queues.lock().unwrap();
+        assert_eq!(queues.queues.len(), 1);
+        assert_eq!(queues.queues.values().next().unwrap().len(), 1);
+    }
+}
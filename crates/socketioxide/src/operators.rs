@@ -8,8 +8,9 @@
 //! * [`BroadcastOperators`]: Chainable operators to select sockets to send a message to and to configure the message to be sent.
 use std::{future::Future, sync::Arc, time::Duration};
 
+use bytes::Bytes;
 use engineioxide::sid::Sid;
-use serde::Serialize;
+use serde::{ser::SerializeTuple, Serialize, Serializer};
 
 use crate::{
     ack::{AckInnerStream, AckStream},
@@ -22,32 +23,46 @@ use crate::{
 };
 
 use socketioxide_core::{
-    adapter::{BroadcastFlags, BroadcastOptions, Room, RoomParam},
+    adapter::{BroadcastFlags, BroadcastOptions, RequestOptions, Room, RoomExpiry, RoomParam},
     packet::Packet,
     parser::{Parse, ParserError},
+    Value,
 };
 
 /// Chainable operators to configure the message to be sent.
 pub struct ConfOperators<'a, A: Adapter = LocalAdapter> {
     timeout: Option<Duration>,
+    volatile: bool,
+    bins: Vec<Bytes>,
     socket: &'a Socket<A>,
 }
 /// Chainable operators to select sockets to send a message to and to configure the message to be sent.
 pub struct BroadcastOperators<A: Adapter = LocalAdapter> {
     timeout: Option<Duration>,
+    allow_partial: bool,
+    bins: Vec<Bytes>,
     ns: Arc<Namespace<A>>,
     parser: Parser,
     opts: BroadcastOptions,
+    user: Option<String>,
+    persistent: bool,
 }
 
 impl<A: Adapter> From<ConfOperators<'_, A>> for BroadcastOperators<A> {
     fn from(conf: ConfOperators<'_, A>) -> Self {
-        let opts = BroadcastOptions::new(conf.socket.id);
+        let mut opts = BroadcastOptions::new(conf.socket.id);
+        if conf.volatile {
+            opts.add_flag(BroadcastFlags::Volatile);
+        }
         Self {
             timeout: conf.timeout,
+            allow_partial: false,
+            bins: conf.bins,
             ns: conf.socket.ns.clone(),
             parser: conf.socket.parser,
             opts,
+            user: None,
+            persistent: false,
         }
     }
 }
@@ -57,6 +72,8 @@ impl<'a, A: Adapter> ConfOperators<'a, A> {
     pub(crate) fn new(sender: &'a Socket<A>) -> Self {
         Self {
             timeout: None,
+            volatile: false,
+            bins: Vec::new(),
             socket: sender,
         }
     }
@@ -78,7 +95,7 @@ impl<'a, A: Adapter> ConfOperators<'a, A> {
 
     #[doc = include_str!("../docs/operators/local.md")]
     pub fn local(self) -> BroadcastOperators<A> {
-        BroadcastOperators::from(self).local()
+        BroadcastOperators::from(self).broadcast().local()
     }
 
     #[doc = include_str!("../docs/operators/broadcast.md")]
@@ -91,6 +108,18 @@ impl<'a, A: Adapter> ConfOperators<'a, A> {
         self.timeout = Some(timeout);
         self
     }
+
+    #[doc = include_str!("../docs/operators/volatile.md")]
+    pub fn volatile(mut self) -> Self {
+        self.volatile = true;
+        self
+    }
+
+    #[doc = include_str!("../docs/operators/bin.md")]
+    pub fn bin(mut self, bins: impl IntoIterator<Item = impl Into<Bytes>>) -> Self {
+        self.bins.extend(bins.into_iter().map(Into::into));
+        self
+    }
 }
 
 // ==== impl ConfOperators consume fns ====
@@ -103,19 +132,30 @@ impl<A: Adapter> ConfOperators<'_, A> {
     ) -> Result<(), SendError> {
         use crate::socket::PermitExt;
         use crate::SocketError;
+        use engineioxide::PacketPriority;
         if !self.socket.connected() {
             return Err(SendError::Socket(SocketError::Closed));
         }
-        let permit = match self.socket.reserve() {
+        if self.volatile && self.socket.is_upgrading() {
+            return Ok(());
+        }
+        let data = self.get_data(event, data)?;
+        let priority = if self.volatile {
+            PacketPriority::Low
+        } else {
+            PacketPriority::Normal
+        };
+        let permit = match self.socket.reserve_with_priority(priority) {
             Ok(permit) => permit,
+            Err(SocketError::InternalChannelFull) if self.volatile => return Ok(()),
             Err(e) => {
                 #[cfg(feature = "tracing")]
                 tracing::debug!("sending error during emit message: {e:?}");
-                return Err(SendError::Socket(e));
+                return Err(SendError::with_payload(e, data));
             }
         };
-        let packet = self.get_packet(event, data)?;
-        permit.send(packet, self.socket.parser);
+        let packet = Packet::event(self.socket.ns.path.clone(), data);
+        permit.send(packet, self.socket);
 
         Ok(())
     }
@@ -130,21 +170,24 @@ impl<A: Adapter> ConfOperators<'_, A> {
         if !self.socket.connected() {
             return Err(SendError::Socket(SocketError::Closed));
         }
+        let data = self.get_data(event, data)?;
         let permit = match self.socket.reserve() {
             Ok(permit) => permit,
             Err(e) => {
                 #[cfg(feature = "tracing")]
                 tracing::debug!("sending error during emit message: {e:?}");
-                return Err(SendError::Socket(e));
+                return Err(SendError::with_payload(e, data));
             }
         };
-        let timeout = self
-            .timeout
-            .unwrap_or_else(|| self.socket.get_io().config().ack_timeout);
-        let packet = self.get_packet(event, data)?;
+        let timeout = self.timeout.unwrap_or(self.socket.ns.ack_timeout);
+        let packet = Packet::event(self.socket.ns.path.clone(), data);
         let rx = self.socket.send_with_ack_permit(packet, permit);
         let stream = AckInnerStream::send(rx, timeout, self.socket.id);
-        Ok(AckStream::<V>::new(stream, self.socket.parser))
+        Ok(AckStream::<V>::new(
+            stream,
+            self.socket.parser,
+            [self.socket.id],
+        ))
     }
 
     #[doc = include_str!("../docs/operators/join.md")]
@@ -158,15 +201,20 @@ impl<A: Adapter> ConfOperators<'_, A> {
     }
 
     /// Creates a packet with the given event and data.
-    fn get_packet<T: ?Sized + Serialize>(
+    fn get_data<T: ?Sized + Serialize>(
         &mut self,
         event: impl AsRef<str>,
         data: &T,
-    ) -> Result<Packet, ParserError> {
-        let ns = self.socket.ns.path.clone();
-        let event = event.as_ref();
-        let data = self.socket.parser.encode_value(&data, Some(event))?;
-        Ok(Packet::event(ns, data))
+    ) -> Result<Value, ParserError> {
+        let event = Some(event.as_ref());
+        if self.bins.is_empty() {
+            self.socket.parser.encode_value(&data, event)
+        } else {
+            let bins = std::mem::take(&mut self.bins);
+            self.socket
+                .parser
+                .encode_value(&WithBin(data, &bins), event)
+        }
     }
 }
 
@@ -174,17 +222,25 @@ impl<A: Adapter> BroadcastOperators<A> {
     pub(crate) fn new(ns: Arc<Namespace<A>>, parser: Parser) -> Self {
         Self {
             timeout: None,
+            allow_partial: false,
+            bins: Vec::new(),
             ns,
             parser,
             opts: BroadcastOptions::default(),
+            user: None,
+            persistent: false,
         }
     }
     pub(crate) fn from_sock(ns: Arc<Namespace<A>>, sid: Sid, parser: Parser) -> Self {
         Self {
             timeout: None,
+            allow_partial: false,
+            bins: Vec::new(),
             ns,
             parser,
             opts: BroadcastOptions::new(sid),
+            user: None,
+            persistent: false,
         }
     }
 
@@ -200,6 +256,19 @@ impl<A: Adapter> BroadcastOperators<A> {
         self
     }
 
+    #[doc = include_str!("../docs/operators/to_socket.md")]
+    pub fn to_socket(mut self, sid: Sid) -> Self {
+        let mut opts = BroadcastOptions::new(sid);
+        for flag in [BroadcastFlags::Local, BroadcastFlags::Volatile] {
+            if self.opts.has_flag(flag) {
+                opts.add_flag(flag);
+            }
+        }
+        self.opts = opts;
+        self.user = None;
+        self
+    }
+
     #[doc = include_str!("../docs/operators/except.md")]
     pub fn except(mut self, rooms: impl RoomParam) -> Self {
         self.opts.except.extend(rooms.into_room_iter());
@@ -223,6 +292,44 @@ impl<A: Adapter> BroadcastOperators<A> {
         self.timeout = Some(timeout);
         self
     }
+
+    #[doc = include_str!("../docs/operators/allow_partial.md")]
+    pub fn allow_partial(mut self) -> Self {
+        self.allow_partial = true;
+        self
+    }
+
+    #[doc = include_str!("../docs/operators/volatile.md")]
+    pub fn volatile(mut self) -> Self {
+        self.opts.add_flag(BroadcastFlags::Volatile);
+        self
+    }
+
+    #[doc = include_str!("../docs/operators/bin.md")]
+    pub fn bin(mut self, bins: impl IntoIterator<Item = impl Into<Bytes>>) -> Self {
+        self.bins.extend(bins.into_iter().map(Into::into));
+        self
+    }
+
+    #[doc = include_str!("../docs/operators/persistent.md")]
+    pub fn persistent(mut self) -> Self {
+        self.persistent = true;
+        self
+    }
+
+    /// Select the sockets of the given user, tracked with the given room.
+    pub(crate) fn with_user(mut self, user: String, room: Room) -> Self {
+        self.user = Some(user);
+        self.within(room)
+    }
+
+    /// The user selected by the operators, if they only select the sockets of this user.
+    fn persistent_user(&self) -> Option<&str> {
+        match &self.user {
+            Some(user) if self.opts.rooms.len() == 1 => Some(user),
+            _ => None,
+        }
+    }
 }
 
 // ==== impl BroadcastOperators consume fns ====
@@ -235,15 +342,26 @@ impl<A: Adapter> BroadcastOperators<A> {
     ) -> impl Future<Output = Result<(), BroadcastError>> + Send {
         let packet = self.get_packet(event, data);
         async move {
-            self.ns
-                .adapter
-                .broadcast(packet?, self.opts)
-                .await
-                .map_err(|e| {
-                    #[cfg(feature = "tracing")]
-                    tracing::debug!("broadcast error: {e}");
-                    e
-                })?;
+            let mut packet = packet?;
+            if self.persistent {
+                let user = self
+                    .persistent_user()
+                    .ok_or(BroadcastError::InvalidPersistent)?;
+                if self
+                    .ns
+                    .queue_offline_packet(user, &packet, &self.opts)
+                    .await?
+                {
+                    return Ok(());
+                }
+            }
+            self.ns.persist_packet(&mut packet, &self.opts).await?;
+            let res = self.ns.adapter.broadcast(packet, self.opts).await;
+            #[cfg(feature = "tracing")]
+            if let Err(e) = &res {
+                tracing::debug!("broadcast error: {e}");
+            }
+            res?;
             Ok(())
         }
     }
@@ -256,13 +374,14 @@ impl<A: Adapter> BroadcastOperators<A> {
     ) -> impl Future<Output = Result<AckStream<V, A>, EmitWithAckError>> + Send {
         let packet = self.get_packet(event, data);
         async move {
+            let targets = self.ns.adapter.get_local().sockets(self.opts.clone());
             let stream = self
                 .ns
                 .adapter
                 .broadcast_with_ack(packet?, self.opts, self.timeout)
                 .await
                 .map_err(|e| EmitWithAckError::Adapter(Box::new(e)))?;
-            Ok(AckStream::new(stream, self.parser))
+            Ok(AckStream::new(stream, self.parser, targets))
         }
     }
 
@@ -289,6 +408,12 @@ impl<A: Adapter> BroadcastOperators<A> {
         Ok(sockets)
     }
 
+    #[doc = include_str!("../docs/operators/fetch_socket.md")]
+    pub async fn fetch_socket(self, sid: Sid) -> Result<Option<RemoteSocket<A>>, A::Error> {
+        let sockets = self.to_socket(sid).fetch_sockets().await?;
+        Ok(sockets.into_iter().next())
+    }
+
     #[doc = include_str!("../docs/operators/disconnect.md")]
     pub async fn disconnect(self) -> Result<(), BroadcastError> {
         self.ns.adapter.disconnect_socket(self.opts).await
@@ -306,9 +431,24 @@ impl<A: Adapter> BroadcastOperators<A> {
         async move { self.ns.adapter.del_sockets(self.opts, rooms).await }
     }
 
+    #[doc = include_str!("../docs/operators/set_expiry.md")]
+    pub fn set_expiry(self, expiry: RoomExpiry) {
+        self.ns
+            .adapter
+            .get_local()
+            .set_expiry(self.opts.rooms.into_vec(), expiry);
+    }
+
     #[doc = include_str!("../docs/operators/rooms.md")]
     pub async fn rooms(self) -> Result<Vec<Room>, A::Error> {
-        self.ns.adapter.rooms(self.opts).await
+        let req_opts = self.req_opts();
+        self.ns.adapter.rooms(self.opts, req_opts).await
+    }
+
+    #[doc = include_str!("../docs/operators/sockets_count.md")]
+    pub async fn sockets_count(self) -> Result<usize, A::Error> {
+        let req_opts = self.req_opts();
+        self.ns.adapter.sockets_count(self.opts, req_opts).await
     }
 
     #[doc = include_str!("../docs/operators/get_socket.md")]
@@ -316,14 +456,45 @@ impl<A: Adapter> BroadcastOperators<A> {
         self.ns.get_socket(sid).map(SocketRef::from).ok()
     }
 
-    /// Creates a packet with the given event and data.
+    /// The options of the requests aggregating the responses of all the servers.
+    fn req_opts(&self) -> RequestOptions {
+        RequestOptions {
+            timeout: self.timeout,
+            allow_partial: self.allow_partial,
+        }
+    }
+
+    /// Creates a packet with the given event and data and calls the outbound interceptors with it.
     fn get_packet<T: ?Sized + Serialize>(
         &mut self,
         event: impl AsRef<str>,
         data: &T,
     ) -> Result<Packet, ParserError> {
         let ns = self.ns.path.clone();
-        let data = self.parser.encode_value(data, Some(event.as_ref()))?;
-        Ok(Packet::event(ns, data))
+        let event = Some(event.as_ref());
+        let data = if self.bins.is_empty() {
+            self.parser.encode_value(data, event)?
+        } else {
+            let bins = std::mem::take(&mut self.bins);
+            self.parser.encode_value(&WithBin(data, &bins), event)?
+        };
+        let mut packet = Packet::event(ns, data);
+        self.ns.interceptors.outbound(&mut packet);
+        Ok(packet)
+    }
+}
+
+/// The event arguments: a data argument followed by binary attachments.
+/// It is serialized as a tuple so that the parsers send each element as an argument of the event.
+struct WithBin<'a, T: ?Sized>(&'a T, &'a [Bytes]);
+
+impl<T: ?Sized + Serialize> Serialize for WithBin<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(1 + self.1.len())?;
+        tuple.serialize_element(self.0)?;
+        for bin in self.1 {
+            tuple.serialize_element(bin)?;
+        }
+        tuple.end()
     }
 }
@@ -0,0 +1,66 @@
+//! The socket.io [`Packet`] model and the packet interceptors.
+//!
+//! Interceptors are registered on the [`SocketIo`](crate::SocketIo) instance with
+//! [`SocketIo::intercept_inbound`](crate::SocketIo::intercept_inbound) and
+//! [`SocketIo::intercept_outbound`](crate::SocketIo::intercept_outbound).
+//! They are called with every packet of every namespace and can observe or mutate it:
+//! * Inbound interceptors are called with each packet received from a client, once it is decoded
+//!   and before it is dispatched to its namespace or socket.
+//! * Outbound interceptors are called with each packet sent to a client, before it is encoded.
+//!   A broadcasted packet is intercepted once, before being passed to the adapter.
+//!
+//! They are called synchronously on the hot path, in their registration order,
+//! so they should be cheap (e.g. field redaction, encryption or auditing).
+//!
+//! **Note**: Registering an interceptor from an interceptor will deadlock.
+use std::fmt;
+use std::sync::RwLock;
+
+pub use socketioxide_core::packet::{ConnectPacket, Packet, PacketData};
+
+/// A packet interceptor registered with
+/// [`SocketIo::intercept_inbound`](crate::SocketIo::intercept_inbound) or
+/// [`SocketIo::intercept_outbound`](crate::SocketIo::intercept_outbound).
+type BoxedInterceptor = Box<dyn Fn(&mut Packet) + Send + Sync + 'static>;
+
+/// The inbound and outbound interceptors of a server.
+#[derive(Default)]
+pub(crate) struct Interceptors {
+    inbound: RwLock<Vec<BoxedInterceptor>>,
+    outbound: RwLock<Vec<BoxedInterceptor>>,
+}
+
+impl Interceptors {
+    pub fn add_inbound(&self, interceptor: BoxedInterceptor) {
+        self.inbound.write().unwrap().push(interceptor);
+    }
+    pub fn add_outbound(&self, interceptor: BoxedInterceptor) {
+        self.outbound.write().unwrap().push(interceptor);
+    }
+
+    /// Call the inbound interceptors with a packet received from a client.
+    pub fn inbound(&self, packet: &mut Packet) {
+        Self::intercept(&self.inbound, packet);
+    }
+    /// Call the outbound interceptors with a packet about to be sent.
+    pub fn outbound(&self, packet: &mut Packet) {
+        Self::intercept(&self.outbound, packet);
+    }
+
+    fn intercept(interceptors: &RwLock<Vec<BoxedInterceptor>>, packet: &mut Packet) {
+        interceptors
+            .read()
+            .unwrap()
+            .iter()
+            .for_each(|interceptor| interceptor(packet));
+    }
+}
+
+impl fmt::Debug for Interceptors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interceptors")
+            .field("inbound", &self.inbound.read().unwrap().len())
+            .field("outbound", &self.outbound.read().unwrap().len())
+            .finish()
+    }
+}
@@ -23,7 +23,7 @@ pub use socketioxide_core::parser::ParserError;
 /// It also implements the [`Parse`] trait and therefore the
 /// parser implementation is done over enum delegation.
 #[non_exhaustive]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Parser {
     /// The default parser
     Common(CommonParser),
@@ -138,4 +138,12 @@ impl Parse for Parser {
             Parser::MsgPack(p) => p.read_event(value),
         }
     }
+
+    fn push_offset(self, value: &mut Value, offset: &str) -> Result<(), ParserError> {
+        match self {
+            Parser::Common(p) => p.push_offset(value, offset),
+            #[cfg(feature = "msgpack")]
+            Parser::MsgPack(p) => p.push_offset(value, offset),
+        }
+    }
 }
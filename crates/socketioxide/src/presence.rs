@@ -0,0 +1,232 @@
+//! Presence tracking built on rooms.
+//!
+//! When enabled with [`SocketIoBuilder::with_presence`](crate::SocketIoBuilder::with_presence),
+//! the server tracks which sockets are in which rooms and notifies the interested clients:
+//! * A socket can attach metadata to its presence (e.g. a user name or a status)
+//!   with [`Socket::set_presence_meta`](crate::socket::Socket::set_presence_meta).
+//! * The current members of a room and their metadata can be retrieved with
+//!   [`SocketIo::presence`](crate::SocketIo::presence). Members are aggregated across all the servers
+//!   through the adapter.
+//! * A socket can subscribe to the presence of a room with
+//!   [`Socket::subscribe_presence`](crate::socket::Socket::subscribe_presence). It will then receive
+//!   a [`PresenceDelta`] each time a socket joins or leaves the room or updates its metadata.
+//!   The event is emitted with the room name and the delta as arguments.
+//!
+//! Subscribers are themselves put in a dedicated room (the room name prefixed with
+//! [`PresenceConfig::room_prefix`]), so that deltas are broadcasted through the adapter
+//! to the subscribers connected on other servers. The presence of these rooms is not tracked.
+//!
+//! **Note**: Deltas are emitted asynchronously, after the room change.
+//!
+//! # Example
+//! ```
+//! # use socketioxide::{SocketIo, extract::*, presence::PresenceConfig};
+//! #[derive(Debug, serde::Serialize, serde::Deserialize)]
+//! struct User {
+//!     name: String,
+//! }
+//! let (_, io) = SocketIo::builder()
+//!     .with_presence(PresenceConfig::default())
+//!     .build_svc();
+//!
+//! io.ns("/", |socket: SocketRef, Data(user): Data<User>| {
+//!     socket.set_presence_meta(user).ok();
+//!     socket.subscribe_presence("lobby");
+//!     socket.join("lobby");
+//!
+//!     socket.on("who", |io: SocketIo| async move {
+//!         let members = io.presence::<User>("lobby").await.unwrap();
+//!         for member in members {
+//!             println!("{} is in the lobby: {:?}", member.id, member.meta);
+//!         }
+//!     });
+//! });
+//! ```
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use socketioxide_core::{adapter::Room, parser::Parse, Sid, Uid, Value};
+
+use crate::parser::{Parser, ParserError};
+
+/// Configuration for the presence subsystem.
+#[derive(Debug, Clone)]
+pub struct PresenceConfig {
+    pub(crate) event: Cow<'static, str>,
+    pub(crate) room_prefix: Cow<'static, str>,
+}
+
+impl PresenceConfig {
+    /// The event used to emit the [`PresenceDelta`]s to the subscribers.
+    ///
+    /// Defaults to `"presence"`.
+    pub fn event(mut self, event: impl Into<Cow<'static, str>>) -> Self {
+        self.event = event.into();
+        self
+    }
+
+    /// The prefix of the rooms joined by the subscribers of a room presence.
+    ///
+    /// Defaults to `"presence:"`.
+    pub fn room_prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        self.room_prefix = prefix.into();
+        self
+    }
+
+    /// The room joined by the subscribers of the given room.
+    pub(crate) fn subscribers_room(&self, room: &str) -> Room {
+        Cow::Owned(format!("{}{}", self.room_prefix, room))
+    }
+
+    /// Check if the given room is a subscribers room, which presence is not tracked.
+    pub(crate) fn is_subscribers_room(&self, room: &str) -> bool {
+        room.starts_with(self.room_prefix.as_ref())
+    }
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            event: Cow::Borrowed("presence"),
+            room_prefix: Cow::Borrowed("presence:"),
+        }
+    }
+}
+
+/// A presence change of a room, emitted to the subscribers of the room.
+///
+/// It is serialized as an object with a `type` field: `"join"`, `"update"` or `"leave"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PresenceDelta<T> {
+    /// A socket joined the room.
+    Join {
+        /// The id of the socket.
+        id: Sid,
+        /// The presence metadata of the socket, if any.
+        meta: Option<T>,
+    },
+    /// A socket in the room updated its presence metadata.
+    Update {
+        /// The id of the socket.
+        id: Sid,
+        /// The new presence metadata of the socket.
+        meta: Option<T>,
+    },
+    /// A socket left the room.
+    Leave {
+        /// The id of the socket.
+        id: Sid,
+    },
+}
+
+/// The kind of a [`PresenceDelta`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DeltaKind {
+    Join,
+    Update,
+    Leave,
+}
+
+impl<T> PresenceDelta<T> {
+    fn new(kind: DeltaKind, id: Sid, meta: Option<T>) -> Self {
+        match kind {
+            DeltaKind::Join => PresenceDelta::Join { id, meta },
+            DeltaKind::Update => PresenceDelta::Update { id, meta },
+            DeltaKind::Leave => PresenceDelta::Leave { id },
+        }
+    }
+}
+
+/// A member of a room, returned by [`SocketIo::presence`](crate::SocketIo::presence).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresenceMember<T> {
+    /// The id of the socket.
+    pub id: Sid,
+    /// The server id this socket is connected to.
+    pub server_id: Uid,
+    /// The presence metadata of the socket, if any.
+    pub meta: Option<T>,
+}
+
+/// The type erased presence metadata of a socket.
+pub(crate) trait PresenceMeta: Send + Sync + 'static {
+    /// Encode the metadata to be shared with the other servers.
+    fn encode(&self, parser: Parser) -> Result<Value, ParserError>;
+    /// Encode a presence event with this metadata.
+    fn encode_delta(
+        &self,
+        parser: Parser,
+        event: &str,
+        room: &Room,
+        kind: DeltaKind,
+        id: Sid,
+    ) -> Result<Value, ParserError>;
+}
+
+impl<T: Serialize + Send + Sync + 'static> PresenceMeta for T {
+    fn encode(&self, parser: Parser) -> Result<Value, ParserError> {
+        parser.encode_default(self)
+    }
+    fn encode_delta(
+        &self,
+        parser: Parser,
+        event: &str,
+        room: &Room,
+        kind: DeltaKind,
+        id: Sid,
+    ) -> Result<Value, ParserError> {
+        encode_delta(
+            parser,
+            event,
+            room,
+            PresenceDelta::new(kind, id, Some(self)),
+        )
+    }
+}
+
+/// Encode a presence event of a socket without metadata.
+pub(crate) fn encode_empty_delta(
+    parser: Parser,
+    event: &str,
+    room: &Room,
+    kind: DeltaKind,
+    id: Sid,
+) -> Result<Value, ParserError> {
+    encode_delta::<()>(parser, event, room, PresenceDelta::new(kind, id, None))
+}
+
+fn encode_delta<T: Serialize>(
+    parser: Parser,
+    event: &str,
+    room: &Room,
+    delta: PresenceDelta<T>,
+) -> Result<Value, ParserError> {
+    parser.encode_value(&(room, delta), Some(event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscribers_room() {
+        let config = PresenceConfig::default().room_prefix("p/");
+        assert_eq!(config.subscribers_room("room1"), "p/room1");
+        assert!(config.is_subscribers_room("p/room1"));
+        assert!(!config.is_subscribers_room("room1"));
+    }
+
+    #[test]
+    fn delta_format() {
+        let id = Sid::new();
+        let room = Room::Borrowed("room1");
+        let data = "meta".encode_delta(Parser::default(), "ev", &room, DeltaKind::Join, id);
+        let expected = format!(r#"["ev","room1",{{"type":"join","id":"{id}","meta":"meta"}}]"#);
+        assert_eq!(data.unwrap(), Value::Str(expected.into(), None));
+
+        let data = encode_empty_delta(Parser::default(), "ev", &room, DeltaKind::Leave, id);
+        let expected = format!(r#"["ev","room1",{{"type":"leave","id":"{id}"}}]"#);
+        assert_eq!(data.unwrap(), Value::Str(expected.into(), None));
+    }
+}
@@ -0,0 +1,76 @@
+//! Rate limiting of the events received by a socket in a namespace.
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+use engineioxide::rate_limit::{RateLimit, RateLimitPolicy, TokenBucket};
+use socketioxide_core::Value;
+use tokio::sync::mpsc;
+
+use crate::{adapter::Adapter, socket::Socket};
+
+type QueuedEvent = (Value, Option<i64>);
+
+/// The rate limiter of the events received by a socket.
+pub(crate) struct EventRateLimiter {
+    bucket: Arc<Mutex<TokenBucket>>,
+    pub policy: RateLimitPolicy,
+    per_second: u32,
+    /// The queue of the delayed events with the [`RateLimitPolicy::Queue`] policy.
+    /// Its worker task is spawned with the first event.
+    queue: OnceLock<mpsc::Sender<QueuedEvent>>,
+}
+
+impl EventRateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            bucket: Arc::new(Mutex::new(TokenBucket::new(limit.per_second))),
+            policy: limit.policy,
+            per_second: limit.per_second,
+            queue: OnceLock::new(),
+        }
+    }
+
+    /// Take a token from the bucket, returns false if the limit is exceeded.
+    pub fn try_acquire(&self) -> bool {
+        self.bucket.lock().unwrap().try_acquire().is_ok()
+    }
+
+    /// Queue an event so that it is handled when the rate limit allows it.
+    /// Events are always queued, even when there are tokens left, so that they stay ordered.
+    ///
+    /// The queue holds at most `2 * per_second` events: a full burst of the bucket
+    /// and one second of delayed events. The next ones are dropped.
+    pub fn enqueue<A: Adapter>(&self, socket: &Arc<Socket<A>>, data: Value, ack: Option<i64>) {
+        let tx = self.queue.get_or_init(|| {
+            let (tx, rx) = mpsc::channel(2 * self.per_second as usize);
+            tokio::spawn(run_queue(Arc::downgrade(socket), self.bucket.clone(), rx));
+            tx
+        });
+        if tx.try_send((data, ack)).is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(?socket.id, "rate limit queue full, dropping event");
+        }
+    }
+}
+
+/// Handle the queued events of a socket at the rate of the token bucket.
+/// It stops when the socket is dropped.
+async fn run_queue<A: Adapter>(
+    socket: Weak<Socket<A>>,
+    bucket: Arc<Mutex<TokenBucket>>,
+    mut rx: mpsc::Receiver<QueuedEvent>,
+) {
+    while let Some((data, ack)) = rx.recv().await {
+        loop {
+            let res = bucket.lock().unwrap().try_acquire();
+            match res {
+                Ok(()) => break,
+                Err(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+        match socket.upgrade() {
+            Some(socket) if socket.connected() => socket.handle_event(data, ack),
+            Some(_) => (),
+            None => break,
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Connection state recovery related types.
+//!
+//! When enabled with [`SocketIoBuilder::with_connection_state_recovery`](crate::SocketIoBuilder),
+//! the server will persist the rooms of a socket that has been disconnected for a recoverable reason
+//! (e.g. network loss, ping timeout...) as well as all the broadcasted packets,
+//! for a configurable amount of time.
+//!
+//! When the client reconnects with a valid session id and the offset of the last received packet,
+//! the socket rooms are restored and all the missed packets are sent to the client.
+//! The socket can then check whether it has been recovered with [`Socket::recovered`](crate::socket::Socket::recovered).
+//!
+//! The [`SessionStore`] trait can be implemented to persist sessions and packets
+//! in an external store. By default, a [`MemoryStore`] is used.
+//!
+//! **Note**: Only packets emitted with [`BroadcastOperators`](crate::operators::BroadcastOperators)
+//! (e.g. `io.emit()`, `socket.broadcast().emit()`, `socket.to("room").emit()`) are persisted.
+//! A packet directly emitted to a single socket with `socket.emit()` is not.
+//!
+//! **Note**: Because the socket id is bound to the underlying engine.io session, a recovered socket
+//! will have a new [`Sid`]. The private session id used for recovery stays the same.
+//!
+//! # Example
+//! ```
+//! # use socketioxide::{SocketIo, extract::*, recovery::RecoveryConfig};
+//! # use std::time::Duration;
+//! let (_, io) = SocketIo::builder()
+//!     .with_connection_state_recovery(RecoveryConfig::new(Duration::from_secs(120)))
+//!     .build_svc();
+//!
+//! io.ns("/", |socket: SocketRef| {
+//!     if socket.recovered() {
+//!         println!("socket {} recovered its session", socket.id);
+//!     } else {
+//!         socket.join("room1");
+//!     }
+//! });
+//! ```
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures_util::future::{self, BoxFuture};
+use serde::Deserialize;
+use socketioxide_core::{
+    adapter::{BroadcastFlags, BroadcastOptions, Room},
+    Sid, Str, Value,
+};
+
+/// A recoverable session of a socket that was disconnected from a namespace.
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// The private session id of the socket.
+    pub pid: Sid,
+    /// The id of the socket when it was disconnected.
+    pub sid: Sid,
+    /// The namespace of the socket.
+    pub ns: Str,
+    /// The rooms the socket was in when it was disconnected.
+    pub rooms: Vec<Room>,
+    /// The packets that the socket missed while it was disconnected.
+    /// It is only set when the session is restored.
+    pub missed_packets: Vec<Value>,
+}
+
+/// A broadcasted packet persisted for connection state recovery.
+#[derive(Debug, Clone)]
+pub struct PersistedPacket {
+    /// The unique offset of the packet. It is also appended as the last argument of the event.
+    pub offset: String,
+    /// The namespace the packet was broadcasted to.
+    pub ns: Str,
+    /// The broadcast options used to emit the packet.
+    pub opts: BroadcastOptions,
+    /// The event payload, including the event name and the offset.
+    pub data: Value,
+}
+
+impl PersistedPacket {
+    /// Check if this packet should have been received by the socket of the given session.
+    pub fn matches(&self, session: &Session) -> bool {
+        if self.ns != session.ns {
+            return false;
+        }
+        if self.opts.has_flag(BroadcastFlags::Broadcast) && self.opts.sid == Some(session.sid) {
+            return false;
+        }
+        let included =
+            self.opts.rooms.is_empty() || self.opts.rooms.iter().any(|r| session.rooms.contains(r));
+        let excluded = self.opts.except.iter().any(|r| session.rooms.contains(r));
+        included && !excluded
+    }
+}
+
+/// A store used to persist sessions and packets for connection state recovery.
+///
+/// Implementations are responsible for discarding expired sessions and packets.
+pub trait SessionStore: fmt::Debug + Send + Sync + 'static {
+    /// Persist the session of a disconnected socket.
+    fn persist_session(&self, session: Session) -> BoxFuture<'_, ()>;
+
+    /// Persist a broadcasted packet.
+    fn persist_packet(&self, packet: PersistedPacket) -> BoxFuture<'_, ()>;
+
+    /// Restore and remove a session with its private id and the offset of the last packet
+    /// received by the client. The returned session should contain all the packets emitted after
+    /// the given offset that [match](PersistedPacket::matches) the session.
+    ///
+    /// If the session has expired or if the offset is unknown, `None` should be returned.
+    fn restore_session(&self, pid: Sid, offset: &str) -> BoxFuture<'_, Option<Session>>;
+}
+
+/// The default in-memory [`SessionStore`].
+pub struct MemoryStore {
+    max_disconnection_duration: Duration,
+    sessions: Mutex<HashMap<Sid, (Instant, Session)>>,
+    packets: Mutex<VecDeque<(Instant, PersistedPacket)>>,
+}
+
+impl MemoryStore {
+    /// Create a new in-memory store that keeps sessions and packets for the given duration.
+    pub fn new(max_disconnection_duration: Duration) -> Self {
+        Self {
+            max_disconnection_duration,
+            sessions: Mutex::new(HashMap::new()),
+            packets: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn prune(&self, now: Instant) {
+        let is_expired = |t: &Instant| now.duration_since(*t) > self.max_disconnection_duration;
+        self.sessions
+            .lock()
+            .unwrap()
+            .retain(|_, (t, _)| !is_expired(t));
+        let mut packets = self.packets.lock().unwrap();
+        while packets.front().is_some_and(|(t, _)| is_expired(t)) {
+            packets.pop_front();
+        }
+    }
+}
+
+impl fmt::Debug for MemoryStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryStore")
+            .field(
+                "max_disconnection_duration",
+                &self.max_disconnection_duration,
+            )
+            .field("sessions", &self.sessions.lock().unwrap().len())
+            .field("packets", &self.packets.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn persist_session(&self, session: Session) -> BoxFuture<'_, ()> {
+        let now = Instant::now();
+        self.prune(now);
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session.pid, (now, session));
+        Box::pin(future::ready(()))
+    }
+
+    fn persist_packet(&self, packet: PersistedPacket) -> BoxFuture<'_, ()> {
+        let now = Instant::now();
+        self.prune(now);
+        self.packets.lock().unwrap().push_back((now, packet));
+        Box::pin(future::ready(()))
+    }
+
+    fn restore_session(&self, pid: Sid, offset: &str) -> BoxFuture<'_, Option<Session>> {
+        self.prune(Instant::now());
+        let session = self.sessions.lock().unwrap().remove(&pid).map(|(_, s)| s);
+        let session = session.and_then(|mut session| {
+            let packets = self.packets.lock().unwrap();
+            let idx = packets.iter().position(|(_, p)| p.offset == offset)?;
+            session.missed_packets = packets
+                .iter()
+                .skip(idx + 1)
+                .filter(|(_, p)| p.matches(&session))
+                .map(|(_, p)| p.data.clone())
+                .collect();
+            Some(session)
+        });
+        Box::pin(future::ready(session))
+    }
+}
+
+/// Configuration for the connection state recovery feature.
+#[derive(Debug, Clone)]
+pub struct RecoveryConfig {
+    pub(crate) store: Arc<dyn SessionStore>,
+    pub(crate) skip_middlewares: bool,
+}
+
+impl RecoveryConfig {
+    /// Create a new recovery config with an in-memory [`MemoryStore`].
+    /// Sessions and packets will be kept for the given `max_disconnection_duration`.
+    pub fn new(max_disconnection_duration: Duration) -> Self {
+        Self::with_store(MemoryStore::new(max_disconnection_duration))
+    }
+
+    /// Create a new recovery config with a custom [`SessionStore`].
+    pub fn with_store(store: impl SessionStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            skip_middlewares: true,
+        }
+    }
+
+    /// Whether the connect middlewares should be skipped upon successful recovery.
+    ///
+    /// Defaults to `true`.
+    pub fn skip_middlewares(mut self, skip_middlewares: bool) -> Self {
+        self.skip_middlewares = skip_middlewares;
+        self
+    }
+}
+
+impl Default for RecoveryConfig {
+    /// A recovery config with a [`MemoryStore`] that keeps sessions for 2 minutes.
+    fn default() -> Self {
+        Self::new(Duration::from_secs(120))
+    }
+}
+
+/// The recovery data sent by the client in the auth payload of the connect packet.
+#[derive(Debug, Deserialize)]
+pub(crate) struct RecoveryAuth {
+    pub pid: Sid,
+    pub offset: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(rooms: &[&'static str]) -> Session {
+        Session {
+            pid: Sid::new(),
+            sid: Sid::new(),
+            ns: Str::from("/"),
+            rooms: rooms.iter().map(|r| Room::from(*r)).collect(),
+            missed_packets: Vec::new(),
+        }
+    }
+    fn packet(offset: &str, opts: BroadcastOptions) -> PersistedPacket {
+        PersistedPacket {
+            offset: offset.to_string(),
+            ns: Str::from("/"),
+            opts,
+            data: Value::Str(Str::from(format!("[\"event\",\"{offset}\"]")), None),
+        }
+    }
+
+    #[test]
+    fn packet_matches() {
+        let s = session(&["room1", "room2"]);
+        assert!(packet("1", BroadcastOptions::default()).matches(&s));
+
+        let mut opts = BroadcastOptions::default();
+        opts.rooms.push("room1".into());
+        assert!(packet("1", opts).matches(&s));
+
+        let mut opts = BroadcastOptions::default();
+        opts.rooms.push("room3".into());
+        assert!(!packet("1", opts).matches(&s));
+
+        let mut opts = BroadcastOptions::default();
+        opts.except.push("room2".into());
+        assert!(!packet("1", opts).matches(&s));
+
+        let mut opts = BroadcastOptions::new(s.sid);
+        opts.add_flag(BroadcastFlags::Broadcast);
+        assert!(!packet("1", opts).matches(&s));
+
+        let mut p = packet("1", BroadcastOptions::default());
+        p.ns = Str::from("/other");
+        assert!(!p.matches(&s));
+    }
+
+    #[tokio::test]
+    async fn memory_store_restore() {
+        let store = MemoryStore::new(Duration::from_secs(60));
+        let s = session(&["room1"]);
+        let pid = s.pid;
+        store.persist_packet(packet("1", Default::default())).await;
+        store.persist_session(s).await;
+        store.persist_packet(packet("2", Default::default())).await;
+        let mut opts = BroadcastOptions::default();
+        opts.rooms.push("room3".into());
+        store.persist_packet(packet("3", opts)).await;
+        store.persist_packet(packet("4", Default::default())).await;
+
+        assert!(store.restore_session(pid, "unknown").await.is_none());
+        // The session is removed even if the offset is unknown
+        assert!(store.restore_session(pid, "1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn memory_store_missed_packets() {
+        let store = MemoryStore::new(Duration::from_secs(60));
+        let s = session(&["room1"]);
+        let pid = s.pid;
+        store.persist_packet(packet("1", Default::default())).await;
+        store.persist_session(s).await;
+        store.persist_packet(packet("2", Default::default())).await;
+        let mut opts = BroadcastOptions::default();
+        opts.rooms.push("room3".into());
+        store.persist_packet(packet("3", opts)).await;
+        store.persist_packet(packet("4", Default::default())).await;
+
+        let session = store.restore_session(pid, "1").await.unwrap();
+        assert_eq!(session.rooms, vec![Room::from("room1")]);
+        assert_eq!(
+            session.missed_packets,
+            vec![
+                packet("2", Default::default()).data,
+                packet("4", Default::default()).data
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_store_expiration() {
+        let store = MemoryStore::new(Duration::from_millis(10));
+        let s = session(&[]);
+        let pid = s.pid;
+        store.persist_packet(packet("1", Default::default())).await;
+        store.persist_session(s).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(store.restore_session(pid, "1").await.is_none());
+    }
+}
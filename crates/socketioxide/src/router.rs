@@ -0,0 +1,382 @@
+//! ## An event router with typed path parameters.
+//!
+//! A [`Router`] dispatches the events of a socket to handlers registered for event name patterns,
+//! the same way an HTTP router dispatches requests on their path. It is useful for applications with
+//! a large number of event types that follow a hierarchy: `user/42/update`, `room/lobby/join`...
+//!
+//! The segments of the patterns are separated by `/`:
+//! * `:name` (or `{name}`) matches a whole segment and captures it as the `name` parameter.
+//! * `*name` (or `{*name}`) matches the rest of the event name, it can only be the last segment.
+//!
+//! The captured parameters are extracted in the handlers with the [`EventParams`] extractor,
+//! which deserializes them to any type implementing [`Deserialize`](serde::Deserialize):
+//! a struct with a field for each parameter, a single value, or a tuple in the order of the parameters.
+//!
+//! A router is registered on a socket with [`Socket::on_router`](crate::socket::Socket::on_router).
+//! The handlers registered with [`Socket::on`](crate::socket::Socket::on) have precedence over
+//! the router, and the router has precedence over the
+//! [prefix handlers](crate::socket::Socket::on_prefix).
+//!
+//! #### Example
+//! ```rust
+//! # use socketioxide::{SocketIo, router::Router, extract::{SocketRef, Data, EventParams}};
+//! # use serde::Deserialize;
+//! #[derive(Deserialize)]
+//! struct RoomParams {
+//!     room: String,
+//!     action: String,
+//! }
+//!
+//! let router = Router::new()
+//!     .route("user/:id/update", |EventParams(id): EventParams<u64>, Data::<String>(name)| {
+//!         println!("user {id} renamed to {name}");
+//!     })
+//!     .route("room/:room/:action", |s: SocketRef, EventParams(p): EventParams<RoomParams>| {
+//!         match p.action.as_str() {
+//!             "join" => s.join(p.room),
+//!             "leave" => s.leave(p.room),
+//!             _ => (),
+//!         }
+//!     });
+//!
+//! let (_, io) = SocketIo::new_svc();
+//! io.ns("/", move |s: SocketRef| s.on_router(router.clone()));
+//! ```
+//!
+//! [`EventParams`]: crate::extract::EventParams
+use std::{borrow::Cow, fmt, sync::Arc};
+
+use serde::de::{self, value::MapDeserializer, value::SeqDeserializer, IntoDeserializer, Visitor};
+
+use crate::{
+    adapter::{Adapter, LocalAdapter},
+    handler::{MakeErasedHandler, MessageHandler, SharedMessageHandler},
+};
+
+/// An event router, see the [module level documentation](self) for more details.
+///
+/// It is cheap to clone, so that the same router can be registered on every socket.
+pub struct Router<A: Adapter = LocalAdapter> {
+    inner: Arc<matchit::Router<SharedMessageHandler<A>>>,
+}
+
+impl<A: Adapter> Router<A> {
+    /// Create a new router without any route.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(matchit::Router::new()),
+        }
+    }
+
+    /// Add a route calling the `handler` for the events matching the `pattern`.
+    ///
+    /// # Panics
+    /// If the pattern is invalid or conflicts with a pattern already added.
+    pub fn route<H, T>(mut self, pattern: &str, handler: H) -> Self
+    where
+        H: MessageHandler<A, T>,
+        T: Send + Sync + 'static,
+    {
+        let handler = MakeErasedHandler::new_message_shared(handler);
+        if let Err(e) = Arc::make_mut(&mut self.inner).insert(to_matchit(pattern), handler) {
+            panic!("invalid route {pattern}: {e}");
+        }
+        self
+    }
+
+    /// Find the handler of the given event.
+    pub(crate) fn handler(&self, event: &str) -> Option<SharedMessageHandler<A>> {
+        self.inner
+            .at(event)
+            .ok()
+            .map(|matched| matched.value.clone())
+    }
+
+    /// Find the handler of the given event with the captured parameters.
+    pub(crate) fn at(&self, event: &str) -> Option<(SharedMessageHandler<A>, Params)> {
+        let matched = self.inner.at(event).ok()?;
+        let params = matched
+            .params
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Some((matched.value.clone(), params))
+    }
+}
+
+impl<A: Adapter> Default for Router<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Adapter> Clone for Router<A> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<A: Adapter> fmt::Debug for Router<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router").finish()
+    }
+}
+
+/// Convert the `:name` and `*name` segments of a pattern to the matchit syntax.
+fn to_matchit(pattern: &str) -> String {
+    pattern
+        .split('/')
+        .map(|segment| match segment.as_bytes().first() {
+            Some(b':') => Cow::Owned(format!("{{{}}}", &segment[1..])),
+            Some(b'*') => Cow::Owned(format!("{{{}}}", segment)),
+            _ => Cow::Borrowed(segment),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The parameters captured by a route, in the order of the pattern.
+pub(crate) type Params = Vec<(String, String)>;
+
+/// A deserializer for the captured parameters of a route.
+///
+/// It deserializes a struct or a map from the parameters by name, a tuple or a sequence
+/// from the parameters in order, and any other type from the single parameter of the route.
+pub(crate) struct ParamsDeserializer<'a>(pub &'a [(String, String)]);
+
+macro_rules! single_param {
+    ($($method:ident)*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            match self.0 {
+                [(_, value)] => ParamDeserializer(value).$method(visitor),
+                params => Err(de::Error::invalid_length(params.len(), &"a single parameter")),
+            }
+        })*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ParamsDeserializer<'de> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let params = self
+            .0
+            .iter()
+            .map(|(k, v)| (k.as_str(), ParamDeserializer(v)));
+        visitor.visit_map(MapDeserializer::new(params))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let params = self.0.iter().map(|(_, v)| ParamDeserializer(v));
+        visitor.visit_seq(SeqDeserializer::new(params))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            [] => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            [(_, value)] => ParamDeserializer(value).deserialize_enum(name, variants, visitor),
+            params => Err(de::Error::invalid_length(
+                params.len(),
+                &"a single parameter",
+            )),
+        }
+    }
+
+    single_param! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_identifier
+        deserialize_ignored_any
+    }
+}
+
+/// A deserializer for a single captured parameter, parsing it to the requested type.
+struct ParamDeserializer<'a>(&'a str);
+
+macro_rules! parse_param {
+    ($($method:ident => $visit:ident,)*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            match self.0.parse() {
+                Ok(value) => visitor.$visit(value),
+                Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(self.0), &visitor)),
+            }
+        })*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ParamDeserializer<'de> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let value = de::value::BorrowedStrDeserializer::<Self::Error>::new(self.0);
+        de::Deserializer::deserialize_enum(value, name, variants, visitor)
+    }
+
+    parse_param! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, de::value::Error> for ParamDeserializer<'de> {
+    type Deserializer = Self;
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    fn params(params: &[(&str, &str)]) -> Params {
+        params
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect()
+    }
+
+    fn deserialize<T: serde::de::DeserializeOwned>(
+        p: &[(&str, &str)],
+    ) -> Result<T, de::value::Error> {
+        T::deserialize(ParamsDeserializer(&params(p)))
+    }
+
+    #[test]
+    fn to_matchit_syntax() {
+        assert_eq!(to_matchit("user/:id/update"), "user/{id}/update");
+        assert_eq!(to_matchit("files/*path"), "files/{*path}");
+        assert_eq!(to_matchit("user/{id}"), "user/{id}");
+        assert_eq!(to_matchit("ping"), "ping");
+    }
+
+    #[test]
+    fn deserialize_params() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct P {
+            id: u64,
+            name: String,
+            flag: Option<bool>,
+        }
+        let p: P = deserialize(&[("id", "42"), ("name", "foo")]).unwrap();
+        assert_eq!(
+            p,
+            P {
+                id: 42,
+                name: "foo".into(),
+                flag: None
+            }
+        );
+        let id: u64 = deserialize(&[("id", "42")]).unwrap();
+        assert_eq!(id, 42);
+        let t: (u64, String) = deserialize(&[("id", "42"), ("name", "foo")]).unwrap();
+        assert_eq!(t, (42, "foo".into()));
+
+        assert!(deserialize::<u64>(&[("id", "foo")]).is_err());
+        assert!(deserialize::<u64>(&[("id", "1"), ("other", "2")]).is_err());
+    }
+}
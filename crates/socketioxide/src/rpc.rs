@@ -0,0 +1,211 @@
+//! A typed request/response layer over acknowledgements.
+//!
+//! An [`RpcService`] groups [`RpcMethod`]s, each one mapped to the event `"{service}:{method}"`.
+//! A method has typed request, response and error types, so the ack correlation,
+//! the timeout and the encoding of the errors don't have to be written by hand:
+//! * [`Rpc::call`] emits the request of a method to a socket and waits for its response,
+//!   implemented by the client with an ack.
+//! * [`Rpc::serve`] exposes a method to the client: the returned result is sent back with the ack.
+//!
+//! The request is sent as the data of the event, a tuple being sent as multiple arguments.
+//! The response is sent as an object with an `ok` or an `err` field, see [`RpcResponse`].
+//!
+//! Calls are cancelled by dropping their future. The handlers of the served methods are
+//! cancelled once the [`timeout`](Rpc::timeout) is reached, the client having stopped waiting
+//! for their response.
+//!
+//! # Example
+//! ```
+//! # use socketioxide::{SocketIo, extract::*, rpc::{RpcService, RpcMethod}};
+//! # use std::time::Duration;
+//! struct Calculator;
+//! impl RpcService for Calculator {
+//!     const NAME: &'static str = "calculator";
+//! }
+//!
+//! struct Div;
+//! impl RpcMethod<Calculator> for Div {
+//!     const NAME: &'static str = "div";
+//!     type Request = (i32, i32);
+//!     type Response = i32;
+//!     type Error = String;
+//! }
+//!
+//! let (_, io) = SocketIo::new_svc();
+//! let rpc = io.rpc::<Calculator>().timeout(Duration::from_secs(2));
+//! io.ns("/", move |socket: SocketRef| {
+//!     // The client can call the "calculator:div" method with an ack
+//!     rpc.serve::<Div, _, _>(&socket, |_, (a, b)| async move {
+//!         a.checked_div(b).ok_or_else(|| "division by zero".to_string())
+//!     });
+//!
+//!     // The server calls the "calculator:div" method implemented by the client
+//!     let rpc = rpc.clone();
+//!     tokio::spawn(async move {
+//!         match rpc.call::<Div>(&socket, &(4, 2)).await {
+//!             Ok(res) => println!("4 / 2 = {res}"),
+//!             Err(err) => println!("call failed: {err}"),
+//!         }
+//!     });
+//! });
+//! ```
+use std::{fmt, future::Future, marker::PhantomData, time::Duration};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    adapter::{Adapter, LocalAdapter},
+    extract::{AckSender, Data, SocketRef},
+    socket::Socket,
+    AckError, SendError,
+};
+
+/// A group of [`RpcMethod`]s, see the [module level documentation](self).
+pub trait RpcService: 'static {
+    /// The name of the service, prefixing the events of its methods.
+    const NAME: &'static str;
+}
+
+/// A method of an [`RpcService`], see the [module level documentation](self).
+pub trait RpcMethod<S: RpcService>: 'static {
+    /// The name of the method. The event of the method is `"{service}:{method}"`.
+    const NAME: &'static str;
+    /// The request sent to the method.
+    type Request: Serialize + DeserializeOwned + Send + Sync + 'static;
+    /// The response of the method.
+    type Response: Serialize + DeserializeOwned + Send + Sync + 'static;
+    /// The error returned by the method.
+    type Error: Serialize + DeserializeOwned + Send + Sync + 'static;
+}
+
+/// The response of an [`RpcMethod`], sent with the ack.
+///
+/// It is serialized as an object with an `ok` field holding the response
+/// or an `err` field holding the error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RpcResponse<T, E> {
+    /// The method succeeded.
+    Ok(T),
+    /// The method returned an error.
+    Err(E),
+}
+
+impl<T, E> From<Result<T, E>> for RpcResponse<T, E> {
+    fn from(res: Result<T, E>) -> Self {
+        match res {
+            Ok(res) => RpcResponse::Ok(res),
+            Err(err) => RpcResponse::Err(err),
+        }
+    }
+}
+
+impl<T, E> From<RpcResponse<T, E>> for Result<T, E> {
+    fn from(res: RpcResponse<T, E>) -> Self {
+        match res {
+            RpcResponse::Ok(res) => Ok(res),
+            RpcResponse::Err(err) => Err(err),
+        }
+    }
+}
+
+/// Error type for the [`Rpc::call`] method.
+#[non_exhaustive]
+#[derive(thiserror::Error, Debug)]
+pub enum RpcError<E> {
+    /// The method returned an error.
+    #[error("the method returned an error: {0:?}")]
+    Remote(E),
+    /// The request could not be sent.
+    #[error("Error sending request: {0:?}")]
+    Send(#[from] SendError),
+    /// The response was not received in time, the socket was closed or the response could not be decoded.
+    #[error("Error receiving response: {0:?}")]
+    Ack(#[from] AckError),
+}
+
+/// A typed facade over the acks of an [`RpcService`], created with [`SocketIo::rpc`](crate::SocketIo::rpc).
+/// See the [module level documentation](self) for more details.
+pub struct Rpc<S, A: Adapter = LocalAdapter> {
+    timeout: Duration,
+    _marker: PhantomData<fn() -> (S, A)>,
+}
+
+impl<S: RpcService, A: Adapter> Rpc<S, A> {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The maximum duration of a call.
+    ///
+    /// Defaults to the [`ack_timeout`](crate::SocketIoBuilder::ack_timeout) of the server.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The event of a method.
+    fn event<M: RpcMethod<S>>() -> String {
+        format!("{}:{}", S::NAME, M::NAME)
+    }
+
+    /// Call a method implemented by the client of a socket and wait for its response.
+    ///
+    /// The call is cancelled if the returned future is dropped.
+    pub async fn call<M: RpcMethod<S>>(
+        &self,
+        socket: &Socket<A>,
+        req: &M::Request,
+    ) -> Result<M::Response, RpcError<M::Error>> {
+        let res = socket
+            .timeout(self.timeout)
+            .emit_with_ack::<_, RpcResponse<M::Response, M::Error>>(Self::event::<M>(), req)?
+            .await?;
+        Result::from(res).map_err(RpcError::Remote)
+    }
+
+    /// Expose a method to the client of a socket.
+    ///
+    /// The handler is called with the decoded request for each call of the client,
+    /// and its result is sent back with the ack. It is cancelled once the timeout is reached.
+    /// The calls with a request that can't be decoded are ignored.
+    pub fn serve<M, F, Fut>(&self, socket: &Socket<A>, handler: F)
+    where
+        M: RpcMethod<S>,
+        F: Fn(SocketRef<A>, M::Request) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = Result<M::Response, M::Error>> + Send + 'static,
+    {
+        let timeout = self.timeout;
+        socket.on(
+            Self::event::<M>(),
+            move |s: SocketRef<A>, Data(req): Data<M::Request>, ack: AckSender<A>| {
+                let res = tokio::time::timeout(timeout, handler(s, req));
+                async move {
+                    if let Ok(res) = res.await {
+                        ack.send(&RpcResponse::from(res)).ok();
+                    }
+                }
+            },
+        );
+    }
+}
+
+impl<S, A: Adapter> Clone for Rpc<S, A> {
+    fn clone(&self) -> Self {
+        Self {
+            timeout: self.timeout,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S, A: Adapter> fmt::Debug for Rpc<S, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rpc")
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
@@ -0,0 +1,194 @@
+//! A minimal standalone http server for the socket.io service, enabled with the `server` feature.
+//!
+//! Every connection is served with hyper over http/1.1, with upgrades enabled for the websocket
+//! transport, or over http/2 when the client starts with the http/2 preface (prior knowledge).
+//! Requests outside of the [`req_path`](crate::SocketIoBuilder::req_path) get a 404 response.
+//!
+//! With the `server-tls` feature, connections can be secured with rustls. The http/2 protocol
+//! is then negotiated with ALPN for clients supporting it. Because websocket upgrades only exist
+//! with http/1.1, clients connected with http/2 use the polling transport.
+//!
+//! The address of the peer is inserted as a [`SocketAddr`] extension in every request.
+//! With [`proxy_protocol`](crate::SocketIoBuilder::proxy_protocol), it is read from the
+//! PROXY protocol header sent by the load balancer at the start of the connection.
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+
+use engineioxide::service::NotFoundService;
+use hyper::{body::Incoming, service::Service as _, Request};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{adapter::Adapter, client::Client, service::SocketIoService};
+
+mod proxy_protocol;
+
+/// The maximum duration to receive the PROXY protocol header of a connection.
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Accept the connections of the listener and serve them with the socket.io service.
+pub(crate) async fn serve<A: Adapter>(
+    client: Arc<Client<A>>,
+    listener: TcpListener,
+    #[cfg(feature = "server-tls")] tls: Option<TlsConfig>,
+) -> io::Result<()> {
+    let proxy_protocol = client.config.proxy_protocol;
+    let svc = SocketIoService::with_client(NotFoundService, client);
+    loop {
+        let (mut stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) if is_connection_error(&e) => continue,
+            Err(_e) => {
+                // Most likely too many open files, wait for some connections to be closed.
+                #[cfg(feature = "tracing")]
+                tracing::error!("failed to accept connection: {_e}");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let svc = svc.clone();
+        #[cfg(feature = "server-tls")]
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            let peer = if proxy_protocol {
+                match read_proxy_header(&mut stream, peer).await {
+                    Ok(peer) => peer,
+                    Err(_e) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!("invalid PROXY header from {peer}: {_e}");
+                        return;
+                    }
+                }
+            } else {
+                peer
+            };
+
+            #[cfg(feature = "server-tls")]
+            if let Some(tls) = tls {
+                let acceptor = tokio_rustls::TlsAcceptor::from(tls.0);
+                match acceptor.accept(stream).await {
+                    Ok(stream) => serve_conn(stream, peer, svc).await,
+                    Err(_e) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!("tls handshake failed: {_e}");
+                    }
+                }
+                return;
+            }
+
+            serve_conn(stream, peer, svc).await;
+        });
+    }
+}
+
+/// Read the PROXY protocol header of a connection and return the address of the client.
+/// The address of the peer is kept if the connection was not proxied.
+async fn read_proxy_header(stream: &mut TcpStream, peer: SocketAddr) -> io::Result<SocketAddr> {
+    let header = proxy_protocol::read_header(stream);
+    match tokio::time::timeout(PROXY_HEADER_TIMEOUT, header).await {
+        Ok(addr) => Ok(addr?.unwrap_or(peer)),
+        Err(_) => Err(io::ErrorKind::TimedOut.into()),
+    }
+}
+
+/// Serve an http/1.1 or http/2 connection.
+async fn serve_conn<A: Adapter>(
+    stream: impl AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    peer: SocketAddr,
+    svc: SocketIoService<NotFoundService, A>,
+) {
+    let svc = hyper::service::service_fn(move |mut req: Request<Incoming>| {
+        req.extensions_mut().insert(peer);
+        svc.call(req)
+    });
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let conn = builder.serve_connection_with_upgrades(TokioIo::new(stream), svc);
+    if let Err(_e) = conn.await {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("error serving connection: {_e}");
+    }
+}
+
+/// Errors that only concern the accepted connection and not the listener.
+fn is_connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    )
+}
+
+/// The TLS configuration of the standalone server, see [`SocketIo::serve_tls`](crate::SocketIo::serve_tls).
+///
+/// The configurations loaded from PEM encoded certificates advertise the `h2` and `http/1.1`
+/// protocols with ALPN. A custom rustls [`ServerConfig`](tokio_rustls::rustls::ServerConfig)
+/// can also be used with the [`From`] impl, its ALPN protocols are then left untouched.
+#[cfg_attr(docsrs, doc(cfg(feature = "server-tls")))]
+#[cfg(feature = "server-tls")]
+#[derive(Clone)]
+pub struct TlsConfig(Arc<tokio_rustls::rustls::ServerConfig>);
+
+#[cfg(feature = "server-tls")]
+impl TlsConfig {
+    /// Create a [`TlsConfig`] from a PEM encoded certificate chain and private key.
+    pub fn from_pem(cert: &[u8], key: &[u8]) -> io::Result<Self> {
+        use tokio_rustls::rustls::{
+            crypto::ring,
+            pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+            ServerConfig,
+        };
+        fn invalid(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, e)
+        }
+        let certs = CertificateDer::pem_slice_iter(cert)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(invalid)?;
+        let key = PrivateKeyDer::from_pem_slice(key).map_err(invalid)?;
+
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+            .map_err(invalid)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Self(Arc::new(config)))
+    }
+
+    /// Create a [`TlsConfig`] from the paths of a PEM encoded certificate chain and private key.
+    pub async fn from_pem_file(
+        cert: impl AsRef<std::path::Path>,
+        key: impl AsRef<std::path::Path>,
+    ) -> io::Result<Self> {
+        let cert = tokio::fs::read(cert).await?;
+        let key = tokio::fs::read(key).await?;
+        Self::from_pem(&cert, &key)
+    }
+}
+
+#[cfg(feature = "server-tls")]
+impl From<tokio_rustls::rustls::ServerConfig> for TlsConfig {
+    fn from(config: tokio_rustls::rustls::ServerConfig) -> Self {
+        Self(Arc::new(config))
+    }
+}
+
+#[cfg(feature = "server-tls")]
+impl From<Arc<tokio_rustls::rustls::ServerConfig>> for TlsConfig {
+    fn from(config: Arc<tokio_rustls::rustls::ServerConfig>) -> Self {
+        Self(config)
+    }
+}
+
+#[cfg(feature = "server-tls")]
+impl std::fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("alpn_protocols", &self.0.alpn_protocols)
+            .finish()
+    }
+}
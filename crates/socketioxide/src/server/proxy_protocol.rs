@@ -0,0 +1,164 @@
+//! Parsing of the PROXY protocol header sent by load balancers at the start of the connections.
+//!
+//! Both the text (v1) and the binary (v2) formats are supported, see the
+//! [specification](https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt).
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+/// The maximum length of a v1 header, including the CRLF.
+const V1_MAX_LEN: usize = 107;
+
+/// Read the PROXY protocol header at the start of a stream, without reading any byte after it.
+///
+/// Returns the source address of the proxied connection, or `None` if the connection was
+/// opened by the proxy itself (`LOCAL` command or `UNKNOWN` protocol).
+pub(crate) async fn read_header<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> io::Result<Option<SocketAddr>> {
+    // Both headers are at least 12 bytes long
+    let mut buf = Vec::with_capacity(V1_MAX_LEN);
+    buf.resize(12, 0);
+    stream.read_exact(&mut buf).await?;
+
+    if buf == V2_SIGNATURE {
+        let mut header = [0; 4];
+        stream.read_exact(&mut header).await?;
+        let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let mut addrs = vec![0; len];
+        stream.read_exact(&mut addrs).await?;
+        parse_v2(header[0], header[1], &addrs)
+    } else if buf.starts_with(b"PROXY ") {
+        while !buf.ends_with(b"\r\n") {
+            if buf.len() == V1_MAX_LEN {
+                return Err(invalid("PROXY v1 header too long"));
+            }
+            buf.push(stream.read_u8().await?);
+        }
+        parse_v1(&buf[..buf.len() - 2])
+    } else {
+        Err(invalid("missing PROXY header"))
+    }
+}
+
+/// Parse a v1 header line, without the trailing CRLF:
+/// `PROXY TCP4 <src ip> <dst ip> <src port> <dst port>`.
+fn parse_v1(line: &[u8]) -> io::Result<Option<SocketAddr>> {
+    let line = std::str::from_utf8(line).map_err(|_| invalid("invalid PROXY v1 header"))?;
+    let mut parts = line.split(' ').skip(1);
+    let ip = match parts.next() {
+        Some("UNKNOWN") => return Ok(None),
+        Some("TCP4") => parts
+            .next()
+            .and_then(|ip| ip.parse::<Ipv4Addr>().ok().map(IpAddr::V4)),
+        Some("TCP6") => parts
+            .next()
+            .and_then(|ip| ip.parse::<Ipv6Addr>().ok().map(IpAddr::V6)),
+        _ => None,
+    };
+    let port = parts.nth(1).and_then(|port| port.parse::<u16>().ok());
+    match (ip, port) {
+        (Some(ip), Some(port)) => Ok(Some(SocketAddr::new(ip, port))),
+        _ => Err(invalid("invalid PROXY v1 header")),
+    }
+}
+
+/// Parse the address block of a v2 header with its version/command and family bytes.
+fn parse_v2(ver_cmd: u8, family: u8, addrs: &[u8]) -> io::Result<Option<SocketAddr>> {
+    if ver_cmd >> 4 != 2 {
+        return Err(invalid("unsupported PROXY header version"));
+    }
+    match ver_cmd & 0x0f {
+        // LOCAL command, the connection was opened by the proxy (e.g. health checks)
+        0 => return Ok(None),
+        1 => (),
+        _ => return Err(invalid("unsupported PROXY v2 command")),
+    }
+    let port = |at: usize| u16::from_be_bytes([addrs[at], addrs[at + 1]]);
+    match family >> 4 {
+        1 if addrs.len() >= 12 => {
+            let ip: [u8; 4] = addrs[..4].try_into().unwrap();
+            Ok(Some(SocketAddr::new(ip.into(), port(8))))
+        }
+        2 if addrs.len() >= 36 => {
+            let ip: [u8; 16] = addrs[..16].try_into().unwrap();
+            Ok(Some(SocketAddr::new(ip.into(), port(32))))
+        }
+        1 | 2 => Err(invalid("invalid PROXY v2 address block")),
+        // Unspecified or unix socket addresses
+        _ => Ok(None),
+    }
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read(mut data: &[u8]) -> (io::Result<Option<SocketAddr>>, &[u8]) {
+        let res = read_header(&mut data).await;
+        (res, data)
+    }
+
+    #[tokio::test]
+    async fn v1() {
+        let (res, rest) = read(b"PROXY TCP4 1.2.3.4 10.0.0.1 5678 80\r\nGET /").await;
+        assert_eq!(res.unwrap(), Some("1.2.3.4:5678".parse().unwrap()));
+        assert_eq!(rest, b"GET /");
+
+        let (res, _) = read(b"PROXY TCP6 2001:db8::1 ::1 5678 443\r\n").await;
+        assert_eq!(res.unwrap(), Some("[2001:db8::1]:5678".parse().unwrap()));
+
+        let (res, rest) = read(b"PROXY UNKNOWN\r\nGET").await;
+        assert_eq!(res.unwrap(), None);
+        assert_eq!(rest, b"GET");
+
+        assert!(read(b"PROXY TCP4 1.2.3.4 10.0.0.1 foo 80\r\n")
+            .await
+            .0
+            .is_err());
+        assert!(read(b"PROXY TCP4 ::1 ::1 1 80\r\n").await.0.is_err());
+        assert!(read(&[b"PROXY "[..].to_vec(), vec![b'a'; 200]].concat())
+            .await
+            .0
+            .is_err());
+        assert!(read(b"GET / HTTP/1.1\r\n\r\n").await.0.is_err());
+    }
+
+    #[tokio::test]
+    async fn v2() {
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend([
+            0x21, 0x11, 0, 12, 1, 2, 3, 4, 10, 0, 0, 1, 0x16, 0x2e, 0, 80,
+        ]);
+        data.extend(b"GET /");
+        let (res, rest) = read(&data).await;
+        assert_eq!(res.unwrap(), Some("1.2.3.4:5678".parse().unwrap()));
+        assert_eq!(rest, b"GET /");
+
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend([0x21, 0x21, 0, 36]);
+        data.extend(Ipv6Addr::LOCALHOST.octets());
+        data.extend(Ipv6Addr::LOCALHOST.octets());
+        data.extend([0x16, 0x2e, 0, 80]);
+        let (res, _) = read(&data).await;
+        assert_eq!(res.unwrap(), Some("[::1]:5678".parse().unwrap()));
+
+        // LOCAL command
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend([0x20, 0x00, 0, 0]);
+        assert_eq!(read(&data).await.0.unwrap(), None);
+
+        // Truncated address block
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend([0x21, 0x11, 0, 4, 1, 2, 3, 4]);
+        assert!(read(&data).await.0.is_err());
+    }
+}
@@ -91,6 +91,12 @@ impl<A: Adapter, S: Clone> SocketIoService<S, A> {
         self.engine_svc.into_make_service()
     }
 
+    /// The config of the underlying engine.io service.
+    #[cfg(any(feature = "actix", feature = "warp", feature = "rocket"))]
+    pub(crate) fn engine_config(&self) -> &engineioxide::config::EngineIoConfig {
+        self.engine_svc.config()
+    }
+
     /// Creates a new [`EngineIoService`] with a custom inner service and a custom config.
     pub(crate) fn with_config_inner(
         inner: S,
@@ -1,6 +1,7 @@
 //! A [`Socket`] represents a client connected to a namespace.
 //! The socket struct itself should not be used directly, but through a [`SocketRef`](crate::extract::SocketRef).
 use std::{
+    any::Any,
     borrow::Cow,
     collections::HashMap,
     fmt::{self, Debug},
@@ -12,43 +13,75 @@ use std::{
     time::Duration,
 };
 
-use engineioxide::socket::{DisconnectReason as EIoDisconnectReason, Permit};
+use bytes::Bytes;
+use engineioxide::{
+    rate_limit::RateLimitPolicy,
+    socket::{
+        CloseReason, DisconnectReason as EIoDisconnectReason, PacketBatch, PacketPriority, Permit,
+    },
+};
 use serde::Serialize;
 use tokio::sync::{
     mpsc::error::TrySendError,
     oneshot::{self, Receiver},
+    OwnedSemaphorePermit,
 };
 
 #[cfg(feature = "extensions")]
 use crate::extensions::Extensions;
+#[cfg(feature = "macros")]
+use crate::typed::SocketEvents;
 
 use crate::{
     ack::{AckInnerStream, AckResult, AckStream},
     adapter::{Adapter, LocalAdapter},
     client::SocketData,
+    concurrency::HandlerQueue,
     errors::Error,
     handler::{
-        BoxedDisconnectHandler, BoxedMessageHandler, DisconnectHandler, MakeErasedHandler,
-        MessageHandler,
+        self, BoxedDisconnectHandler, DisconnectHandler, HandlerKind, HandlerPanic,
+        MakeErasedHandler, MessageHandler, SharedMessageHandler,
     },
     ns::Namespace,
     operators::{BroadcastOperators, ConfOperators},
     parser::Parser,
+    presence::{DeltaKind, PresenceMeta},
+    rate_limit::EventRateLimiter,
+    router::{Params, Router},
     AckError, SendError, SocketError, SocketIo,
 };
 use socketioxide_core::{
-    adapter::{BroadcastOptions, RemoteSocketData, Room, RoomParam},
+    adapter::{BroadcastOptions, RemoteSocketData, RequestOptions, Room, RoomParam},
     errors::{AdapterError, BroadcastError},
-    packet::{Packet, PacketData},
-    parser::Parse,
+    packet::{ConnectPacket, Packet, PacketData},
+    parser::{Parse, ParserError},
     Value,
 };
 
-pub use engineioxide::sid::Sid;
+pub use engineioxide::sid::{RandomSidGenerator, Sid, SidGenerator};
 
 /// All the possible reasons for a [`Socket`] to be disconnected from a namespace.
 ///
 /// It can be used as an extractor in the [`on_disconnect`](crate::handler::disconnect) handler.
+/// The [`is_graceful`](DisconnectReason::is_graceful), [`is_connection_lost`](DisconnectReason::is_connection_lost)
+/// and [`is_server_initiated`](DisconnectReason::is_server_initiated) methods can be used
+/// to distinguish clean logouts from network drops.
+///
+/// # Example
+/// ```
+/// # use socketioxide::{SocketIo, socket::DisconnectReason, extract::*};
+/// let (_, io) = SocketIo::new_svc();
+/// io.ns("/", |socket: SocketRef| {
+///     socket.on_disconnect(|socket: SocketRef, reason: DisconnectReason| {
+///         if reason.is_graceful() {
+///             println!("socket {} logged out", socket.id);
+///         } else if reason.is_connection_lost() {
+///             println!("socket {} lost its connection: {reason}", socket.id);
+///         }
+///     });
+/// });
+/// ```
+#[non_exhaustive]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum DisconnectReason {
     /// The client gracefully closed the connection
@@ -74,6 +107,25 @@ pub enum DisconnectReason {
 
     /// The server is being closed
     ClosingServer,
+
+    /// The client exceeded the rate limit configured with the
+    /// [`Disconnect`](crate::RateLimitPolicy::Disconnect) policy
+    RateLimitExceeded,
+
+    /// The client did not consume its packets before the
+    /// [`slow_consumer_timeout`](crate::SocketIoBuilder::slow_consumer_timeout)
+    SlowConsumer,
+
+    /// A connect or message handler of the socket panicked and
+    /// [`disconnect_on_handler_panic`](crate::SocketIoBuilder::disconnect_on_handler_panic) is enabled
+    HandlerPanic,
+
+    /// The underlying connection was closed with [`Socket::close_with_reason`]
+    ServerClose,
+
+    /// The client sent more events than the handler queue of the
+    /// [concurrency limit](crate::SocketIoBuilder::max_concurrent_handlers) can hold
+    HandlerQueueFull,
 }
 
 impl std::fmt::Display for DisconnectReason {
@@ -88,11 +140,70 @@ impl std::fmt::Display for DisconnectReason {
             ClientNSDisconnect => "client has manually disconnected the socket from the namespace",
             ServerNSDisconnect => "socket was forcefully disconnected from the namespace",
             ClosingServer => "server is being closed",
+            RateLimitExceeded => "client exceeded the rate limit",
+            SlowConsumer => "client did not consume its packets in time",
+            HandlerPanic => "a handler of the socket panicked",
+            ServerClose => "server closed the connection",
+            HandlerQueueFull => "client sent more events than the handler queue can hold",
         };
         f.write_str(str)
     }
 }
 
+impl DisconnectReason {
+    /// Whether the client intentionally closed the connection or left the namespace.
+    pub fn is_graceful(&self) -> bool {
+        matches!(
+            self,
+            DisconnectReason::TransportClose | DisconnectReason::ClientNSDisconnect
+        )
+    }
+
+    /// Whether the connection was lost because of a network failure
+    /// (e.g. the connection was abruptly closed or the client stopped responding to heartbeats).
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(
+            self,
+            DisconnectReason::TransportError | DisconnectReason::HeartbeatTimeout
+        )
+    }
+
+    /// Whether the socket was disconnected by the server, either manually, because it is shutting down,
+    /// because the client broke the protocol (bad packet, concurrent polling requests),
+    /// because the client exceeded a limit (rate limit, slow consumer, handler queue) or because a handler panicked.
+    pub fn is_server_initiated(&self) -> bool {
+        use DisconnectReason::*;
+        matches!(
+            self,
+            MultipleHttpPollingError
+                | PacketParsingError
+                | ServerNSDisconnect
+                | ClosingServer
+                | RateLimitExceeded
+                | SlowConsumer
+                | HandlerPanic
+                | ServerClose
+                | HandlerQueueFull
+        )
+    }
+
+    /// Whether the socket session can be recovered after this disconnection
+    /// when connection state recovery is enabled.
+    pub(crate) fn is_recoverable(&self) -> bool {
+        use DisconnectReason::*;
+        !matches!(
+            self,
+            ClientNSDisconnect
+                | ServerNSDisconnect
+                | ClosingServer
+                | RateLimitExceeded
+                | HandlerPanic
+                | ServerClose
+                | HandlerQueueFull
+        )
+    }
+}
+
 impl From<EIoDisconnectReason> for DisconnectReason {
     fn from(reason: EIoDisconnectReason) -> Self {
         use DisconnectReason::*;
@@ -103,21 +214,22 @@ impl From<EIoDisconnectReason> for DisconnectReason {
             EIoDisconnectReason::MultipleHttpPollingError => MultipleHttpPollingError,
             EIoDisconnectReason::PacketParsingError => PacketParsingError,
             EIoDisconnectReason::ClosingServer => ClosingServer,
+            EIoDisconnectReason::RateLimitExceeded => RateLimitExceeded,
+            EIoDisconnectReason::SlowConsumer => SlowConsumer,
+            EIoDisconnectReason::ServerClose => ServerClose,
+            // A reason added to engine.io but not yet mapped here is reported as a transport error
+            _ => TransportError,
         }
     }
 }
 
 pub(crate) trait PermitExt<'a> {
-    fn send(self, packet: Packet, parser: Parser);
+    fn send<A: Adapter>(self, packet: Packet, socket: &Socket<A>);
     fn send_raw(self, value: Value);
 }
 impl<'a> PermitExt<'a> for Permit<'a> {
-    fn send(self, packet: Packet, parser: Parser) {
-        match parser.encode(packet) {
-            Value::Str(msg, None) => self.emit(msg),
-            Value::Str(msg, Some(bin_payloads)) => self.emit_many(msg, bin_payloads),
-            Value::Bytes(bin) => self.emit_binary(bin),
-        }
+    fn send<A: Adapter>(self, packet: Packet, socket: &Socket<A>) {
+        self.send_raw(socket.encode(packet));
     }
 
     fn send_raw(self, value: Value) {
@@ -129,6 +241,70 @@ impl<'a> PermitExt<'a> for Permit<'a> {
     }
 }
 
+/// A batch of events emitted at once to a [`Socket`], see [`Socket::batch`].
+pub struct Batch<'a, A: Adapter = LocalAdapter> {
+    socket: &'a Socket<A>,
+    events: Vec<Value>,
+    /// The first error that occurred while encoding an event
+    err: Option<ParserError>,
+}
+
+impl<A: Adapter> Batch<'_, A> {
+    /// Add an event to the batch.
+    ///
+    /// The data is encoded right away. If it can't be serialized, the whole batch is discarded
+    /// and [`Socket::batch`] returns the error.
+    pub fn emit<T: ?Sized + Serialize>(&mut self, event: impl AsRef<str>, data: &T) -> &mut Self {
+        if self.err.is_none() {
+            match self.socket.parser.encode_value(data, Some(event.as_ref())) {
+                Ok(data) => self.events.push(data),
+                Err(e) => self.err = Some(e),
+            }
+        }
+        self
+    }
+
+    /// The number of events in the batch.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true if the batch is empty.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl<A: Adapter> fmt::Debug for Batch<'_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batch")
+            .field("id", &self.socket.id)
+            .field("events", &self.events)
+            .field("err", &self.err)
+            .finish()
+    }
+}
+
+/// A catch-all handler registered with [`Socket::on_any`] or [`Socket::on_any_outgoing`].
+type AnyHandler<A> = Box<dyn Fn(&Socket<A>, &str, &Value) + Send + Sync + 'static>;
+
+/// An acknowledgement waiting for the client response.
+#[derive(Debug)]
+struct PendingAck {
+    tx: oneshot::Sender<AckResult<Value>>,
+    #[cfg(feature = "metrics")]
+    sent_at: std::time::Instant,
+}
+impl PendingAck {
+    fn new(tx: oneshot::Sender<AckResult<Value>>) -> Self {
+        Self {
+            tx,
+            #[cfg(feature = "metrics")]
+            sent_at: std::time::Instant::now(),
+        }
+    }
+}
+
 /// A RemoteSocket is a [`Socket`] that is remotely connected on another server.
 /// It implements a subset of the [`Socket`] API.
 pub struct RemoteSocket<A> {
@@ -188,7 +364,7 @@ impl<A: Adapter> RemoteSocket<A> {
             .broadcast_with_ack(packet, opts, None)
             .await
             .map_err(Into::<AdapterError>::into)?;
-        Ok(AckStream::new(stream, self.parser))
+        Ok(AckStream::new(stream, self.parser, [self.data.id]))
     }
 
     /// # Get all room names this remote socket is connected to.
@@ -196,7 +372,8 @@ impl<A: Adapter> RemoteSocket<A> {
     /// See [`Socket::rooms`] for more info.
     #[inline]
     pub async fn rooms(&self) -> Result<Vec<Room>, A::Error> {
-        self.adapter.rooms(self.get_opts()).await
+        let req_opts = RequestOptions::default();
+        self.adapter.rooms(self.get_opts(), req_opts).await
     }
 
     /// # Add the remote socket to the specified room(s).
@@ -252,6 +429,7 @@ impl<A> Clone for RemoteSocket<A> {
 }
 
 /// A error that can occur when emitting a message to a remote socket.
+#[non_exhaustive]
 #[derive(Debug, thiserror::Error)]
 pub enum RemoteActionError {
     /// The message data could not be encoded.
@@ -259,11 +437,21 @@ pub enum RemoteActionError {
     Serialize(#[from] crate::parser::ParserError),
     /// The remote socket is, in fact, a local socket and we should not emit to it.
     #[error("cannot send the message to the local socket: {0}")]
-    Socket(crate::SocketError),
+    Socket(#[source] crate::SocketError),
     /// The message could not be sent to the remote server.
     #[error("cannot propagate the request to the server: {0}")]
     Adapter(#[from] AdapterError),
 }
+impl RemoteActionError {
+    /// The [`ErrorKind`](crate::ErrorKind) of this error.
+    pub fn kind(&self) -> crate::ErrorKind {
+        match self {
+            RemoteActionError::Serialize(_) => crate::ErrorKind::Serialization,
+            RemoteActionError::Socket(err) => err.kind(),
+            RemoteActionError::Adapter(_) => crate::ErrorKind::Adapter,
+        }
+    }
+}
 impl From<BroadcastError> for RemoteActionError {
     fn from(value: BroadcastError) -> Self {
         // This conversion assumes that we broadcast to a single (remote or not) socket.
@@ -274,6 +462,7 @@ impl From<BroadcastError> for RemoteActionError {
             }
             BroadcastError::Adapter(e) => e.into(),
             BroadcastError::Serialize(e) => e.into(),
+            e => RemoteActionError::Adapter(AdapterError(Box::new(e))),
         }
     }
 }
@@ -283,17 +472,32 @@ impl From<BroadcastError> for RemoteActionError {
 /// The socket struct itself should not be used directly, but through a [`SocketRef`](crate::extract::SocketRef).
 pub struct Socket<A: Adapter = LocalAdapter> {
     pub(crate) ns: Arc<Namespace<A>>,
-    message_handlers: RwLock<HashMap<Cow<'static, str>, BoxedMessageHandler<A>>>,
+    message_handlers: RwLock<HashMap<Cow<'static, str>, SharedMessageHandler<A>>>,
+    /// The event router registered with [`Socket::on_router`].
+    router: RwLock<Option<Router<A>>>,
+    /// The handlers registered for an event name prefix, sorted from the longest prefix to the shortest.
+    prefix_handlers: RwLock<Vec<(Cow<'static, str>, SharedMessageHandler<A>)>>,
+    any_handlers: RwLock<Vec<AnyHandler<A>>>,
+    any_outgoing_handlers: RwLock<Vec<AnyHandler<A>>>,
     disconnect_handler: Mutex<Option<BoxedDisconnectHandler<A>>>,
-    ack_message: Mutex<HashMap<i64, oneshot::Sender<AckResult<Value>>>>,
+    ack_message: Mutex<HashMap<i64, PendingAck>>,
     ack_counter: AtomicI64,
     connected: AtomicBool,
     pub(crate) parser: Parser,
     /// The socket id
     pub id: Sid,
+    /// The private session id used for connection state recovery.
+    pub(crate) pid: Option<Sid>,
+    recovered: bool,
+    /// The connect packet payload set with [`Socket::connect_with`].
+    connect_payload: Mutex<Option<Value>>,
+    /// The presence metadata set with [`Socket::set_presence_meta`].
+    presence_meta: RwLock<Option<Arc<dyn PresenceMeta>>>,
 
     /// A type map of protocol extensions.
     /// It can be used to share data through the lifetime of the socket.
+    /// The [removal hooks](Extensions::insert_with_hook) of the extensions are called
+    /// once the socket is disconnected and dropped.
     ///
     /// **Note**: This is not the same data as the `extensions` field on the [`http::Request::extensions()`](http::Request) struct.
     /// If you want to extract extensions from the http request, you should use the [`HttpExtension`](crate::extract::HttpExtension) extractor.
@@ -301,6 +505,13 @@ pub struct Socket<A: Adapter = LocalAdapter> {
     #[cfg(feature = "extensions")]
     pub extensions: Extensions,
     esocket: Arc<engineioxide::Socket<SocketData<A>>>,
+    rate_limiter: Option<EventRateLimiter>,
+    /// The queue of the events waiting for a handler permit, if the number
+    /// of concurrent handlers is limited.
+    handler_queue: Option<Arc<HandlerQueue>>,
+    /// The time at which the socket was created, displayed by the admin UI.
+    #[cfg(feature = "admin-ui")]
+    pub(crate) connected_at: std::time::SystemTime,
 }
 
 impl<A: Adapter> Socket<A> {
@@ -311,24 +522,46 @@ impl<A: Adapter> Socket<A> {
         parser: Parser,
     ) -> Self {
         Self {
-            ns,
             message_handlers: RwLock::new(HashMap::new()),
+            router: RwLock::new(None),
+            prefix_handlers: RwLock::new(Vec::new()),
+            any_handlers: RwLock::new(Vec::new()),
+            any_outgoing_handlers: RwLock::new(Vec::new()),
             disconnect_handler: Mutex::new(None),
             ack_message: Mutex::new(HashMap::new()),
             ack_counter: AtomicI64::new(0),
             connected: AtomicBool::new(false),
             parser,
             id: sid,
+            pid: None,
+            recovered: false,
+            connect_payload: Mutex::new(None),
+            presence_meta: RwLock::new(None),
             #[cfg(feature = "extensions")]
             extensions: Extensions::new(),
+            rate_limiter: ns.rate_limit.map(EventRateLimiter::new),
+            handler_queue: ns.handler_queue(),
+            #[cfg(feature = "admin-ui")]
+            connected_at: std::time::SystemTime::now(),
+            ns,
             esocket,
         }
     }
 
+    pub(crate) fn with_recovery(mut self, pid: Option<Sid>, recovered: bool) -> Self {
+        self.pid = pid;
+        self.recovered = recovered;
+        self
+    }
+
     /// # Registers a [`MessageHandler`] for the given event.
     ///
     /// * See the [`message`](crate::handler::message) module doc for more details on message handler.
     /// * See the [`extract`](crate::extract) module doc for more details on available extractors.
+    /// * See [`MessageHandler::guard`] to check the events before calling the handler.
+    ///
+    /// If a handler is already registered for this event, it is replaced.
+    /// Handlers can be registered, replaced or removed with [`Socket::off`] at any time, including from handlers.
     ///
     /// _It is recommended for code clarity to define your handler as top level function rather than closures._
     ///
@@ -401,7 +634,226 @@ impl<A: Adapter> Socket<A> {
         self.message_handlers
             .write()
             .unwrap()
-            .insert(event.into(), MakeErasedHandler::new_message_boxed(handler));
+            .insert(event.into(), MakeErasedHandler::new_message_shared(handler));
+    }
+
+    /// # Register an event [`Router`] dispatching the events to the handlers of its routes.
+    ///
+    /// It is called for the events that have no handler registered with [`Socket::on`],
+    /// before the prefix handlers. Registering a router replaces the previous one.
+    /// See the [`router`](crate::router) module doc for more details.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, router::Router, extract::*};
+    /// let router = Router::new()
+    ///     .route("user/:id/update", |EventParams(id): EventParams<u64>| println!("update {id}"));
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", move |socket: SocketRef| socket.on_router(router.clone()));
+    /// ```
+    pub fn on_router(&self, router: Router<A>) {
+        self.router.write().unwrap().replace(router);
+    }
+
+    /// # Register a [`MessageHandler`] for all the events starting with the given prefix.
+    ///
+    /// It is called for the events that have no handler registered with [`Socket::on`]
+    /// or with the [router](Socket::on_router) of the socket.
+    /// If several prefixes match an event, the handler of the longest one is called.
+    /// Registering a handler for a prefix that already has one replaces it.
+    ///
+    /// The rest of the event name after the prefix can be extracted with the
+    /// [`EventSuffix`](crate::extract::EventSuffix) extractor.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     // Called for "chat:join", "chat:leave", "chat:message"...
+    ///     socket.on_prefix("chat:", |socket: SocketRef, EventSuffix(action), Data::<String>(room)| {
+    ///         match action.as_str() {
+    ///             "join" => socket.join(room),
+    ///             "leave" => socket.leave(room),
+    ///             _ => (),
+    ///         }
+    ///     });
+    ///     // Called for "chat:message" instead of the "chat:" handler
+    ///     socket.on("chat:message", |Data::<String>(msg)| println!("message: {msg}"));
+    /// });
+    /// ```
+    pub fn on_prefix<H, T>(&self, prefix: impl Into<Cow<'static, str>>, handler: H)
+    where
+        H: MessageHandler<A, T>,
+        T: Send + Sync + 'static,
+    {
+        let prefix = prefix.into();
+        let handler = MakeErasedHandler::new_message_shared(handler);
+        let mut handlers = self.prefix_handlers.write().unwrap();
+        handlers.retain(|(p, _)| *p != prefix);
+        let i = handlers.partition_point(|(p, _)| p.len() >= prefix.len());
+        handlers.insert(i, (prefix, handler));
+    }
+
+    /// # Register a handler for all the events of a [typed event](crate::typed) set.
+    ///
+    /// The handler is registered for each event of [`E::EVENTS`](crate::typed::SocketEvents::EVENTS),
+    /// replacing any handler previously registered for these events.
+    /// The received event can be extracted with the [`Event`](crate::extract::Event) extractor.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*, typed::SocketEvents};
+    /// #[derive(SocketEvents)]
+    /// enum MyEvent {
+    ///     Chat(String),
+    ///     Ping,
+    /// }
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on_typed::<MyEvent, _, _>(|socket: SocketRef, Event(event): Event<MyEvent>| {
+    ///         match event {
+    ///             MyEvent::Chat(msg) => socket.emit_typed(&MyEvent::Chat(msg)).ok(),
+    ///             MyEvent::Ping => socket.emit_typed(&MyEvent::Ping).ok(),
+    ///         };
+    ///     });
+    /// });
+    /// ```
+    #[cfg(feature = "macros")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+    pub fn on_typed<E, H, T>(&self, handler: H)
+    where
+        E: SocketEvents,
+        H: MessageHandler<A, T>,
+        T: Send + Sync + 'static,
+    {
+        let handler = MakeErasedHandler::new_message_shared(handler);
+        let mut handlers = self.message_handlers.write().unwrap();
+        for event in E::EVENTS {
+            handlers.insert(Cow::Borrowed(*event), handler.clone());
+        }
+    }
+
+    /// # Register a catch-all handler for the incoming events.
+    ///
+    /// The handler is called for every event received from the client, before the handler of
+    /// the event (if any), with the event name and the raw payload of the event.
+    /// The payload is encoded with the parser of the server and contains the event name.
+    /// Multiple catch-all handlers can be registered, they are called in the order of registration.
+    ///
+    /// It can be used for logging, auditing or bridging events without registering every event name.
+    ///
+    /// <div class="warning">
+    ///     The handler is called synchronously when receiving an event,
+    ///     so it should not block and must not register or remove catch-all handlers on the socket.
+    /// </div>
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on_any(|socket, event, payload| {
+    ///         println!("socket {} received event {event}: {payload:?}", socket.id);
+    ///     });
+    /// });
+    /// ```
+    pub fn on_any<F>(&self, handler: F)
+    where
+        F: Fn(&Socket<A>, &str, &Value) + Send + Sync + 'static,
+    {
+        self.any_handlers.write().unwrap().push(Box::new(handler));
+    }
+
+    /// # Register a catch-all handler for the outgoing events.
+    ///
+    /// The handler is called for every event emitted on this socket with [`Socket::emit`], [`Socket::emit_async`]
+    /// or [`Socket::emit_with_ack`], with the event name and the raw payload of the event.
+    /// Events broadcasted to the socket (e.g. with [`Socket::broadcast`] or `io.emit`) are not passed to the handler
+    /// because they are encoded once for all the sockets.
+    ///
+    /// See [`Socket::on_any`] for more details.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on_any_outgoing(|socket, event, payload| {
+    ///         println!("socket {} sent event {event}: {payload:?}", socket.id);
+    ///     });
+    ///     socket.emit("hello", "world").ok();
+    /// });
+    /// ```
+    pub fn on_any_outgoing<F>(&self, handler: F)
+    where
+        F: Fn(&Socket<A>, &str, &Value) + Send + Sync + 'static,
+    {
+        self.any_outgoing_handlers
+            .write()
+            .unwrap()
+            .push(Box::new(handler));
+    }
+
+    /// # Remove the [`MessageHandler`] registered for the given event.
+    ///
+    /// Returns `true` if a handler was registered for this event.
+    ///
+    /// Handlers are looked up when an event is dispatched, therefore:
+    /// * Events dispatched after this call won't call the removed handler,
+    ///   including events received before but still queued by the [event rate limiter](crate::SocketIoBuilder::max_events_per_second)
+    ///   or by the [concurrency limit](crate::SocketIoBuilder::max_concurrent_handlers).
+    /// * Handler calls that already started (e.g. async handlers) are not cancelled.
+    ///
+    /// It can be called from a handler, including the handler being removed.
+    /// Calling [`Socket::on`] with the same event name replaces the handler with the same semantics.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     // This handler is only called once
+    ///     socket.on("init", |socket: SocketRef| {
+    ///         socket.off("init");
+    ///         socket.on("message", || println!("message received"));
+    ///     });
+    /// });
+    /// ```
+    pub fn off(&self, event: impl AsRef<str>) -> bool {
+        self.message_handlers
+            .write()
+            .unwrap()
+            .remove(event.as_ref())
+            .is_some()
+    }
+
+    /// # Remove the [`MessageHandler`] registered for the given prefix with [`Socket::on_prefix`].
+    ///
+    /// Returns `true` if a handler was registered for this prefix.
+    /// See [`Socket::off`] for the semantics of the removal.
+    pub fn off_prefix(&self, prefix: impl AsRef<str>) -> bool {
+        let mut handlers = self.prefix_handlers.write().unwrap();
+        let len = handlers.len();
+        handlers.retain(|(p, _)| p != prefix.as_ref());
+        handlers.len() != len
+    }
+
+    /// # Remove all the [`MessageHandler`]s, router, prefix handlers and catch-all handlers registered on this socket.
+    ///
+    /// The disconnect handler is kept. See [`Socket::off`] for the semantics of the removal.
+    ///
+    /// <div class="warning">
+    ///     It must not be called from a catch-all handler.
+    /// </div>
+    pub fn off_all(&self) {
+        self.message_handlers.write().unwrap().clear();
+        self.router.write().unwrap().take();
+        self.prefix_handlers.write().unwrap().clear();
+        self.any_handlers.write().unwrap().clear();
+        self.any_outgoing_handlers.write().unwrap().clear();
     }
 
     /// # Register a disconnect handler.
@@ -449,22 +901,168 @@ impl<A: Adapter> Socket<A> {
             return Err(SendError::Socket(SocketError::Closed));
         }
 
+        let data = self.parser.encode_value(data, Some(event.as_ref()))?;
+        self.emit_encoded(data)
+    }
+
+    /// Emit an already encoded event payload to the client.
+    fn emit_encoded(&self, data: Value) -> Result<(), SendError> {
         let permit = match self.reserve() {
             Ok(permit) => permit,
             Err(e) => {
                 #[cfg(feature = "tracing")]
                 tracing::debug!("sending error during emit message: {e:?}");
-                return Err(SendError::Socket(e));
+                return Err(SendError::with_payload(e, data));
             }
         };
 
+        self.event_sent(&data);
         let ns = self.ns.path.clone();
-        let data = self.parser.encode_value(data, Some(event.as_ref()))?;
+        permit.send(Packet::event(ns, data), self);
+        Ok(())
+    }
+
+    /// # Emit a message to the client and wait for space in the packet buffer if it is full.
+    ///
+    /// Unlike [`Socket::emit`], which returns a [`SendError::BufferFull`] error when the internal
+    /// packet buffer is full, this method applies back-pressure: the returned future resolves once
+    /// the packet has been pushed to the buffer.
+    ///
+    /// The data is encoded before waiting. It will return a [`SendError::Socket(SocketError::Closed)`]
+    /// error if the socket is closed before or while waiting.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| async move {
+    ///     for i in 0..10_000 {
+    ///         // The messages are never dropped, even with a slow client.
+    ///         socket.emit_async("count", &i).await.ok();
+    ///     }
+    /// });
+    /// ```
+    ///
+    /// [`SendError::Socket(SocketError::Closed)`]: crate::SocketError::Closed
+    pub fn emit_async<T: ?Sized + Serialize>(
+        &self,
+        event: impl AsRef<str>,
+        data: &T,
+    ) -> impl Future<Output = Result<(), SendError>> + Send + '_ {
+        let data = if self.connected() {
+            self.parser
+                .encode_value(data, Some(event.as_ref()))
+                .map_err(SendError::from)
+        } else {
+            Err(SendError::Socket(SocketError::Closed))
+        };
+        async move {
+            let data = data?;
+            let permit = match self.esocket.reserve_async().await {
+                Ok(permit) => permit,
+                Err(_) => return Err(SendError::Socket(SocketError::Closed)),
+            };
+            self.event_sent(&data);
+            let ns = self.ns.path.clone();
+            permit.send(Packet::event(ns, data), self);
+            Ok(())
+        }
+    }
+
+    /// # Emit a batch of events to the client at once.
+    ///
+    /// The events added to the [`Batch`] in the closure are pushed at once to the packet buffer,
+    /// so they are written with a single flush of the websocket or in the same polling payload.
+    /// It reduces the number of syscalls and frames for bursts of small events.
+    ///
+    /// The batch takes one place in the packet buffer, so either all the events are sent or none:
+    /// * If an event can't be serialized, a [`SendError::Serialize`] error is returned.
+    /// * If the buffer is full, a [`SendError::Socket(SocketError::InternalChannelFull)`] error is returned.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.batch(|b| {
+    ///         b.emit("position", &(1, 2));
+    ///         b.emit("score", &42);
+    ///     }).ok();
+    /// });
+    /// ```
+    ///
+    /// [`SendError::Socket(SocketError::InternalChannelFull)`]: crate::SocketError::InternalChannelFull
+    pub fn batch<F>(&self, f: F) -> Result<(), SendError>
+    where
+        F: FnOnce(&mut Batch<'_, A>),
+    {
+        if !self.connected() {
+            return Err(SendError::Socket(SocketError::Closed));
+        }
+        let mut batch = Batch {
+            socket: self,
+            events: Vec::new(),
+            err: None,
+        };
+        f(&mut batch);
+        if let Some(err) = batch.err {
+            return Err(SendError::Serialize(err));
+        }
+        if batch.events.is_empty() {
+            return Ok(());
+        }
 
-        permit.send(Packet::event(ns, data), self.parser);
+        #[cfg(feature = "tracing")]
+        let _span = self.emit_span().entered();
+        let permit = self.reserve()?;
+        let mut packets = PacketBatch::with_capacity(batch.events.len());
+        for data in batch.events {
+            self.event_sent(&data);
+            match self.encode(Packet::event(self.ns.path.clone(), data)) {
+                Value::Str(msg, bin_payloads) => {
+                    packets.push_message(msg);
+                    for bin in bin_payloads.into_iter().flatten() {
+                        packets.push_binary(bin);
+                    }
+                }
+                Value::Bytes(bin) => packets.push_binary(bin),
+            }
+        }
+        permit.emit_batch(packets);
         Ok(())
     }
 
+    /// # Emit a [typed event](crate::typed) to the client.
+    ///
+    /// The event name and its data are both taken from the given value.
+    /// See [`Socket::emit`] for more details on the possible errors.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*, typed::SocketEvents};
+    /// #[derive(SocketEvents)]
+    /// enum MyEvent {
+    ///     Chat(String),
+    ///     Move(i32, i32),
+    /// }
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.emit_typed(&MyEvent::Chat("hello".into())).ok();
+    ///     socket.emit_typed(&MyEvent::Move(2, 3)).ok();
+    /// });
+    /// ```
+    #[cfg(feature = "macros")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+    pub fn emit_typed<E: SocketEvents>(&self, event: &E) -> Result<(), SendError> {
+        if !self.connected() {
+            return Err(SendError::Socket(SocketError::Closed));
+        }
+
+        let data = event.encode(self.parser)?;
+        self.emit_encoded(data)
+    }
+
     #[doc = include_str!("../docs/operators/emit_with_ack.md")]
     pub fn emit_with_ack<T: ?Sized + Serialize, V>(
         &self,
@@ -474,20 +1072,21 @@ impl<A: Adapter> Socket<A> {
         if !self.connected() {
             return Err(SendError::Socket(SocketError::Closed));
         }
+        let data = self.parser.encode_value(data, Some(event.as_ref()))?;
         let permit = match self.reserve() {
             Ok(permit) => permit,
             Err(e) => {
                 #[cfg(feature = "tracing")]
                 tracing::debug!("sending error during emit message: {e:?}");
-                return Err(SendError::Socket(e));
+                return Err(SendError::with_payload(e, data));
             }
         };
+        self.event_sent(&data);
         let ns = self.ns.path.clone();
-        let data = self.parser.encode_value(data, Some(event.as_ref()))?;
         let packet = Packet::event(ns, data);
         let rx = self.send_with_ack_permit(packet, permit);
-        let stream = AckInnerStream::send(rx, self.get_io().config().ack_timeout, self.id);
-        Ok(AckStream::<V>::new(stream, self.parser))
+        let stream = AckInnerStream::send(rx, self.ns.ack_timeout, self.id);
+        Ok(AckStream::<V>::new(stream, self.parser, [self.id]))
     }
 
     // Room actions
@@ -557,6 +1156,60 @@ impl<A: Adapter> Socket<A> {
             .collect()
     }
 
+    /// # Bind the socket to the given application user.
+    ///
+    /// The socket can then be addressed with the other sockets of the user, on all the servers,
+    /// through the [`UserRegistry`](crate::user::UserRegistry) of the namespace.
+    /// If the socket was bound to another user, it is unbound from it.
+    /// It is unbound automatically when it disconnects.
+    ///
+    /// If the [offline queue](crate::offline) is enabled, the messages queued while the user
+    /// was offline are sent to this socket. The socket must be connected, so this fn
+    /// should be called in the connect handler rather than in a connect middleware.
+    ///
+    /// See the [`user`](crate::user) module doc for more details.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef, Data(user_id): Data<u64>| {
+    ///     socket.set_user(user_id);
+    ///     assert_eq!(socket.user_id(), Some(user_id.to_string()));
+    /// });
+    /// ```
+    pub fn set_user(&self, user: impl fmt::Display) {
+        let user = user.to_string();
+        let room = crate::user::user_room(&self.ns.user_room_prefix, &user);
+        if self.user_room().as_ref() == Some(&room) {
+            return;
+        }
+        self.clear_user();
+        self.join(room);
+        self.ns.deliver_offline_packets(self.id, user);
+    }
+
+    /// # Get the id of the user the socket is bound to with [`Socket::set_user`].
+    pub fn user_id(&self) -> Option<String> {
+        let room = self.user_room()?;
+        Some(room[self.ns.user_room_prefix.len()..].to_owned())
+    }
+
+    /// # Unbind the socket from its user, if it was bound with [`Socket::set_user`].
+    pub fn clear_user(&self) {
+        if let Some(room) = self.user_room() {
+            self.leave(room);
+        }
+    }
+
+    /// The room of the user the socket is bound to.
+    fn user_room(&self) -> Option<Room> {
+        let prefix = self.ns.user_room_prefix.as_ref();
+        self.rooms()
+            .into_iter()
+            .find(|room| room.starts_with(prefix))
+    }
+
     /// # Return true if the socket is connected to the namespace.
     ///
     /// A socket is considered connected when it has been successfully handshaked with the server
@@ -584,7 +1237,9 @@ impl<A: Adapter> Socket<A> {
 
     #[doc = include_str!("../docs/operators/local.md")]
     pub fn local(&self) -> BroadcastOperators<A> {
-        BroadcastOperators::from_sock(self.ns.clone(), self.id, self.parser).local()
+        BroadcastOperators::from_sock(self.ns.clone(), self.id, self.parser)
+            .broadcast()
+            .local()
     }
 
     #[doc = include_str!("../docs/operators/timeout.md")]
@@ -592,6 +1247,16 @@ impl<A: Adapter> Socket<A> {
         ConfOperators::new(self).timeout(timeout)
     }
 
+    #[doc = include_str!("../docs/operators/volatile.md")]
+    pub fn volatile(&self) -> ConfOperators<'_, A> {
+        ConfOperators::new(self).volatile()
+    }
+
+    #[doc = include_str!("../docs/operators/bin.md")]
+    pub fn bin(&self, bins: impl IntoIterator<Item = impl Into<Bytes>>) -> ConfOperators<'_, A> {
+        ConfOperators::new(self).bin(bins)
+    }
+
     #[doc = include_str!("../docs/operators/broadcast.md")]
     pub fn broadcast(&self) -> BroadcastOperators<A> {
         BroadcastOperators::from_sock(self.ns.clone(), self.id, self.parser).broadcast()
@@ -609,8 +1274,21 @@ impl<A: Adapter> Socket<A> {
     /// # Disconnect the socket from the current namespace,
     ///
     /// It will also call the disconnect handler if it is set with a [`DisconnectReason::ServerNSDisconnect`].
+    ///
+    /// The disconnect packet is queued after all the packets already emitted to the socket,
+    /// including the [volatile](Socket::volatile) ones, so the client receives them before being disconnected.
     pub fn disconnect(self: Arc<Self>) -> Result<(), SocketError> {
-        let res = self.send(Packet::disconnect(self.ns.path.clone()));
+        let packet = Packet::disconnect(self.ns.path.clone());
+        let res = if self.esocket.is_flushed() {
+            self.send(packet)
+        } else {
+            #[cfg(feature = "tracing")]
+            let _span = self.emit_span().entered();
+            // The low priority queue is consumed last
+            self.reserve_with_priority(PacketPriority::Low)
+                .or_else(|_| self.reserve())
+                .map(|permit| permit.send(packet, &self))
+        };
         if let Err(SocketError::InternalChannelFull) = res {
             return Err(SocketError::InternalChannelFull);
         }
@@ -618,6 +1296,214 @@ impl<A: Adapter> Socket<A> {
         Ok(())
     }
 
+    /// # Close the underlying connection with a close code and message.
+    ///
+    /// The socket is disconnected from all its namespaces with the [`DisconnectReason::ServerClose`] reason.
+    /// The code and the message are sent to the client in the websocket close frame,
+    /// so it can show a meaningful error. The polling and webtransport clients only receive
+    /// a close packet, see [`CloseReason`] for more details.
+    ///
+    /// The message is truncated to 123 bytes, the maximum length of a websocket close frame message.
+    ///
+    /// The code should be a valid websocket close code, in the `1000..=1003`, `1007..=1014` or
+    /// `3000..=4999` ranges, the latter being reserved for applications. Any other code is replaced
+    /// with the `1008` policy violation code, see [`CloseReason::FALLBACK_CODE`].
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |s: SocketRef| {
+    ///     s.on("ban", |s: SocketRef| {
+    ///         s.close_with_reason(4003, "you have been banned");
+    ///     });
+    /// });
+    /// ```
+    pub fn close_with_reason(&self, code: u16, message: impl Into<Cow<'static, str>>) {
+        self.esocket
+            .close_with_reason(CloseReason::new(code, message.into()));
+    }
+
+    /// # Disconnect the socket from the current namespace once the packets already emitted are sent.
+    ///
+    /// Same as [`Socket::disconnect`], but it first waits for all the packets emitted to the socket
+    /// to be consumed by the transport, at most for the
+    /// [`disconnect_flush_timeout`](crate::SocketIoBuilder::disconnect_flush_timeout) of the server.
+    /// With the polling transport, the packets are consumed by the next polling request of the client.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// async fn handler(s: SocketRef) {
+    ///     s.emit("goodbye", "see you soon").ok();
+    ///     s.disconnect_after_flush().await.ok();
+    /// }
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", handler);
+    /// ```
+    pub async fn disconnect_after_flush(self: Arc<Self>) -> Result<(), SocketError> {
+        let timeout = self.get_io().config().disconnect_flush_timeout;
+        tokio::time::timeout(timeout, self.esocket.flush())
+            .await
+            .ok();
+        self.disconnect()
+    }
+
+    /// # Return true if the socket session has been recovered.
+    ///
+    /// It is only possible when connection state recovery is enabled, see the
+    /// [`recovery`](crate::recovery) module doc for more details.
+    /// When recovered, the socket is already in its previous rooms and the missed packets were sent.
+    pub fn recovered(&self) -> bool {
+        self.recovered
+    }
+
+    /// # Attach custom data to the connect packet sent to the client.
+    ///
+    /// The data is merged with the socket id in the payload of the connect packet, so it
+    /// should serialize to a map (e.g. a struct), and the `sid` and `pid` keys are reserved.
+    ///
+    /// It should be called from a [connect middleware](crate::handler::connect#middlewares),
+    /// because the connect packet is sent before the connect handler is called.
+    /// The data is ignored for the clients using the socket.io v4 protocol,
+    /// whose connect packets don't have a payload.
+    ///
+    /// Returns an error if the data can't be serialized.
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, ParserError, handler::ConnectHandler, extract::*};
+    /// #[derive(serde::Serialize)]
+    /// struct Handshake {
+    ///     server_time: u64,
+    ///     features: Vec<&'static str>,
+    /// }
+    /// fn middleware(s: SocketRef) -> Result<(), ParserError> {
+    ///     s.connect_with(&Handshake {
+    ///         server_time: 1700000000,
+    ///         features: vec!["chat"],
+    ///     })
+    /// }
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", { || {} }.with(middleware));
+    /// ```
+    pub fn connect_with<T: ?Sized + Serialize>(&self, data: &T) -> Result<(), ParserError> {
+        #[derive(Serialize)]
+        struct ConnectPayload<'a, T: ?Sized> {
+            #[serde(flatten)]
+            packet: ConnectPacket,
+            #[serde(flatten)]
+            data: &'a T,
+        }
+        let payload = ConnectPayload {
+            packet: ConnectPacket::new(self.id).with_pid(self.pid),
+            data,
+        };
+        let payload = self.parser.encode_default(&payload)?;
+        self.connect_payload.lock().unwrap().replace(payload);
+        Ok(())
+    }
+
+    /// Take the connect packet payload set with [`Socket::connect_with`].
+    pub(crate) fn take_connect_payload(&self) -> Option<Value> {
+        self.connect_payload.lock().unwrap().take()
+    }
+
+    /// # Attach metadata to the presence of the socket.
+    ///
+    /// The metadata is returned with the socket by [`SocketIo::presence`] for each room it is in,
+    /// and sent to the presence subscribers with the join deltas of the socket.
+    /// If presence is enabled, an update delta is emitted for each room the socket is already in.
+    ///
+    /// See the [`presence`](crate::presence) module doc for more details.
+    ///
+    /// Returns an error if the metadata can't be serialized.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*, presence::PresenceConfig};
+    /// #[derive(serde::Serialize)]
+    /// struct User {
+    ///     name: String,
+    /// }
+    /// let (_, io) = SocketIo::builder()
+    ///     .with_presence(PresenceConfig::default())
+    ///     .build_svc();
+    /// io.ns("/", |s: SocketRef| {
+    ///     s.join("lobby");
+    ///     s.on("rename", |s: SocketRef, Data::<String>(name)| {
+    ///         s.set_presence_meta(User { name }).ok();
+    ///     });
+    /// });
+    /// ```
+    pub fn set_presence_meta<T: Serialize + Send + Sync + 'static>(
+        &self,
+        meta: T,
+    ) -> Result<(), ParserError> {
+        self.parser.encode_default(&meta)?;
+        let meta: Arc<dyn PresenceMeta> = Arc::new(meta);
+        self.presence_meta.write().unwrap().replace(meta.clone());
+        if self.ns.presence().is_some() {
+            for room in self.rooms() {
+                let meta = Some(meta.clone());
+                self.ns
+                    .emit_presence_delta(DeltaKind::Update, self.id, &room, meta);
+            }
+        }
+        Ok(())
+    }
+
+    /// # Subscribe the socket to the presence of the given rooms.
+    ///
+    /// The socket will receive a [`PresenceDelta`](crate::presence::PresenceDelta)
+    /// each time a socket joins or leaves one of these rooms or updates its presence metadata,
+    /// with the [`PresenceConfig::event`](crate::presence::PresenceConfig::event) event.
+    ///
+    /// It has no effect if presence is not enabled with
+    /// [`SocketIoBuilder::with_presence`](crate::SocketIoBuilder::with_presence).
+    /// See the [`presence`](crate::presence) module doc for more details.
+    pub fn subscribe_presence(&self, rooms: impl RoomParam) {
+        if let Some(presence) = self.ns.presence() {
+            let rooms = rooms.into_room_iter();
+            self.join(
+                rooms
+                    .map(|room| presence.subscribers_room(&room))
+                    .collect::<Vec<_>>(),
+            );
+        }
+    }
+
+    /// # Unsubscribe the socket from the presence of the given rooms.
+    ///
+    /// See [`Socket::subscribe_presence`] for more details.
+    pub fn unsubscribe_presence(&self, rooms: impl RoomParam) {
+        if let Some(presence) = self.ns.presence() {
+            let rooms = rooms.into_room_iter();
+            self.leave(
+                rooms
+                    .map(|room| presence.subscribers_room(&room))
+                    .collect::<Vec<_>>(),
+            );
+        }
+    }
+
+    /// The presence metadata set with [`Socket::set_presence_meta`].
+    pub(crate) fn presence_meta(&self) -> Option<Arc<dyn PresenceMeta>> {
+        self.presence_meta.read().unwrap().clone()
+    }
+
+    /// Encode the presence metadata to be shared with the other servers.
+    pub(crate) fn encode_presence_meta(&self) -> Option<Value> {
+        let meta = self.presence_meta.read().unwrap();
+        let res = meta.as_ref()?.encode(self.parser);
+        #[cfg(feature = "tracing")]
+        if let Err(e) = &res {
+            tracing::debug!(?self.id, "could not encode presence metadata: {e}");
+        }
+        res.ok()
+    }
+
     /// # Get the request info made by the client to connect.
     ///
     /// It might be used to retrieve the [`http::Extensions`]
@@ -625,6 +1511,27 @@ impl<A: Adapter> Socket<A> {
         &self.esocket.req_parts
     }
 
+    /// # Get the ip of the client connected to this [`Socket`].
+    ///
+    /// It is the ip of the peer of the connection, or the ip forwarded by the
+    /// [`trusted_proxies`](crate::SocketIoBuilder::trusted_proxies) if the client is behind them.
+    /// It is `None` if your http server doesn't insert a [`SocketAddr`](std::net::SocketAddr)
+    /// extension in the requests.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::builder()
+    ///     .trusted_proxies(["10.0.0.0/8".parse().unwrap()])
+    ///     .build_svc();
+    /// io.ns("/", |s: SocketRef| {
+    ///     println!("socket connected from {:?}", s.remote_addr());
+    /// });
+    /// ```
+    pub fn remote_addr(&self) -> Option<std::net::IpAddr> {
+        self.esocket.remote_addr()
+    }
+
     /// # Get the [`TransportType`](crate::TransportType) used by the client to connect with this [`Socket`].
     ///
     /// It can also be accessed as an extractor
@@ -668,7 +1575,10 @@ impl<A: Adapter> Socket<A> {
         if !self.esocket.is_closed() {
             #[cfg(feature = "tracing")]
             tracing::debug!("closing underlying transport for socket: {}", self.id);
-            self.esocket.close(EIoDisconnectReason::ClosingServer);
+            let timeout = self.get_io().config().disconnect_flush_timeout;
+            self.esocket
+                .close_after_flush(EIoDisconnectReason::ClosingServer, timeout)
+                .await;
         }
         self.esocket.closed().await;
     }
@@ -678,20 +1588,72 @@ impl<A: Adapter> Socket<A> {
     }
 
     pub(crate) fn reserve(&self) -> Result<Permit<'_>, SocketError> {
-        match self.esocket.reserve() {
+        self.reserve_with_priority(PacketPriority::Normal)
+    }
+
+    /// Reserve a permit in the queue of the given priority of the engine.io socket,
+    /// used to send the acks before the pending events and the volatile events after them.
+    pub(crate) fn reserve_with_priority(
+        &self,
+        priority: PacketPriority,
+    ) -> Result<Permit<'_>, SocketError> {
+        match self.esocket.reserve_with_priority(priority) {
             Ok(permit) => Ok(permit),
             Err(TrySendError::Full(_)) => Err(SocketError::InternalChannelFull),
             Err(TrySendError::Closed(_)) => Err(SocketError::Closed),
         }
     }
 
+    /// Returns true if the underlying engine.io transport is being upgraded.
+    pub(crate) fn is_upgrading(&self) -> bool {
+        self.esocket.is_upgrading()
+    }
+
+    /// Called for every event emitted on this socket.
+    fn event_sent(&self, data: &Value) {
+        #[cfg(feature = "admin-ui")]
+        if let Some(admin) = &self.ns.admin {
+            admin.event_sent(self, data);
+        }
+        let handlers = self.any_outgoing_handlers.read().unwrap();
+        if !handlers.is_empty() {
+            if let Ok(event) = self.parser.read_event(data) {
+                handlers
+                    .iter()
+                    .for_each(|handler| handler(self, event, data));
+            }
+        }
+    }
+
+    /// Call the outbound interceptors with a packet and encode it.
+    pub(crate) fn encode(&self, mut packet: Packet) -> Value {
+        self.ns.interceptors.outbound(&mut packet);
+        self.parser.encode(packet)
+    }
+
+    #[cfg(feature = "tracing")]
+    fn emit_span(&self) -> tracing::Span {
+        tracing::debug_span!(parent: self.esocket.span(), "socketio.emit", ns = %self.ns.path)
+    }
+
     pub(crate) fn send(&self, packet: Packet) -> Result<(), SocketError> {
+        #[cfg(feature = "tracing")]
+        let _span = self.emit_span().entered();
         let permit = self.reserve()?;
-        permit.send(packet, self.parser);
+        permit.send(packet, self);
         Ok(())
     }
     pub(crate) fn send_raw(&self, value: Value) -> Result<(), SocketError> {
-        let permit = self.reserve()?;
+        self.send_raw_with_priority(value, PacketPriority::Normal)
+    }
+    pub(crate) fn send_raw_with_priority(
+        &self,
+        value: Value,
+        priority: PacketPriority,
+    ) -> Result<(), SocketError> {
+        #[cfg(feature = "tracing")]
+        let _span = self.emit_span().entered();
+        let permit = self.reserve_with_priority(priority)?;
         permit.send_raw(value);
         Ok(())
     }
@@ -705,19 +1667,27 @@ impl<A: Adapter> Socket<A> {
 
         let ack = self.ack_counter.fetch_add(1, Ordering::SeqCst) + 1;
         packet.inner.set_ack_id(ack);
-        permit.send(packet, self.parser);
-        self.ack_message.lock().unwrap().insert(ack, tx);
+        permit.send(packet, self);
+        self.ack_message
+            .lock()
+            .unwrap()
+            .insert(ack, PendingAck::new(tx));
         rx
     }
 
+    /// Send a broadcasted packet with an ack id. The packet is not intercepted
+    /// because it was already intercepted once by the [`BroadcastOperators`].
     pub(crate) fn send_with_ack(&self, mut packet: Packet) -> Receiver<AckResult<Value>> {
         let (tx, rx) = oneshot::channel();
 
         let ack = self.ack_counter.fetch_add(1, Ordering::SeqCst) + 1;
         packet.inner.set_ack_id(ack);
-        match self.send(packet) {
+        match self.send_raw(self.parser.encode(packet)) {
             Ok(()) => {
-                self.ack_message.lock().unwrap().insert(ack, tx);
+                self.ack_message
+                    .lock()
+                    .unwrap()
+                    .insert(ack, PendingAck::new(tx));
             }
             Err(e) => {
                 tx.send(Err(AckError::Socket(e))).ok();
@@ -737,10 +1707,25 @@ impl<A: Adapter> Socket<A> {
             #[cfg(feature = "tracing")]
             tracing::trace!(?reason, ?self.id, "spawning disconnect handler");
 
-            handler.call(self.clone(), reason);
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!(
+                parent: self.esocket.span(),
+                "socketio.disconnect",
+                ns = %self.ns.path,
+                ?reason,
+            )
+            .entered();
+            handler::call_guarded(&self, HandlerKind::Disconnect, None, || {
+                handler.call(self.clone(), reason)
+            });
         }
 
+        self.ns.persist_session(&self, reason);
         self.ns.remove_socket(self.id);
+        #[cfg(feature = "admin-ui")]
+        if let Some(admin) = &self.ns.admin {
+            admin.socket_disconnected(&self.ns.path, self.id, reason);
+        }
     }
 
     /// Receive data from client
@@ -752,27 +1737,158 @@ impl<A: Adapter> Socket<A> {
                 self.close(DisconnectReason::ClientNSDisconnect);
                 Ok(())
             }
-            _ => unreachable!(),
+            // Connect errors are only sent by the server
+            _ => Err(Error::InvalidPacketType),
         }
     }
 
     fn recv_event(self: Arc<Self>, data: Value, ack: Option<i64>) -> Result<(), Error> {
-        let event = self.parser.read_event(&data).map_err(|_e| {
+        self.parser.read_event(&data).map_err(|_e| {
             #[cfg(feature = "tracing")]
             tracing::debug!(?_e, "failed to read event");
             Error::InvalidEventName
         })?;
+        #[cfg(feature = "admin-ui")]
+        if let Some(admin) = &self.ns.admin {
+            admin.event_received(&self, &data);
+        }
+
+        if let Some(limiter) = &self.rate_limiter {
+            match limiter.policy {
+                RateLimitPolicy::Queue => {
+                    limiter.enqueue(&self, data, ack);
+                    return Ok(());
+                }
+                policy if !limiter.try_acquire() => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(?self.id, ?policy, "event rate limit exceeded");
+                    if policy == RateLimitPolicy::Disconnect {
+                        self.send(Packet::disconnect(self.ns.path.clone())).ok();
+                        self.close(DisconnectReason::RateLimitExceeded);
+                    }
+                    return Ok(());
+                }
+                _ => (),
+            }
+        }
+        self.handle_event(data, ack);
+        Ok(())
+    }
+
+    /// Call the handler of an event. The event name should already be validated.
+    ///
+    /// If the number of concurrent handlers is limited, the event is queued until a permit is available.
+    /// If the queue is full, the event is dropped and the socket is disconnected.
+    pub(crate) fn handle_event(self: Arc<Self>, data: Value, ack: Option<i64>) {
+        let Some(queue) = self.handler_queue.clone() else {
+            return self.dispatch_event(data, ack, None);
+        };
+        let socket = self.clone();
+        let job = Box::new(move |permit| socket.dispatch_event(data, ack, Some(permit)));
+        if queue.push(job).is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(?self.id, "handler queue full, disconnecting socket");
+            self.send(Packet::disconnect(self.ns.path.clone())).ok();
+            self.close(DisconnectReason::HandlerQueueFull);
+        }
+    }
+
+    fn dispatch_event(
+        self: Arc<Self>,
+        data: Value,
+        ack: Option<i64>,
+        permit: Option<OwnedSemaphorePermit>,
+    ) {
+        let Ok(event) = self.parser.read_event(&data) else {
+            return;
+        };
         #[cfg(feature = "tracing")]
         tracing::debug!(?event, "reading");
-        if let Some(handler) = self.message_handlers.read().unwrap().get(event) {
-            handler.call(self.clone(), data, ack);
+        for handler in self.any_handlers.read().unwrap().iter() {
+            handler(&self, event, &data);
+        }
+        // The handler is cloned so that it can remove or replace handlers of this socket.
+        let handler = self
+            .message_handlers
+            .read()
+            .unwrap()
+            .get_key_value(event)
+            .map(|(event, handler)| (event.clone(), handler.clone()))
+            .or_else(|| {
+                let router = self.router.read().unwrap();
+                let handler = router.as_ref()?.handler(event)?;
+                Some((Cow::Owned(event.to_owned()), handler))
+            })
+            .or_else(|| {
+                let handlers = self.prefix_handlers.read().unwrap();
+                let (_, handler) = handlers.iter().find(|(p, _)| event.starts_with(&**p))?;
+                Some((Cow::Owned(event.to_owned()), handler.clone()))
+            });
+        if let Some((event, handler)) = handler {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!(
+                parent: self.esocket.span(),
+                "socketio.event",
+                ns = %self.ns.path,
+                %event,
+            )
+            .entered();
+            handler::call_guarded(&self, HandlerKind::Message(event), permit, || {
+                handler.call(self.clone(), data, ack)
+            });
+        }
+    }
+
+    /// The params captured by the route of the event in the [router](Socket::on_router),
+    /// if the event has no handler registered with [`Socket::on`].
+    pub(crate) fn route_params(&self, event: &str) -> Option<Params> {
+        if self.message_handlers.read().unwrap().contains_key(event) {
+            return None;
+        }
+        let router = self.router.read().unwrap();
+        router.as_ref()?.at(event).map(|(_, params)| params)
+    }
+
+    /// The longest prefix registered with [`Socket::on_prefix`] that matches the event,
+    /// if the event has no handler registered with [`Socket::on`] or with the router.
+    pub(crate) fn matched_prefix(&self, event: &str) -> Option<Cow<'static, str>> {
+        if self.message_handlers.read().unwrap().contains_key(event) {
+            return None;
+        }
+        let router = self.router.read().unwrap();
+        if router.as_ref().is_some_and(|r| r.handler(event).is_some()) {
+            return None;
+        }
+        let handlers = self.prefix_handlers.read().unwrap();
+        let (prefix, _) = handlers.iter().find(|(p, _)| event.starts_with(&**p))?;
+        Some(prefix.clone())
+    }
+
+    /// Report a panic caught in a handler of this socket and
+    /// disconnect it if [`disconnect_on_handler_panic`](crate::SocketIoBuilder::disconnect_on_handler_panic) is enabled.
+    pub(crate) fn on_handler_panic(
+        self: &Arc<Self>,
+        handler: HandlerKind,
+        payload: Box<dyn Any + Send>,
+    ) {
+        let panic = HandlerPanic::new(self.ns.path.clone(), self.id, handler, payload.as_ref());
+        #[cfg(feature = "tracing")]
+        tracing::error!(?panic.handler, ?panic.message, ?self.id, ns = %self.ns.path, "handler panicked");
+        self.ns.handler_errors.report(&panic);
+        if self.ns.disconnect_on_handler_panic
+            && panic.handler != HandlerKind::Disconnect
+            && self.connected()
+        {
+            self.send(Packet::disconnect(self.ns.path.clone())).ok();
+            self.clone().close(DisconnectReason::HandlerPanic);
         }
-        Ok(())
     }
 
     fn recv_ack(self: Arc<Self>, data: Value, ack: i64) -> Result<(), Error> {
-        if let Some(tx) = self.ack_message.lock().unwrap().remove(&ack) {
-            tx.send(Ok(data)).ok();
+        if let Some(pending) = self.ack_message.lock().unwrap().remove(&ack) {
+            #[cfg(feature = "metrics")]
+            crate::metrics::ack_received(pending.sent_at.elapsed());
+            pending.tx.send(Ok(data)).ok();
         }
         Ok(())
     }
@@ -826,6 +1942,41 @@ impl Socket<LocalAdapter> {
 mod test {
     use super::*;
 
+    #[test]
+    fn disconnect_reason_kind() {
+        use DisconnectReason::*;
+        let reasons = [
+            TransportClose,
+            MultipleHttpPollingError,
+            PacketParsingError,
+            TransportError,
+            HeartbeatTimeout,
+            ClientNSDisconnect,
+            ServerNSDisconnect,
+            ClosingServer,
+            RateLimitExceeded,
+            SlowConsumer,
+            HandlerPanic,
+            ServerClose,
+            HandlerQueueFull,
+        ];
+        for reason in reasons {
+            let kinds = [
+                reason.is_graceful(),
+                reason.is_connection_lost(),
+                reason.is_server_initiated(),
+            ];
+            // A reason has at most one kind
+            assert!(kinds.iter().filter(|k| **k).count() <= 1, "{reason:?}");
+        }
+        assert!(ClientNSDisconnect.is_graceful());
+        assert!(HeartbeatTimeout.is_connection_lost());
+        assert!(SlowConsumer.is_server_initiated());
+        assert!(PacketParsingError.is_server_initiated());
+        assert!(MultipleHttpPollingError.is_server_initiated());
+        assert!(!PacketParsingError.is_graceful());
+    }
+
     #[tokio::test]
     async fn send_with_ack_error() {
         let sid = Sid::new();
@@ -843,9 +1994,6 @@ mod test {
         }
 
         let ack = socket.emit_with_ack::<_, ()>("test", &());
-        assert!(matches!(
-            ack,
-            Err(SendError::Socket(SocketError::InternalChannelFull))
-        ));
+        assert!(matches!(ack, Err(SendError::BufferFull(_))));
     }
 }
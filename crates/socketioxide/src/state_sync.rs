@@ -0,0 +1,324 @@
+//! Synchronization of a state shared by the members of rooms, with JSON patches.
+//!
+//! A [`StateSync`] tracks a serializable state for each room. Each time the state of a room is
+//! [updated](StateSync::update), only its difference with the previous state is emitted to the
+//! members of the room, as a [JSON merge patch](https://www.rfc-editor.org/rfc/rfc7386).
+//! A full snapshot is emitted instead every [`snapshot_interval`](StateSync::snapshot_interval)
+//! updates, so that the clients that missed a patch catch up.
+//!
+//! The updates are emitted with the event of the [`StateSync`] as [`StateUpdate`]s, with a version
+//! incremented at each update. A client should apply a patch only if its version follows the
+//! version of its state, and otherwise wait for the next snapshot.
+//! The sockets joining a room can get the current snapshot with [`StateSync::join`].
+//!
+//! The updates of a room should not be made concurrently, otherwise they may be emitted out of order.
+//!
+//! **Note**: JSON merge patches can't set a value to `null`: the `null` fields of the state
+//! are removed from the state of the clients.
+//!
+//! # Example
+//! ```
+//! # use socketioxide::{SocketIo, extract::*, state_sync::StateSync};
+//! # use std::{collections::HashMap, sync::Arc};
+//! #[derive(serde::Serialize)]
+//! struct Scores {
+//!     teams: HashMap<String, u32>,
+//! }
+//!
+//! let (_, io) = SocketIo::new_svc();
+//! let sync = Arc::new(StateSync::<Scores>::new(io.clone(), "scores").snapshot_interval(50));
+//!
+//! let sync_clone = sync.clone();
+//! io.ns("/", move |socket: SocketRef| {
+//!     // The socket joins the room and receives the current snapshot
+//!     sync_clone.join(&socket, "match-1").ok();
+//! });
+//!
+//! // Later, from the game loop
+//! # async fn game_loop(sync: Arc<StateSync<Scores>>) {
+//! let scores = Scores { teams: [("red".into(), 3)].into() };
+//! sync.update("match-1", &scores).await.ok();
+//! # }
+//! ```
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+    marker::PhantomData,
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use socketioxide_core::adapter::Room;
+
+use crate::{
+    adapter::{Adapter, LocalAdapter},
+    socket::Socket,
+    BroadcastError, ErrorKind, SendError, SocketIo,
+};
+
+/// An update of the state of a room, emitted to its members.
+///
+/// It is serialized as an object with a `type` field: `"snapshot"` or `"patch"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StateUpdate {
+    /// The full state of the room.
+    Snapshot {
+        /// The version of the state.
+        version: u64,
+        /// The state.
+        state: Value,
+    },
+    /// A JSON merge patch to apply to the state of the previous version, see [`apply_patch`].
+    Patch {
+        /// The version of the state once the patch is applied.
+        version: u64,
+        /// The patch.
+        patch: Value,
+    },
+}
+
+/// Error type for the [`StateSync::update`] method.
+#[non_exhaustive]
+#[derive(thiserror::Error, Debug)]
+pub enum StateSyncError {
+    /// The state cannot be serialized to JSON.
+    #[error("Error serializing state: {0:?}")]
+    Serialize(#[from] serde_json::Error),
+    /// The namespace of the [`StateSync`] does not exist.
+    #[error("Namespace not found")]
+    NsNotFound,
+    /// The update could not be broadcasted to the room.
+    #[error("Error broadcasting update: {0:?}")]
+    Broadcast(#[from] BroadcastError),
+}
+
+impl StateSyncError {
+    /// The [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            StateSyncError::Serialize(_) => ErrorKind::Serialization,
+            StateSyncError::NsNotFound => ErrorKind::InvalidInput,
+            StateSyncError::Broadcast(err) => err.kind(),
+        }
+    }
+}
+
+/// The state of a room and its version.
+#[derive(Debug)]
+struct RoomState {
+    state: Value,
+    version: u64,
+}
+
+/// Tracks a state of type `T` per room and emits its patches to the members of the rooms.
+/// See the [module level documentation](self) for more details.
+pub struct StateSync<T, A: Adapter = LocalAdapter> {
+    io: SocketIo<A>,
+    ns: Cow<'static, str>,
+    event: Cow<'static, str>,
+    snapshot_interval: u64,
+    rooms: Mutex<HashMap<Room, RoomState>>,
+    _state: PhantomData<fn(&T)>,
+}
+
+impl<T: Serialize, A: Adapter> StateSync<T, A> {
+    /// Create a [`StateSync`] emitting the updates with the given event
+    /// to the rooms of the default namespace `"/"`.
+    pub fn new(io: SocketIo<A>, event: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            io,
+            ns: Cow::Borrowed("/"),
+            event: event.into(),
+            snapshot_interval: 100,
+            rooms: Mutex::new(HashMap::new()),
+            _state: PhantomData,
+        }
+    }
+
+    /// The namespace of the rooms.
+    ///
+    /// Defaults to `"/"`.
+    pub fn ns(mut self, path: impl Into<Cow<'static, str>>) -> Self {
+        self.ns = path.into();
+        self
+    }
+
+    /// The number of updates after which a full snapshot is emitted instead of a patch.
+    ///
+    /// Defaults to 100.
+    ///
+    /// # Panics
+    /// If `interval` is 0.
+    pub fn snapshot_interval(mut self, interval: u64) -> Self {
+        assert!(interval > 0, "the snapshot interval must be > 0");
+        self.snapshot_interval = interval;
+        self
+    }
+
+    /// Update the state of a room and emit the change to its members.
+    ///
+    /// The first state of a room is emitted as a snapshot. Nothing is emitted if the state didn't change.
+    pub async fn update(&self, room: impl Into<Room>, state: &T) -> Result<(), StateSyncError> {
+        let state = serde_json::to_value(state)?;
+        let room = room.into();
+        let update = match self.rooms.lock().unwrap().entry(room.clone()) {
+            Entry::Vacant(entry) => {
+                entry.insert(RoomState {
+                    state: state.clone(),
+                    version: 1,
+                });
+                StateUpdate::Snapshot { version: 1, state }
+            }
+            Entry::Occupied(mut entry) => {
+                let room_state = entry.get_mut();
+                let Some(patch) = diff(&room_state.state, &state) else {
+                    return Ok(());
+                };
+                room_state.version += 1;
+                room_state.state = state;
+                let version = room_state.version;
+                if version % self.snapshot_interval == 0 {
+                    let state = room_state.state.clone();
+                    StateUpdate::Snapshot { version, state }
+                } else {
+                    StateUpdate::Patch { version, patch }
+                }
+            }
+        };
+
+        let ns = self.io.of(&self.ns).ok_or(StateSyncError::NsNotFound)?;
+        ns.to(room).emit(&self.event, &update).await?;
+        Ok(())
+    }
+
+    /// Make a socket join a room and emit the current snapshot of the room to it, if any.
+    pub fn join(&self, socket: &Socket<A>, room: impl Into<Room>) -> Result<(), SendError> {
+        let room = room.into();
+        socket.join(room.clone());
+        match self.snapshot(&room) {
+            Some(snapshot) => socket.emit(&self.event, &snapshot),
+            None => Ok(()),
+        }
+    }
+
+    /// The current snapshot of a room, if its state was set.
+    pub fn snapshot(&self, room: &str) -> Option<StateUpdate> {
+        let rooms = self.rooms.lock().unwrap();
+        rooms.get(room).map(|room| StateUpdate::Snapshot {
+            version: room.version,
+            state: room.state.clone(),
+        })
+    }
+
+    /// Stop tracking the state of a room. The next update of the room will be emitted as a snapshot.
+    pub fn remove(&self, room: &str) {
+        self.rooms.lock().unwrap().remove(room);
+    }
+}
+
+impl<T, A: Adapter> std::fmt::Debug for StateSync<T, A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateSync")
+            .field("ns", &self.ns)
+            .field("event", &self.event)
+            .field("snapshot_interval", &self.snapshot_interval)
+            .field("rooms", &self.rooms)
+            .finish()
+    }
+}
+
+/// Compute the JSON merge patch turning `old` into `new`.
+///
+/// Returns `None` if the values are equal.
+pub fn diff(old: &Value, new: &Value) -> Option<Value> {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut patch = Map::new();
+            for key in old.keys().filter(|key| !new.contains_key(*key)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            for (key, value) in new {
+                let change = match old.get(key) {
+                    Some(old) => diff(old, value),
+                    None => Some(value.clone()),
+                };
+                if let Some(change) = change {
+                    patch.insert(key.clone(), change);
+                }
+            }
+            (!patch.is_empty()).then_some(Value::Object(patch))
+        }
+        _ if old == new => None,
+        _ => Some(new.clone()),
+    }
+}
+
+/// Apply a JSON merge patch to a value, as defined by
+/// [RFC 7386](https://www.rfc-editor.org/rfc/rfc7386).
+pub fn apply_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        unreachable!()
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            apply_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn diff_and_apply() {
+        let old = json!({ "a": 1, "b": { "c": 2, "d": [1, 2] }, "e": "foo" });
+        let new = json!({ "a": 1, "b": { "c": 3, "d": [1, 2] }, "f": { "g": true } });
+        let patch = diff(&old, &new).unwrap();
+        assert_eq!(
+            patch,
+            json!({ "b": { "c": 3 }, "e": null, "f": { "g": true } })
+        );
+
+        let mut target = old.clone();
+        apply_patch(&mut target, &patch);
+        assert_eq!(target, new);
+
+        assert_eq!(diff(&new, &new), None);
+        assert_eq!(diff(&json!([1, 2]), &json!([1])), Some(json!([1])));
+    }
+
+    #[test]
+    fn apply_non_object() {
+        let mut target = json!([1]);
+        apply_patch(&mut target, &json!({ "a": { "b": null, "c": 1 } }));
+        assert_eq!(target, json!({ "a": { "c": 1 } }));
+        apply_patch(&mut target, &json!(2));
+        assert_eq!(target, json!(2));
+    }
+
+    #[test]
+    fn update_serialization() {
+        let update = StateUpdate::Patch {
+            version: 2,
+            patch: json!({ "a": 1 }),
+        };
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            json!({ "type": "patch", "version": 2, "patch": { "a": 1 } })
+        );
+    }
+}
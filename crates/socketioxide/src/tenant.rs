@@ -0,0 +1,379 @@
+//! ## Multiple socket.io servers sharing a single engine.io endpoint.
+//!
+//! Each [`SocketIo`] instance given to [`Tenants`] is an independent socket.io server, with its own
+//! config, parser, adapter and namespaces. They are served on the same engine.io endpoint and each
+//! connection is routed to the instance whose namespace prefix matches the namespace of the first
+//! connect packet of the client. It avoids running a server on a separate port for each tenant.
+//!
+//! * The namespaces of each instance are registered with their full path, prefix included.
+//! * A connection is bound to a single instance for its whole lifetime, so a client
+//!   can't connect to the namespaces of several instances with the same connection.
+//! * The connections of the clients using the socket.io v4 protocol, which are connected to the
+//!   main namespace without any connect packet, and the connections to a namespace that matches
+//!   no prefix are routed to the [`fallback`](Tenants::fallback) instance, or closed if there is none.
+//! * The engine.io settings of the endpoint (path, heartbeat, payload and buffer sizes...)
+//!   are the ones of the first instance, the engine.io settings of the other instances are ignored.
+//!
+//! #### Example with axum :
+//! ```rust
+//! # use socketioxide::{SocketIo, tenant::Tenants, extract::SocketRef};
+//! # use std::time::Duration;
+//! let (_, io_a) = SocketIo::new_svc();
+//! let (_, io_b) = SocketIo::builder()
+//!     .ack_timeout(Duration::from_secs(30))
+//!     .build_svc();
+//!
+//! io_a.ns("/tenant-a", |s: SocketRef| {});
+//! io_b.ns("/tenant-b/{room}", |s: SocketRef| {});
+//!
+//! let layer = Tenants::new()
+//!     .tenant("/tenant-a", io_a)
+//!     .tenant("/tenant-b", io_b)
+//!     .build_layer();
+//!
+//! let app = axum::Router::<()>::new().layer(layer);
+//! ```
+use std::{
+    borrow::Cow,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use engineioxide::{
+    config::EngineIoConfig,
+    handler::EngineIoHandler,
+    service::{EngineIoService, NotFoundService},
+    socket::{DisconnectReason as EIoDisconnectReason, Socket as EIoSocket},
+    Str,
+};
+use http::{Request, Response};
+use http_body::Body;
+use hyper::service::Service as HyperSvc;
+use socketioxide_core::{
+    packet::{Packet, PacketData},
+    parser::{Parse, ParserState},
+};
+use tower_layer::Layer;
+use tower_service::Service as TowerSvc;
+
+use crate::{
+    adapter::{Adapter, LocalAdapter},
+    client::{Client, SocketData},
+    parser::{ParseError, Parser},
+    ProtocolVersion, SocketIo,
+};
+
+/// A builder of a service routing the connections of a single engine.io endpoint
+/// to several [`SocketIo`] instances. See the [module level documentation](self) for more details.
+pub struct Tenants<A: Adapter = LocalAdapter> {
+    tenants: Vec<(Cow<'static, str>, SocketIo<A>)>,
+    fallback: Option<SocketIo<A>>,
+}
+
+impl<A: Adapter> Tenants<A> {
+    /// Create a new builder without any tenant.
+    pub fn new() -> Self {
+        Self {
+            tenants: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    /// Route the connections to the namespaces starting with `prefix` to the `io` instance.
+    ///
+    /// The prefix matches whole path segments: `/tenant-a` matches `/tenant-a` and
+    /// `/tenant-a/chat` but not `/tenant-ab`. The prefixes are tried in the order they are added.
+    pub fn tenant(mut self, prefix: impl Into<Cow<'static, str>>, io: SocketIo<A>) -> Self {
+        self.tenants.push((prefix.into(), io));
+        self
+    }
+
+    /// Route the connections that match no prefix to the `io` instance,
+    /// including all the connections of the clients using the socket.io v4 protocol.
+    pub fn fallback(mut self, io: SocketIo<A>) -> Self {
+        self.fallback = Some(io);
+        self
+    }
+
+    /// Build a [`TenantLayer`] serving the tenants.
+    ///
+    /// # Panics
+    /// If no tenant nor fallback was added.
+    pub fn build_layer(self) -> TenantLayer<A> {
+        let (router, config) = self.into_router();
+        TenantLayer { router, config }
+    }
+
+    /// Build a standalone [`TenantService`] serving the tenants,
+    /// that returns a 404 error for every non-socket.io request.
+    ///
+    /// # Panics
+    /// If no tenant nor fallback was added.
+    pub fn build_svc(self) -> TenantService<NotFoundService, A> {
+        self.build_with_inner_svc(NotFoundService)
+    }
+
+    /// Build a [`TenantService`] serving the tenants with an inner service for the non-socket.io requests.
+    ///
+    /// # Panics
+    /// If no tenant nor fallback was added.
+    pub fn build_with_inner_svc<S: Clone>(self, inner: S) -> TenantService<S, A> {
+        let (router, config) = self.into_router();
+        TenantService::new(inner, router, config)
+    }
+
+    fn into_router(self) -> (Arc<TenantRouter<A>>, EngineIoConfig) {
+        let first = self
+            .tenants
+            .first()
+            .map(|(_, io)| io)
+            .or(self.fallback.as_ref())
+            .expect("at least one tenant or a fallback should be added");
+        let config = first.config().engine_config.clone();
+        let connect_timeout = first.config().connect_timeout;
+        let router = TenantRouter {
+            tenants: self
+                .tenants
+                .into_iter()
+                .map(|(prefix, io)| (prefix, io.client().clone()))
+                .collect(),
+            fallback: self.fallback.map(|io| io.client().clone()),
+            connect_timeout,
+        };
+        (Arc::new(router), config)
+    }
+}
+
+impl<A: Adapter> Default for Tenants<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Adapter> std::fmt::Debug for Tenants<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prefixes: Vec<_> = self.tenants.iter().map(|(prefix, _)| prefix).collect();
+        f.debug_struct("Tenants")
+            .field("prefixes", &prefixes)
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+/// The engine.io handler binding each session to the socket.io client of a tenant.
+///
+/// A session is bound to a tenant when the first packet of the session is received,
+/// once its namespace is matched against the tenant prefixes.
+pub struct TenantRouter<A: Adapter> {
+    tenants: Vec<(Cow<'static, str>, Arc<Client<A>>)>,
+    fallback: Option<Arc<Client<A>>>,
+    connect_timeout: std::time::Duration,
+}
+
+impl<A: Adapter> TenantRouter<A> {
+    /// Find the client whose prefix matches the namespace of the connect packet decoded with its parser.
+    fn route(
+        &self,
+        decode: impl Fn(Parser) -> Result<Packet, ParseError>,
+    ) -> Option<&Arc<Client<A>>> {
+        let matches = |prefix: &str, client: &Client<A>| match decode(client.parser()) {
+            Ok(Packet {
+                inner: PacketData::Connect(_),
+                ns,
+            }) => ns.strip_prefix(prefix).is_some_and(|rest| {
+                rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/')
+            }),
+            _ => false,
+        };
+        self.tenants
+            .iter()
+            .find(|(prefix, client)| matches(prefix, client))
+            .map(|(_, client)| client)
+            .or(self.fallback.as_ref())
+    }
+
+    /// Bind the session to the given client, or close it if there is none.
+    fn bind(
+        client: Option<&Arc<Client<A>>>,
+        socket: &Arc<EIoSocket<SocketData<A>>>,
+    ) -> Option<Arc<Client<A>>> {
+        match client {
+            Some(client) => {
+                client.clone().on_connect(socket.clone());
+                Some(client.clone())
+            }
+            None => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(sid = ?socket.id, "no tenant for this connection, closing it");
+                socket.close(EIoDisconnectReason::TransportClose);
+                None
+            }
+        }
+    }
+}
+
+impl<A: Adapter> EngineIoHandler for TenantRouter<A> {
+    type Data = SocketData<A>;
+
+    fn on_connect(self: Arc<Self>, socket: Arc<EIoSocket<SocketData<A>>>) {
+        if ProtocolVersion::from(socket.protocol) == ProtocolVersion::V4 {
+            Self::bind(self.fallback.as_ref(), &socket);
+            return;
+        }
+        // Close the sessions that never send a connect packet
+        let socket = Arc::downgrade(&socket);
+        let timeout = self.connect_timeout;
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            if let Some(socket) = socket.upgrade().filter(|s| s.data.io.get().is_none()) {
+                socket.close(EIoDisconnectReason::TransportClose);
+            }
+        });
+    }
+
+    fn on_disconnect(&self, socket: Arc<EIoSocket<SocketData<A>>>, reason: EIoDisconnectReason) {
+        if let Some(client) = socket.data.io.get().map(|io| io.client().clone()) {
+            client.on_disconnect(socket, reason);
+        }
+    }
+
+    fn on_message(self: &Arc<Self>, msg: Str, socket: Arc<EIoSocket<SocketData<A>>>) {
+        let client = match socket.data.io.get() {
+            Some(io) => Some(io.client().clone()),
+            None => {
+                let client = self.route(|p| p.decode_str(&ParserState::default(), msg.clone()));
+                Self::bind(client, &socket)
+            }
+        };
+        if let Some(client) = client {
+            client.on_message(msg, socket);
+        }
+    }
+
+    fn on_binary(self: &Arc<Self>, data: Bytes, socket: Arc<EIoSocket<SocketData<A>>>) {
+        let client = match socket.data.io.get() {
+            Some(io) => Some(io.client().clone()),
+            None => {
+                let client = self.route(|p| p.decode_bin(&ParserState::default(), data.clone()));
+                Self::bind(client, &socket)
+            }
+        };
+        if let Some(client) = client {
+            client.on_binary(data, socket);
+        }
+    }
+}
+
+impl<A: Adapter> std::fmt::Debug for TenantRouter<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prefixes: Vec<_> = self.tenants.iter().map(|(prefix, _)| prefix).collect();
+        f.debug_struct("TenantRouter")
+            .field("prefixes", &prefixes)
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+/// A [`Tower`](TowerSvc)/[`Hyper`](HyperSvc) Service serving several socket.io servers
+/// on a single engine.io endpoint, built with [`Tenants`].
+pub struct TenantService<S, A: Adapter = LocalAdapter> {
+    engine_svc: EngineIoService<TenantRouter<A>, S>,
+}
+
+impl<S: Clone, A: Adapter> TenantService<S, A> {
+    fn new(inner: S, router: Arc<TenantRouter<A>>, config: EngineIoConfig) -> Self {
+        Self {
+            engine_svc: EngineIoService::with_config_inner(inner, router, config),
+        }
+    }
+}
+
+/// Tower Service implementation.
+impl<S, ReqBody, ResBody, A> TowerSvc<Request<ReqBody>> for TenantService<S, A>
+where
+    ReqBody: Body + Send + Unpin + std::fmt::Debug + 'static,
+    <ReqBody as Body>::Error: std::fmt::Debug,
+    <ReqBody as Body>::Data: Send,
+    ResBody: Body + Send + 'static,
+    S: TowerSvc<Request<ReqBody>, Response = Response<ResBody>>,
+    A: Adapter,
+{
+    type Response = <EngineIoService<TenantRouter<A>, S> as TowerSvc<Request<ReqBody>>>::Response;
+    type Error = <EngineIoService<TenantRouter<A>, S> as TowerSvc<Request<ReqBody>>>::Error;
+    type Future = <EngineIoService<TenantRouter<A>, S> as TowerSvc<Request<ReqBody>>>::Future;
+
+    #[inline(always)]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.engine_svc.poll_ready(cx)
+    }
+    #[inline(always)]
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        self.engine_svc.call(req)
+    }
+}
+
+/// Hyper 1.0 Service implementation.
+impl<S, ReqBody, ResBody, A> HyperSvc<Request<ReqBody>> for TenantService<S, A>
+where
+    ReqBody: Body + Send + Unpin + std::fmt::Debug + 'static,
+    <ReqBody as Body>::Error: std::fmt::Debug,
+    <ReqBody as Body>::Data: Send,
+    ResBody: Body + Send + 'static,
+    S: HyperSvc<Request<ReqBody>, Response = Response<ResBody>>,
+    A: Adapter,
+{
+    type Response = <EngineIoService<TenantRouter<A>, S> as HyperSvc<Request<ReqBody>>>::Response;
+    type Error = <EngineIoService<TenantRouter<A>, S> as HyperSvc<Request<ReqBody>>>::Error;
+    type Future = <EngineIoService<TenantRouter<A>, S> as HyperSvc<Request<ReqBody>>>::Future;
+
+    #[inline(always)]
+    fn call(&self, req: Request<ReqBody>) -> Self::Future {
+        self.engine_svc.call(req)
+    }
+}
+
+impl<S: Clone, A: Adapter> Clone for TenantService<S, A> {
+    fn clone(&self) -> Self {
+        Self {
+            engine_svc: self.engine_svc.clone(),
+        }
+    }
+}
+
+impl<S, A: Adapter> std::fmt::Debug for TenantService<S, A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantService").finish()
+    }
+}
+
+/// A [`Layer`] for [`TenantService`], acting as a middleware.
+pub struct TenantLayer<A: Adapter = LocalAdapter> {
+    router: Arc<TenantRouter<A>>,
+    config: EngineIoConfig,
+}
+
+impl<A: Adapter> Clone for TenantLayer<A> {
+    fn clone(&self) -> Self {
+        Self {
+            router: self.router.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<A: Adapter> std::fmt::Debug for TenantLayer<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantLayer")
+            .field("router", &self.router)
+            .finish()
+    }
+}
+
+impl<S: Clone, A: Adapter> Layer<S> for TenantLayer<A> {
+    type Service = TenantService<S, A>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TenantService::new(inner, self.router.clone(), self.config.clone())
+    }
+}
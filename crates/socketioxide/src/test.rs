@@ -0,0 +1,380 @@
+//! An in-process socket.io client to test the handlers of a [`SocketIo`] instance.
+//!
+//! A [`TestClient`] is connected to a namespace of a [`SocketIo`] instance without any transport
+//! or TCP listener: its packets are directly piped to the server. It can then emit events,
+//! wait for their acknowledgements and receive the packets sent by the server.
+//!
+//! All the operations that wait for the server are bounded by the [`timeout`](TestClient::timeout)
+//! of the client, which defaults to the [`ack_timeout`](crate::SocketIoBuilder::ack_timeout)
+//! of the server, so that a misbehaving handler makes the test fail instead of hanging.
+//!
+//! # Example
+//! ```
+//! # use socketioxide::{SocketIo, extract::*, test::TestClient};
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let (_, io) = SocketIo::new_svc();
+//! io.ns("/", |socket: SocketRef| {
+//!     socket.on("echo", |socket: SocketRef, Data::<String>(data), ack: AckSender| {
+//!         socket.emit("echo", &data).ok();
+//!         ack.send(&data).ok();
+//!     });
+//! });
+//!
+//! let mut client = TestClient::connect(&io, "/").await.unwrap();
+//! let ack: String = client.emit_with_ack("echo", &"hello").await.unwrap();
+//! assert_eq!(ack, "hello");
+//!
+//! let event = client.recv_event().await.unwrap();
+//! assert_eq!(event.name(), "echo");
+//! assert_eq!(event.data::<String>().unwrap(), "hello");
+//!
+//! client.disconnect().await;
+//! # }
+//! ```
+use std::{collections::VecDeque, fmt, time::Duration};
+
+use engineioxide::{sid::Sid, Str};
+use serde::{de::DeserializeOwned, Serialize};
+use socketioxide_core::{
+    packet::{ConnectPacket, Packet, PacketData},
+    parser::{Parse, ParserState},
+    Value,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    adapter::Adapter,
+    parser::{ParseError, Parser},
+    ParserError, SocketIo,
+};
+
+type EIoPacket = engineioxide::Packet;
+
+/// Error type for the [`TestClient`] operations.
+#[non_exhaustive]
+#[derive(thiserror::Error, Debug)]
+pub enum TestClientError {
+    /// The connection to the namespace was refused by the server, with its message.
+    #[error("connection refused: {0}")]
+    ConnectRefused(String),
+
+    /// The server did not answer before the [`timeout`](TestClient::timeout) of the client.
+    #[error("timeout waiting for the server")]
+    Timeout,
+
+    /// The client was disconnected by the server.
+    #[error("client disconnected")]
+    Disconnected,
+
+    /// A payload could not be serialized or deserialized.
+    #[error("parser error: {0:?}")]
+    Parser(#[from] ParserError),
+
+    /// The server sent a packet that was not expected at this point.
+    #[error("unexpected packet: {0:?}")]
+    UnexpectedPacket(Packet),
+}
+
+/// An in-process socket.io client connected to a namespace of a [`SocketIo`] instance.
+/// See the [module level documentation](self) for more details.
+///
+/// The client is disconnected from the server when dropped.
+pub struct TestClient {
+    id: Sid,
+    ns: Str,
+    parser: Parser,
+    state: ParserState,
+    tx: mpsc::Sender<EIoPacket>,
+    rx: mpsc::Receiver<EIoPacket>,
+    ack_id: i64,
+    /// The packets received while waiting for an acknowledgement.
+    pending: VecDeque<Packet>,
+    timeout: Duration,
+    closed: bool,
+}
+
+impl TestClient {
+    /// Connect a new client to the namespace `ns` of the given [`SocketIo`] instance.
+    pub async fn connect<A: Adapter>(
+        io: &SocketIo<A>,
+        ns: impl Into<Str>,
+    ) -> Result<Self, TestClientError> {
+        Self::connect_inner(io, ns.into(), None).await
+    }
+
+    /// Connect a new client to the namespace `ns` of the given [`SocketIo`] instance
+    /// with an `auth` payload, available to the handlers with the
+    /// [`Data`](crate::extract::Data) extractor.
+    pub async fn connect_with_auth<A: Adapter>(
+        io: &SocketIo<A>,
+        ns: impl Into<Str>,
+        auth: &impl Serialize,
+    ) -> Result<Self, TestClientError> {
+        let auth = io.client().parser().encode_default(auth)?;
+        Self::connect_inner(io, ns.into(), Some(auth)).await
+    }
+
+    async fn connect_inner<A: Adapter>(
+        io: &SocketIo<A>,
+        ns: Str,
+        auth: Option<Value>,
+    ) -> Result<Self, TestClientError> {
+        let (tx, rx) = io.client().clone().new_piped_sock();
+        let mut client = Self {
+            id: Sid::ZERO,
+            ns: ns.clone(),
+            parser: io.client().parser(),
+            state: ParserState::default(),
+            tx,
+            rx,
+            ack_id: 0,
+            pending: VecDeque::new(),
+            timeout: io.config().ack_timeout,
+            closed: false,
+        };
+        client.send(Packet::connect(ns, auth)).await?;
+        let packet = client.next_packet().await?;
+        match packet.inner {
+            PacketData::Connect(ref value) => {
+                let connect: ConnectPacket = client.parser.decode_default(value.as_ref())?;
+                client.id = connect.sid;
+                Ok(client)
+            }
+            PacketData::ConnectError(message, _) => Err(TestClientError::ConnectRefused(message)),
+            _ => Err(TestClientError::UnexpectedPacket(packet)),
+        }
+    }
+
+    /// The id of the socket of this client on the server.
+    pub fn id(&self) -> Sid {
+        self.id
+    }
+
+    /// The namespace this client is connected to.
+    pub fn ns(&self) -> &str {
+        &self.ns
+    }
+
+    /// The maximum duration to wait for the server.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Set the maximum duration to wait for the server.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Emit an event to the server.
+    pub async fn emit<T: ?Sized + Serialize>(
+        &mut self,
+        event: &str,
+        data: &T,
+    ) -> Result<(), TestClientError> {
+        let value = self.parser.encode_value(data, Some(event))?;
+        self.send(Packet::event(self.ns.clone(), value)).await
+    }
+
+    /// Emit an event to the server and wait for its acknowledgement.
+    ///
+    /// The other packets received in the meantime are kept and returned by the next calls to
+    /// [`recv`](Self::recv) or [`recv_event`](Self::recv_event).
+    pub async fn emit_with_ack<T: ?Sized + Serialize, R: DeserializeOwned>(
+        &mut self,
+        event: &str,
+        data: &T,
+    ) -> Result<R, TestClientError> {
+        let value = self.parser.encode_value(data, Some(event))?;
+        self.ack_id += 1;
+        let ack_id = self.ack_id;
+        let mut packet = Packet::event(self.ns.clone(), value);
+        packet.inner.set_ack_id(ack_id);
+        self.send(packet).await?;
+
+        let mut received = Vec::new();
+        let res = tokio::time::timeout(self.timeout, async {
+            loop {
+                let packet = self.recv_packet().await?;
+                match packet.inner {
+                    PacketData::EventAck(mut value, id) | PacketData::BinaryAck(mut value, id)
+                        if id == ack_id =>
+                    {
+                        break Ok(self.parser.decode_value(&mut value, false)?);
+                    }
+                    _ => received.push(packet),
+                }
+            }
+        })
+        .await
+        .unwrap_or(Err(TestClientError::Timeout));
+        self.pending.extend(received);
+        res
+    }
+
+    /// Wait for the next packet sent by the server.
+    pub async fn recv(&mut self) -> Result<Packet, TestClientError> {
+        self.next_packet().await
+    }
+
+    /// Wait for the next event sent by the server.
+    ///
+    /// Returns a [`TestClientError::Disconnected`] error if the server disconnects the client and
+    /// a [`TestClientError::UnexpectedPacket`] error if any other packet is received.
+    pub async fn recv_event(&mut self) -> Result<TestEvent, TestClientError> {
+        let packet = self.next_packet().await?;
+        match packet.inner {
+            PacketData::Event(value, ack) | PacketData::BinaryEvent(value, ack) => {
+                let name = self.parser.read_event(&value)?.to_string();
+                Ok(TestEvent {
+                    name,
+                    value,
+                    ack,
+                    ns: self.ns.clone(),
+                    parser: self.parser,
+                    tx: self.tx.clone(),
+                })
+            }
+            PacketData::Disconnect => Err(TestClientError::Disconnected),
+            _ => Err(TestClientError::UnexpectedPacket(packet)),
+        }
+    }
+
+    /// Assert that the server does not send any packet during the given duration.
+    ///
+    /// # Panics
+    /// If a packet is received.
+    pub async fn expect_no_packet(&mut self, duration: Duration) {
+        if let Some(packet) = self.pending.pop_front() {
+            panic!("unexpected packet received: {packet:?}");
+        }
+        match tokio::time::timeout(duration, self.recv_packet()).await {
+            Ok(Ok(packet)) => panic!("unexpected packet received: {packet:?}"),
+            Ok(Err(e)) => panic!("unexpected error: {e}"),
+            Err(_) => (),
+        }
+    }
+
+    /// Disconnect the client from the namespace and close its connection.
+    pub async fn disconnect(mut self) {
+        self.send(Packet::disconnect(self.ns.clone())).await.ok();
+        self.tx.send(EIoPacket::Close).await.ok();
+        self.closed = true;
+    }
+
+    async fn send(&self, packet: Packet) -> Result<(), TestClientError> {
+        send(&self.tx, self.parser, packet).await
+    }
+
+    /// The next pending packet or the next packet received, bounded by the timeout.
+    async fn next_packet(&mut self) -> Result<Packet, TestClientError> {
+        if let Some(packet) = self.pending.pop_front() {
+            return Ok(packet);
+        }
+        tokio::time::timeout(self.timeout, self.recv_packet())
+            .await
+            .unwrap_or(Err(TestClientError::Timeout))
+    }
+
+    async fn recv_packet(&mut self) -> Result<Packet, TestClientError> {
+        loop {
+            let res = match self.rx.recv().await {
+                Some(EIoPacket::Message(msg)) => self.parser.decode_str(&self.state, msg),
+                Some(EIoPacket::Binary(bin)) => self.parser.decode_bin(&self.state, bin),
+                Some(EIoPacket::Close) | None => return Err(TestClientError::Disconnected),
+                Some(_) => continue,
+            };
+            match res {
+                Ok(packet) => return Ok(packet),
+                Err(ParseError::NeedsMoreBinaryData) => (),
+                Err(ParseError::ParserError(e)) => return Err(e.into()),
+                Err(e) => panic!("invalid packet sent by the server: {e}"),
+            }
+        }
+    }
+}
+
+impl Drop for TestClient {
+    fn drop(&mut self) {
+        if !self.closed {
+            self.tx.try_send(EIoPacket::Close).ok();
+        }
+    }
+}
+
+impl fmt::Debug for TestClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestClient")
+            .field("id", &self.id)
+            .field("ns", &self.ns)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// An event received by a [`TestClient`].
+pub struct TestEvent {
+    name: String,
+    value: Value,
+    ack: Option<i64>,
+    ns: Str,
+    parser: Parser,
+    tx: mpsc::Sender<EIoPacket>,
+}
+
+impl TestEvent {
+    /// The name of the event.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Deserialize the data of the event.
+    pub fn data<T: DeserializeOwned>(&self) -> Result<T, ParserError> {
+        self.parser.decode_value(&mut self.value.clone(), true)
+    }
+
+    /// Whether the server waits for an acknowledgement of this event.
+    pub fn needs_ack(&self) -> bool {
+        self.ack.is_some()
+    }
+
+    /// Acknowledge the event with the given data.
+    /// It does nothing if the server does not wait for an acknowledgement.
+    pub async fn ack<T: ?Sized + Serialize>(self, data: &T) -> Result<(), TestClientError> {
+        let Some(ack) = self.ack else {
+            return Ok(());
+        };
+        let value = self.parser.encode_value(data, None)?;
+        send(&self.tx, self.parser, Packet::ack(self.ns, value, ack)).await
+    }
+}
+
+impl fmt::Debug for TestEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestEvent")
+            .field("name", &self.name)
+            .field("value", &self.value)
+            .field("ack", &self.ack)
+            .finish()
+    }
+}
+
+/// Encode a packet and send it to the server, with its binary attachments.
+async fn send(
+    tx: &mpsc::Sender<EIoPacket>,
+    parser: Parser,
+    packet: Packet,
+) -> Result<(), TestClientError> {
+    let packets = match parser.encode(packet) {
+        Value::Str(msg, bins) => std::iter::once(EIoPacket::Message(msg))
+            .chain(bins.into_iter().flatten().map(EIoPacket::Binary))
+            .collect(),
+        Value::Bytes(bin) => vec![EIoPacket::Binary(bin)],
+    };
+    for packet in packets {
+        tx.send(packet)
+            .await
+            .map_err(|_| TestClientError::Disconnected)?;
+    }
+    Ok(())
+}
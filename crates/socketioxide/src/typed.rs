@@ -0,0 +1,92 @@
+//! ### Typed events
+//!
+//! The [`SocketEvents`] trait maps a set of event names to their payload types.
+//! It is usually implemented on an enum with the [`SocketEvents`](macro@SocketEvents) derive macro,
+//! where each variant is an event:
+//! * Unit variants are events without any data.
+//! * Variants with a single field use it as the event data.
+//! * Variants with multiple fields are events with multiple arguments.
+//!
+//! The event name is the variant name in `snake_case` unless it is renamed with the
+//! `#[event(rename = "...")]` attribute.
+//!
+//! Typed events can then be emitted with [`Socket::emit_typed`] and received with
+//! [`Socket::on_typed`] and the [`Event`] extractor.
+//!
+//! #### Example
+//! ```rust
+//! # use socketioxide::{SocketIo, extract::*, typed::SocketEvents};
+//! # use serde::{Serialize, Deserialize};
+//! #[derive(Debug, Serialize, Deserialize)]
+//! struct Message {
+//!     user: String,
+//!     text: String,
+//! }
+//!
+//! #[derive(Debug, SocketEvents)]
+//! enum ChatEvent {
+//!     // Event "chat" with a `Message` payload
+//!     Chat(Message),
+//!     // Event "typing" with two arguments
+//!     Typing(String, bool),
+//!     // Event "clear" without data
+//!     #[event(rename = "clear")]
+//!     ClearHistory,
+//! }
+//!
+//! let (_, io) = SocketIo::new_svc();
+//! io.ns("/", |socket: SocketRef| {
+//!     // The handler is called for every event of `ChatEvent`
+//!     socket.on_typed::<ChatEvent, _, _>(|socket: SocketRef, Event(event): Event<ChatEvent>| {
+//!         match event {
+//!             ChatEvent::Chat(msg) => println!("{}: {}", msg.user, msg.text),
+//!             ChatEvent::Typing(user, typing) => println!("{user} typing: {typing}"),
+//!             ChatEvent::ClearHistory => (),
+//!         }
+//!         socket.emit_typed(&ChatEvent::ClearHistory).ok();
+//!     });
+//! });
+//! ```
+//!
+//! [`Socket::emit_typed`]: crate::socket::Socket::emit_typed
+//! [`Socket::on_typed`]: crate::socket::Socket::on_typed
+//! [`Event`]: crate::extract::Event
+use socketioxide_core::{
+    parser::{Parse, ParserError},
+    Value,
+};
+
+pub use socketioxide_macros::SocketEvents;
+
+/// A set of events with their payload types.
+///
+/// It is recommended to use the [`SocketEvents`](macro@SocketEvents) derive macro rather
+/// than implementing this trait manually. See the [`typed`](crate::typed) module doc for more details.
+pub trait SocketEvents: Sized + Send + Sync + 'static {
+    /// All the event names of this set.
+    const EVENTS: &'static [&'static str];
+
+    /// The event name of this value.
+    fn event(&self) -> &'static str;
+
+    /// Encode this value with its event name to a payload.
+    fn encode<P: Parse>(&self, parser: P) -> Result<Value, ParserError>;
+
+    /// Decode a payload received for the given event name. It returns an [`UnknownEventError`]
+    /// if the event is not part of this set.
+    fn decode<P: Parse>(parser: P, event: &str, value: &mut Value) -> Result<Self, ParserError>;
+}
+
+/// Returned when decoding an event that is not part of a [`SocketEvents`] set.
+#[derive(Debug, thiserror::Error)]
+#[error("unknown event: {0}")]
+pub struct UnknownEventError(pub String);
+
+#[doc(hidden)]
+pub mod __private {
+    pub use super::UnknownEventError;
+    pub use socketioxide_core::{
+        parser::{Parse, ParserError},
+        Value,
+    };
+}
@@ -0,0 +1,121 @@
+//! Mapping of application users to their sockets, across all the servers.
+//!
+//! A socket is bound to a user with [`Socket::set_user`](crate::socket::Socket::set_user),
+//! typically in the connect handler once the client is authenticated. A user can have
+//! several sockets at the same time, for example one for each of its devices or browser tabs.
+//!
+//! The [`UserRegistry`] of a namespace, returned by [`SocketIo::users`](crate::SocketIo::users),
+//! can then address all the sockets of a user wherever they are connected:
+//! * [`UserRegistry::to`] (or [`SocketIo::to_user`](crate::SocketIo::to_user)) selects the sockets
+//!   of a user to emit messages to them, make them join or leave rooms or disconnect them.
+//! * [`UserRegistry::sockets`] and [`UserRegistry::is_online`] fetch the sockets of a user.
+//! * [`UserRegistry::online_users`] lists the users with at least one connected socket.
+//! * The events emitted to a user with the [`persistent`](BroadcastOperators::persistent) flag
+//!   are queued while the user is offline, if the [offline queue](crate::offline) is enabled.
+//!
+//! Each user is tracked with a dedicated room: the user id prefixed with
+//! [`SocketIoConfig::user_room_prefix`](crate::SocketIoConfig::user_room_prefix).
+//! Rooms are shared between the servers through the adapter, so the registry works in a cluster
+//! without any additional storage, and a socket is removed from the registry when it disconnects,
+//! like it leaves its other rooms.
+//!
+//! # Example
+//! ```
+//! # use socketioxide::{SocketIo, extract::*};
+//! let (_, io) = SocketIo::new_svc();
+//!
+//! io.ns("/", |socket: SocketRef, Data(user_id): Data<String>| {
+//!     socket.set_user(&user_id);
+//!
+//!     socket.on("dm", |socket: SocketRef, io: SocketIo, Data((to, msg)): Data<(String, String)>| async move {
+//!         let from = socket.user_id();
+//!         io.to_user(&to).emit("dm", &(from, msg)).await.ok();
+//!     });
+//! });
+//! ```
+use std::{borrow::Cow, fmt, sync::Arc};
+
+use socketioxide_core::adapter::Room;
+
+use crate::{
+    adapter::{Adapter, LocalAdapter},
+    ns::Namespace,
+    operators::BroadcastOperators,
+    socket::RemoteSocket,
+};
+
+/// The registry of the users of a namespace, returned by [`SocketIo::users`](crate::SocketIo::users).
+/// See the [`user`](crate::user) module doc for more details.
+pub struct UserRegistry<A: Adapter = LocalAdapter> {
+    ns: Arc<Namespace<A>>,
+}
+
+impl<A: Adapter> UserRegistry<A> {
+    pub(crate) fn new(ns: Arc<Namespace<A>>) -> Self {
+        Self { ns }
+    }
+
+    /// # Select all the sockets of the given user, on all the servers.
+    ///
+    /// The returned operators can be chained like the other [`BroadcastOperators`].
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::SocketIo;
+    /// async fn notify(io: SocketIo, user_id: u64) {
+    ///     io.users().to(user_id).emit("notification", "hello").await.ok();
+    ///     // Log out the user from all its devices
+    ///     io.users().to(user_id).disconnect().await.ok();
+    /// }
+    /// ```
+    pub fn to(&self, user: impl fmt::Display) -> BroadcastOperators<A> {
+        let user = user.to_string();
+        let room = user_room(&self.ns.user_room_prefix, &user);
+        BroadcastOperators::new(self.ns.clone(), self.ns.parser).with_user(user, room)
+    }
+
+    /// # Get all the sockets of the given user, on all the servers.
+    pub async fn sockets(&self, user: impl fmt::Display) -> Result<Vec<RemoteSocket<A>>, A::Error> {
+        self.to(user).fetch_sockets().await
+    }
+
+    /// # Check if the given user has at least one connected socket, on any server.
+    pub async fn is_online(&self, user: impl fmt::Display) -> Result<bool, A::Error> {
+        Ok(self.to(user).sockets_count().await? > 0)
+    }
+
+    /// # Get the ids of the users that have at least one connected socket, on any server.
+    pub async fn online_users(&self) -> Result<Vec<String>, A::Error> {
+        let rooms = BroadcastOperators::new(self.ns.clone(), self.ns.parser)
+            .broadcast()
+            .rooms()
+            .await?;
+        let prefix = &self.ns.user_room_prefix;
+        Ok(rooms
+            .iter()
+            .filter_map(|room| room.strip_prefix(prefix.as_ref()))
+            .map(str::to_owned)
+            .collect())
+    }
+}
+
+impl<A: Adapter> Clone for UserRegistry<A> {
+    fn clone(&self) -> Self {
+        Self {
+            ns: self.ns.clone(),
+        }
+    }
+}
+
+impl<A: Adapter> fmt::Debug for UserRegistry<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserRegistry")
+            .field("ns", &self.ns.path)
+            .finish()
+    }
+}
+
+/// The room tracking the sockets of the given user.
+pub(crate) fn user_room(prefix: &str, user: impl fmt::Display) -> Room {
+    Cow::Owned(format!("{prefix}{user}"))
+}
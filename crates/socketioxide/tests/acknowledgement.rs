@@ -3,8 +3,9 @@ mod utils;
 
 use engineioxide::Packet::*;
 use futures_util::StreamExt;
-use socketioxide::extract::SocketRef;
-use socketioxide::SocketIo;
+use socketioxide::extract::{AckSender, Data, SocketRef};
+use socketioxide::handler::Blocking;
+use socketioxide::{AckError, SocketIo};
 use socketioxide_core::packet::PacketData;
 use socketioxide_core::parser::Parse;
 use socketioxide_parser_common::CommonParser;
@@ -120,3 +121,172 @@ pub async fn broadcast_with_ack() {
         }
     }
 }
+
+#[tokio::test]
+pub async fn room_broadcast_with_ack() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<SocketRef>();
+    io.ns("/", move |s: SocketRef| tx.send(s).unwrap());
+
+    let mut clients = Vec::new();
+    for _ in 0..4 {
+        let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+        assert_some!(srx.recv().await);
+        let socket = assert_some!(rx.recv().await);
+        clients.push((socket, stx, srx));
+    }
+    let [answering1, answering2, silent, outside]: [_; 4] = clients.try_into().unwrap();
+    for (socket, _, _) in [&answering1, &answering2, &silent] {
+        socket.join("room");
+    }
+    let room_sockets = [answering1.0.id, answering2.0.id, silent.0.id];
+
+    let stream = io
+        .to("room")
+        .timeout(Duration::from_millis(50))
+        .emit_with_ack::<_, [String; 1]>("test", "foo")
+        .await;
+    let stream = assert_ok!(stream);
+
+    // Every socket of the room receives the event, only two of them answer it.
+    let (_, _silent_tx, mut silent_rx) = silent;
+    let (_, _outside_tx, mut outside_rx) = outside;
+    for (_, stx, srx) in [answering1, answering2].iter_mut() {
+        let ack = recv_event_ack(srx).await;
+        assert_ok!(stx.send(Message(format!("3{}[\"oof\"]", ack).into())).await);
+    }
+    recv_event_ack(&mut silent_rx).await;
+
+    let results: Vec<_> = stream.collect().await;
+    assert_eq!(results.len(), 3);
+
+    let (acks, timeouts): (Vec<_>, Vec<_>) = results.into_iter().partition(|(_, r)| r.is_ok());
+    assert_eq!(acks.len(), 2);
+    assert_eq!(timeouts.len(), 1);
+    assert!(matches!(timeouts[0].1, Err(AckError::Timeout)));
+    for (id, res) in acks {
+        assert!(room_sockets.contains(&id));
+        assert_eq!(assert_ok!(res)[0], "oof");
+    }
+    assert_eq!(timeouts[0].0, room_sockets[2]);
+
+    let res = tokio::time::timeout(Duration::from_millis(10), outside_rx.recv()).await;
+    assert!(
+        res.is_err(),
+        "socket outside of the room received the event"
+    );
+}
+
+/// Receive an event packet and return its ack id.
+async fn recv_event_ack(srx: &mut mpsc::Receiver<engineioxide::Packet>) -> i64 {
+    let msg = tokio::time::timeout(Duration::from_millis(50), srx.recv()).await;
+    let msg = match assert_some!(assert_ok!(msg)) {
+        Message(msg) => msg,
+        msg => panic!("Unexpected message: {:?}", msg),
+    };
+    match assert_ok!(CommonParser.decode_str(&Default::default(), msg)).inner {
+        PacketData::Event(_, Some(ack)) => ack,
+        _ => panic!("Unexpected packet"),
+    }
+}
+
+#[tokio::test]
+pub async fn handler_return_ack() {
+    let (_svc, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.on("add", |Data::<(i32, i32)>((a, b))| {
+            a.checked_add(b).ok_or("overflow")
+        });
+        s.on("get_user", |Data::<String>(id)| async move {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            Ok::<_, ()>(format!("user {id}"))
+        });
+        s.on("noop", || {});
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(stx.send(Message("21[\"add\",1,2]".into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("31[3]".into()));
+
+    assert_ok!(stx.send(Message("22[\"add\",2147483647,1]".into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("32[{\"error\":\"overflow\"}]".into()));
+
+    assert_ok!(stx.send(Message("23[\"get_user\",\"foo\"]".into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("33[\"user foo\"]".into()));
+
+    // Without ack id or with a handler returning nothing, no ack is sent
+    assert_ok!(stx.send(Message("2[\"add\",1,2]".into())).await);
+    assert_ok!(stx.send(Message("24[\"noop\"]".into())).await);
+    tokio::time::timeout(Duration::from_millis(20), srx.recv())
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+pub async fn streamed_ack() {
+    let (_svc, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.on("query", |Data::<u32>(count), ack: AckSender| async move {
+            let ack = ack.stream();
+            for page in 0..count {
+                assert_ok!(ack.send(&[page * 2, page * 2 + 1]));
+            }
+            assert_ok!(ack.end());
+        });
+        // The end marker is sent when the sender is dropped
+        s.on("dropped", |ack: AckSender| {
+            let ack = ack.stream();
+            assert_ok!(ack.send("foo"));
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(stx.send(Message("21[\"query\",2]".into())).await);
+    for msg in ["31[[0,1],false]", "31[[2,3],false]", "31[null,true]"] {
+        assert_eq!(assert_some!(srx.recv().await), Message(msg.into()));
+    }
+
+    assert_ok!(stx.send(Message("22[\"dropped\"]".into())).await);
+    for msg in ["32[\"foo\",false]", "32[null,true]"] {
+        assert_eq!(assert_some!(srx.recv().await), Message(msg.into()));
+    }
+
+    // Without ack id, nothing is sent
+    assert_ok!(stx.send(Message("2[\"query\",2]".into())).await);
+    tokio::time::timeout(Duration::from_millis(20), srx.recv())
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+pub async fn blocking_handler_ack() {
+    let (_svc, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.on(
+            "sum",
+            Blocking(|Data::<Vec<u64>>(data)| {
+                // Blocking the thread doesn't stall the runtime
+                std::thread::sleep(Duration::from_millis(10));
+                Ok::<_, ()>(data.iter().sum::<u64>())
+            }),
+        );
+        s.on("ping", || Ok::<_, ()>("pong"));
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(stx.send(Message("21[\"sum\",[1,2,3]]".into())).await);
+    assert_ok!(stx.send(Message("22[\"ping\"]".into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("32[\"pong\"]".into()));
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("31[6]".into()));
+}
@@ -0,0 +1,102 @@
+//! Tests for the actix-web integration, with a real actix-web server.
+mod utils;
+
+use std::{net::TcpListener, time::Duration};
+
+use actix_web::{App, HttpServer};
+use futures_util::SinkExt;
+use socketioxide::{extract::*, integrations::actix::ActixService, SocketIo};
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+use utils::{poll_http, recv_ws_text, send_http_req};
+
+/// Spawn an actix-web server on a random port in its own thread and return its address.
+fn create_server() -> String {
+    let (svc, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.on("message", |s: SocketRef, Data::<String>(msg)| {
+            s.emit("message-back", &msg).ok();
+        });
+    });
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        actix_web::rt::System::new().block_on(async move {
+            HttpServer::new(move || App::new().service(ActixService::new(svc.clone())))
+                .h1_allow_half_closed(false)
+                .workers(1)
+                .listen(listener)
+                .unwrap()
+                .run()
+                .await
+                .unwrap();
+        })
+    });
+    addr.to_string()
+}
+
+#[tokio::test]
+pub async fn polling() {
+    let addr = create_server();
+    let (status, open) =
+        send_http_req(&addr, "GET", "/socket.io/?EIO=4&transport=polling", "").await;
+    assert_eq!(status, 200);
+    assert!(open.starts_with(r#"0{"sid":""#), "{open}");
+    let sid = open[9..].split('"').next().unwrap();
+    let path = format!("/socket.io/?EIO=4&transport=polling&sid={sid}");
+
+    let (status, body) = send_http_req(&addr, "POST", &path, "40").await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let body = poll_http(&addr, &path).await;
+    assert!(body.starts_with(r#"40{"sid":""#), "{body}");
+
+    let (status, body) = send_http_req(&addr, "POST", &path, r#"42["message","foo"]"#).await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let body = poll_http(&addr, &path).await;
+    assert_eq!(body, r#"42["message-back","foo"]"#);
+
+    let (status, _) = send_http_req(&addr, "GET", "/other", "").await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+pub async fn websocket() {
+    let addr = create_server();
+    let stream = TcpStream::connect(&addr).await.unwrap();
+    let url = format!("ws://{addr}/socket.io/?EIO=4&transport=websocket");
+    let (mut ws, _) = tokio_tungstenite::client_async(url, stream).await.unwrap();
+    assert!(recv_ws_text(&mut ws).await.starts_with(r#"0{"sid":""#));
+
+    ws.send(Message::Text("40".into())).await.unwrap();
+    assert!(recv_ws_text(&mut ws).await.starts_with(r#"40{"sid":""#));
+
+    ws.send(Message::Text(r#"42["message","foo"]"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(recv_ws_text(&mut ws).await, r#"42["message-back","foo"]"#);
+}
+
+#[tokio::test]
+pub async fn aborted_polling() {
+    let addr = create_server();
+    let (_, open) = send_http_req(&addr, "GET", "/socket.io/?EIO=4&transport=polling", "").await;
+    let sid = open[9..].split('"').next().unwrap();
+    let path = format!("/socket.io/?EIO=4&transport=polling&sid={sid}");
+    send_http_req(&addr, "POST", &path, "40").await;
+    let body = poll_http(&addr, &path).await;
+    assert!(body.starts_with(r#"40{"sid":""#), "{body}");
+
+    // A polling request abandoned by the client before any packet is sent
+    let mut stream = TcpStream::connect(&addr).await.unwrap();
+    let req = format!("GET {path} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\r\n");
+    stream.write_all(req.as_bytes()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    drop(stream);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // The next packets are sent to the next polling request
+    let (status, _) = send_http_req(&addr, "POST", &path, r#"42["message","foo"]"#).await;
+    assert_eq!(status, 200);
+    let body = poll_http(&addr, &path).await;
+    assert_eq!(body, r#"42["message-back","foo"]"#);
+}
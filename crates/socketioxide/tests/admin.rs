@@ -0,0 +1,228 @@
+//! Tests for the admin UI protocol
+mod utils;
+
+use std::time::Duration;
+
+use engineioxide::Packet::*;
+use serde_json::{json, Value};
+use socketioxide::{
+    admin::{AdminUiConfig, AdminUiMode},
+    extract::{Data, SocketRef},
+    socket::DisconnectReason,
+    SocketIo,
+};
+use tokio::sync::mpsc::Receiver;
+
+const ADMIN_PREFIX: &str = "2/admin,";
+
+/// Receive the next admin event, ignoring the server stats unless they are expected.
+async fn recv_admin(rx: &mut Receiver<engineioxide::Packet>, event: &str) -> Vec<Value> {
+    loop {
+        let msg = tokio::time::timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .expect("timeout waiting for admin event")
+            .expect("admin socket closed");
+        let Message(msg) = msg else {
+            panic!("unexpected packet: {msg:?}");
+        };
+        let msg = msg.strip_prefix(ADMIN_PREFIX).expect("not an admin event");
+        let mut args: Vec<Value> = serde_json::from_str(msg).unwrap();
+        if args[0] == "server_stats" && event != "server_stats" {
+            continue;
+        }
+        assert_eq!(args[0], event);
+        args.remove(0);
+        return args;
+    }
+}
+
+async fn connect_admin(
+    io: &SocketIo,
+    auth: Value,
+) -> (
+    tokio::sync::mpsc::Sender<engineioxide::Packet>,
+    Receiver<engineioxide::Packet>,
+) {
+    let (tx, mut rx) = io.new_dummy_sock("/admin", auth).await;
+    let msg = assert_some!(rx.recv().await);
+    assert!(matches!(msg, Message(msg) if msg.starts_with("0/admin,")));
+    (tx, rx)
+}
+
+fn handler(s: SocketRef) {
+    s.join("room1");
+    s.on("ping", |s: SocketRef, Data::<Value>(data)| {
+        s.emit("pong", &data).ok();
+    });
+}
+
+#[tokio::test]
+pub async fn config_and_all_sockets() {
+    let (_svc, io) = SocketIo::builder()
+        .with_admin_ui(AdminUiConfig::new().without_auth())
+        .build_svc();
+    io.ns("/", handler);
+    let (_stx, _srx) = io.new_dummy_sock("/", ()).await;
+    let sid = io.sockets()[0].id;
+
+    let (_tx, mut rx) = connect_admin(&io, json!({})).await;
+    let config = recv_admin(&mut rx, "config").await;
+    assert_eq!(
+        config[0],
+        json!({ "supportedFeatures": ["EMIT", "JOIN", "LEAVE", "DISCONNECT", "MJOIN", "MLEAVE", "MDISCONNECT", "ALL_EVENTS"] })
+    );
+    let sockets = recv_admin(&mut rx, "all_sockets").await;
+    let sockets = sockets[0].as_array().unwrap();
+    assert_eq!(sockets.len(), 1);
+    assert_eq!(sockets[0]["id"], sid.to_string());
+    assert_eq!(sockets[0]["nsp"], "/");
+    assert_eq!(sockets[0]["rooms"], json!(["room1"]));
+
+    let stats = recv_admin(&mut rx, "server_stats").await;
+    assert_eq!(stats[0]["clientsCount"], 2);
+    assert_eq!(stats[0]["serverId"], io.config().server_id.to_string());
+    let namespaces = stats[0]["namespaces"].as_array().unwrap();
+    assert_eq!(namespaces.len(), 2);
+}
+
+#[tokio::test]
+pub async fn basic_auth() {
+    let (_svc, io) = SocketIo::builder()
+        .with_admin_ui(AdminUiConfig::new().with_basic_auth("admin", "secret"))
+        .build_svc();
+
+    let (_tx, mut rx) = io
+        .new_dummy_sock(
+            "/admin",
+            json!({ "username": "admin", "password": "wrong" }),
+        )
+        .await;
+    let msg = assert_some!(rx.recv().await);
+    assert_eq!(
+        msg,
+        Message(r#"4/admin,{"message":"invalid credentials"}"#.into())
+    );
+    let (_tx, mut rx) = io.new_dummy_sock("/admin", ()).await;
+    let msg = assert_some!(rx.recv().await);
+    assert_eq!(
+        msg,
+        Message(r#"4/admin,{"message":"invalid credentials"}"#.into())
+    );
+
+    let auth = json!({ "username": "admin", "password": "secret" });
+    let (_tx, mut rx) = connect_admin(&io, auth).await;
+    recv_admin(&mut rx, "config").await;
+}
+
+#[test]
+#[should_panic(expected = "the admin ui requires credentials")]
+pub fn auth_required() {
+    SocketIo::builder().with_admin_ui(AdminUiConfig::new());
+}
+
+#[tokio::test]
+pub async fn notifications() {
+    let (_svc, io) = SocketIo::builder()
+        .with_admin_ui(AdminUiConfig::new().without_auth())
+        .build_svc();
+    io.ns("/", handler);
+
+    let (_tx, mut rx) = connect_admin(&io, json!({})).await;
+    recv_admin(&mut rx, "config").await;
+    recv_admin(&mut rx, "all_sockets").await;
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    let sid = io.sockets()[0].id.to_string();
+
+    let connected = recv_admin(&mut rx, "socket_connected").await;
+    assert_eq!(connected[0]["id"], sid);
+    assert_eq!(connected[0]["nsp"], "/");
+    assert!(connected[1].as_str().unwrap().ends_with('Z'));
+    let joined = recv_admin(&mut rx, "room_joined").await;
+    assert_eq!(joined[..3], [json!("/"), json!("room1"), json!(sid)]);
+
+    assert_ok!(stx.send(Message(r#"2["ping",1]"#.into())).await);
+    let received = recv_admin(&mut rx, "event_received").await;
+    assert_eq!(received[..3], [json!("/"), json!(sid), json!(["ping", 1])]);
+    let sent = recv_admin(&mut rx, "event_sent").await;
+    assert_eq!(sent[..3], [json!("/"), json!(sid), json!(["pong", 1])]);
+
+    assert_ok!(stx.send(Message("1".into())).await);
+    let left = recv_admin(&mut rx, "room_left").await;
+    assert_eq!(left[..3], [json!("/"), json!("room1"), json!(sid)]);
+    let disconnected = recv_admin(&mut rx, "socket_disconnected").await;
+    assert_eq!(
+        disconnected[..3],
+        [
+            json!("/"),
+            json!(sid),
+            json!(DisconnectReason::ClientNSDisconnect.to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+pub async fn commands() {
+    let (_svc, io) = SocketIo::builder()
+        .with_admin_ui(
+            AdminUiConfig::new()
+                .without_auth()
+                .with_mode(AdminUiMode::Production),
+        )
+        .build_svc();
+    io.ns("/", handler);
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    let sid = io.sockets()[0].id;
+
+    let (tx, mut rx) = connect_admin(&io, json!({})).await;
+    recv_admin(&mut rx, "config").await;
+
+    let cmd = format!(r#"{ADMIN_PREFIX}["join","/","room2","{sid}"]"#);
+    assert_ok!(tx.send(Message(cmd.into())).await);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(io.sockets()[0].rooms().len(), 2);
+    let cmd = format!(r#"{ADMIN_PREFIX}["emit","/","room2","hello","foo",1]"#);
+    assert_ok!(tx.send(Message(cmd.into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message(r#"2["hello","foo",1]"#.into()));
+
+    let cmd = format!(r#"{ADMIN_PREFIX}["leave","/","room1",["{sid}"]]"#);
+    assert_ok!(tx.send(Message(cmd.into())).await);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(io.sockets()[0].rooms(), ["room2"]);
+
+    let cmd = format!(r#"{ADMIN_PREFIX}["_disconnect","/",false,null]"#);
+    assert_ok!(tx.send(Message(cmd.into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("1".into()));
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(io.sockets().is_empty());
+}
+
+#[tokio::test]
+pub async fn readonly() {
+    let config = AdminUiConfig::new()
+        .without_auth()
+        .with_readonly(true)
+        .with_server_id("server1")
+        .with_stats_interval(Duration::from_millis(10));
+    let (_svc, io) = SocketIo::builder().with_admin_ui(config).build_svc();
+    io.ns("/", handler);
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    let (tx, mut rx) = connect_admin(&io, json!({})).await;
+    let config = recv_admin(&mut rx, "config").await;
+    assert_eq!(config[0], json!({ "supportedFeatures": ["ALL_EVENTS"] }));
+    recv_admin(&mut rx, "all_sockets").await;
+
+    let cmd = format!(r#"{ADMIN_PREFIX}["_disconnect","/",false,null]"#);
+    assert_ok!(tx.send(Message(cmd.into())).await);
+    let stats = recv_admin(&mut rx, "server_stats").await;
+    assert_eq!(stats[0]["serverId"], "server1");
+    let stats = recv_admin(&mut rx, "server_stats").await;
+    assert_eq!(stats[0]["clientsCount"], 2);
+    assert_eq!(io.sockets().len(), 1);
+}
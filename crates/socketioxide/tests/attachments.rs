@@ -0,0 +1,108 @@
+//! Tests for the binary attachments: limits, extractor and emit operator
+mod utils;
+
+use bytes::Bytes;
+use engineioxide::Packet::*;
+use socketioxide::{
+    extract::{BinData, Data, SocketRef},
+    SocketIo,
+};
+
+#[tokio::test]
+pub async fn max_attachments_size() {
+    let (_svc, io) = SocketIo::builder().max_attachments_size(100).build_svc();
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<Bytes>(1);
+    io.ns("/", move |s: socketioxide::extract::SocketRef| {
+        s.on("test", move |Data::<(Bytes, Bytes)>((a, b))| {
+            tx.try_send(Bytes::from([a, b].concat())).unwrap();
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    // Below the limit
+    let msg = r#"52-["test",{"_placeholder":true,"num":0},{"_placeholder":true,"num":1}]"#;
+    assert_ok!(stx.send(Message(msg.into())).await);
+    assert_ok!(stx.send(Binary(Bytes::from(vec![1; 50]))).await);
+    assert_ok!(stx.send(Binary(Bytes::from(vec![2; 50]))).await);
+    let data = assert_some!(rx.recv().await);
+    assert_eq!(data.len(), 100);
+
+    // The counter is reset for each packet, the second one is above the limit
+    assert_ok!(stx.send(Message(msg.into())).await);
+    assert_ok!(stx.send(Binary(Bytes::from(vec![1; 50]))).await);
+    assert_ok!(stx.send(Binary(Bytes::from(vec![2; 51]))).await);
+    assert_eq!(assert_some!(srx.recv().await), Close);
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+pub async fn bin_data_extractor() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<(String, Vec<Bytes>)>(1);
+    io.ns("/", move |s: SocketRef| {
+        s.on("test", move |BinData(data, bins): BinData<String>| {
+            tx.try_send((data, bins)).unwrap();
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    let msg = r#"52-["test","foo",{"_placeholder":true,"num":0},{"_placeholder":true,"num":1}]"#;
+    assert_ok!(stx.send(Message(msg.into())).await);
+    assert_ok!(stx.send(Binary(Bytes::from_static(&[1, 2]))).await);
+    assert_ok!(stx.send(Binary(Bytes::from_static(&[3]))).await);
+    let (data, bins) = assert_some!(rx.recv().await);
+    assert_eq!(data, "foo");
+    assert_eq!(
+        bins,
+        [Bytes::from_static(&[1, 2]), Bytes::from_static(&[3])]
+    );
+
+    // Without attachments
+    assert_ok!(stx.send(Message(r#"2["test","bar"]"#.into())).await);
+    let (data, bins) = assert_some!(rx.recv().await);
+    assert_eq!(data, "bar");
+    assert!(bins.is_empty());
+}
+
+#[tokio::test]
+pub async fn emit_bin() {
+    let (_svc, io) = SocketIo::new_svc();
+    io.ns("/", move |s: SocketRef| {
+        s.join("room");
+        s.on("test", |s: SocketRef| async move {
+            s.bin([Bytes::from_static(&[1, 2])])
+                .emit("bin", "foo")
+                .unwrap();
+            s.within("room")
+                .bin(vec![vec![3], vec![4]])
+                .emit("bin", &[1, 2])
+                .await
+                .unwrap();
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message(r#"2["test"]"#.into())).await);
+
+    let msg = r#"51-["bin","foo",{"_placeholder":true,"num":0}]"#;
+    assert_eq!(assert_some!(srx.recv().await), Message(msg.into()));
+    assert_eq!(
+        assert_some!(srx.recv().await),
+        Binary(Bytes::from_static(&[1, 2]))
+    );
+    let msg = r#"52-["bin",[1,2],{"_placeholder":true,"num":0},{"_placeholder":true,"num":1}]"#;
+    assert_eq!(assert_some!(srx.recv().await), Message(msg.into()));
+    assert_eq!(
+        assert_some!(srx.recv().await),
+        Binary(Bytes::from_static(&[3]))
+    );
+    assert_eq!(
+        assert_some!(srx.recv().await),
+        Binary(Bytes::from_static(&[4]))
+    );
+}
@@ -0,0 +1,137 @@
+//! Tests for the emit behavior when the packet buffer of a socket is full
+mod utils;
+
+use engineioxide::Packet::*;
+use socketioxide::{extract::SocketRef, ErrorKind, SendError, SocketIo};
+use tokio::sync::mpsc;
+
+#[tokio::test]
+pub async fn emit_buffer_full() {
+    let (_svc, io) = SocketIo::builder().max_buffer_size(2).build_svc();
+    let (tx, mut rx) = mpsc::channel(1);
+    io.ns("/", move |s: SocketRef| {
+        // The connect packet already takes one place in the buffer
+        let res: Vec<_> = (0..2).map(|i| s.emit("test", &i)).collect();
+        tx.try_send(res).unwrap();
+    });
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    let res = assert_some!(rx.recv().await);
+    assert!(res[0].is_ok());
+    assert_eq!(res[1].as_ref().unwrap_err().kind(), ErrorKind::Buffer);
+    let payload = match &res[1] {
+        Err(SendError::BufferFull(payload)) => payload.as_str().unwrap(),
+        res => panic!("expected a buffer full error, got {res:?}"),
+    };
+    assert_eq!(payload.as_str(), r#"["test",1]"#);
+}
+
+#[tokio::test]
+pub async fn emit_async_backpressure() {
+    let (_svc, io) = SocketIo::builder().max_buffer_size(2).build_svc();
+    io.ns("/", move |s: SocketRef| async move {
+        for i in 0..20 {
+            s.emit_async("test", &i).await.unwrap();
+        }
+    });
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    // No message is dropped
+    for i in 0..20 {
+        let msg = format!(r#"2["test",{i}]"#);
+        assert_eq!(assert_some!(srx.recv().await), Message(msg.into()));
+    }
+}
+
+#[tokio::test]
+pub async fn volatile_emit_buffer_full() {
+    let (_svc, io) = SocketIo::builder().max_buffer_size(2).build_svc();
+    let (tx, mut rx) = mpsc::channel(1);
+    io.ns("/", move |s: SocketRef| {
+        let res: Vec<_> = (0..3).map(|i| s.volatile().emit("test", &i)).collect();
+        tx.try_send(res).unwrap();
+    });
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    // The packets that do not fit in the buffer are silently dropped
+    let res = assert_some!(rx.recv().await);
+    assert!(res.iter().all(Result::is_ok), "{res:?}");
+    assert_eq!(
+        assert_some!(srx.recv().await),
+        Message(r#"2["test",0]"#.into())
+    );
+    assert_err!(srx.try_recv());
+}
+
+#[tokio::test]
+pub async fn volatile_broadcast_buffer_full() {
+    let (_svc, io) = SocketIo::builder().max_buffer_size(2).build_svc();
+    io.ns("/", |s: SocketRef| s.join("room"));
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    // Fill the buffer of the socket
+    let mut sent = 0;
+    while io.to("room").emit("test", &sent).await.is_ok() {
+        sent += 1;
+    }
+    io.volatile().to("room").emit("test", &sent).await.unwrap();
+
+    for i in 0..sent {
+        let msg = format!(r#"2["test",{i}]"#);
+        assert_eq!(assert_some!(srx.recv().await), Message(msg.into()));
+    }
+    assert_err!(srx.try_recv());
+}
+
+#[tokio::test]
+pub async fn batch_emit() {
+    let (_svc, io) = SocketIo::builder().max_buffer_size(2).build_svc();
+    let (tx, mut rx) = mpsc::channel(1);
+    io.ns("/", move |s: SocketRef| {
+        // The whole batch takes a single place in the buffer
+        let res = s.batch(|b| {
+            for i in 0..5 {
+                b.emit("test", &i);
+            }
+        });
+        let full = s.batch(|b| {
+            b.emit("test", &5);
+        });
+        tx.try_send((res, full)).unwrap();
+    });
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    let (res, full) = assert_some!(rx.recv().await);
+    assert_ok!(res);
+    assert_eq!(full.unwrap_err().kind(), ErrorKind::Buffer);
+    for i in 0..5 {
+        let msg = format!(r#"2["test",{i}]"#);
+        assert_eq!(assert_some!(srx.recv().await), Message(msg.into()));
+    }
+}
+
+#[tokio::test]
+pub async fn batch_emit_serialize_error() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::channel(1);
+    io.ns("/", move |s: SocketRef| {
+        let invalid: std::collections::HashMap<Vec<u8>, u8> = [(vec![1], 1)].into();
+        let res = s.batch(|b| {
+            b.emit("test", &1).emit("test", &invalid);
+        });
+        tx.try_send(res).unwrap();
+    });
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    // Nothing is sent if an event can't be serialized
+    let res = assert_some!(rx.recv().await);
+    assert!(matches!(res, Err(SendError::Serialize(_))), "{res:?}");
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_err!(srx.try_recv());
+}
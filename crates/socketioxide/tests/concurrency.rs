@@ -0,0 +1,138 @@
+//! Tests for the limit of concurrent handlers
+mod utils;
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use engineioxide::Packet::*;
+use socketioxide::{
+    extract::{Data, SocketRef},
+    socket::DisconnectReason,
+    ConcurrencyScope, NamespaceConfig, SocketIo,
+};
+use tokio::sync::mpsc;
+
+#[tokio::test]
+pub async fn ordered_socket_handlers() {
+    let (_svc, io) = SocketIo::builder()
+        .max_concurrent_handlers(1, ConcurrencyScope::Socket)
+        .build_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    io.ns("/", move |s: SocketRef| {
+        let tx1 = tx.clone();
+        s.on("slow", move |Data::<u64>(i)| async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            tx1.send(i).unwrap();
+        });
+        let tx = tx.clone();
+        s.on("fast", move |Data::<u64>(i)| tx.send(i).unwrap());
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message("2[\"slow\",1]".into())).await);
+    assert_ok!(stx.send(Message("2[\"fast\",2]".into())).await);
+    assert_ok!(stx.send(Message("2[\"slow\",3]".into())).await);
+    assert_ok!(stx.send(Message("2[\"fast\",4]".into())).await);
+    for i in 1..=4 {
+        assert_eq!(assert_some!(rx.recv().await), i);
+    }
+}
+
+#[tokio::test]
+pub async fn namespace_concurrency_limit() {
+    let (_svc, io) = SocketIo::builder()
+        .max_concurrent_handlers(2, ConcurrencyScope::Namespace)
+        .build_svc();
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    {
+        let (running, max_running) = (running.clone(), max_running.clone());
+        io.ns("/", move |s: SocketRef| {
+            let (running, max_running, tx) = (running.clone(), max_running.clone(), tx.clone());
+            s.on("work", move || async move {
+                let count = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(count, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                tx.send(()).unwrap();
+            });
+        });
+    }
+
+    let (stx1, mut srx1) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx1.recv().await);
+    let (stx2, mut srx2) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx2.recv().await);
+    for _ in 0..3 {
+        assert_ok!(stx1.send(Message("2[\"work\"]".into())).await);
+        assert_ok!(stx2.send(Message("2[\"work\"]".into())).await);
+    }
+    for _ in 0..6 {
+        assert_some!(rx.recv().await);
+    }
+    assert_eq!(max_running.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+pub async fn handler_queue_full() {
+    let (_svc, io) = SocketIo::builder()
+        .max_concurrent_handlers(1, ConcurrencyScope::Socket)
+        .handler_queue_size(1)
+        .build_svc();
+    let (tx, mut rx) = mpsc::channel(1);
+    io.ns("/", move |s: SocketRef| {
+        s.on("block", std::future::pending::<()>);
+        s.on_disconnect(move |reason: DisconnectReason| {
+            tx.try_send(reason).unwrap();
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    // The first event is running, at most two are queued
+    for _ in 0..4 {
+        assert_ok!(stx.send(Message("2[\"block\"]".into())).await);
+    }
+    assert_eq!(assert_some!(srx.recv().await), Message("1".into()));
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        DisconnectReason::HandlerQueueFull
+    );
+}
+
+#[tokio::test]
+pub async fn ordered_namespace() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let handler = move |s: SocketRef| {
+        let tx = tx.clone();
+        s.on("move", move |Data::<u64>(delay)| async move {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            tx.send(delay).unwrap();
+        });
+    };
+    io.ns_with_config("/game", NamespaceConfig::new().ordered(), handler.clone());
+    io.ns("/", handler);
+
+    let (stx, mut srx) = io.new_dummy_sock("/game", ()).await;
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message("2/game,[\"move\",20]".into())).await);
+    assert_ok!(stx.send(Message("2/game,[\"move\",1]".into())).await);
+    assert_eq!(assert_some!(rx.recv().await), 20);
+    assert_eq!(assert_some!(rx.recv().await), 1);
+
+    // Other namespaces are not ordered
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message("2[\"move\",20]".into())).await);
+    assert_ok!(stx.send(Message("2[\"move\",1]".into())).await);
+    assert_eq!(assert_some!(rx.recv().await), 1);
+    assert_eq!(assert_some!(rx.recv().await), 20);
+}
@@ -0,0 +1,562 @@
+//! A port of the socket.io part of the [protocol test suite](https://github.com/socketio/socket.io-protocol),
+//! also run in the CI against the `socketioxide-e2e` server with node.js.
+//!
+//! The server is served over in-memory http/1.1 connections, like the engine.io conformance suite,
+//! so the suite runs with `cargo test` without any network or node.js setup.
+//! The socket.io v4 protocol cases need the `v4` feature.
+use std::time::Duration;
+
+use bytes::Bytes;
+use engineioxide::service::NotFoundService;
+use futures_util::{SinkExt, StreamExt};
+use hyper_util::rt::TokioIo;
+use rmpv::Value;
+use serde_json::Value as Json;
+use socketioxide::{
+    extract::{AckSender, Data, SocketRef},
+    service::SocketIoService,
+    SocketIo,
+};
+use tokio::io::DuplexStream;
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+type Svc = SocketIoService<NotFoundService>;
+
+/// The handlers of the `socketioxide-e2e` server.
+fn on_connect(socket: SocketRef, Data(data): Data<Value>) {
+    socket.emit("auth", &data).ok();
+
+    socket.on("message", |socket: SocketRef, Data::<[Value; 3]>(data)| {
+        socket.emit("message-back", &data).ok();
+    });
+
+    socket.on(
+        "message-with-ack",
+        |Data::<[Value; 3]>(data), ack: AckSender| async move {
+            ack.send(&data).ok();
+        },
+    );
+
+    socket.on(
+        "emit-with-ack",
+        |s: SocketRef, Data::<[Value; 3]>(data)| async move {
+            let ack: [Value; 3] = s
+                .emit_with_ack("emit-with-ack", &data)
+                .unwrap()
+                .await
+                .unwrap();
+            s.emit("emit-with-ack", &ack).ok();
+        },
+    );
+}
+
+fn create_server() -> Svc {
+    let (svc, io) = SocketIo::builder()
+        .ping_interval(Duration::from_millis(300))
+        .ping_timeout(Duration::from_millis(200))
+        .ack_timeout(Duration::from_millis(200))
+        .connect_timeout(Duration::from_millis(1000))
+        .max_payload(1e6 as u64)
+        .build_svc();
+    io.ns("/", on_connect);
+    io.ns("/custom", on_connect);
+    svc
+}
+
+/// Open a websocket connection served over a new in-memory http/1.1 connection.
+async fn ws(svc: &Svc, query: &str) -> WebSocketStream<DuplexStream> {
+    let (client, server) = tokio::io::duplex(1 << 16);
+    let conn = hyper::server::conn::http1::Builder::new()
+        .serve_connection(TokioIo::new(server), svc.clone())
+        .with_upgrades();
+    tokio::spawn(conn);
+    let url = format!("ws://127.0.0.1/socket.io/?{query}");
+    let (ws, _) = tokio_tungstenite::client_async(url, client)
+        .await
+        .expect("websocket upgrade failed");
+    ws
+}
+
+/// Receive the next message, `None` if the connection is closed.
+async fn recv(ws: &mut WebSocketStream<DuplexStream>) -> Option<Message> {
+    match tokio::time::timeout(Duration::from_secs(1), ws.next())
+        .await
+        .expect("timeout")
+    {
+        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => None,
+        Some(Ok(msg)) => Some(msg),
+    }
+}
+
+async fn recv_text(ws: &mut WebSocketStream<DuplexStream>) -> String {
+    match recv(ws).await {
+        Some(Message::Text(text)) => text.to_string(),
+        msg => panic!("expected a text message, got {msg:?}"),
+    }
+}
+
+async fn recv_binary(ws: &mut WebSocketStream<DuplexStream>) -> Bytes {
+    match recv(ws).await {
+        Some(Message::Binary(data)) => data,
+        msg => panic!("expected a binary message, got {msg:?}"),
+    }
+}
+
+async fn send(ws: &mut WebSocketStream<DuplexStream>, msg: impl Into<Message>) {
+    ws.send(msg.into()).await.unwrap();
+}
+
+async fn send_binary(ws: &mut WebSocketStream<DuplexStream>, data: &'static [u8]) {
+    send(ws, Message::Binary(Bytes::from_static(data))).await;
+}
+
+/// Wait for the server to close a websocket connection.
+async fn assert_ws_closed(ws: &mut WebSocketStream<DuplexStream>) {
+    let closed = async { while recv(ws).await.is_some() {} };
+    tokio::time::timeout(Duration::from_secs(2), closed)
+        .await
+        .expect("the connection was not closed");
+}
+
+/// Check a connect packet of the given namespace prefix, it only contains the socket id.
+fn check_connect_packet(packet: &str, prefix: &str) {
+    let handshake = packet
+        .strip_prefix(prefix)
+        .unwrap_or_else(|| panic!("expected a connect packet, got {packet}"));
+    let handshake: Json = serde_json::from_str(handshake).unwrap();
+    let keys: Vec<_> = handshake.as_object().unwrap().keys().collect();
+    assert_eq!(keys, ["sid"]);
+    assert!(handshake["sid"].is_string());
+}
+
+const PLACEHOLDERS: &str = r#"{"_placeholder":true,"num":0},{"_placeholder":true,"num":1}"#;
+
+mod v5 {
+    use super::*;
+
+    const QUERY: &str = "EIO=4&transport=websocket";
+
+    /// Open a websocket session and receive the engine.io handshake and first ping.
+    async fn init_engineio_connection(svc: &Svc) -> WebSocketStream<DuplexStream> {
+        let mut ws = ws(svc, QUERY).await;
+        assert!(recv_text(&mut ws).await.starts_with('0'));
+        assert_eq!(recv_text(&mut ws).await, "2");
+        ws
+    }
+
+    /// Connect to the main namespace and receive the auth packet.
+    async fn init_socketio_connection(svc: &Svc) -> WebSocketStream<DuplexStream> {
+        let mut ws = init_engineio_connection(svc).await;
+        send(&mut ws, "40").await;
+        check_connect_packet(&recv_text(&mut ws).await, "40");
+        assert_eq!(recv_text(&mut ws).await, r#"42["auth",{}]"#);
+        ws
+    }
+
+    #[tokio::test]
+    async fn connect_main_ns() {
+        let svc = create_server();
+        init_socketio_connection(&svc).await;
+    }
+
+    #[tokio::test]
+    async fn connect_main_ns_with_payload() {
+        let svc = create_server();
+        let mut ws = init_engineio_connection(&svc).await;
+        send(&mut ws, r#"40{"token":"123"}"#).await;
+        check_connect_packet(&recv_text(&mut ws).await, "40");
+        assert_eq!(recv_text(&mut ws).await, r#"42["auth",{"token":"123"}]"#);
+    }
+
+    #[tokio::test]
+    async fn connect_custom_ns() {
+        let svc = create_server();
+        let mut ws = init_engineio_connection(&svc).await;
+        send(&mut ws, "40/custom,").await;
+        check_connect_packet(&recv_text(&mut ws).await, "40/custom,");
+        assert_eq!(recv_text(&mut ws).await, r#"42/custom,["auth",{}]"#);
+    }
+
+    #[tokio::test]
+    async fn connect_custom_ns_with_payload() {
+        let svc = create_server();
+        let mut ws = init_engineio_connection(&svc).await;
+        send(&mut ws, r#"40/custom,{"token":"abc"}"#).await;
+        check_connect_packet(&recv_text(&mut ws).await, "40/custom,");
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"42/custom,["auth",{"token":"abc"}]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_unknown_ns() {
+        let svc = create_server();
+        let mut ws = init_engineio_connection(&svc).await;
+        send(&mut ws, "40/random").await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"44/random,{"message":"Invalid namespace"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_invalid_handshake() {
+        let svc = create_server();
+        let mut ws = init_engineio_connection(&svc).await;
+        send(&mut ws, "4abc").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn disconnect_main_ns() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "41").await;
+        // The heartbeat will timeout and close the connection
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn connect_disconnect_custom_ns() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "40/custom").await;
+        check_connect_packet(&recv_text(&mut ws).await, "40/custom,");
+        assert_eq!(recv_text(&mut ws).await, r#"42/custom,["auth",{}]"#);
+
+        send(&mut ws, "41/custom").await;
+        send(&mut ws, r#"42["message","message to main namespace",1,2]"#).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"42["message-back","message to main namespace",1,2]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn emit_with_ack() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, r#"42["emit-with-ack",1,"2",{"3":[true]}]"#).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"421["emit-with-ack",1,"2",{"3":[true]}]"#
+        );
+        send(&mut ws, r#"431[1,"2",{"3":[true]}]"#).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"42["emit-with-ack",1,"2",{"3":[true]}]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn emit_with_binary_ack() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        let data = format!(r#"{PLACEHOLDERS},"test""#);
+
+        send(&mut ws, format!(r#"452-["emit-with-ack",{data}]"#)).await;
+        send_binary(&mut ws, &[1, 2, 3]).await;
+        send_binary(&mut ws, &[4, 5, 6]).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            format!(r#"452-1["emit-with-ack",{data}]"#)
+        );
+        assert_eq!(recv_binary(&mut ws).await, [1, 2, 3][..]);
+        assert_eq!(recv_binary(&mut ws).await, [4, 5, 6][..]);
+
+        send(&mut ws, format!("462-1[{data}]")).await;
+        send_binary(&mut ws, &[1, 2, 3]).await;
+        send_binary(&mut ws, &[4, 5, 6]).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            format!(r#"452-["emit-with-ack",{data}]"#)
+        );
+        assert_eq!(recv_binary(&mut ws).await, [1, 2, 3][..]);
+        assert_eq!(recv_binary(&mut ws).await, [4, 5, 6][..]);
+    }
+
+    #[tokio::test]
+    async fn plain_text_event() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, r#"42["message",1,"2",{"3":[true]}]"#).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"42["message-back",1,"2",{"3":[true]}]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn binary_event() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, format!(r#"452-["message",1,{PLACEHOLDERS}]"#)).await;
+        send_binary(&mut ws, &[1, 2, 3]).await;
+        send_binary(&mut ws, &[4, 5, 6]).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            format!(r#"452-["message-back",1,{PLACEHOLDERS}]"#)
+        );
+        assert_eq!(recv_binary(&mut ws).await, [1, 2, 3][..]);
+        assert_eq!(recv_binary(&mut ws).await, [4, 5, 6][..]);
+    }
+
+    #[tokio::test]
+    async fn plain_text_event_with_ack() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, r#"42456["message-with-ack",1,"2",{"3":[false]}]"#).await;
+        assert_eq!(recv_text(&mut ws).await, r#"43456[1,"2",{"3":[false]}]"#);
+    }
+
+    #[tokio::test]
+    async fn binary_event_with_ack() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(
+            &mut ws,
+            format!(r#"452-789["message-with-ack",1,{PLACEHOLDERS}]"#),
+        )
+        .await;
+        send_binary(&mut ws, &[1, 2, 3]).await;
+        send_binary(&mut ws, &[4, 5, 6]).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            format!("462-789[1,{PLACEHOLDERS}]")
+        );
+        assert_eq!(recv_binary(&mut ws).await, [1, 2, 3][..]);
+        assert_eq!(recv_binary(&mut ws).await, [4, 5, 6][..]);
+    }
+
+    #[tokio::test]
+    async fn invalid_packet_type() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "4abc").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn invalid_payload_format() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "42{}").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn invalid_ack_id() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, r#"42abc["message-with-ack",1,"2",{"3":[false]}]"#).await;
+        assert_ws_closed(&mut ws).await;
+    }
+}
+
+#[cfg(feature = "v4")]
+mod v4 {
+    use super::*;
+
+    const QUERY: &str = "EIO=3&transport=websocket";
+
+    /// Open a websocket session, it is connected to the main namespace by default.
+    async fn init_socketio_connection(svc: &Svc) -> WebSocketStream<DuplexStream> {
+        let mut ws = ws(svc, QUERY).await;
+        assert!(recv_text(&mut ws).await.starts_with('0'));
+        assert!(recv_text(&mut ws).await.starts_with("40"));
+        assert_eq!(recv_text(&mut ws).await, r#"42["auth",{}]"#);
+        ws
+    }
+
+    #[tokio::test]
+    async fn connect_main_ns_by_default() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(
+            &mut ws,
+            r#"42["message","message to main namespace",1,"test"]"#,
+        )
+        .await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"42["message-back","message to main namespace",1,"test"]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_custom_ns() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "40/custom,").await;
+        assert!(recv_text(&mut ws).await.starts_with("40/custom"));
+    }
+
+    #[tokio::test]
+    async fn connect_unknown_ns() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "40/random").await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"44/random,{"message":"Invalid namespace"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_invalid_handshake() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "4abc").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn disconnect_main_ns() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "41").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn connect_disconnect_custom_ns() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "40/custom").await;
+        assert!(recv_text(&mut ws).await.starts_with("40/custom"));
+        assert_eq!(recv_text(&mut ws).await, r#"42/custom,["auth",{}]"#);
+
+        send(&mut ws, "41/custom").await;
+        send(
+            &mut ws,
+            r#"42["message","message to main namespace",1,"test"]"#,
+        )
+        .await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"42["message-back","message to main namespace",1,"test"]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn emit_with_ack() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, r#"42["emit-with-ack",1,"2",{"3":[true]}]"#).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"421["emit-with-ack",1,"2",{"3":[true]}]"#
+        );
+        send(&mut ws, r#"431[1,"2",{"3":[true]}]"#).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"42["emit-with-ack",1,"2",{"3":[true]}]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn emit_with_binary_ack() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        let data = format!(r#"{PLACEHOLDERS},"test""#);
+
+        // The v3 engine.io binary packets are prefixed with their packet type
+        send(&mut ws, format!(r#"452-["emit-with-ack",{data}]"#)).await;
+        send_binary(&mut ws, &[4, 1, 2, 3]).await;
+        send_binary(&mut ws, &[4, 4, 5, 6]).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            format!(r#"452-1["emit-with-ack",{data}]"#)
+        );
+        assert_eq!(recv_binary(&mut ws).await, [4, 1, 2, 3][..]);
+        assert_eq!(recv_binary(&mut ws).await, [4, 4, 5, 6][..]);
+
+        send(&mut ws, format!("462-1[{data}]")).await;
+        send_binary(&mut ws, &[4, 1, 2, 3]).await;
+        send_binary(&mut ws, &[4, 4, 5, 6]).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            format!(r#"452-["emit-with-ack",{data}]"#)
+        );
+        assert_eq!(recv_binary(&mut ws).await, [4, 1, 2, 3][..]);
+        assert_eq!(recv_binary(&mut ws).await, [4, 4, 5, 6][..]);
+    }
+
+    #[tokio::test]
+    async fn plain_text_event() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, r#"42["message",1,"2",{"3":[true]}]"#).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            r#"42["message-back",1,"2",{"3":[true]}]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn binary_event() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, format!(r#"452-["message",{PLACEHOLDERS},"test"]"#)).await;
+        send_binary(&mut ws, &[4, 1, 2, 3]).await;
+        send_binary(&mut ws, &[4, 4, 5, 6]).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            format!(r#"452-["message-back",{PLACEHOLDERS},"test"]"#)
+        );
+        assert_eq!(recv_binary(&mut ws).await, [4, 1, 2, 3][..]);
+        assert_eq!(recv_binary(&mut ws).await, [4, 4, 5, 6][..]);
+    }
+
+    #[tokio::test]
+    async fn plain_text_event_with_ack() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, r#"42456["message-with-ack",1,"2",{"3":[false]}]"#).await;
+        assert_eq!(recv_text(&mut ws).await, r#"43456[1,"2",{"3":[false]}]"#);
+    }
+
+    #[tokio::test]
+    async fn binary_event_with_ack() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(
+            &mut ws,
+            format!(r#"452-789["message-with-ack",{PLACEHOLDERS},"test"]"#),
+        )
+        .await;
+        send_binary(&mut ws, &[4, 1, 2, 3]).await;
+        send_binary(&mut ws, &[4, 4, 5, 6]).await;
+        assert_eq!(
+            recv_text(&mut ws).await,
+            format!(r#"462-789[{PLACEHOLDERS},"test"]"#)
+        );
+        assert_eq!(recv_binary(&mut ws).await, [4, 1, 2, 3][..]);
+        assert_eq!(recv_binary(&mut ws).await, [4, 4, 5, 6][..]);
+    }
+
+    #[tokio::test]
+    async fn invalid_packet_type() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "4abc").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn invalid_payload_format() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, "42{}").await;
+        assert_ws_closed(&mut ws).await;
+    }
+
+    #[tokio::test]
+    async fn invalid_ack_id() {
+        let svc = create_server();
+        let mut ws = init_socketio_connection(&svc).await;
+        send(&mut ws, r#"42abc["message-with-ack",1,"2",{"3":[false]}]"#).await;
+        assert_ws_closed(&mut ws).await;
+    }
+}
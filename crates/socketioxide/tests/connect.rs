@@ -3,7 +3,11 @@ mod utils;
 use bytes::Bytes;
 use engineioxide::Packet::*;
 use serde::Serialize;
-use socketioxide::{extract::SocketRef, handler::ConnectHandler, SendError, SocketError, SocketIo};
+use socketioxide::{
+    extract::{Auth, Data, NsParams, SocketRef},
+    handler::{ConnectError, ConnectHandler},
+    SendError, SocketError, SocketIo,
+};
 use socketioxide_core::{packet::Packet, parser::Parse, Value};
 use socketioxide_parser_common::CommonParser;
 use tokio::sync::mpsc;
@@ -113,6 +117,147 @@ pub async fn connect_middleware_error() {
     assert_err!(rx.try_recv());
 }
 
+#[tokio::test]
+pub async fn connect_middleware_auth() {
+    let (_svc, io) = SocketIo::new_svc();
+    #[derive(Debug, serde::Deserialize)]
+    struct Auth {
+        token: String,
+    }
+    #[derive(Debug)]
+    struct AuthError;
+    impl std::fmt::Display for AuthError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "invalid token")
+        }
+    }
+    let middleware = |Data(auth): Data<Auth>| async move {
+        if auth.token == "secret" {
+            Ok(())
+        } else {
+            Err(AuthError)
+        }
+    };
+    io.ns("/chat", { || {} }.with(middleware));
+
+    let (_, mut srx) = io
+        .new_dummy_sock("/chat", serde_json::json!({ "token": "foo" }))
+        .await;
+    let p = assert_some!(srx.recv().await);
+    assert_eq!(p, Message("4/chat,{\"message\":\"invalid token\"}".into()));
+
+    let (_, mut srx) = io
+        .new_dummy_sock("/chat", serde_json::json!({ "token": "secret" }))
+        .await;
+    let p = assert_some!(srx.recv().await);
+    assert!(matches!(p, Message(s) if s.starts_with("0/chat,")));
+}
+
+#[tokio::test]
+pub async fn connect_middleware_error_data() {
+    let (_svc, io) = SocketIo::new_svc();
+    let middleware = |Data(token): Data<String>| async move {
+        if token == "secret" {
+            Ok(())
+        } else {
+            let data = serde_json::json!({ "code": 401, "retry": false });
+            Err(ConnectError::new("unauthorized").with_data(data))
+        }
+    };
+    io.ns("/chat", { || {} }.with(middleware));
+
+    let (_, mut srx) = io.new_dummy_sock("/chat", "foo").await;
+    let p = assert_some!(srx.recv().await);
+    assert_eq!(
+        p,
+        Message(
+            "4/chat,{\"message\":\"unauthorized\",\"data\":{\"code\":401,\"retry\":false}}".into()
+        )
+    );
+
+    let (_, mut srx) = io.new_dummy_sock("/chat", "secret").await;
+    let p = assert_some!(srx.recv().await);
+    assert!(matches!(p, Message(s) if s.starts_with("0/chat,")));
+}
+
+#[tokio::test]
+pub async fn connect_auth_extractor() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::channel::<String>(100);
+    #[derive(Debug, serde::Deserialize)]
+    struct Credentials {
+        token: String,
+    }
+    io.ns("/chat", move |Auth(creds): Auth<Credentials>| {
+        tx.try_send(creds.token).unwrap();
+    });
+
+    let (_, mut srx) = io
+        .new_dummy_sock("/chat", serde_json::json!({ "foo": "bar" }))
+        .await;
+    let p = assert_some!(srx.recv().await);
+    assert!(
+        matches!(&p, Message(s) if s.starts_with("4/chat,{\"message\":\"invalid auth payload: ")),
+        "{p:?}"
+    );
+    assert_err!(rx.try_recv());
+
+    let (_, mut srx) = io
+        .new_dummy_sock("/chat", serde_json::json!({ "token": "secret" }))
+        .await;
+    let p = assert_some!(srx.recv().await);
+    assert!(matches!(p, Message(s) if s.starts_with("0/chat,")));
+    assert_eq!(timeout_rcv(&mut rx).await, "secret");
+}
+
+#[tokio::test]
+pub async fn connect_auth_extractor_with_middleware() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::channel::<usize>(100);
+    let tx1 = tx.clone();
+    let middleware = move || {
+        tx1.try_send(1).unwrap();
+        Ok::<_, std::convert::Infallible>(())
+    };
+    io.ns(
+        "/",
+        { move |_: Auth<String>| tx.try_send(2).unwrap() }.with(middleware),
+    );
+
+    let (_, mut srx) = io.new_dummy_sock("/", 123).await;
+    let p = assert_some!(srx.recv().await);
+    assert!(matches!(p, Message(s) if s.starts_with("4{")));
+    // The middleware is called before the auth payload is checked
+    assert_eq!(timeout_rcv(&mut rx).await, 1);
+    assert_err!(rx.try_recv());
+}
+
+#[tokio::test]
+pub async fn connect_with_data() {
+    let (_svc, io) = SocketIo::new_svc();
+    #[derive(Serialize)]
+    struct Handshake {
+        user_id: usize,
+    }
+    let middleware = |s: SocketRef| s.connect_with(&Handshake { user_id: 42 });
+    io.ns("/", { || {} }.with(middleware));
+    io.ns("/chat", || {});
+
+    let (_, mut srx) = io.new_dummy_sock("/", ()).await;
+    let p = assert_some!(srx.recv().await);
+    let Message(msg) = p else { panic!("{p:?}") };
+    let payload: serde_json::Value = serde_json::from_str(&msg[1..]).unwrap();
+    assert_eq!(payload["user_id"], 42);
+    assert!(payload["sid"].is_string());
+
+    // Other namespaces only send the socket id
+    let (_, mut srx) = io.new_dummy_sock("/chat", ()).await;
+    let p = assert_some!(srx.recv().await);
+    let Message(msg) = p else { panic!("{p:?}") };
+    let payload: serde_json::Value = serde_json::from_str(&msg[7..]).unwrap();
+    assert_eq!(payload.as_object().unwrap().len(), 1);
+}
+
 #[tokio::test]
 async fn ns_dyn_connect() {
     let (_svc, io) = SocketIo::new_svc();
@@ -126,6 +271,26 @@ async fn ns_dyn_connect() {
     let (_stx, mut _srx) = io.new_dummy_sock("/admin/132/board", ()).await;
     assert_eq!(timeout_rcv(&mut rx).await, "/admin/132/board");
 }
+
+#[tokio::test]
+async fn ns_dyn_connect_params() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<(String, String)>(1);
+
+    io.dyn_ns("/admin/{id}/{*rest}", move |params: NsParams| {
+        let id = params.get("id").unwrap().to_string();
+        let rest = params.get("rest").unwrap().to_string();
+        tx.try_send((id, rest)).unwrap();
+    })
+    .unwrap();
+
+    let (_stx, mut _srx) = io.new_dummy_sock("/admin/132/board/1", ()).await;
+    assert_eq!(
+        timeout_rcv(&mut rx).await,
+        ("132".to_string(), "board/1".to_string())
+    );
+}
+
 #[tokio::test]
 async fn ns_dyn_connect_precedence() {
     let (_svc, io) = SocketIo::new_svc();
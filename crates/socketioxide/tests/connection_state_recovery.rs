@@ -0,0 +1,107 @@
+//! Tests for connection state recovery
+mod fixture;
+mod utils;
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use socketioxide::{extract::SocketRef, recovery::RecoveryConfig, SocketIo};
+use tokio::sync::mpsc;
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+use crate::fixture::{create_ws_connection_with_auth, StreamImpl};
+
+/// Receive the next text message, skipping ping packets
+async fn recv_text(ws: &mut WebSocketStream<StreamImpl>) -> String {
+    loop {
+        let msg = tokio::time::timeout(Duration::from_millis(100), ws.next())
+            .await
+            .expect("timeout waiting for message")
+            .unwrap()
+            .unwrap();
+        match msg {
+            Message::Text(msg) if msg.as_str() == "2" => continue,
+            Message::Text(msg) => break msg.to_string(),
+            msg => panic!("unexpected message: {msg:?}"),
+        }
+    }
+}
+
+/// Skip the engine.io open packet and return the connect packet payload
+async fn recv_connect(ws: &mut WebSocketStream<StreamImpl>) -> serde_json::Value {
+    assert!(recv_text(ws).await.starts_with('0'));
+    let connect = recv_text(ws).await;
+    serde_json::from_str(connect.strip_prefix("40").unwrap()).unwrap()
+}
+
+/// Return the event args of an event packet
+async fn recv_event(ws: &mut WebSocketStream<StreamImpl>) -> Vec<serde_json::Value> {
+    let event = recv_text(ws).await;
+    serde_json::from_str(event.strip_prefix("42").unwrap()).unwrap()
+}
+
+#[tokio::test]
+pub async fn connection_state_recovery() {
+    let (svc, io) = SocketIo::builder()
+        .with_connection_state_recovery(RecoveryConfig::new(Duration::from_secs(10)))
+        .build_svc();
+    let (tx, mut rx) = mpsc::channel::<bool>(10);
+    io.ns("/", move |s: SocketRef| {
+        if !s.recovered() {
+            s.join("room1");
+        }
+        tx.try_send(s.recovered()).unwrap();
+    });
+
+    let mut ws = create_ws_connection_with_auth(&svc, "{}").await;
+    let connect = recv_connect(&mut ws).await;
+    let pid = connect["pid"].as_str().unwrap().to_string();
+    assert!(!rx.recv().await.unwrap());
+
+    io.to("room1").emit("msg", "a").await.unwrap();
+    let event = recv_event(&mut ws).await;
+    assert_eq!(event[..2], ["msg", "a"]);
+    let offset = event[2].as_str().unwrap().to_string();
+
+    ws.send(Message::Close(None)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    io.to("room1").emit("msg", "b").await.unwrap();
+    io.emit("msg", "c").await.unwrap();
+    io.to("room2").emit("msg", "d").await.unwrap();
+
+    let auth = format!("{{\"pid\":\"{pid}\",\"offset\":\"{offset}\"}}");
+    let mut ws = create_ws_connection_with_auth(&svc, &auth).await;
+    let connect = recv_connect(&mut ws).await;
+    assert_eq!(connect["pid"], pid);
+    assert!(rx.recv().await.unwrap());
+
+    assert_eq!(recv_event(&mut ws).await[..2], ["msg", "b"]);
+    assert_eq!(recv_event(&mut ws).await[..2], ["msg", "c"]);
+    assert_eq!(io.within("room1").sockets().len(), 1);
+}
+
+#[tokio::test]
+pub async fn connection_state_recovery_invalid_session() {
+    let (svc, io) = SocketIo::builder()
+        .with_connection_state_recovery(RecoveryConfig::default())
+        .build_svc();
+    let (tx, mut rx) = mpsc::channel::<bool>(10);
+    io.ns("/", move |s: SocketRef| tx.try_send(s.recovered()).unwrap());
+
+    let auth = r#"{"pid":"AAAAAAAAAAAAAAAA","offset":"AAAAAAAAAAAAAAAA"}"#;
+    let mut ws = create_ws_connection_with_auth(&svc, auth).await;
+    let connect = recv_connect(&mut ws).await;
+    assert_ne!(connect["pid"], "AAAAAAAAAAAAAAAA");
+    assert!(!rx.recv().await.unwrap());
+}
+
+#[tokio::test]
+pub async fn connection_state_recovery_disabled() {
+    let (svc, io) = SocketIo::new_svc();
+    io.ns("/", || {});
+
+    let mut ws = create_ws_connection_with_auth(&svc, "{}").await;
+    let connect = recv_connect(&mut ws).await;
+    assert!(connect.get("pid").is_none());
+}
@@ -0,0 +1,92 @@
+//! Tests that the packets emitted before disconnecting a socket or closing the server
+//! are sent to the client before the disconnection.
+use std::time::Duration;
+
+use bytes::Bytes;
+use engineioxide::service::NotFoundService;
+use http::Request;
+use http_body_util::{BodyExt, Empty};
+use socketioxide::{extract::SocketRef, service::SocketIoService};
+use tokio::sync::mpsc;
+use tower_service::Service;
+
+mod fixture;
+
+use fixture::{create_polling_connection, create_server};
+
+/// Poll the pending packets of the session, without the heartbeat packets.
+async fn poll(svc: &SocketIoService<NotFoundService>, sid: &str) -> Vec<String> {
+    let req = Request::get(format!(
+        "http://127.0.0.1/socket.io/?EIO=4&transport=polling&sid={sid}"
+    ))
+    .body(Empty::<Bytes>::new())
+    .unwrap();
+    let res = svc.clone().call(req).await.unwrap();
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8(body.to_vec())
+        .unwrap()
+        .split('\x1e')
+        .filter(|p| *p != "2")
+        .map(String::from)
+        .collect()
+}
+
+#[tokio::test]
+pub async fn polling_disconnect_after_volatile() {
+    let (svc, io) = create_server().await;
+    io.ns("/", |s: SocketRef| {
+        s.emit("msg", "foo").unwrap();
+        s.volatile().emit("volatile", "bar").unwrap();
+        s.disconnect().unwrap();
+    });
+    let sid = create_polling_connection(&svc).await;
+
+    let packets = poll(&svc, &sid).await;
+    assert_eq!(packets.len(), 4, "{packets:?}");
+    assert_eq!(packets[1], r#"42["msg","foo"]"#);
+    assert_eq!(packets[2], r#"42["volatile","bar"]"#);
+    assert_eq!(packets[3], "41");
+}
+
+#[tokio::test]
+pub async fn polling_disconnect_after_flush() {
+    let (svc, io) = create_server().await;
+    let (tx, mut rx) = mpsc::channel(1);
+    io.ns("/", move |s: SocketRef| {
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            s.emit("msg", "foo").unwrap();
+            s.disconnect_after_flush().await.unwrap();
+            tx.send(()).await.unwrap();
+        });
+    });
+    let sid = create_polling_connection(&svc).await;
+    // The socket waits for the client to poll the pending packets
+    assert!(rx.try_recv().is_err());
+
+    let packets = poll(&svc, &sid).await;
+    assert_eq!(packets[1], r#"42["msg","foo"]"#);
+    tokio::time::timeout(Duration::from_millis(100), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(poll(&svc, &sid).await, ["41"]);
+}
+
+#[tokio::test]
+pub async fn polling_server_close_flushes() {
+    let (svc, io) = create_server().await;
+    io.ns("/", |s: SocketRef| s.emit("msg", "foo").unwrap());
+    let sid = create_polling_connection(&svc).await;
+
+    let handle = tokio::spawn(async move { io.close().await });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(!handle.is_finished());
+
+    let packets = poll(&svc, &sid).await;
+    assert_eq!(packets[1], r#"42["msg","foo"]"#);
+    tokio::time::timeout(Duration::from_millis(100), handle)
+        .await
+        .unwrap()
+        .unwrap();
+}
@@ -7,6 +7,7 @@
 //!
 //! * Client namespace disconnect
 //! * Server namespace disconnect
+//! * Server close with a close reason
 
 use std::time::Duration;
 
@@ -219,6 +220,33 @@ pub async fn server_ns_disconnect() {
     assert_eq!(data, DisconnectReason::ServerNSDisconnect);
 }
 
+#[tokio::test]
+pub async fn server_close_with_reason() {
+    let (tx, mut rx) = mpsc::channel::<DisconnectReason>(1);
+    let (svc, io) = create_server().await;
+    io.ns("/", move |socket: SocketRef| {
+        socket.on("ban", |s: SocketRef| s.close_with_reason(4003, "banned"));
+        socket.on_disconnect(move |reason: DisconnectReason| tx.try_send(reason).unwrap());
+    });
+
+    let mut ws = create_ws_connection(&svc).await;
+    ws.send(Message::Text(r#"42["ban"]"#.into())).await.unwrap();
+    let data = tokio::time::timeout(Duration::from_millis(20), rx.recv())
+        .await
+        .expect("timeout waiting for DisconnectReason::ServerClose")
+        .unwrap();
+    assert_eq!(data, DisconnectReason::ServerClose);
+
+    let frame = loop {
+        match ws.next().await.unwrap().unwrap() {
+            Message::Close(frame) => break frame.expect("close frame without reason"),
+            _ => continue,
+        }
+    };
+    assert_eq!(u16::from(frame.code), 4003);
+    assert_eq!(frame.reason.as_str(), "banned");
+}
+
 #[tokio::test]
 pub async fn server_ns_close() {
     let (tx, mut rx) = mpsc::channel::<DisconnectReason>(1);
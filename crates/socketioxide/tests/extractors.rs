@@ -1,9 +1,12 @@
 //! Tests for extractors
 use std::convert::Infallible;
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use serde_json::json;
-use socketioxide::extract::{Data, Extension, MaybeExtension, SocketRef, State, TryData};
+use socketioxide::extract::{
+    Data, Extension, HttpParts, MaybeExtension, SocketRef, State, TryData,
+};
 use socketioxide::handler::ConnectHandler;
 use socketioxide::ParserError;
 use socketioxide_core::parser::Parse;
@@ -63,6 +66,38 @@ pub async fn state_extractor() {
     assert_eq!(timeout_rcv(&mut srx).await, res_packet);
 }
 
+#[tokio::test]
+pub async fn http_parts_extractor() {
+    let (svc, io) = fixture::create_server().await;
+    let (tx, mut rx) = mpsc::channel::<(String, Option<SocketAddr>)>(4);
+
+    fn auth(req: HttpParts) -> Result<(), &'static str> {
+        match req.headers.get("x-token") {
+            Some(token) if token == "secret" => Ok(()),
+            _ => Err("unauthorized"),
+        }
+    }
+    io.ns(
+        "/",
+        (move |req: HttpParts| {
+            assert_ok!(tx.try_send((req.uri.to_string(), req.peer_addr())));
+        })
+        .with(auth),
+    );
+
+    let addr: SocketAddr = "127.0.0.1:1234".parse().unwrap();
+    let req = http::Request::builder()
+        .header("x-token", "secret")
+        .extension(addr);
+    let _ws = fixture::create_ws_connection_with_req(&svc, "{}", req).await;
+    let (uri, peer_addr) = timeout_rcv(&mut rx).await;
+    assert_eq!(uri, "ws://127.0.0.1/socket.io/?EIO=4&transport=websocket");
+    assert_eq!(peer_addr, Some(addr));
+
+    let _ws = fixture::create_ws_connection(&svc).await;
+    timeout_rcv_err(&mut rx).await;
+}
+
 #[tokio::test]
 pub async fn data_extractor() {
     let (_, io) = SocketIo::new_svc();
@@ -222,3 +257,32 @@ pub async fn maybe_extension_extractor() {
         EioPacket::Message("2/test,[\"from_ev_test\",null]".into())
     );
 }
+
+#[tokio::test]
+pub async fn extension_removal_hook() {
+    let (_, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::channel::<usize>(1);
+
+    io.ns("/", move |s: SocketRef| {
+        let tx = tx.clone();
+        s.extensions.insert_with_hook(123usize, move |v: usize| {
+            tx.try_send(v).unwrap();
+        });
+        s.on_disconnect(|s: SocketRef| async move {
+            // The extension is still available in the disconnect handler
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            assert_eq!(s.extensions.get::<usize>(), Some(123));
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert!(matches!(timeout_rcv(&mut srx).await, EioPacket::Message(s) if s.starts_with('0')));
+    timeout_rcv_err(&mut rx).await;
+
+    // The hook is called once the socket is disconnected and dropped
+    assert_ok!(stx.try_send(EioPacket::Message("1".into())));
+    let v = tokio::time::timeout(Duration::from_millis(50), rx.recv())
+        .await
+        .unwrap();
+    assert_eq!(v, Some(123));
+}
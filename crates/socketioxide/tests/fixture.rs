@@ -150,11 +150,29 @@ impl AsyncWrite for StreamImpl {
 
 pub async fn create_ws_connection(
     svc: &SocketIoService<NotFoundService>,
+) -> WebSocketStream<StreamImpl> {
+    create_ws_connection_with_auth(svc, "{}").await
+}
+
+/// Create a websocket connection and connect to the main namespace with the given auth payload.
+pub async fn create_ws_connection_with_auth(
+    svc: &SocketIoService<NotFoundService>,
+    auth: &str,
+) -> WebSocketStream<StreamImpl> {
+    create_ws_connection_with_req(svc, auth, Request::builder()).await
+}
+
+/// Create a websocket connection from the given request builder
+/// and connect to the main namespace with the given auth payload.
+pub async fn create_ws_connection_with_req(
+    svc: &SocketIoService<NotFoundService>,
+    auth: &str,
+    req: http::request::Builder,
 ) -> WebSocketStream<StreamImpl> {
     let (tx, rx) = mpsc::unbounded_channel();
     let (tx1, rx1) = mpsc::unbounded_channel();
 
-    let parts = Request::builder()
+    let parts = req
         .method("GET")
         .header("Host", "127.0.0.1")
         .header("Connection", "Upgrade")
@@ -174,7 +192,7 @@ pub async fn create_ws_connection(
         Default::default(),
     )
     .await;
-    ws.send(Message::text("40{}")).await.unwrap();
+    ws.send(Message::text(format!("40{auth}"))).await.unwrap();
     // wait for the server to process messages and call handlers
     tokio::time::sleep(Duration::from_millis(10)).await;
     ws
@@ -0,0 +1,100 @@
+//! Tests for the guards of the message handlers
+mod utils;
+
+use std::time::Duration;
+
+use engineioxide::Packet::*;
+use socketioxide::{
+    extract::{Data, SocketRef},
+    handler::MessageHandler,
+    SocketIo,
+};
+use tokio::sync::mpsc;
+
+fn is_admin(s: SocketRef) -> Result<(), &'static str> {
+    match s.rooms().iter().any(|room| *room == "admins") {
+        true => Ok(()),
+        false => Err("forbidden"),
+    }
+}
+
+#[tokio::test]
+pub async fn guard_rejects_event() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+
+    io.ns("/", move |s: SocketRef| {
+        let ban = move |Data::<String>(user)| {
+            tx.send(user.clone()).unwrap();
+            Ok::<_, ()>(format!("{user} banned"))
+        };
+        s.on("admin:ban", ban.guard(is_admin));
+        s.on("login", |s: SocketRef| s.join("admins"));
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(stx.send(Message(r#"21["admin:ban","foo"]"#.into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message(r#"31[{"error":"forbidden"}]"#.into()));
+    assert_err!(rx.try_recv());
+
+    // Without ack id, the event is rejected silently
+    assert_ok!(stx.send(Message(r#"2["admin:ban","foo"]"#.into())).await);
+
+    assert_ok!(stx.send(Message(r#"2["login"]"#.into())).await);
+    assert_ok!(stx.send(Message(r#"22["admin:ban","foo"]"#.into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message(r#"32["foo banned"]"#.into()));
+    assert_eq!(assert_some!(rx.recv().await), "foo");
+    assert_err!(rx.try_recv());
+}
+
+#[tokio::test]
+pub async fn async_guards_chain() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<&'static str>();
+
+    io.ns("/", move |s: SocketRef| {
+        let (tx1, tx2) = (tx.clone(), tx.clone());
+        let known_user = move |Data::<String>(user)| {
+            let tx = tx1.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                tx.send("known_user").unwrap();
+                match user.as_str() {
+                    "foo" => Ok(()),
+                    _ => Err(format!("unknown user {user}")),
+                }
+            }
+        };
+        let first = move || {
+            tx2.send("first").unwrap();
+            Ok::<_, ()>(())
+        };
+        let handler = move |Data::<String>(user)| {
+            tx.send("handler").unwrap();
+            Ok::<_, ()>(user)
+        };
+        s.on("msg", handler.guard(known_user).guard(first));
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(stx.send(Message(r#"21["msg","foo"]"#.into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message(r#"31["foo"]"#.into()));
+    // The last added guard is called first
+    assert_eq!(assert_some!(rx.recv().await), "first");
+    assert_eq!(assert_some!(rx.recv().await), "known_user");
+    assert_eq!(assert_some!(rx.recv().await), "handler");
+
+    assert_ok!(stx.send(Message(r#"22["msg","bar"]"#.into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message(r#"32[{"error":"unknown user bar"}]"#.into()));
+    assert_eq!(assert_some!(rx.recv().await), "first");
+    assert_eq!(assert_some!(rx.recv().await), "known_user");
+    assert_err!(rx.try_recv());
+}
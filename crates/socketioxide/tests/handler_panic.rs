@@ -0,0 +1,117 @@
+//! Tests for the isolation of the panics of the handlers
+mod utils;
+
+use engineioxide::Packet::*;
+use socketioxide::{
+    extract::SocketRef,
+    handler::{Blocking, HandlerKind, HandlerPanic},
+    socket::DisconnectReason,
+    SocketIo,
+};
+use tokio::sync::mpsc;
+
+fn panic_hook(io: &SocketIo) -> mpsc::UnboundedReceiver<HandlerPanic> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    io.on_handler_error(move |panic| tx.send(panic.clone()).unwrap());
+    rx
+}
+
+fn boom() {
+    panic!("boom");
+}
+
+#[tokio::test]
+pub async fn sync_handler_panic() {
+    let (_svc, io) = SocketIo::new_svc();
+    let mut panics = panic_hook(&io);
+    io.ns("/", |s: SocketRef| {
+        s.on("panic", boom);
+        s.on("ping", |s: SocketRef| s.emit("pong", &()).unwrap());
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message("2[\"panic\"]".into())).await);
+    let panic = assert_some!(panics.recv().await);
+    assert_eq!(panic.ns, "/");
+    assert_eq!(panic.handler, HandlerKind::Message("panic".into()));
+    assert_eq!(panic.message.as_deref(), Some("boom"));
+
+    // The socket is still usable
+    assert_ok!(stx.send(Message("2[\"ping\"]".into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("2[\"pong\",null]".into()));
+}
+
+fn async_boom(ns: &str) {
+    panic!("async boom {ns}");
+}
+
+#[tokio::test]
+pub async fn async_handler_panic() {
+    let (_svc, io) = SocketIo::new_svc();
+    let mut panics = panic_hook(&io);
+    io.ns("/", |s: SocketRef| {
+        s.on("panic", |s: SocketRef| async move {
+            tokio::task::yield_now().await;
+            async_boom(s.ns())
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message("2[\"panic\"]".into())).await);
+    let panic = assert_some!(panics.recv().await);
+    assert_eq!(panic.handler, HandlerKind::Message("panic".into()));
+    assert_eq!(panic.message.as_deref(), Some("async boom /"));
+}
+
+#[tokio::test]
+pub async fn blocking_handler_panic() {
+    let (_svc, io) = SocketIo::new_svc();
+    let mut panics = panic_hook(&io);
+    io.ns("/", |s: SocketRef| s.on("panic", Blocking(boom)));
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message("2[\"panic\"]".into())).await);
+    let panic = assert_some!(panics.recv().await);
+    assert_eq!(panic.handler, HandlerKind::Message("panic".into()));
+    assert_eq!(panic.message.as_deref(), Some("boom"));
+}
+
+#[tokio::test]
+pub async fn connect_handler_panic() {
+    let (_svc, io) = SocketIo::new_svc();
+    let mut panics = panic_hook(&io);
+    io.ns("/", |_: SocketRef| -> () { panic!("connect boom") });
+
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    let panic = assert_some!(panics.recv().await);
+    assert_eq!(panic.handler, HandlerKind::Connect);
+    assert_eq!(panic.message.as_deref(), Some("connect boom"));
+    assert_eq!(io.sockets().len(), 1);
+}
+
+#[tokio::test]
+pub async fn disconnect_on_handler_panic() {
+    let (_svc, io) = SocketIo::builder()
+        .disconnect_on_handler_panic(true)
+        .build_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    io.ns("/", move |s: SocketRef| {
+        let tx = tx.clone();
+        s.on("panic", boom);
+        s.on_disconnect(move |reason: DisconnectReason| tx.send(reason).unwrap());
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message("2[\"panic\"]".into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("1".into()));
+    let reason = assert_some!(rx.recv().await);
+    assert_eq!(reason, DisconnectReason::HandlerPanic);
+    assert!(io.sockets().is_empty());
+}
@@ -0,0 +1,77 @@
+//! Tests for the inbound and outbound packet interceptors
+mod utils;
+
+use engineioxide::Packet::*;
+use socketioxide::{
+    extract::{Data, SocketRef},
+    packet::{Packet, PacketData},
+    SocketIo,
+};
+use socketioxide_core::Value;
+use tokio::sync::mpsc;
+
+/// Replace the payload of the `secret` events.
+fn redact(packet: &mut Packet) {
+    if let PacketData::Event(Value::Str(data, _), _) = &mut packet.inner {
+        if data.starts_with("[\"secret\"") {
+            *data = "[\"secret\",\"redacted\"]".into();
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn intercept_inbound() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    let (ptx, mut prx) = mpsc::unbounded_channel::<usize>();
+
+    io.intercept_inbound(move |packet: &mut Packet| ptx.send(packet.inner.index()).unwrap());
+    io.intercept_inbound(redact);
+    io.ns("/", move |s: SocketRef| {
+        s.on("secret", move |Data::<String>(data)| tx.send(data).unwrap());
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+    assert_eq!(assert_some!(prx.recv().await), 0); // Connect packet
+
+    assert_ok!(stx.send(Message("2[\"secret\",\"password\"]".into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "redacted");
+    assert_eq!(assert_some!(prx.recv().await), 2); // Event packet
+}
+
+#[tokio::test]
+pub async fn intercept_outbound() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (ptx, mut prx) = mpsc::unbounded_channel::<usize>();
+
+    io.intercept_outbound(move |packet: &mut Packet| ptx.send(packet.inner.index()).unwrap());
+    io.intercept_outbound(redact);
+    io.ns("/", |s: SocketRef| {
+        s.join("room");
+        s.on("test", |s: SocketRef, io: SocketIo| async move {
+            s.emit("secret", "password").unwrap();
+            // Not received by the emitting socket
+            s.broadcast().emit("secret", "password").await.unwrap();
+            io.to("room").emit("secret", "password").await.unwrap();
+            drop(s.emit_with_ack::<_, ()>("secret", "password").unwrap());
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+    assert_eq!(assert_some!(prx.recv().await), 0);
+
+    assert_ok!(stx.send(Message("2[\"test\"]".into())).await);
+    for _ in 0..2 {
+        let msg = assert_some!(srx.recv().await);
+        assert_eq!(msg, Message("2[\"secret\",\"redacted\"]".into()));
+    }
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("21[\"secret\",\"redacted\"]".into()));
+    // Each packet is intercepted once, even the broadcasted ones without any receiver
+    for _ in 0..4 {
+        assert_eq!(assert_some!(prx.recv().await), 2);
+    }
+    assert_err!(prx.try_recv());
+}
@@ -0,0 +1,110 @@
+//! Tests for the namespace config overrides
+mod fixture;
+mod utils;
+
+use engineioxide::Packet::*;
+use futures_util::{SinkExt, StreamExt};
+use socketioxide::{
+    extract::{Data, SocketRef},
+    AckError, NamespaceConfig, RateLimitPolicy,
+};
+use tokio::{sync::mpsc, time::Duration};
+use tokio_tungstenite::tungstenite::Message;
+
+use fixture::{create_server, create_ws_connection};
+
+#[tokio::test]
+pub async fn ack_timeout() {
+    let (_svc, io) = create_server().await;
+    let (tx, mut rx) = mpsc::channel::<Result<String, AckError>>(1);
+    let config = NamespaceConfig::new().ack_timeout(Duration::from_millis(10));
+    io.ns_with_config("/slow", config, move |s: SocketRef| async move {
+        let res = assert_ok!(s.emit_with_ack::<_, String>("test", "foo")).await;
+        assert_ok!(tx.try_send(res));
+    });
+
+    let (_stx, mut srx) = io.new_dummy_sock("/slow", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+    assert_some!(srx.recv().await); // Event with ack
+
+    let res = tokio::time::timeout(Duration::from_millis(100), rx.recv())
+        .await
+        .unwrap();
+    assert!(matches!(res, Some(Err(AckError::Timeout))), "{res:?}");
+}
+
+#[tokio::test]
+pub async fn max_events_per_second() {
+    let (_svc, io) = create_server().await;
+    let (tx, mut rx) = mpsc::channel::<&'static str>(10);
+    let handler = |ns: &'static str, tx: mpsc::Sender<&'static str>| {
+        move |s: SocketRef| {
+            s.on("msg", move || {
+                tx.try_send(ns).unwrap();
+            })
+        }
+    };
+    io.ns("/", handler("/", tx.clone()));
+    let config = NamespaceConfig::new().max_events_per_second(1, RateLimitPolicy::Drop);
+    io.ns_with_config("/limited", config, handler("/limited", tx));
+
+    let (stx, _srx) = io.new_dummy_sock("/", ()).await;
+    let (ltx, _lrx) = io.new_dummy_sock("/limited", ()).await;
+    for _ in 0..3 {
+        assert_ok!(stx.send(Message(r#"2["msg"]"#.into())).await);
+        assert_ok!(ltx.send(Message(r#"2/limited,["msg"]"#.into())).await);
+    }
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let mut received = Vec::new();
+    while let Ok(ns) = rx.try_recv() {
+        received.push(ns);
+    }
+    assert_eq!(received.iter().filter(|ns| **ns == "/").count(), 3);
+    assert_eq!(received.iter().filter(|ns| **ns == "/limited").count(), 1);
+}
+
+#[tokio::test]
+pub async fn max_payload() {
+    let (svc, io) = create_server().await;
+    let (tx, mut rx) = mpsc::channel::<String>(10);
+    let handler = |tx: mpsc::Sender<String>| {
+        move |s: SocketRef| {
+            s.on("msg", move |Data::<String>(data)| {
+                tx.try_send(data).unwrap();
+            })
+        }
+    };
+    io.ns("/", handler(tx.clone()));
+    let config = NamespaceConfig::new().max_payload(20);
+    io.ns_with_config("/small", config, handler(tx));
+
+    let mut ws = create_ws_connection(&svc).await;
+    assert_ok!(ws.send(Message::text("40/small,")).await);
+    while let Some(Ok(msg)) = ws.next().await {
+        if msg.to_text().unwrap().starts_with("40/small,") {
+            break;
+        }
+    }
+
+    let large = "a".repeat(50);
+    let send = |ns: &str, data: &str| Message::text(format!(r#"42{ns}["msg","{data}"]"#));
+    assert_ok!(ws.send(send("/small,", "foo")).await);
+    assert_eq!(assert_some!(rx.recv().await), "foo");
+    // The default namespace has no limit
+    assert_ok!(ws.send(send("", &large)).await);
+    assert_eq!(assert_some!(rx.recv().await), large);
+
+    // The connection is closed
+    assert_ok!(ws.send(send("/small,", &large)).await);
+    let closed = tokio::time::timeout(Duration::from_millis(100), async {
+        while let Some(Ok(msg)) = ws.next().await {
+            if msg.is_close() {
+                break;
+            }
+        }
+    })
+    .await;
+    assert_ok!(closed);
+    assert!(rx.try_recv().is_err());
+}
@@ -0,0 +1,63 @@
+//! Tests for the deregistration and replacement of event handlers
+mod utils;
+
+use engineioxide::Packet::*;
+use socketioxide::{extract::SocketRef, SocketIo};
+use tokio::sync::mpsc;
+
+#[tokio::test]
+pub async fn off_from_handler() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<&'static str>();
+
+    io.ns("/", move |s: SocketRef| {
+        let tx1 = tx.clone();
+        s.on("init", move |s: SocketRef| {
+            assert!(s.off("init"));
+            assert!(!s.off("init"));
+            // Replace the handler of the "msg" event
+            s.on("msg", move || tx.send("replaced").unwrap());
+        });
+        s.on("msg", move || tx1.send("original").unwrap());
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(stx.send(Message("2[\"init\"]".into())).await);
+    assert_ok!(stx.send(Message("2[\"init\"]".into())).await);
+    assert_ok!(stx.send(Message("2[\"msg\"]".into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "replaced");
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_err!(rx.try_recv());
+}
+
+#[tokio::test]
+pub async fn off_all() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<&'static str>();
+
+    io.ns("/", move |s: SocketRef| {
+        let tx1 = tx.clone();
+        let tx2 = tx.clone();
+        s.on_any(move |_, _, _| tx1.send("any").unwrap());
+        s.on("msg", move || tx2.send("msg").unwrap());
+        s.on("off", |s: SocketRef| s.off_all());
+        s.on_disconnect(move || tx.send("disconnect").unwrap());
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(stx.send(Message("2[\"msg\"]".into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "any");
+    assert_eq!(assert_some!(rx.recv().await), "msg");
+
+    assert_ok!(stx.send(Message("2[\"off\"]".into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "any");
+    assert_ok!(stx.send(Message("2[\"msg\"]".into())).await);
+
+    // The disconnect handler is kept
+    assert_ok!(stx.send(Message("1".into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "disconnect");
+}
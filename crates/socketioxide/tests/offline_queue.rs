@@ -0,0 +1,101 @@
+//! Tests for the offline message queue
+mod utils;
+
+use std::time::Duration;
+
+use engineioxide::Packet::*;
+use socketioxide::{
+    extract::{Data, SocketRef},
+    offline::OfflineQueueConfig,
+    BroadcastError, SocketIo,
+};
+
+fn setup(config: Option<OfflineQueueConfig>) -> SocketIo {
+    let mut builder = SocketIo::builder();
+    if let Some(config) = config {
+        builder = builder.with_offline_queue(config);
+    }
+    let (_svc, io) = builder.build_svc();
+    io.ns("/", |s: SocketRef, Data(user): Data<String>| {
+        s.set_user(user);
+    });
+    io
+}
+
+#[tokio::test]
+pub async fn deliver_on_connect() {
+    let io = setup(Some(OfflineQueueConfig::default()));
+
+    assert_ok!(io.to_user("alice").persistent().emit("test", &1).await);
+    assert_ok!(io.to_user("alice").persistent().emit("test", &2).await);
+    // Non persistent events are not queued
+    assert_ok!(io.to_user("alice").emit("test", &3).await);
+
+    let (_stx, mut srx) = io.new_dummy_sock("/", "alice").await;
+    assert_some!(srx.recv().await); // NS connect packet
+    for i in 1..=2 {
+        let msg = assert_some!(srx.recv().await);
+        assert_eq!(msg, Message(format!(r#"2["test",{i}]"#).into()));
+    }
+
+    // Events emitted to an online user are sent directly
+    assert_ok!(io.to_user("alice").persistent().emit("test", &4).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message(r#"2["test",4]"#.into()));
+
+    // The queue is emptied once delivered
+    let (_stx2, mut srx2) = io.new_dummy_sock("/", "alice").await;
+    assert_some!(srx2.recv().await); // NS connect packet
+    tokio::time::timeout(Duration::from_millis(10), srx2.recv())
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+pub async fn queue_caps() {
+    let io = setup(Some(OfflineQueueConfig::new(Duration::from_secs(60), 2)));
+    for i in 1..=3 {
+        assert_ok!(io.to_user("bob").persistent().emit("test", &i).await);
+    }
+
+    let (_stx, mut srx) = io.new_dummy_sock("/", "bob").await;
+    assert_some!(srx.recv().await); // NS connect packet
+    for i in 2..=3 {
+        let msg = assert_some!(srx.recv().await);
+        assert_eq!(msg, Message(format!(r#"2["test",{i}]"#).into()));
+    }
+    tokio::time::timeout(Duration::from_millis(10), srx.recv())
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+pub async fn disabled_queue() {
+    let io = setup(None);
+    assert_ok!(io.to_user("carol").persistent().emit("test", &1).await);
+
+    let (_stx, mut srx) = io.new_dummy_sock("/", "carol").await;
+    assert_some!(srx.recv().await); // NS connect packet
+    tokio::time::timeout(Duration::from_millis(10), srx.recv())
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+pub async fn persistent_without_user() {
+    let io = setup(Some(OfflineQueueConfig::default()));
+    let err = io.to("room").persistent().emit("test", &1).await;
+    assert!(matches!(err, Err(BroadcastError::InvalidPersistent)));
+}
+
+#[tokio::test]
+pub async fn persistent_with_other_rooms() {
+    let io = setup(Some(OfflineQueueConfig::default()));
+    let err = io
+        .to_user("dave")
+        .persistent()
+        .to("room")
+        .emit("test", &1)
+        .await;
+    assert!(matches!(err, Err(BroadcastError::InvalidPersistent)));
+}
@@ -0,0 +1,88 @@
+//! Tests for the catch-all event handlers
+mod utils;
+
+use engineioxide::Packet::*;
+use socketioxide::{extract::SocketRef, SocketIo};
+use socketioxide_core::Value;
+use tokio::sync::mpsc;
+
+fn payload(value: &Value) -> String {
+    match value {
+        Value::Str(data, _) => data.to_string(),
+        Value::Bytes(_) => unreachable!(),
+    }
+}
+
+#[tokio::test]
+pub async fn on_any() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<(String, String)>();
+    let (tx1, mut rx1) = mpsc::unbounded_channel::<()>();
+
+    io.ns("/", move |s: SocketRef| {
+        let tx2 = tx.clone();
+        s.on_any(move |_, event, data| {
+            tx.send((event.to_string(), payload(data))).unwrap();
+        });
+        s.on_any(move |_, event, _| {
+            tx2.send((event.to_string(), "second".into())).unwrap();
+        });
+        s.on("test", move || tx1.send(()).unwrap());
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(stx.send(Message("2[\"test\",1]".into())).await);
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        ("test".into(), "[\"test\",1]".into())
+    );
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        ("test".into(), "second".into())
+    );
+    assert_some!(rx1.recv().await);
+
+    // The handler is called even if there is no handler for the event
+    assert_ok!(stx.send(Message("21[\"unknown\",\"foo\"]".into())).await);
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        ("unknown".into(), "[\"unknown\",\"foo\"]".into())
+    );
+    assert_some!(rx.recv().await);
+    assert_err!(rx1.try_recv());
+}
+
+#[tokio::test]
+pub async fn on_any_outgoing() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<(String, String)>();
+
+    io.ns("/", move |s: SocketRef| {
+        s.on_any_outgoing(move |_, event, data| {
+            tx.send((event.to_string(), payload(data))).unwrap();
+        });
+        s.on("test", |s: SocketRef| async move {
+            s.emit("foo", "bar").unwrap();
+            drop(s.emit_with_ack::<_, ()>("baz", &1).unwrap());
+            // Broadcasts are not passed to the outgoing handler
+            s.broadcast().emit("broadcast", &()).await.unwrap();
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+    assert_ok!(stx.send(Message("2[\"test\"]".into())).await);
+
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        ("foo".into(), "[\"foo\",\"bar\"]".into())
+    );
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        ("baz".into(), "[\"baz\",1]".into())
+    );
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_err!(rx.try_recv());
+}
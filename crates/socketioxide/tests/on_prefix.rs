@@ -0,0 +1,74 @@
+//! Tests for the event handlers registered for an event name prefix
+mod utils;
+
+use engineioxide::Packet::*;
+use socketioxide::{
+    extract::{Data, EventSuffix, SocketRef},
+    SocketIo,
+};
+use tokio::sync::mpsc;
+
+#[tokio::test]
+pub async fn on_prefix() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+
+    io.ns("/", move |s: SocketRef| {
+        let tx1 = tx.clone();
+        let tx2 = tx.clone();
+        s.on_prefix("chat:", move |EventSuffix(suffix), Data::<String>(data)| {
+            tx.send(format!("chat {suffix} {data}")).unwrap()
+        });
+        s.on_prefix("chat:room:", move |EventSuffix(suffix)| {
+            tx1.send(format!("room {suffix}")).unwrap()
+        });
+        s.on("chat:ping", move |EventSuffix(event)| {
+            tx2.send(format!("exact {event}")).unwrap()
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(stx.send(Message(r#"2["chat:message","foo"]"#.into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "chat message foo");
+
+    // The longest prefix wins
+    assert_ok!(stx.send(Message(r#"2["chat:room:join"]"#.into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "room join");
+
+    // The exact handler has precedence over the prefixes
+    assert_ok!(stx.send(Message(r#"2["chat:ping"]"#.into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "exact chat:ping");
+
+    assert_ok!(stx.send(Message(r#"2["other"]"#.into())).await);
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_err!(rx.try_recv());
+}
+
+#[tokio::test]
+pub async fn off_prefix() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<&'static str>();
+
+    io.ns("/", move |s: SocketRef| {
+        let tx1 = tx.clone();
+        s.on_prefix("a:", move || tx.send("original").unwrap());
+        // Replace the handler of the "a:" prefix
+        s.on_prefix("a:", move || tx1.send("replaced").unwrap());
+        s.on("off", |s: SocketRef| {
+            assert!(s.off_prefix("a:"));
+            assert!(!s.off_prefix("a:"));
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(stx.send(Message(r#"2["a:b"]"#.into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "replaced");
+    assert_ok!(stx.send(Message(r#"2["off"]"#.into())).await);
+    assert_ok!(stx.send(Message(r#"2["a:b"]"#.into())).await);
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_err!(rx.try_recv());
+}
@@ -0,0 +1,227 @@
+//! Tests for the composition of the broadcast operators and the serialization of broadcasted packets
+mod utils;
+
+use std::time::Duration;
+
+use engineioxide::Packet::*;
+use futures_util::StreamExt;
+use socketioxide::{
+    extract::{Data, SocketRef},
+    SocketIo,
+};
+use tokio::sync::mpsc;
+
+/// Connects three sockets: `a` in room1, `b` in room1 and room2 and `c` in room2.
+/// Returns the socket `a` and the receivers of the three clients.
+async fn setup(io: &SocketIo) -> (SocketRef, [mpsc::Receiver<engineioxide::Packet>; 3]) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    io.ns("/", move |s: SocketRef, Data(rooms): Data<Vec<String>>| {
+        s.join(rooms);
+        tx.send(s).unwrap();
+    });
+    let mut clients = Vec::new();
+    let mut sockets = Vec::new();
+    for rooms in [vec!["room1"], vec!["room1", "room2"], vec!["room2"]] {
+        let (stx, mut srx) = io.new_dummy_sock("/", rooms).await;
+        assert_some!(srx.recv().await); // NS connect packet
+        sockets.push(assert_some!(rx.recv().await));
+        // Keep the client alive for the duration of the test
+        std::mem::forget(stx);
+        clients.push(srx);
+    }
+    (sockets.remove(0), clients.try_into().unwrap())
+}
+
+/// Asserts which clients received the `test` event and that nothing else was received.
+async fn assert_received(
+    clients: &mut [mpsc::Receiver<engineioxide::Packet>; 3],
+    expected: [bool; 3],
+) {
+    // Let the packets go through the dummy transports
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    for (i, (client, expected)) in clients.iter_mut().zip(expected).enumerate() {
+        match client.try_recv() {
+            Ok(Message(msg)) if expected => assert!(msg.contains(r#"["test","#), "{msg}"),
+            Err(_) if !expected => {}
+            res => panic!("client {i}: expected to receive: {expected}, got {res:?}"),
+        }
+        assert_err!(client.try_recv());
+    }
+}
+
+#[tokio::test]
+pub async fn socket_operators() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (a, mut clients) = setup(&io).await;
+
+    assert_ok!(a.to("room2").emit("test", "").await);
+    assert_received(&mut clients, [false, true, true]).await;
+
+    assert_ok!(a.to("room1").to("room2").emit("test", "").await);
+    assert_received(&mut clients, [false, true, true]).await;
+
+    assert_ok!(a.within("room1").emit("test", "").await);
+    assert_received(&mut clients, [true, true, false]).await;
+
+    assert_ok!(a.except("room1").emit("test", "").await);
+    assert_received(&mut clients, [false, false, true]).await;
+
+    assert_ok!(a.broadcast().emit("test", "").await);
+    assert_received(&mut clients, [false, true, true]).await;
+
+    assert_ok!(a.local().emit("test", "").await);
+    assert_received(&mut clients, [false, true, true]).await;
+
+    // Any order gives the same result
+    assert_ok!(a.local().to("room2").except("room1").emit("test", "").await);
+    assert_received(&mut clients, [false, false, true]).await;
+    assert_ok!(a.except("room1").local().to("room2").emit("test", "").await);
+    assert_received(&mut clients, [false, false, true]).await;
+    let timeout = Duration::from_millis(10);
+    assert_ok!(
+        a.timeout(timeout)
+            .to("room2")
+            .local()
+            .emit("test", "")
+            .await
+    );
+    assert_received(&mut clients, [false, true, true]).await;
+    assert_ok!(
+        a.to("room2")
+            .local()
+            .timeout(timeout)
+            .emit("test", "")
+            .await
+    );
+    assert_received(&mut clients, [false, true, true]).await;
+}
+
+#[tokio::test]
+pub async fn io_operators() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (_a, mut clients) = setup(&io).await;
+
+    assert_ok!(io.emit("test", "").await);
+    assert_received(&mut clients, [true, true, true]).await;
+
+    assert_ok!(io.broadcast().emit("test", "").await);
+    assert_received(&mut clients, [true, true, true]).await;
+
+    assert_ok!(io.local().emit("test", "").await);
+    assert_received(&mut clients, [true, true, true]).await;
+
+    assert_ok!(io.to("room2").emit("test", "").await);
+    assert_received(&mut clients, [false, true, true]).await;
+
+    assert_ok!(io.except("room2").emit("test", "").await);
+    assert_received(&mut clients, [true, false, false]).await;
+
+    assert_ok!(
+        io.to("room1")
+            .except("room2")
+            .local()
+            .emit("test", "")
+            .await
+    );
+    assert_received(&mut clients, [true, false, false]).await;
+    assert_ok!(
+        io.local()
+            .except("room2")
+            .to("room1")
+            .emit("test", "")
+            .await
+    );
+    assert_received(&mut clients, [true, false, false]).await;
+}
+
+#[tokio::test]
+pub async fn to_socket() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (a, mut clients) = setup(&io).await;
+
+    assert_ok!(io.to_socket(a.id).emit("test", "").await);
+    assert_received(&mut clients, [true, false, false]).await;
+
+    // The previously selected rooms are discarded
+    assert_ok!(io.to("room2").to_socket(a.id).emit("test", "").await);
+    assert_received(&mut clients, [true, false, false]).await;
+
+    assert_ok!(io.to_socket(a.id).join("room3").await);
+    let mut rooms = a.rooms();
+    rooms.sort();
+    assert_eq!(rooms, ["room1", "room3"]);
+    let socket = assert_some!(assert_ok!(io.fetch_socket(a.id).await));
+    assert_eq!(socket.data().id, a.id);
+
+    // Unknown sockets are ignored
+    let sid = socketioxide::socket::Sid::new();
+    assert_ok!(io.to_socket(sid).emit("test", "").await);
+    assert_received(&mut clients, [false, false, false]).await;
+    assert!(assert_ok!(io.fetch_socket(sid).await).is_none());
+}
+
+#[tokio::test]
+pub async fn operators_with_ack() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (a, mut clients) = setup(&io).await;
+    let timeout = Duration::from_millis(10);
+
+    // The dummy clients never answer so every ack times out
+    let stream = assert_ok!(
+        a.local()
+            .timeout(timeout)
+            .except("room1")
+            .emit_with_ack::<_, ()>("test", "")
+            .await
+    );
+    assert_eq!(stream.count().await, 1);
+    assert_received(&mut clients, [false, false, true]).await;
+
+    let stream = assert_ok!(
+        a.timeout(timeout)
+            .to("room2")
+            .local()
+            .emit_with_ack::<_, ()>("test", "")
+            .await
+    );
+    assert_eq!(stream.count().await, 2);
+    assert_received(&mut clients, [false, true, true]).await;
+
+    let stream = assert_ok!(
+        io.timeout(timeout)
+            .except("room2")
+            .local()
+            .emit_with_ack::<_, ()>("test", "")
+            .await
+    );
+    assert_eq!(stream.count().await, 1);
+    assert_received(&mut clients, [true, false, false]).await;
+
+    let stream = assert_ok!(
+        io.to("room2")
+            .local()
+            .timeout(timeout)
+            .emit_with_ack::<_, ()>("test", "")
+            .await
+    );
+    assert_eq!(stream.count().await, 2);
+    assert_received(&mut clients, [false, true, true]).await;
+}
+
+#[tokio::test]
+pub async fn broadcast_shared_buffer() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (_, mut clients) = setup(&io).await;
+
+    assert_ok!(io.emit("test", "shared").await);
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    let msgs: Vec<_> = clients
+        .iter_mut()
+        .map(|client| match assert_ok!(client.try_recv()) {
+            Message(msg) => msg,
+            p => panic!("unexpected packet {p:?}"),
+        })
+        .collect();
+    // The packet is serialized once for all the sockets
+    assert!(msgs.iter().all(|msg| msg.as_ptr() == msgs[0].as_ptr()));
+}
@@ -0,0 +1,266 @@
+//! Property-based tests of the packet encoders and decoders.
+//!
+//! Generated socket.io packets are encoded with the common parser, sent through the engine.io
+//! polling payload encoders and decoders of the v3 and v4 protocols, split in arbitrary chunks,
+//! and decoded back.
+use std::collections::VecDeque;
+
+use bytes::Bytes;
+use engineioxide::{decode_payload, encode_packets, Packet as EIoPacket, ProtocolVersion};
+use proptest::{prelude::*, sample::Index};
+use serde_json::{Map, Number, Value as Json};
+use socketioxide_core::{
+    packet::{Packet, PacketData},
+    parser::{Parse, ParseError, ParserState},
+    Str, Value,
+};
+use socketioxide_parser_common::CommonParser;
+
+const MAX_PAYLOAD: u64 = 10_000_000;
+
+/// Characters and grapheme clusters used to generate strings: json and packet delimiters,
+/// multi-byte, combining, astral and control characters.
+const CHARS: &[&str] = &[
+    "a",
+    "Z",
+    "0",
+    "9",
+    " ",
+    "\"",
+    "\\",
+    "/",
+    ",",
+    ":",
+    "[",
+    "]",
+    "{",
+    "}",
+    "-",
+    "é",
+    "€",
+    "日本",
+    "e\u{301}",
+    "🦀",
+    "👩\u{200d}👩\u{200d}👧",
+    "🇫🇷",
+    "\n",
+    "\r\n",
+    "\u{1e}",
+    "\u{0}",
+    "\u{ff}",
+];
+
+/// A string made of delimiters and tricky unicode chars, or any unicode string.
+fn string(max_len: usize) -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => prop::collection::vec(prop::sample::select(CHARS), 0..=max_len)
+            .prop_map(|chars| chars.concat()),
+        1 => prop::collection::vec(any::<char>(), 0..=max_len)
+            .prop_map(|chars| chars.into_iter().collect()),
+    ]
+}
+
+fn json() -> impl Strategy<Value = Json> {
+    let leaf = prop_oneof![
+        Just(Json::Null),
+        any::<bool>().prop_map(Json::Bool),
+        any::<i64>().prop_map(|n| Json::Number(n.into())),
+        (-8000..8000).prop_map(|n| Json::Number(Number::from_f64(f64::from(n) / 8.0).unwrap())),
+        string(8).prop_map(Json::String),
+    ];
+    leaf.prop_recursive(3, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Json::Array),
+            prop::collection::vec((string(4), inner), 0..4)
+                .prop_map(|entries| Json::Object(entries.into_iter().collect())),
+        ]
+    })
+}
+
+fn ns() -> impl Strategy<Value = Str> {
+    // A namespace can't contain a comma, which ends it in the common parser,
+    // nor control characters, which are not escaped unlike json strings
+    let ns = string(6).prop_map(|ns| ns.replace(|c: char| c == ',' || c.is_control(), ""));
+    prop_oneof![
+        Just(Str::from("/")),
+        ns.prop_map(|ns| match ns.is_empty() {
+            true => Str::from("/"),
+            false => Str::from(format!("/{ns}")),
+        }),
+    ]
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(untagged)]
+enum Arg {
+    Json(Json),
+    Bin(Bytes),
+}
+
+/// The arguments of an event or an ack, with binary attachments.
+fn args() -> impl Strategy<Value = Vec<Arg>> {
+    let bin = prop::collection::vec(any::<u8>(), 0..64).prop_map(|bin| Arg::Bin(bin.into()));
+    let arg = prop_oneof![2 => json().prop_map(Arg::Json), 1 => bin];
+    prop::collection::vec(arg, 0..4)
+}
+
+/// A json object, as the auth payload must be an object and a null connect error data is decoded as no data.
+fn object() -> impl Strategy<Value = Json> {
+    prop::collection::vec((string(4), json()), 0..4)
+        .prop_map(|entries| Json::Object(entries.into_iter().collect::<Map<_, _>>()))
+}
+
+fn packet() -> impl Strategy<Value = Packet> {
+    prop_oneof![
+        (ns(), prop::option::of(object())).prop_map(|(ns, auth)| {
+            let value = auth.map(|auth| CommonParser.encode_default(&auth).unwrap());
+            Packet::connect(ns, value)
+        }),
+        ns().prop_map(Packet::disconnect),
+        (ns(), string(16), prop::option::of(object())).prop_map(|(ns, msg, data)| {
+            let data = data.map(|data| CommonParser.encode_default(&data).unwrap());
+            Packet::connect_error(ns, msg, data)
+        }),
+        (ns(), string(8), args(), prop::option::of(0..i64::MAX)).prop_map(
+            |(ns, event, args, ack)| {
+                let value = CommonParser.encode_value(&args, Some(&event)).unwrap();
+                let mut packet = Packet::event(ns, value);
+                if let Some(ack) = ack {
+                    packet.inner.set_ack_id(ack);
+                }
+                packet
+            }
+        ),
+        (ns(), args(), 0..i64::MAX).prop_map(|(ns, args, ack)| {
+            let value = CommonParser.encode_value(&args, None).unwrap();
+            Packet::ack(ns, value, ack)
+        }),
+    ]
+}
+
+/// The engine.io packets of an encoded socket.io packet, with the binary packets of the protocol.
+fn encode(packet: Packet, protocol: ProtocolVersion) -> Vec<EIoPacket> {
+    let binary = match protocol {
+        ProtocolVersion::V3 => EIoPacket::BinaryV3,
+        ProtocolVersion::V4 => EIoPacket::Binary,
+    };
+    match CommonParser.encode(packet) {
+        Value::Str(msg, bins) => std::iter::once(EIoPacket::Message(msg))
+            .chain(bins.into_iter().flatten().map(binary))
+            .collect(),
+        Value::Bytes(bin) => vec![binary(bin)],
+    }
+}
+
+/// Decode the socket.io packets from the engine.io packets.
+fn decode(packets: Vec<EIoPacket>) -> Result<Vec<Packet>, TestCaseError> {
+    let state = ParserState::default();
+    let mut res = Vec::new();
+    for packet in packets {
+        let packet = match packet {
+            EIoPacket::Message(msg) => CommonParser.decode_str(&state, msg),
+            EIoPacket::Binary(bin) | EIoPacket::BinaryV3(bin) => {
+                CommonParser.decode_bin(&state, bin)
+            }
+            p => return Err(TestCaseError::fail(format!("unexpected packet {p:?}"))),
+        };
+        match packet {
+            Ok(packet) => res.push(normalize(packet)),
+            Err(ParseError::NeedsMoreBinaryData) => (),
+            Err(e) => return Err(TestCaseError::fail(format!("decoding error: {e}"))),
+        }
+    }
+    Ok(res)
+}
+
+/// The decoded packets have no attachments list when there are no attachments.
+fn normalize(mut packet: Packet) -> Packet {
+    match &mut packet.inner {
+        PacketData::Event(Value::Str(_, bins), _)
+        | PacketData::EventAck(Value::Str(_, bins), _)
+            if bins.as_ref().is_some_and(VecDeque::is_empty) =>
+        {
+            *bins = None
+        }
+        _ => (),
+    }
+    packet
+}
+
+/// Encode the packets in a polling payload and decode it back, split at the given indexes.
+fn payload_roundtrip(
+    packets: Vec<EIoPacket>,
+    protocol: ProtocolVersion,
+    supports_binary: bool,
+    splits: &[Index],
+) -> Result<Vec<EIoPacket>, TestCaseError> {
+    let binary = supports_binary
+        && protocol == ProtocolVersion::V3
+        && packets
+            .iter()
+            .any(|p| matches!(p, EIoPacket::Binary(_) | EIoPacket::BinaryV3(_)));
+    let data = futures_util::FutureExt::now_or_never(encode_packets(
+        packets,
+        protocol,
+        supports_binary,
+        MAX_PAYLOAD,
+    ))
+    .expect("the encoder should not wait with buffered packets");
+    let data = Bytes::from(data.concat());
+
+    let mut splits: Vec<usize> = splits.iter().map(|i| i.index(data.len() + 1)).collect();
+    splits.push(data.len());
+    splits.sort_unstable();
+    splits.dedup();
+    let mut start = 0;
+    let chunks = splits
+        .into_iter()
+        .filter(|&end| end > 0)
+        .map(|end| data.slice(std::mem::replace(&mut start, end)..end))
+        .collect();
+    decode_payload(chunks, protocol, binary, MAX_PAYLOAD)
+        .into_iter()
+        .map(|p| p.map_err(|e| TestCaseError::fail(format!("payload decoding error: {e}"))))
+        .collect()
+}
+
+fn roundtrip(
+    packets: Vec<Packet>,
+    protocol: ProtocolVersion,
+    supports_binary: bool,
+    splits: &[Index],
+) -> Result<(), TestCaseError> {
+    let eio_packets = packets
+        .iter()
+        .flat_map(|p| encode(p.clone(), protocol))
+        .collect();
+    let eio_packets = payload_roundtrip(eio_packets, protocol, supports_binary, splits)?;
+    let expected: Vec<Packet> = packets.into_iter().map(normalize).collect();
+    prop_assert_eq!(decode(eio_packets)?, expected);
+    Ok(())
+}
+
+fn packets() -> impl Strategy<Value = Vec<Packet>> {
+    prop::collection::vec(packet(), 1..4)
+}
+
+fn splits() -> impl Strategy<Value = Vec<Index>> {
+    prop::collection::vec(any::<Index>(), 0..8)
+}
+
+proptest! {
+    #[test]
+    fn roundtrip_v4(packets in packets(), splits in splits()) {
+        roundtrip(packets, ProtocolVersion::V4, false, &splits)?;
+    }
+
+    #[test]
+    fn roundtrip_v3_string(packets in packets(), splits in splits()) {
+        roundtrip(packets, ProtocolVersion::V3, false, &splits)?;
+    }
+
+    #[test]
+    fn roundtrip_v3_binary(packets in packets(), splits in splits()) {
+        roundtrip(packets, ProtocolVersion::V3, true, &splits)?;
+    }
+}
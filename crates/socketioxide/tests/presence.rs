@@ -0,0 +1,108 @@
+//! Tests for the presence subsystem
+mod utils;
+
+use engineioxide::Packet::*;
+use serde::{Deserialize, Serialize};
+use socketioxide::{
+    extract::{Data, SocketRef},
+    presence::PresenceConfig,
+    SocketIo,
+};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct User {
+    name: String,
+}
+
+#[tokio::test]
+pub async fn presence_members() {
+    let (_svc, io) = SocketIo::builder()
+        .with_presence(PresenceConfig::default())
+        .build_svc();
+    io.ns("/", |s: SocketRef, Data(user): Data<Option<User>>| {
+        if let Some(user) = user {
+            s.set_presence_meta(user).unwrap();
+        }
+        s.join("room");
+    });
+
+    let (_stx1, mut srx1) = io.new_dummy_sock("/", User { name: "foo".into() }).await;
+    assert_some!(srx1.recv().await);
+    let (_stx2, mut srx2) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx2.recv().await);
+
+    let mut members = io.presence::<User>("room").await.unwrap();
+    assert_eq!(members.len(), 2);
+    members.sort_by_key(|m| m.meta.is_none());
+    assert_eq!(members[0].meta, Some(User { name: "foo".into() }));
+    assert_eq!(members[0].server_id, io.config().server_id);
+    assert_eq!(members[1].meta, None);
+
+    assert!(io.presence::<User>("other").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+pub async fn presence_deltas() {
+    let (_svc, io) = SocketIo::builder()
+        .with_presence(PresenceConfig::default())
+        .build_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    io.ns("/", move |s: SocketRef, Data(join): Data<bool>| {
+        if join {
+            tx.send(s.id).unwrap();
+            s.set_presence_meta(User { name: "foo".into() }).unwrap();
+            s.join("room");
+            s.on("rename", |s: SocketRef, Data::<String>(name)| {
+                s.set_presence_meta(User { name }).unwrap();
+            });
+        } else {
+            s.subscribe_presence("room");
+        }
+    });
+
+    let (_stx1, mut srx1) = io.new_dummy_sock("/", false).await;
+    assert_some!(srx1.recv().await);
+    let (stx2, mut srx2) = io.new_dummy_sock("/", true).await;
+    assert_some!(srx2.recv().await);
+    let id = assert_some!(rx.recv().await);
+
+    let msg = assert_some!(srx1.recv().await);
+    let expected =
+        format!(r#"2["presence","room",{{"type":"join","id":"{id}","meta":{{"name":"foo"}}}}]"#);
+    assert_eq!(msg, Message(expected.into()));
+
+    assert_ok!(stx2.send(Message("2[\"rename\",\"bar\"]".into())).await);
+    let msg = assert_some!(srx1.recv().await);
+    let expected =
+        format!(r#"2["presence","room",{{"type":"update","id":"{id}","meta":{{"name":"bar"}}}}]"#);
+    assert_eq!(msg, Message(expected.into()));
+
+    assert_ok!(stx2.send(Close).await);
+    let msg = assert_some!(srx1.recv().await);
+    let expected = format!(r#"2["presence","room",{{"type":"leave","id":"{id}"}}]"#);
+    assert_eq!(msg, Message(expected.into()));
+
+    // The joining socket is not subscribed
+    assert_err!(srx2.try_recv());
+}
+
+#[tokio::test]
+pub async fn presence_disabled() {
+    let (_svc, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.subscribe_presence("room");
+        s.set_presence_meta(User { name: "foo".into() }).unwrap();
+        s.join("room");
+    });
+
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    let members = io.presence::<User>("room").await.unwrap();
+    assert_eq!(members[0].meta, Some(User { name: "foo".into() }));
+
+    // No subscribers room nor delta
+    assert_eq!(io.rooms().await.unwrap(), ["room"]);
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_err!(srx.try_recv());
+}
@@ -0,0 +1,85 @@
+//! Tests for the rate limiting of the events received by a socket
+mod utils;
+
+use std::time::{Duration, Instant};
+
+use engineioxide::Packet::*;
+use socketioxide::{
+    extract::{Data, SocketRef},
+    socket::DisconnectReason,
+    RateLimitPolicy, SocketIo,
+};
+use tokio::sync::mpsc;
+
+fn create_server(policy: RateLimitPolicy) -> (SocketIo, mpsc::Receiver<usize>) {
+    let (_svc, io) = SocketIo::builder()
+        .max_events_per_second(3, policy)
+        .build_svc();
+    let (tx, rx) = mpsc::channel(100);
+    io.ns("/", move |s: SocketRef| {
+        s.on("test", move |Data::<usize>(i)| {
+            tx.try_send(i).unwrap();
+        });
+    });
+    (io, rx)
+}
+
+fn event(i: usize) -> engineioxide::Packet {
+    Message(format!(r#"2["test",{i}]"#).into())
+}
+
+#[tokio::test]
+pub async fn drop_policy() {
+    let (io, mut rx) = create_server(RateLimitPolicy::Drop);
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    for i in 0..5 {
+        assert_ok!(stx.send(event(i)).await);
+    }
+    for i in 0..3 {
+        assert_eq!(assert_some!(rx.recv().await), i);
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+pub async fn queue_policy() {
+    let (io, mut rx) = create_server(RateLimitPolicy::Queue);
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    let start = Instant::now();
+    for i in 0..5 {
+        assert_ok!(stx.send(event(i)).await);
+    }
+    // All the events are handled in order, the last two are delayed
+    for i in 0..5 {
+        assert_eq!(assert_some!(rx.recv().await), i);
+    }
+    assert!(start.elapsed() >= Duration::from_millis(600));
+}
+
+#[tokio::test]
+pub async fn disconnect_policy() {
+    let (_svc, io) = SocketIo::builder()
+        .max_events_per_second(1, RateLimitPolicy::Disconnect)
+        .build_svc();
+    let (tx, mut rx) = mpsc::channel(1);
+    io.ns("/", move |s: SocketRef| {
+        s.on_disconnect(move |reason: DisconnectReason| {
+            tx.try_send(reason).unwrap();
+        });
+    });
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    assert_ok!(stx.send(event(0)).await);
+    assert_ok!(stx.send(event(1)).await);
+    assert_eq!(assert_some!(srx.recv().await), Message("1".into()));
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        DisconnectReason::RateLimitExceeded
+    );
+}
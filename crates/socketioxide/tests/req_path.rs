@@ -0,0 +1,72 @@
+//! Tests for the request paths of the socket.io server
+use bytes::Bytes;
+use http::{Request, StatusCode};
+use http_body_util::Empty;
+use socketioxide::{ReqPath, SocketIo};
+use tower_service::Service;
+
+async fn handshake_status<S, B>(svc: &mut S, path: &str) -> StatusCode
+where
+    S: Service<Request<Empty<Bytes>>, Response = http::Response<B>>,
+    S::Error: std::fmt::Debug,
+{
+    let req = Request::get(format!("http://127.0.0.1{path}?EIO=4&transport=polling"))
+        .body(Empty::new())
+        .unwrap();
+    svc.call(req).await.unwrap().status()
+}
+
+#[tokio::test]
+pub async fn multiple_paths() {
+    let (mut svc, _io) = SocketIo::builder()
+        .req_path(["/socket.io", "/legacy/socket.io"])
+        .build_svc();
+    assert_eq!(
+        handshake_status(&mut svc, "/socket.io/").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        handshake_status(&mut svc, "/legacy/socket.io/").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        handshake_status(&mut svc, "/other/").await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+pub async fn matcher() {
+    let (mut svc, _io) = SocketIo::builder()
+        .req_path(ReqPath::matcher(|path| {
+            path.starts_with("/tenant-") && path.ends_with("/socket.io/")
+        }))
+        .build_svc();
+    assert_eq!(
+        handshake_status(&mut svc, "/tenant-1/socket.io/").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        handshake_status(&mut svc, "/socket.io/").await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+pub async fn set_at_runtime() {
+    let (mut svc, io) = SocketIo::new_svc();
+    assert_eq!(
+        handshake_status(&mut svc, "/socket.io/").await,
+        StatusCode::OK
+    );
+
+    io.set_req_path("/v2/socket.io");
+    assert_eq!(
+        handshake_status(&mut svc, "/socket.io/").await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        handshake_status(&mut svc, "/v2/socket.io/").await,
+        StatusCode::OK
+    );
+}
@@ -0,0 +1,83 @@
+//! Tests for the rocket integration, with a real rocket server.
+mod utils;
+
+use futures_util::SinkExt;
+use rocket::{config::LogLevel, fairing::AdHoc, get, routes, Config};
+use socketioxide::{extract::*, integrations::rocket::RocketService, SocketIo};
+use tokio::{net::TcpStream, sync::oneshot};
+use tokio_tungstenite::tungstenite::Message;
+use utils::{poll_http, recv_ws_text, send_http_req};
+
+#[get("/hello")]
+fn hello() -> &'static str {
+    "Hello, World!"
+}
+
+/// Spawn a rocket server on a random port and return its address.
+async fn create_server() -> String {
+    let (svc, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.on("message", |s: SocketRef, Data::<String>(msg)| {
+            s.emit("message-back", &msg).ok();
+        });
+    });
+    let config = Config {
+        port: 0,
+        log_level: LogLevel::Off,
+        ..Config::debug_default()
+    };
+    let (tx, rx) = oneshot::channel();
+    let rocket = rocket::custom(config)
+        .attach(RocketService::new(svc))
+        .mount("/", routes![hello])
+        .attach(AdHoc::on_liftoff("addr", |rocket| {
+            let config = rocket.config();
+            tx.send(format!("{}:{}", config.address, config.port)).ok();
+            Box::pin(async {})
+        }));
+    tokio::spawn(rocket.launch());
+    rx.await.unwrap()
+}
+
+#[tokio::test]
+pub async fn polling() {
+    let addr = create_server().await;
+    let (status, open) =
+        send_http_req(&addr, "GET", "/socket.io/?EIO=4&transport=polling", "").await;
+    assert_eq!(status, 200);
+    assert!(open.starts_with(r#"0{"sid":""#), "{open}");
+    let sid = open[9..].split('"').next().unwrap();
+    let path = format!("/socket.io/?EIO=4&transport=polling&sid={sid}");
+
+    let (status, body) = send_http_req(&addr, "POST", &path, "40").await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let body = poll_http(&addr, &path).await;
+    assert!(body.starts_with(r#"40{"sid":""#), "{body}");
+
+    let (status, body) = send_http_req(&addr, "POST", &path, r#"42["message","foo"]"#).await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let body = poll_http(&addr, &path).await;
+    assert_eq!(body, r#"42["message-back","foo"]"#);
+
+    let (status, body) = send_http_req(&addr, "GET", "/hello", "").await;
+    assert_eq!((status, body.as_str()), (200, "Hello, World!"));
+    let (status, _) = send_http_req(&addr, "GET", "/other", "").await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+pub async fn websocket() {
+    let addr = create_server().await;
+    let stream = TcpStream::connect(&addr).await.unwrap();
+    let url = format!("ws://{addr}/socket.io/?EIO=4&transport=websocket");
+    let (mut ws, _) = tokio_tungstenite::client_async(url, stream).await.unwrap();
+    assert!(recv_ws_text(&mut ws).await.starts_with(r#"0{"sid":""#));
+
+    ws.send(Message::Text("40".into())).await.unwrap();
+    assert!(recv_ws_text(&mut ws).await.starts_with(r#"40{"sid":""#));
+
+    ws.send(Message::Text(r#"42["message","foo"]"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(recv_ws_text(&mut ws).await, r#"42["message-back","foo"]"#);
+}
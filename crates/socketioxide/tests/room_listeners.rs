@@ -0,0 +1,137 @@
+//! Tests for the room listeners registered on the [`SocketIo`] instance
+mod utils;
+
+use std::time::Duration;
+
+use engineioxide::Packet::*;
+use socketioxide::{adapter::RoomExpiry, extract::SocketRef, SocketIo};
+use tokio::sync::mpsc;
+
+#[derive(Debug, PartialEq)]
+enum RoomEvent {
+    Create(String),
+    Join(String),
+    Leave(String),
+    Delete(String),
+    Expire(String),
+}
+
+#[tokio::test]
+pub async fn room_listeners() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<RoomEvent>();
+
+    let tx1 = tx.clone();
+    io.on_create_room(move |_, room| tx1.send(RoomEvent::Create(room.to_string())).unwrap());
+    let tx1 = tx.clone();
+    io.on_join_room(move |_, _, room| tx1.send(RoomEvent::Join(room.to_string())).unwrap());
+    let tx1 = tx.clone();
+    io.on_leave_room(move |_, _, room| tx1.send(RoomEvent::Leave(room.to_string())).unwrap());
+    io.on_delete_room(move |ns, room| {
+        assert_eq!(ns, "/");
+        tx.send(RoomEvent::Delete(room.to_string())).unwrap()
+    });
+    io.ns("/", |s: SocketRef| {
+        s.join("room1");
+        s.on("leave", |s: SocketRef| s.leave("room1"));
+    });
+
+    let (stx1, mut srx1) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx1.recv().await);
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        RoomEvent::Create("room1".into())
+    );
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        RoomEvent::Join("room1".into())
+    );
+
+    // The room already exists
+    let (stx2, mut srx2) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx2.recv().await);
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        RoomEvent::Join("room1".into())
+    );
+
+    assert_ok!(stx1.send(Message("2[\"leave\"]".into())).await);
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        RoomEvent::Leave("room1".into())
+    );
+
+    // The last socket is disconnected
+    assert_ok!(stx2.send(Close).await);
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        RoomEvent::Leave("room1".into())
+    );
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        RoomEvent::Delete("room1".into())
+    );
+    assert_err!(rx.try_recv());
+}
+
+#[tokio::test]
+pub async fn room_listener_use_io() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<usize>();
+    let io1 = io.clone();
+    // The rooms are not locked while the listeners are called
+    io.on_join_room(move |ns, _, room| {
+        let sockets = io1.of(ns).unwrap().within(room.clone()).sockets();
+        tx.send(sockets.len()).unwrap();
+    });
+    io.ns("/", |s: SocketRef| s.join("room1"));
+
+    let (_stx1, mut srx1) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx1.recv().await);
+    assert_eq!(assert_some!(rx.recv().await), 1);
+    let (_stx2, mut srx2) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx2.recv().await);
+    assert_eq!(assert_some!(rx.recv().await), 2);
+}
+
+#[tokio::test]
+pub async fn room_expiry() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<RoomEvent>();
+    let tx1 = tx.clone();
+    io.on_delete_room(move |_, room| tx1.send(RoomEvent::Delete(room.to_string())).unwrap());
+    io.on_expire_room(move |_, room| tx.send(RoomEvent::Expire(room.to_string())).unwrap());
+    io.ns("/", |s: SocketRef| {
+        s.join(["call", "lobby"]);
+        s.to("call")
+            .set_expiry(RoomExpiry::WhenEmpty(Duration::from_millis(20)));
+        s.to("lobby")
+            .set_expiry(RoomExpiry::Ttl(Duration::from_millis(100)));
+        s.on("leave", |s: SocketRef| s.leave("call"));
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message("2[\"leave\"]".into())).await);
+
+    // The call is deleted once it stayed empty for its grace period
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        RoomEvent::Delete("call".into())
+    );
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        RoomEvent::Expire("call".into())
+    );
+
+    // The socket leaves the lobby once its ttl elapsed
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        RoomEvent::Delete("lobby".into())
+    );
+    assert_eq!(
+        assert_some!(rx.recv().await),
+        RoomEvent::Expire("lobby".into())
+    );
+    assert!(assert_ok!(io.rooms().await).is_empty());
+}
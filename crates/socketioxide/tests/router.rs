@@ -0,0 +1,115 @@
+//! Tests for the event router and its typed path parameters
+mod utils;
+
+use engineioxide::Packet::*;
+use serde::Deserialize;
+use socketioxide::{
+    extract::{Data, EventParams, SocketRef},
+    router::Router,
+    SocketIo,
+};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Deserialize)]
+struct RoomParams {
+    room: String,
+    action: String,
+}
+
+#[tokio::test]
+pub async fn route_params() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+
+    let (tx1, tx2) = (tx.clone(), tx.clone());
+    let router = Router::new()
+        .route(
+            "user/:id/update",
+            move |EventParams(id): EventParams<u64>, Data::<String>(name)| {
+                tx.send(format!("user {id} {name}")).unwrap()
+            },
+        )
+        .route(
+            "room/:room/:action",
+            move |EventParams(p): EventParams<RoomParams>| {
+                tx1.send(format!("room {} {}", p.room, p.action)).unwrap()
+            },
+        )
+        .route(
+            "file/:kind/*path",
+            move |EventParams((kind, path)): EventParams<(String, String)>| {
+                tx2.send(format!("file {kind} {path}")).unwrap()
+            },
+        );
+    io.ns("/", move |s: SocketRef| s.on_router(router.clone()));
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    assert_ok!(
+        stx.send(Message(r#"2["user/42/update","foo"]"#.into()))
+            .await
+    );
+    assert_eq!(assert_some!(rx.recv().await), "user 42 foo");
+
+    assert_ok!(stx.send(Message(r#"2["room/lobby/join"]"#.into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "room lobby join");
+
+    assert_ok!(stx.send(Message(r#"2["file/img/a/b.png"]"#.into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "file img a/b.png");
+
+    // The id param can't be parsed as an u64, the handler is not called
+    assert_ok!(
+        stx.send(Message(r#"2["user/foo/update","foo"]"#.into()))
+            .await
+    );
+    // Unknown route
+    assert_ok!(stx.send(Message(r#"2["user/42"]"#.into())).await);
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert_err!(rx.try_recv());
+}
+
+#[tokio::test]
+pub async fn route_precedence() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+
+    io.ns("/", move |s: SocketRef| {
+        let (tx1, tx2) = (tx.clone(), tx.clone());
+        s.on_router(Router::new().route(
+            "chat/:room",
+            move |EventParams(room): EventParams<String>| {
+                tx.send(format!("router {room}")).unwrap()
+            },
+        ));
+        s.on(
+            "chat/general",
+            move |EventParams(p): EventParams<Vec<String>>| {
+                tx1.send(format!("exact {}", p.len())).unwrap()
+            },
+        );
+        s.on_prefix("chat/", move || tx2.send("prefix".into()).unwrap());
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+
+    // The exact handler has precedence over the router and has no params
+    assert_ok!(stx.send(Message(r#"2["chat/general"]"#.into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "exact 0");
+
+    assert_ok!(stx.send(Message(r#"2["chat/random"]"#.into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "router random");
+
+    // The router has precedence over the prefix handlers
+    assert_ok!(stx.send(Message(r#"2["chat/random/nested"]"#.into())).await);
+    assert_eq!(assert_some!(rx.recv().await), "prefix");
+}
+
+#[test]
+#[should_panic = "invalid route"]
+pub fn invalid_route() {
+    Router::<socketioxide::adapter::LocalAdapter>::new()
+        .route("a/:id", || ())
+        .route("a/:name", || ());
+}
@@ -0,0 +1,111 @@
+//! Tests for the typed rpc layer over acks
+mod utils;
+
+use std::time::Duration;
+
+use engineioxide::Packet::*;
+use socketioxide::{
+    extract::SocketRef,
+    rpc::{RpcError, RpcMethod, RpcService},
+    AckError, SocketIo,
+};
+use tokio::sync::mpsc;
+
+struct Calculator;
+impl RpcService for Calculator {
+    const NAME: &'static str = "calculator";
+}
+
+struct Div;
+impl RpcMethod<Calculator> for Div {
+    const NAME: &'static str = "div";
+    type Request = (i32, i32);
+    type Response = i32;
+    type Error = String;
+}
+
+#[tokio::test]
+pub async fn rpc_serve() {
+    let (_svc, io) = SocketIo::new_svc();
+    let rpc = io.rpc::<Calculator>();
+    io.ns("/", move |s: SocketRef| {
+        rpc.serve::<Div, _, _>(&s, |_, (a, b)| async move {
+            a.checked_div(b)
+                .ok_or_else(|| "division by zero".to_string())
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    assert_ok!(stx.send(Message("21[\"calculator:div\",4,2]".into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("31[{\"ok\":2}]".into()));
+
+    assert_ok!(stx.send(Message("22[\"calculator:div\",4,0]".into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("32[{\"err\":\"division by zero\"}]".into()));
+}
+
+#[tokio::test]
+pub async fn rpc_serve_timeout() {
+    let (_svc, io) = SocketIo::new_svc();
+    let rpc = io.rpc::<Calculator>().timeout(Duration::from_millis(10));
+    io.ns("/", move |s: SocketRef| {
+        rpc.serve::<Div, _, _>(&s, |_, (a, b)| async move {
+            tokio::time::sleep(Duration::from_millis(a as u64)).await;
+            Ok(a / b)
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    // The handler is cancelled once the timeout is reached
+    assert_ok!(
+        stx.send(Message("21[\"calculator:div\",50,1]".into()))
+            .await
+    );
+    assert_ok!(stx.send(Message("22[\"calculator:div\",1,1]".into())).await);
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("32[{\"ok\":1}]".into()));
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert_err!(srx.try_recv());
+}
+
+#[tokio::test]
+pub async fn rpc_call() {
+    let (_svc, io) = SocketIo::new_svc();
+    let rpc = io.rpc::<Calculator>().timeout(Duration::from_millis(50));
+    let (tx, mut rx) = mpsc::channel(4);
+    io.ns("/", move |s: SocketRef| {
+        let rpc = rpc.clone();
+        tokio::spawn(async move {
+            for _ in 0..3 {
+                tx.send(rpc.call::<Div>(&s, &(4, 2)).await).await.unwrap();
+            }
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("21[\"calculator:div\",4,2]".into()));
+    assert_ok!(stx.send(Message("31[{\"ok\":2}]".into())).await);
+    assert_eq!(assert_ok!(assert_some!(rx.recv().await)), 2);
+
+    // The error of the method is mapped to a remote error
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message("32[{\"err\":\"nope\"}]".into())).await);
+    let err = assert_some!(rx.recv().await).unwrap_err();
+    assert!(
+        matches!(err, RpcError::Remote(ref e) if e == "nope"),
+        "{err:?}"
+    );
+
+    // No response
+    assert_some!(srx.recv().await);
+    let err = assert_some!(rx.recv().await).unwrap_err();
+    assert!(matches!(err, RpcError::Ack(AckError::Timeout)), "{err:?}");
+}
@@ -0,0 +1,85 @@
+//! Tests for the salvo integration, with a real salvo server:
+//! * Polling and websocket transports
+//! * Salvo request extensions available in the connect handler
+mod utils;
+
+use futures_util::SinkExt;
+use salvo_core::{conn::TcpListener, handler, Listener, Request, Router, Server};
+use socketioxide::{extract::*, integrations::salvo::SalvoHandler, SocketIo};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use utils::{poll_http, recv_ws_text, send_http_req};
+
+#[derive(Clone)]
+struct Token(&'static str);
+
+#[handler]
+async fn auth(req: &mut Request) {
+    req.extensions_mut().insert(Token("foo"));
+}
+
+/// Spawn a salvo server on a random port and return its address.
+async fn create_server() -> String {
+    let (svc, io) = SocketIo::new_svc();
+    io.ns(
+        "/",
+        |s: SocketRef, HttpExtension(token): HttpExtension<Token>| {
+            s.emit("token", token.0).ok();
+            s.on("message", |s: SocketRef, Data::<String>(msg)| {
+                s.emit("message-back", &msg).ok();
+            });
+        },
+    );
+    let acceptor = TcpListener::new("127.0.0.1:0").bind().await;
+    let addr = acceptor.local_addr().unwrap();
+    let router = Router::new().push(
+        Router::with_path("socket.io/{**}")
+            .hoop(auth)
+            .goal(SalvoHandler::new(svc)),
+    );
+    tokio::spawn(Server::new(acceptor).serve(router));
+    addr.to_string()
+}
+
+#[tokio::test]
+pub async fn polling() {
+    let addr = create_server().await;
+    let (status, open) =
+        send_http_req(&addr, "GET", "/socket.io/?EIO=4&transport=polling", "").await;
+    assert_eq!(status, 200);
+    assert!(open.starts_with(r#"0{"sid":""#), "{open}");
+    let sid = open[9..].split('"').next().unwrap();
+    let path = format!("/socket.io/?EIO=4&transport=polling&sid={sid}");
+
+    let (status, body) = send_http_req(&addr, "POST", &path, "40").await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let body = poll_http(&addr, &path).await;
+    assert!(body.starts_with(r#"40{"sid":""#), "{body}");
+    assert!(body.ends_with("\x1e42[\"token\",\"foo\"]"), "{body}");
+
+    let (status, body) = send_http_req(&addr, "POST", &path, r#"42["message","foo"]"#).await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let body = poll_http(&addr, &path).await;
+    assert_eq!(body, r#"42["message-back","foo"]"#);
+
+    let (status, _) = send_http_req(&addr, "GET", "/other", "").await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+pub async fn websocket() {
+    let addr = create_server().await;
+    let stream = TcpStream::connect(&addr).await.unwrap();
+    let url = format!("ws://{addr}/socket.io/?EIO=4&transport=websocket");
+    let (mut ws, _) = tokio_tungstenite::client_async(url, stream).await.unwrap();
+    assert!(recv_ws_text(&mut ws).await.starts_with(r#"0{"sid":""#));
+
+    ws.send(Message::Text("40".into())).await.unwrap();
+    assert!(recv_ws_text(&mut ws).await.starts_with(r#"40{"sid":""#));
+    assert_eq!(recv_ws_text(&mut ws).await, r#"42["token","foo"]"#);
+
+    ws.send(Message::Text(r#"42["message","foo"]"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(recv_ws_text(&mut ws).await, r#"42["message-back","foo"]"#);
+}
@@ -0,0 +1,179 @@
+//! Tests for the standalone server of the `server` feature.
+mod utils;
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::SinkExt;
+use http_body_util::{BodyExt, Empty};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use socketioxide::{extract::*, SocketIo, SocketIoBuilder};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+use utils::{poll_http, recv_ws_text, send_http_req};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Spawn a standalone server on a random port and return its address.
+async fn create_server() -> String {
+    let (_, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.on("message", |s: SocketRef, Data::<String>(msg)| {
+            s.emit("message-back", &msg).ok();
+        });
+    });
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { io.serve_listener(listener).await });
+    addr.to_string()
+}
+
+#[tokio::test]
+pub async fn polling() {
+    let addr = create_server().await;
+    let (status, open) =
+        send_http_req(&addr, "GET", "/socket.io/?EIO=4&transport=polling", "").await;
+    assert_eq!(status, 200);
+    assert!(open.starts_with(r#"0{"sid":""#), "{open}");
+    let sid = open[9..].split('"').next().unwrap();
+    let path = format!("/socket.io/?EIO=4&transport=polling&sid={sid}");
+
+    let (status, body) = send_http_req(&addr, "POST", &path, "40").await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let body = poll_http(&addr, &path).await;
+    assert!(body.starts_with(r#"40{"sid":""#), "{body}");
+
+    let (status, body) = send_http_req(&addr, "POST", &path, r#"42["message","foo"]"#).await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let body = poll_http(&addr, &path).await;
+    assert_eq!(body, r#"42["message-back","foo"]"#);
+
+    let (status, _) = send_http_req(&addr, "GET", "/other", "").await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+pub async fn websocket() {
+    let addr = create_server().await;
+    let stream = TcpStream::connect(&addr).await.unwrap();
+    let url = format!("ws://{addr}/socket.io/?EIO=4&transport=websocket");
+    let (mut ws, _) = tokio_tungstenite::client_async(url, stream).await.unwrap();
+    assert!(recv_ws_text(&mut ws).await.starts_with(r#"0{"sid":""#));
+
+    ws.send(Message::Text("40".into())).await.unwrap();
+    assert!(recv_ws_text(&mut ws).await.starts_with(r#"40{"sid":""#));
+
+    ws.send(Message::Text(r#"42["message","foo"]"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(recv_ws_text(&mut ws).await, r#"42["message-back","foo"]"#);
+}
+
+#[tokio::test]
+pub async fn serve_bind_error() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (_, io) = SocketIo::new_svc();
+    assert!(io.serve(addr).await.is_err());
+}
+
+#[tokio::test]
+pub async fn h2c_polling() {
+    let addr = create_server().await;
+    let stream = TcpStream::connect(&addr).await.unwrap();
+    let (mut sender, conn) =
+        hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream))
+            .await
+            .unwrap();
+    tokio::spawn(conn);
+
+    let req = http::Request::get(format!("http://{addr}/socket.io/?EIO=4&transport=polling"))
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    let res = sender.send_request(req).await.unwrap();
+    assert_eq!(res.version(), http::Version::HTTP_2);
+    assert_eq!(res.status(), 200);
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    assert!(body.starts_with(br#"0{"sid":""#));
+}
+
+/// Spawn a standalone server emitting the remote address of the sockets on connection.
+async fn create_remote_addr_server(builder: SocketIoBuilder) -> String {
+    let (_, io) = builder.build_svc();
+    io.ns("/", |s: SocketRef| {
+        let addr = s.remote_addr().map(|ip| ip.to_string());
+        s.emit("addr", &addr).ok();
+    });
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { io.serve_listener(listener).await });
+    addr.to_string()
+}
+
+/// Connect a websocket client on the given stream and return the address emitted on connection.
+async fn recv_remote_addr(stream: TcpStream, req: http::Request<()>) -> String {
+    let (mut ws, _) = tokio_tungstenite::client_async(req, stream).await.unwrap();
+    assert!(recv_ws_text(&mut ws).await.starts_with(r#"0{"sid":""#));
+    ws.send(Message::Text("40".into())).await.unwrap();
+    assert!(recv_ws_text(&mut ws).await.starts_with(r#"40{"sid":""#));
+    recv_ws_text(&mut ws).await
+}
+
+fn ws_req(addr: &str) -> http::Request<()> {
+    format!("ws://{addr}/socket.io/?EIO=4&transport=websocket")
+        .into_client_request()
+        .unwrap()
+}
+
+#[tokio::test]
+pub async fn remote_addr() {
+    let addr = create_remote_addr_server(SocketIo::builder()).await;
+    let stream = TcpStream::connect(&addr).await.unwrap();
+    let mut req = ws_req(&addr);
+    // The header is ignored without trusted proxies
+    req.headers_mut()
+        .insert("X-Forwarded-For", "1.2.3.4".parse().unwrap());
+    let msg = recv_remote_addr(stream, req).await;
+    assert_eq!(msg, r#"42["addr","127.0.0.1"]"#);
+}
+
+#[tokio::test]
+pub async fn remote_addr_forwarded() {
+    let builder = SocketIo::builder().trusted_proxies(["127.0.0.0/8".parse().unwrap()]);
+    let addr = create_remote_addr_server(builder).await;
+    let stream = TcpStream::connect(&addr).await.unwrap();
+    let mut req = ws_req(&addr);
+    req.headers_mut()
+        .insert("X-Forwarded-For", "1.2.3.4, 127.0.0.2".parse().unwrap());
+    let msg = recv_remote_addr(stream, req).await;
+    assert_eq!(msg, r#"42["addr","1.2.3.4"]"#);
+}
+
+#[tokio::test]
+pub async fn proxy_protocol() {
+    let addr = create_remote_addr_server(SocketIo::builder().proxy_protocol(true)).await;
+
+    let mut stream = TcpStream::connect(&addr).await.unwrap();
+    stream
+        .write_all(b"PROXY TCP6 2001:db8::1 ::1 5678 3000\r\n")
+        .await
+        .unwrap();
+    let msg = recv_remote_addr(stream, ws_req(&addr)).await;
+    assert_eq!(msg, r#"42["addr","2001:db8::1"]"#);
+
+    // Connections without a PROXY header are closed
+    let mut stream = TcpStream::connect(&addr).await.unwrap();
+    stream
+        .write_all(b"GET /socket.io/ HTTP/1.1\r\n\r\n")
+        .await
+        .unwrap();
+    let mut res = Vec::new();
+    tokio::time::timeout(TIMEOUT, stream.read_to_end(&mut res))
+        .await
+        .expect("timeout")
+        .ok();
+    assert!(res.is_empty());
+}
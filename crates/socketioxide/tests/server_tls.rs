@@ -0,0 +1,165 @@
+//! Tests for the TLS support of the standalone server:
+//! * Polling and websocket transports over TLS
+//! * Polling over http/2 negotiated with ALPN
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt};
+use http_body_util::{BodyExt, Empty};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use socketioxide::{extract::*, SocketIo, TlsConfig};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{crypto::ring, pki_types::ServerName, ClientConfig, RootCertStore},
+    TlsConnector,
+};
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Spawn a standalone TLS server on a random port with a self-signed certificate.
+/// Return its address and a connector trusting the certificate.
+async fn create_server(alpn: &[&[u8]]) -> (String, TlsConnector) {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+    let tls = TlsConfig::from_pem(
+        cert.cert.pem().as_bytes(),
+        cert.key_pair.serialize_pem().as_bytes(),
+    )
+    .unwrap();
+
+    let (_, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.on("message", |s: SocketRef, Data::<String>(msg)| {
+            s.emit("message-back", &msg).ok();
+        });
+    });
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { io.serve_listener_tls(listener, tls).await });
+
+    let mut roots = RootCertStore::empty();
+    roots.add(cert.cert.der().clone()).unwrap();
+    let mut config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+    (addr.to_string(), TlsConnector::from(Arc::new(config)))
+}
+
+async fn connect(addr: &str, connector: &TlsConnector) -> TlsStream<TcpStream> {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let domain = ServerName::try_from("localhost").unwrap();
+    connector.connect(domain, stream).await.unwrap()
+}
+
+/// Send a raw http/1.1 request over TLS and return the status and the body of the response.
+async fn send_req(
+    addr: &str,
+    connector: &TlsConnector,
+    method: &str,
+    path: &str,
+    body: &str,
+) -> (u16, String) {
+    let mut stream = connect(addr, connector).await;
+    let req = format!(
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(req.as_bytes()).await.unwrap();
+    let mut res = Vec::new();
+    // The server may close the connection without a TLS close_notify alert
+    tokio::time::timeout(TIMEOUT, stream.read_to_end(&mut res))
+        .await
+        .expect("timeout")
+        .ok();
+    let res = String::from_utf8(res).unwrap();
+    let status = res[9..12].parse().unwrap();
+    let (_, body) = res.split_once("\r\n\r\n").unwrap();
+    (status, body.to_string())
+}
+
+/// Receive the next text message, skipping the ping packets.
+async fn recv(ws: &mut WebSocketStream<TlsStream<TcpStream>>) -> String {
+    loop {
+        match tokio::time::timeout(TIMEOUT, ws.next()).await {
+            Ok(Some(Ok(Message::Text(msg)))) if msg.as_str() == "2" => continue,
+            Ok(Some(Ok(Message::Text(msg)))) => return msg.to_string(),
+            msg => panic!("unexpected message: {msg:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn polling() {
+    let (addr, connector) = create_server(&[]).await;
+    let (status, open) = send_req(
+        &addr,
+        &connector,
+        "GET",
+        "/socket.io/?EIO=4&transport=polling",
+        "",
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(open.starts_with(r#"0{"sid":""#), "{open}");
+    let sid = open[9..].split('"').next().unwrap();
+    let path = format!("/socket.io/?EIO=4&transport=polling&sid={sid}");
+
+    let (status, body) = send_req(&addr, &connector, "POST", &path, "40").await;
+    assert_eq!((status, body.as_str()), (200, "ok"));
+    let (_, body) = send_req(&addr, &connector, "GET", &path, "").await;
+    // A ping packet may be sent before the connect packet
+    assert!(
+        body.trim_start_matches("2\x1e")
+            .starts_with(r#"40{"sid":""#),
+        "{body}"
+    );
+}
+
+#[tokio::test]
+pub async fn websocket() {
+    let (addr, connector) = create_server(&[b"http/1.1"]).await;
+    let stream = connect(&addr, &connector).await;
+    let url = "wss://localhost/socket.io/?EIO=4&transport=websocket";
+    let (mut ws, _) = tokio_tungstenite::client_async(url, stream).await.unwrap();
+    assert!(recv(&mut ws).await.starts_with(r#"0{"sid":""#));
+
+    ws.send(Message::Text("40".into())).await.unwrap();
+    assert!(recv(&mut ws).await.starts_with(r#"40{"sid":""#));
+
+    ws.send(Message::Text(r#"42["message","foo"]"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(recv(&mut ws).await, r#"42["message-back","foo"]"#);
+}
+
+#[tokio::test]
+pub async fn alpn_h2() {
+    let (addr, connector) = create_server(&[b"h2", b"http/1.1"]).await;
+    let stream = connect(&addr, &connector).await;
+    assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+
+    let (mut sender, conn) =
+        hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream))
+            .await
+            .unwrap();
+    tokio::spawn(conn);
+    let req = http::Request::get("https://localhost/socket.io/?EIO=4&transport=polling")
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    let res = sender.send_request(req).await.unwrap();
+    assert_eq!(res.version(), http::Version::HTTP_2);
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    assert!(body.starts_with(br#"0{"sid":""#));
+}
+
+#[test]
+pub fn invalid_pem() {
+    assert!(TlsConfig::from_pem(b"foo", b"bar").is_err());
+}
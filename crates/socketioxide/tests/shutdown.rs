@@ -0,0 +1,102 @@
+//! Tests for the graceful shutdown of the server
+mod fixture;
+mod utils;
+
+use std::time::{Duration, Instant};
+
+use engineioxide::Packet::*;
+use futures_util::StreamExt;
+use socketioxide::SocketIo;
+use tokio_tungstenite::tungstenite;
+
+use crate::fixture::{create_polling_connection, create_ws_connection, send_req};
+use tower_service::Service;
+
+#[tokio::test]
+pub async fn shutdown_emit_event() {
+    let (_svc, io) = SocketIo::builder()
+        .shutdown_event("shutting_down")
+        .build_svc();
+    io.ns("/", || {});
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    let start = Instant::now();
+    let shutdown = tokio::spawn({
+        let io = io.clone();
+        async move { io.shutdown(Duration::from_secs(5)).await }
+    });
+
+    let msg = assert_some!(srx.recv().await);
+    assert_eq!(msg, Message("2[\"shutting_down\",5000]".into()));
+
+    // The client disconnects by itself before the end of the grace period
+    assert_ok!(stx.send(Close).await);
+    let res = tokio::time::timeout(Duration::from_secs(1), shutdown).await;
+    assert_ok!(assert_ok!(res));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[tokio::test]
+pub async fn shutdown_grace_period() {
+    let (svc, io) = SocketIo::new_svc();
+    io.ns("/", || {});
+
+    let mut stream = create_ws_connection(&svc).await;
+    stream.next().await; // engine.io open packet
+    stream.next().await; // socket.io open packet
+
+    let start = Instant::now();
+    let shutdown = tokio::spawn({
+        let io = io.clone();
+        async move { io.shutdown(Duration::from_millis(50)).await }
+    });
+    // The client ignores the shutdown and is closed at the end of the grace period
+    let mut last = None;
+    while let Some(msg) = stream.next().await {
+        last = Some(msg);
+    }
+    assert!(matches!(
+        assert_some!(last),
+        Ok(tungstenite::Message::Close(_))
+    ));
+    let res = tokio::time::timeout(Duration::from_secs(1), shutdown).await;
+    assert_ok!(assert_ok!(res));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+pub async fn shutdown_reject_new_sessions() {
+    let (svc, io) = SocketIo::new_svc();
+    io.ns("/", || {});
+
+    io.shutdown(Duration::from_millis(10)).await;
+
+    // New handshakes are rejected before any session is created
+    let req = http::Request::builder()
+        .uri("http://127.0.0.1/socket.io/?EIO=4&transport=polling")
+        .body(http_body_util::Empty::<bytes::Bytes>::new())
+        .unwrap();
+    let res = assert_ok!(svc.clone().call(req).await);
+    assert_eq!(res.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+pub async fn shutdown_close_sessions() {
+    let (svc, io) = SocketIo::new_svc();
+    io.ns("/", || {});
+
+    let sid = create_polling_connection(&svc).await;
+    io.shutdown(Duration::from_millis(10)).await;
+
+    let res = send_req(
+        &svc,
+        format!("transport=polling&sid={sid}"),
+        http::Method::GET,
+        None,
+    )
+    .await;
+    // The engine.io session is closed at the end of the grace period
+    assert_eq!(res, "{\"code\":\"1\",\"message\":\"Session ID unknown\"}");
+}
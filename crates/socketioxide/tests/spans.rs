@@ -0,0 +1,53 @@
+//! Tests for the tracing spans recorded with the `tracing` feature
+mod utils;
+
+use std::sync::{Arc, Mutex};
+
+use engineioxide::Packet::*;
+use socketioxide::{extract::SocketRef, SocketIo};
+use tracing::{span, Subscriber};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+/// A span name with the name of its parent
+type SpanEntry = (&'static str, Option<&'static str>);
+
+/// Record the name of each new span with the name of its parent
+#[derive(Clone, Default)]
+struct SpanRecorder(Arc<Mutex<Vec<SpanEntry>>>);
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanRecorder {
+    fn on_new_span(&self, _: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        let parent = span.parent().map(|p| p.name());
+        self.0.lock().unwrap().push((span.name(), parent));
+    }
+}
+
+#[tokio::test]
+pub async fn event_spans() {
+    let recorder = SpanRecorder::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+    let (_svc, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.on("test", |s: SocketRef| async move {
+            s.emit("test", "hello").unwrap();
+        });
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+    assert_ok!(stx.send(Message(r#"2["test"]"#.into())).await);
+    assert_eq!(
+        assert_some!(srx.recv().await),
+        Message(r#"2["test","hello"]"#.into())
+    );
+
+    let spans = recorder.0.lock().unwrap().clone();
+    let session = Some("engineio.session");
+    assert!(spans.contains(&("engineio.session", None)));
+    assert!(spans.contains(&("socketio.connect", session)));
+    assert!(spans.contains(&("socketio.event", session)));
+    assert!(spans.contains(&("socketio.emit", session)));
+}
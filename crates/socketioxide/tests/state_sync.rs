@@ -0,0 +1,91 @@
+//! Tests for the synchronization of room states with patches
+mod utils;
+
+use engineioxide::Packet::*;
+use serde::Serialize;
+use serde_json::json;
+use socketioxide::{extract::SocketRef, state_sync::StateSync, SocketIo};
+use std::sync::Arc;
+use tokio::sync::mpsc::Receiver;
+
+#[derive(Serialize)]
+struct Scores {
+    red: u32,
+    blue: u32,
+}
+
+/// Receive the next state update of the "scores" event.
+async fn recv(srx: &mut Receiver<engineioxide::Packet>) -> serde_json::Value {
+    let Message(msg) = assert_some!(srx.recv().await) else {
+        panic!("expected a message");
+    };
+    let (event, update): (String, serde_json::Value) = serde_json::from_str(&msg[1..]).unwrap();
+    assert_eq!(event, "scores");
+    update
+}
+
+#[tokio::test]
+pub async fn state_sync_patches() {
+    let (_svc, io) = SocketIo::new_svc();
+    let sync = Arc::new(StateSync::<Scores>::new(io.clone(), "scores").snapshot_interval(3));
+    let sync_clone = sync.clone();
+    io.ns("/", move |s: SocketRef| {
+        sync_clone.join(&s, "match").unwrap();
+    });
+
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await);
+
+    assert_ok!(sync.update("match", &Scores { red: 0, blue: 0 }).await);
+    let update = recv(&mut srx).await;
+    assert_eq!(
+        update,
+        json!({ "type": "snapshot", "version": 1, "state": { "red": 0, "blue": 0 } })
+    );
+
+    // Unchanged states are not emitted
+    assert_ok!(sync.update("match", &Scores { red: 0, blue: 0 }).await);
+    assert_ok!(sync.update("match", &Scores { red: 1, blue: 0 }).await);
+    let update = recv(&mut srx).await;
+    assert_eq!(
+        update,
+        json!({ "type": "patch", "version": 2, "patch": { "red": 1 } })
+    );
+
+    // A snapshot is emitted every 3 updates
+    assert_ok!(sync.update("match", &Scores { red: 1, blue: 2 }).await);
+    let update = recv(&mut srx).await;
+    assert_eq!(
+        update,
+        json!({ "type": "snapshot", "version": 3, "state": { "red": 1, "blue": 2 } })
+    );
+}
+
+#[tokio::test]
+pub async fn state_sync_join_snapshot() {
+    let (_svc, io) = SocketIo::new_svc();
+    let sync = Arc::new(StateSync::<Scores>::new(io.clone(), "scores"));
+    let sync_clone = sync.clone();
+    io.ns("/", move |s: SocketRef| {
+        sync_clone.join(&s, "match").unwrap();
+    });
+    assert_ok!(sync.update("match", &Scores { red: 4, blue: 2 }).await);
+
+    // A socket joining the room receives the current snapshot
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    let update = loop {
+        // Skip the connect packet and any other packet until the snapshot arrives
+        let Message(msg) = assert_some!(srx.recv().await) else {
+            continue;
+        };
+        let data = msg.as_str().strip_prefix('2').unwrap_or_default();
+        if let Ok(("scores", update)) = serde_json::from_str::<(&str, serde_json::Value)>(data) {
+            break update;
+        }
+    };
+    // The fields are compared as json values because their order depends on the serde_json features
+    assert_eq!(
+        update,
+        json!({ "type": "snapshot", "version": 1, "state": { "red": 4, "blue": 2 } })
+    );
+}
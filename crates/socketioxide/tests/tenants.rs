@@ -0,0 +1,133 @@
+//! Tests the routing of the connections of a single engine.io endpoint to several socket.io servers.
+use std::time::Duration;
+
+use bytes::Bytes;
+use engineioxide::service::NotFoundService;
+use http::{Method, Request};
+use http_body_util::{BodyExt, Full};
+use socketioxide::{
+    extract::SocketRef,
+    tenant::{TenantService, Tenants},
+    SocketIo,
+};
+use tokio::sync::mpsc;
+use tower_service::Service;
+
+async fn send(
+    svc: &TenantService<NotFoundService>,
+    method: Method,
+    sid: Option<&str>,
+    body: &'static str,
+) -> String {
+    let sid = sid.map(|sid| format!("&sid={sid}")).unwrap_or_default();
+    let req = Request::builder()
+        .method(method)
+        .uri(format!(
+            "http://127.0.0.1/socket.io/?EIO=4&transport=polling{sid}"
+        ))
+        .body(Full::new(Bytes::from_static(body.as_bytes())))
+        .unwrap();
+    let res = svc.clone().call(req).await.unwrap();
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+/// Open a polling session and send a connect packet to the given namespace
+/// and return the session id with the response to the connect packet.
+async fn connect(svc: &TenantService<NotFoundService>, connect: &'static str) -> (String, String) {
+    let open = send(svc, Method::GET, None, "").await;
+    let open: serde_json::Value = serde_json::from_str(&open[1..]).unwrap();
+    let sid = open["sid"].as_str().unwrap().to_string();
+    send(svc, Method::POST, Some(&sid), connect).await;
+    let res = send(svc, Method::GET, Some(&sid), "").await;
+    (sid, res)
+}
+
+fn tenant(prefix: &'static str, tx: mpsc::Sender<&'static str>) -> SocketIo {
+    let (_, io) = SocketIo::new_svc();
+    io.ns(prefix, move |_: SocketRef| tx.try_send(prefix).unwrap());
+    io
+}
+
+async fn recv(rx: &mut mpsc::Receiver<&'static str>) -> &'static str {
+    tokio::time::timeout(Duration::from_millis(200), rx.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+pub async fn route_by_prefix() {
+    let (tx, mut rx) = mpsc::channel(4);
+    let io_a = tenant("/tenant-a", tx.clone());
+    let io_b = tenant("/tenant-b/chat", tx);
+    let svc = Tenants::new()
+        .tenant("/tenant-a", io_a.clone())
+        .tenant("/tenant-b", io_b.clone())
+        .build_svc();
+
+    let (_, res) = connect(&svc, "40/tenant-b/chat,").await;
+    assert!(res.starts_with("40/tenant-b/chat,"), "{res}");
+    assert_eq!(recv(&mut rx).await, "/tenant-b/chat");
+
+    let (_, res) = connect(&svc, "40/tenant-a,").await;
+    assert!(res.starts_with("40/tenant-a,"), "{res}");
+    assert_eq!(recv(&mut rx).await, "/tenant-a");
+
+    assert_eq!(io_a.of("/tenant-a").unwrap().sockets().len(), 1);
+    assert_eq!(io_b.of("/tenant-b/chat").unwrap().sockets().len(), 1);
+}
+
+#[tokio::test]
+pub async fn session_bound_to_one_tenant() {
+    let (tx, mut rx) = mpsc::channel(4);
+    let io_a = tenant("/tenant-a", tx.clone());
+    let io_b = tenant("/tenant-b", tx);
+    let svc = Tenants::new()
+        .tenant("/tenant-a", io_a)
+        .tenant("/tenant-b", io_b)
+        .build_svc();
+
+    let (sid, _) = connect(&svc, "40/tenant-a,").await;
+    assert_eq!(recv(&mut rx).await, "/tenant-a");
+
+    // The namespaces of the other tenants are unknown to this session
+    send(&svc, Method::POST, Some(&sid), "40/tenant-b,").await;
+    let res = send(&svc, Method::GET, Some(&sid), "").await;
+    assert!(res.starts_with("44/tenant-b,"), "{res}");
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+pub async fn prefix_matches_path_segments() {
+    let (tx, mut rx) = mpsc::channel(4);
+    let io_a = tenant("/tenant-ab", tx.clone());
+    let fallback = tenant("/tenant-ab", tx);
+    let svc = Tenants::new()
+        .tenant("/tenant-a", io_a.clone())
+        .fallback(fallback.clone())
+        .build_svc();
+
+    let (_, res) = connect(&svc, "40/tenant-ab,").await;
+    assert!(res.starts_with("40/tenant-ab,"), "{res}");
+    recv(&mut rx).await;
+    assert_eq!(io_a.of("/tenant-ab").unwrap().sockets().len(), 0);
+    assert_eq!(fallback.of("/tenant-ab").unwrap().sockets().len(), 1);
+}
+
+#[tokio::test]
+pub async fn unknown_prefix_without_fallback() {
+    let (tx, mut rx) = mpsc::channel(4);
+    let io_a = tenant("/tenant-a", tx);
+    let svc = Tenants::new().tenant("/tenant-a", io_a).build_svc();
+
+    let (_, res) = connect(&svc, "40/unknown,").await;
+    assert!(!res.contains("40/unknown"), "{res}");
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+#[should_panic = "at least one tenant or a fallback should be added"]
+pub async fn no_tenant() {
+    Tenants::<socketioxide::adapter::LocalAdapter>::new().build_svc();
+}
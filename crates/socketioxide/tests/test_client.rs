@@ -0,0 +1,155 @@
+//! Tests for the in-process test client
+mod utils;
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use serde_json::json;
+use socketioxide::{
+    extract::{AckSender, Data, SocketRef},
+    handler::ConnectHandler,
+    test::{TestClient, TestClientError},
+    SocketIo,
+};
+
+#[tokio::test]
+pub async fn test_client_connect() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    io.ns("/chat", move |s: SocketRef| tx.try_send(s.id).unwrap());
+
+    let client = assert_ok!(TestClient::connect(&io, "/chat").await);
+    assert_eq!(client.ns(), "/chat");
+    assert_eq!(assert_some!(rx.recv().await), client.id());
+
+    let err = TestClient::connect(&io, "/unknown").await.unwrap_err();
+    assert!(matches!(err, TestClientError::ConnectRefused(_)));
+}
+
+#[tokio::test]
+pub async fn test_client_connect_auth() {
+    let (_svc, io) = SocketIo::new_svc();
+    #[derive(Debug, serde::Deserialize)]
+    struct Auth {
+        token: String,
+    }
+    let middleware = |Data(auth): Data<Auth>| async move {
+        if auth.token == "secret" {
+            Ok(())
+        } else {
+            Err("invalid token")
+        }
+    };
+    io.ns("/", { || {} }.with(middleware));
+
+    assert_ok!(TestClient::connect_with_auth(&io, "/", &json!({ "token": "secret" })).await);
+    let err = TestClient::connect_with_auth(&io, "/", &json!({ "token": "foo" }))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, TestClientError::ConnectRefused(msg) if msg == "invalid token"));
+}
+
+#[tokio::test]
+pub async fn test_client_emit_with_ack() {
+    let (_svc, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.on(
+            "add",
+            |s: SocketRef, Data((a, b)): Data<(u32, u32)>, ack: AckSender| {
+                s.emit("added", &(a + b)).unwrap();
+                ack.send(&(a + b)).unwrap();
+            },
+        );
+    });
+
+    let mut client = assert_ok!(TestClient::connect(&io, "/").await);
+    let sum: u32 = assert_ok!(client.emit_with_ack("add", &(1, 2)).await);
+    assert_eq!(sum, 3);
+
+    // The event emitted before the ack is kept
+    let event = assert_ok!(client.recv_event().await);
+    assert_eq!(event.name(), "added");
+    assert_eq!(assert_ok!(event.data::<u32>()), 3);
+    assert!(!event.needs_ack());
+    client.expect_no_packet(Duration::from_millis(20)).await;
+}
+
+#[tokio::test]
+pub async fn test_client_binary() {
+    let (_svc, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef| {
+        s.on("bin", |ack: AckSender, Data::<Bytes>(data)| {
+            ack.send(&data).unwrap();
+        });
+    });
+
+    let mut client = assert_ok!(TestClient::connect(&io, "/").await);
+    let data = Bytes::from_static(&[1, 2, 3]);
+    let res: Bytes = assert_ok!(client.emit_with_ack("bin", &data).await);
+    assert_eq!(res, data);
+}
+
+#[tokio::test]
+pub async fn test_client_ack_server_event() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    io.ns("/", move |s: SocketRef| {
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let ack = s.emit_with_ack::<_, String>("ping", &1).unwrap().await;
+            tx.send(ack.unwrap()).await.unwrap();
+        });
+    });
+
+    let mut client = assert_ok!(TestClient::connect(&io, "/").await);
+    let event = assert_ok!(client.recv_event().await);
+    assert_eq!(event.name(), "ping");
+    assert!(event.needs_ack());
+    assert_ok!(event.ack(&"pong").await);
+    assert_eq!(assert_some!(rx.recv().await), "pong");
+}
+
+#[tokio::test]
+pub async fn test_client_disconnect() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    io.ns("/", move |s: SocketRef| {
+        let tx = tx.clone();
+        s.on_disconnect(move || tx.try_send(()).unwrap());
+        s.on("leave", |s: SocketRef| s.disconnect().unwrap());
+    });
+
+    let client = assert_ok!(TestClient::connect(&io, "/").await);
+    client.disconnect().await;
+    assert_some!(rx.recv().await);
+
+    let mut client = assert_ok!(TestClient::connect(&io, "/").await);
+    assert_ok!(client.emit("leave", &()).await);
+    let err = client.recv_event().await.unwrap_err();
+    assert!(matches!(err, TestClientError::Disconnected));
+    assert_some!(rx.recv().await);
+
+    // Dropping the client closes its connection
+    let client = assert_ok!(TestClient::connect(&io, "/").await);
+    drop(client);
+    assert_some!(rx.recv().await);
+}
+
+#[tokio::test]
+pub async fn test_client_timeout() {
+    let (_svc, io) = SocketIo::builder()
+        .ack_timeout(Duration::from_millis(20))
+        .build_svc();
+    io.ns("/", |s: SocketRef| s.on("noop", || {}));
+
+    let mut client = assert_ok!(TestClient::connect(&io, "/").await);
+    assert_eq!(client.timeout(), Duration::from_millis(20));
+    let err = client
+        .emit_with_ack::<_, ()>("noop", &())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, TestClientError::Timeout));
+
+    let err = client.recv().await.unwrap_err();
+    assert!(matches!(err, TestClientError::Timeout));
+}
@@ -0,0 +1,91 @@
+//! Tests for the user registry
+mod utils;
+
+use std::time::Duration;
+
+use engineioxide::Packet::*;
+use socketioxide::{
+    extract::{Data, SocketRef},
+    SocketIo,
+};
+use tokio::sync::mpsc;
+
+#[tokio::test]
+pub async fn to_user() {
+    let (_svc, io) = SocketIo::new_svc();
+    io.ns("/", |s: SocketRef, Data(user): Data<String>| {
+        s.set_user(user);
+    });
+
+    let (_stx1, mut srx1) = io.new_dummy_sock("/", "alice").await;
+    let (_stx2, mut srx2) = io.new_dummy_sock("/", "alice").await;
+    let (_stx3, mut srx3) = io.new_dummy_sock("/", "bob").await;
+    for srx in [&mut srx1, &mut srx2, &mut srx3] {
+        assert_some!(srx.recv().await); // NS connect packet
+    }
+
+    assert_ok!(io.to_user("alice").emit("test", "hello").await);
+    for srx in [&mut srx1, &mut srx2] {
+        let msg = assert_some!(srx.recv().await);
+        assert_eq!(msg, Message(r#"2["test","hello"]"#.into()));
+    }
+    tokio::time::timeout(Duration::from_millis(10), srx3.recv())
+        .await
+        .unwrap_err();
+
+    let users = io.users();
+    assert_eq!(assert_ok!(users.sockets("alice").await).len(), 2);
+    assert!(assert_ok!(users.is_online("bob").await));
+    assert!(!assert_ok!(users.is_online("carol").await));
+    let mut online = assert_ok!(users.online_users().await);
+    online.sort();
+    assert_eq!(online, ["alice", "bob"]);
+}
+
+#[tokio::test]
+pub async fn set_user() {
+    let (_svc, io) = SocketIo::new_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Option<String>>();
+    io.ns("/", move |s: SocketRef| {
+        s.set_user(1);
+        s.join("room1");
+        tx.send(s.user_id()).unwrap();
+        // Binding the socket to another user unbinds it from the previous one
+        s.set_user(2);
+        tx.send(s.user_id()).unwrap();
+        assert_eq!(s.rooms().len(), 2);
+        s.clear_user();
+        tx.send(s.user_id()).unwrap();
+        assert_eq!(s.rooms(), ["room1"]);
+        s.set_user(3);
+    });
+
+    let (stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+    assert_eq!(assert_some!(rx.recv().await).as_deref(), Some("1"));
+    assert_eq!(assert_some!(rx.recv().await).as_deref(), Some("2"));
+    assert_eq!(assert_some!(rx.recv().await), None);
+    assert!(assert_ok!(io.users().is_online(3).await));
+
+    // The socket is removed from the registry when it disconnects
+    assert_ok!(stx.send(Message("1".into())).await);
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(!assert_ok!(io.users().is_online(3).await));
+    assert!(assert_ok!(io.users().online_users().await).is_empty());
+}
+
+#[tokio::test]
+pub async fn user_room_prefix() {
+    let (_svc, io) = SocketIo::builder().user_room_prefix("u/").build_svc();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<String>>();
+    io.ns("/", move |s: SocketRef| {
+        s.set_user("alice");
+        let rooms = s.rooms().into_iter().map(String::from).collect();
+        tx.send(rooms).unwrap();
+    });
+
+    let (_stx, mut srx) = io.new_dummy_sock("/", ()).await;
+    assert_some!(srx.recv().await); // NS connect packet
+    assert_eq!(assert_some!(rx.recv().await), ["u/alice"]);
+    assert!(io.users_of("/other").is_none());
+}
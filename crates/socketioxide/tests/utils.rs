@@ -1,5 +1,14 @@
 #![allow(dead_code)]
 
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
 #[macro_export]
 macro_rules! assert_ok {
     ($e:expr) => {
@@ -59,3 +68,55 @@ macro_rules! assert_some {
         }
     }};
 }
+
+/// The timeout of the requests sent to a real server.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Send a raw http/1.1 request to the server listening on `addr`
+/// and return the status and the body of the response.
+pub async fn send_http_req(addr: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let req = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(req.as_bytes()).await.unwrap();
+    let mut res = String::new();
+    tokio::time::timeout(HTTP_TIMEOUT, stream.read_to_string(&mut res))
+        .await
+        .expect("timeout")
+        .unwrap();
+    let status = res[9..12].parse().unwrap();
+    let (_, body) = res.split_once("\r\n\r\n").unwrap();
+    (status, body.to_string())
+}
+
+/// Send polling requests to the server listening on `addr` until it sends other packets
+/// than pings and return them, without the pings.
+///
+/// The first ping is sent as soon as the session is opened, so it may be received
+/// alone or with any other packet.
+pub async fn poll_http(addr: &str, path: &str) -> String {
+    loop {
+        let (status, body) = send_http_req(addr, "GET", path, "").await;
+        assert_eq!(status, 200, "{body}");
+        let packets: Vec<&str> = body.split('\x1e').filter(|p| *p != "2").collect();
+        if !packets.is_empty() {
+            return packets.join("\x1e");
+        }
+    }
+}
+
+/// Receive the next text message of a websocket, skipping the ping packets.
+pub async fn recv_ws_text<S>(ws: &mut WebSocketStream<S>) -> String
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    loop {
+        match tokio::time::timeout(HTTP_TIMEOUT, ws.next()).await {
+            Ok(Some(Ok(Message::Text(msg)))) if msg.as_str() == "2" => continue,
+            Ok(Some(Ok(Message::Text(msg)))) => return msg.to_string(),
+            msg => panic!("unexpected message: {msg:?}"),
+        }
+    }
+}
@@ -74,6 +74,28 @@ describe("Engine.IO protocol", () => {
         assert.deepStrictEqual(response2.status, 400);
       });
 
+      it("successfully opens a session with the jsonp transport", async () => {
+        const response = await fetch(
+          `${POLLING_URL}/engine.io/?EIO=3&transport=polling&j=1`,
+        );
+
+        assert.deepStrictEqual(response.status, 200);
+        assert.deepStrictEqual(
+          response.headers.get("content-type"),
+          "text/javascript; charset=UTF-8",
+        );
+
+        const text = await response.text();
+
+        assert(text.startsWith("___eio[1]("));
+        assert(text.endsWith(");"));
+
+        const [, content] = decodePayload(JSON.parse(text.slice(10, -2)));
+
+        assert.deepStrictEqual(content[0], "0");
+        assert.equal(typeof JSON.parse(content.substring(1)).sid, "string");
+      });
+
       it("fails with an invalid request method", async () => {
         const response = await fetch(
           `${POLLING_URL}/engine.io/?EIO=3&transport=polling`,
@@ -370,6 +392,22 @@ describe("Engine.IO protocol", () => {
         socket.close();
       });
 
+      it("sends and receives a base64 encoded binary packet (b64)", async () => {
+        const socket = new WebSocketStream(
+          `${WS_URL}/engine.io/?EIO=3&transport=websocket&b64=1`,
+        );
+
+        await waitForMessage(socket); // handshake
+
+        socket.send("b4AQIDBA==");
+
+        const data = await waitForMessage(socket);
+
+        assert.deepStrictEqual(data, "b4AQIDBA==");
+
+        socket.close();
+      });
+
       it("closes the session upon invalid packet format", async () => {
         const socket = new WebSocketStream(
           `${WS_URL}/engine.io/?EIO=3&transport=websocket`,
@@ -0,0 +1,51 @@
+use actix_web::{web, App, HttpServer};
+use rmpv::Value;
+use socketioxide::{
+    extract::{AckSender, Data, SocketRef},
+    integrations::actix::ActixService,
+    SocketIo,
+};
+use tracing::info;
+use tracing_subscriber::FmtSubscriber;
+
+fn on_connect(socket: SocketRef, Data(data): Data<Value>) {
+    info!(ns = socket.ns(), ?socket.id, "Socket.IO connected");
+    socket.emit("auth", &data).ok();
+
+    socket.on("message", |socket: SocketRef, Data::<Value>(data)| {
+        info!(?data, "Received event");
+        socket.emit("message-back", &data).ok();
+    });
+
+    socket.on("message-with-ack", |Data::<Value>(data), ack: AckSender| {
+        info!(?data, "Received event");
+        ack.send(&data).ok();
+    });
+}
+
+#[actix_web::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let subscriber = FmtSubscriber::new();
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    let (svc, io) = SocketIo::new_svc();
+
+    io.ns("/", on_connect);
+    io.ns("/custom", on_connect);
+
+    info!("Starting server");
+
+    HttpServer::new(move || {
+        App::new()
+            // Mount Socket.IO on the `/socket.io` path
+            .service(ActixService::new(svc.clone()))
+            .route("/", web::get().to(|| async { "Hello, World!" }))
+    })
+    // Abort the polling requests of the closed connections
+    .h1_allow_half_closed(false)
+    .bind("127.0.0.1:3000")?
+    .run()
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use rmpv::Value;
+use rocket::get;
+use socketioxide::{
+    extract::{AckSender, Data, SocketRef},
+    integrations::rocket::RocketService,
+    SocketIo,
+};
+use tracing::info;
+use tracing_subscriber::FmtSubscriber;
+
+fn on_connect(socket: SocketRef, Data(data): Data<Value>) {
+    info!(ns = socket.ns(), ?socket.id, "Socket.IO connected");
+    socket.emit("auth", &data).ok();
+
+    socket.on("message", |socket: SocketRef, Data::<Value>(data)| {
+        info!(?data, "Received event");
+        socket.emit("message-back", &data).ok();
+    });
+
+    socket.on("message-with-ack", |Data::<Value>(data), ack: AckSender| {
+        info!(?data, "Received event");
+        ack.send(&data).ok();
+    });
+}
+
+#[get("/")]
+fn hello() -> &'static str {
+    "Hello, World!"
+}
+
+#[rocket::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let subscriber = FmtSubscriber::new();
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    let (svc, io) = SocketIo::new_svc();
+
+    io.ns("/", on_connect);
+    io.ns("/custom", on_connect);
+
+    info!("Starting server");
+
+    // Mount Socket.IO on the `/socket.io` path
+    rocket::build()
+        .attach(RocketService::new(svc))
+        .mount("/", rocket::routes![hello])
+        .launch()
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use rmpv::Value;
+use socketioxide::{
+    extract::{AckSender, Data, SocketRef},
+    integrations::warp::filter,
+    SocketIo,
+};
+use tracing::info;
+use tracing_subscriber::FmtSubscriber;
+use warp::Filter;
+
+fn on_connect(socket: SocketRef, Data(data): Data<Value>) {
+    info!(ns = socket.ns(), ?socket.id, "Socket.IO connected");
+    socket.emit("auth", &data).ok();
+
+    socket.on("message", |socket: SocketRef, Data::<Value>(data)| {
+        info!(?data, "Received event");
+        socket.emit("message-back", &data).ok();
+    });
+
+    socket.on("message-with-ack", |Data::<Value>(data), ack: AckSender| {
+        info!(?data, "Received event");
+        ack.send(&data).ok();
+    });
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let subscriber = FmtSubscriber::new();
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    let (svc, io) = SocketIo::new_svc();
+
+    io.ns("/", on_connect);
+    io.ns("/custom", on_connect);
+
+    // Mount Socket.IO on the `/socket.io` path
+    let hello = warp::path::end().map(|| "Hello, World!");
+    let routes = filter(svc).or(hello);
+
+    info!("Starting server");
+
+    warp::serve(routes).run(([127, 0, 0, 1], 3000)).await;
+
+    Ok(())
+}